@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use std::convert::{TryFrom, TryInto};
 use std::fmt::{Display, Formatter};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt,
     net::{Ipv4Addr, Ipv6Addr},
     time::Duration,
@@ -16,7 +16,9 @@ pub mod _private {
 }
 
 pub mod lease;
+pub mod metrics;
 pub mod quic;
+pub mod session;
 
 use libcontainer::oci_spec::runtime::Capability;
 pub use libvault::modules::pki::types::{IssueCertificateRequest, IssueCertificateResponse};
@@ -311,6 +313,52 @@ pub struct PodSpec {
     pub affinity: Option<Affinity>,
     #[serde(default)]
     pub restart_policy: RestartPolicy,
+    /// Scheduler profile that should place this pod. Empty means the default profile.
+    #[serde(rename = "schedulerName", default)]
+    pub scheduler_name: Option<String>,
+    /// Seconds the containers get to shut down after the pod is deleted,
+    /// before they are killed. Defaults to 30 seconds.
+    #[serde(rename = "terminationGracePeriodSeconds", default)]
+    pub termination_grace_period_seconds: Option<u64>,
+    /// Whether the pod shares the host's user namespace. When false the pod
+    /// gets a user namespace of its own, mapping root to an unprivileged
+    /// range of host ids. Defaults to true.
+    #[serde(rename = "hostUsers", default)]
+    pub host_users: Option<bool>,
+    /// Pods with a higher priority are scheduled first and evicted last
+    /// when the node runs short of resources. Defaults to 0.
+    #[serde(default)]
+    pub priority: Option<i32>,
+    /// Volumes the containers can mount with `volumeMounts`.
+    #[serde(default)]
+    pub volumes: Vec<Volume>,
+    /// Secrets of type `kubernetes.io/dockerconfigjson` holding the
+    /// credentials to pull the containers' images with.
+    #[serde(rename = "imagePullSecrets", default)]
+    pub image_pull_secrets: Vec<LocalObjectReference>,
+    /// Containers added to the running pod to debug it, e.g. with
+    /// `rkl pod debug`. They are never restarted.
+    #[serde(rename = "ephemeralContainers", default)]
+    pub ephemeral_containers: Vec<EphemeralContainer>,
+}
+
+/// A reference to an object by name, e.g. a [`Secret`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct LocalObjectReference {
+    pub name: String,
+}
+
+pub const DEFAULT_TERMINATION_GRACE_PERIOD_SECONDS: u64 = 30;
+
+impl PodSpec {
+    pub fn termination_grace_period(&self) -> u64 {
+        self.termination_grace_period_seconds
+            .unwrap_or(DEFAULT_TERMINATION_GRACE_PERIOD_SECONDS)
+    }
+
+    pub fn host_users(&self) -> bool {
+        self.host_users.unwrap_or(true)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
@@ -321,6 +369,17 @@ pub enum RestartPolicy {
     Never,
 }
 
+/// When the node pulls a container's image from its registry.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ImagePullPolicy {
+    /// On every container start.
+    Always,
+    /// Only if the image is not on the node yet.
+    IfNotPresent,
+    /// The image has to be on the node already.
+    Never,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct ContainerRes {
     pub limits: Option<Resource>,
@@ -347,6 +406,53 @@ pub struct SecurityContext {
     pub allow_privilege_escalation: Option<bool>,
 
     pub capabilities: Option<Capabilities>,
+
+    #[serde(rename = "readOnlyRootFilesystem", default)]
+    pub read_only_root_filesystem: Option<bool>,
+
+    #[serde(rename = "seccompProfile", default)]
+    pub seccomp_profile: Option<SeccompProfile>,
+
+    #[serde(rename = "procMount", default)]
+    pub proc_mount: Option<ProcMountType>,
+}
+
+impl SecurityContext {
+    /// Whether the container process gets `no_new_privs`. Escalation is denied
+    /// unless explicitly allowed, and always allowed for privileged containers.
+    pub fn no_new_privileges(&self) -> bool {
+        self.allow_privilege_escalation != Some(true) && self.privileged != Some(true)
+    }
+}
+
+/// Seccomp filter applied to a container. A container without a profile runs
+/// unconfined.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SeccompProfile {
+    #[serde(rename = "type")]
+    pub profile_type: SeccompProfileType,
+
+    /// Path of the profile relative to the node's seccomp directory, required
+    /// for `Localhost` profiles.
+    #[serde(rename = "localhostProfile", default)]
+    pub localhost_profile: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SeccompProfileType {
+    RuntimeDefault,
+    #[default]
+    Unconfined,
+    Localhost,
+}
+
+/// `Default` masks and makes read-only the usual `/proc` and `/sys` paths,
+/// `Unmasked` leaves them untouched.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProcMountType {
+    #[default]
+    Default,
+    Unmasked,
 }
 
 /// The pattern should be like: "CAP_AUDIT_CONTROL" refers to  <http://man7.org/linux/man-pages/man7/capabilities.7.html>
@@ -365,26 +471,82 @@ pub struct EnvVar {
 
     #[serde(default)]
     pub value: Option<String>,
-    // #[serde(rename = "valueFrom", default)]
-    // pub value_from: Option<EnvVarSource>,
+
+    /// Takes the value from the pod or the container, ignored when `value` is set.
+    #[serde(rename = "valueFrom", default)]
+    pub value_from: Option<EnvVarSource>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct EnvVarSource {
+    #[serde(rename = "fieldRef", default)]
+    pub field_ref: Option<ObjectFieldSelector>, // Selects a field of the pod
+
+    #[serde(rename = "resourceFieldRef", default)]
+    pub resource_field_ref: Option<ResourceFieldSelector>, // Selects a limit of a container
 }
 
-// #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
-// pub struct EnvVarSource {
-//     #[serde(rename = "secretKeyRef", default)]
-//     pub secret_key_ref: Option<SecretKeySelector>, // Selects a key of a Secret
+/// A field of the pod: `metadata.name`, `metadata.namespace`, `metadata.uid`,
+/// `metadata.labels['<key>']`, `metadata.annotations['<key>']`,
+/// `spec.nodeName` or `status.podIP`. Volumes may also select all of
+/// `metadata.labels` or `metadata.annotations`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ObjectFieldSelector {
+    #[serde(rename = "fieldPath")]
+    pub field_path: String,
+}
 
-//     #[serde(rename = "configMapKeyRef", default)]
-//     pub config_map_key_ref: Option<ConfigMapKeySelector>, // Selects a key of a ConfigMap
+/// A resource of a container: `limits.cpu`, `limits.memory`, `requests.cpu`
+/// or `requests.memory`. Requests are the limits, as containers have no
+/// separate requests. Without a limit, the node's allocatable amount is used.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ResourceFieldSelector {
+    /// Required for volumes, defaults to the container itself for env vars.
+    #[serde(rename = "containerName", default)]
+    pub container_name: Option<String>,
 
-//     #[serde(rename = "fieldRef", default)]
-//     pub field_ref: Option<ObjectFieldSelector>, // Selects a field of the Pod/container
-// }
+    pub resource: String,
 
-// // Placeholder structures for EnvVarSource fields
-// pub struct SecretKeySelector { /* ... */ }
-// pub struct ConfigMapKeySelector { /* ... */ }
-// pub struct ObjectFieldSelector { /* ... */ }
+    /// Unit the value is given in, rounded up. Defaults to `1`.
+    #[serde(default)]
+    pub divisor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Volume {
+    pub name: String,
+
+    /// Pod fields projected as files. Other volume types are not supported
+    /// for pods.
+    #[serde(rename = "downwardAPI", default)]
+    pub downward_api: Option<DownwardAPIVolumeSource>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct DownwardAPIVolumeSource {
+    #[serde(default)]
+    pub items: Vec<DownwardAPIVolumeFile>,
+
+    /// Mode of the files without a mode of their own. Defaults to 0644.
+    #[serde(rename = "defaultMode", default)]
+    pub default_mode: Option<u32>,
+}
+
+/// A file of a `downwardAPI` volume, with either a field or a resource.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DownwardAPIVolumeFile {
+    /// Relative path of the file in the volume.
+    pub path: String,
+
+    #[serde(rename = "fieldRef", default)]
+    pub field_ref: Option<ObjectFieldSelector>,
+
+    #[serde(rename = "resourceFieldRef", default)]
+    pub resource_field_ref: Option<ResourceFieldSelector>,
+
+    #[serde(default)]
+    pub mode: Option<u32>,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct VolumeMount {
@@ -438,6 +600,120 @@ pub struct ContainerSpec {
 
     #[serde(rename = "workingDir", default)]
     pub working_dir: Option<String>,
+
+    #[serde(default)]
+    pub lifecycle: Option<Lifecycle>,
+
+    /// Only `Always` is allowed, on init containers. Such an init container
+    /// is a sidecar: it keeps running alongside the workload containers and
+    /// is restarted whenever it exits.
+    #[serde(rename = "restartPolicy", default)]
+    pub restart_policy: Option<RestartPolicy>,
+
+    #[serde(rename = "imagePullPolicy", default)]
+    pub image_pull_policy: Option<ImagePullPolicy>,
+}
+
+impl ContainerSpec {
+    /// Whether this init container is a sidecar.
+    pub fn is_sidecar(&self) -> bool {
+        self.restart_policy == Some(RestartPolicy::Always)
+    }
+
+    /// The image pull policy, which defaults to `Always` for images tagged
+    /// `latest` or not tagged at all and to `IfNotPresent` otherwise.
+    pub fn image_pull_policy(&self) -> ImagePullPolicy {
+        if let Some(policy) = self.image_pull_policy {
+            return policy;
+        }
+        if self.image.contains('@') {
+            return ImagePullPolicy::IfNotPresent;
+        }
+        let name = self.image.rsplit('/').next().unwrap_or(&self.image);
+        match name.split_once(':') {
+            Some((_, tag)) if tag != "latest" => ImagePullPolicy::IfNotPresent,
+            _ => ImagePullPolicy::Always,
+        }
+    }
+}
+
+/// A container run in an existing pod to troubleshoot it, in the
+/// namespaces of the pod's pause container. Unlike the pod's containers it
+/// has no ports, probes, resources or lifecycle hooks and is never restarted.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct EphemeralContainer {
+    pub name: String,
+
+    pub image: String,
+
+    #[serde(default)]
+    pub command: Option<Vec<String>>,
+
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    #[serde(default)]
+    pub env: Option<Vec<EnvVar>>,
+
+    #[serde(rename = "workingDir", default)]
+    pub working_dir: Option<String>,
+
+    #[serde(rename = "securityContext", default)]
+    pub security_context: Option<SecurityContext>,
+
+    #[serde(rename = "imagePullPolicy", default)]
+    pub image_pull_policy: Option<ImagePullPolicy>,
+
+    /// Container of the pod whose PID namespace is joined, to see and
+    /// trace its processes.
+    #[serde(rename = "targetContainerName", default)]
+    pub target_container_name: Option<String>,
+}
+
+impl EphemeralContainer {
+    /// The container to run, as a spec of a regular container.
+    pub fn to_container_spec(&self) -> ContainerSpec {
+        ContainerSpec {
+            name: self.name.clone(),
+            image: self.image.clone(),
+            ports: vec![],
+            args: self.args.clone(),
+            resources: None,
+            liveness_probe: None,
+            readiness_probe: None,
+            startup_probe: None,
+            security_context: self.security_context.clone(),
+            env: self.env.clone(),
+            volume_mounts: None,
+            command: self.command.clone(),
+            working_dir: self.working_dir.clone(),
+            lifecycle: None,
+            restart_policy: None,
+            image_pull_policy: self.image_pull_policy,
+        }
+    }
+}
+
+/// Actions run by rkl right after a container started and right before it
+/// is stopped.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct Lifecycle {
+    /// Runs right after the container is started. The container is killed
+    /// if the handler fails.
+    #[serde(rename = "postStart", default)]
+    pub post_start: Option<LifecycleHandler>,
+
+    /// Runs before the container is sent its stop signal. The time it takes
+    /// counts against the pod's termination grace period.
+    #[serde(rename = "preStop", default)]
+    pub pre_stop: Option<LifecycleHandler>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum LifecycleHandler {
+    Exec(ExecAction),
+    HttpGet(HttpGetAction),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -446,6 +722,7 @@ pub enum ProbeAction {
     Exec(ExecAction),
     HttpGet(HttpGetAction),
     TcpSocket(TcpSocketAction),
+    Grpc(GrpcAction),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
@@ -474,7 +751,8 @@ impl Probe {
         match &self.action {
             Some(_) => Ok(()),
             None => Err(
-                "probe must specify exactly one action (exec, httpGet, or tcpSocket)".to_string(),
+                "probe must specify exactly one action (exec, httpGet, tcpSocket, or grpc)"
+                    .to_string(),
             ),
         }
     }
@@ -519,6 +797,16 @@ pub struct TcpSocketAction {
     pub host: Option<String>,
 }
 
+/// Calls `grpc.health.v1.Health/Check` on the pod's IP.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct GrpcAction {
+    pub port: u16,
+
+    /// The service to check the health of, the server as a whole if unset.
+    #[serde(default)]
+    pub service: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct Port {
     #[serde(rename = "containerPort")]
@@ -547,6 +835,42 @@ pub struct PodTask {
     pub status: PodStatus,
 }
 
+impl PodTask {
+    /// Add an ephemeral container to the pod. Its name must not be used by
+    /// another container of the pod, and its target must be one of the
+    /// pod's containers.
+    pub fn add_ephemeral_container(&mut self, container: EphemeralContainer) -> Result<(), String> {
+        if container.name.is_empty() || container.image.is_empty() {
+            return Err("an ephemeral container needs a name and an image".to_string());
+        }
+        let spec = &self.spec;
+        let names = spec
+            .init_containers
+            .iter()
+            .chain(&spec.containers)
+            .map(|c| &c.name)
+            .chain(spec.ephemeral_containers.iter().map(|c| &c.name));
+        for name in names {
+            if *name == container.name {
+                return Err(format!(
+                    "pod {} already has a container named {}",
+                    self.metadata.name, container.name
+                ));
+            }
+        }
+        if let Some(target) = &container.target_container_name
+            && !spec.containers.iter().any(|c| c.name == *target)
+        {
+            return Err(format!(
+                "target container {} not found in pod {}",
+                target, self.metadata.name
+            ));
+        }
+        self.spec.ephemeral_containers.push(container);
+        Ok(())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
 pub struct PodStatus {
     #[serde(rename = "podIP")]
@@ -554,6 +878,10 @@ pub struct PodStatus {
 
     #[serde(rename = "containerStatuses", default)]
     pub container_statuses: Vec<ContainerStatus>,
+    #[serde(rename = "initContainerStatuses", default)]
+    pub init_container_statuses: Vec<ContainerStatus>,
+    #[serde(rename = "ephemeralContainerStatuses", default)]
+    pub ephemeral_container_statuses: Vec<ContainerStatus>,
     /// Phase indicates the high-level summary of the pod's status.
     #[serde(default)]
     pub phase: PodPhase,
@@ -743,9 +1071,35 @@ pub enum RksMessage {
     //request
     CreatePod(Box<PodTask>),
     DeletePod(String),
+    /// Stop a pod marked for deletion within its grace period. Sent by rks
+    /// to the node running the pod.
+    TerminatePod(Box<PodTask>),
+    /// New labels or annotations of a pod, sent by rks to the node running
+    /// the pod to refresh its downwardAPI volumes.
+    UpdatePodMetadata(Box<PodTask>),
     GetPodByUid(Uuid),
     GetPod(String),
     ListPod,
+    /// Move a running pod to another node with its state, checkpointing
+    /// and restoring its containers. rks picks the node through the
+    /// scheduler unless `node_name` is given. Answered with the moved pod.
+    MigratePod {
+        pod_name: String,
+        node_name: Option<String>,
+    },
+    /// Add an ephemeral container to a running pod. Answered with the pod.
+    DebugPod {
+        pod_name: String,
+        container: Box<EphemeralContainer>,
+    },
+    /// A pod with new ephemeral containers, sent by rks to the node running
+    /// the pod to start them.
+    RunEphemeralContainers(Box<PodTask>),
+    /// Status of an ephemeral container, sent by the node running it.
+    UpdateEphemeralContainerStatus {
+        pod_name: String,
+        status: Box<ContainerStatus>,
+    },
 
     CreateReplicaSet(Box<ReplicaSet>),
     UpdateReplicaSet(Box<ReplicaSet>),
@@ -772,6 +1126,20 @@ pub enum RksMessage {
     GetService(String),
     ListService,
 
+    // Secret operations
+    CreateSecret(Box<Secret>),
+    DeleteSecret(String),
+    GetSecret(String),
+    ListSecret,
+
+    // PodDisruptionBudget operations
+    CreatePodDisruptionBudget(Box<PodDisruptionBudget>),
+    DeletePodDisruptionBudget {
+        namespace: String,
+        name: String,
+    },
+    ListPodDisruptionBudget,
+
     GetNodeCount,
     RegisterNode(Box<Node>),
     UserRequest(String),
@@ -798,6 +1166,14 @@ pub enum RksMessage {
         status: PodStatus,
     },
 
+    /// Read container logs of a pod. Sent by users to rks, and forwarded by
+    /// rks to the node running the pod.
+    GetPodLogs(Box<PodLogsRequest>),
+    /// Resource usage of a node and its pods, sent by the node periodically.
+    UpdateNodeStats(Box<NodeStats>),
+    /// Resource usage of all connected nodes. Answered with `NodeStatsRes`.
+    GetNodeStats,
+
     //response
     Ack,
     Error(String),
@@ -814,9 +1190,15 @@ pub enum RksMessage {
     // Service responses
     GetServiceRes(Box<ServiceTask>),
     ListServiceRes(Vec<ServiceTask>),
+    // Secret responses
+    GetSecretRes(Box<Secret>),
+    ListSecretRes(Vec<Secret>),
+    ListPodDisruptionBudgetRes(Vec<PodDisruptionBudget>),
     // (Podname, Podip)
     SetPodip((String, String)),
     Certificate(IssueCertificateResponse),
+    PodLogsRes(Box<PodLogsResponse>),
+    NodeStatsRes(Vec<NodeStats>),
 }
 
 impl std::fmt::Debug for RksMessage {
@@ -827,9 +1209,45 @@ impl std::fmt::Debug for RksMessage {
             Self::DeletePod(pod_name) => {
                 write!(f, "RksMessage::DeletePod {{ pod_name: {} }}", pod_name)
             }
+            Self::TerminatePod(pod) => write!(
+                f,
+                "RksMessage::TerminatePod {{ pod_name: {} }}",
+                pod.metadata.name
+            ),
+            Self::UpdatePodMetadata(pod) => write!(
+                f,
+                "RksMessage::UpdatePodMetadata {{ pod_name: {} }}",
+                pod.metadata.name
+            ),
             Self::GetPodByUid(uid) => write!(f, "RksMessage::GetPodByUid({})", uid),
             Self::GetPod(name) => write!(f, "RksMessage::GetPod({})", name),
             Self::ListPod => f.write_str("RksMessage::ListPod"),
+            Self::MigratePod {
+                pod_name,
+                node_name,
+            } => write!(
+                f,
+                "RksMessage::MigratePod {{ pod_name: {}, node_name: {:?} }}",
+                pod_name, node_name
+            ),
+            Self::DebugPod {
+                pod_name,
+                container,
+            } => write!(
+                f,
+                "RksMessage::DebugPod {{ pod_name: {}, container: {} }}",
+                pod_name, container.name
+            ),
+            Self::RunEphemeralContainers(pod) => write!(
+                f,
+                "RksMessage::RunEphemeralContainers {{ pod_name: {} }}",
+                pod.metadata.name
+            ),
+            Self::UpdateEphemeralContainerStatus { pod_name, status } => write!(
+                f,
+                "RksMessage::UpdateEphemeralContainerStatus {{ pod_name: {}, container: {} }}",
+                pod_name, status.name
+            ),
             Self::CreateReplicaSet(_) => f.write_str("RksMessage::CreateReplicaSet { .. }"),
             Self::UpdateReplicaSet(_) => f.write_str("RksMessage::UpdateReplicaSet { .. }"),
             Self::DeleteReplicaSet(name) => {
@@ -865,6 +1283,23 @@ impl std::fmt::Debug for RksMessage {
             }
             Self::GetService(name) => write!(f, "RksMessage::GetService {{ name: {} }}", name),
             Self::ListService => f.write_str("RksMessage::ListService"),
+            Self::CreateSecret(_) => f.write_str("RksMessage::CreateSecret { .. }"),
+            Self::DeleteSecret(name) => {
+                write!(f, "RksMessage::DeleteSecret {{ name: {} }}", name)
+            }
+            Self::GetSecret(name) => write!(f, "RksMessage::GetSecret {{ name: {} }}", name),
+            Self::ListSecret => f.write_str("RksMessage::ListSecret"),
+            Self::CreatePodDisruptionBudget(_) => {
+                f.write_str("RksMessage::CreatePodDisruptionBudget { .. }")
+            }
+            Self::DeletePodDisruptionBudget { namespace, name } => {
+                write!(
+                    f,
+                    "RksMessage::DeletePodDisruptionBudget {{ namespace: {}, name: {} }}",
+                    namespace, name
+                )
+            }
+            Self::ListPodDisruptionBudget => f.write_str("RksMessage::ListPodDisruptionBudget"),
             Self::GetNodeCount => f.write_str("RksMessage::GetNodeCount"),
             Self::RegisterNode(_) => f.write_str("RksMessage::RegisterNode { .. }"),
             Self::UserRequest(_) => f.write_str("RksMessage::UserRequest { .. }"),
@@ -940,6 +1375,21 @@ impl std::fmt::Debug for RksMessage {
                     services.len()
                 )
             }
+            Self::GetSecretRes(_) => f.write_str("RksMessage::GetSecretRes { .. }"),
+            Self::ListSecretRes(secrets) => {
+                write!(
+                    f,
+                    "RksMessage::ListSecretRes {{ count: {} }}",
+                    secrets.len()
+                )
+            }
+            Self::ListPodDisruptionBudgetRes(pdbs) => {
+                write!(
+                    f,
+                    "RksMessage::ListPodDisruptionBudgetRes {{ count: {} }}",
+                    pdbs.len()
+                )
+            }
             Self::SetPodip((pod_name, pod_ip)) => {
                 write!(
                     f,
@@ -953,6 +1403,27 @@ impl std::fmt::Debug for RksMessage {
                 ip, dns_port,
             ),
             Self::Certificate(_) => f.write_str("RksMessage::Certificate"),
+            Self::GetPodLogs(req) => write!(
+                f,
+                "RksMessage::GetPodLogs {{ request_id: {}, pod_name: {} }}",
+                req.request_id, req.pod_name
+            ),
+            Self::PodLogsRes(res) => write!(
+                f,
+                "RksMessage::PodLogsRes {{ request_id: {}, len: {} }}",
+                res.request_id,
+                res.logs.len()
+            ),
+            Self::UpdateNodeStats(stats) => write!(
+                f,
+                "RksMessage::UpdateNodeStats {{ node_name: {}, pods: {} }}",
+                stats.node_name,
+                stats.pods.len()
+            ),
+            Self::GetNodeStats => f.write_str("RksMessage::GetNodeStats"),
+            Self::NodeStatsRes(stats) => {
+                write!(f, "RksMessage::NodeStatsRes {{ count: {} }}", stats.len())
+            }
         }
     }
 }
@@ -967,9 +1438,36 @@ impl Display for RksMessage {
                 pod.metadata.name, pod.metadata.namespace
             ),
             Self::DeletePod(pod_name) => write!(f, "Delete pod '{}'", pod_name),
+            Self::TerminatePod(pod) => write!(f, "Terminate pod '{}'", pod.metadata.name),
+            Self::UpdatePodMetadata(pod) => {
+                write!(f, "Update metadata of pod '{}'", pod.metadata.name)
+            }
             Self::GetPodByUid(uid) => write!(f, "Get pod by UID '{}'", uid),
             Self::GetPod(name) => write!(f, "Get pod '{}'", name),
             Self::ListPod => f.write_str("List pods"),
+            Self::MigratePod {
+                pod_name,
+                node_name,
+            } => match node_name {
+                Some(node_name) => write!(f, "Migrate pod '{}' to node '{}'", pod_name, node_name),
+                None => write!(f, "Migrate pod '{}'", pod_name),
+            },
+            Self::DebugPod {
+                pod_name,
+                container,
+            } => write!(
+                f,
+                "Add ephemeral container '{}' to pod '{}'",
+                container.name, pod_name
+            ),
+            Self::RunEphemeralContainers(pod) => {
+                write!(f, "Run ephemeral containers of pod '{}'", pod.metadata.name)
+            }
+            Self::UpdateEphemeralContainerStatus { pod_name, status } => write!(
+                f,
+                "Update status of ephemeral container '{}' in pod '{}'",
+                status.name, pod_name
+            ),
             Self::CreateReplicaSet(rs) => write!(f, "Create replicaset '{}'", rs.metadata.name),
             Self::UpdateReplicaSet(rs) => write!(f, "Update replicaset '{}'", rs.metadata.name),
             Self::DeleteReplicaSet(name) => write!(f, "Delete replicaset '{}'", name),
@@ -995,6 +1493,17 @@ impl Display for RksMessage {
             Self::DeleteService(name) => write!(f, "Delete service '{}'", name),
             Self::GetService(name) => write!(f, "Get service '{}'", name),
             Self::ListService => f.write_str("List services"),
+            Self::CreateSecret(secret) => write!(f, "Create secret '{}'", secret.metadata.name),
+            Self::DeleteSecret(name) => write!(f, "Delete secret '{}'", name),
+            Self::GetSecret(name) => write!(f, "Get secret '{}'", name),
+            Self::ListSecret => f.write_str("List secrets"),
+            Self::CreatePodDisruptionBudget(pdb) => {
+                write!(f, "Create poddisruptionbudget '{}'", pdb.metadata.name)
+            }
+            Self::DeletePodDisruptionBudget { namespace, name } => {
+                write!(f, "Delete poddisruptionbudget '{}/{}'", namespace, name)
+            }
+            Self::ListPodDisruptionBudget => f.write_str("List poddisruptionbudgets"),
             Self::GetNodeCount => f.write_str("Get node count"),
             Self::RegisterNode(node) => write!(f, "Register node '{}'", node.metadata.name),
             Self::UserRequest(payload) => write!(f, "User request: {}", payload),
@@ -1151,10 +1660,35 @@ impl Display for RksMessage {
                 }
                 write!(f, "List services response: {}", preview.join(", "))
             }
+            Self::GetSecretRes(secret) => {
+                write!(f, "Get secret '{}' response", secret.metadata.name)
+            }
+            Self::ListSecretRes(secrets) => {
+                write!(f, "List secrets response: {} secret(s)", secrets.len())
+            }
+            Self::ListPodDisruptionBudgetRes(pdbs) => {
+                write!(
+                    f,
+                    "List poddisruptionbudgets response: {} poddisruptionbudget(s)",
+                    pdbs.len()
+                )
+            }
             Self::SetPodip((pod_name, pod_ip)) => {
                 write!(f, "Set pod '{}' IP address to {}", pod_name, pod_ip)
             }
             Self::Certificate(_) => f.write_str("Certificate response received"),
+            Self::GetPodLogs(req) => write!(f, "Get logs of pod '{}'", req.pod_name),
+            Self::PodLogsRes(res) => match &res.error {
+                Some(err) => write!(f, "Pod logs response: error: {}", err),
+                None => write!(f, "Pod logs response: {} byte(s)", res.logs.len()),
+            },
+            Self::UpdateNodeStats(stats) => {
+                write!(f, "Update resource usage of node '{}'", stats.node_name)
+            }
+            Self::GetNodeStats => f.write_str("Get resource usage of nodes"),
+            Self::NodeStatsRes(stats) => {
+                write!(f, "Node stats response: {} node(s)", stats.len())
+            }
         }
     }
 }
@@ -1261,6 +1795,71 @@ pub struct NodeStatus {
     pub addresses: Vec<NodeAddress>, // Node IPs, hostnames, etc.
     #[serde(default)]
     pub conditions: Vec<NodeCondition>, // Health and status flags
+    #[serde(default)]
+    pub images: Vec<ContainerImage>, // Images cached on the node
+    /// Typed `capacity`, which takes precedence over the string map when set.
+    #[serde(
+        rename = "capacityResources",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub capacity_resources: Option<NodeResources>,
+    /// Typed `allocatable`: the capacity minus the system reservations and
+    /// hard eviction thresholds.
+    #[serde(
+        rename = "allocatableResources",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub allocatable_resources: Option<NodeResources>,
+}
+
+/// Resources of a node, in the units the scheduler counts them in.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeResources {
+    /// CPU in millicores.
+    pub cpu: u64,
+    /// Memory in bytes.
+    pub memory: u64,
+    /// Local storage for container layers and logs, in bytes.
+    #[serde(default)]
+    pub ephemeral_storage: u64,
+    /// Bytes of hugepages, keyed by page size, e.g. `2Mi`.
+    #[serde(default)]
+    pub hugepages: BTreeMap<String, u64>,
+    /// Maximum number of pods.
+    #[serde(default)]
+    pub pods: u64,
+}
+
+impl NodeResources {
+    /// The resources as Kubernetes quantities, for the `capacity` and
+    /// `allocatable` maps of [`NodeStatus`].
+    pub fn quantities(&self) -> HashMap<String, String> {
+        let mut quantities = HashMap::from([
+            ("cpu".to_string(), format!("{}m", self.cpu)),
+            ("memory".to_string(), format!("{}Ki", self.memory / 1024)),
+            (
+                "ephemeral-storage".to_string(),
+                format!("{}Ki", self.ephemeral_storage / 1024),
+            ),
+            ("pods".to_string(), self.pods.to_string()),
+        ]);
+        for (size, bytes) in &self.hugepages {
+            quantities.insert(format!("hugepages-{size}"), format!("{}Ki", bytes / 1024));
+        }
+        quantities
+    }
+}
+
+/// Container image available on a node
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ContainerImage {
+    #[serde(default)]
+    pub names: Vec<String>, // Image references, e.g. "library/nginx:latest"
+    #[serde(rename = "sizeBytes", default)]
+    pub size_bytes: u64,
 }
 
 /// Node address entry
@@ -1382,6 +1981,96 @@ impl Node {
     }
 }
 
+/// Which container logs to read, and how much of them.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct PodLogOptions {
+    /// Container name, may be omitted for single-container pods.
+    pub container: Option<String>,
+    /// Read the logs of the previous instance of the container.
+    pub previous: bool,
+    /// Only lines newer than this many seconds.
+    pub since_seconds: Option<u64>,
+    /// Only lines written after this instant.
+    pub since_time: Option<DateTime<Utc>>,
+    /// Only the last N lines.
+    pub tail_lines: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PodLogsRequest {
+    /// Set by rks when forwarding to a node, to match the response.
+    pub request_id: u64,
+    pub pod_name: String,
+    pub options: PodLogOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PodLogsResponse {
+    pub request_id: u64,
+    /// Log lines in CRI format.
+    pub logs: String,
+    pub error: Option<String>,
+}
+
+/// Resource usage of a container, read from its cgroup.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ContainerStats {
+    /// Full container name, prefixed with the pod name.
+    pub name: String,
+    /// CPU time used since the container started, in microseconds.
+    pub cpu_usage_usec: u64,
+    /// CPU used over the last collection interval, in millicores.
+    pub cpu_millicores: u64,
+    pub memory_bytes: u64,
+    pub io_read_bytes: u64,
+    pub io_write_bytes: u64,
+    pub pids: u64,
+}
+
+/// Traffic of a pod's network namespace, loopback excluded.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct NetworkStats {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct PodStats {
+    pub name: String,
+    pub namespace: String,
+    pub containers: Vec<ContainerStats>,
+    pub network: NetworkStats,
+}
+
+impl PodStats {
+    pub fn cpu_millicores(&self) -> u64 {
+        self.containers.iter().map(|c| c.cpu_millicores).sum()
+    }
+
+    pub fn memory_bytes(&self) -> u64 {
+        self.containers.iter().map(|c| c.memory_bytes).sum()
+    }
+}
+
+/// Resource usage of a node and of the pods running on it, collected by
+/// the rkl daemon and reported to rks.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct NodeStats {
+    pub node_name: String,
+    pub timestamp: Option<DateTime<Utc>>,
+    /// CPU time used since boot, in microseconds.
+    pub cpu_usage_usec: u64,
+    /// CPU used over the last collection interval, in millicores.
+    pub cpu_millicores: u64,
+    /// CPU capacity, in millicores.
+    pub cpu_capacity: u64,
+    pub memory_bytes: u64,
+    pub memory_capacity: u64,
+    pub pods: Vec<PodStats>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NodeNetworkConfig {
     pub node_id: String,
@@ -1431,6 +2120,41 @@ pub struct ServicePort {
     pub node_port: Option<i32>, // NodePort
 }
 
+/// Type of the secrets holding a `~/.docker/config.json` file under the
+/// [`DOCKER_CONFIG_JSON_KEY`] key.
+pub const SECRET_TYPE_DOCKER_CONFIG_JSON: &str = "kubernetes.io/dockerconfigjson";
+pub const DOCKER_CONFIG_JSON_KEY: &str = ".dockerconfigjson";
+
+/// Sensitive data such as registry credentials, which pods refer to by name.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Secret {
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    pub metadata: ObjectMeta,
+    #[serde(rename = "type", default = "default_secret_type")]
+    pub secret_type: String,
+    /// Base64 encoded values by key.
+    #[serde(default)]
+    pub data: HashMap<String, String>,
+}
+
+fn default_secret_type() -> String {
+    "Opaque".to_string()
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Keep the values out of the logs
+        f.debug_struct("Secret")
+            .field("metadata", &self.metadata)
+            .field("secret_type", &self.secret_type)
+            .field("keys", &self.data.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServiceTask {
     #[serde(rename = "apiVersion")]
@@ -1450,6 +2174,26 @@ pub struct LabelSelector {
     pub match_expressions: Vec<LabelSelectorRequirement>,
 }
 
+impl LabelSelector {
+    /// Whether `labels` satisfy every label and expression of the selector.
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        let labels_match = self
+            .match_labels
+            .iter()
+            .all(|(k, v)| labels.get(k) == Some(v));
+        labels_match
+            && self.match_expressions.iter().all(|expr| {
+                let value = labels.get(&expr.key);
+                match expr.operator {
+                    LabelSelectorOperator::In => value.is_some_and(|v| expr.values.contains(v)),
+                    LabelSelectorOperator::NotIn => !value.is_some_and(|v| expr.values.contains(v)),
+                    LabelSelectorOperator::Exists => value.is_some(),
+                    LabelSelectorOperator::DoesNotExist => value.is_none(),
+                }
+            })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct LabelSelectorRequirement {
     pub key: String,
@@ -1509,6 +2253,43 @@ pub struct ReplicaSet {
     pub status: ReplicaSetStatus,
 }
 
+/// Limits the voluntary disruption of the pods matching `selector`, such as
+/// evictions by the descheduler. At most one of `min_available` and
+/// `max_unavailable` may be set.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PodDisruptionBudget {
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+    #[serde(rename = "kind")]
+    pub kind: String,
+    pub metadata: ObjectMeta,
+    pub spec: PodDisruptionBudgetSpec,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PodDisruptionBudgetSpec {
+    #[serde(default)]
+    pub selector: LabelSelector,
+    /// Matching pods which must stay ready after an eviction.
+    #[serde(rename = "minAvailable", default)]
+    pub min_available: Option<IntOrPercentage>,
+    /// Matching pods which may be unavailable after an eviction.
+    #[serde(rename = "maxUnavailable", default)]
+    pub max_unavailable: Option<IntOrPercentage>,
+}
+
+impl PodDisruptionBudget {
+    /// How many of `expected` matching pods must stay ready. Without
+    /// `min_available` or `max_unavailable`, all of them.
+    pub fn desired_healthy(&self, expected: i32) -> i32 {
+        match (&self.spec.min_available, &self.spec.max_unavailable) {
+            (Some(min), _) => min.resolve(expected),
+            (None, Some(max)) => (expected - max.resolve(expected)).max(0),
+            (None, None) => expected,
+        }
+    }
+}
+
 /// Endpoint related types (similar to Kubernetes Endpoints)
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct EndpointPort {
@@ -0,0 +1,294 @@
+//! Prometheus metrics shared by rks and rkl.
+//!
+//! Metrics are declared as statics with a fixed name, help text and label
+//! names, and updated with one label value per label name. They are backed
+//! by the vectors of the `prometheus` crate, created on first use. A binary
+//! gathers the metrics it exports in a [`Registry`] built by [`registry`]
+//! and serves them in the text exposition format with [`render`].
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use prometheus::core::Collector;
+use prometheus::{CounterVec, GaugeVec, HistogramOpts, HistogramVec, Opts, TextEncoder};
+
+pub use prometheus::Registry;
+
+/// Buckets of latency histograms, in seconds.
+pub const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+/// Buckets of histograms of slow operations such as image pulls and pod
+/// starts, in seconds.
+pub const SLOW_BUCKETS: &[f64] = &[
+    0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0,
+];
+
+/// A metric which can be registered in a [`Registry`].
+pub trait Metric: Sync {
+    /// The collector of the metric, sharing its series with it.
+    fn collector(&self) -> Box<dyn Collector>;
+}
+
+/// A registry holding `metrics`. Panics if two of them share a name.
+pub fn registry(metrics: &[&dyn Metric]) -> Registry {
+    let registry = Registry::new();
+    for metric in metrics {
+        registry
+            .register(metric.collector())
+            .expect("metrics must have distinct names");
+    }
+    registry
+}
+
+/// Render the metrics of `registry` in the Prometheus text exposition format.
+pub fn render(registry: &Registry) -> String {
+    TextEncoder::new()
+        .encode_to_string(&registry.gather())
+        .unwrap_or_default()
+}
+
+/// A counter which only goes up.
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    vec: OnceLock<CounterVec>,
+}
+
+impl Counter {
+    pub const fn new(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            vec: OnceLock::new(),
+        }
+    }
+
+    fn vec(&self) -> &CounterVec {
+        self.vec.get_or_init(|| {
+            CounterVec::new(Opts::new(self.name, self.help), self.labels).expect("invalid counter")
+        })
+    }
+
+    pub fn inc(&self, labels: &[&str]) {
+        self.inc_by(labels, 1.0);
+    }
+
+    pub fn inc_by(&self, labels: &[&str], value: f64) {
+        self.vec().with_label_values(labels).inc_by(value);
+    }
+
+    /// Drop every series whose `label` is `value`, e.g. all the series of a
+    /// deleted object, so their label sets don't pile up.
+    pub fn remove_matching(&self, label: &str, value: &str) {
+        let vec = self.vec();
+        for values in matching_series(&vec.collect(), self.labels, label, value) {
+            let values = values.iter().map(String::as_str).collect::<Vec<_>>();
+            let _ = vec.remove_label_values(&values);
+        }
+    }
+}
+
+impl Metric for Counter {
+    fn collector(&self) -> Box<dyn Collector> {
+        Box::new(self.vec().clone())
+    }
+}
+
+/// A gauge which can be set to any value.
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    vec: OnceLock<GaugeVec>,
+}
+
+impl Gauge {
+    pub const fn new(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            vec: OnceLock::new(),
+        }
+    }
+
+    fn vec(&self) -> &GaugeVec {
+        self.vec.get_or_init(|| {
+            GaugeVec::new(Opts::new(self.name, self.help), self.labels).expect("invalid gauge")
+        })
+    }
+
+    pub fn set(&self, labels: &[&str], value: f64) {
+        self.vec().with_label_values(labels).set(value);
+    }
+
+    pub fn add(&self, labels: &[&str], value: f64) {
+        self.vec().with_label_values(labels).add(value);
+    }
+
+    /// Drop the series of `labels`, e.g. once the object it describes is gone.
+    pub fn remove(&self, labels: &[&str]) {
+        let _ = self.vec().remove_label_values(labels);
+    }
+}
+
+impl Metric for Gauge {
+    fn collector(&self) -> Box<dyn Collector> {
+        Box::new(self.vec().clone())
+    }
+}
+
+/// A histogram of observed values, e.g. latencies in seconds.
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    buckets: &'static [f64],
+    vec: OnceLock<HistogramVec>,
+}
+
+impl Histogram {
+    pub const fn new(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+        buckets: &'static [f64],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            buckets,
+            vec: OnceLock::new(),
+        }
+    }
+
+    fn vec(&self) -> &HistogramVec {
+        self.vec.get_or_init(|| {
+            let opts = HistogramOpts::new(self.name, self.help).buckets(self.buckets.to_vec());
+            HistogramVec::new(opts, self.labels).expect("invalid histogram")
+        })
+    }
+
+    pub fn observe(&self, labels: &[&str], value: f64) {
+        self.vec().with_label_values(labels).observe(value);
+    }
+
+    /// Observe the seconds elapsed since `start`.
+    pub fn observe_since(&self, labels: &[&str], start: Instant) {
+        self.observe(labels, start.elapsed().as_secs_f64());
+    }
+}
+
+impl Metric for Histogram {
+    fn collector(&self) -> Box<dyn Collector> {
+        Box::new(self.vec().clone())
+    }
+}
+
+/// The label values, in the order of `names`, of the series of `families`
+/// whose `label` is `value`.
+fn matching_series(
+    families: &[prometheus::proto::MetricFamily],
+    names: &[&str],
+    label: &str,
+    value: &str,
+) -> Vec<Vec<String>> {
+    families
+        .iter()
+        .flat_map(|family| family.get_metric())
+        .map(|metric| {
+            metric
+                .get_label()
+                .iter()
+                .map(|pair| (pair.get_name(), pair.get_value()))
+                .collect::<HashMap<_, _>>()
+        })
+        .filter(|pairs| pairs.get(label) == Some(&value))
+        .map(|pairs| {
+            names
+                .iter()
+                .map(|name| pairs.get(name).copied().unwrap_or_default().to_string())
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counter() {
+        static RESTARTS: Counter = Counter::new(
+            "test_restarts_total",
+            "Number of restarts.",
+            &["pod", "container"],
+        );
+        RESTARTS.inc(&["web", "nginx"]);
+        RESTARTS.inc_by(&["db", "say \"hi\"\\\n"], 2.0);
+        let registry = registry(&[&RESTARTS]);
+
+        let out = render(&registry);
+        assert!(out.contains("# HELP test_restarts_total Number of restarts.\n"));
+        assert!(out.contains("# TYPE test_restarts_total counter\n"));
+        assert!(out.contains("test_restarts_total{container=\"nginx\",pod=\"web\"} 1\n"));
+        assert!(
+            out.contains("test_restarts_total{container=\"say \\\"hi\\\"\\\\\\n\",pod=\"db\"} 2\n")
+        );
+
+        RESTARTS.remove_matching("pod", "web");
+        let out = render(&registry);
+        assert!(!out.contains("pod=\"web\""));
+        assert!(out.contains("pod=\"db\""));
+    }
+
+    #[test]
+    fn test_render_histogram() {
+        static DURATION: Histogram = Histogram::new(
+            "test_duration_seconds",
+            "Latency in seconds.",
+            &["result"],
+            &[0.1, 1.0],
+        );
+        DURATION.observe(&["success"], 0.0625);
+        DURATION.observe(&["success"], 0.5);
+        DURATION.observe(&["success"], 4.0);
+
+        let out = render(&registry(&[&DURATION]));
+        assert!(out.contains("# TYPE test_duration_seconds histogram\n"));
+        assert!(out.contains("test_duration_seconds_bucket{result=\"success\",le=\"0.1\"} 1\n"));
+        assert!(out.contains("test_duration_seconds_bucket{result=\"success\",le=\"1\"} 2\n"));
+        assert!(out.contains("test_duration_seconds_bucket{result=\"success\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("test_duration_seconds_sum{result=\"success\"} 4.5625\n"));
+        assert!(out.contains("test_duration_seconds_count{result=\"success\"} 3\n"));
+    }
+
+    #[test]
+    fn test_gauge_remove() {
+        static DEPTH: Gauge = Gauge::new("test_queue_depth", "Queue depth.", &["queue"]);
+        DEPTH.set(&["pods"], 3.0);
+        DEPTH.add(&["pods"], 2.0);
+        DEPTH.set(&["nodes"], 1.0);
+        let registry = registry(&[&DEPTH]);
+        assert!(render(&registry).contains("test_queue_depth{queue=\"pods\"} 5\n"));
+
+        DEPTH.remove(&["pods"]);
+        let out = render(&registry);
+        assert!(!out.contains("queue=\"pods\""));
+        assert!(out.contains("test_queue_depth{queue=\"nodes\"} 1\n"));
+    }
+}
@@ -0,0 +1,209 @@
+//! Interactive sessions proxied by rks.
+//!
+//! A session runs on its own QUIC bidirectional stream. The client opens the
+//! stream to rks and writes a [`SessionRequest`]; rks forwards the request to
+//! the rkl daemon of the node the pod runs on and from then on relays the raw
+//! bytes in both directions. Client and daemon exchange [`Frame`]s, so stdin,
+//! stdout, stderr, terminal resizes and the exit status share the stream.
+//!
+//! To migrate a pod rks opens sessions to workers itself, checkpointing the
+//! pod on one node and restoring it on another. The checkpoint archive is
+//! carried in the same frames.
+//!
+//! On the wire the request is a big-endian `u32` length followed by its JSON
+//! encoding, and every frame is a one byte tag, a big-endian `u32` length and
+//! the payload.
+
+use anyhow::{Context, bail};
+use quinn::{ReadExactError, RecvStream, SendStream};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Upper bound for a single frame or request payload.
+pub const MAX_FRAME_SIZE: usize = 1 << 20;
+
+/// What the client wants to do, sent once at the start of the stream.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum SessionRequest {
+    /// Run `command` in a container of the pod.
+    Exec {
+        pod_name: String,
+        /// May be omitted for single-container pods.
+        container: Option<String>,
+        command: Vec<String>,
+        /// Allocate a pseudo terminal for the process.
+        tty: bool,
+        /// Forward the client's stdin to the process.
+        stdin: bool,
+    },
+    /// Stream the output of a running container.
+    Attach {
+        pod_name: String,
+        container: Option<String>,
+    },
+    /// Connect to `port` inside the pod's network namespace.
+    PortForward { pod_name: String, port: u16 },
+    /// Checkpoint the pod and remove it from the node. The daemon sends the
+    /// checkpoint archive as `Stdout` frames.
+    Checkpoint {
+        pod_name: String,
+        /// Checkpoint established TCP connections.
+        tcp_established: bool,
+    },
+    /// Restore a pod from the checkpoint archive the client sends as
+    /// `Stdin` frames, ended by `CloseStdin`. The daemon answers with the
+    /// new pod IP as a `Stdout` frame.
+    Restore { pod_name: String },
+}
+
+impl SessionRequest {
+    pub fn pod_name(&self) -> &str {
+        match self {
+            SessionRequest::Exec { pod_name, .. }
+            | SessionRequest::Attach { pod_name, .. }
+            | SessionRequest::PortForward { pod_name, .. }
+            | SessionRequest::Checkpoint { pod_name, .. }
+            | SessionRequest::Restore { pod_name } => pod_name,
+        }
+    }
+}
+
+/// A unit of data exchanged once the session is set up.
+///
+/// `Stdin`, `Resize` and `CloseStdin` flow from the client, the others from
+/// the daemon. For port forwarding `Stdin` and `Stdout` carry the bytes of
+/// the forwarded connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Stdin(Vec<u8>),
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+    Resize { cols: u16, rows: u16 },
+    CloseStdin,
+    /// Exit code of the command, 128 plus the signal number if it was killed.
+    Exit(i32),
+    Error(String),
+}
+
+const TAG_STDIN: u8 = 0;
+const TAG_STDOUT: u8 = 1;
+const TAG_STDERR: u8 = 2;
+const TAG_RESIZE: u8 = 3;
+const TAG_CLOSE_STDIN: u8 = 4;
+const TAG_EXIT: u8 = 5;
+const TAG_ERROR: u8 = 6;
+
+impl Frame {
+    pub fn encode(&self) -> Vec<u8> {
+        let (tag, payload): (u8, Cow<[u8]>) = match self {
+            Frame::Stdin(data) => (TAG_STDIN, data.into()),
+            Frame::Stdout(data) => (TAG_STDOUT, data.into()),
+            Frame::Stderr(data) => (TAG_STDERR, data.into()),
+            Frame::Resize { cols, rows } => (
+                TAG_RESIZE,
+                [cols.to_be_bytes(), rows.to_be_bytes()].concat().into(),
+            ),
+            Frame::CloseStdin => (TAG_CLOSE_STDIN, Cow::Borrowed(&[])),
+            Frame::Exit(code) => (TAG_EXIT, code.to_be_bytes().to_vec().into()),
+            Frame::Error(msg) => (TAG_ERROR, msg.as_bytes().into()),
+        };
+        let mut buf = Vec::with_capacity(5 + payload.len());
+        buf.push(tag);
+        buf.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&payload);
+        buf
+    }
+
+    pub fn decode(tag: u8, payload: Vec<u8>) -> anyhow::Result<Self> {
+        let fixed = |payload: &[u8]| -> anyhow::Result<[u8; 4]> {
+            payload.try_into().with_context(|| {
+                format!("invalid payload length {} for frame {tag}", payload.len())
+            })
+        };
+        Ok(match tag {
+            TAG_STDIN => Frame::Stdin(payload),
+            TAG_STDOUT => Frame::Stdout(payload),
+            TAG_STDERR => Frame::Stderr(payload),
+            TAG_RESIZE => {
+                let b = fixed(&payload)?;
+                Frame::Resize {
+                    cols: u16::from_be_bytes([b[0], b[1]]),
+                    rows: u16::from_be_bytes([b[2], b[3]]),
+                }
+            }
+            TAG_CLOSE_STDIN => Frame::CloseStdin,
+            TAG_EXIT => Frame::Exit(i32::from_be_bytes(fixed(&payload)?)),
+            TAG_ERROR => Frame::Error(String::from_utf8_lossy(&payload).into_owned()),
+            _ => bail!("unknown session frame tag {tag}"),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+pub trait SessionSendExt {
+    async fn send_request(&mut self, req: &SessionRequest) -> anyhow::Result<()>;
+    async fn send_frame(&mut self, frame: &Frame) -> anyhow::Result<()>;
+}
+
+#[async_trait::async_trait]
+pub trait SessionRecvExt {
+    async fn recv_request(&mut self) -> anyhow::Result<SessionRequest>;
+    /// Read the next frame, `None` once the peer has finished the stream.
+    async fn recv_frame(&mut self) -> anyhow::Result<Option<Frame>>;
+}
+
+#[async_trait::async_trait]
+impl SessionSendExt for SendStream {
+    async fn send_request(&mut self, req: &SessionRequest) -> anyhow::Result<()> {
+        let body = serde_json::to_vec(req)?;
+        let mut buf = Vec::with_capacity(4 + body.len());
+        buf.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&body);
+        self.write_all(&buf)
+            .await
+            .with_context(|| "Failed to send session request")
+    }
+
+    async fn send_frame(&mut self, frame: &Frame) -> anyhow::Result<()> {
+        self.write_all(&frame.encode())
+            .await
+            .with_context(|| "Failed to send session frame")
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionRecvExt for RecvStream {
+    async fn recv_request(&mut self) -> anyhow::Result<SessionRequest> {
+        let mut len = [0u8; 4];
+        self.read_exact(&mut len)
+            .await
+            .with_context(|| "Failed to read session request")?;
+        let body = read_payload(self, u32::from_be_bytes(len)).await?;
+        serde_json::from_slice(&body).with_context(|| "Failed to deserialize session request")
+    }
+
+    async fn recv_frame(&mut self) -> anyhow::Result<Option<Frame>> {
+        let mut header = [0u8; 5];
+        match self.read_exact(&mut header).await {
+            Ok(()) => {}
+            Err(ReadExactError::FinishedEarly(0)) => return Ok(None),
+            Err(e) => return Err(e).with_context(|| "Failed to read session frame"),
+        }
+        let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+        let payload = read_payload(self, len).await?;
+        Frame::decode(header[0], payload).map(Some)
+    }
+}
+
+async fn read_payload(stream: &mut RecvStream, len: u32) -> anyhow::Result<Vec<u8>> {
+    let len = len as usize;
+    if len > MAX_FRAME_SIZE {
+        bail!("session frame of {len} bytes exceeds the limit of {MAX_FRAME_SIZE}");
+    }
+    let mut payload = vec![0u8; len];
+    stream
+        .read_exact(&mut payload)
+        .await
+        .with_context(|| "Failed to read session frame payload")?;
+    Ok(payload)
+}
@@ -0,0 +1,732 @@
+//! Container checkpoint and restore with CRIU.
+//!
+//! A checkpoint is a directory, shipped around as a gzipped tarball:
+//!
+//! - `checkpoint.json`: the [`CheckpointManifest`]
+//! - `config.json`: the OCI spec the container ran with
+//! - `images/`: the CRIU images of the container's process tree
+//! - `rootfs-diff/`: the upper dir of the container's overlay rootfs, that is
+//!   every file the container added, changed or removed
+//!
+//! The network and pid namespaces a pod container joins from its pause
+//! container are dumped as external namespaces. A restored container joins
+//! the namespaces of the pause container it is restored into, so a pod can
+//! be restored around a fresh sandbox on another node.
+
+use crate::cri::ContainerStdio;
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Utc};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use libcontainer::config::YoukiConfig;
+use libcontainer::container::{Container, ContainerStatus};
+use nix::fcntl::{FcntlArg, FdFlag, fcntl};
+use oci_spec::runtime::{LinuxNamespaceType, Spec};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, info};
+use walkdir::WalkDir;
+
+pub const MANIFEST_FILE: &str = "checkpoint.json";
+pub const CONFIG_FILE: &str = "config.json";
+pub const IMAGES_DIR: &str = "images";
+pub const ROOTFS_DIFF_DIR: &str = "rootfs-diff";
+
+const CRIU: &str = "criu";
+/// CRIU 3.15 is the first release able to dump external pid namespaces.
+const MIN_CRIU_VERSION: u32 = 31500;
+const EXT_NET_NS_KEY: &str = "extRootNetNS";
+const EXT_PID_NS_KEY: &str = "extRootPidNS";
+const DUMP_LOG: &str = "dump.log";
+const RESTORE_LOG: &str = "restore.log";
+const RESTORE_PID_FILE: &str = "restore.pid";
+
+/// How the process tree is dumped. Restoring uses the same options.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointOptions {
+    /// Keep the container running once it has been dumped.
+    #[serde(default)]
+    pub leave_running: bool,
+    /// Dump established TCP connections. The restored container has a new
+    /// IP, so they come back closed and the peers see a reset.
+    #[serde(default)]
+    pub tcp_established: bool,
+    /// Dump file locks.
+    #[serde(default)]
+    pub file_locks: bool,
+    /// Dump unix sockets connected to processes outside the container.
+    #[serde(default)]
+    pub ext_unix_sk: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointManifest {
+    pub container_id: String,
+    /// Image the container was created from, see [`crate::oci::IMAGE_ANNOTATION`].
+    pub image: Option<String>,
+    pub created: DateTime<Utc>,
+    pub options: CheckpointOptions,
+    /// CRIU keys of the container's stdin, stdout and stderr. Streams with a
+    /// key are handed new file descriptors on restore.
+    #[serde(default)]
+    pub stdio: Vec<Option<String>>,
+    /// The container's network namespace was dumped empty and has to be set
+    /// up again after the restore.
+    #[serde(default)]
+    pub empty_netns: bool,
+}
+
+impl CheckpointManifest {
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let content =
+            fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_slice(&content).with_context(|| format!("invalid {}", path.display()))
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(MANIFEST_FILE);
+        fs::write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+/// Fail unless a recent enough CRIU is installed.
+pub fn check_criu() -> Result<()> {
+    let output = Command::new(CRIU)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("failed to run {CRIU}, is it installed?"))?;
+    if !output.status.success() {
+        bail!("{CRIU} --version exited with {}", output.status);
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = parse_criu_version(&stdout)
+        .ok_or_else(|| anyhow!("unexpected output of {CRIU} --version: {stdout}"))?;
+    if version < MIN_CRIU_VERSION {
+        bail!("CRIU {version} is too old, at least {MIN_CRIU_VERSION} is required");
+    }
+    Ok(())
+}
+
+/// Dump `container` into `dir` together with its spec and rootfs changes.
+///
+/// Unless `options.leave_running` is set the container's processes are gone
+/// afterwards, deleting the container is up to the caller.
+pub fn dump(container: &Container, dir: &Path, options: &CheckpointOptions) -> Result<()> {
+    let container_id = container.id();
+    if container.status() != ContainerStatus::Running {
+        bail!(
+            "container {container_id} is {}, only running containers can be checkpointed",
+            container.status()
+        );
+    }
+    let pid = container
+        .pid()
+        .ok_or_else(|| anyhow!("container {container_id} has no pid"))?
+        .as_raw();
+    let bundle = container.bundle();
+    let spec = Spec::load(bundle.join(CONFIG_FILE))
+        .with_context(|| format!("failed to load the spec of container {container_id}"))?;
+    if has_namespace(&spec, LinuxNamespaceType::User) {
+        bail!("container {container_id} runs in a user namespace, which CRIU can't checkpoint");
+    }
+
+    let images_dir = dir.join(IMAGES_DIR);
+    fs::create_dir_all(&images_dir)
+        .with_context(|| format!("failed to create {}", images_dir.display()))?;
+
+    let stdio = (0..3)
+        .map(|fd| stdio_key(pid, fd))
+        .collect::<Result<Vec<_>>>()?;
+    let external_ns = ExternalNamespaces {
+        net: joined_namespace_inode(&spec, pid, LinuxNamespaceType::Network)?,
+        pid: joined_namespace_inode(&spec, pid, LinuxNamespaceType::Pid)?,
+    };
+    let manifest = CheckpointManifest {
+        container_id: container_id.to_string(),
+        image: spec
+            .annotations()
+            .as_ref()
+            .and_then(|a| a.get(crate::oci::IMAGE_ANNOTATION))
+            .cloned(),
+        created: Utc::now(),
+        options: options.clone(),
+        empty_netns: external_ns.net.is_none(),
+        stdio,
+    };
+
+    let args = dump_args(pid, &images_dir, &spec, &external_ns, &manifest);
+    info!("Dumping container {container_id} (pid {pid})");
+    debug!("criu {}", args.join(" "));
+    run_criu(Command::new(CRIU).args(&args), &images_dir.join(DUMP_LOG))
+        .with_context(|| format!("failed to dump container {container_id}"))?;
+
+    fs::copy(bundle.join(CONFIG_FILE), dir.join(CONFIG_FILE))
+        .with_context(|| format!("failed to save the spec of container {container_id}"))?;
+    let upper_dir = bundle.join("upper");
+    if upper_dir.is_dir() {
+        copy_tree(&upper_dir, &dir.join(ROOTFS_DIFF_DIR))?;
+    }
+    manifest.save(dir)
+}
+
+/// Where and how a checkpoint is restored.
+pub struct RestoreTarget<'a> {
+    pub container_id: &'a str,
+    /// Root path of the runtime state, see [`crate::rootpath`].
+    pub root_path: &'a Path,
+    /// Bundle holding the container's `config.json` and its rootfs, with
+    /// the rootfs diff already applied.
+    pub bundle: &'a Path,
+    /// Pause container whose network and pid namespaces are joined.
+    pub pause_pid: Option<i32>,
+    /// New stdio of the container. Streams left as `None` are inherited.
+    pub stdio: ContainerStdio,
+}
+
+/// Restore the container checkpointed in `dir` into `target` and register
+/// it with the runtime as running. Returns the pid of its init process.
+pub fn restore(dir: &Path, target: RestoreTarget) -> Result<i32> {
+    let manifest = CheckpointManifest::load(dir)?;
+    let container_id = target.container_id;
+    let spec = Spec::load(target.bundle.join(CONFIG_FILE))
+        .with_context(|| format!("failed to load the spec of container {container_id}"))?;
+    let rootfs = spec
+        .root()
+        .as_ref()
+        .map(|root| target.bundle.join(root.path()))
+        .ok_or_else(|| anyhow!("the spec of container {container_id} has no root"))?;
+
+    // Every file descriptor handed to criu must survive its exec.
+    let mut inherited: Vec<(RawFd, String)> = Vec::new();
+    let mut keep_open: Vec<OwnedFd> = Vec::new();
+    if let Some(pause_pid) = target.pause_pid {
+        for (ns, key) in [("net", EXT_NET_NS_KEY), ("pid", EXT_PID_NS_KEY)] {
+            let path = format!("/proc/{pause_pid}/ns/{ns}");
+            let file = File::open(&path).with_context(|| format!("failed to open {path}"))?;
+            let fd = OwnedFd::from(file);
+            inherited.push((fd.as_raw_fd(), key.to_string()));
+            keep_open.push(fd);
+        }
+    } else if !manifest.empty_netns {
+        bail!("container {container_id} was checkpointed in a pod and must be restored into one");
+    }
+    let ContainerStdio {
+        stdin,
+        stdout,
+        stderr,
+    } = target.stdio;
+    for (fd, (new, key)) in [stdin, stdout, stderr]
+        .into_iter()
+        .zip(manifest.stdio.iter().chain(std::iter::repeat(&None)))
+        .enumerate()
+    {
+        let Some(key) = key else { continue };
+        match new {
+            Some(new) => {
+                inherited.push((new.as_raw_fd(), key.clone()));
+                keep_open.push(new);
+            }
+            None => inherited.push((fd as RawFd, key.clone())),
+        }
+    }
+
+    let pid_file = dir.join(RESTORE_PID_FILE);
+    let _ = fs::remove_file(&pid_file);
+    let args = restore_args(
+        &dir.join(IMAGES_DIR),
+        &rootfs,
+        &pid_file,
+        &spec,
+        &manifest,
+        &inherited,
+    );
+    info!("Restoring container {container_id}");
+    debug!("criu {}", args.join(" "));
+    let fds: Vec<RawFd> = inherited.iter().map(|(fd, _)| *fd).collect();
+    let mut command = Command::new(CRIU);
+    command.args(&args);
+    // SAFETY: only calls fcntl, which is async-signal-safe.
+    unsafe {
+        command.pre_exec(move || {
+            for fd in &fds {
+                fcntl(*fd, FcntlArg::F_SETFD(FdFlag::empty()))?;
+            }
+            Ok(())
+        });
+    }
+    run_criu(&mut command, &dir.join(IMAGES_DIR).join(RESTORE_LOG))
+        .with_context(|| format!("failed to restore container {container_id}"))?;
+    drop(keep_open);
+
+    let pid: i32 = fs::read_to_string(&pid_file)
+        .with_context(|| format!("criu wrote no pid file for container {container_id}"))?
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid pid file of container {container_id}"))?;
+    register(container_id, target.root_path, target.bundle, &spec, pid)?;
+    info!("Container {container_id} restored with pid {pid}");
+    Ok(pid)
+}
+
+/// Record a restored container in the runtime state, as `create` and
+/// `start` would have.
+fn register(
+    container_id: &str,
+    root_path: &Path,
+    bundle: &Path,
+    spec: &Spec,
+    pid: i32,
+) -> Result<()> {
+    let container_root = root_path.join(container_id);
+    if container_root.exists() {
+        bail!("container {container_id} already exists");
+    }
+    fs::create_dir_all(&container_root)
+        .with_context(|| format!("failed to create {}", container_root.display()))?;
+    let container = Container::new(
+        container_id,
+        ContainerStatus::Running,
+        Some(pid),
+        bundle,
+        &container_root,
+    )?;
+    container.save()?;
+    YoukiConfig::from_spec(spec, container_id)?.save(&container_root)?;
+    Ok(())
+}
+
+/// Apply a checkpoint's rootfs diff to a new rootfs.
+///
+/// An overlay upper dir takes the diff as it is. A plain rootfs gets the
+/// changed files copied over it, and the files the diff marks as removed
+/// deleted.
+pub fn apply_rootfs_diff(dir: &Path, target: &Path, overlay: bool) -> Result<()> {
+    let diff = dir.join(ROOTFS_DIFF_DIR);
+    if !diff.is_dir() {
+        return Ok(());
+    }
+    copy_tree(&diff, target)?;
+    if overlay {
+        return Ok(());
+    }
+    for entry in WalkDir::new(&diff) {
+        let entry = entry.with_context(|| format!("failed to walk {}", diff.display()))?;
+        let metadata = entry.path().symlink_metadata()?;
+        // overlayfs whiteouts are 0:0 character devices
+        if metadata.file_type().is_char_device() && metadata.rdev() == 0 {
+            let removed = target.join(entry.path().strip_prefix(&diff)?);
+            fs::remove_file(&removed)
+                .with_context(|| format!("failed to remove {}", removed.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Pack a checkpoint directory into a gzipped tarball.
+pub fn export(dir: &Path, archive: &Path) -> Result<()> {
+    let file =
+        File::create(archive).with_context(|| format!("failed to create {}", archive.display()))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    builder.follow_symlinks(false);
+    builder
+        .append_dir_all(".", dir)
+        .with_context(|| format!("failed to pack {}", dir.display()))?;
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Unpack a tarball written by [`export`] into `dir`.
+pub fn import(archive: &Path, dir: &Path) -> Result<()> {
+    let file =
+        File::open(archive).with_context(|| format!("failed to open {}", archive.display()))?;
+    let mut tar = tar::Archive::new(GzDecoder::new(file));
+    tar.set_preserve_permissions(true);
+    tar.set_preserve_ownerships(true);
+    tar.unpack(dir)
+        .with_context(|| format!("failed to unpack {}", archive.display()))
+}
+
+/// Inodes of the namespaces the container joined instead of creating them.
+#[derive(Debug, Default)]
+struct ExternalNamespaces {
+    net: Option<u64>,
+    pid: Option<u64>,
+}
+
+fn dump_args(
+    pid: i32,
+    images_dir: &Path,
+    spec: &Spec,
+    external_ns: &ExternalNamespaces,
+    manifest: &CheckpointManifest,
+) -> Vec<String> {
+    let mut args = vec![
+        "dump".to_string(),
+        "--tree".to_string(),
+        pid.to_string(),
+        "--images-dir".to_string(),
+        images_dir.display().to_string(),
+        "--log-file".to_string(),
+        DUMP_LOG.to_string(),
+        "-v4".to_string(),
+        "--manage-cgroups".to_string(),
+    ];
+    common_args(&mut args, &manifest.options);
+    if manifest.options.tcp_established {
+        args.push("--tcp-established".to_string());
+    }
+    if manifest.options.leave_running {
+        args.push("--leave-running".to_string());
+    }
+    match external_ns.net {
+        Some(inode) => args.extend([
+            "--external".to_string(),
+            format!("net[{inode}]:{EXT_NET_NS_KEY}"),
+        ]),
+        None => args.extend(["--empty-ns".to_string(), "net".to_string()]),
+    }
+    if let Some(inode) = external_ns.pid {
+        args.extend([
+            "--external".to_string(),
+            format!("pid[{inode}]:{EXT_PID_NS_KEY}"),
+        ]);
+    }
+    for (destination, _) in external_mounts(spec) {
+        args.extend([
+            "--external".to_string(),
+            format!("mnt[{destination}]:{destination}"),
+        ]);
+    }
+    for key in manifest.stdio.iter().flatten() {
+        if key.starts_with("file[") {
+            args.extend(["--external".to_string(), key.clone()]);
+        }
+    }
+    args
+}
+
+fn restore_args(
+    images_dir: &Path,
+    rootfs: &Path,
+    pid_file: &Path,
+    spec: &Spec,
+    manifest: &CheckpointManifest,
+    inherited: &[(RawFd, String)],
+) -> Vec<String> {
+    let mut args = vec![
+        "restore".to_string(),
+        "--images-dir".to_string(),
+        images_dir.display().to_string(),
+        "--log-file".to_string(),
+        RESTORE_LOG.to_string(),
+        "-v4".to_string(),
+        "--root".to_string(),
+        rootfs.display().to_string(),
+        "--restore-detached".to_string(),
+        "--pidfile".to_string(),
+        pid_file.display().to_string(),
+        "--manage-cgroups".to_string(),
+    ];
+    common_args(&mut args, &manifest.options);
+    if manifest.options.tcp_established {
+        args.push("--tcp-close".to_string());
+    }
+    if manifest.empty_netns {
+        args.extend(["--empty-ns".to_string(), "net".to_string()]);
+    }
+    for (destination, source) in external_mounts(spec) {
+        args.extend([
+            "--external".to_string(),
+            format!("mnt[{destination}]:{source}"),
+        ]);
+    }
+    for (fd, key) in inherited {
+        args.extend(["--inherit-fd".to_string(), format!("fd[{fd}]:{key}")]);
+    }
+    args
+}
+
+fn common_args(args: &mut Vec<String>, options: &CheckpointOptions) {
+    for (enabled, flag) in [
+        (options.file_locks, "--file-locks"),
+        (options.ext_unix_sk, "--ext-unix-sk"),
+    ] {
+        if enabled {
+            args.push(flag.to_string());
+        }
+    }
+}
+
+/// Mounts CRIU can't recreate by itself, as `(destination, source)`: bind
+/// mounts from the host, and `/dev/null` over the masked files.
+fn external_mounts(spec: &Spec) -> Vec<(String, String)> {
+    let mut mounts: Vec<(String, String)> = spec
+        .mounts()
+        .iter()
+        .flatten()
+        .filter(|m| {
+            m.typ().as_deref() == Some("bind")
+                || m.options()
+                    .iter()
+                    .flatten()
+                    .any(|o| o == "bind" || o == "rbind")
+        })
+        .filter_map(|m| {
+            let source = m.source().as_ref()?;
+            Some((
+                m.destination().display().to_string(),
+                source.display().to_string(),
+            ))
+        })
+        .collect();
+    let masked = spec
+        .linux()
+        .as_ref()
+        .and_then(|linux| linux.masked_paths().clone())
+        .unwrap_or_default();
+    mounts.extend(
+        masked
+            .into_iter()
+            .filter(|path| Path::new(path).exists() && !Path::new(path).is_dir())
+            .map(|path| (path, "/dev/null".to_string())),
+    );
+    mounts
+}
+
+fn has_namespace(spec: &Spec, typ: LinuxNamespaceType) -> bool {
+    spec.linux()
+        .as_ref()
+        .and_then(|linux| linux.namespaces().as_ref())
+        .is_some_and(|namespaces| namespaces.iter().any(|ns| ns.typ() == typ))
+}
+
+/// Inode of a namespace of `pid` that the spec joins by path.
+fn joined_namespace_inode(spec: &Spec, pid: i32, typ: LinuxNamespaceType) -> Result<Option<u64>> {
+    let joined = spec
+        .linux()
+        .as_ref()
+        .and_then(|linux| linux.namespaces().as_ref())
+        .is_some_and(|namespaces| {
+            namespaces
+                .iter()
+                .any(|ns| ns.typ() == typ && ns.path().is_some())
+        });
+    if !joined {
+        return Ok(None);
+    }
+    let name = match typ {
+        LinuxNamespaceType::Network => "net",
+        LinuxNamespaceType::Pid => "pid",
+        _ => bail!("namespace {typ:?} can't be external"),
+    };
+    let path = format!("/proc/{pid}/ns/{name}");
+    let metadata = fs::metadata(&path).with_context(|| format!("failed to stat {path}"))?;
+    Ok(Some(metadata.ino()))
+}
+
+/// The CRIU key of a stdio stream of `pid`: pipes are known by their inode,
+/// files (like the FIFOs of the log shim) are dumped as external files.
+/// `/dev/null` and terminals need no key.
+fn stdio_key(pid: i32, fd: i32) -> Result<Option<String>> {
+    let link = format!("/proc/{pid}/fd/{fd}");
+    let target = match fs::read_link(&link) {
+        Ok(target) => target,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {link}")),
+    };
+    let target = target.to_string_lossy();
+    if target.starts_with("pipe:[") {
+        return Ok(Some(target.into_owned()));
+    }
+    if target == "/dev/null" || target.starts_with("/dev/pts/") || target.contains(":[") {
+        return Ok(None);
+    }
+    let inode = fs::metadata(&link)
+        .with_context(|| format!("failed to stat {link}"))?
+        .ino();
+    let fdinfo = format!("/proc/{pid}/fdinfo/{fd}");
+    let mnt_id = fs::read_to_string(&fdinfo)
+        .with_context(|| format!("failed to read {fdinfo}"))?
+        .lines()
+        .find_map(|line| line.strip_prefix("mnt_id:"))
+        .and_then(|id| id.trim().parse::<u32>().ok())
+        .ok_or_else(|| anyhow!("no mnt_id in {fdinfo}"))?;
+    Ok(Some(format!("file[{mnt_id:x}:{inode:x}]")))
+}
+
+/// The version printed by `criu --version`, e.g. `Version: 3.17.1`, as
+/// `major * 10000 + minor * 100 + sublevel` like CRIU numbers it.
+fn parse_criu_version(output: &str) -> Option<u32> {
+    let version = output
+        .lines()
+        .find_map(|line| line.strip_prefix("Version:"))?
+        .trim();
+    let mut parts = version.split('.').map(|part| part.parse::<u32>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next()?.ok()?;
+    let sublevel = parts.next().transpose().ok()?.unwrap_or(0);
+    Some(major * 10000 + minor * 100 + sublevel)
+}
+
+/// Run criu and include the tail of its log in the error when it fails.
+fn run_criu(command: &mut Command, log: &Path) -> Result<()> {
+    let status = command
+        .status()
+        .with_context(|| format!("failed to run {CRIU}"))?;
+    if status.success() {
+        return Ok(());
+    }
+    let tail = fs::read_to_string(log)
+        .map(|content| {
+            let lines: Vec<&str> = content.lines().collect();
+            lines[lines.len().saturating_sub(10)..].join("\n")
+        })
+        .unwrap_or_default();
+    bail!(
+        "{CRIU} exited with {status}, see {}:\n{tail}",
+        log.display()
+    )
+}
+
+/// Copy a directory tree keeping ownership, modes, xattrs and device files,
+/// which overlay upper dirs rely on for whiteouts and opaque directories.
+fn copy_tree(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to).with_context(|| format!("failed to create {}", to.display()))?;
+    let mut source = PathBuf::from(from);
+    source.push(".");
+    let status = Command::new("cp")
+        .arg("-a")
+        .arg(&source)
+        .arg(to)
+        .status()
+        .context("failed to run cp")?;
+    if !status.success() {
+        bail!("failed to copy {} to {}", from.display(), to.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oci_spec::runtime::{LinuxBuilder, MountBuilder, SpecBuilder};
+
+    fn spec() -> Spec {
+        SpecBuilder::default()
+            .mounts(vec![
+                MountBuilder::default()
+                    .destination("/proc")
+                    .typ("proc")
+                    .source("proc")
+                    .build()
+                    .unwrap(),
+                MountBuilder::default()
+                    .destination("/data")
+                    .typ("bind")
+                    .source("/var/lib/rkl/volumes/data")
+                    .options(vec!["rw".to_string(), "rbind".to_string()])
+                    .build()
+                    .unwrap(),
+            ])
+            .linux(
+                LinuxBuilder::default()
+                    .masked_paths(vec![])
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    fn manifest(options: CheckpointOptions, empty_netns: bool) -> CheckpointManifest {
+        CheckpointManifest {
+            container_id: "pod-app".to_string(),
+            image: None,
+            created: Utc::now(),
+            options,
+            stdio: vec![
+                None,
+                Some("pipe:[1234]".to_string()),
+                Some("file[1a:2b]".to_string()),
+            ],
+            empty_netns,
+        }
+    }
+
+    #[test]
+    fn test_dump_args_mark_pod_namespaces_external() {
+        let options = CheckpointOptions {
+            tcp_established: true,
+            ..Default::default()
+        };
+        let external_ns = ExternalNamespaces {
+            net: Some(4026532000),
+            pid: Some(4026532001),
+        };
+        let args = dump_args(
+            42,
+            Path::new("/tmp/ckpt/images"),
+            &spec(),
+            &external_ns,
+            &manifest(options, false),
+        )
+        .join(" ");
+        assert!(args.starts_with("dump --tree 42 --images-dir /tmp/ckpt/images"));
+        assert!(args.contains("--tcp-established"));
+        assert!(!args.contains("--leave-running"));
+        assert!(args.contains("--external net[4026532000]:extRootNetNS"));
+        assert!(args.contains("--external pid[4026532001]:extRootPidNS"));
+        assert!(args.contains("--external mnt[/data]:/data"));
+        assert!(!args.contains("mnt[/proc]"));
+        assert!(args.contains("--external file[1a:2b]"));
+        assert!(!args.contains("pipe:[1234]"));
+        assert!(!args.contains("--empty-ns"));
+    }
+
+    #[test]
+    fn test_parse_criu_version() {
+        assert_eq!(
+            parse_criu_version("Version: 3.17.1\nGitID: v3.17.1\n"),
+            Some(31701)
+        );
+        assert_eq!(parse_criu_version("Version: 3.15\n"), Some(31500));
+        assert_eq!(parse_criu_version("criu: unknown option\n"), None);
+        assert_eq!(parse_criu_version("Version: three\n"), None);
+    }
+
+    #[test]
+    fn test_restore_args() {
+        let args = restore_args(
+            Path::new("/tmp/ckpt/images"),
+            Path::new("/bundle/merged"),
+            Path::new("/tmp/ckpt/restore.pid"),
+            &spec(),
+            &manifest(
+                CheckpointOptions {
+                    tcp_established: true,
+                    ..Default::default()
+                },
+                true,
+            ),
+            &[(7, "pipe:[1234]".to_string())],
+        )
+        .join(" ");
+        assert!(args.contains("--root /bundle/merged"));
+        assert!(args.contains("--pidfile /tmp/ckpt/restore.pid"));
+        assert!(args.contains("--tcp-close"));
+        assert!(!args.contains("--tcp-established"));
+        assert!(args.contains("--empty-ns net"));
+        assert!(args.contains("--external mnt[/data]:/var/lib/rkl/volumes/data"));
+        assert!(args.contains("--inherit-fd fd[7]:pipe:[1234]"));
+    }
+}
@@ -7,7 +7,7 @@ use thiserror::Error;
 
 use crate::cri::cri_api::{
     CdiDevice, ContainerConfig, ContainerMetadata, Device, ImageSpec, KeyValue,
-    LinuxContainerConfig, LinuxContainerResources, Mount, WindowsContainerConfig,
+    LinuxContainerConfig, LinuxContainerResources, Mount, Signal, WindowsContainerConfig,
 };
 use common::{ContainerRes, ContainerSpec};
 
@@ -140,6 +140,23 @@ impl ContainerConfigBuilder {
         self
     }
 
+    /// Use the image's STOPSIGNAL, given by name like `SIGQUIT` or `QUIT`.
+    /// Unknown signals keep the runtime default.
+    pub fn stop_signal_from_image_config(&mut self, signal: &Option<String>) -> &mut Self {
+        if let Some(signal) = signal.as_deref() {
+            let name = signal.trim().to_ascii_uppercase();
+            let name = if name.starts_with("SIG") {
+                name
+            } else {
+                format!("SIG{name}")
+            };
+            self.stop_signal = Signal::from_str_name(&name)
+                .unwrap_or(Signal::RuntimeDefault)
+                .into();
+        }
+        self
+    }
+
     pub fn mounts(&mut self, mounts: Vec<Mount>) -> &mut Self {
         self.mounts.extend(mounts);
         self
@@ -126,6 +126,7 @@
 //                 volume_mounts: None,
 //                 command: None,
 //                 working_dir: None,
+//                 lifecycle: None,
 //             },
 //             config: None,
 //             container_id: container_id.to_string(),
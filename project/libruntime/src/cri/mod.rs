@@ -8,6 +8,7 @@ use liboci_cli::{Create, Delete, Kill, List, Start, State};
 use std::fmt::Write as _;
 use std::fs::{self};
 use std::io::{self, Write};
+use std::os::fd::OwnedFd;
 use std::path::{Path, PathBuf};
 use tabwriter::TabWriter;
 use tracing::info;
@@ -129,12 +130,40 @@ pub fn kill(args: Kill, root_path: PathBuf) -> Result<()> {
 // it is running, it is just another process, and has attributes such as pid, file descriptors, etc.
 // associated with it like any other process.
 pub fn create(args: Create, root_path: PathBuf, systemd_cgroup: bool) -> Result<()> {
-    ContainerBuilder::new(args.container_id.clone(), SyscallType::default())
+    create_with_stdio(args, root_path, systemd_cgroup, ContainerStdio::default())
+}
+
+/// File descriptors handed to the container's init process as its stdio.
+/// Streams left as `None` are inherited from the caller.
+#[derive(Default)]
+pub struct ContainerStdio {
+    pub stdin: Option<OwnedFd>,
+    pub stdout: Option<OwnedFd>,
+    pub stderr: Option<OwnedFd>,
+}
+
+pub fn create_with_stdio(
+    args: Create,
+    root_path: PathBuf,
+    systemd_cgroup: bool,
+    stdio: ContainerStdio,
+) -> Result<()> {
+    let mut builder = ContainerBuilder::new(args.container_id.clone(), SyscallType::default())
         .with_executor(libcontainer::workload::default::DefaultExecutor {})
         .with_pid_file(args.pid_file.as_ref())?
         .with_console_socket(args.console_socket.as_ref())
         .with_root_path(root_path)?
-        .with_preserved_fds(args.preserve_fds)
+        .with_preserved_fds(args.preserve_fds);
+    if let Some(fd) = stdio.stdin {
+        builder = builder.with_stdin(fd);
+    }
+    if let Some(fd) = stdio.stdout {
+        builder = builder.with_stdout(fd);
+    }
+    if let Some(fd) = stdio.stderr {
+        builder = builder.with_stderr(fd);
+    }
+    builder
         .validate_id()?
         .as_init(&args.bundle)
         .with_systemd(systemd_cgroup)
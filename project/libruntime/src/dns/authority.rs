@@ -1,5 +1,10 @@
 #[allow(unused)]
-use std::{collections::HashMap, str::FromStr, sync::Arc};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Arc,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 use tokio::fs;
 use tokio::sync::Mutex;
 use tokio::{
@@ -28,6 +33,9 @@ use crate::dns::{DNS_SOCKET_PATH, DNSUpdateMessage};
 pub struct LocalAuthority {
     pub origin: LowerName,
     pub store: Arc<Mutex<dyn RecordStore + Send + Sync>>,
+    /// rotates the records of names with several addresses, e.g. the
+    /// replicas of a compose service, for round-robin resolution
+    rotation: AtomicUsize,
 }
 
 #[async_trait::async_trait]
@@ -85,12 +93,18 @@ impl Authority for LocalAuthority {
         // Only support A type
         let store = self.store.lock().await;
         debug!("{:?}", store);
-        if let Ok(data) = store.get(name).await {
-            let a = data.into_a().unwrap().0;
+        if let Ok(mut records) = store.get(name).await
+            && !records.is_empty()
+        {
+            let shift = self.rotation.fetch_add(1, Ordering::Relaxed) % records.len();
+            records.rotate_left(shift);
+
             let mut set = RecordSet::new(name.into(), RecordType::A, 30);
-            set.add_rdata(RData::A(A(a)));
+            for record in records {
+                set.add_rdata(record);
+            }
 
-            debug!("lookup for {name}, get addr {a}");
+            debug!("lookup for {name}, get addrs {set:?}");
 
             return LookupControlFlow::Continue(Ok(LookupRecords::Records {
                 lookup_options,
@@ -103,11 +117,12 @@ impl Authority for LocalAuthority {
 
 impl LocalAuthority {
     pub fn _from_mem(origin: &str) -> Self {
-        let map: HashMap<LowerName, RData> = HashMap::new();
+        let map: HashMap<LowerName, Vec<RData>> = HashMap::new();
         let mem_store = MemStore(map);
         Self {
             origin: LowerName::from_str(origin).unwrap(),
             store: Arc::new(Mutex::new(mem_store)),
+            rotation: AtomicUsize::new(0),
         }
     }
     async fn handle_connection(
@@ -123,7 +138,7 @@ impl LocalAuthority {
             let mut store = self.store.lock().await;
             match msg.action {
                 Add => store.add(&msg.name, RData::A(A(msg.ip))).await?,
-                Update => store.add(&msg.name, RData::A(A(msg.ip))).await?,
+                Update => store.set(&msg.name, RData::A(A(msg.ip))).await?,
                 Delete => store.del(&msg.name).await?,
             };
         }
@@ -162,6 +177,7 @@ impl LocalAuthority {
         let authority = Arc::new(Self {
             origin: LowerName::from_str(origin).unwrap(),
             store,
+            rotation: AtomicUsize::new(0),
         });
         let background = authority.clone();
         tokio::spawn(async move {
@@ -176,25 +192,35 @@ use std::fmt::Debug;
 
 #[async_trait::async_trait]
 pub trait RecordStore: Debug {
+    /// Add `record` to the records of `name`.
     async fn add(&mut self, name: &LowerName, record: RData) -> Result<()>;
+    /// Replace the records of `name` with `record`.
+    async fn set(&mut self, name: &LowerName, record: RData) -> Result<()>;
     async fn del(&mut self, name: &LowerName) -> Result<()>;
-    async fn get(&self, name: &LowerName) -> Result<RData>;
+    async fn get(&self, name: &LowerName) -> Result<Vec<RData>>;
 }
 
 #[derive(Debug)]
-pub struct MemStore(HashMap<LowerName, RData>);
+pub struct MemStore(HashMap<LowerName, Vec<RData>>);
 
 #[async_trait::async_trait]
 impl RecordStore for MemStore {
     async fn add(&mut self, name: &LowerName, record: RData) -> Result<()> {
-        self.0.insert(name.clone(), record.clone());
+        let records = self.0.entry(name.clone()).or_default();
+        if !records.contains(&record) {
+            records.push(record);
+        }
+        Ok(())
+    }
+    async fn set(&mut self, name: &LowerName, record: RData) -> Result<()> {
+        self.0.insert(name.clone(), vec![record]);
         Ok(())
     }
     async fn del(&mut self, name: &LowerName) -> Result<()> {
         self.0.remove(name);
         Ok(())
     }
-    async fn get(&self, name: &LowerName) -> Result<RData> {
+    async fn get(&self, name: &LowerName) -> Result<Vec<RData>> {
         self.0
             .get(name)
             .ok_or_else(|| anyhow!("record not found"))
@@ -204,7 +230,7 @@ impl RecordStore for MemStore {
 
 impl MemStore {
     pub fn new() -> Self {
-        let map: HashMap<LowerName, RData> = HashMap::new();
+        let map: HashMap<LowerName, Vec<RData>> = HashMap::new();
         MemStore(map)
     }
 }
@@ -213,3 +239,26 @@ impl Default for MemStore {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[tokio::test]
+    async fn test_mem_store_keeps_every_replica() {
+        let name = LowerName::from_str("web.rkl.internal.").unwrap();
+        let a = |last| RData::A(A(Ipv4Addr::new(172, 17, 0, last)));
+        let mut store = MemStore::new();
+        store.add(&name, a(2)).await.unwrap();
+        store.add(&name, a(3)).await.unwrap();
+        store.add(&name, a(2)).await.unwrap();
+        assert_eq!(store.get(&name).await.unwrap(), vec![a(2), a(3)]);
+
+        store.set(&name, a(4)).await.unwrap();
+        assert_eq!(store.get(&name).await.unwrap(), vec![a(4)]);
+
+        store.del(&name).await.unwrap();
+        assert!(store.get(&name).await.is_err());
+    }
+}
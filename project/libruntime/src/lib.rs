@@ -1,4 +1,5 @@
 pub mod bundle;
+pub mod checkpoint;
 pub mod cri;
 pub mod dns;
 pub mod network;
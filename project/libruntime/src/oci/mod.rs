@@ -1,14 +1,25 @@
 use lazy_static::lazy_static;
 use libcontainer::oci_spec::runtime::{
     Capability, LinuxBuilder, LinuxCapabilities, LinuxNamespaceBuilder, LinuxNamespaceType,
-    ProcessBuilder, Spec,
+    LinuxSeccomp, MountBuilder, ProcessBuilder, Spec, get_default_maskedpaths,
+    get_default_readonly_paths,
 };
 
-use crate::cri::cri_api::ContainerConfig;
+use crate::cri::cri_api::{ContainerConfig, MountPropagation};
 use anyhow::{Result, anyhow};
-use common::ContainerSpec;
+use common::{ContainerSpec, ProcMountType, SecurityContext};
 use oci_spec::runtime::{LinuxNamespace, RootBuilder};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+pub mod seccomp;
+pub mod userns;
+
+use userns::IdRange;
+
+/// Annotation recording the image a container was created from, so that a
+/// checkpoint of it can be restored on top of the same image.
+pub const IMAGE_ANNOTATION: &str = "io.rk8s.image";
 
 // Default supported capabilities (from docker's implementation)
 lazy_static! {
@@ -37,6 +48,9 @@ pub struct OCISpecGenerator {
     container_config: ContainerConfig,
     container_spec: ContainerSpec,
     pause_pid: Option<i32>,
+    id_range: Option<IdRange>,
+    cgroups_path: Option<PathBuf>,
+    pid_namespace_pid: Option<i32>,
 }
 
 impl OCISpecGenerator {
@@ -46,9 +60,35 @@ impl OCISpecGenerator {
             container_config: config.clone(),
             container_spec: spec.clone(),
             pause_pid,
+            id_range: None,
+            cgroups_path: None,
+            pid_namespace_pid: None,
         }
     }
 
+    /// Run the container in a user namespace mapping root to `id_range`.
+    /// With a pause pid the container joins the pause container's user
+    /// namespace, otherwise a new one is created.
+    pub fn user_namespace(mut self, id_range: Option<IdRange>) -> Self {
+        self.id_range = id_range;
+        self
+    }
+
+    /// Join the PID namespace of process `pid` instead of the pause
+    /// container's, e.g. for an ephemeral container targeting another
+    /// container of the pod.
+    pub fn pid_namespace_of(mut self, pid: Option<i32>) -> Self {
+        self.pid_namespace_pid = pid;
+        self
+    }
+
+    /// Create the container in the cgroup `cgroups_path` instead of the
+    /// runtime's default one.
+    pub fn cgroups_path(mut self, cgroups_path: Option<PathBuf>) -> Self {
+        self.cgroups_path = cgroups_path;
+        self
+    }
+
     fn get_capabilities(&self) -> Result<LinuxCapabilities> {
         let mut capabilities = new_linux_capabilities_with_defaults();
 
@@ -91,15 +131,77 @@ impl OCISpecGenerator {
         };
         process.set_args(Some(arg));
 
+        // Variables from the image and the container config override the defaults
+        if !self.container_config.envs.is_empty() {
+            let mut env = process.env().clone().unwrap_or_default();
+            for kv in &self.container_config.envs {
+                let prefix = format!("{}=", kv.key);
+                env.retain(|e| !e.starts_with(&prefix));
+                env.push(format!("{}={}", kv.key, kv.value));
+            }
+            process.set_env(Some(env));
+        }
+        if !self.container_config.working_dir.is_empty() {
+            process.set_cwd(PathBuf::from(&self.container_config.working_dir));
+        }
+
         let capabilities = self.get_capabilities()?;
         process.set_capabilities(Some(capabilities));
 
+        let no_new_privileges = self
+            .security_context()
+            .is_none_or(SecurityContext::no_new_privileges);
+        process.set_no_new_privileges(Some(no_new_privileges));
+
         self.inner_spec.set_process(Some(process));
         Ok(())
     }
 
+    /// Bind mount the host paths of the container config, after the default mounts.
+    fn mounts_set(&mut self) -> Result<()> {
+        if self.container_config.mounts.is_empty() {
+            return Ok(());
+        }
+        let mut mounts = self.inner_spec.mounts().clone().unwrap_or_default();
+        for mount in &self.container_config.mounts {
+            let propagation = match MountPropagation::try_from(mount.propagation) {
+                Ok(MountPropagation::PropagationHostToContainer) => "rslave",
+                Ok(MountPropagation::PropagationBidirectional) => "rshared",
+                _ => "rprivate",
+            };
+            let mut options = vec!["rbind".to_string(), propagation.to_string()];
+            if mount.readonly {
+                options.push("ro".to_string());
+            }
+            mounts.push(
+                MountBuilder::default()
+                    .destination(&mount.container_path)
+                    .typ("bind")
+                    .source(&mount.host_path)
+                    .options(options)
+                    .build()?,
+            );
+        }
+        self.inner_spec.set_mounts(Some(mounts));
+        Ok(())
+    }
+
+    fn security_context(&self) -> Option<&SecurityContext> {
+        self.container_spec.security_context.as_ref()
+    }
+
+    fn privileged(&self) -> bool {
+        self.security_context()
+            .and_then(|ctx| ctx.privileged)
+            .unwrap_or(false)
+    }
+
     pub fn generate(mut self) -> Result<Spec> {
-        let root = RootBuilder::default().readonly(false).build()?;
+        let readonly = self
+            .security_context()
+            .and_then(|ctx| ctx.read_only_root_filesystem)
+            .unwrap_or(false);
+        let root = RootBuilder::default().readonly(readonly).build()?;
         self.inner_spec.set_root(Some(root));
 
         let namespaces = self
@@ -111,18 +213,73 @@ impl OCISpecGenerator {
 
         let mut linux_builder = LinuxBuilder::default().namespaces(namespaces);
 
+        if let Some(id_range) = &self.id_range {
+            linux_builder = linux_builder
+                .uid_mappings(id_range.uid_mappings()?)
+                .gid_mappings(id_range.gid_mappings()?);
+        }
+
+        if let Some(cgroups_path) = &self.cgroups_path {
+            linux_builder = linux_builder.cgroups_path(cgroups_path.clone());
+        }
+
         if let Some(linux_config) = &self.container_config.linux
             && let Some(resources) = &linux_config.resources
         {
             linux_builder = linux_builder.resources(&resources.clone());
         }
 
+        // Privileged containers and procMount: Unmasked see /proc and /sys as they are
+        let unmasked =
+            self.security_context().and_then(|ctx| ctx.proc_mount) == Some(ProcMountType::Unmasked);
+        if !self.privileged() && !unmasked {
+            linux_builder = linux_builder
+                .masked_paths(get_default_maskedpaths())
+                .readonly_paths(get_default_readonly_paths());
+        }
+
+        if let Some(seccomp) = self
+            .create_seccomp()
+            .map_err(|e| anyhow!("failed to setup seccomp: {e}"))?
+        {
+            linux_builder = linux_builder.seccomp(seccomp);
+        }
+
         let linux = linux_builder.build()?;
         self.inner_spec.set_linux(Some(linux));
 
+        self.mounts_set()
+            .map_err(|e| anyhow!("failed to setup mounts: {e}"))?;
+
+        if !self.container_spec.image.is_empty() {
+            self.inner_spec.set_annotations(Some(HashMap::from([(
+                IMAGE_ANNOTATION.to_string(),
+                self.container_spec.image.clone(),
+            )])));
+        }
+
         Ok(self.inner_spec)
     }
 
+    fn create_seccomp(&self) -> Result<Option<LinuxSeccomp>> {
+        // Privileged containers are never confined, whatever their profile says
+        if self.privileged() {
+            return Ok(None);
+        }
+        let Some(profile) = self
+            .security_context()
+            .and_then(|ctx| ctx.seccomp_profile.as_ref())
+        else {
+            return Ok(None);
+        };
+        let capabilities = self
+            .get_capabilities()?
+            .bounding()
+            .clone()
+            .unwrap_or_default();
+        seccomp::seccomp_for_profile(profile, &capabilities)
+    }
+
     fn create_container_namespaces(&self) -> Result<Vec<LinuxNamespace>> {
         let mut namespaces = Vec::new();
 
@@ -138,11 +295,20 @@ impl OCISpecGenerator {
                 .build()?,
         );
 
+        if self.id_range.is_some() {
+            let mut user = LinuxNamespaceBuilder::default().typ(LinuxNamespaceType::User);
+            if let Some(pid) = self.pause_pid {
+                user = user.path(format!("/proc/{pid}/ns/user"));
+            }
+            namespaces.push(user.build()?);
+        }
+
         if let Some(pid) = self.pause_pid {
+            let pid_namespace_pid = self.pid_namespace_pid.unwrap_or(pid);
             namespaces.push(
                 LinuxNamespaceBuilder::default()
                     .typ(LinuxNamespaceType::Pid)
-                    .path(format!("/proc/{pid}/ns/pid"))
+                    .path(format!("/proc/{pid_namespace_pid}/ns/pid"))
                     .build()?,
             );
             namespaces.push(
@@ -298,3 +464,86 @@ fn get_all_capabilities() -> HashSet<Capability> {
     .into_iter()
     .collect::<HashSet<_>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cri::cri_api::Mount;
+
+    fn generate(spec: &str) -> Spec {
+        let spec: ContainerSpec = serde_yaml::from_str(spec).unwrap();
+        OCISpecGenerator::new(&ContainerConfig::default(), &spec, None)
+            .generate()
+            .unwrap()
+    }
+
+    fn no_new_privileges(spec: &Spec) -> Option<bool> {
+        spec.process().as_ref().unwrap().no_new_privileges()
+    }
+
+    #[test]
+    fn test_no_new_privileges_by_default() {
+        let spec = generate("{name: app, image: busybox}");
+        assert_eq!(no_new_privileges(&spec), Some(true));
+
+        let spec = generate("{name: app, image: busybox, securityContext: {}}");
+        assert_eq!(no_new_privileges(&spec), Some(true));
+
+        let spec = generate(
+            "{name: app, image: busybox, securityContext: {allowPrivilegeEscalation: true}}",
+        );
+        assert_eq!(no_new_privileges(&spec), Some(false));
+
+        let spec = generate("{name: app, image: busybox, securityContext: {privileged: true}}");
+        assert_eq!(no_new_privileges(&spec), Some(false));
+    }
+
+    #[test]
+    fn test_bind_mounts() {
+        let config = ContainerConfig {
+            mounts: vec![
+                Mount {
+                    container_path: "/data".to_string(),
+                    host_path: "/srv/data".to_string(),
+                    readonly: true,
+                    ..Default::default()
+                },
+                Mount {
+                    container_path: "/shared".to_string(),
+                    host_path: "/srv/shared".to_string(),
+                    propagation: MountPropagation::PropagationBidirectional as i32,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let spec: ContainerSpec = serde_yaml::from_str("{name: app, image: busybox}").unwrap();
+        let spec = OCISpecGenerator::new(&config, &spec, None)
+            .generate()
+            .unwrap();
+        let mounts = spec.mounts().as_ref().unwrap();
+        let mount = |destination: &str| {
+            mounts
+                .iter()
+                .find(|m| m.destination() == &PathBuf::from(destination))
+                .unwrap()
+        };
+        let data = mount("/data");
+        assert_eq!(data.typ().as_deref(), Some("bind"));
+        assert_eq!(data.source(), &Some(PathBuf::from("/srv/data")));
+        assert_eq!(
+            data.options().as_deref(),
+            Some(
+                &[
+                    "rbind".to_string(),
+                    "rprivate".to_string(),
+                    "ro".to_string()
+                ][..]
+            )
+        );
+        assert_eq!(
+            mount("/shared").options().as_deref(),
+            Some(&["rbind".to_string(), "rshared".to_string()][..])
+        );
+    }
+}
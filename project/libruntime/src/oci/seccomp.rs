@@ -0,0 +1,249 @@
+use anyhow::{Context, Result, anyhow, bail};
+use common::{SeccompProfile, SeccompProfileType};
+use libcontainer::oci_spec::runtime::{
+    Arch, Capability, LinuxSeccomp, LinuxSeccompAction, LinuxSeccompArgBuilder,
+    LinuxSeccompOperator, LinuxSyscall, LinuxSyscallBuilder,
+};
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+/// Directory holding the profiles referenced by `Localhost` seccomp profiles.
+pub const SECCOMP_PROFILE_ROOT: &str = "/var/lib/rkl/seccomp";
+
+/// The RuntimeDefault profile, in OCI format. Like Docker's default profile it
+/// denies everything with EPERM except an allow list of syscalls, the syscalls
+/// gated by capabilities are added in [`runtime_default`].
+const RUNTIME_DEFAULT_PROFILE: &str = include_str!("seccomp_default.json");
+
+/// Clone flags creating new namespaces, which need CAP_SYS_ADMIN.
+const CLONE_NEW_NAMESPACES: u64 = 0x7e020000;
+const ENOSYS: u32 = 38;
+
+/// Syscalls Docker's default profile only allows with the given capability.
+const CAPABILITY_SYSCALLS: &[(Capability, &[&str])] = &[
+    (
+        Capability::SysAdmin,
+        &[
+            "bpf",
+            "clone",
+            "clone3",
+            "fanotify_init",
+            "fsconfig",
+            "fsmount",
+            "fsopen",
+            "fspick",
+            "lookup_dcookie",
+            "mount",
+            "mount_setattr",
+            "move_mount",
+            "open_tree",
+            "perf_event_open",
+            "quotactl",
+            "quotactl_fd",
+            "setdomainname",
+            "sethostname",
+            "setns",
+            "syslog",
+            "umount",
+            "umount2",
+            "unshare",
+        ],
+    ),
+    (Capability::SysBoot, &["reboot"]),
+    (Capability::SysChroot, &["chroot"]),
+    (
+        Capability::SysModule,
+        &["delete_module", "init_module", "finit_module"],
+    ),
+    (Capability::SysPacct, &["acct"]),
+    (Capability::SysPtrace, &["kcmp", "pidfd_getfd"]),
+    (Capability::SysRawio, &["iopl", "ioperm"]),
+    (
+        Capability::SysTime,
+        &["settimeofday", "stime", "clock_settime", "clock_settime64"],
+    ),
+    (Capability::SysTtyConfig, &["vhangup"]),
+    (
+        Capability::SysNice,
+        &[
+            "get_mempolicy",
+            "mbind",
+            "set_mempolicy",
+            "set_mempolicy_home_node",
+        ],
+    ),
+    (Capability::Syslog, &["syslog"]),
+    (Capability::Bpf, &["bpf"]),
+    (Capability::Perfmon, &["perf_event_open"]),
+];
+
+/// Build the seccomp filter for a profile, `None` means unconfined.
+pub fn seccomp_for_profile(
+    profile: &SeccompProfile,
+    capabilities: &HashSet<Capability>,
+) -> Result<Option<LinuxSeccomp>> {
+    match profile.profile_type {
+        SeccompProfileType::Unconfined => Ok(None),
+        SeccompProfileType::RuntimeDefault => runtime_default(capabilities).map(Some),
+        SeccompProfileType::Localhost => {
+            let name = profile
+                .localhost_profile
+                .as_deref()
+                .ok_or_else(|| anyhow!("seccomp profile type Localhost needs localhostProfile"))?;
+            let path = localhost_profile_path(Path::new(SECCOMP_PROFILE_ROOT), name)?;
+            load_profile(&path).map(Some)
+        }
+    }
+}
+
+/// The RuntimeDefault profile for a container running with `capabilities`.
+pub fn runtime_default(capabilities: &HashSet<Capability>) -> Result<LinuxSeccomp> {
+    let mut seccomp: LinuxSeccomp = serde_json::from_str(RUNTIME_DEFAULT_PROFILE)
+        .context("failed to parse the RuntimeDefault seccomp profile")?;
+    seccomp.set_architectures(Some(native_architectures()));
+
+    let mut syscalls = seccomp.syscalls().clone().unwrap_or_default();
+    let mut allowed = HashSet::new();
+    for (cap, names) in CAPABILITY_SYSCALLS {
+        if capabilities.contains(cap) {
+            allowed.extend(names.iter().copied());
+        }
+    }
+
+    if !capabilities.contains(&Capability::SysAdmin) {
+        // Threads and processes may be cloned, namespaces may not. clone3
+        // can't be filtered on its flags, so make libc fall back to clone.
+        syscalls.push(
+            LinuxSyscallBuilder::default()
+                .names(vec!["clone".to_string()])
+                .action(LinuxSeccompAction::ScmpActAllow)
+                .args(vec![
+                    LinuxSeccompArgBuilder::default()
+                        .index(0usize)
+                        .value(CLONE_NEW_NAMESPACES)
+                        .value_two(0u64)
+                        .op(LinuxSeccompOperator::ScmpCmpMaskedEq)
+                        .build()?,
+                ])
+                .build()?,
+        );
+        syscalls.push(
+            LinuxSyscallBuilder::default()
+                .names(vec!["clone3".to_string()])
+                .action(LinuxSeccompAction::ScmpActErrno)
+                .errno_ret(ENOSYS)
+                .build()?,
+        );
+    }
+
+    if !allowed.is_empty() {
+        let mut names: Vec<String> = allowed.into_iter().map(str::to_string).collect();
+        names.sort();
+        syscalls.push(allow(names)?);
+    }
+    let arch_syscalls = arch_syscalls();
+    if !arch_syscalls.is_empty() {
+        syscalls.push(allow(arch_syscalls)?);
+    }
+
+    seccomp.set_syscalls(Some(syscalls));
+    Ok(seccomp)
+}
+
+/// Load a seccomp profile written in the OCI `linux.seccomp` format.
+pub fn load_profile(path: &Path) -> Result<LinuxSeccomp> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read seccomp profile {}", path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("invalid seccomp profile {}", path.display()))
+}
+
+/// Resolve a `localhostProfile`, which must stay inside the profile root.
+fn localhost_profile_path(root: &Path, name: &str) -> Result<PathBuf> {
+    let relative = Path::new(name);
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        bail!("localhostProfile {name} must be a relative path inside {SECCOMP_PROFILE_ROOT}");
+    }
+    Ok(root.join(relative))
+}
+
+fn allow(names: Vec<String>) -> Result<LinuxSyscall> {
+    Ok(LinuxSyscallBuilder::default()
+        .names(names)
+        .action(LinuxSeccompAction::ScmpActAllow)
+        .build()?)
+}
+
+fn native_architectures() -> Vec<Arch> {
+    if cfg!(target_arch = "x86_64") {
+        vec![Arch::ScmpArchX86_64, Arch::ScmpArchX86, Arch::ScmpArchX32]
+    } else if cfg!(target_arch = "aarch64") {
+        vec![Arch::ScmpArchAarch64, Arch::ScmpArchArm]
+    } else {
+        vec![Arch::ScmpArchNative]
+    }
+}
+
+fn arch_syscalls() -> Vec<String> {
+    let names: &[&str] = if cfg!(target_arch = "x86_64") {
+        &["arch_prctl", "modify_ldt"]
+    } else if cfg!(target_arch = "aarch64") {
+        &[
+            "arm_fadvise64_64",
+            "arm_sync_file_range",
+            "breakpoint",
+            "cacheflush",
+            "set_tls",
+            "sync_file_range2",
+        ]
+    } else {
+        &[]
+    };
+    names.iter().map(|s| s.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(seccomp: &LinuxSeccomp, name: &str) -> bool {
+        seccomp.syscalls().iter().flatten().any(|s| {
+            s.action() == LinuxSeccompAction::ScmpActAllow
+                && s.args().is_none()
+                && s.names().iter().any(|n| n == name)
+        })
+    }
+
+    #[test]
+    fn test_runtime_default_denies_by_default() {
+        let seccomp = runtime_default(&HashSet::new()).unwrap();
+        assert_eq!(seccomp.default_action(), LinuxSeccompAction::ScmpActErrno);
+        assert!(allowed(&seccomp, "read"));
+        assert!(!allowed(&seccomp, "mount"));
+        assert!(!allowed(&seccomp, "clone"));
+    }
+
+    #[test]
+    fn test_runtime_default_follows_capabilities() {
+        let caps = HashSet::from([Capability::SysAdmin, Capability::SysTime]);
+        let seccomp = runtime_default(&caps).unwrap();
+        assert!(allowed(&seccomp, "mount"));
+        assert!(allowed(&seccomp, "clone3"));
+        assert!(allowed(&seccomp, "clock_settime"));
+        assert!(!allowed(&seccomp, "reboot"));
+    }
+
+    #[test]
+    fn test_localhost_profile_path() {
+        let root = Path::new(SECCOMP_PROFILE_ROOT);
+        assert_eq!(
+            localhost_profile_path(root, "profiles/audit.json").unwrap(),
+            root.join("profiles/audit.json")
+        );
+        assert!(localhost_profile_path(root, "../audit.json").is_err());
+        assert!(localhost_profile_path(root, "/etc/audit.json").is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use libcontainer::oci_spec::runtime::{LinuxIdMapping, LinuxIdMappingBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt, lchown};
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Host ids a pod's user namespace maps to: container uid 0 is host uid
+/// `host_uid`, container gid 0 is host gid `host_gid`, for `size` ids each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdRange {
+    pub host_uid: u32,
+    pub host_gid: u32,
+    pub size: u32,
+}
+
+impl IdRange {
+    pub fn uid_mappings(&self) -> Result<Vec<LinuxIdMapping>> {
+        Ok(vec![
+            LinuxIdMappingBuilder::default()
+                .container_id(0u32)
+                .host_id(self.host_uid)
+                .size(self.size)
+                .build()?,
+        ])
+    }
+
+    pub fn gid_mappings(&self) -> Result<Vec<LinuxIdMapping>> {
+        Ok(vec![
+            LinuxIdMappingBuilder::default()
+                .container_id(0u32)
+                .host_id(self.host_gid)
+                .size(self.size)
+                .build()?,
+        ])
+    }
+
+    /// Mapping for libfuse-fs' passthrough layers (see
+    /// `libfuse_fs::util::mapping::IdMappings`): files owned by root on disk
+    /// are served as owned by the container's root, and files created by the
+    /// container are stored as owned by root.
+    pub fn fuse_mapping(&self) -> String {
+        format!(
+            "uidmapping=0:{}:{},gidmapping=0:{}:{}",
+            self.host_uid, self.size, self.host_gid, self.size
+        )
+    }
+
+    fn host_uid_of(&self, uid: u32) -> Option<u32> {
+        (uid < self.size).then(|| self.host_uid + uid)
+    }
+
+    fn host_gid_of(&self, gid: u32) -> Option<u32> {
+        (gid < self.size).then(|| self.host_gid + gid)
+    }
+}
+
+/// Shift the ownership of a root filesystem copied for a single container
+/// into `range`, so that its files keep their owners inside the user namespace.
+/// Ids outside the range are left untouched.
+pub fn shift_ownership(root: &Path, range: &IdRange) -> Result<()> {
+    for entry in WalkDir::new(root) {
+        let entry = entry.with_context(|| format!("failed to walk {}", root.display()))?;
+        let metadata = entry
+            .path()
+            .symlink_metadata()
+            .with_context(|| format!("failed to stat {}", entry.path().display()))?;
+        let uid = range.host_uid_of(metadata.uid());
+        let gid = range.host_gid_of(metadata.gid());
+        if uid.is_none() && gid.is_none() {
+            continue;
+        }
+        lchown(entry.path(), uid, gid)
+            .with_context(|| format!("failed to chown {}", entry.path().display()))?;
+        // chown clears the setuid and setgid bits
+        if !metadata.file_type().is_symlink() && metadata.mode() & 0o6000 != 0 {
+            fs::set_permissions(entry.path(), fs::Permissions::from_mode(metadata.mode()))
+                .with_context(|| format!("failed to chmod {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuse_mapping_matches_oci_mappings() {
+        let range = IdRange {
+            host_uid: 2147483648,
+            host_gid: 2147549184,
+            size: 65536,
+        };
+        assert_eq!(
+            range.fuse_mapping(),
+            "uidmapping=0:2147483648:65536,gidmapping=0:2147549184:65536"
+        );
+        let uid = range.uid_mappings().unwrap();
+        assert_eq!(uid[0].host_id(), 2147483648);
+        assert_eq!(uid[0].container_id(), 0);
+        assert_eq!(range.gid_mappings().unwrap()[0].host_id(), 2147549184);
+        assert_eq!(range.host_uid_of(65535), Some(2147483648 + 65535));
+        assert_eq!(range.host_uid_of(65536), None);
+    }
+}
@@ -179,7 +179,7 @@ pub fn exec(args: Exec, root_path: PathBuf) -> Result<i32> {
 
     match waitpid(pid, None)? {
         WaitStatus::Exited(_, status) => Ok(status),
-        WaitStatus::Signaled(_, sig, _) => Ok(sig as i32),
+        WaitStatus::Signaled(_, sig, _) => Ok(128 + sig as i32),
         _ => Ok(0),
     }
 }
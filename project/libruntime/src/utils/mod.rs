@@ -237,6 +237,7 @@ pub async fn handle_image_typ(
             builder.envs_from_image_config(config.env());
             // set work_dir
             builder.work_dir(config.working_dir());
+            builder.stop_signal_from_image_config(config.stop_signal());
             // builder.users(config.user());
         }
         return Ok((Some(builder), bundle_path));
@@ -261,6 +262,7 @@ pub fn sync_handle_image_typ(
             builder.envs_from_image_config(config.env());
             // set work_dir
             builder.work_dir(config.working_dir());
+            builder.stop_signal_from_image_config(config.stop_signal());
             // builder.users(config.user());
         }
         return Ok((Some(builder), bundle_path));
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use crate::models::{NodeInfo, PodInfo, PodNameWithPriority};
 
 /// A shortcut of the node status.
+#[derive(Clone)]
 pub struct Cache {
     pods: HashMap<String, PodInfo>,
     nodes: HashMap<String, NodeInfo>,
@@ -50,6 +51,7 @@ impl Cache {
         pod_info.scheduled = Some(node_name.to_owned());
         node.requested.cpu += pod_info.spec.resources.cpu;
         node.requested.memory += pod_info.spec.resources.memory;
+        node.pod_count += 1;
 
         true
     }
@@ -66,6 +68,7 @@ impl Cache {
         pod_info.scheduled = None;
         node.requested.cpu -= pod_info.spec.resources.cpu;
         node.requested.memory -= pod_info.spec.resources.memory;
+        node.pod_count -= 1;
 
         Some(pod_info.clone())
     }
@@ -81,6 +84,7 @@ impl Cache {
         {
             node.requested.cpu -= p.spec.resources.cpu;
             node.requested.memory -= p.spec.resources.memory;
+            node.pod_count -= 1;
         }
         self.pods.remove(pod_name)
     }
@@ -92,6 +96,8 @@ impl Cache {
             old_node.labels = new_node.labels;
             old_node.spec = new_node.spec;
             old_node.allocatable = new_node.allocatable;
+            old_node.max_pods = new_node.max_pods;
+            old_node.images = new_node.images;
             old_node.requested = requested;
             Some(old_node.clone())
         } else {
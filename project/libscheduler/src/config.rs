@@ -0,0 +1,414 @@
+//! Scheduler configuration.
+//!
+//! Mirrors the shape of `KubeSchedulerConfiguration` in Kubernetes: a list of
+//! named profiles, each of which enables or disables plugins per extension
+//! point on top of [`Plugins::default`], picks a scoring strategy for
+//! `NodeResourcesFit` and tunes the pod backoff. Pods select a profile through
+//! `schedulerName`; pods without it use [`DEFAULT_SCHEDULER_NAME`].
+
+use std::collections::HashSet;
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::plugins::node_resources_fit::ScoringStrategy;
+use crate::plugins::{PluginInfo, Plugins, Registry};
+
+/// Profile used by pods which don't set `schedulerName`.
+pub const DEFAULT_SCHEDULER_NAME: &str = "default-scheduler";
+
+/// Weight given to a newly enabled score plugin when the config omits it.
+const DEFAULT_SCORE_WEIGHT: i64 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SchedulerConfiguration {
+    #[serde(default = "default_profiles")]
+    pub profiles: Vec<SchedulerProfile>,
+}
+
+impl Default for SchedulerConfiguration {
+    fn default() -> Self {
+        Self {
+            profiles: default_profiles(),
+        }
+    }
+}
+
+fn default_profiles() -> Vec<SchedulerProfile> {
+    vec![SchedulerProfile::default()]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SchedulerProfile {
+    #[serde(default = "default_scheduler_name")]
+    pub scheduler_name: String,
+    #[serde(default)]
+    pub scoring_strategy: ScoringStrategy,
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+    #[serde(default)]
+    pub backoff: BackoffConfig,
+}
+
+impl Default for SchedulerProfile {
+    fn default() -> Self {
+        Self {
+            scheduler_name: default_scheduler_name(),
+            scoring_strategy: ScoringStrategy::default(),
+            plugins: PluginsConfig::default(),
+            backoff: BackoffConfig::default(),
+        }
+    }
+}
+
+fn default_scheduler_name() -> String {
+    DEFAULT_SCHEDULER_NAME.to_string()
+}
+
+/// Plugins to enable or disable at each extension point.
+///
+/// Plugins enabled by default stay enabled unless they're listed in `disabled`;
+/// `"*"` in `disabled` drops every default plugin of that extension point.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PluginsConfig {
+    #[serde(default)]
+    pub pre_enqueue: PluginSet,
+    #[serde(default)]
+    pub pre_filter: PluginSet,
+    #[serde(default)]
+    pub filter: PluginSet,
+    #[serde(default)]
+    pub post_filter: PluginSet,
+    #[serde(default)]
+    pub pre_score: PluginSet,
+    #[serde(default)]
+    pub score: PluginSet,
+    #[serde(default)]
+    pub reserve: PluginSet,
+    #[serde(default)]
+    pub permit: PluginSet,
+    #[serde(default)]
+    pub pre_bind: PluginSet,
+    #[serde(default)]
+    pub bind: PluginSet,
+    #[serde(default)]
+    pub post_bind: PluginSet,
+    #[serde(default)]
+    pub enqueue_extensions: PluginSet,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PluginSet {
+    #[serde(default)]
+    pub enabled: Vec<PluginEntry>,
+    #[serde(default)]
+    pub disabled: Vec<PluginEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PluginEntry {
+    pub name: String,
+    /// Only meaningful for score plugins.
+    #[serde(default)]
+    pub weight: Option<i64>,
+}
+
+/// Backoff applied to pods that failed a scheduling attempt.
+///
+/// A pod waits `initial_backoff_seconds * 2^attempts`, capped at
+/// `max_backoff_seconds`. Once it fails more than `max_backoff_attempts`
+/// times it is parked in the unschedulable queue until a cluster event or
+/// the periodic flush moves it back.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackoffConfig {
+    #[serde(default = "default_initial_backoff_seconds")]
+    pub initial_backoff_seconds: u64,
+    #[serde(default = "default_max_backoff_seconds")]
+    pub max_backoff_seconds: u64,
+    #[serde(default = "default_max_backoff_attempts")]
+    pub max_backoff_attempts: usize,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff_seconds: default_initial_backoff_seconds(),
+            max_backoff_seconds: default_max_backoff_seconds(),
+            max_backoff_attempts: default_max_backoff_attempts(),
+        }
+    }
+}
+
+fn default_initial_backoff_seconds() -> u64 {
+    1
+}
+
+fn default_max_backoff_seconds() -> u64 {
+    512
+}
+
+fn default_max_backoff_attempts() -> usize {
+    8
+}
+
+impl BackoffConfig {
+    pub fn duration(&self, attempts: usize) -> std::time::Duration {
+        let factor = 2_u64.saturating_pow(attempts.min(u32::MAX as usize) as u32);
+        let secs = self
+            .initial_backoff_seconds
+            .saturating_mul(factor)
+            .min(self.max_backoff_seconds);
+        std::time::Duration::from_secs(secs)
+    }
+}
+
+/// A profile after its plugin set has been resolved against the registry.
+pub struct Profile {
+    pub scheduler_name: String,
+    pub strategy: ScoringStrategy,
+    pub plugins: Plugins,
+    pub backoff: BackoffConfig,
+}
+
+impl Profile {
+    pub fn new(scheduler_name: &str, strategy: ScoringStrategy, plugins: Plugins) -> Self {
+        Self {
+            scheduler_name: scheduler_name.to_string(),
+            strategy,
+            plugins,
+            backoff: BackoffConfig::default(),
+        }
+    }
+}
+
+impl SchedulerConfiguration {
+    /// Resolve every profile, failing on duplicated scheduler names or unknown plugins.
+    pub fn build_profiles(&self) -> Result<Vec<Profile>, anyhow::Error> {
+        if self.profiles.is_empty() {
+            bail!("scheduler configuration must contain at least one profile");
+        }
+        let mut names = HashSet::new();
+        let mut res = Vec::with_capacity(self.profiles.len());
+        for p in self.profiles.iter() {
+            if !names.insert(p.scheduler_name.as_str()) {
+                bail!("duplicated scheduler profile {}", p.scheduler_name);
+            }
+            res.push(p.build()?);
+        }
+        Ok(res)
+    }
+}
+
+impl SchedulerProfile {
+    pub fn build(&self) -> Result<Profile, anyhow::Error> {
+        if self.backoff.initial_backoff_seconds > self.backoff.max_backoff_seconds {
+            bail!(
+                "profile {}: initial_backoff_seconds must not exceed max_backoff_seconds",
+                self.scheduler_name
+            );
+        }
+        let registry = Registry::default();
+        let mut plugins = Plugins::default();
+        let cfg = &self.plugins;
+
+        macro_rules! apply_plugin_set {
+            ($point:ident, $default_weight:expr) => {
+                plugins.$point = cfg
+                    .$point
+                    .apply(
+                        stringify!($point),
+                        std::mem::take(&mut plugins.$point),
+                        &registry.$point.iter().map(|p| p.name()).collect::<Vec<_>>(),
+                        $default_weight,
+                    )
+                    .map_err(|e| anyhow!("profile {}: {e}", self.scheduler_name))?;
+            };
+        }
+
+        apply_plugin_set!(pre_enqueue, 0);
+        apply_plugin_set!(pre_filter, 0);
+        apply_plugin_set!(filter, 0);
+        apply_plugin_set!(post_filter, 0);
+        apply_plugin_set!(pre_score, 0);
+        apply_plugin_set!(score, DEFAULT_SCORE_WEIGHT);
+        apply_plugin_set!(reserve, 0);
+        apply_plugin_set!(permit, 0);
+        apply_plugin_set!(pre_bind, 0);
+        apply_plugin_set!(bind, 0);
+        apply_plugin_set!(post_bind, 0);
+        apply_plugin_set!(enqueue_extensions, 0);
+
+        Ok(Profile {
+            scheduler_name: self.scheduler_name.clone(),
+            strategy: self.scoring_strategy.clone(),
+            plugins,
+            backoff: self.backoff.clone(),
+        })
+    }
+}
+
+impl PluginSet {
+    fn apply(
+        &self,
+        point: &str,
+        mut current: Vec<PluginInfo>,
+        registered: &[&str],
+        default_weight: i64,
+    ) -> Result<Vec<PluginInfo>, anyhow::Error> {
+        for d in self.disabled.iter() {
+            if d.name == "*" {
+                current.clear();
+            } else {
+                current.retain(|p| p.name != d.name);
+            }
+        }
+        for e in self.enabled.iter() {
+            if !registered.contains(&e.name.as_str()) {
+                bail!(
+                    "plugin {} is not registered at extension point {point}",
+                    e.name
+                );
+            }
+            if let Some(weight) = e.weight
+                && weight < 0
+            {
+                bail!("plugin {} has negative weight {weight}", e.name);
+            }
+            if let Some(p) = current.iter_mut().find(|p| p.name == e.name) {
+                if let Some(weight) = e.weight {
+                    p.weight = weight;
+                }
+            } else {
+                current.push(PluginInfo {
+                    name: e.name.clone(),
+                    weight: e.weight.unwrap_or(default_weight),
+                });
+            }
+        }
+        Ok(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(infos: &[PluginInfo]) -> Vec<&str> {
+        infos.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_default_configuration() {
+        let profiles = SchedulerConfiguration::default().build_profiles().unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].scheduler_name, DEFAULT_SCHEDULER_NAME);
+        let default = Plugins::default();
+        assert_eq!(names(&profiles[0].plugins.score), names(&default.score));
+        assert_eq!(names(&profiles[0].plugins.filter), names(&default.filter));
+    }
+
+    #[test]
+    fn test_parse_profiles_from_yaml() {
+        let yaml = r#"
+profiles:
+  - scheduler_name: default-scheduler
+  - scheduler_name: bin-packing
+    scoring_strategy: MostAllocated
+    plugins:
+      score:
+        enabled:
+          - name: NodeResourcesFit
+            weight: 5
+        disabled:
+          - name: NodeResourcesBalancedAllocation
+      pre_score:
+        disabled:
+          - name: NodeResourcesBalancedAllocation
+    backoff:
+      initial_backoff_seconds: 2
+      max_backoff_seconds: 60
+"#;
+        let cfg: SchedulerConfiguration = serde_yaml::from_str(yaml).unwrap();
+        let profiles = cfg.build_profiles().unwrap();
+        assert_eq!(profiles.len(), 2);
+
+        let packing = &profiles[1];
+        assert_eq!(packing.scheduler_name, "bin-packing");
+        assert_eq!(packing.strategy, ScoringStrategy::MostAllocated);
+        assert!(!names(&packing.plugins.score).contains(&"NodeResourcesBalancedAllocation"));
+        assert!(!names(&packing.plugins.pre_score).contains(&"NodeResourcesBalancedAllocation"));
+        let fit = packing
+            .plugins
+            .score
+            .iter()
+            .find(|p| p.name == "NodeResourcesFit")
+            .unwrap();
+        assert_eq!(fit.weight, 5);
+        assert_eq!(packing.backoff.initial_backoff_seconds, 2);
+        assert_eq!(packing.backoff.max_backoff_seconds, 60);
+        assert_eq!(packing.backoff.max_backoff_attempts, 8);
+    }
+
+    #[test]
+    fn test_disable_all_and_enable() {
+        let set = PluginSet {
+            enabled: vec![PluginEntry {
+                name: "TaintToleration".to_string(),
+                weight: None,
+            }],
+            disabled: vec![PluginEntry {
+                name: "*".to_string(),
+                weight: None,
+            }],
+        };
+        let res = set
+            .apply(
+                "score",
+                Plugins::default().score,
+                &["TaintToleration", "NodeAffinity"],
+                DEFAULT_SCORE_WEIGHT,
+            )
+            .unwrap();
+        assert_eq!(names(&res), vec!["TaintToleration"]);
+        assert_eq!(res[0].weight, DEFAULT_SCORE_WEIGHT);
+    }
+
+    #[test]
+    fn test_unknown_plugin_rejected() {
+        let profile = SchedulerProfile {
+            plugins: PluginsConfig {
+                filter: PluginSet {
+                    enabled: vec![PluginEntry {
+                        name: "NoSuchPlugin".to_string(),
+                        weight: None,
+                    }],
+                    disabled: vec![],
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(profile.build().is_err());
+    }
+
+    #[test]
+    fn test_duplicated_profile_rejected() {
+        let cfg = SchedulerConfiguration {
+            profiles: vec![SchedulerProfile::default(), SchedulerProfile::default()],
+        };
+        assert!(cfg.build_profiles().is_err());
+    }
+
+    #[test]
+    fn test_backoff_duration() {
+        let backoff = BackoffConfig {
+            initial_backoff_seconds: 1,
+            max_backoff_seconds: 10,
+            max_backoff_attempts: 8,
+        };
+        assert_eq!(backoff.duration(1).as_secs(), 2);
+        assert_eq!(backoff.duration(3).as_secs(), 8);
+        assert_eq!(backoff.duration(4).as_secs(), 10);
+        assert_eq!(backoff.duration(100).as_secs(), 10);
+    }
+}
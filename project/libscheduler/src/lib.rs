@@ -1,6 +1,11 @@
 mod cache;
+pub mod config;
 mod cycle_state;
+pub mod metrics;
 pub mod models;
 pub mod plugins;
+pub mod predicates;
 pub mod scheduler;
+pub mod simulation;
+mod waiting_pods;
 pub mod with_xline;
@@ -0,0 +1,28 @@
+//! Metrics of the scheduling cycles, exported by rks on `/metrics`.
+
+use common::metrics::{Counter, DEFAULT_BUCKETS, Histogram, Metric};
+
+/// Scheduling attempts by profile and result, which is `scheduled`,
+/// `unschedulable`, `waiting` (held back by a permit plugin) or `error`.
+pub static SCHEDULE_ATTEMPTS: Counter = Counter::new(
+    "scheduler_schedule_attempts_total",
+    "Number of attempts to schedule pods, by profile and result.",
+    &["profile", "result"],
+);
+
+pub static SCHEDULING_ATTEMPT_DURATION: Histogram = Histogram::new(
+    "scheduler_scheduling_attempt_duration_seconds",
+    "Latency of a scheduling cycle in seconds, by profile and result.",
+    &["profile", "result"],
+    DEFAULT_BUCKETS,
+);
+
+/// Every metric of this module.
+pub fn metrics() -> [&'static dyn Metric; 2] {
+    [&SCHEDULE_ATTEMPTS, &SCHEDULING_ATTEMPT_DURATION]
+}
+
+pub(crate) fn record_attempt(profile: &str, result: &str, seconds: f64) {
+    SCHEDULE_ATTEMPTS.inc(&[profile, result]);
+    SCHEDULING_ATTEMPT_DURATION.observe(&[profile, result], seconds);
+}
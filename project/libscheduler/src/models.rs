@@ -1,4 +1,4 @@
-use std::{cmp::Ordering, collections::HashMap};
+use std::{cmp::Ordering, collections::HashMap, time::Duration};
 
 use tokio::time::Instant;
 
@@ -21,6 +21,102 @@ pub struct PodSpec {
     pub node_name: Option<String>,
     pub node_selector: HashMap<String, String>,
     pub affinity: Option<Affinity>,
+    /// Name of the scheduler profile which should handle this pod.
+    pub scheduler_name: Option<String>,
+    /// Host ports requested by the containers of the pod.
+    pub host_ports: Vec<HostPort>,
+    /// Images of all containers, normalized by [`normalized_image_name`].
+    pub images: Vec<String>,
+    /// Gang the pod belongs to, see [`PodGroup`].
+    pub pod_group: Option<PodGroup>,
+}
+
+/// Label naming the pod group of a pod.
+pub const POD_GROUP_LABEL: &str = "pod-group.scheduling.rk8s.io/name";
+/// Annotation holding `minMember` of the pod group.
+pub const POD_GROUP_MIN_MEMBER_ANNOTATION: &str = "pod-group.scheduling.rk8s.io/min-member";
+/// Annotation holding `scheduleTimeoutSeconds` of the pod group.
+pub const POD_GROUP_SCHEDULE_TIMEOUT_ANNOTATION: &str =
+    "pod-group.scheduling.rk8s.io/schedule-timeout-seconds";
+
+const DEFAULT_POD_GROUP_SCHEDULE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A group of pods which must be placed together or not at all.
+///
+/// Members that found a node wait at Permit with their resources reserved
+/// until `min_member` pods of the group are assigned. If that doesn't happen
+/// within `schedule_timeout`, every waiting member is unreserved and sent
+/// back to the backoff queue.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PodGroup {
+    pub name: String,
+    pub min_member: usize,
+    pub schedule_timeout: Duration,
+}
+
+impl PodGroup {
+    /// Read the pod group from the pod labels and annotations.
+    ///
+    /// Returns `None` if the pod carries no [`POD_GROUP_LABEL`].
+    pub fn from_metadata(
+        labels: &HashMap<String, String>,
+        annotations: &HashMap<String, String>,
+    ) -> Option<Self> {
+        let name = labels.get(POD_GROUP_LABEL)?.clone();
+        let min_member = annotations
+            .get(POD_GROUP_MIN_MEMBER_ANNOTATION)
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(1);
+        let schedule_timeout = annotations
+            .get(POD_GROUP_SCHEDULE_TIMEOUT_ANNOTATION)
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_POD_GROUP_SCHEDULE_TIMEOUT);
+        Some(Self {
+            name,
+            min_member,
+            schedule_timeout,
+        })
+    }
+}
+
+/// A port on the node which is bound by a pod.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash)]
+pub struct HostPort {
+    /// Empty or `0.0.0.0` means all addresses of the node.
+    pub ip: String,
+    pub protocol: String,
+    pub port: i32,
+}
+
+impl HostPort {
+    fn binds_all(&self) -> bool {
+        self.ip.is_empty() || self.ip == "0.0.0.0"
+    }
+
+    /// Two ports conflict if they share protocol and port number
+    /// and either of them binds the address the other one binds.
+    pub fn conflicts(&self, other: &HostPort) -> bool {
+        self.port == other.port
+            && self.protocol.eq_ignore_ascii_case(&other.protocol)
+            && (self.binds_all() || other.binds_all() || self.ip == other.ip)
+    }
+}
+
+/// Normalize an image reference to the form rkforge stores locally,
+/// e.g. `nginx` becomes `library/nginx:latest`.
+pub fn normalized_image_name(image: &str) -> String {
+    let image = if image.contains('/') {
+        image.to_string()
+    } else {
+        format!("library/{image}")
+    };
+    let name = image.rsplit('/').next().unwrap_or_default();
+    if name.contains(':') || name.contains('@') {
+        image
+    } else {
+        format!("{image}:latest")
+    }
 }
 
 #[derive(Clone, Default, Debug)]
@@ -249,6 +345,14 @@ impl PodInfo {
             scheduled: None,
         }
     }
+
+    /// The scheduler profile of the pod, falling back to the default profile.
+    pub fn scheduler_name(&self) -> &str {
+        self.spec
+            .scheduler_name
+            .as_deref()
+            .unwrap_or(crate::config::DEFAULT_SCHEDULER_NAME)
+    }
 }
 
 #[derive(Clone, Default, Debug)]
@@ -264,6 +368,12 @@ pub struct NodeInfo {
     pub spec: NodeSpec,
     pub requested: ResourcesRequirements,
     pub allocatable: ResourcesRequirements,
+    /// Maximum number of pods the node accepts, 0 means unlimited.
+    pub max_pods: u64,
+    /// Number of pods assigned to the node.
+    pub pod_count: u64,
+    /// Images cached on the node, mapped to their size in bytes.
+    pub images: HashMap<String, u64>,
 }
 
 pub type PodNameWithPriority = (u64, String);
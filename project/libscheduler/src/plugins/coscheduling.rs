@@ -0,0 +1,172 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{
+    cycle_state::CycleState,
+    models::PodInfo,
+    plugins::{Code, PermitPlugin, Plugin, ReservePlugin, Status},
+    waiting_pods::{WAITING_PODS_KEY, WaitingPods},
+};
+
+/// Coscheduling places the pods of a [`PodGroup`](crate::models::PodGroup)
+/// all together or not at all.
+///
+/// Members are held at Permit with their resources reserved until
+/// `min_member` pods of the group are assigned, then released together.
+/// When a member is unreserved, the waiting members of its group are
+/// rejected too so the group goes back to the queue as a whole.
+pub struct Coscheduling;
+
+fn in_group(pod: &PodInfo, group: &str) -> bool {
+    matches!(&pod.spec.pod_group, Some(g) if g.name == group)
+}
+
+impl Plugin for Coscheduling {
+    fn name(&self) -> &str {
+        "Coscheduling"
+    }
+}
+
+impl ReservePlugin for Coscheduling {
+    fn reserve(&self, _state: &mut CycleState, _pod: &PodInfo, _node_name: &str) -> Status {
+        Status::default()
+    }
+
+    fn unreserve(&self, state: &mut CycleState, pod: &PodInfo, _node_name: &str) {
+        let Some(group) = &pod.spec.pod_group else {
+            return;
+        };
+        let Some(waiting) = state.read::<Arc<WaitingPods>>(WAITING_PODS_KEY) else {
+            return;
+        };
+        for p in waiting.pods() {
+            if p.name != pod.name && in_group(&p, &group.name) {
+                waiting.reject(
+                    &p.name,
+                    self.name(),
+                    &format!("pod {} of group {} was unreserved", pod.name, group.name),
+                );
+            }
+        }
+    }
+}
+
+impl PermitPlugin for Coscheduling {
+    fn permit(
+        &self,
+        state: &mut CycleState,
+        pod: &PodInfo,
+        _node_name: &str,
+    ) -> (Status, Duration) {
+        let Some(group) = &pod.spec.pod_group else {
+            return (Status::default(), Duration::ZERO);
+        };
+        // Waiting members are assumed, so they are counted here as well.
+        let assigned = state
+            .read::<Vec<PodInfo>>("AllScheduledPods")
+            .map(|pods| {
+                pods.iter()
+                    .filter(|p| p.name != pod.name && in_group(p, &group.name))
+                    .count()
+            })
+            .unwrap_or(0);
+        if assigned + 1 < group.min_member {
+            return (
+                Status::new(
+                    Code::Wait,
+                    vec![format!(
+                        "pod group {} has {} of {} members assigned",
+                        group.name,
+                        assigned + 1,
+                        group.min_member
+                    )],
+                ),
+                group.schedule_timeout,
+            );
+        }
+        if let Some(waiting) = state.read::<Arc<WaitingPods>>(WAITING_PODS_KEY) {
+            for p in waiting.pods() {
+                if in_group(&p, &group.name) {
+                    waiting.allow(&p.name, self.name());
+                }
+            }
+        }
+        (Status::default(), Duration::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::models::{PodGroup, PodSpec};
+
+    fn make_pod(name: &str, scheduled: Option<&str>) -> PodInfo {
+        PodInfo {
+            name: name.to_string(),
+            spec: PodSpec {
+                pod_group: Some(PodGroup {
+                    name: "gang".to_string(),
+                    min_member: 2,
+                    schedule_timeout: Duration::from_secs(10),
+                }),
+                ..Default::default()
+            },
+            scheduled: scheduled.map(|s| s.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_permit_without_group() {
+        let mut state = CycleState::default();
+        let (status, _) = Coscheduling.permit(&mut state, &PodInfo::default(), "node");
+        assert_eq!(status.code, Code::Success);
+    }
+
+    #[test]
+    fn test_permit_waits_until_min_member() {
+        let waiting = Arc::new(WaitingPods::default());
+        let mut state = CycleState::default();
+        state.write(WAITING_PODS_KEY, Box::new(waiting.clone()));
+        state.write("AllScheduledPods", Box::new(Vec::<PodInfo>::new()));
+
+        let first = make_pod("first", None);
+        let (status, timeout) = Coscheduling.permit(&mut state, &first, "node");
+        assert_eq!(status.code, Code::Wait);
+        assert_eq!(timeout, Duration::from_secs(10));
+        waiting.add(
+            make_pod("first", Some("node")),
+            "node",
+            HashMap::from([("Coscheduling".to_string(), timeout)]),
+        );
+
+        state.write(
+            "AllScheduledPods",
+            Box::new(vec![make_pod("first", Some("node"))]),
+        );
+        let (status, _) = Coscheduling.permit(&mut state, &make_pod("second", None), "node");
+        assert_eq!(status.code, Code::Success);
+        let allowed = waiting.take_allowed();
+        assert_eq!(allowed.len(), 1);
+        assert_eq!(allowed[0].pod.name, "first");
+    }
+
+    #[test]
+    fn test_unreserve_rejects_waiting_members() {
+        let waiting = Arc::new(WaitingPods::default());
+        let mut state = CycleState::default();
+        state.write(WAITING_PODS_KEY, Box::new(waiting.clone()));
+        waiting.add(
+            make_pod("first", Some("node")),
+            "node",
+            HashMap::from([("Coscheduling".to_string(), Duration::from_secs(10))]),
+        );
+
+        Coscheduling.unreserve(&mut state, &make_pod("second", Some("node")), "node");
+        let rejected = waiting.take_rejected(tokio::time::Instant::now());
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].pod.name, "first");
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use crate::{
+    cycle_state::CycleState,
+    models::{NodeInfo, PodInfo},
+    plugins::{
+        Code, DefaultNormalizeScore, Plugin, PreScorePlugin, ScoreExtension, ScorePlugin, Status,
+    },
+};
+
+/// ImageLocality favors nodes that already have the images of the pod,
+/// so less data has to be pulled before the pod starts.
+pub struct ImageLocality;
+
+const PRE_SCORE_KEY: &str = "PreScoreImageLocality";
+
+/// Images smaller than this don't make a difference to the score.
+const MIN_THRESHOLD: u64 = 23 * 1024 * 1024;
+/// Upper bound of the contribution of a single container.
+const MAX_CONTAINER_THRESHOLD: u64 = 1000 * 1024 * 1024;
+const MAX_NODE_SCORE: i64 = 100;
+
+struct PreScoreState {
+    images: Vec<String>,
+    /// Number of candidate nodes holding each image of the pod.
+    spread: HashMap<String, usize>,
+    total_nodes: usize,
+}
+
+impl Plugin for ImageLocality {
+    fn name(&self) -> &str {
+        "ImageLocality"
+    }
+}
+
+impl PreScorePlugin for ImageLocality {
+    fn pre_score(&self, state: &mut CycleState, pod: &PodInfo, nodes: Vec<NodeInfo>) -> Status {
+        if pod.spec.images.is_empty() {
+            return Status::new(Code::Skip, vec![]);
+        }
+        let spread = pod
+            .spec
+            .images
+            .iter()
+            .map(|img| {
+                let count = nodes.iter().filter(|n| n.images.contains_key(img)).count();
+                (img.clone(), count)
+            })
+            .collect();
+        state.write(
+            PRE_SCORE_KEY,
+            Box::new(PreScoreState {
+                images: pod.spec.images.clone(),
+                spread,
+                total_nodes: nodes.len(),
+            }),
+        );
+        Status::default()
+    }
+}
+
+impl ScorePlugin for ImageLocality {
+    fn score(&self, state: &mut CycleState, _pod: &PodInfo, node_info: NodeInfo) -> (i64, Status) {
+        let Some(s) = state.read::<PreScoreState>(PRE_SCORE_KEY) else {
+            return (
+                0,
+                Status::error("ImageLocality scoring error when get pre-score state"),
+            );
+        };
+        (
+            calculate_priority(sum_image_scores(s, &node_info), s.images.len()),
+            Status::default(),
+        )
+    }
+
+    fn score_extension(&self) -> Box<dyn ScoreExtension> {
+        Box::new(DefaultNormalizeScore {
+            max_score: MAX_NODE_SCORE,
+            reverse: false,
+        })
+    }
+}
+
+/// Sum of the sizes of the pod images present on the node, each scaled down
+/// by how few candidate nodes have it, so that pods aren't all piled onto the
+/// one node that happens to have an image.
+fn sum_image_scores(state: &PreScoreState, node: &NodeInfo) -> u64 {
+    if state.total_nodes == 0 {
+        return 0;
+    }
+    state
+        .images
+        .iter()
+        .filter_map(|img| {
+            let size = node.images.get(img)?;
+            let spread = *state.spread.get(img).unwrap_or(&0) as f64 / state.total_nodes as f64;
+            Some((*size as f64 * spread) as u64)
+        })
+        .sum()
+}
+
+fn calculate_priority(sum_scores: u64, num_containers: usize) -> i64 {
+    let max_threshold = MAX_CONTAINER_THRESHOLD * num_containers as u64;
+    if max_threshold <= MIN_THRESHOLD {
+        return 0;
+    }
+    let sum_scores = sum_scores.clamp(MIN_THRESHOLD, max_threshold);
+    MAX_NODE_SCORE * (sum_scores - MIN_THRESHOLD) as i64 / (max_threshold - MIN_THRESHOLD) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PodSpec;
+
+    const MB: u64 = 1024 * 1024;
+
+    fn make_node(name: &str, images: &[(&str, u64)]) -> NodeInfo {
+        NodeInfo {
+            name: name.to_string(),
+            images: images.iter().map(|(n, s)| (n.to_string(), *s)).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pre_score_skip_without_images() {
+        let mut state = CycleState::default();
+        let status = ImageLocality.pre_score(&mut state, &PodInfo::default(), vec![]);
+        assert_eq!(status.code, Code::Skip);
+    }
+
+    #[test]
+    fn test_score_prefers_node_with_image() {
+        let mut state = CycleState::default();
+        let pod = PodInfo {
+            name: "pod".to_string(),
+            spec: PodSpec {
+                images: vec!["library/nginx:latest".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let with_image = make_node("node1", &[("library/nginx:latest", 500 * MB)]);
+        let without_image = make_node("node2", &[]);
+        let status = ImageLocality.pre_score(
+            &mut state,
+            &pod,
+            vec![with_image.clone(), without_image.clone()],
+        );
+        assert_eq!(status.code, Code::Success);
+
+        let (with_score, _) = ImageLocality.score(&mut state, &pod, with_image);
+        let (without_score, _) = ImageLocality.score(&mut state, &pod, without_image);
+        assert!(with_score > without_score);
+        assert_eq!(without_score, 0);
+    }
+
+    #[test]
+    fn test_small_images_are_ignored() {
+        assert_eq!(calculate_priority(10 * MB, 1), 0);
+        assert_eq!(calculate_priority(5000 * MB, 1), MAX_NODE_SCORE);
+    }
+}
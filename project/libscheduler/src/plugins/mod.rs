@@ -9,7 +9,10 @@
 use crate::cycle_state::CycleState;
 use crate::models::{NodeInfo, PodInfo};
 use crate::plugins::balanced_allocation::BalancedAllocation;
+use crate::plugins::coscheduling::Coscheduling;
+use crate::plugins::image_locality::ImageLocality;
 use crate::plugins::node_name::NodeName;
+use crate::plugins::node_ports::NodePorts;
 use crate::plugins::node_resources_fit::Fit;
 use crate::plugins::node_unschedulable::NodeUnschedulable;
 use crate::plugins::scheduling_gates::SchedulingGates;
@@ -21,8 +24,11 @@ use std::sync::Arc;
 use std::time::Duration;
 
 pub mod balanced_allocation;
+pub mod coscheduling;
+pub mod image_locality;
 pub mod node_affinity;
 pub mod node_name;
+pub mod node_ports;
 pub mod node_resources_fit;
 pub mod node_unschedulable;
 pub mod pod_affinity;
@@ -83,11 +89,19 @@ impl Default for Plugins {
         let taint_toleration = PluginInfo::with_weight("TaintToleration", 3);
         let balanced_allocation = PluginInfo::with_weight("NodeResourcesBalancedAllocation", 1);
         let pod_affinity = PluginInfo::with_weight("PodAffinity", 2);
+        let node_ports = PluginInfo::new("NodePorts");
+        let image_locality = PluginInfo::with_weight("ImageLocality", 1);
+        let coscheduling = PluginInfo::new("Coscheduling");
 
         Self {
             pre_enqueue: vec![scheduling_gates.clone()],
             queue_sort: PluginInfo::new("PrioritySort"),
-            pre_filter: vec![node_affinity.clone(), fit.clone(), pod_affinity.clone()],
+            pre_filter: vec![
+                node_affinity.clone(),
+                fit.clone(),
+                pod_affinity.clone(),
+                node_ports.clone(),
+            ],
             filter: vec![
                 node_affinity.clone(),
                 fit.clone(),
@@ -95,6 +109,7 @@ impl Default for Plugins {
                 node_name.clone(),
                 node_unschedulable.clone(),
                 pod_affinity.clone(),
+                node_ports.clone(),
             ],
             post_filter: vec![],
             pre_score: vec![
@@ -103,6 +118,7 @@ impl Default for Plugins {
                 balanced_allocation.clone(),
                 taint_toleration.clone(),
                 pod_affinity.clone(),
+                image_locality.clone(),
             ],
             score: vec![
                 node_affinity.clone(),
@@ -110,9 +126,10 @@ impl Default for Plugins {
                 balanced_allocation.clone(),
                 taint_toleration.clone(),
                 pod_affinity.clone(),
+                image_locality.clone(),
             ],
-            reserve: vec![],
-            permit: vec![],
+            reserve: vec![coscheduling.clone()],
+            permit: vec![coscheduling.clone()],
             pre_bind: vec![],
             bind: vec![],
             post_bind: vec![],
@@ -123,6 +140,7 @@ impl Default for Plugins {
                 fit.clone(),
                 taint_toleration.clone(),
                 pod_affinity.clone(),
+                node_ports.clone(),
             ],
         }
     }
@@ -310,7 +328,7 @@ impl ScoreExtension for DefaultNormalizeScore {
 /// Plugin that manages state updates when pods are reserved/unreserved
 pub trait ReservePlugin: Plugin + Send + Sync {
     /// Called when scheduler cache is updated. Failure triggers Unreserve for all plugins.
-    fn reserve(&self, state: &mut CycleState, pod: &PodInfo, node_name: &str) -> &Status;
+    fn reserve(&self, state: &mut CycleState, pod: &PodInfo, node_name: &str) -> Status;
 
     /// Called when a reserved pod is rejected or fails later. Must be idempotent.
     fn unreserve(&self, state: &mut CycleState, pod: &PodInfo, node_name: &str);
@@ -393,10 +411,18 @@ impl Default for Registry {
         let taint_toleration = Arc::new(TaintToleration {});
         let balanced_allocation = Arc::new(BalancedAllocation::default());
         let pod_affinity = Arc::new(pod_affinity::PodAffinityPlugin);
+        let node_ports = Arc::new(NodePorts);
+        let image_locality = Arc::new(ImageLocality);
+        let coscheduling = Arc::new(Coscheduling);
 
         Self {
             pre_enqueue: vec![scheduling_gates.clone()],
-            pre_filter: vec![node_affinity.clone(), fit.clone(), pod_affinity.clone()],
+            pre_filter: vec![
+                node_affinity.clone(),
+                fit.clone(),
+                pod_affinity.clone(),
+                node_ports.clone(),
+            ],
             filter: vec![
                 node_affinity.clone(),
                 fit.clone(),
@@ -404,6 +430,7 @@ impl Default for Registry {
                 node_name.clone(),
                 node_unschedulable.clone(),
                 pod_affinity.clone(),
+                node_ports.clone(),
             ],
             post_filter: vec![],
             pre_score: vec![
@@ -412,6 +439,7 @@ impl Default for Registry {
                 balanced_allocation.clone(),
                 taint_toleration.clone(),
                 pod_affinity.clone(),
+                image_locality.clone(),
             ],
             score: vec![
                 node_affinity.clone(),
@@ -419,10 +447,11 @@ impl Default for Registry {
                 balanced_allocation.clone(),
                 taint_toleration.clone(),
                 pod_affinity.clone(),
+                image_locality.clone(),
             ],
+            reserve: vec![coscheduling.clone()],
+            permit: vec![coscheduling.clone()],
             // below features are unimplemented
-            reserve: vec![],
-            permit: vec![],
             pre_bind: vec![],
             bind: vec![],
             post_bind: vec![],
@@ -433,6 +462,7 @@ impl Default for Registry {
                 fit.clone(),
                 taint_toleration.clone(),
                 pod_affinity.clone(),
+                node_ports.clone(),
             ],
         }
     }
@@ -496,7 +526,7 @@ pub enum Code {
     /// other postFilter plugins like preemption would not change anything.
     UnschedulableAndUnresolvable,
     /// Wait is used when a Permit plugin finds a pod scheduling should wait.
-    _Wait,
+    Wait,
     /// Skip is used in the following scenarios:
     /// - when a Bind plugin chooses to skip binding.
     /// - when a PreFilter plugin returns Skip so that coupled Filter plugin/PreFilterExtensions() will be skipped.
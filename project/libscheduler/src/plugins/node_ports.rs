@@ -0,0 +1,222 @@
+use crate::{
+    cycle_state::CycleState,
+    models::{HostPort, NodeInfo, PodInfo},
+    plugins::{
+        ActionType, ClusterEvent, ClusterEventWithHint, Code, EnqueueExtension, EventInner,
+        EventResource, FilterPlugin, Plugin, PreFilterPlugin, PreFilterResult, QueueingHint,
+        Status,
+    },
+};
+
+/// NodePorts checks if a node has free ports for the requested pod ports.
+pub struct NodePorts;
+
+const PRE_FILTER_KEY: &str = "PreFilterNodePorts";
+const ERR_REASON: &str = "node(s) didn't have free ports for the requested pod ports";
+
+impl Plugin for NodePorts {
+    fn name(&self) -> &str {
+        "NodePorts"
+    }
+}
+
+impl EnqueueExtension for NodePorts {
+    fn events_to_register(&self) -> Vec<ClusterEventWithHint> {
+        vec![
+            ClusterEventWithHint {
+                event: ClusterEvent {
+                    resource: EventResource::Pod,
+                    action_type: ActionType::Delete,
+                },
+                queueing_hint_fn: Some(Box::new(is_schedulable_after_pod_deleted)),
+            },
+            ClusterEventWithHint {
+                event: ClusterEvent {
+                    resource: EventResource::Node,
+                    action_type: ActionType::Add,
+                },
+                queueing_hint_fn: Some(Box::new(|_, _| Ok(QueueingHint::Queue))),
+            },
+        ]
+    }
+}
+
+fn is_schedulable_after_pod_deleted(
+    pod: PodInfo,
+    event: EventInner,
+) -> Result<QueueingHint, String> {
+    match event {
+        EventInner::Pod(original, modified) => {
+            if modified.is_some() {
+                return Ok(QueueingHint::Skip);
+            }
+            let Some(deleted) = *original else {
+                return Ok(QueueingHint::Skip);
+            };
+            if fits_ports(&pod.spec.host_ports, &deleted.spec.host_ports) {
+                Ok(QueueingHint::Skip)
+            } else {
+                log::trace!(
+                    "pod with conflicting host ports was deleted. pod {} deleted {}",
+                    pod.name,
+                    deleted.name
+                );
+                Ok(QueueingHint::Queue)
+            }
+        }
+        _ => Err(format!(
+            "event inner {event:?} not match event resource pod"
+        )),
+    }
+}
+
+struct PreFilterState {
+    wanted_ports: Vec<HostPort>,
+}
+
+fn fits_ports(wanted: &[HostPort], used: &[HostPort]) -> bool {
+    !wanted.iter().any(|w| used.iter().any(|u| w.conflicts(u)))
+}
+
+fn used_ports(all_pods: &[PodInfo], node_name: &str) -> Vec<HostPort> {
+    all_pods
+        .iter()
+        .filter(|p| p.scheduled.as_deref().or(p.spec.node_name.as_deref()) == Some(node_name))
+        .flat_map(|p| p.spec.host_ports.iter().cloned())
+        .collect()
+}
+
+impl PreFilterPlugin for NodePorts {
+    fn pre_filter(
+        &self,
+        state: &mut CycleState,
+        pod: &PodInfo,
+        _nodes: Vec<NodeInfo>,
+    ) -> (PreFilterResult, Status) {
+        if pod.spec.host_ports.is_empty() {
+            return (
+                PreFilterResult { node_names: vec![] },
+                Status::new(Code::Skip, vec![]),
+            );
+        }
+        state.write(
+            PRE_FILTER_KEY,
+            Box::new(PreFilterState {
+                wanted_ports: pod.spec.host_ports.clone(),
+            }),
+        );
+        (PreFilterResult { node_names: vec![] }, Status::default())
+    }
+}
+
+impl FilterPlugin for NodePorts {
+    fn filter(&self, state: &mut CycleState, _pod: &PodInfo, node_info: NodeInfo) -> Status {
+        let Some(s) = state.read::<PreFilterState>(PRE_FILTER_KEY) else {
+            return Status::error("Failed to read pre-filter state");
+        };
+        let all_pods = state
+            .read::<Vec<PodInfo>>("AllScheduledPods")
+            .map(|p| p.as_slice())
+            .unwrap_or_default();
+        if fits_ports(&s.wanted_ports, &used_ports(all_pods, &node_info.name)) {
+            Status::default()
+        } else {
+            Status::new(Code::Unschedulable, vec![ERR_REASON.to_string()])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PodSpec;
+
+    fn port(ip: &str, port: i32) -> HostPort {
+        HostPort {
+            ip: ip.to_string(),
+            protocol: "TCP".to_string(),
+            port,
+        }
+    }
+
+    fn make_pod(name: &str, node: Option<&str>, host_ports: Vec<HostPort>) -> PodInfo {
+        PodInfo {
+            name: name.to_string(),
+            spec: PodSpec {
+                host_ports,
+                ..Default::default()
+            },
+            scheduled: node.map(|n| n.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_host_port_conflicts() {
+        assert!(port("", 80).conflicts(&port("10.0.0.1", 80)));
+        assert!(port("0.0.0.0", 80).conflicts(&port("", 80)));
+        assert!(port("10.0.0.1", 80).conflicts(&port("10.0.0.1", 80)));
+        assert!(!port("10.0.0.1", 80).conflicts(&port("10.0.0.2", 80)));
+        assert!(!port("", 80).conflicts(&port("", 8080)));
+
+        let mut udp = port("", 80);
+        udp.protocol = "UDP".to_string();
+        assert!(!udp.conflicts(&port("", 80)));
+    }
+
+    #[test]
+    fn test_pre_filter_skip_without_host_ports() {
+        let mut state = CycleState::default();
+        let pod = make_pod("pod", None, vec![]);
+        let (_, status) = NodePorts.pre_filter(&mut state, &pod, vec![]);
+        assert_eq!(status.code, Code::Skip);
+    }
+
+    #[test]
+    fn test_filter_node_ports() {
+        let mut state = CycleState::default();
+        let pod = make_pod("pod", None, vec![port("", 8080)]);
+        state.write(
+            "AllScheduledPods",
+            Box::new(vec![make_pod(
+                "existing",
+                Some("node1"),
+                vec![port("", 8080)],
+            )]),
+        );
+        let (_, status) = NodePorts.pre_filter(&mut state, &pod, vec![]);
+        assert_eq!(status.code, Code::Success);
+
+        let node1 = NodeInfo {
+            name: "node1".to_string(),
+            ..Default::default()
+        };
+        let node2 = NodeInfo {
+            name: "node2".to_string(),
+            ..Default::default()
+        };
+        let result = NodePorts.filter(&mut state, &pod, node1);
+        assert_eq!(result.code, Code::Unschedulable);
+        assert!(result.reasons.contains(&ERR_REASON.to_string()));
+        let result = NodePorts.filter(&mut state, &pod, node2);
+        assert_eq!(result.code, Code::Success);
+    }
+
+    #[test]
+    fn test_requeue_after_conflicting_pod_deleted() {
+        let pod = make_pod("pod", None, vec![port("", 8080)]);
+        let deleted = make_pod("existing", Some("node1"), vec![port("", 8080)]);
+        let hint = is_schedulable_after_pod_deleted(
+            pod.clone(),
+            EventInner::Pod(Box::new(Some(deleted)), Box::new(None)),
+        );
+        assert!(matches!(hint, Ok(QueueingHint::Queue)));
+
+        let deleted = make_pod("other", Some("node1"), vec![port("", 9090)]);
+        let hint = is_schedulable_after_pod_deleted(
+            pod,
+            EventInner::Pod(Box::new(Some(deleted)), Box::new(None)),
+        );
+        assert!(matches!(hint, Ok(QueueingHint::Skip)));
+    }
+}
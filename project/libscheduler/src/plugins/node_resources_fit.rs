@@ -7,12 +7,13 @@ use crate::{
         PreFilterResult, PreScorePlugin, QueueingHint, ScoreExtension, ScorePlugin, Status,
     },
 };
+use serde::{Deserialize, Serialize};
 
 pub struct Fit;
 
 const SCORING_STRATEGY_CONFIG_KEY: &str = "ScoringStrategyConfig";
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ScoringStrategy {
     #[default]
     LeastAllocated,
@@ -115,7 +116,16 @@ impl PreFilterPlugin for Fit {
     }
 }
 
+/// Whether the node already holds as many pods as it allows.
+fn is_full(node: &NodeInfo) -> bool {
+    node.max_pods > 0 && node.pod_count >= node.max_pods
+}
+
 fn is_fit(pod_requests: &ResourcesRequirements, node: &NodeInfo) -> bool {
+    if is_full(node) {
+        return false;
+    }
+
     let node_allocatable = &node.allocatable;
     let node_requested = &node.requested;
 
@@ -133,12 +143,18 @@ fn is_fit(pod_requests: &ResourcesRequirements, node: &NodeInfo) -> bool {
 }
 
 const ERR_REASON_RESOURCES: &str = "node(s) didn't have enough resource(s)";
+const ERR_REASON_TOO_MANY_PODS: &str = "Too many pods";
 
 impl FilterPlugin for Fit {
     fn filter(&self, state: &mut CycleState, _pod: &PodInfo, node_info: NodeInfo) -> Status {
         let s = state.read::<PreFilterState>("PreFilterNodeResourcesFit");
         if let Some(sta) = s {
-            if !is_fit(&sta.pod_requests, &node_info) {
+            if is_full(&node_info) {
+                Status::new(
+                    Code::Unschedulable,
+                    vec![ERR_REASON_TOO_MANY_PODS.to_string()],
+                )
+            } else if !is_fit(&sta.pod_requests, &node_info) {
                 Status::new(Code::Unschedulable, vec![ERR_REASON_RESOURCES.to_string()])
             } else {
                 Status::default()
@@ -417,6 +433,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_node_resources_fit_filter_too_many_pods() {
+        let plugin = Fit;
+        let mut state = CycleState::default();
+
+        let pod = PodInfo {
+            name: "test-pod".to_string(),
+            ..Default::default()
+        };
+
+        let mut node = NodeInfo {
+            name: "test-node".to_string(),
+            allocatable: ResourcesRequirements {
+                cpu: 4000,
+                memory: 8 * 1024 * 1024 * 1024,
+            },
+            max_pods: 2,
+            pod_count: 2,
+            ..Default::default()
+        };
+
+        state.write(
+            "PreFilterNodeResourcesFit",
+            Box::new(PreFilterState {
+                pod_requests: ResourcesRequirements::default(),
+            }),
+        );
+
+        let result = plugin.filter(&mut state, &pod, node.clone());
+        assert_eq!(result.code, Code::Unschedulable);
+        assert!(result.reasons.contains(&"Too many pods".to_string()));
+
+        node.pod_count = 1;
+        let result = plugin.filter(&mut state, &pod, node);
+        assert_eq!(result.code, Code::Success);
+    }
+
     #[test]
     fn test_node_resources_fit_pre_score() {
         let plugin = Fit;
@@ -0,0 +1,135 @@
+//! Filter plugins run outside of a scheduling cycle.
+//!
+//! Lets other components, e.g. a descheduler, ask the same questions the
+//! scheduler asks: does this pod still fit the node it runs on, and is there
+//! another node it could go to.
+
+use std::sync::Arc;
+
+use crate::cycle_state::CycleState;
+use crate::models::{NodeInfo, PodInfo};
+use crate::plugins::{Code, FilterPlugin, PreFilterPlugin, Registry};
+use crate::simulation::NodeRejection;
+
+pub struct Predicates {
+    pre_filter: Vec<Arc<dyn PreFilterPlugin>>,
+    filter: Vec<Arc<dyn FilterPlugin>>,
+}
+
+impl Predicates {
+    /// Pick the pre-filter and filter plugins named in `plugins` from the
+    /// default registry. Unknown names are ignored.
+    pub fn new(plugins: &[&str]) -> Self {
+        let registry = Registry::default();
+        Self {
+            pre_filter: registry
+                .pre_filter
+                .into_iter()
+                .filter(|p| plugins.contains(&p.name()))
+                .collect(),
+            filter: registry
+                .filter
+                .into_iter()
+                .filter(|p| plugins.contains(&p.name()))
+                .collect(),
+        }
+    }
+
+    /// Check whether `pod` passes the filters on the node `node_name`.
+    ///
+    /// `nodes` and `pods` are the cluster state seen by the plugins. The pod
+    /// itself is left out of `pods`, so a running pod is checked as if it was
+    /// being placed again.
+    pub fn check(
+        &self,
+        pod: &PodInfo,
+        node_name: &str,
+        nodes: &[NodeInfo],
+        pods: &[PodInfo],
+    ) -> Result<(), NodeRejection> {
+        let Some(node) = nodes.iter().find(|n| n.name == node_name) else {
+            return Err(NodeRejection {
+                node_name: node_name.to_string(),
+                plugin: String::new(),
+                reasons: vec!["node not found".to_string()],
+            });
+        };
+        let reject = |plugin: &str, reasons: Vec<String>| NodeRejection {
+            node_name: node_name.to_string(),
+            plugin: plugin.to_string(),
+            reasons,
+        };
+
+        let mut state = CycleState::default();
+        let scheduled: Vec<PodInfo> = pods
+            .iter()
+            .filter(|p| p.name != pod.name)
+            .filter(|p| p.scheduled.is_some() || p.spec.node_name.is_some())
+            .cloned()
+            .collect();
+        state.write("AllScheduledPods", Box::new(scheduled));
+
+        for pl in self.pre_filter.iter() {
+            let (res, sta) = pl.pre_filter(&mut state, pod, nodes.to_vec());
+            match sta.code {
+                Code::Skip => {
+                    state.skip_filter_plugins.insert(pl.name().to_string());
+                }
+                Code::Success | Code::Error => {}
+                _ => return Err(reject(pl.name(), sta.reasons)),
+            }
+            if res.node_names.iter().any(|n| n == node_name) {
+                return Err(reject(
+                    pl.name(),
+                    vec!["filtered out at PreFilter".to_string()],
+                ));
+            }
+        }
+        for pl in self.filter.iter() {
+            if state.skip_filter_plugins.contains(pl.name()) {
+                continue;
+            }
+            let sta = pl.filter(&mut state, pod, node.clone());
+            if !matches!(sta.code, Code::Success | Code::Skip) {
+                return Err(reject(pl.name(), sta.reasons));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::{Taint, TaintEffect, TaintKey};
+
+    use super::*;
+    use crate::models::NodeSpec;
+
+    #[test]
+    fn test_check_untolerated_taint() {
+        let predicates = Predicates::new(&["TaintToleration"]);
+        let nodes = vec![
+            NodeInfo {
+                name: "tainted".to_string(),
+                spec: NodeSpec {
+                    taints: vec![Taint::new(TaintKey::NodeNotReady, TaintEffect::NoSchedule)],
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            NodeInfo {
+                name: "clean".to_string(),
+                ..Default::default()
+            },
+        ];
+        let pod = PodInfo {
+            name: "pod".to_string(),
+            ..Default::default()
+        };
+
+        let err = predicates.check(&pod, "tainted", &nodes, &[]).unwrap_err();
+        assert_eq!(err.plugin, "TaintToleration");
+        assert!(predicates.check(&pod, "clean", &nodes, &[]).is_ok());
+        assert!(predicates.check(&pod, "missing", &nodes, &[]).is_err());
+    }
+}
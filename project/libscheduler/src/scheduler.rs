@@ -6,21 +6,36 @@ use tokio::time::interval;
 use tokio::time::{Duration, Instant};
 
 use crate::cache::Cache;
+use crate::config::{BackoffConfig, DEFAULT_SCHEDULER_NAME, Profile};
 use crate::cycle_state::CycleState;
+use crate::metrics;
 use crate::models::{Assignment, BackOffPod, PodNameWithPriority};
 use crate::models::{NodeInfo, PodInfo};
 use crate::plugins::node_resources_fit::ScoringStrategy;
 use crate::plugins::{
-    ClusterEventWithHint, Code, EnabledPlugins, EventInner, EventResource, FilterPlugin, Plugins,
-    PreFilterPlugin, PreScorePlugin, QueueingHint, Registry, ScorePlugin, Status,
+    ClusterEventWithHint, Code, EnabledPlugins, EnqueueExtension, EventInner, EventResource,
+    FilterPlugin, PermitPlugin, Plugins, PreFilterPlugin, PreScorePlugin, QueueingHint, Registry,
+    ReservePlugin, ScorePlugin, Status,
 };
+use crate::simulation::{NodeRejection, NodeScore, PluginScore, PodPlacement};
+use crate::waiting_pods::{WAITING_PODS_KEY, WaitingPods};
 
 pub struct Scheduler {
     cache: Arc<RwLock<Cache>>,
     queue: Arc<SchedulingQueue>,
-    // Differ to k8s, we don't have profile cofig now
+    /// Frameworks keyed by the scheduler name of their profile.
+    profiles: Arc<HashMap<String, Framework>>,
+    /// Pods assumed on a node but held back by permit plugins.
+    waiting: Arc<WaitingPods>,
+}
+
+/// The runtime form of a [`Profile`]: the plugins it enabled and the
+/// parameters shared by every scheduling cycle of the profile.
+#[derive(Clone)]
+struct Framework {
     strategy: ScoringStrategy,
     enabled_plugins: EnabledPlugins,
+    backoff: BackoffConfig,
 }
 
 type ActiveQueue = Arc<Mutex<BinaryHeap<PodNameWithPriority>>>;
@@ -159,10 +174,9 @@ impl SchedulingQueue {
         self.add_count().await;
     }
 
-    async fn push_unschedulable(&self, mut pod: PodInfo) {
+    async fn push_unschedulable(&self, mut pod: PodInfo, backoff: &BackoffConfig) {
         pod.queued_info.attempts += 1;
-        let expire =
-            Instant::now() + Duration::from_secs(2_u64.pow(pod.queued_info.attempts as u32));
+        let expire = Instant::now() + backoff.duration(pod.queued_info.attempts);
         let backoff_pod = BackOffPod {
             pod: (pod.spec.priority, pod.name.clone()),
             expire,
@@ -171,15 +185,14 @@ impl SchedulingQueue {
         guard.push((backoff_pod, Instant::now()));
     }
 
-    async fn push_backoff(&self, mut pod: PodInfo) {
+    async fn push_backoff(&self, mut pod: PodInfo, backoff: &BackoffConfig) {
         pod.queued_info.attempts += 1;
-        let expire =
-            Instant::now() + Duration::from_secs(2_u64.pow(pod.queued_info.attempts as u32));
+        let expire = Instant::now() + backoff.duration(pod.queued_info.attempts);
         let backoff_pod = BackOffPod {
             pod: (pod.spec.priority, pod.name.clone()),
             expire,
         };
-        if pod.queued_info.attempts > 8 {
+        if pod.queued_info.attempts > backoff.max_backoff_attempts {
             let mut guard = self.unschedulable_queue.lock().await;
             guard.push((backoff_pod, Instant::now()));
         } else {
@@ -252,9 +265,10 @@ impl Default for Scheduler {
     }
 }
 
-impl Scheduler {
-    pub fn new(strategy: ScoringStrategy, plugins: Plugins) -> Self {
-        let registry = Registry::default();
+impl Framework {
+    /// The framework of `profile`, and the enqueue extensions it enabled.
+    fn new(profile: Profile, registry: &Registry) -> (Self, Vec<Arc<dyn EnqueueExtension>>) {
+        let plugins = profile.plugins;
         let mut enabled = EnabledPlugins::default();
 
         macro_rules! enable_plugins {
@@ -284,23 +298,67 @@ impl Scheduler {
         enable_plugins!(bind);
         enable_plugins!(post_bind);
 
+        let enqueue_extensions = plugins
+            .enqueue_extensions
+            .iter()
+            .filter_map(|p| {
+                registry
+                    .enqueue_extensions
+                    .iter()
+                    .find(|item| item.name() == p.name)
+                    .cloned()
+            })
+            .collect();
+
+        (
+            Self {
+                strategy: profile.strategy,
+                enabled_plugins: enabled,
+                backoff: profile.backoff,
+            },
+            enqueue_extensions,
+        )
+    }
+}
+
+impl Scheduler {
+    /// Create a scheduler with a single profile named [`DEFAULT_SCHEDULER_NAME`].
+    pub fn new(strategy: ScoringStrategy, plugins: Plugins) -> Self {
+        Self::with_profiles(vec![Profile::new(
+            DEFAULT_SCHEDULER_NAME,
+            strategy,
+            plugins,
+        )])
+    }
+
+    /// Create a scheduler serving several profiles.
+    ///
+    /// Pods pick a profile through `schedulerName`. Pods asking for a profile
+    /// that isn't configured here are left alone, since they're expected to be
+    /// handled by another scheduler.
+    pub fn with_profiles(profiles: Vec<Profile>) -> Self {
+        let registry = Registry::default();
+        let mut frameworks = HashMap::new();
         let mut queueing_hints = Vec::new();
-        for p in plugins.enqueue_extensions.iter() {
-            let plugin = registry
-                .enqueue_extensions
-                .iter()
-                .find(|item| item.name() == p.name)
-                .cloned();
-            if let Some(plugin) = plugin {
-                queueing_hints.append(&mut plugin.events_to_register());
+        let mut registered = HashSet::new();
+        for p in profiles {
+            let name = p.scheduler_name.clone();
+            let (framework, enqueue_extensions) = Framework::new(p, &registry);
+            // The queue is shared by the profiles, so the events of a plugin
+            // enabled in several of them are registered once.
+            for plugin in enqueue_extensions {
+                if registered.insert(plugin.name().to_string()) {
+                    queueing_hints.append(&mut plugin.events_to_register());
+                }
             }
+            frameworks.insert(name, framework);
         }
 
         Self {
             cache: Arc::new(RwLock::new(Cache::new())),
             queue: Arc::new(SchedulingQueue::new(queueing_hints)),
-            strategy,
-            enabled_plugins: enabled,
+            profiles: Arc::new(frameworks),
+            waiting: Arc::new(WaitingPods::default()),
         }
     }
 
@@ -310,7 +368,20 @@ impl Scheduler {
         pod: &PodInfo,
         nodes: &[NodeInfo],
     ) -> (Vec<NodeInfo>, Status) {
-        let mut filtered_set = HashSet::new();
+        let (passed_nodes, _, sta) =
+            Self::run_prefilter_plugin_with_reasons(plugins, state, pod, nodes);
+        (passed_nodes, sta)
+    }
+
+    /// Like [`Self::run_prefilter_plugin`], also returning the nodes filtered
+    /// out together with the plugin which filtered them.
+    fn run_prefilter_plugin_with_reasons(
+        plugins: &Vec<(Arc<dyn PreFilterPlugin>, i64)>,
+        state: &mut CycleState,
+        pod: &PodInfo,
+        nodes: &[NodeInfo],
+    ) -> (Vec<NodeInfo>, Vec<NodeRejection>, Status) {
+        let mut filtered_set = HashMap::new();
         for (pl, _) in plugins {
             let (res, sta) = pl.pre_filter(state, pod, nodes.to_owned());
             if let Code::Error = sta.code {
@@ -320,21 +391,30 @@ impl Scheduler {
                 continue;
             }
             res.node_names.into_iter().for_each(|n| {
-                filtered_set.insert(n);
+                filtered_set
+                    .entry(n)
+                    .or_insert_with(|| pl.name().to_string());
             });
             if matches!(
                 sta.code,
                 Code::Unschedulable | Code::UnschedulableAndUnresolvable | Code::Pending
             ) {
-                return (vec![], sta);
+                return (vec![], vec![], sta);
             }
         }
-        let passed_nodes = nodes
+        let (passed_nodes, filtered_nodes): (Vec<_>, Vec<_>) = nodes
             .iter()
-            .filter(|n| !filtered_set.contains(&n.name))
             .cloned()
+            .partition(|n| !filtered_set.contains_key(&n.name));
+        let rejected = filtered_nodes
+            .into_iter()
+            .map(|n| NodeRejection {
+                plugin: filtered_set.remove(&n.name).unwrap_or_default(),
+                node_name: n.name,
+                reasons: vec!["filtered out at PreFilter".to_string()],
+            })
             .collect();
-        (passed_nodes, Status::default())
+        (passed_nodes, rejected, Status::default())
     }
 
     fn run_filter_plugin(
@@ -343,19 +423,37 @@ impl Scheduler {
         pod: &PodInfo,
         nodes: &[NodeInfo],
     ) -> Vec<NodeInfo> {
-        let mut nodes = nodes.to_owned();
-        for (pl, _) in plugins {
-            if state.skip_filter_plugins.contains(pl.name()) {
-                continue;
+        Self::run_filter_plugin_with_reasons(plugins, state, pod, nodes).0
+    }
+
+    /// Like [`Self::run_filter_plugin`], also returning the first plugin which
+    /// rejected each node that didn't pass.
+    fn run_filter_plugin_with_reasons(
+        plugins: &Vec<(Arc<dyn FilterPlugin>, i64)>,
+        state: &mut CycleState,
+        pod: &PodInfo,
+        nodes: &[NodeInfo],
+    ) -> (Vec<NodeInfo>, Vec<NodeRejection>) {
+        let mut passed = Vec::new();
+        let mut rejected = Vec::new();
+        'nodes: for n in nodes {
+            for (pl, _) in plugins {
+                if state.skip_filter_plugins.contains(pl.name()) {
+                    continue;
+                }
+                let sta = pl.filter(state, pod, n.clone());
+                if !matches!(sta.code, Code::Success | Code::Skip) {
+                    rejected.push(NodeRejection {
+                        node_name: n.name.clone(),
+                        plugin: pl.name().to_string(),
+                        reasons: status_reasons(&sta),
+                    });
+                    continue 'nodes;
+                }
             }
-            nodes.retain(|n| {
-                matches!(
-                    pl.filter(state, pod, n.clone()).code,
-                    Code::Success | Code::Skip
-                )
-            });
+            passed.push(n.clone());
         }
-        nodes
+        (passed, rejected)
     }
 
     fn run_pre_score_plugin(
@@ -382,13 +480,8 @@ impl Scheduler {
         nodes: &[NodeInfo],
     ) -> Vec<(i64, NodeInfo)> {
         let mut score = vec![0_i64; nodes.len()];
-        for (pl, w) in plugins {
-            let mut cur_score: Vec<i64> = nodes
-                .iter()
-                .map(|n| pl.score(state, pod, n.clone()).0)
-                .collect();
-            let normalizer = pl.score_extension();
-            normalizer.normalize_score(state, pod, &mut cur_score);
+        let per_plugin = Self::run_score_plugin_per_plugin(plugins, state, pod, nodes);
+        for ((_, w), cur_score) in plugins.iter().zip(per_plugin) {
             for i in 0..score.len() {
                 score[i] += cur_score[i] * w;
             }
@@ -396,12 +489,171 @@ impl Scheduler {
         score.into_iter().zip(nodes.to_owned()).collect()
     }
 
+    /// Normalized scores given by each plugin, in the order of `plugins`
+    /// and then of `nodes`, before the weights are applied.
+    fn run_score_plugin_per_plugin(
+        plugins: &Vec<(Arc<dyn ScorePlugin>, i64)>,
+        state: &mut CycleState,
+        pod: &PodInfo,
+        nodes: &[NodeInfo],
+    ) -> Vec<Vec<i64>> {
+        plugins
+            .iter()
+            .map(|(pl, _)| {
+                let mut cur_score: Vec<i64> = nodes
+                    .iter()
+                    .map(|n| pl.score(state, pod, n.clone()).0)
+                    .collect();
+                let normalizer = pl.score_extension();
+                normalizer.normalize_score(state, pod, &mut cur_score);
+                cur_score
+            })
+            .collect()
+    }
+
+    fn run_reserve_plugin(
+        plugins: &Vec<(Arc<dyn ReservePlugin>, i64)>,
+        state: &mut CycleState,
+        pod: &PodInfo,
+        node_name: &str,
+    ) -> Status {
+        for (pl, _) in plugins {
+            let sta = pl.reserve(state, pod, node_name);
+            if !matches!(sta.code, Code::Success) {
+                return sta;
+            }
+        }
+        Status::default()
+    }
+
+    fn run_unreserve_plugin(
+        plugins: &Vec<(Arc<dyn ReservePlugin>, i64)>,
+        state: &mut CycleState,
+        pod: &PodInfo,
+        node_name: &str,
+    ) {
+        for (pl, _) in plugins.iter().rev() {
+            pl.unreserve(state, pod, node_name);
+        }
+    }
+
+    /// Run permit plugins, returning `Wait` together with the timeout asked by
+    /// each waiting plugin if no plugin rejected the pod.
+    fn run_permit_plugin(
+        plugins: &Vec<(Arc<dyn PermitPlugin>, i64)>,
+        state: &mut CycleState,
+        pod: &PodInfo,
+        node_name: &str,
+    ) -> (Status, HashMap<String, Duration>) {
+        let mut waits = HashMap::new();
+        for (pl, _) in plugins {
+            let (sta, timeout) = pl.permit(state, pod, node_name);
+            match sta.code {
+                Code::Success | Code::Skip => {}
+                Code::Wait => {
+                    waits.insert(pl.name().to_string(), timeout);
+                }
+                _ => return (sta, HashMap::new()),
+            }
+        }
+        if waits.is_empty() {
+            (Status::default(), waits)
+        } else {
+            (Status::new(Code::Wait, vec![]), waits)
+        }
+    }
+
+    /// Undo the assumption of a pod and send it to the backoff queue.
+    async fn rollback(
+        cache: &RwLock<Cache>,
+        queue: &SchedulingQueue,
+        pod_name: &str,
+        backoff: &BackoffConfig,
+    ) {
+        let mut cache_write = cache.write().await;
+        let Some(pod_info) = cache_write.unassume(pod_name) else {
+            return;
+        };
+        if cache_write.add_fail(pod_name) {
+            drop(cache_write);
+            queue.push_backoff(pod_info, backoff).await;
+        }
+    }
+
+    /// Roll back waiting pods which were rejected or timed out.
+    ///
+    /// Unreserving one pod may reject others, e.g. the rest of its pod group,
+    /// so this runs until no rejected pod is left.
+    async fn reject_waiting_pods(
+        profiles: &HashMap<String, Framework>,
+        cache: &RwLock<Cache>,
+        queue: &SchedulingQueue,
+        waiting: &Arc<WaitingPods>,
+    ) {
+        loop {
+            let rejected = waiting.take_rejected(Instant::now());
+            if rejected.is_empty() {
+                return;
+            }
+            for w in rejected {
+                let Some(framework) = profiles.get(w.pod.scheduler_name()) else {
+                    continue;
+                };
+                let mut state = CycleState::default();
+                state.write(WAITING_PODS_KEY, Box::new(waiting.clone()));
+                Self::run_unreserve_plugin(
+                    &framework.enabled_plugins.reserve,
+                    &mut state,
+                    &w.pod,
+                    &w.node_name,
+                );
+                Self::rollback(cache, queue, &w.pod.name, &framework.backoff).await;
+            }
+        }
+    }
+
+    fn new_cycle_state(
+        framework: &Framework,
+        waiting: &Arc<WaitingPods>,
+        cache: &Cache,
+    ) -> CycleState {
+        const SCORING_STRATEGY_CONFIG_KEY: &str = "ScoringStrategyConfig";
+        let mut cycle_state = CycleState::default();
+        cycle_state.write(
+            SCORING_STRATEGY_CONFIG_KEY,
+            Box::new(framework.strategy.clone()),
+        );
+        cycle_state.write(WAITING_PODS_KEY, Box::new(waiting.clone()));
+
+        // Get all scheduled pods for pod affinity plugins
+        let all_scheduled_pods: Vec<PodInfo> = cache
+            .get_pods()
+            .into_values()
+            .filter(|p| p.scheduled.is_some() || p.spec.node_name.is_some())
+            .collect();
+        cycle_state.write("AllScheduledPods", Box::new(all_scheduled_pods));
+        cycle_state
+    }
+
+    fn send_assignment(
+        res_sx: &UnboundedSender<Result<Assignment, anyhow::Error>>,
+        pod_name: String,
+        node_name: String,
+    ) {
+        res_sx
+            .send(Ok(Assignment {
+                pod_name,
+                node_name,
+            }))
+            .expect("scheduling result rx closed before scheduler closed");
+    }
+
     async fn schedule_one(
-        enabled_plugins: EnabledPlugins,
+        profiles: Arc<HashMap<String, Framework>>,
         cache: Arc<RwLock<Cache>>,
         queue: Arc<SchedulingQueue>,
+        waiting: Arc<WaitingPods>,
         res_sx: UnboundedSender<Result<Assignment, anyhow::Error>>,
-        strategy: ScoringStrategy,
     ) {
         let (pod_priority, pod_name) = queue.next_pod().await;
         let cache_read = cache.read().await;
@@ -413,30 +665,34 @@ impl Scheduler {
                 // The pod priority is already updated.
                 return;
             }
+            let Some(framework) = profiles.get(pod_info.scheduler_name()) else {
+                log::warn!(
+                    "pod {pod_name} requests unknown scheduler profile {}",
+                    pod_info.scheduler_name()
+                );
+                return;
+            };
+            let enabled_plugins = &framework.enabled_plugins;
+            let profile = pod_info.scheduler_name().to_string();
+            let start = Instant::now();
+            let record = |result: &str| {
+                metrics::record_attempt(&profile, result, start.elapsed().as_secs_f64());
+            };
 
             macro_rules! break_cycle {
                 ($v: ident) => {
+                    record("unschedulable");
                     let mut cache_write = cache.write().await;
                     if cache_write.add_fail(&pod_name) {
-                        queue.$v(pod_info).await;
+                        queue.$v(pod_info, &framework.backoff).await;
                     }
                     return;
                 };
             }
 
-            const SCORING_STRATEGY_CONFIG_KEY: &str = "ScoringStrategyConfig";
-            let mut cycle_state = CycleState::default();
-            cycle_state.write(SCORING_STRATEGY_CONFIG_KEY, Box::new(strategy));
-
-            // Get all scheduled pods for pod affinity plugins
             let cache_read = cache.read().await;
-            let all_scheduled_pods: Vec<PodInfo> = cache_read
-                .get_pods()
-                .into_values()
-                .filter(|p| p.scheduled.is_some() || p.spec.node_name.is_some())
-                .collect();
+            let mut cycle_state = Self::new_cycle_state(framework, &waiting, &cache_read);
             drop(cache_read);
-            cycle_state.write("AllScheduledPods", Box::new(all_scheduled_pods));
 
             let (passed_prefilter, sta) = Self::run_prefilter_plugin(
                 &enabled_plugins.pre_filter,
@@ -481,16 +737,219 @@ impl Scheduler {
                 &filtered,
             );
             scores.sort_by_key(|b| std::cmp::Reverse(b.0));
+            let node_name = scores[0].1.name.clone();
             let mut cache_write = cache.write().await;
-            if cache_write.assume(&pod_name, &scores[0].1.name) {
-                res_sx
-                    .send(Ok(Assignment {
-                        pod_name,
-                        node_name: scores[0].1.name.clone(),
-                    }))
-                    .expect("scheduling result rx closed before scheduler closed");
+            if !cache_write.assume(&pod_name, &node_name) {
+                record("error");
+                return;
             }
+            drop(cache_write);
+
+            let sta = Self::run_reserve_plugin(
+                &enabled_plugins.reserve,
+                &mut cycle_state,
+                &pod_info,
+                &node_name,
+            );
+            let sta = if matches!(sta.code, Code::Success) {
+                let (sta, waits) = Self::run_permit_plugin(
+                    &enabled_plugins.permit,
+                    &mut cycle_state,
+                    &pod_info,
+                    &node_name,
+                );
+                if let Code::Wait = sta.code {
+                    log::debug!("pod {pod_name} waits at permit on node {node_name}");
+                    waiting.add(pod_info.clone(), &node_name, waits);
+                }
+                sta
+            } else {
+                sta
+            };
+
+            match sta.code {
+                Code::Success => {
+                    record("scheduled");
+                    Self::send_assignment(&res_sx, pod_name, node_name)
+                }
+                Code::Wait => record("waiting"),
+                _ => {
+                    record("error");
+                    Self::run_unreserve_plugin(
+                        &enabled_plugins.reserve,
+                        &mut cycle_state,
+                        &pod_info,
+                        &node_name,
+                    );
+                    Self::rollback(&cache, &queue, &pod_name, &framework.backoff).await;
+                }
+            }
+
+            for w in waiting.take_allowed() {
+                Self::send_assignment(&res_sx, w.pod.name, w.node_name);
+            }
+            Self::reject_waiting_pods(&profiles, &cache, &queue, &waiting).await;
+        }
+    }
+
+    /// Run the filter and score cycle for pending pods against a copy of the
+    /// cache, without reserving, permitting or binding anything.
+    ///
+    /// Pods are placed in order of priority and each one sees the placements
+    /// made before it. With `reschedule_existing`, pods already scheduled are
+    /// taken off their nodes first and placed again as well.
+    pub async fn simulate(&self, reschedule_existing: bool) -> Vec<PodPlacement> {
+        let mut cache = self.cache.read().await.clone();
+        let mut previous = HashMap::new();
+        if reschedule_existing {
+            for p in cache.get_pods().into_values() {
+                if let Some(node_name) = p.scheduled {
+                    // The binding is forgotten too, so the pod isn't pinned
+                    // to its node or counted there by the affinity plugins.
+                    if let Some(mut pod) = cache.unassume(&p.name) {
+                        pod.spec.node_name = None;
+                        cache.update_pod(pod);
+                    }
+                    previous.insert(p.name, node_name);
+                }
+            }
+        }
+
+        let mut pending: Vec<_> = cache
+            .get_pods()
+            .into_values()
+            .filter(|p| p.scheduled.is_none())
+            .collect();
+        pending.sort_by(|a, b| {
+            b.spec
+                .priority
+                .cmp(&a.spec.priority)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        let waiting = Arc::new(WaitingPods::default());
+
+        let mut res = Vec::with_capacity(pending.len());
+        for pod in pending {
+            let mut placement = PodPlacement {
+                pod_name: pod.name.clone(),
+                previous_node: previous.remove(&pod.name),
+                ..Default::default()
+            };
+            Self::simulate_one(&self.profiles, &mut cache, &waiting, &pod, &mut placement);
+            res.push(placement);
+        }
+        res
+    }
+
+    fn simulate_one(
+        profiles: &HashMap<String, Framework>,
+        cache: &mut Cache,
+        waiting: &Arc<WaitingPods>,
+        pod: &PodInfo,
+        placement: &mut PodPlacement,
+    ) {
+        let Some(framework) = profiles.get(pod.scheduler_name()) else {
+            placement.reasons.push(format!(
+                "scheduler profile {} is not served here",
+                pod.scheduler_name()
+            ));
+            return;
+        };
+        let enabled_plugins = &framework.enabled_plugins;
+        for (pl, _) in &enabled_plugins.pre_enqueue {
+            let sta = pl.pre_enqueue(pod);
+            if !matches!(
+                sta.code,
+                Code::Error | Code::Skip | Code::Success | Code::Pending
+            ) {
+                placement.reasons.push(format!(
+                    "blocked by {} at PreEnqueue: {}",
+                    pl.name(),
+                    status_reasons(&sta).join(", ")
+                ));
+                return;
+            }
+        }
+
+        let mut nodes = cache.get_nodes();
+        nodes.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut cycle_state = Self::new_cycle_state(framework, waiting, cache);
+        let (passed_prefilter, mut rejected, sta) = Self::run_prefilter_plugin_with_reasons(
+            &enabled_plugins.pre_filter,
+            &mut cycle_state,
+            pod,
+            &nodes,
+        );
+        if !matches!(sta.code, Code::Success) {
+            placement.reasons.push(format!(
+                "rejected at PreFilter: {}",
+                status_reasons(&sta).join(", ")
+            ));
+            return;
+        }
+
+        let (filtered, mut filter_rejected) = Self::run_filter_plugin_with_reasons(
+            &enabled_plugins.filter,
+            &mut cycle_state,
+            pod,
+            &passed_prefilter,
+        );
+        rejected.append(&mut filter_rejected);
+        rejected.sort_by(|a, b| a.node_name.cmp(&b.node_name));
+        placement.rejected = rejected;
+        if filtered.is_empty() {
+            placement
+                .reasons
+                .push(format!("0/{} nodes are available", nodes.len()));
+            return;
+        }
+
+        let sta = Self::run_pre_score_plugin(
+            &enabled_plugins.pre_score,
+            &mut cycle_state,
+            pod,
+            &filtered,
+        );
+        if !matches!(sta.code, Code::Success) {
+            placement.reasons.push(format!(
+                "rejected at PreScore: {}",
+                status_reasons(&sta).join(", ")
+            ));
+            return;
         }
+
+        let per_plugin = Self::run_score_plugin_per_plugin(
+            &enabled_plugins.score,
+            &mut cycle_state,
+            pod,
+            &filtered,
+        );
+        let mut scores: Vec<NodeScore> = filtered
+            .iter()
+            .enumerate()
+            .map(|(i, n)| {
+                let plugins: Vec<PluginScore> = enabled_plugins
+                    .score
+                    .iter()
+                    .zip(per_plugin.iter())
+                    .map(|((pl, w), s)| PluginScore {
+                        plugin: pl.name().to_string(),
+                        score: s[i],
+                        weight: *w,
+                    })
+                    .collect();
+                NodeScore {
+                    node_name: n.name.clone(),
+                    total: plugins.iter().map(|p| p.score * p.weight).sum(),
+                    plugins,
+                }
+            })
+            .collect();
+        scores.sort_by_key(|s| std::cmp::Reverse(s.total));
+        let node_name = scores[0].node_name.clone();
+        cache.assume(&pod.name, &node_name);
+        placement.node_name = Some(node_name);
+        placement.scores = scores;
     }
 
     /// Un assume a pod, if the pod is not scheduled, do nothing
@@ -506,26 +965,46 @@ impl Scheduler {
         self.queue.run();
         let queue = self.queue.clone();
         let cache = self.cache.clone();
-        let enabled_plugins = self.enabled_plugins.clone();
+        let profiles = self.profiles.clone();
+        let waiting = self.waiting.clone();
         let (sx, rx) = unbounded_channel();
-        let strategy = self.strategy.clone();
         tokio::spawn(async move {
             loop {
                 Self::schedule_one(
-                    enabled_plugins.clone(),
+                    profiles.clone(),
                     cache.clone(),
                     queue.clone(),
+                    waiting.clone(),
                     sx.clone(),
-                    strategy.clone(),
                 )
                 .await;
             }
         });
+
+        let queue = self.queue.clone();
+        let cache = self.cache.clone();
+        let profiles = self.profiles.clone();
+        let waiting = self.waiting.clone();
+        tokio::spawn(async move {
+            let mut timer = interval(Duration::from_secs(1));
+            loop {
+                timer.tick().await;
+                Self::reject_waiting_pods(&profiles, &cache, &queue, &waiting).await;
+            }
+        });
         rx
     }
 
     pub async fn enqueue(&self, pod: PodInfo) {
-        for (p, _) in &self.enabled_plugins.pre_enqueue {
+        let Some(framework) = self.profiles.get(pod.scheduler_name()) else {
+            log::debug!(
+                "skip pod {} for scheduler profile {} which is not served here",
+                pod.name,
+                pod.scheduler_name()
+            );
+            return;
+        };
+        for (p, _) in &framework.enabled_plugins.pre_enqueue {
             let sta = p.pre_enqueue(&pod);
             if !matches!(
                 sta.code,
@@ -573,6 +1052,18 @@ impl Scheduler {
     }
 
     pub async fn remove_cache_pod(&mut self, pod_name: &str) {
+        if let Some(w) = self.waiting.remove(pod_name)
+            && let Some(framework) = self.profiles.get(w.pod.scheduler_name())
+        {
+            let mut state = CycleState::default();
+            state.write(WAITING_PODS_KEY, Box::new(self.waiting.clone()));
+            Self::run_unreserve_plugin(
+                &framework.enabled_plugins.reserve,
+                &mut state,
+                &w.pod,
+                &w.node_name,
+            );
+        }
         let mut write_lock = self.cache.write().await;
         let ori = (*write_lock).remove_pod(pod_name);
         drop(write_lock);
@@ -619,12 +1110,23 @@ impl Scheduler {
     }
 }
 
+/// Reasons of a status for reports, falling back to its code.
+fn status_reasons(sta: &Status) -> Vec<String> {
+    if !sta.reasons.is_empty() {
+        sta.reasons.clone()
+    } else if !sta.err.is_empty() {
+        vec![sta.err.clone()]
+    } else {
+        vec![format!("{:?}", sta.code)]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use tokio::time::timeout;
 
     use super::*;
-    use crate::models::{NodeSpec, PodSpec, QueuedInfo, ResourcesRequirements};
+    use crate::models::{NodeSpec, PodGroup, PodSpec, QueuedInfo, ResourcesRequirements};
 
     #[test]
     fn test_plugins_enabled() {
@@ -635,7 +1137,11 @@ mod tests {
             ($plugin: ident) => {{
                 let mut enabled_names = Vec::new();
                 let mut expected_names = Vec::new();
-                for (pl, _) in scheduler.enabled_plugins.$plugin {
+                for (pl, _) in scheduler.profiles[DEFAULT_SCHEDULER_NAME]
+                    .enabled_plugins
+                    .$plugin
+                    .iter()
+                {
                     enabled_names.push(pl.name().to_string());
                 }
                 for pl in plugins.$plugin.iter() {
@@ -722,7 +1228,7 @@ mod tests {
             },
             scheduled: None,
         };
-        queue.push_backoff(pod).await;
+        queue.push_backoff(pod, &BackoffConfig::default()).await;
         let unschedulable = queue.unschedulable_queue.lock().await;
         assert_eq!(unschedulable.len(), 1);
     }
@@ -742,7 +1248,7 @@ mod tests {
             scheduled: None,
         };
         queue.run();
-        queue.push_backoff(pod).await;
+        queue.push_backoff(pod, &BackoffConfig::default()).await;
         let res = timeout(Duration::from_secs(3), queue.next_pod()).await;
         assert!(res.is_ok());
     }
@@ -788,6 +1294,48 @@ mod tests {
         assert!(cache.get_nodes().is_empty());
     }
 
+    #[tokio::test]
+    async fn test_shared_plugins_requeue_once_per_event() {
+        let single = Scheduler::new(ScoringStrategy::LeastAllocated, Plugins::default());
+        let mut scheduler = Scheduler::with_profiles(vec![
+            Profile::new(
+                DEFAULT_SCHEDULER_NAME,
+                ScoringStrategy::LeastAllocated,
+                Plugins::default(),
+            ),
+            Profile::new(
+                "other-scheduler",
+                ScoringStrategy::MostAllocated,
+                Plugins::default(),
+            ),
+        ]);
+        assert_eq!(
+            scheduler.queue.node_events_hint.len(),
+            single.queue.node_events_hint.len()
+        );
+        assert_eq!(
+            scheduler.queue.pod_events_hint.len(),
+            single.queue.pod_events_hint.len()
+        );
+
+        let pod = make_pod("pod", 1);
+        scheduler.cache.write().await.update_pod(pod.clone());
+        scheduler
+            .queue
+            .push_unschedulable(pod, &BackoffConfig::default())
+            .await;
+        let node = NodeInfo {
+            name: "node1".to_string(),
+            allocatable: ResourcesRequirements { cpu: 2, memory: 10 },
+            requested: ResourcesRequirements { cpu: 0, memory: 0 },
+            spec: NodeSpec::default(),
+            ..Default::default()
+        };
+        scheduler.update_cache_node(node).await;
+        assert!(scheduler.queue.unschedulable_queue.lock().await.is_empty());
+        assert_eq!(scheduler.queue.active_queue.lock().await.len(), 1);
+    }
+
     #[tokio::test]
     async fn test_schedule_one_assigns_pod() {
         let scheduler: Scheduler =
@@ -830,11 +1378,11 @@ mod tests {
         scheduler.queue.push("pod".to_string(), 1).await;
         let (sx, mut rx) = unbounded_channel();
         Scheduler::schedule_one(
-            scheduler.enabled_plugins,
+            scheduler.profiles.clone(),
             scheduler.cache.clone(),
             scheduler.queue.clone(),
+            scheduler.waiting.clone(),
             sx,
-            scheduler.strategy,
         )
         .await;
         let res = rx.recv().await.unwrap();
@@ -843,4 +1391,104 @@ mod tests {
         assert_eq!(assignment.pod_name, "pod");
         assert_eq!(assignment.node_name, "node");
     }
+
+    #[tokio::test]
+    async fn test_schedule_one_rolls_back_timed_out_pod_group() {
+        let scheduler: Scheduler =
+            Scheduler::new(ScoringStrategy::LeastAllocated, Plugins::default());
+        let mut cache = scheduler.cache.write().await;
+        cache.update_node(NodeInfo {
+            name: "node".to_string(),
+            allocatable: ResourcesRequirements { cpu: 2, memory: 10 },
+            ..Default::default()
+        });
+        let mut pod = make_pod("pod", 1);
+        pod.spec.pod_group = Some(PodGroup {
+            name: "gang".to_string(),
+            min_member: 2,
+            schedule_timeout: Duration::ZERO,
+        });
+        cache.update_pod(pod);
+        drop(cache);
+
+        scheduler.queue.push("pod".to_string(), 1).await;
+        let (sx, mut rx) = unbounded_channel();
+        Scheduler::schedule_one(
+            scheduler.profiles.clone(),
+            scheduler.cache.clone(),
+            scheduler.queue.clone(),
+            scheduler.waiting.clone(),
+            sx,
+        )
+        .await;
+        assert!(rx.try_recv().is_err());
+        assert!(scheduler.waiting.pods().is_empty());
+
+        let cache = scheduler.cache.read().await;
+        assert!(cache.get_pod("pod").unwrap().scheduled.is_none());
+        assert_eq!(cache.get_nodes()[0].requested.cpu, 0);
+        assert_eq!(cache.get_nodes()[0].pod_count, 0);
+        drop(cache);
+        assert_eq!(scheduler.queue.backoff_queue.lock().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_does_not_touch_cache() {
+        let mut scheduler: Scheduler =
+            Scheduler::new(ScoringStrategy::LeastAllocated, Plugins::default());
+        scheduler
+            .set_cache_node(vec![
+                NodeInfo {
+                    name: "big".to_string(),
+                    allocatable: ResourcesRequirements { cpu: 4, memory: 10 },
+                    ..Default::default()
+                },
+                NodeInfo {
+                    name: "small".to_string(),
+                    allocatable: ResourcesRequirements { cpu: 0, memory: 10 },
+                    ..Default::default()
+                },
+            ])
+            .await;
+        scheduler.update_cache_pod(make_pod("pod1", 2)).await;
+        scheduler.update_cache_pod(make_pod("pod2", 1)).await;
+
+        let res = scheduler.simulate(false).await;
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].pod_name, "pod1");
+        for p in res.iter() {
+            assert_eq!(p.node_name.as_deref(), Some("big"));
+            assert_eq!(p.scores.len(), 1);
+            assert!(!p.scores[0].plugins.is_empty());
+            assert_eq!(p.rejected.len(), 1);
+            assert_eq!(p.rejected[0].node_name, "small");
+            assert_eq!(p.rejected[0].plugin, "NodeResourcesFit");
+        }
+
+        let cache = scheduler.cache.read().await;
+        assert!(cache.get_pod("pod1").unwrap().scheduled.is_none());
+        assert!(cache.get_nodes().iter().all(|n| n.pod_count == 0));
+    }
+
+    #[tokio::test]
+    async fn test_simulate_reschedule_existing() {
+        let mut scheduler: Scheduler =
+            Scheduler::new(ScoringStrategy::LeastAllocated, Plugins::default());
+        scheduler
+            .set_cache_node(vec![NodeInfo {
+                name: "node".to_string(),
+                allocatable: ResourcesRequirements { cpu: 4, memory: 10 },
+                ..Default::default()
+            }])
+            .await;
+        let mut pod = make_pod("pod", 1);
+        pod.scheduled = Some("node".to_string());
+        scheduler.update_cache_pod(pod).await;
+
+        assert!(scheduler.simulate(false).await.is_empty());
+        let res = scheduler.simulate(true).await;
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].previous_node.as_deref(), Some("node"));
+        assert_eq!(res[0].node_name.as_deref(), Some("node"));
+    }
 }
@@ -0,0 +1,200 @@
+//! What-if scheduling.
+//!
+//! Loads a snapshot of nodes and pods into a [`Scheduler`] and runs the
+//! filter and score cycle for every pending pod without reserving or binding
+//! anything, so the effect of a change to node taints or labels can be
+//! checked before it's made on the real cluster.
+
+use anyhow::{Context, bail};
+use common::{Node, PodTask};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Profile;
+use crate::models::{NodeInfo, PodInfo};
+use crate::scheduler::Scheduler;
+use crate::with_xline::utils::{convert_k8s_node_to_node_info, convert_pod_task_to_pod_info};
+
+/// Nodes and pods to run a simulation against.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    #[serde(default)]
+    pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub pods: Vec<PodTask>,
+}
+
+impl Snapshot {
+    /// Parse a snapshot from YAML.
+    ///
+    /// Accepts either a single document with `nodes` and `pods` lists, or a
+    /// stream of `Node` and `Pod` manifests separated by `---`.
+    pub fn from_yaml(content: &str) -> Result<Self, anyhow::Error> {
+        let mut snapshot = Snapshot::default();
+        for doc in serde_yaml::Deserializer::from_str(content) {
+            let value = serde_yaml::Value::deserialize(doc)?;
+            if value.is_null() {
+                continue;
+            }
+            match value.get("kind").and_then(|k| k.as_str()) {
+                Some("Node") => snapshot
+                    .nodes
+                    .push(serde_yaml::from_value(value).context("invalid node in snapshot")?),
+                Some("Pod") => snapshot
+                    .pods
+                    .push(serde_yaml::from_value(value).context("invalid pod in snapshot")?),
+                Some(kind) => bail!("unsupported kind {kind} in snapshot"),
+                None => {
+                    let mut s: Snapshot = serde_yaml::from_value(value)?;
+                    snapshot.nodes.append(&mut s.nodes);
+                    snapshot.pods.append(&mut s.pods);
+                }
+            }
+        }
+        Ok(snapshot)
+    }
+
+    pub async fn simulate(
+        self,
+        profiles: Vec<Profile>,
+        reschedule_existing: bool,
+    ) -> Vec<PodPlacement> {
+        simulate(
+            self.nodes
+                .into_iter()
+                .map(convert_k8s_node_to_node_info)
+                .collect(),
+            self.pods
+                .into_iter()
+                .map(convert_pod_task_to_pod_info)
+                .collect(),
+            profiles,
+            reschedule_existing,
+        )
+        .await
+    }
+}
+
+/// Where a pod would land, and why.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PodPlacement {
+    pub pod_name: String,
+    /// Node the pod would be placed on, `None` if it's unschedulable.
+    pub node_name: Option<String>,
+    /// Node the pod was running on before, for rescheduled pods.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_node: Option<String>,
+    /// Scores of the feasible nodes, best first.
+    #[serde(default)]
+    pub scores: Vec<NodeScore>,
+    /// Nodes which didn't pass the filters.
+    #[serde(default)]
+    pub rejected: Vec<NodeRejection>,
+    /// Why the pod couldn't be placed at all.
+    #[serde(default)]
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct NodeScore {
+    pub node_name: String,
+    /// Sum of the weighted plugin scores.
+    pub total: i64,
+    pub plugins: Vec<PluginScore>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PluginScore {
+    pub plugin: String,
+    /// Normalized score, before the weight is applied.
+    pub score: i64,
+    pub weight: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct NodeRejection {
+    pub node_name: String,
+    /// The first plugin which rejected the node.
+    pub plugin: String,
+    pub reasons: Vec<String>,
+}
+
+/// Simulate scheduling of `pods` onto `nodes` with the given profiles.
+///
+/// See [`Scheduler::simulate`] for how the pods are placed.
+pub async fn simulate(
+    nodes: Vec<NodeInfo>,
+    pods: Vec<PodInfo>,
+    profiles: Vec<Profile>,
+    reschedule_existing: bool,
+) -> Vec<PodPlacement> {
+    let mut scheduler = Scheduler::with_profiles(profiles);
+    scheduler.set_cache_node(nodes).await;
+    for p in pods {
+        scheduler.update_cache_pod(p).await;
+    }
+    scheduler.simulate(reschedule_existing).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NODE: &str = r#"
+apiVersion: v1
+kind: Node
+metadata:
+  name: node1
+spec:
+  podCIDR: 10.0.0.0/24
+status:
+  capacity:
+    cpu: "4"
+    memory: 8Gi
+  allocatable:
+    cpu: "4"
+    memory: 8Gi
+"#;
+
+    const POD: &str = r#"
+apiVersion: v1
+kind: Pod
+metadata:
+  name: pod1
+spec:
+  containers:
+    - name: app
+      image: nginx
+"#;
+
+    #[test]
+    fn test_snapshot_from_manifest_stream() {
+        let content = format!("{NODE}---{POD}");
+        let snapshot = Snapshot::from_yaml(&content).unwrap();
+        assert_eq!(snapshot.nodes.len(), 1);
+        assert_eq!(snapshot.pods.len(), 1);
+        assert_eq!(snapshot.nodes[0].metadata.name, "node1");
+    }
+
+    #[test]
+    fn test_typed_allocatable_takes_precedence() {
+        let content = format!(
+            "{NODE}  allocatableResources:\n    cpu: 3500\n    memory: 1073741824\n    pods: 20\n"
+        );
+        let snapshot = Snapshot::from_yaml(&content).unwrap();
+        let node = convert_k8s_node_to_node_info(snapshot.nodes[0].clone());
+        assert_eq!(node.allocatable.cpu, 3500);
+        assert_eq!(node.allocatable.memory, 1 << 30);
+        assert_eq!(node.max_pods, 20);
+
+        let snapshot = Snapshot::from_yaml(NODE).unwrap();
+        let node = convert_k8s_node_to_node_info(snapshot.nodes[0].clone());
+        assert_eq!(node.allocatable.cpu, 4000);
+        assert_eq!(node.allocatable.memory, 8 << 30);
+    }
+
+    #[test]
+    fn test_snapshot_rejects_unknown_kind() {
+        let content = "apiVersion: v1\nkind: Service\nmetadata:\n  name: svc\n";
+        assert!(Snapshot::from_yaml(content).is_err());
+    }
+}
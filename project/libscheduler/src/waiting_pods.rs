@@ -0,0 +1,98 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use tokio::time::{Duration, Instant};
+
+use crate::models::PodInfo;
+
+/// Key of the [`WaitingPods`] handle in the cycle state.
+pub const WAITING_PODS_KEY: &str = "WaitingPods";
+
+/// A pod which is assumed on a node but held at Permit.
+#[derive(Clone)]
+pub struct WaitingPod {
+    pub pod: PodInfo,
+    pub node_name: String,
+    /// Permit plugins which haven't allowed the pod yet.
+    pending_plugins: HashSet<String>,
+    deadline: Instant,
+    rejected: Option<String>,
+}
+
+/// Pods held at Permit, shared between the scheduling cycles and the permit plugins.
+///
+/// A waiting pod is released once every plugin that asked it to wait has
+/// allowed it, and rolled back once any plugin rejects it or its deadline passes.
+#[derive(Default)]
+pub struct WaitingPods {
+    pods: Mutex<HashMap<String, WaitingPod>>,
+}
+
+impl WaitingPods {
+    /// Hold a pod until each of `plugins` allows it; the shortest timeout wins.
+    pub(crate) fn add(&self, pod: PodInfo, node_name: &str, plugins: HashMap<String, Duration>) {
+        let timeout = plugins.values().min().copied().unwrap_or_default();
+        let waiting = WaitingPod {
+            pod,
+            node_name: node_name.to_string(),
+            pending_plugins: plugins.into_keys().collect(),
+            deadline: Instant::now() + timeout,
+            rejected: None,
+        };
+        self.lock().insert(waiting.pod.name.clone(), waiting);
+    }
+
+    /// Snapshot of the pods currently waiting.
+    pub fn pods(&self) -> Vec<PodInfo> {
+        self.lock().values().map(|w| w.pod.clone()).collect()
+    }
+
+    pub fn allow(&self, pod_name: &str, plugin: &str) {
+        if let Some(w) = self.lock().get_mut(pod_name) {
+            w.pending_plugins.remove(plugin);
+        }
+    }
+
+    pub fn reject(&self, pod_name: &str, plugin: &str, msg: &str) {
+        if let Some(w) = self.lock().get_mut(pod_name) {
+            w.rejected = Some(format!("rejected by {plugin}: {msg}"));
+        }
+    }
+
+    pub(crate) fn remove(&self, pod_name: &str) -> Option<WaitingPod> {
+        self.lock().remove(pod_name)
+    }
+
+    /// Remove and return the pods which every permit plugin has allowed.
+    pub(crate) fn take_allowed(&self) -> Vec<WaitingPod> {
+        self.take(|w| w.rejected.is_none() && w.pending_plugins.is_empty())
+    }
+
+    /// Remove and return the pods which were rejected or whose deadline passed.
+    pub(crate) fn take_rejected(&self, now: Instant) -> Vec<WaitingPod> {
+        let res = self.take(|w| w.rejected.is_some() || w.deadline <= now);
+        for w in res.iter() {
+            log::info!(
+                "pod {} waiting on node {} is rolled back: {}",
+                w.pod.name,
+                w.node_name,
+                w.rejected.as_deref().unwrap_or("permit timeout")
+            );
+        }
+        res
+    }
+
+    fn take(&self, pred: impl Fn(&WaitingPod) -> bool) -> Vec<WaitingPod> {
+        let mut guard = self.lock();
+        let names: Vec<_> = guard
+            .values()
+            .filter(|w| pred(w))
+            .map(|w| w.pod.name.clone())
+            .collect();
+        names.iter().filter_map(|n| guard.remove(n)).collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, WaitingPod>> {
+        self.pods.lock().expect("waiting pods lock poisoned")
+    }
+}
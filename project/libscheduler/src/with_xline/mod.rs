@@ -6,9 +6,11 @@ pub mod model;
 pub mod utils;
 
 use crate::{
+    config::{DEFAULT_SCHEDULER_NAME, Profile},
     models::Assignment,
     plugins::{Plugins, node_resources_fit::ScoringStrategy},
     scheduler::Scheduler,
+    simulation::{PodPlacement, simulate},
     with_xline::utils::{get_node_from_kv, get_pod_from_kv, list_nodes, list_pods},
 };
 
@@ -20,10 +22,28 @@ pub async fn run_scheduler_with_xline(
     xline_option: XlineOptions,
     strategy: ScoringStrategy,
     plugins: Plugins,
+    unassume_rx: UnboundedReceiver<String>,
+) -> Result<UnboundedReceiver<Result<Assignment, anyhow::Error>>, anyhow::Error> {
+    run_scheduler_with_xline_profiles(
+        xline_option,
+        vec![Profile::new(DEFAULT_SCHEDULER_NAME, strategy, plugins)],
+        unassume_rx,
+    )
+    .await
+}
+
+/// Start a scheduler serving several profiles with xline watcher
+///
+/// # Argument
+/// - profiles: resolved profiles, usually from [`crate::config::SchedulerConfiguration`].
+/// - unassume_rx: a receiver passing pod's name that bind failed.
+pub async fn run_scheduler_with_xline_profiles(
+    xline_option: XlineOptions,
+    profiles: Vec<Profile>,
     mut unassume_rx: UnboundedReceiver<String>,
 ) -> Result<UnboundedReceiver<Result<Assignment, anyhow::Error>>, anyhow::Error> {
     let mut client = Client::connect(xline_option.endpoints, xline_option.config).await?;
-    let mut scheduler = Scheduler::new(strategy, plugins);
+    let mut scheduler = Scheduler::with_profiles(profiles);
     let exist_nodes = list_nodes(&mut client).await?;
     let exist_pods = list_pods(&mut client).await?;
     scheduler.set_cache_node(exist_nodes).await;
@@ -116,6 +136,20 @@ pub async fn run_scheduler_with_xline(
     Ok(rx)
 }
 
+/// Simulate scheduling of the nodes and pods currently stored in xline.
+///
+/// Nothing is written back; see [`Scheduler::simulate`].
+pub async fn simulate_with_xline(
+    xline_option: XlineOptions,
+    profiles: Vec<Profile>,
+    reschedule_existing: bool,
+) -> Result<Vec<PodPlacement>, anyhow::Error> {
+    let mut client = Client::connect(xline_option.endpoints, xline_option.config).await?;
+    let nodes = list_nodes(&mut client).await?;
+    let pods = list_pods(&mut client).await?;
+    Ok(simulate(nodes, pods, profiles, reschedule_existing).await)
+}
+
 async fn handle_pod_update(
     scheduler: &mut Scheduler,
     pod_msg: Result<Option<WatchResponse>, etcd_client::Error>,
@@ -2,9 +2,16 @@ use std::collections::HashMap;
 
 use etcd_client::{Client, GetOptions, KeyValue};
 
-use crate::models::{NodeInfo, NodeSpec, PodInfo, PodSpec, QueuedInfo, ResourcesRequirements};
+use crate::models::{
+    HostPort, NodeInfo, NodeSpec, PodGroup, PodInfo, PodSpec, QueuedInfo, ResourcesRequirements,
+    normalized_image_name,
+};
 use common::{Node, PodTask};
 
+/// Pods a node accepts when it doesn't report a `pods` allocatable, the
+/// Kubernetes kubelet default.
+const DEFAULT_MAX_PODS: u64 = 110;
+
 pub async fn get_pod(
     client: &mut Client,
     pod_name: &str,
@@ -91,17 +98,45 @@ pub fn convert_pod_task_to_pod_info(pod_task: PodTask) -> PodInfo {
     total_cpu = total_cpu.max(init_cpu);
     total_memory = total_memory.max(init_memory);
 
+    let host_ports = pod_task
+        .spec
+        .containers
+        .iter()
+        .flat_map(|c| c.ports.iter())
+        .filter(|p| p.host_port > 0)
+        .map(|p| HostPort {
+            ip: p.host_ip.clone(),
+            protocol: p.protocol.clone(),
+            port: p.host_port,
+        })
+        .collect();
+
+    let images = pod_task
+        .spec
+        .init_containers
+        .iter()
+        .chain(pod_task.spec.containers.iter())
+        .map(|c| normalized_image_name(&c.image))
+        .collect();
+
     let spec = PodSpec {
         resources: ResourcesRequirements {
             cpu: total_cpu,
             memory: total_memory,
         },
-        priority: 0,
+        priority: pod_task.spec.priority.unwrap_or(0).max(0) as u64,
         scheduling_gates: Vec::new(),
         tolerations: pod_task.spec.tolerations,
         node_name: pod_task.spec.node_name.clone(),
         node_selector: HashMap::new(),
         affinity: pod_task.spec.affinity.map(crate::models::Affinity::from),
+        scheduler_name: pod_task.spec.scheduler_name,
+        host_ports,
+        images,
+        pod_group: PodGroup::from_metadata(
+            &pod_task.metadata.labels,
+            &pod_task.metadata.annotations,
+        ),
     };
 
     PodInfo {
@@ -121,29 +156,53 @@ fn convert_k8s_node_to_node_info(k8s_node: Node) -> NodeInfo {
         taints: k8s_node.spec.taints,
     };
 
-    let allocatable = ResourcesRequirements {
-        cpu: parse_cpu(
-            k8s_node
-                .status
-                .allocatable
-                .get("cpu")
-                .unwrap_or(&"0".to_string()),
-        ),
-        memory: parse_memory(
-            k8s_node
-                .status
-                .allocatable
-                .get("memory")
-                .unwrap_or(&"0".to_string()),
+    // rkl reports typed resources, the string maps are only parsed for
+    // nodes registered by older agents
+    let (allocatable, max_pods) = match &k8s_node.status.allocatable_resources {
+        Some(resources) => (
+            ResourcesRequirements {
+                cpu: resources.cpu,
+                memory: resources.memory,
+            },
+            resources.pods,
         ),
+        None => {
+            let allocatable = &k8s_node.status.allocatable;
+            (
+                ResourcesRequirements {
+                    cpu: allocatable.get("cpu").map_or(0, |cpu| parse_cpu(cpu)),
+                    memory: allocatable
+                        .get("memory")
+                        .map_or(0, |memory| parse_memory(memory)),
+                },
+                allocatable
+                    .get("pods")
+                    .and_then(|p| p.parse::<u64>().ok())
+                    .unwrap_or(DEFAULT_MAX_PODS),
+            )
+        }
     };
 
+    let images = k8s_node
+        .status
+        .images
+        .iter()
+        .flat_map(|img| {
+            img.names
+                .iter()
+                .map(|name| (normalized_image_name(name), img.size_bytes))
+        })
+        .collect();
+
     NodeInfo {
         name: k8s_node.metadata.name,
         labels,
         spec,
         requested: ResourcesRequirements::default(),
         allocatable,
+        max_pods,
+        pod_count: 0,
+        images,
     }
 }
 
@@ -188,3 +247,39 @@ fn parse_memory(memory_str: &str) -> u64 {
         memory_str.parse::<u64>().unwrap_or(0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(allocatable: &[(&str, &str)]) -> Node {
+        let mut node: Node = serde_yaml::from_str(
+            r#"
+apiVersion: v1
+kind: Node
+metadata:
+  name: node1
+spec:
+  podCIDR: 10.0.0.0/24
+status:
+  capacity: {}
+  allocatable: {}
+"#,
+        )
+        .unwrap();
+        node.status.allocatable = allocatable
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        node
+    }
+
+    #[test]
+    fn test_max_pods_defaults_when_not_reported() {
+        let info = convert_k8s_node_to_node_info(node(&[("cpu", "2"), ("memory", "4Gi")]));
+        assert_eq!(info.max_pods, DEFAULT_MAX_PODS);
+
+        let info = convert_k8s_node_to_node_info(node(&[("pods", "8")]));
+        assert_eq!(info.max_pods, 8);
+    }
+}
@@ -1,8 +1,9 @@
 use common::{LabelSelector, Taint, TaintEffect, TaintKey, Toleration, TolerationOperator};
+use libscheduler::config::{DEFAULT_SCHEDULER_NAME, Profile};
 use libscheduler::models::{
     Affinity, NodeAffinity, NodeInfo, NodeSelector, NodeSelectorOperator, NodeSelectorRequirement,
-    NodeSelectorTerm, NodeSpec, PodAffinity, PodAffinityTerm, PodAntiAffinity, PodInfo, PodSpec,
-    PreferredSchedulingTerm, PreferredSchedulingTerms, ResourcesRequirements,
+    NodeSelectorTerm, NodeSpec, PodAffinity, PodAffinityTerm, PodAntiAffinity, PodGroup, PodInfo,
+    PodSpec, PreferredSchedulingTerm, PreferredSchedulingTerms, ResourcesRequirements,
     WeightedPodAffinityTerm,
 };
 use libscheduler::plugins::Plugins;
@@ -31,6 +32,7 @@ fn make_node(name: &str, cpu: u64, memory: u64) -> NodeInfo {
         requested: ResourcesRequirements { cpu: 0, memory: 0 },
         spec: NodeSpec::default(),
         labels: HashMap::new(),
+        ..Default::default()
     }
 }
 
@@ -938,3 +940,120 @@ async fn test_scheduler_pod_affinity_weighted_preferred() {
     assert_eq!(assignment.pod_name, "preferred-affinity-pod");
     assert_eq!(assignment.node_name, "node1");
 }
+
+#[tokio::test]
+async fn test_scheduler_profiles_select_by_scheduler_name() {
+    let spread = Profile::new(
+        DEFAULT_SCHEDULER_NAME,
+        ScoringStrategy::LeastAllocated,
+        Plugins::default(),
+    );
+    let packing = Profile::new(
+        "bin-packing",
+        ScoringStrategy::MostAllocated,
+        Plugins::default(),
+    );
+    let mut scheduler = Scheduler::with_profiles(vec![spread, packing]);
+    let mut busy = make_node("busy", 4000, 4096);
+    busy.requested = ResourcesRequirements {
+        cpu: 2000,
+        memory: 2048,
+    };
+    scheduler.update_cache_node(busy).await;
+    scheduler
+        .update_cache_node(make_node("idle", 4000, 4096))
+        .await;
+
+    let mut packed = make_pod("packed", 10, 100, 100);
+    packed.spec.scheduler_name = Some("bin-packing".to_string());
+    scheduler.update_cache_pod(packed).await;
+    let mut unknown = make_pod("unknown", 10, 100, 100);
+    unknown.spec.scheduler_name = Some("other-scheduler".to_string());
+    scheduler.update_cache_pod(unknown).await;
+    scheduler
+        .update_cache_pod(make_pod("spread", 5, 100, 100))
+        .await;
+
+    let mut rx = scheduler.run();
+    let mut assignments = HashMap::new();
+    for _ in 0..2 {
+        let res = timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assignments.insert(res.pod_name, res.node_name);
+    }
+    assert_eq!(assignments["packed"], "busy");
+    assert_eq!(assignments["spread"], "idle");
+    assert!(
+        timeout(Duration::from_millis(500), rx.recv())
+            .await
+            .is_err(),
+        "pods for other schedulers must not be assigned"
+    );
+}
+
+fn make_gang_pod(name: &str, min_member: usize, timeout_secs: u64) -> PodInfo {
+    let mut pod = make_pod(name, 10, 1, 100);
+    pod.spec.pod_group = Some(PodGroup {
+        name: "gang".to_string(),
+        min_member,
+        schedule_timeout: Duration::from_secs(timeout_secs),
+    });
+    pod
+}
+
+#[tokio::test]
+async fn test_scheduler_pod_group_released_together() {
+    let mut scheduler = Scheduler::new(ScoringStrategy::LeastAllocated, Plugins::default());
+    scheduler
+        .update_cache_node(make_node("node1", 4, 4096))
+        .await;
+    scheduler
+        .update_cache_pod(make_gang_pod("worker-0", 2, 30))
+        .await;
+
+    let mut rx = scheduler.run();
+    assert!(
+        timeout(Duration::from_millis(500), rx.recv())
+            .await
+            .is_err(),
+        "a partial pod group must be held at permit"
+    );
+
+    scheduler
+        .update_cache_pod(make_gang_pod("worker-1", 2, 30))
+        .await;
+    let mut assigned = Vec::new();
+    for _ in 0..2 {
+        let res = timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assigned.push(res.pod_name);
+    }
+    assigned.sort();
+    assert_eq!(assigned, vec!["worker-0", "worker-1"]);
+}
+
+#[tokio::test]
+async fn test_scheduler_pod_group_timeout() {
+    let mut scheduler = Scheduler::new(ScoringStrategy::LeastAllocated, Plugins::default());
+    scheduler
+        .update_cache_node(make_node("node1", 4, 4096))
+        .await;
+    scheduler
+        .update_cache_pod(make_gang_pod("worker-0", 3, 1))
+        .await;
+    scheduler
+        .update_cache_pod(make_gang_pod("worker-1", 3, 1))
+        .await;
+
+    let mut rx = scheduler.run();
+    assert!(
+        timeout(Duration::from_secs(3), rx.recv()).await.is_err(),
+        "members of an incomplete pod group must not be assigned"
+    );
+}
@@ -115,6 +115,9 @@ fn create_test_node(name: &str, cpu: &str, memory: &str) -> Node {
                 status: common::ConditionStatus::True,
                 last_heartbeat_time: None,
             }],
+            images: vec![],
+            capacity_resources: None,
+            allocatable_resources: None,
         },
     }
 }
@@ -157,6 +160,9 @@ fn create_test_pod(name: &str, cpu_limit: Option<&str>, memory_limit: Option<&st
                 volume_mounts: None,
                 command: None,
                 working_dir: None,
+                lifecycle: None,
+                restart_policy: None,
+                image_pull_policy: None,
             }],
             init_containers: vec![],
             tolerations: vec![],
@@ -269,6 +269,9 @@ impl ComposeManager {
                     volume_mounts: None,
                     command: None,
                     working_dir: None,
+                    lifecycle: None,
+                    restart_policy: None,
+                    image_pull_policy: None,
                 };
 
                 // handle the services volume name
@@ -221,6 +221,9 @@ impl ContainerRunner {
                 volume_mounts: None,
                 command: None,
                 working_dir: None,
+                lifecycle: None,
+                restart_policy: None,
+                image_pull_policy: None,
             },
             config: None,
             container_id: container_id.to_string(),
@@ -926,6 +929,9 @@ mod test {
             volume_mounts: None,
             command: None,
             working_dir: None,
+            lifecycle: None,
+            restart_policy: None,
+            image_pull_policy: None,
         };
         let runner = ContainerRunner::from_spec(spec.clone(), None).unwrap();
         assert_eq!(runner.container_id, "demo1");
@@ -959,6 +965,9 @@ mod test {
                 volume_mounts: None,
                 command: None,
                 working_dir: None,
+                lifecycle: None,
+                restart_policy: None,
+                image_pull_policy: None,
             },
             None,
         )
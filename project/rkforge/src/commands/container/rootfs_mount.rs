@@ -54,6 +54,31 @@ impl RootfsMount {
         bundle_path: &Path,
         use_libfuse: bool,
     ) -> Result<Self> {
+        Self::start_with_id_mapping(
+            lower_dirs,
+            upper_dir,
+            work_dir,
+            mountpoint,
+            bundle_path,
+            use_libfuse,
+            None,
+        )
+    }
+
+    /// Like [`RootfsMount::start`], with files shown through a UID/GID mapping
+    /// (`uidmapping=H:T:L,gidmapping=H:T:L`). Only the libfuse backend can map
+    /// ids, so it is always used when `id_mapping` is set.
+    pub fn start_with_id_mapping(
+        lower_dirs: &[PathBuf],
+        upper_dir: &Path,
+        work_dir: &Path,
+        mountpoint: &Path,
+        bundle_path: &Path,
+        use_libfuse: bool,
+        id_mapping: Option<&str>,
+    ) -> Result<Self> {
+        let use_libfuse = use_libfuse || id_mapping.is_some();
+
         // Construct MountConfig for the child process
         let mount_cfg = MountConfig {
             lower_dir: lower_dirs.to_vec(),
@@ -63,6 +88,7 @@ impl RootfsMount {
             overlay: bundle_path.to_path_buf(),
             upper_cnt: 0,
             libfuse: use_libfuse,
+            id_mapping: id_mapping.map(str::to_string),
         };
 
         let cfg_json =
@@ -11,6 +11,7 @@ use serde_json::Value;
 use std::collections::HashSet;
 use std::io::{self, Write};
 use std::path::{Component, Path};
+use std::time::SystemTime;
 use tabwriter::TabWriter;
 
 // ---------------------------------------------------------------------------
@@ -813,18 +814,108 @@ fn short_id(digest: &str) -> String {
     }
 }
 
-/// Return (size, created) for display, reading the manifest only once.
-fn get_image_metadata(digest: &str) -> Result<(String, String)> {
-    let manifest = read_manifest(digest)?;
+/// List every locally cached image reference together with its size in bytes.
+///
+/// Images whose manifest cannot be read are skipped.
+pub fn local_images() -> Result<Vec<(String, u64)>> {
+    let repos = Repositories::load()?;
+    Ok(repos
+        .entries()
+        .into_iter()
+        .filter_map(|(image_ref, digest)| {
+            let manifest = read_manifest(digest).ok()?;
+            let size = manifest_size(digest, &manifest).ok()?;
+            Some((image_ref.clone(), size))
+        })
+        .collect())
+}
+
+/// A locally cached image together with every reference tagging it.
+pub struct CachedImage {
+    pub digest: String,
+    pub image_refs: Vec<String>,
+    pub size_bytes: u64,
+    /// When the image was stored on this node.
+    pub stored_at: SystemTime,
+}
+
+/// List the locally cached images, one entry per manifest digest.
+///
+/// Images whose manifest cannot be read are skipped.
+pub fn cached_images() -> Result<Vec<CachedImage>> {
+    let repos = Repositories::load()?;
+    let mut images: Vec<CachedImage> = Vec::new();
+    for (image_ref, digest) in repos.entries() {
+        if let Some(image) = images.iter_mut().find(|i| i.digest == *digest) {
+            image.image_refs.push(image_ref.clone());
+            continue;
+        }
+        let Ok(manifest) = read_manifest(digest) else {
+            continue;
+        };
+        let Ok(size_bytes) = manifest_size(digest, &manifest) else {
+            continue;
+        };
+        let stored_at = ultimate_blob_path(digest)
+            .and_then(|path| Ok(std::fs::metadata(path)?.modified()?))
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        images.push(CachedImage {
+            digest: digest.clone(),
+            image_refs: vec![image_ref.clone()],
+            size_bytes,
+            stored_at,
+        });
+    }
+    Ok(images)
+}
 
-    let total_bytes: i64 = match &manifest {
+/// The manifest digest of a locally cached image reference, if there is one.
+pub fn cached_image_digest(image_ref: &str) -> Option<String> {
+    resolve_image_digest(image_ref).ok()
+}
+
+/// Untag every reference to the image with manifest `digest` and remove its
+/// blobs which no other image uses. The tags are restored if the blobs
+/// can't be removed.
+pub fn remove_cached_image(digest: &str) -> Result<()> {
+    let mut repos = Repositories::load()?;
+    let image_refs = repos
+        .entries()
+        .into_iter()
+        .filter(|(_, d)| *d == digest)
+        .map(|(image_ref, _)| image_ref.clone())
+        .collect::<Vec<_>>();
+    if image_refs.is_empty() {
+        bail!("Image {} not found locally", short_id(digest));
+    }
+    for image_ref in &image_refs {
+        repos.remove(image_ref);
+    }
+    if let Err(e) = cleanup_image_blobs(digest, &repos) {
+        for image_ref in &image_refs {
+            repos.add(image_ref, digest);
+        }
+        repos.store()?;
+        return Err(e.context("Failed to clean up blobs; tags restored"));
+    }
+    repos.store()
+}
+
+fn manifest_size(digest: &str, manifest: &OciManifest) -> Result<u64> {
+    let total_bytes: i64 = match manifest {
         OciManifest::Image(img) => img.layers.iter().map(|l| l.size).sum::<i64>() + img.config.size,
         OciManifest::ImageIndex(idx) => idx.manifests.iter().map(|m| m.size).sum(),
     };
     if total_bytes < 0 {
         bail!("invalid negative size in manifest for digest {}", digest);
     }
-    let size = format_size(total_bytes as u64);
+    Ok(total_bytes as u64)
+}
+
+/// Return (size, created) for display, reading the manifest only once.
+fn get_image_metadata(digest: &str) -> Result<(String, String)> {
+    let manifest = read_manifest(digest)?;
+    let size = format_size(manifest_size(digest, &manifest)?);
 
     let created = if let OciManifest::Image(img) = &manifest
         && let Some(config) = read_image_config_value(&img.config.digest)?
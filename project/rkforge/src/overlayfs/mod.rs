@@ -119,7 +119,7 @@ fn daemon_mount_libfuse(
             upperdir: &cfg.upper_dir,
             mountpoint: &cfg.mountpoint,
             privileged: CONFIG.is_root,
-            mapping: None::<&str>,
+            mapping: cfg.id_mapping.as_deref(),
             name: None::<String>,
             allow_other: true,
         })
@@ -273,6 +273,10 @@ pub struct MountConfig {
     pub overlay: PathBuf,
     pub(crate) upper_cnt: i32,
     pub libfuse: bool,
+    /// UID/GID mapping applied by the libfuse backend, in the
+    /// `uidmapping=H:T:L,gidmapping=H:T:L` format of libfuse-fs.
+    #[serde(default)]
+    pub id_mapping: Option<String>,
 }
 
 impl MountConfig {
@@ -285,6 +289,7 @@ impl MountConfig {
             overlay,
             upper_cnt: 0,
             libfuse,
+            id_mapping: None,
         }
     }
 
@@ -340,6 +345,7 @@ impl Default for MountConfig {
             overlay: CONFIG.build_dir.join("overlay"),
             upper_cnt: 0,
             libfuse: false,
+            id_mapping: None,
         }
     }
 }
@@ -162,6 +162,9 @@ impl TaskRunner {
             volume_mounts: None,
             command: None,
             working_dir: None,
+            lifecycle: None,
+            restart_policy: None,
+            image_pull_policy: None,
         };
 
         let puller = RkforgeImagePuller {};
@@ -372,9 +375,14 @@ impl TaskRunner {
 
         // In overlay mode, override Root path to "merged" (OCISpecGenerator defaults to "rootfs")
         if self.rootfs_mounts.contains_key(&container_id) {
+            let readonly = spec
+                .root()
+                .as_ref()
+                .and_then(|root| root.readonly())
+                .unwrap_or(false);
             let root = RootBuilder::default()
                 .path("merged")
-                .readonly(false)
+                .readonly(readonly)
                 .build()
                 .map_err(|e| anyhow!("failed to build root spec: {e}"))?;
             spec.set_root(Some(root));
@@ -3,22 +3,54 @@ mod layer;
 pub mod media;
 
 use crate::config::auth::AuthConfig;
+use crate::config::meta::Repositories;
 use crate::pull::layer::pull_layers;
-use crate::storage::{parse_image_ref, write_manifest};
+use crate::storage::{
+    full_image_ref, parse_image_ref, read_manifest, ultimate_blob_path, write_manifest,
+};
 use anyhow::Context;
 use anyhow::anyhow;
 use clap::Parser;
 use oci_client::client::ClientConfig;
 use oci_client::manifest::OciManifest;
-use oci_client::secrets::RegistryAuth;
 use oci_client::{Client, client};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::OnceLock;
 use std::thread;
 use tokio::runtime::{Handle, Runtime};
 
+pub use oci_client::secrets::RegistryAuth;
+
 static RUNTIME: OnceLock<Runtime> = OnceLock::new();
 
+/// When an image is pulled from its registry rather than taken from the
+/// local store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PullPolicy {
+    /// Always fetch the manifest and any missing layers.
+    #[default]
+    Always,
+    /// Only pull images which are not stored locally.
+    IfNotPresent,
+    /// Never pull, the image has to be stored locally.
+    Never,
+}
+
+/// How to get an image, e.g. the one of a pod's container.
+#[derive(Debug, Clone, Default)]
+pub struct PullOptions {
+    pub policy: PullPolicy,
+    /// Credentials by registry url, used instead of the ones logged in with
+    /// `rkforge login`.
+    pub credentials: HashMap<String, RegistryAuth>,
+}
+
+/// An image is not stored locally and its pull policy is [`PullPolicy::Never`].
+#[derive(Debug, thiserror::Error)]
+#[error("image {0} is not present locally and its pull policy is Never")]
+pub struct ImageNotPresent(pub String);
+
 #[derive(Parser, Debug)]
 pub struct PullArgs {
     /// Image reference. (e.g "ubuntu:latest" or "me.org/ubuntu:latest")
@@ -53,10 +85,7 @@ pub fn sync_pull_or_get_image_with_policy_and_output(
 
     let url = auth_config.resolve_url(url);
 
-    let auth_method = match auth_config.find_entry_by_url(&url) {
-        Ok(entry) => RegistryAuth::Bearer(entry.pat.clone()),
-        Err(_) => RegistryAuth::Anonymous,
-    };
+    let auth_method = registry_auth(&auth_config, &url);
 
     let client_config = ClientConfig {
         protocol: client::ClientProtocol::Http,
@@ -81,9 +110,17 @@ pub fn sync_pull_or_get_image_with_policy_and_output(
         let manifest_path = write_manifest(&image_ref, &manifest, &digest).await?;
         Ok((manifest_path, layers))
     };
+    block_on(do_pull)
+}
+
+/// Run `fut` to completion from synchronous code, inside or outside of a
+/// tokio runtime.
+fn block_on<T: Send + 'static>(
+    fut: impl Future<Output = anyhow::Result<T>> + Send + 'static,
+) -> anyhow::Result<T> {
     match Handle::try_current() {
         Ok(handle) => {
-            let pull_or_get = thread::spawn(move || handle.block_on(do_pull));
+            let pull_or_get = thread::spawn(move || handle.block_on(fut));
             pull_or_get.join().map_err(|_| anyhow!("thread panicked"))?
         }
         Err(_) => {
@@ -94,11 +131,42 @@ pub fn sync_pull_or_get_image_with_policy_and_output(
                     .expect("Failed to build tokio runtime")
             });
 
-            rt.block_on(do_pull)
+            rt.block_on(fut)
         }
     }
 }
 
+/// The credentials logged in with `rkforge login` for the registry `url`.
+fn registry_auth(auth_config: &AuthConfig, url: &str) -> RegistryAuth {
+    match auth_config.find_entry_by_url(url) {
+        Ok(entry) => RegistryAuth::Bearer(entry.pat.clone()),
+        Err(_) => RegistryAuth::Anonymous,
+    }
+}
+
+/// The manifest and layers of `image_ref` from registry `url`, if the image
+/// is stored locally with all of its blobs.
+fn local_image(url: &str, image_ref: &str) -> anyhow::Result<Option<(PathBuf, Vec<PathBuf>)>> {
+    let reference = parse_image_ref(url, image_ref, None::<String>)?;
+    let key = full_image_ref(reference.repository(), reference.tag());
+    let Some(digest) = Repositories::load()?.get(&key)?.map(str::to_string) else {
+        return Ok(None);
+    };
+    let Ok(OciManifest::Image(manifest)) = read_manifest(&digest) else {
+        return Ok(None);
+    };
+    let layers = manifest
+        .layers
+        .iter()
+        .map(|layer| ultimate_blob_path(&layer.digest))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if !ultimate_blob_path(&manifest.config.digest)?.exists() || !layers.iter().all(|l| l.exists())
+    {
+        return Ok(None);
+    }
+    Ok(Some((ultimate_blob_path(&digest)?, layers)))
+}
+
 /// Ensures an image is available locally, pulling any missing components.
 ///
 /// This function implements a "local-first" strategy for image layers.
@@ -147,11 +215,57 @@ pub async fn pull_or_get_image_with_policy(
 
     let url = auth_config.resolve_url(url);
 
-    let auth_method = match auth_config.find_entry_by_url(&url) {
-        Ok(entry) => RegistryAuth::Bearer(entry.pat.clone()),
-        Err(_) => RegistryAuth::Anonymous,
+    let auth_method = registry_auth(&auth_config, &url);
+    pull_image(url, image_ref, &auth_method, no_cache).await
+}
+
+/// Like [`pull_or_get_image`], following the pull policy and using the
+/// registry credentials of `options`. Fails with [`ImageNotPresent`] if the
+/// policy forbids pulling a missing image.
+pub async fn pull_or_get_image_with_options(
+    image_ref: impl AsRef<str>,
+    url: Option<impl AsRef<str>>,
+    options: &PullOptions,
+) -> anyhow::Result<(PathBuf, Vec<PathBuf>)> {
+    let image_ref = image_ref.as_ref();
+
+    let auth_config = AuthConfig::load()?;
+
+    let url = auth_config.resolve_url(url);
+
+    if options.policy != PullPolicy::Always
+        && let Some(image) = local_image(&url, image_ref)?
+    {
+        return Ok(image);
+    }
+    if options.policy == PullPolicy::Never {
+        return Err(ImageNotPresent(image_ref.to_string()).into());
+    }
+
+    let auth_method = match options.credentials.get(&url) {
+        Some(auth) => auth.clone(),
+        None => registry_auth(&auth_config, &url),
     };
+    pull_image(url, image_ref, &auth_method, false).await
+}
 
+pub fn sync_pull_or_get_image_with_options(
+    image_ref: impl AsRef<str>,
+    url: Option<impl AsRef<str>>,
+    options: &PullOptions,
+) -> anyhow::Result<(PathBuf, Vec<PathBuf>)> {
+    let image_ref = image_ref.as_ref().to_string();
+    let url = url.map(|url| url.as_ref().to_string());
+    let options = options.clone();
+    block_on(async move { pull_or_get_image_with_options(image_ref, url, &options).await })
+}
+
+async fn pull_image(
+    url: String,
+    image_ref: &str,
+    auth_method: &RegistryAuth,
+    no_cache: bool,
+) -> anyhow::Result<(PathBuf, Vec<PathBuf>)> {
     let client_config = ClientConfig {
         protocol: client::ClientProtocol::Http,
         ..Default::default()
@@ -160,7 +274,7 @@ pub async fn pull_or_get_image_with_policy(
 
     let image_ref = parse_image_ref(url, image_ref, None::<String>)?;
     let (manifest, digest) = client
-        .pull_manifest(&image_ref, &auth_method)
+        .pull_manifest(&image_ref, auth_method)
         .await
         .with_context(|| "Failed to pull manifest")?;
 
@@ -0,0 +1,64 @@
+use anyhow::{Result, anyhow};
+use common::RksMessage;
+
+use crate::commands::compose::convert::ClusterObjects;
+use crate::commands::pod::TLSConnectionArgs;
+use crate::quic::client::{Cli, QUICClient};
+
+/// Submit the objects of a compose project to rks. Deployments and
+/// Services are applied, Secrets created unless they exist. ConfigMaps and
+/// PersistentVolumeClaims are skipped, rks does not serve them.
+pub async fn deploy(objects: ClusterObjects, addr: &str, tls_cfg: TLSConnectionArgs) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+    println!("RKL connected to RKS at {addr}");
+
+    for secret in objects.secrets {
+        let name = secret.metadata.name.clone();
+        cli.send_msg(&RksMessage::CreateSecret(Box::new(secret)))
+            .await?;
+        match cli.fetch_msg().await? {
+            RksMessage::Ack => println!("secret/{name} created"),
+            RksMessage::Error(err) if err.contains("already exists") => {
+                eprintln!("warning: secret/{name} already exists, left unchanged")
+            }
+            RksMessage::Error(err) => return Err(anyhow!("Failed to create secret: {}", err)),
+            msg => return Err(anyhow!("Unexpected response: {:?}", msg)),
+        }
+    }
+
+    for config_map in &objects.config_maps {
+        eprintln!(
+            "warning: configmap/{} skipped, rks does not serve ConfigMaps",
+            config_map.metadata.name
+        );
+    }
+    for claim in &objects.claims {
+        eprintln!(
+            "warning: persistentvolumeclaim/{} skipped, rks does not serve PersistentVolumeClaims",
+            claim.metadata.name
+        );
+    }
+
+    for deployment in objects.deployments {
+        let name = deployment.metadata.name.clone();
+        cli.send_msg(&RksMessage::UpdateDeployment(Box::new(deployment)))
+            .await?;
+        match cli.fetch_msg().await? {
+            RksMessage::Ack => println!("deployment/{name} configured"),
+            RksMessage::Error(err) => return Err(anyhow!("Failed to apply deployment: {}", err)),
+            msg => return Err(anyhow!("Unexpected response: {:?}", msg)),
+        }
+    }
+
+    for service in objects.services {
+        let name = service.metadata.name.clone();
+        cli.send_msg(&RksMessage::UpdateService(Box::new(service)))
+            .await?;
+        match cli.fetch_msg().await? {
+            RksMessage::Ack => println!("service/{name} configured"),
+            RksMessage::Error(err) => return Err(anyhow!("Failed to apply service: {}", err)),
+            msg => return Err(anyhow!("Unexpected response: {:?}", msg)),
+        }
+    }
+    Ok(())
+}
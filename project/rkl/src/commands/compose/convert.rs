@@ -0,0 +1,495 @@
+//! Translation of a compose project into cluster objects.
+//!
+//! Each service becomes a Deployment and, when it publishes ports, a
+//! Service selecting its pods. Top-level secrets become Secrets, configs
+//! ConfigMaps and named volumes PersistentVolumeClaims. Whatever has no
+//! counterpart in the cluster is left out with a warning.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use common::{
+    Deployment, DeploymentSpec, DeploymentStrategy, LabelSelector, ObjectMeta, PodSpec,
+    PodTemplateSpec, RestartPolicy, RollingUpdateStrategy, Secret, ServicePort, ServiceTask,
+};
+use libruntime::volume::{PatternType, string_to_pattern};
+use serde::Serialize;
+
+use crate::commands::compose::map_port_style;
+use crate::commands::compose::secret::read_secret;
+use crate::commands::compose::spec::{ComposeSpec, ServiceSpec};
+
+/// Labels selecting the pods of a compose service.
+pub const PROJECT_LABEL: &str = "compose.rk8s.io/project";
+pub const SERVICE_LABEL: &str = "compose.rk8s.io/service";
+
+/// Size requested for a named volume without a `size` driver option.
+const DEFAULT_CLAIM_SIZE: &str = "1Gi";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ConfigMap {
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+    pub kind: String,
+    pub metadata: ObjectMeta,
+    pub data: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PersistentVolumeClaim {
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+    pub kind: String,
+    pub metadata: ObjectMeta,
+    pub spec: PersistentVolumeClaimSpec,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistentVolumeClaimSpec {
+    pub access_modes: Vec<String>,
+    pub resources: VolumeResourceRequirements,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct VolumeResourceRequirements {
+    pub requests: BTreeMap<String, String>,
+}
+
+/// The cluster objects of a compose project.
+#[derive(Default)]
+pub struct ClusterObjects {
+    pub secrets: Vec<Secret>,
+    pub config_maps: Vec<ConfigMap>,
+    pub claims: Vec<PersistentVolumeClaim>,
+    pub deployments: Vec<Deployment>,
+    pub services: Vec<ServiceTask>,
+    /// What could not be translated.
+    pub warnings: Vec<String>,
+}
+
+impl ClusterObjects {
+    /// All objects as a multi-document YAML stream.
+    pub fn to_yaml(&self) -> Result<String> {
+        let mut docs = Vec::new();
+        for secret in &self.secrets {
+            docs.push(serde_yaml::to_string(secret)?);
+        }
+        for config_map in &self.config_maps {
+            docs.push(serde_yaml::to_string(config_map)?);
+        }
+        for claim in &self.claims {
+            docs.push(serde_yaml::to_string(claim)?);
+        }
+        for deployment in &self.deployments {
+            docs.push(serde_yaml::to_string(deployment)?);
+        }
+        for service in &self.services {
+            docs.push(serde_yaml::to_string(service)?);
+        }
+        Ok(docs.join("---\n"))
+    }
+}
+
+/// Translate `spec`, the compose file of `project` in `base_dir`.
+pub fn convert(spec: &ComposeSpec, project: &str, base_dir: &Path) -> Result<ClusterObjects> {
+    let mut objects = ClusterObjects::default();
+
+    let mut secrets = spec.secrets.iter().flatten().collect::<Vec<_>>();
+    secrets.sort_by_key(|(name, _)| *name);
+    for (name, secret_spec) in secrets {
+        let content = read_secret(name, secret_spec, base_dir)?;
+        objects.secrets.push(Secret {
+            api_version: "v1".to_string(),
+            kind: "Secret".to_string(),
+            metadata: metadata(object_name(name, &mut objects.warnings), HashMap::new()),
+            secret_type: "Opaque".to_string(),
+            data: HashMap::from([(name.clone(), BASE64.encode(content))]),
+        });
+    }
+
+    let mut configs = spec.configs.iter().flatten().collect::<Vec<_>>();
+    configs.sort_by_key(|(name, _)| *name);
+    for (name, config) in configs {
+        let path = base_dir.join(&config.file);
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("failed to read config {name} from {}: {e}", path.display()))?;
+        let key = Path::new(&config.file)
+            .file_name()
+            .map(|file| file.to_string_lossy().to_string())
+            .unwrap_or_else(|| name.clone());
+        objects.config_maps.push(ConfigMap {
+            api_version: "v1".to_string(),
+            kind: "ConfigMap".to_string(),
+            metadata: metadata(object_name(name, &mut objects.warnings), HashMap::new()),
+            data: BTreeMap::from([(key, content)]),
+        });
+    }
+
+    let mut volumes = spec.volumes.iter().flatten().collect::<Vec<_>>();
+    volumes.sort_by_key(|(name, _)| *name);
+    for (name, volume) in volumes {
+        if volume.external.unwrap_or(false) {
+            objects.warnings.push(format!(
+                "external volume {name} is expected to exist in the cluster"
+            ));
+            continue;
+        }
+        let size = volume
+            .opts
+            .as_ref()
+            .and_then(|opts| opts.get("size"))
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_CLAIM_SIZE.to_string());
+        let claim_name = volume.name.as_deref().unwrap_or(name);
+        objects.claims.push(PersistentVolumeClaim {
+            api_version: "v1".to_string(),
+            kind: "PersistentVolumeClaim".to_string(),
+            metadata: metadata(
+                object_name(claim_name, &mut objects.warnings),
+                HashMap::new(),
+            ),
+            spec: PersistentVolumeClaimSpec {
+                access_modes: vec!["ReadWriteOnce".to_string()],
+                resources: VolumeResourceRequirements {
+                    requests: BTreeMap::from([("storage".to_string(), size)]),
+                },
+            },
+        });
+    }
+
+    let mut services = spec.services.iter().collect::<Vec<_>>();
+    services.sort_by_key(|(name, _)| *name);
+    for (srv_name, srv) in services {
+        convert_service(srv_name, srv, project, base_dir, &mut objects)?;
+    }
+    Ok(objects)
+}
+
+fn convert_service(
+    srv_name: &str,
+    srv: &ServiceSpec,
+    project: &str,
+    base_dir: &Path,
+    objects: &mut ClusterObjects,
+) -> Result<()> {
+    let warnings = &mut objects.warnings;
+    let name = object_name(srv_name, warnings);
+    let selector = HashMap::from([
+        (PROJECT_LABEL.to_string(), project.to_string()),
+        (SERVICE_LABEL.to_string(), srv_name.to_string()),
+    ]);
+
+    let mut container = srv
+        .container_spec(name.clone(), base_dir)
+        .map_err(|e| anyhow!("service {srv_name} is invalid: {e}"))?;
+    // the Service publishes the ports, not the node the pod runs on
+    for port in &mut container.ports {
+        port.host_port = 0;
+        port.host_ip = String::new();
+    }
+    // pods of a Deployment are always restarted
+    if let Some(restart) = &srv.restart
+        && srv.restart_policy()? != Some(RestartPolicy::Always)
+    {
+        warnings.push(format!(
+            "service {srv_name}: restart {restart} is ignored, pods of a Deployment are always restarted"
+        ));
+    }
+
+    if srv.container_name.is_some() {
+        warnings.push(format!(
+            "service {srv_name}: container_name is ignored, pods are named after the Deployment"
+        ));
+    }
+    if !srv.networks.is_empty() {
+        warnings.push(format!(
+            "service {srv_name}: networks are ignored, all pods share the cluster network"
+        ));
+    }
+    if !srv.depends_on.services().is_empty() {
+        warnings.push(format!(
+            "service {srv_name}: depends_on is ignored, Deployments start independently"
+        ));
+    }
+    for volume in &srv.volumes {
+        let pattern = string_to_pattern(volume)?;
+        match pattern.pattern_type {
+            PatternType::Named => warnings.push(format!(
+                "service {srv_name}: volume {volume} is dropped, pods cannot mount PersistentVolumeClaims"
+            )),
+            PatternType::BindMount | PatternType::Anonymous => warnings.push(format!(
+                "service {srv_name}: volume {volume} cannot be translated, only named volumes have a cluster counterpart"
+            )),
+        }
+    }
+    for config in srv.configs.iter().flatten() {
+        warnings.push(format!(
+            "service {srv_name}: config {} is not mounted at {}, pods cannot mount ConfigMaps",
+            config.source, config.target
+        ));
+    }
+    for secret in srv.secrets.iter().flatten() {
+        warnings.push(format!(
+            "service {srv_name}: secret {} is not mounted at {}, pods cannot mount Secrets",
+            secret.source(),
+            secret.target()
+        ));
+    }
+
+    let annotations = srv.labels();
+    objects.deployments.push(Deployment {
+        api_version: "apps/v1".to_string(),
+        kind: "Deployment".to_string(),
+        metadata: ObjectMeta {
+            annotations: annotations.clone(),
+            ..metadata(name.clone(), selector.clone())
+        },
+        spec: DeploymentSpec {
+            replicas: srv.replicas() as i32,
+            selector: LabelSelector {
+                match_labels: selector.clone(),
+                match_expressions: vec![],
+            },
+            template: PodTemplateSpec {
+                metadata: ObjectMeta {
+                    annotations,
+                    ..metadata(String::new(), selector.clone())
+                },
+                spec: PodSpec {
+                    containers: vec![container],
+                    restart_policy: RestartPolicy::Always,
+                    ..Default::default()
+                },
+            },
+            strategy: DeploymentStrategy::RollingUpdate {
+                rolling_update: RollingUpdateStrategy::default(),
+            },
+            progress_deadline_seconds: 600,
+            revision_history_limit: 10,
+        },
+        status: Default::default(),
+    });
+
+    if let Some(service) = service_task(srv_name, &name, srv, selector, &mut objects.warnings)? {
+        objects.services.push(service);
+    }
+    Ok(())
+}
+
+/// A NodePort Service for the ports a service publishes, or a ClusterIP
+/// Service when they are only published on the loopback address.
+fn service_task(
+    srv_name: &str,
+    name: &str,
+    srv: &ServiceSpec,
+    selector: HashMap<String, String>,
+    warnings: &mut Vec<String>,
+) -> Result<Option<ServiceTask>> {
+    let published = map_port_style(srv.ports.clone())?;
+    if published.is_empty() {
+        return Ok(None);
+    }
+
+    let mut node_port = false;
+    let mut ports: Vec<ServicePort> = Vec::new();
+    for port in published {
+        if ports.iter().any(|p| p.port == port.container_port) {
+            warnings.push(format!(
+                "service {srv_name}: port {} is published twice, only the first is kept",
+                port.container_port
+            ));
+            continue;
+        }
+        let loopback = port
+            .host_ip
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback());
+        node_port |= !loopback;
+        ports.push(ServicePort {
+            port: port.container_port,
+            name: None,
+            target_port: Some(port.container_port),
+            protocol: "TCP".to_string(),
+            node_port: (!loopback).then_some(port.host_port),
+        });
+    }
+
+    Ok(Some(ServiceTask {
+        api_version: "v1".to_string(),
+        kind: "Service".to_string(),
+        metadata: metadata(name.to_string(), selector.clone()),
+        spec: common::ServiceSpec {
+            service_type: if node_port { "NodePort" } else { "ClusterIP" }.to_string(),
+            selector: Some(LabelSelector {
+                match_labels: selector,
+                match_expressions: vec![],
+            }),
+            ports,
+            cluster_ip: None,
+        },
+    }))
+}
+
+fn metadata(name: String, labels: HashMap<String, String>) -> ObjectMeta {
+    ObjectMeta {
+        name,
+        labels,
+        ..Default::default()
+    }
+}
+
+/// `name` as a valid object name, e.g. `my-db` for `my_db`.
+fn object_name(name: &str, warnings: &mut Vec<String>) -> String {
+    let object_name = name
+        .to_ascii_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .trim_matches('-')
+        .to_string();
+    if object_name != name {
+        warnings.push(format!("{name} is renamed to {object_name} in the cluster"));
+    }
+    object_name
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn convert_yaml(yaml: &str, base_dir: &Path) -> ClusterObjects {
+        let spec: ComposeSpec = serde_yaml::from_str(yaml).unwrap();
+        convert(&spec, "shop", base_dir).unwrap()
+    }
+
+    #[test]
+    fn test_convert_services() {
+        let dir = tempdir().unwrap();
+        let objects = convert_yaml(
+            r#"
+services:
+  web:
+    image: nginx:latest
+    ports: ["8080:80", "127.0.0.1:9090:9090"]
+    restart: on-failure
+    deploy:
+      replicas: 3
+    labels:
+      tier: frontend
+  db_main:
+    image: postgres:16
+    ports: ["127.0.0.1:5432:5432"]
+    depends_on: [web]
+  worker:
+    image: busybox
+"#,
+            dir.path(),
+        );
+
+        let names = objects
+            .deployments
+            .iter()
+            .map(|d| d.metadata.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["db-main", "web", "worker"]);
+
+        let web = &objects.deployments[1];
+        assert_eq!(web.spec.replicas, 3);
+        assert_eq!(web.spec.selector.match_labels[SERVICE_LABEL], "web");
+        assert_eq!(web.spec.selector.match_labels[PROJECT_LABEL], "shop");
+        assert_eq!(
+            web.spec.template.metadata.labels,
+            web.spec.selector.match_labels
+        );
+        assert_eq!(web.metadata.annotations["tier"], "frontend");
+        let container = &web.spec.template.spec.containers[0];
+        assert_eq!(container.image, "nginx:latest");
+        assert!(container.ports.iter().all(|p| p.host_port == 0));
+        assert_eq!(web.spec.template.spec.restart_policy, RestartPolicy::Always);
+
+        // worker publishes no port
+        assert_eq!(objects.services.len(), 2);
+        let db = &objects.services[0];
+        assert_eq!(db.spec.service_type, "ClusterIP");
+        assert_eq!(db.spec.ports[0].node_port, None);
+        let web = &objects.services[1];
+        assert_eq!(web.spec.service_type, "NodePort");
+        assert_eq!(web.spec.ports[0].port, 80);
+        assert_eq!(web.spec.ports[0].node_port, Some(8080));
+        assert_eq!(web.spec.ports[1].node_port, None);
+        assert_eq!(
+            web.spec.selector.as_ref().unwrap().match_labels,
+            objects.deployments[1].spec.selector.match_labels
+        );
+
+        let warnings = objects.warnings.join("\n");
+        assert!(warnings.contains("db_main is renamed to db-main"));
+        assert!(warnings.contains("service db_main: depends_on is ignored"));
+        assert!(warnings.contains("service web: restart on-failure is ignored"));
+    }
+
+    #[test]
+    fn test_convert_secrets_configs_and_volumes() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("db_password.txt"), "s3cret").unwrap();
+        std::fs::write(dir.path().join("nginx.conf"), "worker_processes 1;").unwrap();
+        let objects = convert_yaml(
+            r#"
+services:
+  web:
+    image: nginx
+    volumes: ["data:/var/lib/data", "./html:/usr/share/nginx/html"]
+    configs:
+      - source: nginx
+        target: /etc/nginx/nginx.conf
+    secrets: [db_password]
+configs:
+  nginx:
+    file: nginx.conf
+secrets:
+  db_password:
+    file: db_password.txt
+volumes:
+  data:
+    opts:
+      size: 10Gi
+  shared:
+    external: true
+"#,
+            dir.path(),
+        );
+
+        assert_eq!(objects.secrets.len(), 1);
+        assert_eq!(objects.secrets[0].metadata.name, "db-password");
+        assert_eq!(
+            objects.secrets[0].data["db_password"],
+            BASE64.encode("s3cret")
+        );
+
+        assert_eq!(objects.config_maps.len(), 1);
+        assert_eq!(
+            objects.config_maps[0].data["nginx.conf"],
+            "worker_processes 1;"
+        );
+
+        assert_eq!(objects.claims.len(), 1);
+        assert_eq!(objects.claims[0].metadata.name, "data");
+        assert_eq!(objects.claims[0].spec.resources.requests["storage"], "10Gi");
+
+        let warnings = objects.warnings.join("\n");
+        assert!(warnings.contains("external volume shared"));
+        assert!(warnings.contains("volume data:/var/lib/data is dropped"));
+        assert!(warnings.contains("volume ./html:/usr/share/nginx/html cannot be translated"));
+        assert!(warnings.contains("config nginx is not mounted"));
+        assert!(warnings.contains("secret db_password is not mounted"));
+
+        let yaml = objects.to_yaml().unwrap();
+        assert_eq!(yaml.matches("---\n").count(), 3);
+        assert!(yaml.contains("kind: PersistentVolumeClaim"));
+    }
+}
@@ -20,13 +20,17 @@ use libruntime::dns::run_local_dns;
 use crate::commands::{
     compose::{
         config::ConfigManager,
+        convert::{ClusterObjects, convert},
         network::NetworkManager,
-        spec::{ComposeSpec, ServiceSpec},
+        secret::SecretManager,
+        service::{apply_scale, enable_profiles, wait_healthy},
+        spec::{ComposeSpec, DependsOnCondition, ServiceSpec},
     },
     container::{ContainerRunner, remove_container},
     delete, list,
+    pod::TLSConnectionArgs,
 };
-use common::{ContainerSpec, Port};
+use common::{ContainerSpec, Port, RestartPolicy};
 use libruntime::rootpath;
 use libruntime::volume::{
     PatternType, VolumeManager, VolumeMetadata, VolumePattern, string_to_pattern,
@@ -34,8 +38,12 @@ use libruntime::volume::{
 type ComposeAction = Box<dyn FnOnce(&mut ComposeManager) -> Result<()>>;
 
 // pub mod config;
+pub mod cluster;
 pub mod config;
+pub mod convert;
 pub mod network;
+pub mod secret;
+pub mod service;
 pub mod spec;
 
 use clap::Args;
@@ -66,6 +74,55 @@ pub struct UpArgs {
 
     #[arg(long = "project-name", value_name = "PROJECT_NAME")]
     pub project_name: Option<String>,
+
+    /// Enable the services of a profile, in addition to COMPOSE_PROFILES
+    #[arg(long = "profile", value_name = "PROFILE")]
+    pub profiles: Vec<String>,
+
+    /// Run N containers of a service, overriding its deploy.replicas
+    #[arg(long = "scale", value_name = "SERVICE=N")]
+    pub scale: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    #[arg(short = 'f', value_name = "COMPOSE_YAML")]
+    pub compose_yaml: Option<String>,
+
+    #[arg(long = "project-name", value_name = "PROJECT_NAME")]
+    pub project_name: Option<String>,
+
+    /// Enable the services of a profile, in addition to COMPOSE_PROFILES
+    #[arg(long = "profile", value_name = "PROFILE")]
+    pub profiles: Vec<String>,
+
+    /// Write the objects to a file instead of stdout
+    #[arg(short = 'o', long = "output", value_name = "FILE")]
+    pub output: Option<String>,
+}
+
+#[derive(Args)]
+pub struct DeployArgs {
+    #[arg(short = 'f', value_name = "COMPOSE_YAML")]
+    pub compose_yaml: Option<String>,
+
+    #[arg(long = "project-name", value_name = "PROJECT_NAME")]
+    pub project_name: Option<String>,
+
+    /// Enable the services of a profile, in addition to COMPOSE_PROFILES
+    #[arg(long = "profile", value_name = "PROFILE")]
+    pub profiles: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "RKS_ADDRESS",
+        env = "RKS_ADDRESS",
+        required = false
+    )]
+    pub cluster: Option<String>,
+
+    #[clap(flatten)]
+    pub tls_cfg: TLSConnectionArgs,
 }
 
 #[derive(Subcommand)]
@@ -79,6 +136,12 @@ pub enum ComposeCommand {
     #[command(about = "List all the containers' state in compose application")]
     Ps(PsArgs),
 
+    #[command(about = "Translate a compose yaml into cluster Deployments and Services")]
+    Convert(ConvertArgs),
+
+    #[command(about = "Deploy a compose application to the cluster")]
+    Deploy(DeployArgs),
+
     #[command(about = "Start up DNS Server", hide = true)]
     Server,
 }
@@ -99,6 +162,9 @@ pub struct ComposeManager {
     network_manager: NetworkManager,
     config_manager: ConfigManager,
     startup_order: HashMap<String, usize>,
+    secret_manager: SecretManager,
+    /// the containers started so far, by service name
+    started: HashMap<String, Vec<ContainerSpec>>,
 }
 
 impl ComposeManager {
@@ -109,6 +175,7 @@ impl ComposeManager {
         let root_path = Path::new(&root_path).join("compose").join(&project_name);
 
         Ok(Self {
+            secret_manager: SecretManager::new(&root_path),
             root_path,
             network_manager: NetworkManager::new(project_name.clone()),
             config_manager: ConfigManager::new(),
@@ -116,6 +183,7 @@ impl ComposeManager {
             containers: vec![],
             volumes: vec![],
             startup_order: HashMap::new(),
+            started: HashMap::new(),
         })
     }
 
@@ -134,6 +202,7 @@ impl ComposeManager {
             remove_container(&self.root_path, container)?;
         }
         self.clean_up_network()?;
+        SecretManager::clean_up(&self.root_path)?;
 
         fs::remove_dir_all(&self.root_path)
             .map_err(|e| anyhow!("failed to delete the whole project: {}", e))
@@ -155,7 +224,10 @@ impl ComposeManager {
         let target_path = get_yml_path(compose_yaml)?;
 
         // read the yaml
-        let spec = parse_spec(target_path)?;
+        let mut spec = parse_spec(target_path.clone())?;
+
+        enable_profiles(&mut spec, &with_env_profiles(args.profiles))?;
+        apply_scale(&mut spec, &args.scale)?;
 
         self.handle_depends_on(&spec)?;
 
@@ -166,8 +238,18 @@ impl ComposeManager {
 
         let _ = &mut self.config_manager.handle(&spec);
 
+        // relative paths such as env_file are resolved against the compose file
+        let base_dir = target_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
         // start the whole containers
-        if let Err(err) = self.run(&spec) {
+        if let Err(err) = self
+            .secret_manager
+            .handle(&spec, &base_dir)
+            .and_then(|_| self.run(&spec, &base_dir))
+        {
             self.clean_up().ok();
             return Err(anyhow!("failed to up: {}", err));
         }
@@ -210,7 +292,7 @@ impl ComposeManager {
         Ok(spec)
     }
 
-    fn run(&mut self, spec: &ComposeSpec) -> Result<()> {
+    fn run(&mut self, spec: &ComposeSpec, base_dir: &Path) -> Result<()> {
         let network_mapping = self.network_manager.network_service_mapping();
 
         for (network_name, services) in network_mapping {
@@ -219,26 +301,7 @@ impl ComposeManager {
             ordered.sort_by_key(|(name, _)| self.startup_order.get(name).unwrap());
 
             for (srv_name, srv) in ordered.into_iter() {
-                let container_ports = map_port_style(srv.ports.clone())?;
-                let container_spec = ContainerSpec {
-                    name: srv
-                        .container_name
-                        .clone()
-                        // .map(|str| format!("compose_{}", str))
-                        .unwrap_or(self.generate_container_name(&srv_name)),
-                    image: srv.image.clone(),
-                    ports: container_ports,
-                    args: srv.command.clone(),
-                    resources: None,
-                    liveness_probe: None,
-                    readiness_probe: None,
-                    startup_probe: None,
-                    security_context: None,
-                    env: None,
-                    volume_mounts: None,
-                    command: None,
-                    working_dir: None,
-                };
+                self.wait_for_dependencies(&srv_name, &srv)?;
 
                 // handle the services volume name
                 let mut patterns: Result<Vec<VolumePattern>> = srv
@@ -280,44 +343,72 @@ impl ComposeManager {
                             e
                         )
                     })?;
+                if let Some(restart) = &srv.restart
+                    && srv.restart_policy()? != Some(RestartPolicy::Never)
+                {
+                    eprintln!(
+                        "warning: service {srv_name}: restart {restart} is not enforced, rkl compose doesn't restart the containers it started"
+                    );
+                }
                 let configs_mounts = self.config_manager.get_mounts_by_service(&srv_name);
+                let secrets_mounts = self.secret_manager.get_mounts_by_service(&srv_name);
 
-                let mut runner =
-                    ContainerRunner::from_spec(container_spec, Some(self.root_path.clone()))?;
-
-                runner.add_mounts(mounts);
-                runner.add_mounts(configs_mounts);
-
-                match runner.run() {
-                    std::result::Result::Ok(_) => {
-                        self.containers.push(runner.get_container_state()?);
-                    }
-                    Err(err) => {
-                        // create one container failed delete others
-                        println!(
-                            "container {} created failed: {}",
-                            runner.get_container_id()?,
-                            err
-                        );
-                        for state in &self.containers {
-                            if let Err(err) = delete(
-                                Delete {
-                                    container_id: state.id.clone(),
-                                    force: true,
-                                },
-                                self.root_path.clone(),
-                            ) {
-                                println!("container {} deleted failed: {}", state.id, err)
-                            } else {
-                                println!("container {} deleted during the rollback", state.id)
+                // every replica is registered under the service name in the dns server
+                for index in 1..=srv.replicas() {
+                    let container_name = srv
+                        .container_name
+                        .clone()
+                        // .map(|str| format!("compose_{}", str))
+                        .unwrap_or(self.generate_container_name(&srv_name, index));
+                    let container_spec = srv
+                        .container_spec(container_name, base_dir)
+                        .map_err(|e| anyhow!("Service [{}] is invalid: {}", srv_name, e))?;
+
+                    let mut runner = ContainerRunner::from_spec(
+                        container_spec.clone(),
+                        Some(self.root_path.clone()),
+                    )?;
+
+                    runner.add_mounts(mounts.clone());
+                    runner.add_mounts(configs_mounts.clone());
+                    runner.add_mounts(secrets_mounts.clone());
+                    runner.add_annotations(srv.labels());
+
+                    match runner.run() {
+                        std::result::Result::Ok(_) => {
+                            self.containers.push(runner.get_container_state()?);
+                            self.started
+                                .entry(srv_name.clone())
+                                .or_default()
+                                .push(container_spec);
+                        }
+                        Err(err) => {
+                            // create one container failed delete others
+                            println!(
+                                "container {} created failed: {}",
+                                runner.get_container_id()?,
+                                err
+                            );
+                            for state in &self.containers {
+                                if let Err(err) = delete(
+                                    Delete {
+                                        container_id: state.id.clone(),
+                                        force: true,
+                                    },
+                                    self.root_path.clone(),
+                                ) {
+                                    println!("container {} deleted failed: {}", state.id, err)
+                                } else {
+                                    println!("container {} deleted during the rollback", state.id)
+                                }
                             }
+                            return Err(err);
                         }
-                        return Err(err);
-                    }
-                };
-                self.network_manager
-                    .after_container_started(&srv_name, runner)
-                    .map_err(|e| anyhow!("network setup failed: {e}"))?;
+                    };
+                    self.network_manager
+                        .after_container_started(&srv_name, runner)
+                        .map_err(|e| anyhow!("network setup failed: {e}"))?;
+                }
             }
         }
         // return the compose application's state
@@ -325,6 +416,51 @@ impl ComposeManager {
         Ok(())
     }
 
+    /// The cluster objects of the compose application at `compose_yaml`.
+    fn cluster_objects(
+        &self,
+        compose_yaml: Option<String>,
+        profiles: Vec<String>,
+    ) -> Result<ClusterObjects> {
+        let target_path = get_yml_path(compose_yaml)?;
+        let mut spec = parse_spec(target_path.clone())?;
+        enable_profiles(&mut spec, &with_env_profiles(profiles))?;
+        let base_dir = target_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let objects = convert(&spec, &self.project_name, &base_dir)?;
+        for warning in &objects.warnings {
+            eprintln!("warning: {warning}");
+        }
+        Ok(objects)
+    }
+
+    fn convert(&self, args: ConvertArgs) -> Result<()> {
+        let yaml = self
+            .cluster_objects(args.compose_yaml, args.profiles)?
+            .to_yaml()?;
+        match args.output {
+            Some(path) => fs::write(&path, yaml)
+                .map_err(|e| anyhow!("failed to write the objects to {path}: {e}")),
+            None => {
+                print!("{yaml}");
+                Ok(())
+            }
+        }
+    }
+
+    fn deploy(&self, args: DeployArgs) -> Result<()> {
+        let objects = self.cluster_objects(args.compose_yaml, args.profiles)?;
+        let addr = args
+            .cluster
+            .ok_or_else(|| anyhow!("No RKS address provided. Set RKS_ADDRESS or use --cluster"))?;
+        let rt = Runtime::new()?;
+        rt.block_on(cluster::deploy(objects, &addr, args.tls_cfg))?;
+        println!("Project {} deployed to {addr}", self.project_name);
+        Ok(())
+    }
+
     fn ps(&self, ps_args: PsArgs) -> Result<()> {
         let PsArgs {
             compose_yaml,
@@ -359,16 +495,15 @@ impl ComposeManager {
         })
     }
 
-    /// if the `container_name` field is not supplied then create a random container_name
-    /// for the service container
-    pub fn generate_container_name(&self, srv_name: &String) -> String {
+    /// if the `container_name` field is not supplied then name the `index`th
+    /// replica of the service after the project and the service
+    pub fn generate_container_name(&self, srv_name: &String, index: u32) -> String {
         let root = self
             .root_path
             .file_name()
             .and_then(|os_str| os_str.to_str())
             .unwrap_or("unknown");
-        let timestamp = chrono::Utc::now().timestamp() % 1000; // persist 4 bits
-        format!("{root}_{srv_name}_{timestamp}")
+        format!("{root}_{srv_name}_{index}")
     }
 
     /// This function interate the named volumes in compose spec
@@ -431,11 +566,11 @@ impl ComposeManager {
         }
 
         for (srv_name, srv_spec) in &spec.services {
-            for dep in &srv_spec.depends_on {
-                if !graph.contains_key(dep) {
+            for (dep, _) in srv_spec.depends_on.services() {
+                if !graph.contains_key(&dep) {
                     return Err(anyhow!("{srv_name} depends on {dep} is not defined"));
                 }
-                graph.get_mut(dep).unwrap().push(srv_name.clone());
+                graph.get_mut(&dep).unwrap().push(srv_name.clone());
                 *in_degree.get_mut(srv_name).unwrap() += 1
             }
         }
@@ -473,6 +608,41 @@ impl ComposeManager {
         Ok(())
     }
 
+    /// Block until the services `srv_name` depends on meet their
+    /// `depends_on` condition. They have all been started before it.
+    fn wait_for_dependencies(&self, srv_name: &str, srv: &ServiceSpec) -> Result<()> {
+        for (dep, condition) in srv.depends_on.services() {
+            let Some(dep_specs) = self.started.get(&dep) else {
+                return Err(anyhow!("{srv_name} depends on {dep} which is not started"));
+            };
+            // every replica of the dependency must meet the condition
+            for dep_spec in dep_specs {
+                match condition {
+                    DependsOnCondition::ServiceStarted => {}
+                    DependsOnCondition::ServiceHealthy => {
+                        let probe = dep_spec.readiness_probe.as_ref().ok_or_else(|| {
+                            anyhow!(
+                                "{srv_name} waits for {dep} to be healthy, but {dep} has no healthcheck"
+                            )
+                        })?;
+                        println!("Waiting for {} to be healthy", dep_spec.name);
+                        wait_healthy(&self.root_path, &dep_spec.name, probe)
+                            .map_err(|e| anyhow!("{} is unhealthy: {e}", dep_spec.name))?;
+                    }
+                    DependsOnCondition::ServiceCompletedSuccessfully => {
+                        println!("Waiting for {} to complete", dep_spec.name);
+                        let (code, _, _) =
+                            crate::task::wait_for_exit(&self.root_path, &dep_spec.name)?;
+                        if code != 0 {
+                            return Err(anyhow!("{} exited with code {code}", dep_spec.name));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn start_dns_server(&self) -> Result<()> {
         let rt = Runtime::new()
             .map_err(|e| anyhow!("failed to init dns server's tokio runtime: {e}"))?;
@@ -559,6 +729,20 @@ pub fn get_yml_path(compose_yaml: Option<String>) -> Result<PathBuf> {
     Ok(target_path)
 }
 
+/// `profiles` and those of COMPOSE_PROFILES, a comma-separated list.
+fn with_env_profiles(mut profiles: Vec<String>) -> Vec<String> {
+    if let std::result::Result::Ok(env_profiles) = env::var("COMPOSE_PROFILES") {
+        profiles.extend(
+            env_profiles
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(String::from),
+        );
+    }
+    profiles
+}
+
 pub fn get_manager_from_name(project_name: Option<String>) -> Result<Box<ComposeManager>> {
     let manager = match project_name {
         Some(name) => ComposeManager::new(name),
@@ -589,6 +773,14 @@ pub fn compose_execute(command: ComposeCommand) -> Result<()> {
             let name = ps_args.project_name.clone();
             (name, Box::new(move |manager| manager.ps(ps_args)))
         }
+        ComposeCommand::Convert(convert_args) => {
+            let name = convert_args.project_name.clone();
+            (name, Box::new(move |manager| manager.convert(convert_args)))
+        }
+        ComposeCommand::Deploy(deploy_args) => {
+            let name = deploy_args.project_name.clone();
+            (name, Box::new(move |manager| manager.deploy(deploy_args)))
+        }
         ComposeCommand::Server => (None, Box::new(move |manager| manager.start_dns_server())),
     };
 
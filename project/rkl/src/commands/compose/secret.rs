@@ -0,0 +1,194 @@
+use crate::commands::compose::spec::{ComposeSpec, SecretSpec};
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use libruntime::cri::cri_api::Mount;
+use nix::errno::Errno;
+use nix::mount::{MntFlags, MsFlags, mount, umount2};
+
+/// Secrets are written to a tmpfs under the project's root path, so that
+/// they never reach the disk, and bind mounted read-only into the services
+/// granted them.
+pub struct SecretManager {
+    /// the tmpfs holding the secrets of the project
+    dir: PathBuf,
+    /// key: service name; value: (host path, container path)
+    service_secret_mounts: HashMap<String, Vec<(String, String)>>,
+}
+
+impl SecretManager {
+    pub fn new(root_path: &Path) -> Self {
+        Self {
+            dir: root_path.join("secrets"),
+            service_secret_mounts: HashMap::new(),
+        }
+    }
+
+    /// Read the secrets of `spec`, files being relative to `base_dir`, and
+    /// write the ones used by a service to the tmpfs.
+    pub fn handle(&mut self, spec: &ComposeSpec, base_dir: &Path) -> Result<()> {
+        let secrets = spec.secrets.clone().unwrap_or_default();
+        let mut written: HashMap<String, String> = HashMap::new();
+
+        for (srv_name, srv_spec) in &spec.services {
+            let mut mounts = vec![];
+            for secret in srv_spec.secrets.iter().flatten() {
+                let source = secret.source();
+                let host_path = match written.get(source) {
+                    Some(path) => path.clone(),
+                    None => {
+                        let secret_spec = secrets.get(source).ok_or_else(|| {
+                            anyhow!("service {srv_name} uses secret {source} which is not defined")
+                        })?;
+                        let content = read_secret(source, secret_spec, base_dir)?;
+                        let path = self.write(source, &content)?;
+                        written.insert(source.to_string(), path.clone());
+                        path
+                    }
+                };
+                mounts.push((host_path, secret.target()));
+            }
+
+            if !mounts.is_empty() {
+                self.service_secret_mounts.insert(srv_name.clone(), mounts);
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&self, name: &str, content: &[u8]) -> Result<String> {
+        if !self.dir.exists() {
+            fs::create_dir_all(&self.dir)?;
+            mount(
+                Some("tmpfs"),
+                &self.dir,
+                Some("tmpfs"),
+                MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC,
+                Some("mode=0700"),
+            )
+            .map_err(|e| anyhow!("failed to mount the secrets tmpfs: {e}"))?;
+        }
+        let path = self.dir.join(name);
+        write_secret(&path, content)?;
+        Ok(path.to_string_lossy().to_string())
+    }
+
+    pub fn get_mounts_by_service(&self, service: &str) -> Vec<Mount> {
+        self.service_secret_mounts
+            .get(service)
+            .unwrap_or(&vec![])
+            .iter()
+            .map(|(host_path, container_path)| Mount {
+                container_path: container_path.clone(),
+                host_path: host_path.clone(),
+                readonly: true,
+                selinux_relabel: false,
+                propagation: 0,
+                uid_mappings: vec![],
+                gid_mappings: vec![],
+                recursive_read_only: false,
+                image: None,
+                image_sub_path: "".to_string(),
+            })
+            .collect()
+    }
+
+    /// Unmount the secrets tmpfs of the project at `root_path`, if any.
+    pub fn clean_up(root_path: &Path) -> Result<()> {
+        let dir = root_path.join("secrets");
+        if dir.exists() {
+            // EINVAL: the tmpfs was never mounted
+            match umount2(&dir, MntFlags::MNT_DETACH) {
+                Ok(()) | Err(Errno::EINVAL) => {}
+                Err(e) => return Err(anyhow!("failed to unmount the secrets tmpfs: {e}")),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write `content` to `path`, readable by whatever user the service runs
+/// as, as with Docker.
+fn write_secret(path: &Path, content: &[u8]) -> Result<()> {
+    fs::write(path, content)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o444))?;
+    Ok(())
+}
+
+pub(crate) fn read_secret(name: &str, spec: &SecretSpec, base_dir: &Path) -> Result<Vec<u8>> {
+    match (&spec.file, &spec.environment) {
+        (Some(file), None) => {
+            let path = base_dir.join(file);
+            fs::read(&path)
+                .map_err(|e| anyhow!("failed to read secret {name} from {}: {e}", path.display()))
+        }
+        (None, Some(var)) => std::env::var(var)
+            .map(String::into_bytes)
+            .map_err(|_| anyhow!("secret {name}: environment variable {var} is not set")),
+        _ => Err(anyhow!(
+            "secret {name} must set exactly one of `file` and `environment`"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::commands::compose::spec::ServiceSecretSpec;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_read_secret() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("db_password.txt"), "s3cret").unwrap();
+        let file = SecretSpec {
+            file: Some("db_password.txt".to_string()),
+            environment: None,
+        };
+        assert_eq!(read_secret("db", &file, dir.path()).unwrap(), b"s3cret");
+
+        let both = SecretSpec {
+            file: Some("db_password.txt".to_string()),
+            environment: Some("DB_PASSWORD".to_string()),
+        };
+        assert!(read_secret("db", &both, dir.path()).is_err());
+        assert!(read_secret("db", &SecretSpec::default(), dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_write_secret_mode() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("db_password");
+        write_secret(&path, b"s3cret").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o444);
+        assert_eq!(fs::read(&path).unwrap(), b"s3cret");
+    }
+
+    #[test]
+    fn test_service_secret_target() {
+        let secrets: Vec<ServiceSecretSpec> = serde_yaml::from_str(
+            r#"
+- db_password
+- source: api_key
+  target: key.txt
+- source: tls_cert
+  target: /etc/tls/cert.pem
+"#,
+        )
+        .unwrap();
+        let targets = secrets.iter().map(|s| s.target()).collect::<Vec<_>>();
+        assert_eq!(
+            targets,
+            vec![
+                "/run/secrets/db_password",
+                "/run/secrets/key.txt",
+                "/etc/tls/cert.pem"
+            ]
+        );
+        assert_eq!(secrets[1].source(), "api_key");
+    }
+}
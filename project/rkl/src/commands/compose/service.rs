@@ -0,0 +1,720 @@
+//! Mapping of compose services onto the [`ContainerSpec`]s rkl runs.
+//!
+//! `environment` and `env_file` become the container's env, `entrypoint`
+//! its `command`, `user` its security context, `healthcheck` an exec
+//! readiness probe, `restart` its restart policy and
+//! `deploy.resources.limits` its resource limits.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+use common::{
+    ContainerRes, ContainerSpec, EnvVar, ExecAction, Probe, ProbeAction, Resource, RestartPolicy,
+    SecurityContext,
+};
+use tracing::debug;
+
+use crate::commands::compose::map_port_style;
+use crate::commands::compose::spec::{
+    ComposeSpec, HealthcheckSpec, ListOrDict, ResourceLimitsSpec, ServiceSpec, StringOrList,
+};
+use crate::commands::{Exec, exec};
+
+/// Compose's defaults for `healthcheck.interval`/`timeout` and `retries`.
+const HEALTHCHECK_PERIOD_SECONDS: u32 = 30;
+const HEALTHCHECK_RETRIES: u32 = 3;
+
+impl ServiceSpec {
+    /// The container running this service, called `name`. Relative
+    /// `env_file`s are resolved against `base_dir`, the directory of the
+    /// compose file.
+    pub fn container_spec(&self, name: String, base_dir: &Path) -> Result<ContainerSpec> {
+        let limits = self
+            .deploy
+            .as_ref()
+            .and_then(|deploy| deploy.resources.as_ref())
+            .and_then(|resources| resources.limits.as_ref());
+        Ok(ContainerSpec {
+            name,
+            image: self.image.clone(),
+            ports: map_port_style(self.ports.clone())?,
+            args: self.command.to_args()?,
+            resources: limits
+                .map(resource_limits)
+                .transpose()?
+                .map(|limits| ContainerRes {
+                    limits: Some(limits),
+                }),
+            liveness_probe: None,
+            readiness_probe: self
+                .healthcheck
+                .as_ref()
+                .map(healthcheck_probe)
+                .transpose()?
+                .flatten(),
+            startup_probe: None,
+            security_context: self.user.as_deref().map(security_context).transpose()?,
+            env: Some(self.env(base_dir)?).filter(|env| !env.is_empty()),
+            volume_mounts: None,
+            command: self
+                .entrypoint
+                .as_ref()
+                .map(StringOrList::to_args)
+                .transpose()?,
+            working_dir: self.working_dir.clone(),
+            lifecycle: None,
+            restart_policy: None,
+            image_pull_policy: None,
+        })
+    }
+
+    /// The env of the service: its `env_file`s in order, then `environment`.
+    /// A variable listed without a value takes the value it has in rkl's
+    /// environment, and is left out if unset there.
+    pub fn env(&self, base_dir: &Path) -> Result<Vec<EnvVar>> {
+        let mut vars: Vec<(String, String)> = Vec::new();
+        let mut set = |key: String, value: String| match vars.iter_mut().find(|(k, _)| *k == key) {
+            Some(var) => var.1 = value,
+            None => vars.push((key, value)),
+        };
+
+        let env_files = match &self.env_file {
+            Some(files) => files.to_list(),
+            None => vec![],
+        };
+        for file in env_files {
+            let path = base_dir.join(&file);
+            let iter = dotenvy::from_path_iter(&path)
+                .map_err(|e| anyhow!("failed to read env_file {}: {e}", path.display()))?;
+            for item in iter {
+                let (key, value) =
+                    item.map_err(|e| anyhow!("failed to parse env_file {}: {e}", path.display()))?;
+                set(key, value);
+            }
+        }
+
+        if let Some(environment) = &self.environment {
+            for (key, value) in environment.to_pairs() {
+                match value.or_else(|| std::env::var(&key).ok()) {
+                    Some(value) => set(key, value),
+                    None => debug!("environment variable {key} is unset, leaving it out"),
+                }
+            }
+        }
+
+        Ok(vars
+            .into_iter()
+            .map(|(name, value)| EnvVar {
+                name,
+                value: Some(value),
+                value_from: None,
+            })
+            .collect())
+    }
+
+    /// The policy `restart` asks for, only used to warn that it is not
+    /// honoured: `rkl compose up` doesn't restart the containers it
+    /// started, and the pods of a converted Deployment are always restarted.
+    pub fn restart_policy(&self) -> Result<Option<RestartPolicy>> {
+        self.restart.as_deref().map(restart_policy).transpose()
+    }
+
+    /// Number of containers to run, `deploy.replicas` or 1.
+    pub fn replicas(&self) -> u32 {
+        self.deploy
+            .as_ref()
+            .and_then(|deploy| deploy.replicas)
+            .unwrap_or(1)
+    }
+
+    /// The `labels` of the service, a label without value being empty.
+    pub fn labels(&self) -> HashMap<String, String> {
+        self.labels
+            .iter()
+            .flat_map(ListOrDict::to_pairs)
+            .map(|(key, value)| (key, value.unwrap_or_default()))
+            .collect()
+    }
+}
+
+impl StringOrList {
+    /// The strings of a list, or the single string.
+    pub fn to_list(&self) -> Vec<String> {
+        match self {
+            StringOrList::String(s) => vec![s.clone()],
+            StringOrList::List(list) => list.clone(),
+        }
+    }
+
+    /// The arguments of a command, a single string being split like a
+    /// shell would.
+    pub fn to_args(&self) -> Result<Vec<String>> {
+        match self {
+            StringOrList::String(s) => split_command(s),
+            StringOrList::List(list) => Ok(list.clone()),
+        }
+    }
+}
+
+impl ListOrDict {
+    /// The keys and values, `None` for a key given without value.
+    pub fn to_pairs(&self) -> Vec<(String, Option<String>)> {
+        match self {
+            ListOrDict::List(list) => list
+                .iter()
+                .map(|item| match item.split_once('=') {
+                    Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                    None => (item.clone(), None),
+                })
+                .collect(),
+            ListOrDict::Dict(dict) => {
+                let mut pairs = dict
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.as_ref().and_then(scalar)))
+                    .collect::<Vec<_>>();
+                pairs.sort();
+                pairs
+            }
+        }
+    }
+}
+
+/// Only keep the services which have no `profiles` or one of `profiles`.
+pub fn enable_profiles(spec: &mut ComposeSpec, profiles: &[String]) -> Result<()> {
+    let disabled = spec
+        .services
+        .iter()
+        .filter(|(_, srv)| {
+            !srv.profiles.is_empty() && !srv.profiles.iter().any(|p| profiles.contains(p))
+        })
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    for name in &disabled {
+        let srv = spec.services.remove(name).unwrap();
+        debug!(
+            "service {name} is disabled, its profiles are {:?}",
+            srv.profiles
+        );
+    }
+    for (name, srv) in &spec.services {
+        for (dep, _) in srv.depends_on.services() {
+            if disabled.contains(&dep) {
+                bail!("service {name} depends on {dep}, whose profiles are not enabled");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Override `deploy.replicas` with `scales` given as `service=N`. Services
+/// scaled to 0 are not started, and dropped from the `depends_on` of the
+/// others.
+pub fn apply_scale(spec: &mut ComposeSpec, scales: &[String]) -> Result<()> {
+    for scale in scales {
+        let (name, replicas) = scale
+            .split_once('=')
+            .ok_or_else(|| anyhow!("invalid scale {scale}, expected SERVICE=REPLICAS"))?;
+        let replicas = replicas
+            .parse::<u32>()
+            .map_err(|_| anyhow!("invalid number of replicas in {scale}"))?;
+        let srv = spec
+            .services
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("cannot scale {name}: no such service"))?;
+        srv.deploy.get_or_insert_default().replicas = Some(replicas);
+    }
+    let stopped = spec
+        .services
+        .iter()
+        .filter(|(_, srv)| srv.replicas() == 0)
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    for name in &stopped {
+        spec.services.remove(name);
+        debug!("service {name} is scaled to 0 and not started");
+        for srv in spec.services.values_mut() {
+            srv.depends_on.remove(name);
+        }
+    }
+    for (name, srv) in &spec.services {
+        if srv.replicas() > 1 && srv.container_name.is_some() {
+            bail!("service {name} sets container_name and cannot be scaled");
+        }
+        if srv.replicas() > 1 && !srv.ports.is_empty() {
+            bail!("service {name} publishes host ports and cannot be scaled");
+        }
+    }
+    Ok(())
+}
+
+/// Run the healthcheck `probe` in `container_id` until it succeeds, or
+/// fail once it failed `failureThreshold` times in a row.
+pub fn wait_healthy(root_path: &Path, container_id: &str, probe: &Probe) -> Result<()> {
+    let Some(ProbeAction::Exec(ExecAction { command })) = &probe.action else {
+        bail!("only exec healthchecks are supported");
+    };
+    let seconds = |s: Option<u32>, default: u32| Duration::from_secs(s.unwrap_or(default).into());
+    let period = seconds(probe.period_seconds, HEALTHCHECK_PERIOD_SECONDS);
+    let timeout = seconds(probe.timeout_seconds, HEALTHCHECK_PERIOD_SECONDS);
+    let retries = probe
+        .failure_threshold
+        .unwrap_or(HEALTHCHECK_RETRIES)
+        .max(1);
+
+    std::thread::sleep(seconds(probe.initial_delay_seconds, 0));
+    let mut failures = 0;
+    loop {
+        let err = match exec_with_timeout(root_path, container_id, command, timeout) {
+            Ok(0) => return Ok(()),
+            Ok(code) => anyhow!("healthcheck exited with code {code}"),
+            Err(e) => e,
+        };
+        failures += 1;
+        debug!("healthcheck of {container_id} failed ({failures}/{retries}): {err}");
+        if failures >= retries {
+            return Err(err.context(format!("healthcheck failed {failures} times")));
+        }
+        std::thread::sleep(period);
+    }
+}
+
+fn exec_with_timeout(
+    root_path: &Path,
+    container_id: &str,
+    command: &[String],
+    timeout: Duration,
+) -> Result<i32> {
+    let args = Exec {
+        pod_name: None,
+        container_id: container_id.to_string(),
+        command: command.to_vec(),
+        console_socket: None,
+        cwd: None,
+        env: Vec::new(),
+        tty: false,
+        user: None,
+        additional_gids: Vec::new(),
+        process: None,
+        detach: false,
+        pid_file: None,
+        process_label: None,
+        apparmor: None,
+        no_new_privs: false,
+        cap: Vec::new(),
+        preserve_fds: 0,
+        ignore_paused: false,
+        cgroup: None,
+    };
+    let root_path = root_path.to_path_buf();
+    // exec() blocks in waitpid(), wait for it on another thread so that a
+    // hanging healthcheck counts as a failure.
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(exec(args, root_path));
+    });
+    rx.recv_timeout(timeout)
+        .map_err(|_| anyhow!("healthcheck timed out after {}s", timeout.as_secs()))?
+}
+
+/// An exec probe running the `test` of `healthcheck`, `None` when the
+/// healthcheck is disabled or only inherited from the image.
+fn healthcheck_probe(healthcheck: &HealthcheckSpec) -> Result<Option<Probe>> {
+    if healthcheck.disable {
+        return Ok(None);
+    }
+    let shell = |cmd: &str| vec!["/bin/sh".to_string(), "-c".to_string(), cmd.to_string()];
+    let command = match &healthcheck.test {
+        None => return Ok(None),
+        Some(StringOrList::String(cmd)) => shell(cmd),
+        Some(StringOrList::List(test)) => match test.split_first() {
+            Some((kind, [])) if kind == "NONE" => return Ok(None),
+            Some((kind, [cmd])) if kind == "CMD-SHELL" => shell(cmd),
+            Some((kind, args)) if kind == "CMD" && !args.is_empty() => args.to_vec(),
+            _ => bail!("invalid healthcheck test {test:?}"),
+        },
+    };
+    Ok(Some(Probe {
+        action: Some(ProbeAction::Exec(ExecAction { command })),
+        initial_delay_seconds: duration_seconds(healthcheck.start_period.as_deref())?,
+        period_seconds: Some(
+            duration_seconds(healthcheck.interval.as_deref())?
+                .unwrap_or(HEALTHCHECK_PERIOD_SECONDS),
+        ),
+        timeout_seconds: Some(
+            duration_seconds(healthcheck.timeout.as_deref())?.unwrap_or(HEALTHCHECK_PERIOD_SECONDS),
+        ),
+        success_threshold: Some(1),
+        failure_threshold: Some(healthcheck.retries.unwrap_or(HEALTHCHECK_RETRIES)),
+    }))
+}
+
+/// A compose duration like `1m30s`, rounded up to whole seconds.
+fn duration_seconds(duration: Option<&str>) -> Result<Option<u32>> {
+    duration
+        .map(|d| {
+            let duration =
+                humantime::parse_duration(d).map_err(|e| anyhow!("invalid duration {d}: {e}"))?;
+            let seconds = duration.as_secs() + u64::from(duration.subsec_nanos() > 0);
+            u32::try_from(seconds).map_err(|_| anyhow!("duration {d} is too long"))
+        })
+        .transpose()
+}
+
+fn restart_policy(restart: &str) -> Result<RestartPolicy> {
+    let policy = restart
+        .split_once(':')
+        .map_or(restart, |(policy, _)| policy);
+    match policy {
+        "no" => Ok(RestartPolicy::Never),
+        "always" | "unless-stopped" => Ok(RestartPolicy::Always),
+        "on-failure" => Ok(RestartPolicy::OnFailure),
+        _ => bail!("invalid restart policy {restart}"),
+    }
+}
+
+/// `cpus` as millicores and `memory` in Ki, the units of [`Resource`].
+fn resource_limits(limits: &ResourceLimitsSpec) -> Result<Resource> {
+    let cpu = limits
+        .cpus
+        .as_ref()
+        .map(|cpus| {
+            scalar(cpus)
+                .and_then(|cpus| cpus.parse::<f64>().ok())
+                .filter(|cpus| *cpus > 0.0)
+                .map(|cpus| format!("{}m", (cpus * 1000.0).round() as i64))
+                .ok_or_else(|| anyhow!("invalid cpus limit {cpus:?}"))
+        })
+        .transpose()?;
+    let memory = limits.memory.as_deref().map(memory_limit).transpose()?;
+    Ok(Resource { cpu, memory })
+}
+
+/// A compose byte value like `512m` or `1.5gb` in Ki, rounded up.
+fn memory_limit(memory: &str) -> Result<String> {
+    let value = memory.trim().to_ascii_lowercase();
+    let value = value.strip_suffix('b').unwrap_or(&value);
+    let (number, unit) = match value.chars().last() {
+        Some('k') => (&value[..value.len() - 1], 1024.0),
+        Some('m') => (&value[..value.len() - 1], 1024.0 * 1024.0),
+        Some('g') => (&value[..value.len() - 1], 1024.0 * 1024.0 * 1024.0),
+        _ => (value, 1.0),
+    };
+    let bytes = number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|n| *n > 0.0)
+        .ok_or_else(|| anyhow!("invalid memory limit {memory}"))?
+        * unit;
+    Ok(format!("{}Ki", (bytes / 1024.0).ceil() as i64))
+}
+
+/// A numeric `uid[:gid]`.
+fn security_context(user: &str) -> Result<SecurityContext> {
+    let id = |id: &str| {
+        id.parse::<i64>()
+            .map_err(|_| anyhow!("user {user}: only a numeric uid[:gid] is supported"))
+    };
+    let (uid, gid) = match user.split_once(':') {
+        Some((uid, gid)) => (uid, Some(gid)),
+        None => (user, None),
+    };
+    Ok(SecurityContext {
+        run_as_user: Some(id(uid)?),
+        run_as_group: gid.map(id).transpose()?,
+        privileged: None,
+        allow_privilege_escalation: None,
+        capabilities: None,
+        read_only_root_filesystem: None,
+        seccomp_profile: None,
+        proc_mount: None,
+    })
+}
+
+fn scalar(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Split `command` into words like a shell, honouring quotes and
+/// backslash escapes but without expansions.
+fn split_command(command: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => bail!("unterminated quote in {command}"),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => bail!("unterminated quote in {command}"),
+                        },
+                        Some(c) => word.push(c),
+                        None => bail!("unterminated quote in {command}"),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    word.get_or_insert_default().push(c);
+                }
+            }
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn service(yaml: &str) -> ServiceSpec {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_container_spec_maps_service_fields() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("app.env"), "# app\nMODE=dev\nLEVEL=info\n").unwrap();
+        let srv = service(
+            r#"
+image: nginx:latest
+command: nginx -g "daemon off;"
+entrypoint: ["/docker-entrypoint.sh"]
+working_dir: /srv
+user: "1000:1000"
+env_file: app.env
+environment:
+  MODE: prod
+  REPLICAS: 2
+restart: on-failure:3
+deploy:
+  resources:
+    limits:
+      cpus: "0.5"
+      memory: 512M
+labels:
+  - tier=frontend
+healthcheck:
+  test: ["CMD", "curl", "-f", "http://localhost"]
+  interval: 1m30s
+  timeout: 500ms
+  retries: 5
+  start_period: 40s
+"#,
+        );
+        let spec = srv.container_spec("web".to_string(), dir.path()).unwrap();
+        assert_eq!(spec.args, vec!["nginx", "-g", "daemon off;"]);
+        assert_eq!(
+            spec.command,
+            Some(vec!["/docker-entrypoint.sh".to_string()])
+        );
+        assert_eq!(spec.working_dir.as_deref(), Some("/srv"));
+        let ctx = spec.security_context.unwrap();
+        assert_eq!(
+            (ctx.run_as_user, ctx.run_as_group),
+            (Some(1000), Some(1000))
+        );
+        let env = spec
+            .env
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.name, e.value.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            env,
+            vec![
+                ("MODE".to_string(), "prod".to_string()),
+                ("LEVEL".to_string(), "info".to_string()),
+                ("REPLICAS".to_string(), "2".to_string()),
+            ]
+        );
+        assert_eq!(spec.restart_policy, None);
+        assert_eq!(
+            srv.restart_policy().unwrap(),
+            Some(RestartPolicy::OnFailure)
+        );
+        let limits = spec.resources.unwrap().limits.unwrap();
+        assert_eq!(limits.cpu.as_deref(), Some("500m"));
+        assert_eq!(limits.memory.as_deref(), Some("524288Ki"));
+        assert_eq!(
+            srv.labels(),
+            HashMap::from([("tier".to_string(), "frontend".to_string())])
+        );
+
+        let probe = spec.readiness_probe.unwrap();
+        assert_eq!(
+            probe.action,
+            Some(ProbeAction::Exec(ExecAction {
+                command: vec!["curl".into(), "-f".into(), "http://localhost".into()],
+            }))
+        );
+        assert_eq!(probe.period_seconds, Some(90));
+        assert_eq!(probe.timeout_seconds, Some(1));
+        assert_eq!(probe.failure_threshold, Some(5));
+        assert_eq!(probe.initial_delay_seconds, Some(40));
+    }
+
+    #[test]
+    fn test_healthcheck_test_forms() {
+        let probe = |yaml: &str| {
+            let healthcheck: HealthcheckSpec = serde_yaml::from_str(yaml).unwrap();
+            healthcheck_probe(&healthcheck)
+                .unwrap()
+                .map(|probe| match probe.action {
+                    Some(ProbeAction::Exec(exec)) => exec.command,
+                    _ => panic!("not an exec probe"),
+                })
+        };
+        let shell = vec!["/bin/sh", "-c", "pg_isready -U postgres"];
+        assert_eq!(
+            probe(r#"test: ["CMD-SHELL", "pg_isready -U postgres"]"#).unwrap(),
+            shell
+        );
+        assert_eq!(probe("test: pg_isready -U postgres").unwrap(), shell);
+        assert_eq!(probe(r#"test: ["NONE"]"#), None);
+        assert_eq!(probe("test: [\"CMD\", \"true\"]\ndisable: true"), None);
+        assert_eq!(probe("interval: 10s"), None);
+    }
+
+    #[test]
+    fn test_limits_and_restart_policies() {
+        assert_eq!(memory_limit("1g").unwrap(), "1048576Ki");
+        assert_eq!(memory_limit("1.5GB").unwrap(), "1572864Ki");
+        assert_eq!(memory_limit("1000").unwrap(), "1Ki");
+        assert!(memory_limit("lots").is_err());
+        assert_eq!(restart_policy("no").unwrap(), RestartPolicy::Never);
+        assert_eq!(
+            restart_policy("unless-stopped").unwrap(),
+            RestartPolicy::Always
+        );
+        assert!(restart_policy("sometimes").is_err());
+        assert!(security_context("postgres").is_err());
+    }
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(
+            split_command(r#"sh -c 'echo "$HOME"' a\ b "c \"d\"""#).unwrap(),
+            vec!["sh", "-c", r#"echo "$HOME""#, "a b", r#"c "d""#]
+        );
+        assert!(split_command("echo 'oops").is_err());
+    }
+
+    #[test]
+    fn test_apply_scale() {
+        let mut spec: ComposeSpec = serde_yaml::from_str(
+            r#"
+services:
+  worker:
+    image: busybox
+    deploy:
+      replicas: 2
+  web:
+    image: nginx
+    ports: ["8080:80"]
+"#,
+        )
+        .unwrap();
+        apply_scale(&mut spec, &[]).unwrap();
+        assert_eq!(spec.services["worker"].replicas(), 2);
+        assert_eq!(spec.services["web"].replicas(), 1);
+
+        apply_scale(&mut spec, &["worker=3".to_string()]).unwrap();
+        assert_eq!(spec.services["worker"].replicas(), 3);
+        assert!(apply_scale(&mut spec, &["db=2".to_string()]).is_err());
+        assert!(apply_scale(&mut spec, &["worker=two".to_string()]).is_err());
+        assert!(apply_scale(&mut spec, &["web=2".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_apply_scale_to_zero() {
+        let mut spec: ComposeSpec = serde_yaml::from_str(
+            r#"
+services:
+  db:
+    image: postgres
+    deploy:
+      replicas: 0
+  cache:
+    image: redis
+  web:
+    image: nginx
+    depends_on:
+      db:
+        condition: service_healthy
+      cache:
+        condition: service_started
+  worker:
+    image: busybox
+    depends_on: [db, cache]
+"#,
+        )
+        .unwrap();
+        apply_scale(&mut spec, &["cache=0".to_string()]).unwrap();
+        assert!(!spec.services.contains_key("db"));
+        assert!(!spec.services.contains_key("cache"));
+        assert!(spec.services["web"].depends_on.services().is_empty());
+        assert!(spec.services["worker"].depends_on.services().is_empty());
+    }
+
+    #[test]
+    fn test_enable_profiles() {
+        let mut spec: ComposeSpec = serde_yaml::from_str(
+            r#"
+services:
+  web:
+    image: nginx
+  debug:
+    image: busybox
+    profiles: [debug]
+  tools:
+    image: busybox
+    profiles: [tools]
+    depends_on:
+      db:
+        condition: service_healthy
+  db:
+    image: postgres
+"#,
+        )
+        .unwrap();
+        enable_profiles(&mut spec, &["tools".to_string()]).unwrap();
+        let mut names = spec.services.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["db", "tools", "web"]);
+
+        spec.services.get_mut("web").unwrap().profiles = vec!["frontend".to_string()];
+        spec.services.get_mut("db").unwrap().profiles = vec!["backend".to_string()];
+        assert!(enable_profiles(&mut spec, &["tools".to_string()]).is_err());
+    }
+}
@@ -20,7 +20,7 @@ pub struct ComposeSpec {
     #[serde(default)]
     pub networks: Option<HashMap<String, NetworkSpec>>,
     #[serde(default)]
-    pub secrets: Option<SecretSpec>,
+    pub secrets: Option<HashMap<String, SecretSpec>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -42,16 +42,192 @@ pub struct ServiceSpec {
     pub volumes: Vec<String>,
 
     #[serde(default)]
-    pub command: Vec<String>,
+    pub command: StringOrList,
+
+    #[serde(default)]
+    pub entrypoint: Option<StringOrList>,
+
+    #[serde(default)]
+    pub working_dir: Option<String>,
+
+    /// `uid[:gid]`, user and group names are not resolved.
+    #[serde(default)]
+    pub user: Option<String>,
+
+    #[serde(default)]
+    pub environment: Option<ListOrDict>,
+
+    /// Files of `KEY=VALUE` lines, relative to the compose file. Values of
+    /// `environment` take precedence.
+    #[serde(default)]
+    pub env_file: Option<StringOrList>,
+
+    #[serde(default)]
+    pub healthcheck: Option<HealthcheckSpec>,
+
+    /// `no`, `always`, `on-failure[:max-retries]` or `unless-stopped`.
+    #[serde(default)]
+    pub restart: Option<String>,
+
+    #[serde(default)]
+    pub deploy: Option<DeploySpec>,
+
+    #[serde(default)]
+    pub labels: Option<ListOrDict>,
+
+    /// The service only starts when one of these profiles is enabled.
+    #[serde(default)]
+    pub profiles: Vec<String>,
 
     #[serde(default)]
     pub configs: Option<Vec<ConfigSpec>>,
 
     #[serde(default)]
-    pub secrets: Option<Vec<String>>,
+    pub secrets: Option<Vec<ServiceSecretSpec>>,
 
     #[serde(default)]
-    pub depends_on: Vec<String>,
+    pub depends_on: DependsOn,
+}
+
+/// A value given either as a single string or as a list of strings.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum StringOrList {
+    String(String),
+    List(Vec<String>),
+}
+
+impl Default for StringOrList {
+    fn default() -> Self {
+        StringOrList::List(vec![])
+    }
+}
+
+/// `KEY=VALUE` strings or a `KEY: VALUE` mapping, as used by `environment`
+/// and `labels`. A key without value is `None`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ListOrDict {
+    List(Vec<String>),
+    Dict(HashMap<String, Option<serde_yaml::Value>>),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HealthcheckSpec {
+    /// `["CMD", args...]`, `["CMD-SHELL", command]`, `["NONE"]` or a shell
+    /// command.
+    #[serde(default)]
+    pub test: Option<StringOrList>,
+
+    #[serde(default)]
+    pub interval: Option<String>,
+
+    #[serde(default)]
+    pub timeout: Option<String>,
+
+    #[serde(default)]
+    pub retries: Option<u32>,
+
+    #[serde(default)]
+    pub start_period: Option<String>,
+
+    #[serde(default)]
+    pub start_interval: Option<String>,
+
+    #[serde(default)]
+    pub disable: bool,
+}
+
+/// The `deploy` section, of which only the replicas and resource limits
+/// are applied.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DeploySpec {
+    /// Number of containers of the service, 1 by default. Overridden by
+    /// `rkl compose up --scale`.
+    #[serde(default)]
+    pub replicas: Option<u32>,
+
+    #[serde(default)]
+    pub resources: Option<DeployResourcesSpec>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DeployResourcesSpec {
+    #[serde(default)]
+    pub limits: Option<ResourceLimitsSpec>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ResourceLimitsSpec {
+    /// Fraction of CPUs, e.g. `0.5` or `"2"`.
+    #[serde(default)]
+    pub cpus: Option<serde_yaml::Value>,
+
+    /// Bytes with an optional `b`, `k`, `m` or `g` unit, e.g. `512M`.
+    #[serde(default)]
+    pub memory: Option<String>,
+}
+
+/// Services a service depends on, as a list or with conditions.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum DependsOn {
+    List(Vec<String>),
+    Map(HashMap<String, DependsOnSpec>),
+}
+
+impl Default for DependsOn {
+    fn default() -> Self {
+        DependsOn::List(vec![])
+    }
+}
+
+impl DependsOn {
+    /// The services depended on and the condition they must meet.
+    pub fn services(&self) -> Vec<(String, DependsOnCondition)> {
+        match self {
+            DependsOn::List(names) => names
+                .iter()
+                .map(|name| (name.clone(), DependsOnCondition::ServiceStarted))
+                .collect(),
+            DependsOn::Map(deps) => deps
+                .iter()
+                .map(|(name, dep)| (name.clone(), dep.condition))
+                .collect(),
+        }
+    }
+
+    /// Stop depending on the service `name`.
+    pub fn remove(&mut self, name: &str) {
+        match self {
+            DependsOn::List(names) => names.retain(|n| n != name),
+            DependsOn::Map(deps) => {
+                deps.remove(name);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DependsOnSpec {
+    #[serde(default)]
+    pub condition: DependsOnCondition,
+
+    #[serde(default)]
+    pub restart: Option<bool>,
+
+    #[serde(default)]
+    pub required: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DependsOnCondition {
+    #[default]
+    ServiceStarted,
+    ServiceHealthy,
+    ServiceCompletedSuccessfully,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -88,9 +264,57 @@ pub struct ConfigsSpec {
     pub file: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A secret read from a file or from an environment variable of rkl.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 #[serde(deny_unknown_fields)]
-pub struct SecretSpec {}
+pub struct SecretSpec {
+    /// Path of the secret, relative to the compose file.
+    #[serde(default)]
+    pub file: Option<String>,
+
+    /// Name of the environment variable holding the secret.
+    #[serde(default)]
+    pub environment: Option<String>,
+}
+
+/// A secret granted to a service, by name or with the path to mount it at.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ServiceSecretSpec {
+    Name(String),
+    Long {
+        source: String,
+        /// File name under `/run/secrets`, or an absolute path. Defaults to
+        /// `source`.
+        #[serde(default)]
+        target: Option<String>,
+    },
+}
+
+impl ServiceSecretSpec {
+    pub fn source(&self) -> &str {
+        match self {
+            ServiceSecretSpec::Name(name) => name,
+            ServiceSecretSpec::Long { source, .. } => source,
+        }
+    }
+
+    /// The path of the secret in the container.
+    pub fn target(&self) -> String {
+        let target = match self {
+            ServiceSecretSpec::Long {
+                target: Some(target),
+                ..
+            } => target,
+            _ => self.source(),
+        };
+        if target.starts_with('/') {
+            target.to_string()
+        } else {
+            format!("/run/secrets/{target}")
+        }
+    }
+}
 
 #[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
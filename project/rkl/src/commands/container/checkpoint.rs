@@ -0,0 +1,219 @@
+use crate::commands::container::{
+    handle_image_typ, handle_image_with_overlay, remove_container_network, setup_network_conf,
+};
+use crate::commands::{delete, load_container};
+use crate::config::OVERLAY_CONFIG;
+use crate::task::get_cni;
+use anyhow::{Result, anyhow};
+use clap::Args;
+use common::ContainerSpec;
+use libcontainer::syscall::syscall::create_syscall;
+use liboci_cli::Delete;
+use libruntime::checkpoint::{self, CheckpointManifest, CheckpointOptions, RestoreTarget};
+use libruntime::cri::ContainerStdio;
+use libruntime::rootpath;
+use oci_spec::runtime::{RootBuilder, Spec};
+use rkforge::commands::container::rootfs_mount::RootfsMount;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct CheckpointArgs {
+    /// Keep the container running after the checkpoint
+    #[arg(long)]
+    pub leave_running: bool,
+
+    /// Checkpoint established TCP connections
+    #[arg(long)]
+    pub tcp_established: bool,
+
+    /// Checkpoint file locks
+    #[arg(long)]
+    pub file_locks: bool,
+
+    /// Checkpoint unix sockets connected to processes outside the container
+    #[arg(long)]
+    pub ext_unix_sk: bool,
+}
+
+impl From<CheckpointArgs> for CheckpointOptions {
+    fn from(args: CheckpointArgs) -> Self {
+        CheckpointOptions {
+            leave_running: args.leave_running,
+            tcp_established: args.tcp_established,
+            file_locks: args.file_locks,
+            ext_unix_sk: args.ext_unix_sk,
+        }
+    }
+}
+
+/// Scratch directory a checkpoint is dumped into or unpacked to, under the
+/// runtime root so that it lives on the same filesystem as the bundles.
+/// Removed when dropped.
+pub struct CheckpointDir(PathBuf);
+
+impl CheckpointDir {
+    pub fn new(name: &str) -> Result<Self> {
+        let root_path = rootpath::determine(None, &*create_syscall())?;
+        let path = root_path
+            .join("checkpoints")
+            .join(format!("{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path)
+            .map_err(|e| anyhow!("failed to create {}: {e}", path.display()))?;
+        Ok(CheckpointDir(path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for CheckpointDir {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.0) {
+            warn!("Failed to remove {}: {e}", self.0.display());
+        }
+    }
+}
+
+/// command checkpoint
+pub fn checkpoint_container(
+    container_name: &str,
+    options: CheckpointOptions,
+    archive: &Path,
+) -> Result<()> {
+    checkpoint::check_criu()?;
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let container = load_container(&root_path, container_name)?;
+    let pid = container.pid();
+    let bundle_path = container.bundle().to_path_buf();
+
+    let dir = CheckpointDir::new(container_name)?;
+    checkpoint::dump(&container, dir.path(), &options)?;
+    checkpoint::export(dir.path(), archive)?;
+    info!(
+        "Container {container_name} checkpointed to {}",
+        archive.display()
+    );
+
+    if options.leave_running {
+        return Ok(());
+    }
+    // The processes are gone, their network namespace with them.
+    if let Some(pid) = pid
+        && let Err(e) = remove_container_network(pid)
+    {
+        warn!("Failed to remove the network of container {container_name}: {e}");
+    }
+    delete(
+        Delete {
+            container_id: container_name.to_string(),
+            force: true,
+        },
+        root_path,
+    )?;
+    if let Some(rootfs_mount) = RootfsMount::load(&bundle_path)?
+        && let Err(e) = rootfs_mount.stop()
+    {
+        warn!("Failed to stop rootfs overlay mount for {container_name}: {e}");
+    }
+    Ok(())
+}
+
+/// command restore
+///
+/// The container is rebuilt from the image recorded in the checkpoint, with
+/// the checkpointed rootfs changes applied on top.
+pub fn restore_container(container_name: Option<String>, archive: &Path) -> Result<()> {
+    checkpoint::check_criu()?;
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let dir = CheckpointDir::new("restore")?;
+    checkpoint::import(archive, dir.path())?;
+    let manifest = CheckpointManifest::load(dir.path())?;
+    let container_id = container_name.unwrap_or_else(|| manifest.container_id.clone());
+    if load_container(&root_path, &container_id).is_ok() {
+        return Err(anyhow!("container {container_id} already exists"));
+    }
+    let image = manifest.image.clone().ok_or_else(|| {
+        anyhow!("the checkpoint doesn't record the image of the container, it can't be restored")
+    })?;
+
+    let container_spec = ContainerSpec {
+        name: container_id.clone(),
+        image,
+        ports: vec![],
+        args: vec![],
+        resources: None,
+        liveness_probe: None,
+        readiness_probe: None,
+        startup_probe: None,
+        security_context: None,
+        env: None,
+        volume_mounts: None,
+        command: None,
+        working_dir: None,
+        lifecycle: None,
+        restart_policy: None,
+        image_pull_policy: None,
+    };
+    let (builder, bundle_path, rootfs_mount) = if OVERLAY_CONFIG.use_overlay_rootfs {
+        handle_image_with_overlay(&container_spec)?
+    } else {
+        let (b, bp) = handle_image_typ(&container_spec)?;
+        (b, bp, None)
+    };
+    if builder.is_none() {
+        return Err(anyhow!(
+            "image {} of container {container_id} is not an image reference",
+            container_spec.image
+        ));
+    }
+    let bundle = PathBuf::from(&bundle_path);
+
+    // The checkpointed spec, pointed at the new rootfs
+    let mut spec = Spec::load(dir.path().join(checkpoint::CONFIG_FILE))?;
+    let rootfs = if rootfs_mount.is_some() {
+        "merged"
+    } else {
+        "rootfs"
+    };
+    let readonly = spec
+        .root()
+        .as_ref()
+        .and_then(|root| root.readonly())
+        .unwrap_or(false);
+    spec.set_root(Some(
+        RootBuilder::default()
+            .path(rootfs)
+            .readonly(readonly)
+            .build()?,
+    ));
+    spec.save(bundle.join(checkpoint::CONFIG_FILE))?;
+    // Through the mounted overlay when there is one, which turns the
+    // whiteouts of the diff into removals.
+    checkpoint::apply_rootfs_diff(dir.path(), &bundle.join(rootfs), false)?;
+
+    let target = RestoreTarget {
+        container_id: &container_id,
+        root_path: &root_path,
+        bundle: &bundle,
+        pause_pid: None,
+        stdio: ContainerStdio::default(),
+    };
+    let pid = checkpoint::restore(dir.path(), target)?;
+
+    if manifest.empty_netns {
+        setup_network_conf()?;
+        let mut cni = get_cni()?;
+        cni.load_default_conf();
+        cni.setup(format!("{pid}"), format!("/proc/{pid}/ns/net"))
+            .map_err(|e| anyhow!("Failed to add CNI network: {}", e))?;
+    }
+    info!(
+        "Container {container_id} restored from {}",
+        archive.display()
+    );
+    Ok(())
+}
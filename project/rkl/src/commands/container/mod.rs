@@ -20,6 +20,7 @@ use libcontainer::{
 };
 use liboci_cli::{Create, Delete, List, Start};
 use libruntime::cri::config::ContainerConfigBuilder;
+use libruntime::cri::cri_api::KeyValue;
 use libruntime::oci;
 use libruntime::rootpath;
 use libruntime::utils::{
@@ -31,10 +32,13 @@ use libruntime::{
     utils::ImagePuller,
 };
 use nix::unistd::Pid;
-use oci_spec::runtime::{LinuxBuilder, ProcessBuilder, RootBuilder, Spec, get_default_namespaces};
+use oci_spec::runtime::{
+    LinuxBuilder, ProcessBuilder, RootBuilder, Spec, UserBuilder, get_default_namespaces,
+};
 use oci_spec::runtime::{Mount as OciMount, MountBuilder};
 use rkforge::commands::container::rootfs_mount::RootfsMount;
 use std::{
+    collections::HashMap,
     env,
     io::{self, BufWriter},
 };
@@ -48,6 +52,10 @@ use std::{
 use tabwriter::TabWriter;
 use tracing::{debug, error, info, warn};
 
+pub mod checkpoint;
+
+use checkpoint::CheckpointArgs;
+
 struct RkforgeImagePuller {}
 
 #[async_trait::async_trait]
@@ -107,6 +115,30 @@ pub enum ContainerCommand {
     },
 
     Exec(Box<ExecContainer>),
+
+    #[command(about = "Checkpoint a running container with CRIU into an archive")]
+    Checkpoint {
+        #[arg(value_name = "CONTAINER_NAME")]
+        container_name: String,
+
+        /// Archive to write the checkpoint to
+        #[arg(long, value_name = "FILE")]
+        export: PathBuf,
+
+        #[clap(flatten)]
+        args: CheckpointArgs,
+    },
+
+    #[command(about = "Restore a container from a checkpoint archive")]
+    Restore {
+        /// Name of the restored container, defaults to the checkpointed one
+        #[arg(value_name = "CONTAINER_NAME")]
+        container_name: Option<String>,
+
+        /// Archive to read the checkpoint from
+        #[arg(long, value_name = "FILE")]
+        import: PathBuf,
+    },
 }
 
 pub struct ContainerRunner {
@@ -119,6 +151,8 @@ pub struct ContainerRunner {
     ip: Option<IpAddr>,
     /// Persistent overlay rootfs mount (None when disabled or using traditional cp mode)
     rootfs_mount: Option<RootfsMount>,
+    /// Image reference the bundle was built from
+    image_ref: Option<String>,
 }
 
 impl ContainerRunner {
@@ -140,8 +174,9 @@ impl ContainerRunner {
             let (b, bp) = handle_image_typ(&spec)?;
             (b, bp, None)
         };
+        let mut image_ref = None;
         if builder.is_some() {
-            spec.image = bundle_path;
+            image_ref = Some(std::mem::replace(&mut spec.image, bundle_path));
         }
 
         Ok(ContainerRunner {
@@ -156,6 +191,7 @@ impl ContainerRunner {
             volumes: None,
             ip: None,
             rootfs_mount,
+            image_ref,
         })
     }
 
@@ -176,8 +212,9 @@ impl ContainerRunner {
             let (b, bp) = handle_image_typ(&container_spec)?;
             (b, bp, None)
         };
+        let mut image_ref = None;
         if builder.is_some() {
-            container_spec.image = bundle_path;
+            image_ref = Some(std::mem::replace(&mut container_spec.image, bundle_path));
         }
 
         let container_id = container_spec.name.clone();
@@ -191,6 +228,7 @@ impl ContainerRunner {
             volumes,
             ip: None,
             rootfs_mount,
+            image_ref,
         })
     }
 
@@ -211,6 +249,9 @@ impl ContainerRunner {
                 volume_mounts: None,
                 command: None,
                 working_dir: None,
+                lifecycle: None,
+                restart_policy: None,
+                image_pull_policy: None,
             },
             config: None,
             container_id: container_id.to_string(),
@@ -221,6 +262,7 @@ impl ContainerRunner {
             volumes: None,
             ip: None,
             rootfs_mount: None,
+            image_ref: None,
         })
     }
 
@@ -255,6 +297,11 @@ impl ContainerRunner {
         self.config_builder.mounts(mounts);
     }
 
+    /// Annotations set on the OCI spec of the container, e.g. compose labels.
+    pub fn add_annotations(&mut self, annotations: HashMap<String, String>) {
+        self.config_builder.annotations.extend(annotations);
+    }
+
     pub fn create(&mut self) -> Result<()> {
         // create container_config
         self.build_config()?;
@@ -306,11 +353,23 @@ impl ContainerRunner {
     pub fn build_config(&mut self) -> Result<()> {
         let _ = self.handle_volumes()?;
 
-        let config = self
-            .config_builder
-            .container_spec(self.spec.clone())?
-            .clone()
-            .build();
+        let builder = self.config_builder.container_spec(self.spec.clone())?;
+        // `command` replaces the entrypoint of the image, `args` its cmd
+        if let Some(command) = &self.spec.command {
+            builder.args = Some(command.iter().chain(&self.spec.args).cloned().collect());
+        }
+        if self.spec.working_dir.is_some() {
+            builder.work_dir(&self.spec.working_dir);
+        }
+        builder
+            .envs
+            .extend(self.spec.env.iter().flatten().filter_map(|env| {
+                env.value.as_ref().map(|value| KeyValue {
+                    key: env.name.clone(),
+                    value: value.clone(),
+                })
+            }));
+        let config = builder.clone().build();
 
         debug!("After building config: {:#?}", config);
         self.config = Some(config);
@@ -362,8 +421,24 @@ impl ContainerRunner {
         process.set_capabilities(Some(capabilities));
         process.set_terminal(Some(false));
         process.set_args(Some(config.args.clone()));
-        // TODO: env
-        // process.set_env(Some(config.envs));
+        // the variables of the container spec come after the image's, and win
+        let mut env: Vec<String> = Vec::new();
+        for kv in &config.envs {
+            let prefix = format!("{}=", kv.key);
+            env.retain(|e| !e.starts_with(&prefix));
+            env.push(format!("{}={}", kv.key, kv.value));
+        }
+        process.set_env(Some(env));
+        if let Some(ctx) = &self.spec.security_context
+            && let Some(uid) = ctx.run_as_user
+        {
+            let id = |id: i64| u32::try_from(id).map_err(|_| anyhow!("invalid user id {id}"));
+            let user = UserBuilder::default()
+                .uid(id(uid)?)
+                .gid(id(ctx.run_as_group.unwrap_or(0))?)
+                .build()?;
+            process.set_user(user);
+        }
 
         spec.set_process(Some(process));
 
@@ -372,6 +447,14 @@ impl ContainerRunner {
         mounts.extend(existing_mounts);
         spec.set_mounts(Some(mounts));
 
+        let mut annotations = config.annotations.clone();
+        if let Some(image_ref) = &self.image_ref {
+            annotations.insert(oci::IMAGE_ANNOTATION.to_string(), image_ref.clone());
+        }
+        if !annotations.is_empty() {
+            spec.set_annotations(Some(annotations));
+        }
+
         Ok(spec)
     }
 
@@ -813,6 +896,7 @@ pub fn handle_image_typ(
             builder.envs_from_image_config(config.env());
             // set work_dir
             builder.work_dir(config.working_dir());
+            builder.stop_signal_from_image_config(config.stop_signal());
             // builder.users(config.user());
         }
         return Ok((Some(builder), bundle_path));
@@ -856,6 +940,7 @@ fn handle_image_with_overlay(
             builder.args_from_image_config(config.entrypoint(), config.cmd());
             builder.envs_from_image_config(config.env());
             builder.work_dir(config.working_dir());
+            builder.stop_signal_from_image_config(config.stop_signal());
         }
 
         return Ok((Some(builder), bundle_path, Some(rootfs_mount)));
@@ -884,6 +969,15 @@ pub fn container_execute(cmd: ContainerCommand) -> Result<()> {
                 exec_container((*exec).clone(), exec.root_path.as_ref().map(PathBuf::from))?;
             std::process::exit(exit_code)
         }
+        ContainerCommand::Checkpoint {
+            container_name,
+            export,
+            args,
+        } => checkpoint::checkpoint_container(&container_name, args.into(), &export),
+        ContainerCommand::Restore {
+            container_name,
+            import,
+        } => checkpoint::restore_container(container_name, &import),
     }
 }
 
@@ -911,6 +1005,9 @@ mod test {
             volume_mounts: None,
             command: None,
             working_dir: None,
+            lifecycle: None,
+            restart_policy: None,
+            image_pull_policy: None,
         };
         let runner = ContainerRunner::from_spec(spec.clone(), None).unwrap();
         assert_eq!(runner.container_id, "demo1");
@@ -943,6 +1040,9 @@ mod test {
                 volume_mounts: None,
                 command: None,
                 working_dir: None,
+                lifecycle: None,
+                restart_policy: None,
+                image_pull_policy: None,
             },
             None,
         )
@@ -28,12 +28,17 @@ use chrono::{DateTime, Local};
 use tabwriter::TabWriter;
 use tracing::info;
 
+use crate::commands::pod::TLSConnectionArgs;
+
 pub mod compose;
 pub mod container;
 pub mod deployment;
+pub mod pdb;
 pub mod pod;
 pub mod replicaset;
+pub mod secret;
 pub mod service;
+pub mod top;
 pub mod volume;
 
 pub(crate) fn format_duration(duration: chrono::Duration) -> String {
@@ -216,7 +221,7 @@ pub fn exec(args: Exec, root_path: PathBuf) -> Result<i32> {
 
     match waitpid(pid, None)? {
         WaitStatus::Exited(_, status) => Ok(status),
-        WaitStatus::Signaled(_, sig, _) => Ok(sig as i32),
+        WaitStatus::Signaled(_, sig, _) => Ok(128 + sig as i32),
         _ => Ok(0),
     }
 }
@@ -342,6 +347,21 @@ pub struct ExecPod {
     #[clap(required = false)]
     pub command: Vec<String>,
 
+    /// Pass stdin to the command, only used with --cluster
+    #[clap(short = 'i', long)]
+    pub stdin: bool,
+
+    #[arg(
+        long,
+        value_name = "RKS_ADDRESS",
+        env = "RKS_ADDRESS",
+        required = false
+    )]
+    pub cluster: Option<String>,
+
+    #[clap(flatten)]
+    pub tls_cfg: TLSConnectionArgs,
+
     #[clap(flatten)]
     pub base: ExecBase,
 }
@@ -0,0 +1,150 @@
+use anyhow::{Result, anyhow};
+use common::{IntOrPercentage, PodDisruptionBudget, RksMessage};
+use std::fs::File;
+use std::io::{self, Write};
+use tabwriter::TabWriter;
+
+use crate::commands::format_duration;
+use crate::commands::pod::TLSConnectionArgs;
+use crate::quic::client::{Cli, QUICClient};
+
+/// Create a new PodDisruptionBudget
+pub async fn create_pdb(pdb_yaml: &str, addr: &str, tls_cfg: TLSConnectionArgs) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+    println!("RKL connected to RKS at {addr}");
+
+    let pdb = pdb_from_path(pdb_yaml)?;
+    let pdb_name = pdb.metadata.name.clone();
+
+    cli.send_msg(&RksMessage::CreatePodDisruptionBudget(pdb))
+        .await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::Ack => {
+            println!("poddisruptionbudget/{pdb_name} created");
+            Ok(())
+        }
+        RksMessage::Error(err) => Err(anyhow!("Failed to create poddisruptionbudget: {}", err)),
+        msg => Err(anyhow!("Unexpected response: {:?}", msg)),
+    }
+}
+
+/// Delete a PodDisruptionBudget by namespace and name
+pub async fn delete_pdb(
+    namespace: &str,
+    pdb_name: &str,
+    addr: &str,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+    println!("RKL connected to RKS at {addr}");
+
+    cli.send_msg(&RksMessage::DeletePodDisruptionBudget {
+        namespace: namespace.to_string(),
+        name: pdb_name.to_string(),
+    })
+    .await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::Ack => {
+            println!("poddisruptionbudget/{pdb_name} deleted");
+            Ok(())
+        }
+        RksMessage::Error(err) => Err(anyhow!("Failed to delete poddisruptionbudget: {}", err)),
+        msg => Err(anyhow!("Unexpected response: {:?}", msg)),
+    }
+}
+
+/// List all PodDisruptionBudgets
+pub async fn list_pdbs(addr: &str, tls_cfg: TLSConnectionArgs) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+    println!("RKL connected to RKS at {addr}");
+
+    cli.send_msg(&RksMessage::ListPodDisruptionBudget).await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::ListPodDisruptionBudgetRes(pdbs) => list_print(pdbs),
+        RksMessage::Error(err) => Err(anyhow!("Failed to list poddisruptionbudgets: {}", err)),
+        msg => Err(anyhow!("Unexpected response: {:?}", msg)),
+    }
+}
+
+fn pdb_from_path(pdb_yaml: &str) -> Result<Box<PodDisruptionBudget>> {
+    let pdb_file =
+        File::open(pdb_yaml).map_err(|e| anyhow!("Failed to open file '{}': {}", pdb_yaml, e))?;
+    let pdb: PodDisruptionBudget =
+        serde_yaml::from_reader(pdb_file).map_err(|e| anyhow!("Failed to parse YAML: {}", e))?;
+
+    if pdb.metadata.name.is_empty() {
+        return Err(anyhow!(
+            "PodDisruptionBudget metadata.name must not be empty"
+        ));
+    }
+    if pdb.spec.min_available.is_some() && pdb.spec.max_unavailable.is_some() {
+        return Err(anyhow!(
+            "PodDisruptionBudget minAvailable and maxUnavailable cannot both be set"
+        ));
+    }
+    for value in [&pdb.spec.min_available, &pdb.spec.max_unavailable]
+        .into_iter()
+        .flatten()
+    {
+        validate(value)?;
+    }
+
+    Ok(Box::new(pdb))
+}
+
+/// Non-negative numbers and percentages from 0% to 100%.
+fn validate(value: &IntOrPercentage) -> Result<()> {
+    let valid = match value {
+        IntOrPercentage::Int(n) => *n >= 0,
+        IntOrPercentage::String(s) => match s.strip_suffix('%') {
+            Some(percent) => percent
+                .parse::<f64>()
+                .is_ok_and(|p| (0.0..=100.0).contains(&p)),
+            None => s.parse::<i32>().is_ok_and(|n| n >= 0),
+        },
+    };
+    if !valid {
+        return Err(anyhow!("invalid PodDisruptionBudget value: {:?}", value));
+    }
+    Ok(())
+}
+
+fn format_value(value: &Option<IntOrPercentage>) -> String {
+    match value {
+        Some(IntOrPercentage::Int(n)) => n.to_string(),
+        Some(IntOrPercentage::String(s)) => s.clone(),
+        None => "N/A".to_string(),
+    }
+}
+
+fn list_print(pdbs: Vec<PodDisruptionBudget>) -> Result<()> {
+    let mut tab_writer = TabWriter::new(io::stdout());
+    writeln!(
+        &mut tab_writer,
+        "NAMESPACE\tNAME\tMIN AVAILABLE\tMAX UNAVAILABLE\tAGE"
+    )?;
+
+    for pdb in pdbs {
+        let age = pdb
+            .metadata
+            .creation_timestamp
+            .map(|ts| format_duration(chrono::Utc::now().signed_duration_since(ts)))
+            .unwrap_or_else(|| "<unknown>".to_string());
+
+        writeln!(
+            &mut tab_writer,
+            "{}\t{}\t{}\t{}\t{}",
+            pdb.metadata.namespace,
+            pdb.metadata.name,
+            format_value(&pdb.spec.min_available),
+            format_value(&pdb.spec.max_unavailable),
+            age
+        )?;
+    }
+
+    tab_writer.flush()?;
+    Ok(())
+}
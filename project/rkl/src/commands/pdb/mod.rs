@@ -0,0 +1,95 @@
+use anyhow::{Result, anyhow};
+use clap::Subcommand;
+use std::env;
+
+use crate::commands::pod::TLSConnectionArgs;
+
+pub mod cluster;
+
+#[derive(Subcommand)]
+pub enum PdbCommand {
+    #[command(about = "Create a PodDisruptionBudget from a YAML file")]
+    Create {
+        #[arg(value_name = "PDB_YAML")]
+        pdb_yaml: String,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
+    #[command(about = "Delete a PodDisruptionBudget by name")]
+    Delete {
+        #[arg(value_name = "PDB_NAME")]
+        pdb_name: String,
+
+        #[arg(long, short = 'n', value_name = "NAMESPACE", default_value = "default")]
+        namespace: String,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
+    #[command(about = "List all PodDisruptionBudgets")]
+    List {
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+}
+
+pub fn pdb_execute(cmd: PdbCommand) -> Result<()> {
+    let rt = tokio::runtime::Runtime::new()?;
+    match cmd {
+        PdbCommand::Create {
+            pdb_yaml,
+            cluster,
+            tls_cfg,
+        } => rt.block_on(cluster::create_pdb(
+            &pdb_yaml,
+            &rks_address(cluster)?,
+            tls_cfg,
+        )),
+        PdbCommand::Delete {
+            pdb_name,
+            namespace,
+            cluster,
+            tls_cfg,
+        } => rt.block_on(cluster::delete_pdb(
+            &namespace,
+            &pdb_name,
+            &rks_address(cluster)?,
+            tls_cfg,
+        )),
+        PdbCommand::List { cluster, tls_cfg } => {
+            rt.block_on(cluster::list_pdbs(&rks_address(cluster)?, tls_cfg))
+        }
+    }
+}
+
+fn rks_address(addr: Option<String>) -> Result<String> {
+    addr.or_else(|| env::var("RKS_ADDRESS").ok())
+        .ok_or_else(|| anyhow!("No RKS address provided. Set RKS_ADDRESS or use --cluster"))
+}
@@ -0,0 +1,174 @@
+//! Checkpoint and restore of whole pods.
+//!
+//! A pod checkpoint holds the pod spec as `pod.yaml` and a container
+//! checkpoint (see [`libruntime::checkpoint`]) of every workload container
+//! under `containers/<name>`. The pause container isn't checkpointed: the
+//! pod is restored around a new sandbox, possibly on another node, and gets
+//! a new IP there.
+
+use crate::commands::container::checkpoint::CheckpointDir;
+use crate::commands::pod::{PodInfo, PodRunResult, standalone};
+use crate::commands::{delete, load_container};
+use crate::task::TaskRunner;
+use anyhow::{Result, anyhow};
+use common::PodTask;
+use libcontainer::syscall::syscall::create_syscall;
+use liboci_cli::Delete;
+use libruntime::checkpoint::{self, CheckpointOptions};
+use libruntime::cri::cri_api::{RemovePodSandboxRequest, StopPodSandboxRequest};
+use libruntime::rootpath;
+use std::fs;
+use std::path::Path;
+use tracing::{error, info};
+
+const POD_SPEC_FILE: &str = "pod.yaml";
+const CONTAINERS_DIR: &str = "containers";
+
+/// Checkpoint the workload containers of a local pod into `archive`.
+///
+/// Unless `options.leave_running` is set the pod is deleted once the
+/// archive is written.
+pub fn checkpoint_pod(pod_name: &str, options: &CheckpointOptions, archive: &Path) -> Result<()> {
+    checkpoint::check_criu()?;
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let pod_info = PodInfo::load(&root_path, pod_name)?;
+    let pod_spec = pod_info.pod_spec.as_ref().ok_or_else(|| {
+        anyhow!("Pod {pod_name} doesn't record its spec, recreate it to checkpoint it")
+    })?;
+    if !pod_spec.spec.host_users() {
+        return Err(anyhow!(
+            "Pod {pod_name} runs in its own user namespace, which can't be checkpointed"
+        ));
+    }
+
+    let dir = CheckpointDir::new(pod_name)?;
+    fs::write(
+        dir.path().join(POD_SPEC_FILE),
+        serde_yaml::to_string(pod_spec)?,
+    )?;
+    for container_name in &pod_info.container_names {
+        let container = load_container(&root_path, container_name)?;
+        let container_dir = dir.path().join(CONTAINERS_DIR).join(container_name);
+        fs::create_dir_all(&container_dir)?;
+        checkpoint::dump(&container, &container_dir, options)
+            .map_err(|e| anyhow!("Failed to checkpoint pod {pod_name}: {e}"))?;
+        info!("Container checkpointed: {container_name}");
+    }
+    checkpoint::export(dir.path(), archive)?;
+    info!("Pod {pod_name} checkpointed to {}", archive.display());
+
+    if !options.leave_running {
+        standalone::delete_pod(pod_name)?;
+    }
+    Ok(())
+}
+
+/// Restore a pod from an archive written by [`checkpoint_pod`].
+pub fn restore_pod(archive: &Path) -> Result<PodRunResult> {
+    checkpoint::check_criu()?;
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let dir = CheckpointDir::new("restore")?;
+    checkpoint::import(archive, dir.path())?;
+
+    let content = fs::read_to_string(dir.path().join(POD_SPEC_FILE))
+        .map_err(|e| anyhow!("invalid pod checkpoint, failed to read {POD_SPEC_FILE}: {e}"))?;
+    let task: PodTask = serde_yaml::from_str(&content)?;
+    let mut task_runner = TaskRunner::from_task(task)?;
+    let pod_name = task_runner.task.metadata.name.clone();
+    if root_path.join("pods").join(&pod_name).exists() {
+        return Err(anyhow!("Pod {pod_name} already exists"));
+    }
+
+    let containers = task_runner.task.spec.containers.clone();
+    for container in &containers {
+        let container_dir = dir.path().join(CONTAINERS_DIR).join(&container.name);
+        if !container_dir.join(checkpoint::MANIFEST_FILE).exists() {
+            return Err(anyhow!(
+                "the checkpoint of pod {pod_name} has no container {}",
+                container.name
+            ));
+        }
+        task_runner.set_checkpoint(&container.name, container_dir);
+    }
+
+    let pod_request = task_runner.build_run_pod_sandbox_request();
+    let config = pod_request
+        .config
+        .as_ref()
+        .ok_or_else(|| anyhow!("PodSandbox config is required"))?;
+    task_runner.sandbox_config = Some(config.clone());
+    let (pod_response, pod_ip) = task_runner.sync_run_pod_sandbox(pod_request)?;
+    let pod_sandbox_id = pod_response.pod_sandbox_id;
+    info!("PodSandbox (Pause) created: {pod_sandbox_id}");
+
+    let mut container_names = Vec::new();
+    for container in &containers {
+        let restored = task_runner
+            .sync_build_create_container_request(&pod_sandbox_id, container)
+            .and_then(|request| task_runner.restore_container(request));
+        match restored {
+            Ok(response) => {
+                info!("Container restored: {}", response.container_id);
+                container_names.push(response.container_id);
+            }
+            Err(e) => {
+                error!("Failed to restore container {}: {}", container.name, e);
+                rollback(&mut task_runner, &pod_sandbox_id, &container_names);
+                return Err(anyhow!(
+                    "Failed to restore container {}: {}",
+                    container.name,
+                    e
+                ));
+            }
+        }
+    }
+
+    let pod_info = PodInfo::new(
+        &task_runner,
+        pod_sandbox_id.clone(),
+        container_names.clone(),
+        &pod_ip,
+    );
+    pod_info.save(&root_path, &pod_name)?;
+
+    info!("Pod {pod_name} restored successfully");
+    Ok(PodRunResult {
+        pod_sandbox_id,
+        pod_ip,
+        container_names,
+        pod_task: task_runner.task.clone(),
+    })
+}
+
+/// Remove what a failed restore left behind.
+fn rollback(task_runner: &mut TaskRunner, pod_sandbox_id: &str, restored: &[String]) {
+    task_runner.stop_all_rootfs_mounts();
+    let root_path = match rootpath::determine(None, &*create_syscall()) {
+        Ok(root_path) => root_path,
+        Err(e) => {
+            error!("Failed to determine root path during rollback: {e}");
+            return;
+        }
+    };
+    for container_id in restored {
+        let delete_args = Delete {
+            container_id: container_id.clone(),
+            force: true,
+        };
+        if let Err(e) = delete(delete_args, root_path.clone()) {
+            error!("Failed to delete container {container_id} during rollback: {e}");
+        }
+    }
+    let stop_request = StopPodSandboxRequest {
+        pod_sandbox_id: pod_sandbox_id.to_string(),
+    };
+    if let Err(e) = task_runner.stop_pod_sandbox(stop_request) {
+        error!("Failed to stop PodSandbox {pod_sandbox_id} during rollback: {e}");
+    }
+    let remove_request = RemovePodSandboxRequest {
+        pod_sandbox_id: pod_sandbox_id.to_string(),
+    };
+    if let Err(e) = task_runner.remove_pod_sandbox(remove_request) {
+        error!("Failed to remove PodSandbox {pod_sandbox_id} during rollback: {e}");
+    }
+}
@@ -2,8 +2,11 @@ use anyhow::Ok;
 use anyhow::Result;
 use anyhow::anyhow;
 use chrono::Utc;
+use common::ContainerState;
+use common::EphemeralContainer;
 use common::PodTask;
 use common::RksMessage;
+use common::{PodLogOptions, PodLogsRequest};
 use std::fs::File;
 use std::io;
 use std::io::Write;
@@ -12,6 +15,7 @@ use tracing::info;
 
 use crate::commands::format_duration;
 use crate::commands::pod::TLSConnectionArgs;
+use crate::logs;
 use crate::quic::client::{Cli, QUICClient};
 
 pub async fn delete_pod(pod_name: &str, addr: &str, tls_cfg: TLSConnectionArgs) -> Result<()> {
@@ -37,6 +41,69 @@ pub async fn create_pod(pod_yaml: &str, addr: &str, tls_cfg: TLSConnectionArgs)
     Ok(())
 }
 
+pub async fn migrate_pod(
+    pod_name: &str,
+    node_name: Option<String>,
+    rks_addr: &str,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(rks_addr, &tls_cfg).await?;
+    info!("RKL connected to RKS at {rks_addr}");
+
+    cli.send_msg(&RksMessage::MigratePod {
+        pod_name: pod_name.to_string(),
+        node_name,
+    })
+    .await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::GetPodRes(pod) => {
+            println!(
+                "pod {} migrated to {}",
+                pod_name,
+                pod.spec.node_name.as_deref().unwrap_or("<unknown>")
+            );
+            Ok(())
+        }
+        RksMessage::Error(err) => Err(anyhow!("Failed to migrate pod: {}", err)),
+        msg => Err(anyhow!("unexpected response {:?} ", msg)),
+    }
+}
+
+pub async fn debug_pod(
+    pod_name: &str,
+    container: EphemeralContainer,
+    rks_addr: &str,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(rks_addr, &tls_cfg).await?;
+    info!("RKL connected to RKS at {rks_addr}");
+
+    let name = container.name.clone();
+    cli.send_msg(&RksMessage::DebugPod {
+        pod_name: pod_name.to_string(),
+        container: Box::new(container),
+    })
+    .await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::GetPodRes(pod) => {
+            println!(
+                "ephemeral container {} added to pod {} on {}, use `rkl pod exec {} {}-{} -- <command>` to enter it",
+                name,
+                pod_name,
+                pod.spec.node_name.as_deref().unwrap_or("<unknown>"),
+                pod_name,
+                pod_name,
+                name
+            );
+            Ok(())
+        }
+        RksMessage::Error(err) => Err(anyhow!("Failed to debug pod: {}", err)),
+        msg => Err(anyhow!("unexpected response {:?} ", msg)),
+    }
+}
+
 pub async fn list_pod(addr: &str, tls_cfg: TLSConnectionArgs) -> Result<()> {
     let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
     info!("RKL connected to RKS at {addr}");
@@ -67,6 +134,43 @@ pub async fn get_pod(pod_name: &str, rks_addr: &str, tls_cfg: TLSConnectionArgs)
     }
 }
 
+pub async fn pod_logs(
+    pod_name: &str,
+    mut options: PodLogOptions,
+    follow: bool,
+    rks_addr: &str,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(rks_addr, &tls_cfg).await?;
+    info!("RKL connected to RKS at {rks_addr}");
+
+    loop {
+        cli.send_msg(&RksMessage::GetPodLogs(Box::new(PodLogsRequest {
+            request_id: 0,
+            pod_name: pod_name.to_string(),
+            options: options.clone(),
+        })))
+        .await?;
+
+        let lines = match cli.fetch_msg().await? {
+            RksMessage::PodLogsRes(res) => match res.error {
+                Some(err) => return Err(anyhow!("Failed to get logs: {}", err)),
+                None => logs::parse_lines(&res.logs),
+            },
+            RksMessage::Error(err) => return Err(anyhow!("Failed to get logs: {}", err)),
+            msg => return Err(anyhow!("unexpected response {:?} ", msg)),
+        };
+        logs::print_lines(&lines, &mut io::stdout())?;
+
+        // The previous instance has exited, there is nothing to follow.
+        if !follow || options.previous {
+            return Ok(());
+        }
+        options = logs::follow_options(&options, &lines, Utc::now());
+        tokio::time::sleep(logs::FOLLOW_INTERVAL).await;
+    }
+}
+
 pub fn pod_task_from_path(pod_yaml: &str) -> Result<Box<PodTask>> {
     let pod_file = File::open(pod_yaml)?;
     let task: PodTask = serde_yaml::from_reader(pod_file)?;
@@ -86,7 +190,26 @@ fn list_print(pod_list: Vec<PodTask>) -> Result<()> {
             .count();
         let total_count = pod.spec.containers.len();
         let ready = format!("{}/{}", ready_count, total_count);
-        let status = format!("{:?}", pod.status.phase);
+        // A container waiting to be restarted, e.g. in CrashLoopBackOff,
+        // says more than the pod's phase.
+        let waiting_reason = pod
+            .status
+            .container_statuses
+            .iter()
+            .find_map(|c| match &c.state {
+                Some(ContainerState::Waiting {
+                    reason: Some(reason),
+                    ..
+                }) => Some(reason.clone()),
+                _ => None,
+            });
+        let status = if pod.metadata.deletion_timestamp.is_some() {
+            "Terminating".to_string()
+        } else if let Some(reason) = waiting_reason {
+            reason
+        } else {
+            format!("{:?}", pod.status.phase)
+        };
         let restarts: u32 = pod
             .status
             .container_statuses
@@ -0,0 +1,283 @@
+//! Ephemeral containers, added to a running pod with `rkl pod debug`.
+//!
+//! An ephemeral container joins the namespaces of the pod's pause container,
+//! and the PID namespace of its target container if it has one, so that pods
+//! whose images have no shell can be inspected. It is never restarted and is
+//! removed along with the pod. In cluster mode rks records it in the pod's
+//! spec, and the node running the pod starts it and reports its status.
+
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use common::{ContainerState, ContainerStatus, EphemeralContainer, PodTask};
+use libcontainer::syscall::syscall::create_syscall;
+use libruntime::rootpath;
+use rkforge::pull::RegistryAuth;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::commands::load_container;
+use crate::commands::pod::PodInfo;
+use crate::commands::pod::standalone::resolve_container;
+use crate::task::{self, TaskRunner};
+
+/// The ephemeral container `rkl pod debug` adds. Without a name it is called
+/// `debugger-` followed by a random suffix.
+pub fn ephemeral_container(
+    name: Option<String>,
+    image: String,
+    target: Option<String>,
+    command: Vec<String>,
+) -> EphemeralContainer {
+    let name = name.unwrap_or_else(|| {
+        let suffix = Uuid::new_v4().simple().to_string();
+        format!("debugger-{}", &suffix[..5])
+    });
+    EphemeralContainer {
+        name,
+        image,
+        command: (!command.is_empty()).then_some(command),
+        target_container_name: target,
+        ..Default::default()
+    }
+}
+
+/// Start the ephemeral containers of `pod` which this node has not started
+/// yet. Returns their statuses, keyed by the full container name.
+pub async fn run_ephemeral_containers(
+    pod: &PodTask,
+    registry_credentials: HashMap<String, RegistryAuth>,
+) -> Result<Vec<ContainerStatus>> {
+    let pod_name = &pod.metadata.name;
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let mut pod_info = PodInfo::load(&root_path, pod_name)?;
+    let pause_pid = pod_info
+        .get_pod_sandbox(&root_path)?
+        .state
+        .pid
+        .ok_or_else(|| anyhow!("Pause container PID not found for pod {pod_name}"))?;
+
+    let mut task_runner = TaskRunner::from_task(pod.clone())?;
+    task_runner.pause_pid = Some(pause_pid);
+    task_runner.registry_credentials = registry_credentials;
+    task_runner.allocate_id_range()?;
+    let mut sandbox_config =
+        task_runner.create_pod_sandbox_config(&pod.metadata.uid.to_string(), 0)?;
+    if let Some(dir) = &pod_info.log_directory {
+        sandbox_config.log_directory = dir.clone();
+    }
+    task_runner.sandbox_config = Some(sandbox_config);
+
+    let mut statuses = Vec::new();
+    for container in &pod.spec.ephemeral_containers {
+        let container_id = format!("{pod_name}-{}", container.name);
+        if pod_info.ephemeral_containers.contains(&container_id) {
+            continue;
+        }
+        let started = match target_pid(&root_path, &pod_info, pod_name, container) {
+            Ok(target_pid) => {
+                task_runner
+                    .run_ephemeral_container(&pod_info.pod_sandbox_id, container, target_pid)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        let state = match started {
+            Ok(_) => {
+                pod_info.ephemeral_containers.push(container_id.clone());
+                ContainerState::Running {
+                    started_at: Some(Utc::now()),
+                }
+            }
+            Err(e) => {
+                warn!("failed to start ephemeral container {container_id}: {e}");
+                ContainerState::Terminated {
+                    exit_code: 128,
+                    signal: None,
+                    reason: Some("StartError".to_string()),
+                    message: Some(e.to_string()),
+                    started_at: None,
+                    finished_at: Some(Utc::now()),
+                }
+            }
+        };
+        statuses.push(ContainerStatus {
+            name: container_id,
+            state: Some(state),
+            ..Default::default()
+        });
+    }
+
+    if let Some(spec) = pod_info.pod_spec.as_mut() {
+        spec.spec.ephemeral_containers = pod.spec.ephemeral_containers.clone();
+    }
+    PodInfo::delete(&root_path, pod_name)?;
+    pod_info.save(&root_path, pod_name)?;
+    Ok(statuses)
+}
+
+/// A process of the target container of `container`, whose PID namespace
+/// it joins.
+fn target_pid(
+    root_path: &std::path::Path,
+    pod_info: &PodInfo,
+    pod_name: &str,
+    container: &EphemeralContainer,
+) -> Result<Option<i32>> {
+    let Some(target) = &container.target_container_name else {
+        return Ok(None);
+    };
+    let target_id = resolve_container(pod_name, pod_info, Some(target))?;
+    let pid = load_container(root_path, target_id)?
+        .state
+        .pid
+        .ok_or_else(|| anyhow!("target container {target} of pod {pod_name} is not running"))?;
+    Ok(Some(pid))
+}
+
+/// Wait for the ephemeral container `container_id` started at `started_at`
+/// to exit, and return its final status.
+pub fn exited_status(
+    container_id: &str,
+    started_at: Option<DateTime<Utc>>,
+) -> Result<ContainerStatus> {
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let (exit_code, signal, message) = task::wait_for_exit(&root_path, container_id)?;
+    Ok(ContainerStatus {
+        name: container_id.to_string(),
+        state: Some(ContainerState::Terminated {
+            exit_code,
+            signal,
+            reason: Some(if exit_code == 0 { "Completed" } else { "Error" }.to_string()),
+            message,
+            started_at,
+            finished_at: Some(Utc::now()),
+        }),
+        ..Default::default()
+    })
+}
+
+/// Add an ephemeral container to a local pod and start it.
+pub fn debug_pod(pod_name: &str, container: EphemeralContainer) -> Result<()> {
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let pod_info = PodInfo::load(&root_path, pod_name)?;
+    let mut pod = pod_info
+        .pod_spec
+        .ok_or_else(|| anyhow!("Pod {pod_name} has no recorded spec to add a container to"))?;
+    pod.add_ephemeral_container(container.clone())
+        .map_err(|e| anyhow!(e))?;
+
+    let rt = tokio::runtime::Runtime::new()?;
+    let statuses = rt.block_on(run_ephemeral_containers(&pod, HashMap::new()))?;
+    for status in statuses {
+        if let Some(ContainerState::Terminated {
+            message: Some(message),
+            ..
+        }) = status.state
+        {
+            return Err(anyhow!(
+                "failed to start ephemeral container {}: {message}",
+                status.name
+            ));
+        }
+        info!("ephemeral container {} started", status.name);
+        println!(
+            "ephemeral container {} started, use `rkl pod exec {pod_name} {} -- <command>` to enter it",
+            container.name, status.name
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{ContainerSpec, ObjectMeta, PodSpec};
+
+    fn pod() -> PodTask {
+        PodTask {
+            api_version: "v1".to_string(),
+            kind: "Pod".to_string(),
+            metadata: ObjectMeta {
+                name: "web".to_string(),
+                ..Default::default()
+            },
+            spec: PodSpec {
+                containers: vec![ContainerSpec {
+                    name: "app".to_string(),
+                    image: "distroless/app:1.0".to_string(),
+                    ports: vec![],
+                    args: vec![],
+                    resources: None,
+                    liveness_probe: None,
+                    readiness_probe: None,
+                    startup_probe: None,
+                    security_context: None,
+                    env: None,
+                    volume_mounts: None,
+                    command: None,
+                    working_dir: None,
+                    lifecycle: None,
+                    restart_policy: None,
+                    image_pull_policy: None,
+                }],
+                ..Default::default()
+            },
+            status: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_ephemeral_container_generates_name() {
+        let container = ephemeral_container(
+            None,
+            "busybox".to_string(),
+            Some("app".to_string()),
+            vec!["sleep".to_string(), "3600".to_string()],
+        );
+        assert!(container.name.starts_with("debugger-"));
+        assert_eq!(container.name.len(), "debugger-".len() + 5);
+        assert_eq!(
+            container.command,
+            Some(vec!["sleep".to_string(), "3600".to_string()])
+        );
+
+        let container = ephemeral_container(
+            Some("shell".to_string()),
+            "busybox".to_string(),
+            None,
+            vec![],
+        );
+        assert_eq!(container.name, "shell");
+        assert_eq!(container.command, None);
+    }
+
+    #[test]
+    fn test_add_ephemeral_container_validates_name_and_target() {
+        let mut pod = pod();
+        let debugger = ephemeral_container(
+            Some("debugger".to_string()),
+            "busybox".to_string(),
+            Some("app".to_string()),
+            vec![],
+        );
+        pod.add_ephemeral_container(debugger.clone()).unwrap();
+        assert_eq!(pod.spec.ephemeral_containers, vec![debugger.clone()]);
+
+        // Names are unique among all the containers of the pod
+        assert!(pod.add_ephemeral_container(debugger).is_err());
+        let clash =
+            ephemeral_container(Some("app".to_string()), "busybox".to_string(), None, vec![]);
+        assert!(pod.add_ephemeral_container(clash).is_err());
+
+        let unknown_target = ephemeral_container(
+            Some("other".to_string()),
+            "busybox".to_string(),
+            Some("db".to_string()),
+            vec![],
+        );
+        assert!(pod.add_ephemeral_container(unknown_target).is_err());
+        assert_eq!(pod.spec.ephemeral_containers.len(), 1);
+    }
+}
@@ -0,0 +1,506 @@
+//! Downward API: pod fields and container resources exposed to containers.
+//!
+//! Env vars take them with `valueFrom.fieldRef` and
+//! `valueFrom.resourceFieldRef`. A `downwardAPI` volume projects them as
+//! files under `<root>/downward/<pod>/<volume>`, which is bind mounted
+//! read-only into the containers mounting the volume. The files are
+//! rewritten when rks reports new labels or annotations for the pod, each
+//! replaced atomically.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use common::{ContainerSpec, NodeResources, PodTask, ResourceFieldSelector, Volume};
+use libcontainer::syscall::syscall::create_syscall;
+use libruntime::cri::cri_api::{KeyValue, Mount};
+use libruntime::rootpath;
+use tracing::info;
+
+use crate::commands::pod::PodInfo;
+use crate::daemon::allocatable::{self, parse_millicores};
+use crate::daemon::eviction::threshold::Quantity;
+
+const DEFAULT_MODE: u32 = 0o644;
+
+/// Directory holding the downward API volumes of a pod.
+fn pod_dir(root_path: &Path, pod_name: &str) -> PathBuf {
+    root_path.join("downward").join(pod_name)
+}
+
+/// The value of the pod field `field_path`.
+pub fn field_value(pod: &PodTask, pod_ip: &str, field_path: &str) -> Result<String> {
+    let metadata = &pod.metadata;
+    let value = match field_path {
+        "metadata.name" => metadata.name.clone(),
+        "metadata.namespace" => metadata.namespace.clone(),
+        "metadata.uid" => metadata.uid.to_string(),
+        "metadata.labels" => format_map(&metadata.labels),
+        "metadata.annotations" => format_map(&metadata.annotations),
+        "spec.nodeName" => pod.spec.node_name.clone().unwrap_or_default(),
+        "status.podIP" => pod_ip.split('/').next().unwrap_or_default().to_string(),
+        path => {
+            let (map, key) = if let Some(key) = subscript(path, "metadata.labels") {
+                (&metadata.labels, key)
+            } else if let Some(key) = subscript(path, "metadata.annotations") {
+                (&metadata.annotations, key)
+            } else {
+                bail!("unsupported fieldPath {path}");
+            };
+            map.get(key).cloned().unwrap_or_default()
+        }
+    };
+    Ok(value)
+}
+
+/// `key` of `<field>['key']`.
+fn subscript<'a>(path: &'a str, field: &str) -> Option<&'a str> {
+    path.strip_prefix(field)?
+        .strip_prefix("['")?
+        .strip_suffix("']")
+}
+
+/// Labels or annotations as `key="value"` lines, sorted by key.
+fn format_map(map: &std::collections::HashMap<String, String>) -> String {
+    map.iter()
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(key, value)| format!("{key}={value:?}\n"))
+        .collect()
+}
+
+/// The value of a resource of the container `container` of `pod`, in units
+/// of the selector's divisor. Unset limits are the node's allocatable amount.
+pub fn resource_value(
+    pod: &PodTask,
+    container: &str,
+    selector: &ResourceFieldSelector,
+    allocatable: Option<&NodeResources>,
+) -> Result<String> {
+    let container = selector.container_name.as_deref().unwrap_or(container);
+    let prefixed = format!("{}-{container}", pod.metadata.name);
+    let spec = pod
+        .spec
+        .init_containers
+        .iter()
+        .chain(&pod.spec.containers)
+        .find(|c| c.name == container || c.name == prefixed)
+        .ok_or_else(|| anyhow!("container {container} not found in pod"))?;
+    let limits = spec.resources.as_ref().and_then(|r| r.limits.as_ref());
+    let divisor = selector.divisor.as_deref().unwrap_or("1");
+
+    let (amount, unit) = match selector.resource.as_str() {
+        "limits.cpu" | "requests.cpu" => {
+            let millicores = match limits.and_then(|l| l.cpu.as_deref()) {
+                Some(cpu) => parse_millicores(cpu)?,
+                None => allocatable.map_or(0, |a| a.cpu),
+            };
+            (millicores, parse_millicores(divisor)?)
+        }
+        "limits.memory" | "requests.memory" => {
+            let bytes = match limits.and_then(|l| l.memory.as_deref()) {
+                Some(memory) => parse_bytes(memory)?,
+                None => allocatable.map_or(0, |a| a.memory),
+            };
+            (bytes, parse_bytes(divisor)?)
+        }
+        resource => bail!("unsupported resource {resource}"),
+    };
+    if unit == 0 {
+        bail!("divisor {divisor} must be positive");
+    }
+    Ok(amount.div_ceil(unit).to_string())
+}
+
+fn parse_bytes(s: &str) -> Result<u64> {
+    match s.parse::<Quantity>()? {
+        Quantity::Absolute(bytes) => Ok(bytes),
+        Quantity::Percentage(_) => bail!("invalid quantity {s}"),
+    }
+}
+
+fn node_allocatable() -> Option<NodeResources> {
+    allocatable::node_resources()
+        .ok()
+        .map(|(_, allocatable)| allocatable)
+}
+
+/// The env vars of `container`, with the downward API references resolved.
+pub fn container_env(
+    pod: &PodTask,
+    pod_ip: &str,
+    container: &ContainerSpec,
+) -> Result<Vec<KeyValue>> {
+    let mut allocatable = None;
+    let mut envs = Vec::new();
+    for env in container.env.iter().flatten() {
+        let value = match (&env.value, &env.value_from) {
+            (Some(value), _) => value.clone(),
+            (None, Some(source)) => {
+                if let Some(field) = &source.field_ref {
+                    field_value(pod, pod_ip, &field.field_path)?
+                } else if let Some(resource) = &source.resource_field_ref {
+                    let allocatable = allocatable.get_or_insert_with(node_allocatable);
+                    resource_value(pod, &container.name, resource, allocatable.as_ref())?
+                } else {
+                    String::new()
+                }
+            }
+            (None, None) => String::new(),
+        };
+        envs.push(KeyValue {
+            key: env.name.clone(),
+            value,
+        });
+    }
+    Ok(envs)
+}
+
+/// A relative path which stays within its volume.
+fn check_path(path: &str) -> Result<&Path> {
+    let relative = Path::new(path);
+    if path.is_empty()
+        || !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+    {
+        bail!("invalid downwardAPI path {path}");
+    }
+    Ok(relative)
+}
+
+fn downward_volumes(pod: &PodTask) -> impl Iterator<Item = &Volume> {
+    pod.spec.volumes.iter().filter(|v| v.downward_api.is_some())
+}
+
+/// Write the files of the pod's downward API volumes.
+pub fn write_volumes(root_path: &Path, pod: &PodTask, pod_ip: &str) -> Result<()> {
+    let mut allocatable = None;
+    for volume in downward_volumes(pod) {
+        let source = volume.downward_api.as_ref().unwrap();
+        let dir = pod_dir(root_path, &pod.metadata.name).join(&volume.name);
+        fs::create_dir_all(&dir)?;
+        for item in &source.items {
+            let value = if let Some(field) = &item.field_ref {
+                field_value(pod, pod_ip, &field.field_path)?
+            } else if let Some(resource) = &item.resource_field_ref {
+                if resource.container_name.is_none() {
+                    bail!(
+                        "resourceFieldRef of {} in volume {} needs a containerName",
+                        item.path,
+                        volume.name
+                    );
+                }
+                let allocatable = allocatable.get_or_insert_with(node_allocatable);
+                resource_value(pod, "", resource, allocatable.as_ref())?
+            } else {
+                bail!("{} in volume {} selects nothing", item.path, volume.name);
+            };
+            let mode = item.mode.or(source.default_mode).unwrap_or(DEFAULT_MODE);
+            write_atomic(&dir.join(check_path(&item.path)?), &value, mode)?;
+        }
+    }
+    Ok(())
+}
+
+/// Replace `path` with a file holding `content`, so that readers see either
+/// the old or the new content.
+fn write_atomic(path: &Path, content: &str, mode: u32) -> Result<()> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("invalid path {}", path.display()))?;
+    fs::create_dir_all(dir)?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = dir.join(format!(".{name}.tmp"));
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(content.as_bytes())?;
+    file.set_permissions(fs::Permissions::from_mode(mode))?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Read-only bind mounts of the downward API volumes `container` mounts.
+pub fn container_mounts(root_path: &Path, pod: &PodTask, container: &ContainerSpec) -> Vec<Mount> {
+    container
+        .volume_mounts
+        .iter()
+        .flatten()
+        .filter_map(|volume_mount| {
+            let volume = downward_volumes(pod).find(|v| v.name == volume_mount.name)?;
+            let mut host_path = pod_dir(root_path, &pod.metadata.name).join(&volume.name);
+            if let Some(sub_path) = volume_mount.sub_path.as_deref().filter(|p| !p.is_empty()) {
+                host_path = host_path.join(check_path(sub_path).ok()?);
+            }
+            Some(Mount {
+                container_path: volume_mount.mount_path.clone(),
+                host_path: host_path.display().to_string(),
+                readonly: true,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// Remove the downward API volumes of a pod.
+pub fn remove_volumes(root_path: &Path, pod_name: &str) -> Result<()> {
+    match fs::remove_dir_all(pod_dir(root_path, pod_name)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Take the new labels and annotations of a pod running on this node, and
+/// rewrite its downward API volumes.
+pub fn update_metadata(pod: &PodTask) -> Result<()> {
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let pod_name = &pod.metadata.name;
+    let mut pod_info = PodInfo::load(&root_path, pod_name)?;
+    let Some(current) = pod_info.pod_spec.as_mut() else {
+        return Ok(());
+    };
+    if current.metadata.labels == pod.metadata.labels
+        && current.metadata.annotations == pod.metadata.annotations
+    {
+        return Ok(());
+    }
+    current.metadata.labels = pod.metadata.labels.clone();
+    current.metadata.annotations = pod.metadata.annotations.clone();
+    let current = current.clone();
+
+    PodInfo::delete(&root_path, pod_name)?;
+    pod_info.save(&root_path, pod_name)?;
+    write_volumes(
+        &root_path,
+        &current,
+        pod_info.pod_ip.as_deref().unwrap_or_default(),
+    )?;
+    info!("updated the metadata of pod {pod_name}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{
+        ContainerRes, DownwardAPIVolumeFile, DownwardAPIVolumeSource, EnvVar, EnvVarSource,
+        ObjectFieldSelector, Resource, VolumeMount,
+    };
+
+    fn pod() -> PodTask {
+        serde_yaml::from_str(
+            r#"
+apiVersion: v1
+kind: Pod
+metadata:
+  name: web
+  namespace: default
+  labels:
+    app: web
+    tier: front
+  annotations:
+    build: "42"
+spec:
+  node_name: node1
+  containers:
+    - name: app
+      image: nginx
+      resources:
+        limits:
+          cpu: 500m
+          memory: 64Mi
+"#,
+        )
+        .unwrap()
+    }
+
+    fn field(field_path: &str) -> Option<ObjectFieldSelector> {
+        Some(ObjectFieldSelector {
+            field_path: field_path.to_string(),
+        })
+    }
+
+    fn resource(resource: &str, divisor: Option<&str>) -> ResourceFieldSelector {
+        ResourceFieldSelector {
+            container_name: Some("app".to_string()),
+            resource: resource.to_string(),
+            divisor: divisor.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_field_value() {
+        let pod = pod();
+        let value = |path| field_value(&pod, "10.1.0.5/24", path).unwrap();
+        assert_eq!(value("metadata.name"), "web");
+        assert_eq!(value("metadata.namespace"), "default");
+        assert_eq!(value("spec.nodeName"), "node1");
+        assert_eq!(value("status.podIP"), "10.1.0.5");
+        assert_eq!(value("metadata.labels['tier']"), "front");
+        assert_eq!(value("metadata.annotations['missing']"), "");
+        assert_eq!(value("metadata.labels"), "app=\"web\"\ntier=\"front\"\n");
+        assert!(field_value(&pod, "", "spec.hostname").is_err());
+    }
+
+    #[test]
+    fn test_resource_value() {
+        let mut pod = pod();
+        fn value(
+            pod: &PodTask,
+            selector: ResourceFieldSelector,
+            allocatable: Option<&NodeResources>,
+        ) -> String {
+            resource_value(pod, "app", &selector, allocatable).unwrap()
+        }
+        assert_eq!(value(&pod, resource("limits.cpu", None), None), "1");
+        assert_eq!(value(&pod, resource("limits.cpu", Some("1m")), None), "500");
+        assert_eq!(
+            value(&pod, resource("requests.memory", Some("1Mi")), None),
+            "64"
+        );
+
+        pod.spec.containers[0].resources = Some(ContainerRes {
+            limits: Some(Resource {
+                cpu: None,
+                memory: None,
+            }),
+        });
+        let allocatable = NodeResources {
+            cpu: 3500,
+            memory: 1 << 30,
+            ..Default::default()
+        };
+        assert_eq!(
+            value(&pod, resource("limits.cpu", Some("1m")), Some(&allocatable)),
+            "3500"
+        );
+        assert!(resource_value(&pod, "app", &resource("limits.gpu", None), None).is_err());
+    }
+
+    #[test]
+    fn test_container_env() {
+        let pod = pod();
+        let mut container = pod.spec.containers[0].clone();
+        container.env = Some(vec![
+            EnvVar {
+                name: "MODE".to_string(),
+                value: Some("prod".to_string()),
+                value_from: None,
+            },
+            EnvVar {
+                name: "POD_NAME".to_string(),
+                value: None,
+                value_from: Some(EnvVarSource {
+                    field_ref: field("metadata.name"),
+                    resource_field_ref: None,
+                }),
+            },
+            EnvVar {
+                name: "MEMORY_LIMIT".to_string(),
+                value: None,
+                value_from: Some(EnvVarSource {
+                    field_ref: None,
+                    resource_field_ref: Some(ResourceFieldSelector {
+                        container_name: None,
+                        ..resource("limits.memory", None)
+                    }),
+                }),
+            },
+        ]);
+        let envs = container_env(&pod, "10.1.0.5", &container).unwrap();
+        let envs = envs
+            .iter()
+            .map(|kv| (kv.key.as_str(), kv.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            envs,
+            [
+                ("MODE", "prod"),
+                ("POD_NAME", "web"),
+                ("MEMORY_LIMIT", "67108864")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_volumes() {
+        let root = tempfile::tempdir().unwrap();
+        let mut pod = pod();
+        pod.spec.volumes = vec![Volume {
+            name: "podinfo".to_string(),
+            downward_api: Some(DownwardAPIVolumeSource {
+                items: vec![
+                    DownwardAPIVolumeFile {
+                        path: "labels".to_string(),
+                        field_ref: field("metadata.labels"),
+                        resource_field_ref: None,
+                        mode: None,
+                    },
+                    DownwardAPIVolumeFile {
+                        path: "limits/cpu".to_string(),
+                        field_ref: None,
+                        resource_field_ref: Some(resource("limits.cpu", Some("1m"))),
+                        mode: Some(0o600),
+                    },
+                ],
+                default_mode: None,
+            }),
+        }];
+        write_volumes(root.path(), &pod, "").unwrap();
+        let dir = root.path().join("downward/web/podinfo");
+        assert_eq!(
+            fs::read_to_string(dir.join("labels")).unwrap(),
+            "app=\"web\"\ntier=\"front\"\n"
+        );
+        let cpu = dir.join("limits/cpu");
+        assert_eq!(fs::read_to_string(&cpu).unwrap(), "500");
+        assert_eq!(
+            fs::metadata(&cpu).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        pod.metadata.labels.remove("tier");
+        write_volumes(root.path(), &pod, "").unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("labels")).unwrap(),
+            "app=\"web\"\n"
+        );
+
+        let mut container = pod.spec.containers[0].clone();
+        container.volume_mounts = Some(vec![
+            VolumeMount {
+                name: "podinfo".to_string(),
+                mount_path: "/etc/podinfo".to_string(),
+                read_only: None,
+                sub_path: None,
+            },
+            VolumeMount {
+                name: "data".to_string(),
+                mount_path: "/data".to_string(),
+                read_only: None,
+                sub_path: None,
+            },
+        ]);
+        let mounts = container_mounts(root.path(), &pod, &container);
+        assert_eq!(mounts.len(), 1);
+        assert_eq!(mounts[0].container_path, "/etc/podinfo");
+        assert_eq!(mounts[0].host_path, dir.display().to_string());
+        assert!(mounts[0].readonly);
+
+        remove_volumes(root.path(), "web").unwrap();
+        assert!(!dir.exists());
+        remove_volumes(root.path(), "web").unwrap();
+    }
+
+    #[test]
+    fn test_check_path() {
+        assert!(check_path("labels").is_ok());
+        assert!(check_path("a/b").is_ok());
+        assert!(check_path("../etc").is_err());
+        assert!(check_path("/etc").is_err());
+        assert!(check_path("").is_err());
+    }
+}
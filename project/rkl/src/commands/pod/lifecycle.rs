@@ -0,0 +1,278 @@
+//! Container lifecycle hooks and graceful termination.
+//!
+//! Pods are stopped the same way whether the deletion comes from rks, a
+//! removed static pod manifest or `rkl pod delete`: every container first runs
+//! its preStop hook, then gets its stop signal and is killed once the pod's
+//! termination grace period is over.
+
+use crate::commands::pod::PodInfo;
+use crate::commands::{Exec, exec, kill, load_container};
+use anyhow::{Result, anyhow, bail};
+use common::{HttpGetAction, Lifecycle, LifecycleHandler};
+use libcontainer::container::ContainerStatus;
+use liboci_cli::Kill;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+/// Upper bound for a postStart hook, which has no grace period to count against.
+const POST_START_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long to wait for a container to exit after SIGKILL.
+const KILL_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How a container is started and stopped, recorded in the pod's [`PodInfo`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContainerLifecycle {
+    /// Signal from the image's STOPSIGNAL, SIGTERM is sent when unset.
+    #[serde(default)]
+    pub stop_signal: Option<String>,
+    #[serde(default)]
+    pub hooks: Lifecycle,
+}
+
+/// Run the postStart hooks of a freshly started pod. A container whose hook
+/// fails is killed, so that its restart policy applies.
+pub fn run_post_start_hooks(root_path: &Path, pod_info: &PodInfo) {
+    for container_id in &pod_info.container_names {
+        let Some(handler) = pod_info
+            .lifecycles
+            .get(container_id)
+            .and_then(|l| l.hooks.post_start.as_ref())
+        else {
+            continue;
+        };
+        let res = run_handler(
+            root_path,
+            container_id,
+            handler,
+            pod_info.pod_ip.as_deref(),
+            POST_START_TIMEOUT,
+        );
+        if let Err(e) = res {
+            error!("postStart hook of container {container_id} failed, killing it: {e}");
+            if let Err(e) = signal_and_wait(root_path, container_id, "SIGKILL", KILL_TIMEOUT) {
+                warn!("Failed to kill container {container_id}: {e}");
+            }
+        }
+    }
+}
+
+/// Stop all containers of the pod in parallel, then its sidecars, giving
+/// them the pod's termination grace period before they are killed.
+pub fn terminate_containers(root_path: &Path, pod_info: &PodInfo) {
+    let grace_period = Duration::from_secs(
+        pod_info
+            .termination_grace_period
+            .unwrap_or(common::DEFAULT_TERMINATION_GRACE_PERIOD_SECONDS),
+    );
+    let deadline = Instant::now() + grace_period;
+    // Sidecars keep serving the workload containers until these are stopped
+    let (sidecars, containers): (Vec<_>, Vec<_>) = pod_info
+        .container_names
+        .iter()
+        .partition(|name| pod_info.is_sidecar(name));
+    for group in [containers, sidecars] {
+        thread::scope(|s| {
+            for container_id in group {
+                let lifecycle = pod_info
+                    .lifecycles
+                    .get(container_id)
+                    .cloned()
+                    .unwrap_or_default();
+                s.spawn(move || {
+                    terminate_container(
+                        root_path,
+                        container_id,
+                        &lifecycle,
+                        pod_info.pod_ip.as_deref(),
+                        deadline,
+                    )
+                });
+            }
+        });
+    }
+}
+
+fn terminate_container(
+    root_path: &Path,
+    container_id: &str,
+    lifecycle: &ContainerLifecycle,
+    pod_ip: Option<&str>,
+    deadline: Instant,
+) {
+    if !is_running(root_path, container_id) {
+        debug!("Container {container_id} already stopped");
+        return;
+    }
+
+    if let Some(handler) = &lifecycle.hooks.pre_stop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match run_handler(root_path, container_id, handler, pod_ip, timeout) {
+            Ok(()) => debug!("preStop hook of container {container_id} finished"),
+            Err(e) => warn!("preStop hook of container {container_id} failed: {e}"),
+        }
+    }
+
+    let signal = lifecycle.stop_signal.as_deref().unwrap_or("SIGTERM");
+    let timeout = deadline.saturating_duration_since(Instant::now());
+    match signal_and_wait(root_path, container_id, signal, timeout) {
+        Ok(true) => {
+            info!("Container {container_id} stopped after {signal}");
+            return;
+        }
+        Ok(false) => info!("Container {container_id} did not stop within its grace period"),
+        Err(e) => warn!("Failed to send {signal} to container {container_id}: {e}"),
+    }
+
+    match signal_and_wait(root_path, container_id, "SIGKILL", KILL_TIMEOUT) {
+        Ok(true) => info!("Container {container_id} killed"),
+        Ok(false) => warn!("Container {container_id} did not stop within timeout"),
+        Err(e) => warn!("Failed to kill container {container_id}: {e}"),
+    }
+}
+
+/// Send `signal` to all processes of a container and wait up to `timeout` for
+/// it to stop. Returns whether the container is stopped.
+pub fn signal_and_wait(
+    root_path: &Path,
+    container_id: &str,
+    signal: &str,
+    timeout: Duration,
+) -> Result<bool> {
+    if !is_running(root_path, container_id) {
+        return Ok(true);
+    }
+    let kill_args = Kill {
+        container_id: container_id.to_string(),
+        signal: signal.to_string(),
+        all: true,
+    };
+    kill(kill_args, root_path.to_path_buf())?;
+
+    let deadline = Instant::now() + timeout;
+    while Instant::now() < deadline {
+        thread::sleep(POLL_INTERVAL);
+        if !is_running(root_path, container_id) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn is_running(root_path: &Path, container_id: &str) -> bool {
+    load_container(root_path, container_id)
+        .map(|c| c.status() != ContainerStatus::Stopped)
+        .unwrap_or(false)
+}
+
+/// Run a lifecycle hook against a container, failing if it doesn't complete
+/// within `timeout`.
+pub fn run_handler(
+    root_path: &Path,
+    container_id: &str,
+    handler: &LifecycleHandler,
+    pod_ip: Option<&str>,
+    timeout: Duration,
+) -> Result<()> {
+    match handler {
+        LifecycleHandler::Exec(action) => run_exec(
+            root_path.to_path_buf(),
+            container_id,
+            &action.command,
+            timeout,
+        ),
+        LifecycleHandler::HttpGet(action) => run_http_get(action, pod_ip, timeout),
+    }
+}
+
+fn run_exec(
+    root_path: PathBuf,
+    container_id: &str,
+    command: &[String],
+    timeout: Duration,
+) -> Result<()> {
+    if command.is_empty() {
+        bail!("exec hook command cannot be empty");
+    }
+    let exec_args = Exec {
+        pod_name: None,
+        container_id: container_id.to_string(),
+        command: command.to_vec(),
+        console_socket: None,
+        cwd: None,
+        env: Vec::new(),
+        tty: false,
+        user: None,
+        additional_gids: Vec::new(),
+        process: None,
+        detach: false,
+        pid_file: None,
+        process_label: None,
+        apparmor: None,
+        no_new_privs: false,
+        cap: Vec::new(),
+        preserve_fds: 0,
+        ignore_paused: false,
+        cgroup: None,
+    };
+
+    // exec() blocks in waitpid(), so wait for it on a thread of its own. A
+    // hook running past the timeout is left behind, it ends with the
+    // container.
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(exec(exec_args, root_path));
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(Ok(0)) => Ok(()),
+        Ok(Ok(code)) => Err(anyhow!("exec hook exited with status {code}")),
+        Ok(Err(e)) => Err(anyhow!("exec hook error: {e}")),
+        Err(_) => Err(anyhow!("exec hook timed out after {timeout:?}")),
+    }
+}
+
+fn run_http_get(action: &HttpGetAction, pod_ip: Option<&str>, timeout: Duration) -> Result<()> {
+    let host = action
+        .host
+        .as_deref()
+        .or(pod_ip)
+        .ok_or_else(|| anyhow!("http hook has no host and the pod has no IP"))?;
+    if timeout.is_zero() {
+        bail!("http hook has no time left to run");
+    }
+    let addr = (host, action.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("could not resolve {host}"))?;
+
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: rkl-lifecycle/0.1\r\nConnection: close\r\n\r\n",
+        action.path
+    )?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line)?;
+    let status_code: u16 = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| anyhow!("http hook received malformed status line: {status_line}"))?;
+    if (200..400).contains(&status_code) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "http hook non-success status: {}",
+            status_line.trim()
+        ))
+    }
+}
@@ -1,25 +1,37 @@
 use crate::commands::ExecPod;
+use crate::commands::container::checkpoint::CheckpointArgs;
 use crate::commands::pod::standalone::{exec_pod, start_pod, state_pod};
 use crate::daemon;
+use crate::logs;
 use crate::task::TaskRunner;
 use anyhow::{Result, anyhow};
 use clap::{Args, Subcommand};
 use daemonize::Daemonize;
 use libcontainer::container::Container;
 use libruntime::rootpath;
+use std::collections::HashMap;
 use std::env;
 use std::fs::{self, File};
 use std::io::Read;
 use std::io::Write;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tracing::info;
 
-use common::PodTask;
+use common::{ContainerStatus, EphemeralContainer, PodLogOptions, PodTask};
 use libcontainer::syscall::syscall::create_syscall;
 
+pub mod checkpoint;
 pub mod cluster;
+pub mod debug;
+pub mod downward;
+pub mod lifecycle;
+pub mod session;
 pub mod standalone;
+pub mod userns;
+
+use lifecycle::ContainerLifecycle;
 
 #[derive(Debug, Clone)]
 pub struct PodRunResult {
@@ -96,6 +108,39 @@ pub enum PodCommand {
     #[command(about = "Execute a command inside a specific container of a pod")]
     Exec(Box<ExecPod>),
 
+    #[command(about = "Add an ephemeral container to a running pod to debug it")]
+    Debug {
+        #[arg(value_name = "POD_NAME")]
+        pod_name: String,
+
+        /// Image of the ephemeral container
+        #[arg(long)]
+        image: String,
+
+        /// Container whose process namespace the ephemeral container joins
+        #[arg(long)]
+        target: Option<String>,
+
+        /// Name of the ephemeral container, generated if not given
+        #[arg(short, long)]
+        container: Option<String>,
+
+        /// Command run in the ephemeral container instead of the image's
+        #[arg(last = true)]
+        command: Vec<String>,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
     #[command(about = "List all of pods")]
     List {
         #[arg(
@@ -127,6 +172,130 @@ pub enum PodCommand {
         tls_cfg: TLSConnectionArgs,
     },
 
+    #[command(about = "Print the logs of a container in a pod")]
+    Logs {
+        #[arg(value_name = "POD_NAME")]
+        pod_name: String,
+
+        /// Container to print the logs of, may be omitted for single-container pods
+        #[arg(short, long)]
+        container: Option<String>,
+
+        /// Keep printing new log lines
+        #[arg(short, long)]
+        follow: bool,
+
+        /// Print the logs of the previous instance of the container
+        #[arg(short, long)]
+        previous: bool,
+
+        /// Only print lines newer than a relative duration like 30s, 5m or 2h
+        #[arg(long, value_parser = humantime::parse_duration)]
+        since: Option<Duration>,
+
+        /// Number of lines from the end of the logs to print
+        #[arg(long)]
+        tail: Option<usize>,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
+    #[command(about = "Print the output of a running container in a pod")]
+    Attach {
+        #[arg(value_name = "POD_NAME")]
+        pod_name: String,
+
+        /// Container to attach to, may be omitted for single-container pods
+        #[arg(short, long)]
+        container: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
+    #[command(about = "Forward local ports to ports of a pod")]
+    PortForward {
+        #[arg(value_name = "POD_NAME")]
+        pod_name: String,
+
+        /// Ports to forward, as LOCAL_PORT:REMOTE_PORT or a single PORT
+        #[arg(value_name = "PORT", required = true, value_parser = session::parse_port_mapping)]
+        ports: Vec<(u16, u16)>,
+
+        /// Local address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        address: IpAddr,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
+    #[command(about = "Checkpoint the containers of a local pod with CRIU into an archive")]
+    Checkpoint {
+        #[arg(value_name = "POD_NAME")]
+        pod_name: String,
+
+        /// Archive to write the checkpoint to
+        #[arg(long, value_name = "FILE")]
+        export: PathBuf,
+
+        #[clap(flatten)]
+        args: CheckpointArgs,
+    },
+
+    #[command(about = "Restore a local pod from a checkpoint archive")]
+    Restore {
+        /// Archive to read the checkpoint from
+        #[arg(long, value_name = "FILE")]
+        import: PathBuf,
+    },
+
+    #[command(about = "Move a running pod with its state to another node")]
+    Migrate {
+        #[arg(value_name = "POD_NAME")]
+        pod_name: String,
+
+        /// Node to move the pod to, picked by the scheduler if not given
+        #[arg(long)]
+        node: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
     // Run as a daemon process.
     // For convenient, I won't remove cli part now.
     #[command(
@@ -143,9 +312,72 @@ pub enum PodCommand {
 pub struct PodInfo {
     pub pod_sandbox_id: String,
     pub container_names: Vec<String>,
+    /// Directory the container logs are written to.
+    pub log_directory: Option<String>,
+    pub pod_ip: Option<String>,
+    /// Seconds the containers get to stop before being killed.
+    pub termination_grace_period: Option<u64>,
+    /// Hooks and stop signals, keyed by container name.
+    pub lifecycles: HashMap<String, ContainerLifecycle>,
+    /// The pod as it was submitted, needed to rebuild it from a checkpoint.
+    pub pod_spec: Option<PodTask>,
+    /// Final statuses of the init containers, keyed by container name.
+    pub init_container_statuses: Vec<ContainerStatus>,
+    /// Ephemeral containers added to the running pod, never restarted.
+    pub ephemeral_containers: Vec<String>,
 }
 
 impl PodInfo {
+    pub fn new(
+        task_runner: &TaskRunner,
+        pod_sandbox_id: String,
+        container_names: Vec<String>,
+        pod_ip: &str,
+    ) -> Self {
+        let lifecycles = task_runner
+            .task
+            .spec
+            .init_containers
+            .iter()
+            .filter(|c| c.is_sidecar())
+            .chain(&task_runner.task.spec.containers)
+            .map(|c| {
+                let lifecycle = ContainerLifecycle {
+                    stop_signal: task_runner.stop_signal(&c.name),
+                    hooks: c.lifecycle.clone().unwrap_or_default(),
+                };
+                (c.name.clone(), lifecycle)
+            })
+            .filter(|(_, lifecycle)| *lifecycle != ContainerLifecycle::default())
+            .collect();
+        // TaskRunner prefixes the container names with the pod name
+        let mut pod_spec = task_runner.task.clone();
+        let prefix = format!("{}-", pod_spec.metadata.name);
+        for container in pod_spec
+            .spec
+            .init_containers
+            .iter_mut()
+            .chain(&mut pod_spec.spec.containers)
+        {
+            if let Some(name) = container.name.strip_prefix(&prefix) {
+                container.name = name.to_string();
+            }
+        }
+        PodInfo {
+            pod_sandbox_id,
+            container_names,
+            log_directory: task_runner.log_directory(),
+            pod_ip: Some(pod_ip.split('/').next().unwrap_or(pod_ip))
+                .filter(|ip| !ip.is_empty())
+                .map(str::to_string),
+            termination_grace_period: Some(task_runner.task.spec.termination_grace_period()),
+            lifecycles,
+            pod_spec: Some(pod_spec),
+            init_container_statuses: task_runner.init_container_statuses().to_vec(),
+            ephemeral_containers: Vec::new(),
+        }
+    }
+
     pub fn load(root_path: &Path, pod_name: &str) -> Result<Self> {
         // get path like pods/podname
         let pod_info_path = root_path.join("pods").join(pod_name);
@@ -156,9 +388,33 @@ impl PodInfo {
 
         let mut pod_sandbox_id = None;
         let mut container_names = Vec::new();
+        let mut log_directory = None;
+        let mut pod_ip = None;
+        let mut termination_grace_period = None;
+        let mut lifecycles = HashMap::new();
+        let mut pod_spec = None;
+        let mut init_container_statuses = Vec::new();
+        let mut ephemeral_containers = Vec::new();
         for line in contents.lines() {
             if line.starts_with("PodSandbox ID: ") {
                 pod_sandbox_id = Some(line.trim_start_matches("PodSandbox ID: ").to_string());
+            } else if let Some(dir) = line.strip_prefix("Log Directory: ") {
+                log_directory = Some(dir.to_string());
+            } else if let Some(ip) = line.strip_prefix("Pod IP: ") {
+                pod_ip = Some(ip.to_string());
+            } else if let Some(secs) = line.strip_prefix("Termination Grace Period: ") {
+                termination_grace_period = Some(secs.parse()?);
+            } else if let Some(entry) = line.strip_prefix("Lifecycle ") {
+                let (name, json) = entry
+                    .split_once(": ")
+                    .ok_or_else(|| anyhow!("invalid lifecycle entry for Pod {}", pod_name))?;
+                lifecycles.insert(name.to_string(), serde_json::from_str(json)?);
+            } else if let Some(json) = line.strip_prefix("Pod Spec: ") {
+                pod_spec = Some(serde_json::from_str(json)?);
+            } else if let Some(json) = line.strip_prefix("Init Container Status: ") {
+                init_container_statuses.push(serde_json::from_str(json)?);
+            } else if let Some(name) = line.strip_prefix("Ephemeral Container: ") {
+                ephemeral_containers.push(name.to_string());
             } else if line.starts_with("- ") {
                 let container_name = line.trim_start_matches("- ").to_string();
                 container_names.push(container_name);
@@ -170,6 +426,13 @@ impl PodInfo {
         Ok(PodInfo {
             pod_sandbox_id,
             container_names,
+            log_directory,
+            pod_ip,
+            termination_grace_period,
+            lifecycles,
+            pod_spec,
+            init_container_statuses,
+            ephemeral_containers,
         })
     }
 
@@ -198,6 +461,35 @@ impl PodInfo {
 
         let mut file = File::create(&pod_info_path)?;
         writeln!(file, "PodSandbox ID: {}", self.pod_sandbox_id)?;
+        if let Some(dir) = &self.log_directory {
+            writeln!(file, "Log Directory: {dir}")?;
+        }
+        if let Some(ip) = &self.pod_ip {
+            writeln!(file, "Pod IP: {ip}")?;
+        }
+        if let Some(secs) = self.termination_grace_period {
+            writeln!(file, "Termination Grace Period: {secs}")?;
+        }
+        for (name, lifecycle) in &self.lifecycles {
+            writeln!(
+                file,
+                "Lifecycle {name}: {}",
+                serde_json::to_string(lifecycle)?
+            )?;
+        }
+        if let Some(pod_spec) = &self.pod_spec {
+            writeln!(file, "Pod Spec: {}", serde_json::to_string(pod_spec)?)?;
+        }
+        for status in &self.init_container_statuses {
+            writeln!(
+                file,
+                "Init Container Status: {}",
+                serde_json::to_string(status)?
+            )?;
+        }
+        for name in &self.ephemeral_containers {
+            writeln!(file, "Ephemeral Container: {name}")?;
+        }
         writeln!(file, "Containers:")?;
         for container_name in &self.container_names {
             writeln!(file, "- {container_name}")?;
@@ -211,6 +503,17 @@ impl PodInfo {
         Ok(())
     }
 
+    /// Whether `container_name` is a sidecar, an init container kept running
+    /// alongside the workload containers.
+    pub fn is_sidecar(&self, container_name: &str) -> bool {
+        let Some(pod) = &self.pod_spec else {
+            return false;
+        };
+        pod.spec.init_containers.iter().any(|c| {
+            c.is_sidecar() && format!("{}-{}", pod.metadata.name, c.name) == container_name
+        })
+    }
+
     pub fn get_pod_containers(&self, root_path: &Path) -> Result<Vec<Container>> {
         let mut containers = Vec::new();
         for container_name in &self.container_names {
@@ -235,20 +538,19 @@ pub fn sync_run_pod_from_taskrunner(
     let (pod_sandbox_id, podip) = task_runner.sync_run()?;
     info!("PodSandbox ID: {}", pod_sandbox_id);
 
-    let container_names: Vec<String> = task_runner
-        .task
-        .spec
-        .containers
-        .iter()
-        .map(|c| c.name.clone())
-        .collect();
+    let container_names = task_runner.container_names();
 
     let root_path = rootpath::determine(None, &*create_syscall())?;
-    let pod_info = PodInfo {
-        pod_sandbox_id: pod_sandbox_id.clone(),
-        container_names: container_names.clone(),
-    };
+    let pod_info = PodInfo::new(
+        &task_runner,
+        pod_sandbox_id.clone(),
+        container_names.clone(),
+        &podip,
+    );
+    // saved without the workload containers while the init containers ran
+    PodInfo::delete(&root_path, &pod_name)?;
     pod_info.save(&root_path, &pod_name)?;
+    lifecycle::run_post_start_hooks(&root_path, &pod_info);
 
     info!("Pod {} created and started successfully", pod_name);
     Ok(PodRunResult {
@@ -266,20 +568,19 @@ pub async fn run_pod_from_taskrunner(
     let (pod_sandbox_id, podip) = task_runner.run().await?;
     info!("PodSandbox ID: {}", pod_sandbox_id);
 
-    let container_names: Vec<String> = task_runner
-        .task
-        .spec
-        .containers
-        .iter()
-        .map(|c| c.name.clone())
-        .collect();
+    let container_names = task_runner.container_names();
 
     let root_path = rootpath::determine(None, &*create_syscall())?;
-    let pod_info = PodInfo {
-        pod_sandbox_id: pod_sandbox_id.clone(),
-        container_names: container_names.clone(),
-    };
+    let pod_info = PodInfo::new(
+        &task_runner,
+        pod_sandbox_id.clone(),
+        container_names.clone(),
+        &podip,
+    );
+    // saved without the workload containers while the init containers ran
+    PodInfo::delete(&root_path, &pod_name)?;
     pod_info.save(&root_path, &pod_name)?;
+    lifecycle::run_post_start_hooks(&root_path, &pod_info);
 
     info!("Pod {} created and started successfully", pod_name);
     Ok(PodRunResult {
@@ -340,9 +641,21 @@ pub fn pod_execute(cmd: PodCommand) -> Result<()> {
         } => pod_delete(&pod_name, cluster, tls_cfg),
         PodCommand::State { pod_name } => state_pod(&pod_name),
         PodCommand::Exec(exec) => {
-            let exit_code = exec_pod(*exec)?;
+            let exit_code = pod_exec(*exec)?;
             std::process::exit(exit_code);
         }
+        PodCommand::Debug {
+            pod_name,
+            image,
+            target,
+            container,
+            command,
+            cluster,
+            tls_cfg,
+        } => {
+            let container = debug::ephemeral_container(container, image, target, command);
+            pod_debug(&pod_name, container, cluster, tls_cfg)
+        }
         PodCommand::Daemon { tls_cfg } => start_daemon(tls_cfg),
         PodCommand::List { cluster, tls_cfg } => pod_list(cluster, tls_cfg),
         PodCommand::Get {
@@ -350,6 +663,142 @@ pub fn pod_execute(cmd: PodCommand) -> Result<()> {
             cluster,
             tls_cfg,
         } => pod_get(&pod_name, cluster, tls_cfg),
+        PodCommand::Logs {
+            pod_name,
+            container,
+            follow,
+            previous,
+            since,
+            tail,
+            cluster,
+            tls_cfg,
+        } => {
+            let options = PodLogOptions {
+                container,
+                previous,
+                since_seconds: since.map(|d| d.as_secs()),
+                since_time: None,
+                tail_lines: tail,
+            };
+            pod_logs(&pod_name, options, follow, cluster, tls_cfg)
+        }
+        PodCommand::Attach {
+            pod_name,
+            container,
+            cluster,
+            tls_cfg,
+        } => {
+            let exit_code = pod_attach(&pod_name, container, cluster, tls_cfg)?;
+            std::process::exit(exit_code);
+        }
+        PodCommand::PortForward {
+            pod_name,
+            ports,
+            address,
+            cluster,
+            tls_cfg,
+        } => pod_port_forward(&pod_name, ports, address, cluster, tls_cfg),
+        PodCommand::Checkpoint {
+            pod_name,
+            export,
+            args,
+        } => checkpoint::checkpoint_pod(&pod_name, &args.into(), &export),
+        PodCommand::Restore { import } => checkpoint::restore_pod(&import).map(|_| ()),
+        PodCommand::Migrate {
+            pod_name,
+            node,
+            cluster,
+            tls_cfg,
+        } => pod_migrate(&pod_name, node, cluster, tls_cfg),
+    }
+}
+
+fn pod_exec(exec: ExecPod) -> Result<i32> {
+    let env_addr = env::var("RKS_ADDRESS").ok();
+    match exec.cluster.clone().or(env_addr) {
+        Some(rks_addr) => {
+            let rt = tokio::runtime::Runtime::new()?;
+            let result = rt.block_on(session::exec(
+                &exec.pod_name,
+                &exec.container_id,
+                exec.command,
+                exec.base.tty,
+                exec.stdin,
+                &rks_addr,
+                exec.tls_cfg,
+            ));
+            // Reading stdin blocks a runtime thread that can't be joined.
+            rt.shutdown_background();
+            result
+        }
+        None => exec_pod(exec),
+    }
+}
+
+fn pod_debug(
+    pod_name: &str,
+    container: EphemeralContainer,
+    addr: Option<String>,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let env_addr = env::var("RKS_ADDRESS").ok();
+    match addr.or(env_addr) {
+        Some(rks_addr) => {
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(cluster::debug_pod(pod_name, container, &rks_addr, tls_cfg))
+        }
+        None => debug::debug_pod(pod_name, container),
+    }
+}
+
+fn pod_attach(
+    pod_name: &str,
+    container: Option<String>,
+    addr: Option<String>,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<i32> {
+    let env_addr = env::var("RKS_ADDRESS").ok();
+    let rt = tokio::runtime::Runtime::new()?;
+    match addr.or(env_addr) {
+        Some(rks_addr) => rt.block_on(session::attach(pod_name, container, &rks_addr, tls_cfg)),
+        None => Err(anyhow!(
+            "No RKS address provided. Set RKS_ADDRESS or use --cluster"
+        )),
+    }
+}
+
+fn pod_port_forward(
+    pod_name: &str,
+    ports: Vec<(u16, u16)>,
+    address: IpAddr,
+    addr: Option<String>,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let env_addr = env::var("RKS_ADDRESS").ok();
+    let rt = tokio::runtime::Runtime::new()?;
+    match addr.or(env_addr) {
+        Some(rks_addr) => rt.block_on(session::port_forward(
+            pod_name, ports, address, &rks_addr, tls_cfg,
+        )),
+        None => Err(anyhow!(
+            "No RKS address provided. Set RKS_ADDRESS or use --cluster"
+        )),
+    }
+}
+
+fn pod_migrate(
+    pod_name: &str,
+    node: Option<String>,
+    addr: Option<String>,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let env_addr = env::var("RKS_ADDRESS").ok();
+    let rt = tokio::runtime::Runtime::new()?;
+    match addr.or(env_addr) {
+        Some(rks_addr) => rt.block_on(cluster::migrate_pod(pod_name, node, &rks_addr, tls_cfg)),
+        None => Err(anyhow!(
+            "No RKS address provided. Set RKS_ADDRESS or use --cluster"
+        )),
     }
 }
 
@@ -378,6 +827,27 @@ fn pod_get(pod_name: &str, addr: Option<String>, tls_cfg: TLSConnectionArgs) ->
     }
 }
 
+fn pod_logs(
+    pod_name: &str,
+    options: PodLogOptions,
+    follow: bool,
+    addr: Option<String>,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let env_addr = env::var("RKS_ADDRESS").ok();
+    match addr.or(env_addr) {
+        Some(rks_addr) => {
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(cluster::pod_logs(
+                pod_name, options, follow, &rks_addr, tls_cfg,
+            ))
+        }
+        None => logs::print_logs(&options, follow, |options| {
+            standalone::pod_logs(pod_name, options)
+        }),
+    }
+}
+
 fn pod_delete(pod_name: &str, addr: Option<String>, tls_cfg: TLSConnectionArgs) -> Result<()> {
     let env_addr = env::var("RKS_ADDRESS").ok();
     let rt = tokio::runtime::Runtime::new()?;
@@ -405,3 +875,61 @@ fn pod_create(pod_yaml: &str, addr: Option<String>, tls_cfg: TLSConnectionArgs)
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{ContainerState, ExecAction, Lifecycle, LifecycleHandler};
+
+    #[test]
+    fn test_pod_info_round_trip() {
+        let root = tempfile::tempdir().unwrap();
+        let lifecycle = ContainerLifecycle {
+            stop_signal: Some("SIGQUIT".to_string()),
+            hooks: Lifecycle {
+                post_start: None,
+                pre_stop: Some(LifecycleHandler::Exec(ExecAction {
+                    command: vec!["nginx".to_string(), "-s".to_string(), "quit".to_string()],
+                })),
+            },
+        };
+        let pod_info = PodInfo {
+            pod_sandbox_id: "web".to_string(),
+            container_names: vec!["web-nginx".to_string()],
+            log_directory: None,
+            pod_ip: Some("10.1.0.5".to_string()),
+            termination_grace_period: Some(60),
+            lifecycles: HashMap::from([("web-nginx".to_string(), lifecycle.clone())]),
+            pod_spec: None,
+            init_container_statuses: vec![ContainerStatus {
+                name: "web-init".to_string(),
+                state: Some(ContainerState::Terminated {
+                    exit_code: 0,
+                    signal: None,
+                    reason: Some("Completed".to_string()),
+                    message: None,
+                    started_at: None,
+                    finished_at: None,
+                }),
+                ready: true,
+                ..Default::default()
+            }],
+            ephemeral_containers: vec!["web-debugger".to_string()],
+        };
+        pod_info.save(root.path(), "web").unwrap();
+
+        let loaded = PodInfo::load(root.path(), "web").unwrap();
+        assert_eq!(loaded.container_names, vec!["web-nginx".to_string()]);
+        assert_eq!(loaded.pod_ip.as_deref(), Some("10.1.0.5"));
+        assert_eq!(loaded.termination_grace_period, Some(60));
+        assert_eq!(loaded.lifecycles.get("web-nginx"), Some(&lifecycle));
+        assert_eq!(
+            loaded.init_container_statuses,
+            pod_info.init_container_statuses
+        );
+        assert_eq!(
+            loaded.ephemeral_containers,
+            vec!["web-debugger".to_string()]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Client side of the interactive sessions relayed by rks: `pod exec` and
+//! `pod attach` against a cluster, and `pod port-forward`.
+
+use std::io::{self, IsTerminal};
+use std::net::IpAddr;
+use std::os::fd::AsRawFd;
+
+use anyhow::{Context, Result, anyhow, bail};
+use common::quic::RksConnection;
+use common::session::{Frame, SessionRecvExt, SessionRequest, SessionSendExt};
+use nix::sys::termios::{SetArg, Termios, cfmakeraw, tcgetattr, tcsetattr};
+use quinn::{RecvStream, SendStream};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::commands::pod::TLSConnectionArgs;
+use crate::quic::client::{Cli, QUICClient};
+
+const READ_BUFFER_SIZE: usize = 32 * 1024;
+
+/// Run `command` in a container of a pod anywhere in the cluster and return
+/// its exit code.
+pub async fn exec(
+    pod_name: &str,
+    container: &str,
+    command: Vec<String>,
+    tty: bool,
+    stdin: bool,
+    rks_addr: &str,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<i32> {
+    let cli = QUICClient::<Cli>::connect(rks_addr, &tls_cfg).await?;
+    info!("RKL connected to RKS at {rks_addr}");
+
+    let (mut send, recv) = cli.open_bi().await?.into_inner();
+    send.send_request(&SessionRequest::Exec {
+        pod_name: pod_name.to_string(),
+        container: Some(container.to_string()),
+        command,
+        tty,
+        stdin,
+    })
+    .await?;
+
+    let (tx, rx) = mpsc::channel(32);
+    tokio::spawn(write_frames(send, rx));
+
+    // Restored when dropped, also on errors.
+    let _raw_terminal = if tty { RawTerminal::enable()? } else { None };
+    if tty && let Some((cols, rows)) = terminal_size() {
+        let _ = tx.send(Frame::Resize { cols, rows }).await;
+        tokio::spawn(forward_resizes(tx.clone()));
+    }
+    if stdin {
+        tokio::spawn(forward_stdin(tx));
+    }
+
+    read_output(recv).await
+}
+
+/// Print the output of a running container until it exits.
+pub async fn attach(
+    pod_name: &str,
+    container: Option<String>,
+    rks_addr: &str,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<i32> {
+    let cli = QUICClient::<Cli>::connect(rks_addr, &tls_cfg).await?;
+    info!("RKL connected to RKS at {rks_addr}");
+
+    let (mut send, recv) = cli.open_bi().await?.into_inner();
+    send.send_request(&SessionRequest::Attach {
+        pod_name: pod_name.to_string(),
+        container,
+    })
+    .await?;
+    send.finish()?;
+
+    read_output(recv).await
+}
+
+/// Listen on `address` for each `(local, remote)` port pair and forward every
+/// connection to the remote port in the pod, until interrupted.
+pub async fn port_forward(
+    pod_name: &str,
+    ports: Vec<(u16, u16)>,
+    address: IpAddr,
+    rks_addr: &str,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(rks_addr, &tls_cfg).await?;
+    info!("RKL connected to RKS at {rks_addr}");
+
+    let mut listeners = Vec::new();
+    for (local, remote) in ports {
+        let listener = TcpListener::bind((address, local))
+            .await
+            .with_context(|| format!("failed to listen on {address}:{local}"))?;
+        println!("Forwarding from {} -> {remote}", listener.local_addr()?);
+        listeners.push(tokio::spawn(accept_loop(
+            listener,
+            (*cli).clone(),
+            pod_name.to_string(),
+            remote,
+        )));
+    }
+    for listener in listeners {
+        listener.await??;
+    }
+    Ok(())
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    conn: RksConnection,
+    pod_name: String,
+    port: u16,
+) -> Result<()> {
+    loop {
+        let (socket, peer) = listener.accept().await?;
+        println!("Handling connection for {}", listener.local_addr()?.port());
+        let conn = conn.clone();
+        let pod_name = pod_name.clone();
+        tokio::spawn(async move {
+            if let Err(e) = forward_connection(conn, socket, pod_name, port).await {
+                error!("port-forward of connection from {peer} failed: {e:#}");
+            }
+        });
+    }
+}
+
+async fn forward_connection(
+    conn: RksConnection,
+    socket: TcpStream,
+    pod_name: String,
+    port: u16,
+) -> Result<()> {
+    let (mut send, mut recv) = conn.open_bi().await?.into_inner();
+    send.send_request(&SessionRequest::PortForward { pod_name, port })
+        .await?;
+    let (mut reader, mut writer) = socket.into_split();
+
+    let upstream = async {
+        let mut buf = vec![0u8; READ_BUFFER_SIZE];
+        loop {
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            send.send_frame(&Frame::Stdin(buf[..n].to_vec())).await?;
+        }
+        send.send_frame(&Frame::CloseStdin).await?;
+        send.finish()?;
+        anyhow::Ok(())
+    };
+    let downstream = async {
+        while let Some(frame) = recv.recv_frame().await? {
+            match frame {
+                Frame::Stdout(data) => writer.write_all(&data).await?,
+                Frame::Error(msg) => bail!(msg),
+                other => warn!("unexpected port-forward frame {other:?}"),
+            }
+        }
+        writer.shutdown().await?;
+        anyhow::Ok(())
+    };
+    tokio::pin!(upstream, downstream);
+    tokio::select! {
+        res = &mut downstream => return res,
+        res = &mut upstream => res?,
+    }
+    downstream.await
+}
+
+/// Parse `LOCAL:REMOTE`, or a single port used for both.
+pub fn parse_port_mapping(s: &str) -> Result<(u16, u16)> {
+    let parse = |port: &str| {
+        port.parse::<u16>()
+            .map_err(|e| anyhow!("invalid port {port:?}: {e}"))
+    };
+    match s.split_once(':') {
+        Some((local, remote)) => Ok((parse(local)?, parse(remote)?)),
+        None => {
+            let port = parse(s)?;
+            Ok((port, port))
+        }
+    }
+}
+
+async fn write_frames(mut send: SendStream, mut rx: mpsc::Receiver<Frame>) -> Result<()> {
+    while let Some(frame) = rx.recv().await {
+        send.send_frame(&frame).await?;
+    }
+    send.finish()?;
+    Ok(())
+}
+
+/// Copy the session output to stdout and stderr, and return the exit code.
+async fn read_output(mut recv: RecvStream) -> Result<i32> {
+    let mut stdout = tokio::io::stdout();
+    let mut stderr = tokio::io::stderr();
+    while let Some(frame) = recv.recv_frame().await? {
+        match frame {
+            Frame::Stdout(data) => {
+                stdout.write_all(&data).await?;
+                stdout.flush().await?;
+            }
+            Frame::Stderr(data) => {
+                stderr.write_all(&data).await?;
+                stderr.flush().await?;
+            }
+            Frame::Exit(code) => return Ok(code),
+            Frame::Error(msg) => bail!(msg),
+            other => warn!("unexpected session frame {other:?}"),
+        }
+    }
+    bail!("session closed before the process exited")
+}
+
+async fn forward_stdin(tx: mpsc::Sender<Frame>) -> Result<()> {
+    let mut stdin = tokio::io::stdin();
+    let mut buf = vec![0u8; READ_BUFFER_SIZE];
+    loop {
+        let n = stdin.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        tx.send(Frame::Stdin(buf[..n].to_vec())).await?;
+    }
+    tx.send(Frame::CloseStdin).await?;
+    Ok(())
+}
+
+async fn forward_resizes(tx: mpsc::Sender<Frame>) -> Result<()> {
+    let mut winch = signal(SignalKind::window_change())?;
+    while winch.recv().await.is_some() {
+        if let Some((cols, rows)) = terminal_size() {
+            tx.send(Frame::Resize { cols, rows }).await?;
+        }
+    }
+    Ok(())
+}
+
+fn terminal_size() -> Option<(u16, u16)> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes to the winsize passed to it.
+    let res = unsafe { libc::ioctl(io::stdout().as_raw_fd(), libc::TIOCGWINSZ, &mut size) };
+    (res == 0).then_some((size.ws_col, size.ws_row))
+}
+
+/// Puts the local terminal into raw mode, so that keys like Ctrl-C reach the
+/// remote process, and restores it when dropped.
+struct RawTerminal(Termios);
+
+impl RawTerminal {
+    fn enable() -> Result<Option<Self>> {
+        let stdin = io::stdin();
+        if !stdin.is_terminal() {
+            return Ok(None);
+        }
+        let original = tcgetattr(&stdin)?;
+        let mut raw = original.clone();
+        cfmakeraw(&mut raw);
+        tcsetattr(&stdin, SetArg::TCSANOW, &raw)?;
+        Ok(Some(Self(original)))
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = tcsetattr(io::stdin(), SetArg::TCSANOW, &self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_port_mapping() {
+        assert_eq!(parse_port_mapping("8080:80").unwrap(), (8080, 80));
+        assert_eq!(parse_port_mapping("5432").unwrap(), (5432, 5432));
+        assert!(parse_port_mapping("8080:").is_err());
+        assert!(parse_port_mapping("70000").is_err());
+    }
+}
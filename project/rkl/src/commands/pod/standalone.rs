@@ -1,12 +1,16 @@
-use crate::commands::pod::PodInfo;
+use crate::commands::pod::{PodInfo, downward, lifecycle, userns};
 use crate::commands::{Exec, ExecPod};
 use crate::commands::{delete, exec, kill, load_container, start, state};
+use crate::logs::{self, LogLine};
 use crate::task::{self, TaskRunner};
 use anyhow::{Result, anyhow};
+use chrono::Utc;
+use common::PodLogOptions;
 use libcontainer::container::ContainerStatus;
 use liboci_cli::{Delete, Kill, Start, State};
 use libruntime::rootpath;
 use rkforge::commands::container::rootfs_mount::RootfsMount;
+use std::path::Path;
 use std::thread;
 use std::time::Duration;
 use tracing::{debug, error, info, warn};
@@ -71,14 +75,19 @@ pub fn delete_pod(pod_name: &str) -> Result<(), anyhow::Error> {
         .state
         .pid
         .ok_or_else(|| anyhow!("PID not found for container {}", pod_name))?;
+
+    // First, stop all containers gracefully. The network stays up meanwhile
+    // so they can finish in-flight requests.
+    lifecycle::terminate_containers(&root_path, &pod_info);
     remove_pod_network(pid_i32)?;
 
-    // First, kill all containers and wait for them to stop
-    for container_name in &pod_info.container_names {
-        if let Err(e) = kill_and_wait_container(&root_path, container_name) {
-            warn!("Failed to kill container {}: {}", container_name, e);
-        }
-    }
+    remove_pod(&root_path, pod_name, &pod_info)
+}
+
+/// Kill what is left of a pod whose network is already torn down, and
+/// remove its containers, logs and pod file.
+pub fn remove_pod(root_path: &Path, pod_name: &str, pod_info: &PodInfo) -> Result<()> {
+    let root_path = root_path.to_path_buf();
 
     // Kill the pause container
     if let Err(e) = kill_and_wait_container(&root_path, &pod_info.pod_sandbox_id) {
@@ -88,8 +97,12 @@ pub fn delete_pod(pod_name: &str) -> Result<(), anyhow::Error> {
         );
     }
 
-    // Now delete all containers
-    for container_name in &pod_info.container_names {
+    // Now delete all containers, the ephemeral ones included
+    for container_name in pod_info
+        .container_names
+        .iter()
+        .chain(&pod_info.ephemeral_containers)
+    {
         // Get bundle_path for cleaning up overlay mount
         let bundle_path = load_container(root_path.clone(), container_name)
             .ok()
@@ -132,6 +145,29 @@ pub fn delete_pod(pod_name: &str) -> Result<(), anyhow::Error> {
     } else {
         info!("PodSandbox deleted: {}", pod_info.pod_sandbox_id);
     }
+    task::remove_sandbox_bundle(&root_path, &pod_info.pod_sandbox_id);
+
+    // delete container logs and the log shims' fifo directories
+    if let Some(dir) = &pod_info.log_directory
+        && let Err(e) = std::fs::remove_dir_all(dir)
+    {
+        warn!("Failed to remove log directory {dir}: {e}");
+    }
+    for container_name in pod_info
+        .container_names
+        .iter()
+        .chain(&pod_info.ephemeral_containers)
+    {
+        let _ = std::fs::remove_dir_all(root_path.join("logs").join(container_name));
+    }
+
+    if let Err(e) = downward::remove_volumes(&root_path, pod_name) {
+        warn!("Failed to remove downwardAPI volumes of pod {pod_name}: {e}");
+    }
+
+    if let Err(e) = userns::release(pod_name) {
+        warn!("Failed to release user namespace range of pod {pod_name}: {e}");
+    }
 
     // delete pod file
     PodInfo::delete(&root_path, pod_name)?;
@@ -139,6 +175,60 @@ pub fn delete_pod(pod_name: &str) -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+/// Read the logs of a container of a local pod.
+///
+/// `options.container` may be the name from the pod spec or the full
+/// container name, and can be left out if the pod has a single container.
+pub fn pod_logs(pod_name: &str, options: &PodLogOptions) -> Result<Vec<LogLine>> {
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let pod_info = PodInfo::load(&root_path, pod_name)?;
+    let log_directory = pod_info
+        .log_directory
+        .as_ref()
+        .ok_or_else(|| anyhow!("Pod {} has no log directory", pod_name))?;
+
+    let container = resolve_container(pod_name, &pod_info, options.container.as_deref())?;
+
+    logs::read_logs(
+        &Path::new(log_directory).join(container),
+        options,
+        Utc::now(),
+    )
+}
+
+/// Find the full name of `container` in a pod, which may be given with or
+/// without the pod name prefix. It may be omitted for single-container pods.
+/// Ephemeral containers are only found by name.
+pub fn resolve_container<'a>(
+    pod_name: &str,
+    pod_info: &'a PodInfo,
+    container: Option<&str>,
+) -> Result<&'a String> {
+    match container {
+        Some(name) => {
+            let full_name = format!("{pod_name}-{name}");
+            pod_info
+                .container_names
+                .iter()
+                .chain(&pod_info.ephemeral_containers)
+                .find(|c| **c == full_name || *c == name)
+                .ok_or_else(|| anyhow!("container {} is not valid for pod {}", name, pod_name))
+        }
+        None => match pod_info.container_names.as_slice() {
+            [only] => Ok(only),
+            names => Err(anyhow!(
+                "a container name must be specified for pod {}, choose one of: [{}]",
+                pod_name,
+                names
+                    .iter()
+                    .map(|n| n.strip_prefix(&format!("{pod_name}-")).unwrap_or(n))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )),
+        },
+    }
+}
+
 pub fn remove_pod_network(pid: i32) -> Result<(), anyhow::Error> {
     let mut cni = task::get_cni()?;
     cni.load_default_conf();
@@ -154,6 +244,14 @@ pub fn remove_pod_network(pid: i32) -> Result<(), anyhow::Error> {
 pub fn create_pod(pod_yaml: &str) -> Result<(), anyhow::Error> {
     let mut task_runner = TaskRunner::from_file(pod_yaml)?;
     let pod_name = task_runner.task.metadata.name.clone();
+    // Init containers have to complete before the workload containers are
+    // created, which needs the pod to be run at once.
+    if !task_runner.task.spec.init_containers.is_empty() {
+        return Err(anyhow!(
+            "Pod {pod_name} has init containers, use `rkl pod run` to run it"
+        ));
+    }
+    task_runner.allocate_id_range()?;
 
     let pod_request = task_runner.build_run_pod_sandbox_request();
     let config = pod_request
@@ -161,7 +259,7 @@ pub fn create_pod(pod_yaml: &str) -> Result<(), anyhow::Error> {
         .as_ref()
         .ok_or_else(|| anyhow!("PodSandbox config is required"))?;
     task_runner.sandbox_config = Some(config.clone());
-    let (pod_response, _) = task_runner.sync_run_pod_sandbox(pod_request)?;
+    let (pod_response, pod_ip) = task_runner.sync_run_pod_sandbox(pod_request)?;
     let pod_sandbox_id = pod_response.pod_sandbox_id;
 
     let pause_pid = task_runner.pause_pid.ok_or_else(|| {
@@ -189,10 +287,7 @@ pub fn create_pod(pod_yaml: &str) -> Result<(), anyhow::Error> {
     }
 
     let root_path = rootpath::determine(None, &*create_syscall())?;
-    let pod_info = PodInfo {
-        pod_sandbox_id,
-        container_names: container_ids,
-    };
+    let pod_info = PodInfo::new(&task_runner, pod_sandbox_id, container_ids, &pod_ip);
     pod_info.save(&root_path, &pod_name)?;
 
     info!("Pod {} created successfully", pod_name);
@@ -215,6 +310,7 @@ pub fn start_pod(pod_name: &str) -> Result<(), anyhow::Error> {
             .map_err(|e| anyhow!("Failed to start container {}: {}", container_name, e))?;
         info!("Container started: {}", container_name);
     }
+    lifecycle::run_post_start_hooks(&root_path, &pod_info);
 
     info!("Pod {} started successfully", pod_name);
     Ok(())
@@ -0,0 +1,136 @@
+//! Subordinate uid/gid ranges of pods with `hostUsers: false`.
+//!
+//! Every such pod gets a range of [`RANGE_SIZE`] ids of its own, carved out of
+//! the `rkl` entries of `/etc/subuid` and `/etc/subgid`. Ranges are recorded in
+//! [`ALLOCATIONS_FILE`] so that a pod keeps its range across rkl restarts, and
+//! only go back to the pool when the pod is deleted.
+
+use anyhow::{Context, Result, anyhow};
+use libruntime::oci::userns::IdRange;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use tracing::{debug, info};
+
+pub const ALLOCATIONS_FILE: &str = "/var/lib/rkl/userns.json";
+/// Number of ids mapped into every pod, enough for the full 16-bit id space.
+pub const RANGE_SIZE: u32 = 65536;
+
+const SUBUID_FILE: &str = "/etc/subuid";
+const SUBGID_FILE: &str = "/etc/subgid";
+const SUBID_OWNER: &str = "rkl";
+/// Used when /etc/subuid or /etc/subgid has no `rkl` entry: 1024 pods
+/// starting at 2^31, far above the ranges handed out to regular users.
+const DEFAULT_SUBID_START: u32 = 1 << 31;
+const DEFAULT_SUBID_COUNT: u32 = RANGE_SIZE * 1024;
+
+/// Return the id range of `pod_name`, allocating a free one if the pod has none yet.
+pub fn allocate(pod_name: &str) -> Result<IdRange> {
+    let subuid = subid_range(Path::new(SUBUID_FILE))?;
+    let subgid = subid_range(Path::new(SUBGID_FILE))?;
+    let slots = subuid.1.min(subgid.1) / RANGE_SIZE;
+
+    let slot = with_allocations(|allocations| {
+        if let Some(slot) = allocations.get(pod_name) {
+            return Ok(*slot);
+        }
+        let slot = (0..slots)
+            .find(|slot| !allocations.values().any(|s| s == slot))
+            .ok_or_else(|| anyhow!("no free user namespace range left for pod {pod_name}"))?;
+        allocations.insert(pod_name.to_string(), slot);
+        info!("Allocated user namespace range {slot} to pod {pod_name}");
+        Ok(slot)
+    })?;
+
+    Ok(IdRange {
+        host_uid: subuid.0 + slot * RANGE_SIZE,
+        host_gid: subgid.0 + slot * RANGE_SIZE,
+        size: RANGE_SIZE,
+    })
+}
+
+/// Give the range of a deleted pod back to the pool.
+pub fn release(pod_name: &str) -> Result<()> {
+    if !Path::new(ALLOCATIONS_FILE).exists() {
+        return Ok(());
+    }
+    with_allocations(|allocations| {
+        if allocations.remove(pod_name).is_some() {
+            debug!("Released user namespace range of pod {pod_name}");
+        }
+        Ok(())
+    })
+}
+
+/// Run `f` on the allocations while holding an exclusive lock on the
+/// allocations file, then write them back.
+fn with_allocations<T>(f: impl FnOnce(&mut BTreeMap<String, u32>) -> Result<T>) -> Result<T> {
+    if let Some(dir) = Path::new(ALLOCATIONS_FILE).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(ALLOCATIONS_FILE)
+        .with_context(|| format!("failed to open {ALLOCATIONS_FILE}"))?;
+    file.lock()
+        .with_context(|| format!("failed to lock {ALLOCATIONS_FILE}"))?;
+
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let mut allocations: BTreeMap<String, u32> = if content.trim().is_empty() {
+        BTreeMap::new()
+    } else {
+        serde_json::from_str(&content).with_context(|| format!("invalid {ALLOCATIONS_FILE}"))?
+    };
+
+    let res = f(&mut allocations)?;
+    write_allocations(&mut file, &allocations)?;
+    Ok(res)
+}
+
+fn write_allocations(file: &mut File, allocations: &BTreeMap<String, u32>) -> Result<()> {
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(serde_json::to_string_pretty(allocations)?.as_bytes())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// The `(start, count)` of the `rkl` entry of a subordinate id file.
+fn subid_range(path: &Path) -> Result<(u32, u32)> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    Ok(parse_subid(&content).unwrap_or((DEFAULT_SUBID_START, DEFAULT_SUBID_COUNT)))
+}
+
+fn parse_subid(content: &str) -> Option<(u32, u32)> {
+    content.lines().find_map(|line| {
+        let mut fields = line.trim().split(':');
+        if fields.next()? != SUBID_OWNER {
+            return None;
+        }
+        let start = fields.next()?.parse().ok()?;
+        let count = fields.next()?.parse().ok()?;
+        Some((start, count))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_subid() {
+        let content = "alice:100000:65536\nrkl:1000000:6553600\n";
+        assert_eq!(parse_subid(content), Some((1000000, 6553600)));
+        assert_eq!(parse_subid("alice:100000:65536\n"), None);
+        assert_eq!(parse_subid("rkl:bad:65536\n"), None);
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{Result, anyhow};
+use common::{RksMessage, SECRET_TYPE_DOCKER_CONFIG_JSON, Secret};
+use std::fs::File;
+use std::io::{self, Write};
+use tabwriter::TabWriter;
+
+use crate::commands::format_duration;
+use crate::commands::pod::TLSConnectionArgs;
+use crate::commands::secret::registry_credentials;
+use crate::quic::client::{Cli, QUICClient};
+
+/// Create a new Secret
+pub async fn create_secret(
+    secret_yaml: &str,
+    addr: &str,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+    println!("RKL connected to RKS at {addr}");
+
+    let secret = secret_from_path(secret_yaml)?;
+    let secret_name = secret.metadata.name.clone();
+
+    cli.send_msg(&RksMessage::CreateSecret(secret)).await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::Ack => {
+            println!("secret/{secret_name} created");
+            Ok(())
+        }
+        RksMessage::Error(err) => Err(anyhow!("Failed to create secret: {}", err)),
+        msg => Err(anyhow!("Unexpected response: {:?}", msg)),
+    }
+}
+
+/// Delete a Secret by name
+pub async fn delete_secret(
+    secret_name: &str,
+    addr: &str,
+    tls_cfg: TLSConnectionArgs,
+) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+    println!("RKL connected to RKS at {addr}");
+
+    cli.send_msg(&RksMessage::DeleteSecret(secret_name.to_string()))
+        .await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::Ack => {
+            println!("secret/{secret_name} deleted");
+            Ok(())
+        }
+        RksMessage::Error(err) => Err(anyhow!("Failed to delete secret: {}", err)),
+        msg => Err(anyhow!("Unexpected response: {:?}", msg)),
+    }
+}
+
+/// Get a specific Secret
+pub async fn get_secret(secret_name: &str, addr: &str, tls_cfg: TLSConnectionArgs) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+    println!("RKL connected to RKS at {addr}");
+
+    cli.send_msg(&RksMessage::GetSecret(secret_name.to_string()))
+        .await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::GetSecretRes(secret) => {
+            let yaml = serde_yaml::to_string(&*secret)?;
+            println!("{}", yaml);
+            Ok(())
+        }
+        RksMessage::Error(err) => Err(anyhow!("Failed to get secret: {}", err)),
+        msg => Err(anyhow!("Unexpected response: {:?}", msg)),
+    }
+}
+
+/// List all Secrets
+pub async fn list_secrets(addr: &str, tls_cfg: TLSConnectionArgs) -> Result<()> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+    println!("RKL connected to RKS at {addr}");
+
+    cli.send_msg(&RksMessage::ListSecret).await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::ListSecretRes(secrets) => list_print(secrets),
+        RksMessage::Error(err) => Err(anyhow!("Failed to list secrets: {}", err)),
+        msg => Err(anyhow!("Unexpected response: {:?}", msg)),
+    }
+}
+
+fn secret_from_path(secret_yaml: &str) -> Result<Box<Secret>> {
+    let secret_file = File::open(secret_yaml)
+        .map_err(|e| anyhow!("Failed to open file '{}': {}", secret_yaml, e))?;
+    let secret: Secret =
+        serde_yaml::from_reader(secret_file).map_err(|e| anyhow!("Failed to parse YAML: {}", e))?;
+
+    if secret.metadata.name.is_empty() {
+        return Err(anyhow!("Secret metadata.name must not be empty"));
+    }
+    if secret.secret_type == SECRET_TYPE_DOCKER_CONFIG_JSON {
+        registry_credentials(&secret)?;
+    }
+
+    Ok(Box::new(secret))
+}
+
+fn list_print(secrets: Vec<Secret>) -> Result<()> {
+    let mut tab_writer = TabWriter::new(io::stdout());
+    writeln!(&mut tab_writer, "NAME\tTYPE\tDATA\tAGE")?;
+
+    for secret in secrets {
+        let age = secret
+            .metadata
+            .creation_timestamp
+            .map(|ts| format_duration(chrono::Utc::now().signed_duration_since(ts)))
+            .unwrap_or_else(|| "<unknown>".to_string());
+
+        writeln!(
+            &mut tab_writer,
+            "{}\t{}\t{}\t{}",
+            secret.metadata.name,
+            secret.secret_type,
+            secret.data.len(),
+            age
+        )?;
+    }
+
+    tab_writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,288 @@
+use anyhow::{Context, Result, anyhow, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use clap::Subcommand;
+use common::{DOCKER_CONFIG_JSON_KEY, SECRET_TYPE_DOCKER_CONFIG_JSON, Secret};
+use rkforge::pull::RegistryAuth;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+
+use crate::commands::pod::TLSConnectionArgs;
+
+pub mod cluster;
+
+#[derive(Subcommand)]
+pub enum SecretCommand {
+    #[command(about = "Create a Secret from a YAML file")]
+    Create {
+        #[arg(value_name = "SECRET_YAML")]
+        secret_yaml: String,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
+    #[command(about = "Delete a Secret by name")]
+    Delete {
+        #[arg(value_name = "SECRET_NAME")]
+        secret_name: String,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
+    #[command(about = "Get details of a specific Secret")]
+    Get {
+        #[arg(value_name = "SECRET_NAME")]
+        secret_name: String,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
+    #[command(about = "List all Secrets")]
+    List {
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+}
+
+pub fn secret_execute(cmd: SecretCommand) -> Result<()> {
+    let rt = tokio::runtime::Runtime::new()?;
+    match cmd {
+        SecretCommand::Create {
+            secret_yaml,
+            cluster,
+            tls_cfg,
+        } => rt.block_on(cluster::create_secret(
+            &secret_yaml,
+            &rks_address(cluster)?,
+            tls_cfg,
+        )),
+        SecretCommand::Delete {
+            secret_name,
+            cluster,
+            tls_cfg,
+        } => rt.block_on(cluster::delete_secret(
+            &secret_name,
+            &rks_address(cluster)?,
+            tls_cfg,
+        )),
+        SecretCommand::Get {
+            secret_name,
+            cluster,
+            tls_cfg,
+        } => rt.block_on(cluster::get_secret(
+            &secret_name,
+            &rks_address(cluster)?,
+            tls_cfg,
+        )),
+        SecretCommand::List { cluster, tls_cfg } => {
+            rt.block_on(cluster::list_secrets(&rks_address(cluster)?, tls_cfg))
+        }
+    }
+}
+
+fn rks_address(addr: Option<String>) -> Result<String> {
+    addr.or_else(|| env::var("RKS_ADDRESS").ok())
+        .ok_or_else(|| anyhow!("No RKS address provided. Set RKS_ADDRESS or use --cluster"))
+}
+
+/// A `~/.docker/config.json` file.
+#[derive(Deserialize)]
+struct DockerConfig {
+    #[serde(default)]
+    auths: HashMap<String, DockerAuth>,
+}
+
+#[derive(Deserialize)]
+struct DockerAuth {
+    username: Option<String>,
+    password: Option<String>,
+    /// Base64 encoded `username:password`.
+    auth: Option<String>,
+    #[serde(rename = "registrytoken")]
+    registry_token: Option<String>,
+}
+
+/// The registry credentials of a `kubernetes.io/dockerconfigjson` secret,
+/// by registry url as rkforge resolves it, e.g. `registry.example.com:5000`.
+pub fn registry_credentials(secret: &Secret) -> Result<HashMap<String, RegistryAuth>> {
+    if secret.secret_type != SECRET_TYPE_DOCKER_CONFIG_JSON {
+        bail!(
+            "secret {} has type {}, expected {SECRET_TYPE_DOCKER_CONFIG_JSON}",
+            secret.metadata.name,
+            secret.secret_type
+        );
+    }
+    let encoded = secret.data.get(DOCKER_CONFIG_JSON_KEY).ok_or_else(|| {
+        anyhow!(
+            "secret {} has no {DOCKER_CONFIG_JSON_KEY} key",
+            secret.metadata.name
+        )
+    })?;
+    let config: DockerConfig = serde_json::from_slice(&decode(encoded)?).with_context(|| {
+        format!(
+            "invalid {DOCKER_CONFIG_JSON_KEY} in secret {}",
+            secret.metadata.name
+        )
+    })?;
+
+    let mut credentials = HashMap::new();
+    for (registry, auth) in config.auths {
+        let auth = match auth {
+            DockerAuth {
+                username: Some(username),
+                password: Some(password),
+                ..
+            } => RegistryAuth::Basic(username, password),
+            DockerAuth {
+                auth: Some(auth), ..
+            } => {
+                let decoded = String::from_utf8(decode(&auth)?)?;
+                let (username, password) = decoded
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("invalid auth of registry {registry}"))?;
+                RegistryAuth::Basic(username.to_string(), password.to_string())
+            }
+            DockerAuth {
+                registry_token: Some(token),
+                ..
+            } => RegistryAuth::Bearer(token),
+            _ => bail!("no credentials for registry {registry}"),
+        };
+        credentials.insert(registry_host(&registry).to_string(), auth);
+    }
+    Ok(credentials)
+}
+
+fn decode(value: &str) -> Result<Vec<u8>> {
+    BASE64
+        .decode(value.trim())
+        .map_err(|e| anyhow!("invalid base64 value: {e}"))
+}
+
+/// The registry host an image is pulled from, which [`registry_credentials`]
+/// are keyed by, and the reference of the image within that registry. As
+/// for Docker, the first component names a registry if it has a `.` or a
+/// `:`, or is `localhost`. Images without one come from the default
+/// registry of rkforge.
+pub fn image_registry(image: &str) -> (Option<&str>, &str) {
+    match image.split_once('/') {
+        Some((host, reference)) if host.contains(['.', ':']) || host == "localhost" => {
+            (Some(host), reference)
+        }
+        _ => (None, image),
+    }
+}
+
+/// `registry.example.com` for `https://registry.example.com/v1/`.
+fn registry_host(registry: &str) -> &str {
+    let registry = registry
+        .strip_prefix("https://")
+        .or_else(|| registry.strip_prefix("http://"))
+        .unwrap_or(registry);
+    registry.split('/').next().unwrap_or(registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn docker_config_secret(config: &str) -> Secret {
+        Secret {
+            secret_type: SECRET_TYPE_DOCKER_CONFIG_JSON.to_string(),
+            data: HashMap::from([(DOCKER_CONFIG_JSON_KEY.to_string(), BASE64.encode(config))]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_registry_credentials() {
+        let secret = docker_config_secret(&format!(
+            r#"{{"auths": {{
+                "https://registry.example.com/v1/": {{"username": "alice", "password": "secret"}},
+                "localhost:5000": {{"auth": "{}"}},
+                "token.example.com": {{"registrytoken": "abc"}}
+            }}}}"#,
+            BASE64.encode("bob:hunter2")
+        ));
+        let credentials = registry_credentials(&secret).unwrap();
+        assert_eq!(credentials.len(), 3);
+        assert!(matches!(
+            &credentials["registry.example.com"],
+            RegistryAuth::Basic(user, password) if user == "alice" && password == "secret"
+        ));
+        assert!(matches!(
+            &credentials["localhost:5000"],
+            RegistryAuth::Basic(user, password) if user == "bob" && password == "hunter2"
+        ));
+        assert!(matches!(
+            &credentials["token.example.com"],
+            RegistryAuth::Bearer(token) if token == "abc"
+        ));
+    }
+
+    #[test]
+    fn test_image_registry() {
+        assert_eq!(
+            image_registry("registry.example.com:5000/team/app:v1"),
+            (Some("registry.example.com:5000"), "team/app:v1")
+        );
+        assert_eq!(image_registry("localhost/app"), (Some("localhost"), "app"));
+        assert_eq!(image_registry("team/app:v1"), (None, "team/app:v1"));
+        assert_eq!(image_registry("busybox:latest"), (None, "busybox:latest"));
+    }
+
+    #[test]
+    fn test_registry_credentials_match_image_registry() {
+        let secret = docker_config_secret(
+            r#"{"auths": {
+                "https://registry.example.com:5000/v1/": {"username": "alice", "password": "secret"}
+            }}"#,
+        );
+        let credentials = registry_credentials(&secret).unwrap();
+        let (registry, _) = image_registry("registry.example.com:5000/team/app:v1");
+        assert!(credentials.contains_key(registry.unwrap()));
+    }
+
+    #[test]
+    fn test_registry_credentials_rejects_opaque_secret() {
+        let mut secret = docker_config_secret(r#"{"auths": {}}"#);
+        secret.secret_type = "Opaque".to_string();
+        assert!(registry_credentials(&secret).is_err());
+    }
+}
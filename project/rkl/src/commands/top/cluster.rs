@@ -0,0 +1,18 @@
+use anyhow::{Result, anyhow};
+use common::{NodeStats, RksMessage};
+
+use crate::commands::pod::TLSConnectionArgs;
+use crate::quic::client::{Cli, QUICClient};
+
+/// The latest resource usage reported by the nodes of the cluster.
+pub async fn node_stats(addr: &str, tls_cfg: TLSConnectionArgs) -> Result<Vec<NodeStats>> {
+    let cli = QUICClient::<Cli>::connect(addr, &tls_cfg).await?;
+
+    cli.send_msg(&RksMessage::GetNodeStats).await?;
+
+    match cli.fetch_msg().await? {
+        RksMessage::NodeStatsRes(stats) => Ok(stats),
+        RksMessage::Error(err) => Err(anyhow!("Failed to get node stats: {}", err)),
+        msg => Err(anyhow!("Unexpected response: {:?}", msg)),
+    }
+}
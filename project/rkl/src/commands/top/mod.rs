@@ -0,0 +1,324 @@
+use anyhow::{Result, anyhow};
+use clap::Subcommand;
+use common::NodeStats;
+use gethostname::gethostname;
+use libcontainer::syscall::syscall::create_syscall;
+use libruntime::rootpath;
+use std::env;
+use std::io::{self, Write};
+use std::time::Duration;
+use tabwriter::TabWriter;
+
+use crate::commands::pod::TLSConnectionArgs;
+use crate::daemon::stats::StatsCollector;
+
+pub mod cluster;
+
+/// How long local CPU usage is sampled over.
+const LOCAL_SAMPLE_PERIOD: Duration = Duration::from_secs(1);
+
+#[derive(Subcommand)]
+pub enum TopCommand {
+    #[command(about = "Show the CPU, memory and network usage of pods")]
+    Pod {
+        /// Only show this pod
+        #[arg(value_name = "POD_NAME")]
+        pod_name: Option<String>,
+
+        /// Show the usage of each container
+        #[arg(long)]
+        containers: bool,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+
+    #[command(about = "Show the CPU and memory usage of nodes")]
+    Node {
+        /// Only show this node
+        #[arg(value_name = "NODE_NAME")]
+        node_name: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "RKS_ADDRESS",
+            env = "RKS_ADDRESS",
+            required = false
+        )]
+        cluster: Option<String>,
+
+        #[clap(flatten)]
+        tls_cfg: TLSConnectionArgs,
+    },
+}
+
+pub fn top_execute(cmd: TopCommand) -> Result<()> {
+    match cmd {
+        TopCommand::Pod {
+            pod_name,
+            containers,
+            cluster,
+            tls_cfg,
+        } => {
+            let stats = node_stats(cluster, tls_cfg)?;
+            let stats = filter_pods(stats, pod_name.as_deref())?;
+            let mut out = io::stdout();
+            if containers {
+                print_containers(&mut out, &stats)
+            } else {
+                print_pods(&mut out, &stats)
+            }
+        }
+        TopCommand::Node {
+            node_name,
+            cluster,
+            tls_cfg,
+        } => {
+            let mut stats = node_stats(cluster, tls_cfg)?;
+            if let Some(name) = node_name {
+                stats.retain(|node| node.node_name == name);
+                if stats.is_empty() {
+                    return Err(anyhow!("no resource usage reported by node {name}"));
+                }
+            }
+            print_nodes(&mut io::stdout(), &stats)
+        }
+    }
+}
+
+/// Usage of the cluster's nodes from rks, or of this node when no cluster
+/// address is given.
+fn node_stats(addr: Option<String>, tls_cfg: TLSConnectionArgs) -> Result<Vec<NodeStats>> {
+    match addr.or_else(|| env::var("RKS_ADDRESS").ok()) {
+        Some(rks_addr) => {
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(cluster::node_stats(&rks_addr, tls_cfg))
+        }
+        None => local_stats().map(|stats| vec![stats]),
+    }
+}
+
+/// Usage of this node, with CPU sampled over [`LOCAL_SAMPLE_PERIOD`].
+fn local_stats() -> Result<NodeStats> {
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let mut collector = StatsCollector::default();
+    collector.collect(&root_path)?;
+    std::thread::sleep(LOCAL_SAMPLE_PERIOD);
+    let mut stats = collector.collect(&root_path)?;
+    stats.node_name = gethostname().to_string_lossy().into_owned();
+    Ok(stats)
+}
+
+fn filter_pods(mut stats: Vec<NodeStats>, pod_name: Option<&str>) -> Result<Vec<NodeStats>> {
+    let Some(pod_name) = pod_name else {
+        return Ok(stats);
+    };
+    for node in &mut stats {
+        node.pods.retain(|pod| pod.name == pod_name);
+    }
+    stats.retain(|node| !node.pods.is_empty());
+    if stats.is_empty() {
+        return Err(anyhow!("no resource usage found for pod {pod_name}"));
+    }
+    Ok(stats)
+}
+
+fn print_pods(out: &mut impl Write, stats: &[NodeStats]) -> Result<()> {
+    let mut tab_writer = TabWriter::new(out);
+    writeln!(
+        &mut tab_writer,
+        "NAME\tNODE\tCPU(cores)\tMEMORY(bytes)\tNET RX\tNET TX"
+    )?;
+    for node in stats {
+        for pod in &node.pods {
+            writeln!(
+                &mut tab_writer,
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                pod.name,
+                node.node_name,
+                format_millicores(pod.cpu_millicores()),
+                format_bytes(pod.memory_bytes()),
+                format_bytes(pod.network.rx_bytes),
+                format_bytes(pod.network.tx_bytes)
+            )?;
+        }
+    }
+    tab_writer.flush()?;
+    Ok(())
+}
+
+fn print_containers(out: &mut impl Write, stats: &[NodeStats]) -> Result<()> {
+    let mut tab_writer = TabWriter::new(out);
+    writeln!(
+        &mut tab_writer,
+        "POD\tNAME\tCPU(cores)\tMEMORY(bytes)\tPIDS\tIO READ\tIO WRITE"
+    )?;
+    for pod in stats.iter().flat_map(|node| &node.pods) {
+        for container in &pod.containers {
+            writeln!(
+                &mut tab_writer,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                pod.name,
+                container
+                    .name
+                    .strip_prefix(&format!("{}-", pod.name))
+                    .unwrap_or(&container.name),
+                format_millicores(container.cpu_millicores),
+                format_bytes(container.memory_bytes),
+                container.pids,
+                format_bytes(container.io_read_bytes),
+                format_bytes(container.io_write_bytes)
+            )?;
+        }
+    }
+    tab_writer.flush()?;
+    Ok(())
+}
+
+fn print_nodes(out: &mut impl Write, stats: &[NodeStats]) -> Result<()> {
+    let mut tab_writer = TabWriter::new(out);
+    writeln!(
+        &mut tab_writer,
+        "NAME\tCPU(cores)\tCPU%\tMEMORY(bytes)\tMEMORY%\tPODS"
+    )?;
+    for node in stats {
+        writeln!(
+            &mut tab_writer,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            node.node_name,
+            format_millicores(node.cpu_millicores),
+            format_percent(node.cpu_millicores, node.cpu_capacity),
+            format_bytes(node.memory_bytes),
+            format_percent(node.memory_bytes, node.memory_capacity),
+            node.pods.len()
+        )?;
+    }
+    tab_writer.flush()?;
+    Ok(())
+}
+
+fn format_millicores(millicores: u64) -> String {
+    format!("{millicores}m")
+}
+
+/// Bytes in the largest binary unit they amount to at least one of.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [(&str, u64); 4] = [
+        ("Ti", 1 << 40),
+        ("Gi", 1 << 30),
+        ("Mi", 1 << 20),
+        ("Ki", 1 << 10),
+    ];
+    UNITS
+        .iter()
+        .find(|(_, size)| bytes >= *size)
+        .map(|(unit, size)| format!("{}{unit}", bytes / size))
+        .unwrap_or_else(|| bytes.to_string())
+}
+
+fn format_percent(used: u64, capacity: u64) -> String {
+    if capacity == 0 {
+        return "<unknown>".to_string();
+    }
+    format!("{}%", used * 100 / capacity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{ContainerStats, NetworkStats, PodStats};
+
+    fn stats() -> Vec<NodeStats> {
+        let pod = |name: &str, millicores: u64, memory: u64| PodStats {
+            name: name.to_string(),
+            namespace: "default".to_string(),
+            containers: vec![ContainerStats {
+                name: format!("{name}-app"),
+                cpu_millicores: millicores,
+                memory_bytes: memory,
+                pids: 2,
+                ..Default::default()
+            }],
+            network: NetworkStats {
+                rx_bytes: 2048,
+                ..Default::default()
+            },
+        };
+        vec![NodeStats {
+            node_name: "node-1".to_string(),
+            cpu_millicores: 500,
+            cpu_capacity: 2000,
+            memory_bytes: 512 << 20,
+            memory_capacity: 2 << 30,
+            pods: vec![pod("web", 250, 64 << 20), pod("db", 100, 128 << 20)],
+            ..Default::default()
+        }]
+    }
+
+    #[test]
+    fn test_format_quantities() {
+        assert_eq!(format_millicores(250), "250m");
+        assert_eq!(format_bytes(512), "512");
+        assert_eq!(format_bytes(1536), "1Ki");
+        assert_eq!(format_bytes(64 << 20), "64Mi");
+        assert_eq!(format_bytes(3 << 30), "3Gi");
+        assert_eq!(format_percent(500, 2000), "25%");
+        assert_eq!(format_percent(500, 0), "<unknown>");
+    }
+
+    #[test]
+    fn test_filter_pods() {
+        let filtered = filter_pods(stats(), Some("db")).unwrap();
+        assert_eq!(filtered[0].pods.len(), 1);
+        assert_eq!(filtered[0].pods[0].name, "db");
+        assert!(filter_pods(stats(), Some("cache")).is_err());
+        assert_eq!(filter_pods(stats(), None).unwrap()[0].pods.len(), 2);
+    }
+
+    #[test]
+    fn test_print_tables() {
+        let mut out = Vec::new();
+        print_pods(&mut out, &stats()).unwrap();
+        let pods = String::from_utf8(out).unwrap();
+        let lines = pods.lines().collect::<Vec<_>>();
+        assert!(lines[0].starts_with("NAME"));
+        assert_eq!(
+            lines[1].split_whitespace().collect::<Vec<_>>(),
+            ["web", "node-1", "250m", "64Mi", "2Ki", "0"]
+        );
+
+        let mut out = Vec::new();
+        print_containers(&mut out, &stats()).unwrap();
+        let containers = String::from_utf8(out).unwrap();
+        assert_eq!(
+            containers
+                .lines()
+                .nth(2)
+                .unwrap()
+                .split_whitespace()
+                .collect::<Vec<_>>(),
+            ["db", "app", "100m", "128Mi", "2", "0", "0"]
+        );
+
+        let mut out = Vec::new();
+        print_nodes(&mut out, &stats()).unwrap();
+        let nodes = String::from_utf8(out).unwrap();
+        assert_eq!(
+            nodes
+                .lines()
+                .nth(1)
+                .unwrap()
+                .split_whitespace()
+                .collect::<Vec<_>>(),
+            ["node-1", "500m", "25%", "512Mi", "25%", "2"]
+        );
+    }
+}
@@ -1,15 +1,61 @@
-//! RKL overlay rootfs configuration.
+//! RKL overlay rootfs, container log, node-pressure eviction, node allocatable and CRI
+//! configuration.
 //!
 //! Controls whether persistent overlayfs mounts are used for container rootfs,
-//! and which overlay backend (libfuse or Linux native) to use.
+//! which overlay backend (libfuse or Linux native) to use, how container
+//! log files are rotated, when the daemon evicts pods and collects garbage,
+//! which resources are reserved for the system, where it serves the CRI, and
+//! how it collects and serves resource usage.
 //!
 //! Environment variables:
 //! - `RKL_OVERLAY_ROOTFS`: set to `0` to disable overlay mode and fall back to traditional cp.
 //!   Defaults to `1` (enabled).
 //! - `RKL_USE_LIBFUSE`: set to `1` to use libfuse overlay backend, `0` for Linux native.
 //!   Defaults to `0` (native). Both modes require root privileges.
+//! - `RKL_CONTAINER_LOG_MAX_SIZE`: size in bytes at which a container log file is rotated.
+//!   Defaults to 10 MiB.
+//! - `RKL_CONTAINER_LOG_MAX_FILES`: number of log files kept per container instance,
+//!   including the one being written. Defaults to `5`.
+//! - `RKL_EVICTION_HARD`: thresholds at which pods are evicted right away, e.g.
+//!   `memory.available<100Mi,nodefs.available<10%`. Defaults to
+//!   `memory.available<100Mi,nodefs.available<10%,nodefs.inodesFree<5%,imagefs.available<15%`.
+//! - `RKL_EVICTION_SOFT`: thresholds at which pods are evicted once they have been met for
+//!   the soft grace period. Empty by default.
+//! - `RKL_EVICTION_SOFT_GRACE_PERIOD`: the soft grace period in seconds. Defaults to `90`.
+//! - `RKL_EVICTION_PRESSURE_TRANSITION_PERIOD`: seconds a pressure condition stays set after
+//!   its thresholds are no longer met. Defaults to `300`.
+//! - `RKL_EVICTION_MONITORING_INTERVAL`: seconds between two checks. Defaults to `10`.
+//! - `RKL_NODEFS_PATH`: a path on the filesystem holding container logs and writable layers.
+//!   Defaults to `/`.
+//! - `RKL_IMAGE_GC_HIGH_THRESHOLD` / `RKL_IMAGE_GC_LOW_THRESHOLD`: image filesystem usage in
+//!   percent above which garbage collection starts, and below which it stops. Default to `85`
+//!   and `80`.
+//! - `RKL_CONTAINER_GC_MIN_AGE`: seconds an exited container is kept at least. Defaults to `60`.
+//! - `RKL_KUBE_RESERVED` / `RKL_SYSTEM_RESERVED`: resources set aside for rkl and rks, and for
+//!   the rest of the system, e.g. `cpu=500m,memory=1Gi,ephemeral-storage=1Gi,pid=1000`.
+//!   Nothing is reserved by default.
+//! - `RKL_MAX_PODS`: number of pods the node accepts. Defaults to `110`.
+//! - `RKL_ENFORCE_NODE_ALLOCATABLE`: set to `0` to not run pods in a cgroup limited to the
+//!   capacity minus the reservations. Defaults to `1` (enforced).
+//! - `RKL_CRI_SOCKET`: unix socket the daemon serves the CRI runtime and image services on.
+//!   Set to an empty value to not serve them. Defaults to `/run/rkl/rkl.sock`.
+//! - `RKL_CRI_STREAMING_ADDRESS`: address the streaming server, which CRI `Exec` returns URLs
+//!   of, listens on. Defaults to `127.0.0.1:10010`.
+//! - `RKL_STATS_INTERVAL`: seconds between two collections of the resource usage of pods.
+//!   Defaults to `10`.
+//! - `RKL_METRICS_ADDRESS`: address the daemon serves Prometheus metrics on. Set to an empty
+//!   value to not serve them. Defaults to `0.0.0.0:10255`.
 
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::LazyLock;
+use std::time::Duration;
+
+use tracing::warn;
+
+use crate::daemon::allocatable::Reservation;
+use crate::daemon::eviction::threshold::{DEFAULT_HARD_THRESHOLDS, Threshold, parse_thresholds};
+use crate::logs::{DEFAULT_MAX_FILES, DEFAULT_MAX_SIZE};
 
 /// Global overlay configuration for RKL container rootfs.
 pub struct OverlayConfig {
@@ -29,3 +75,155 @@ pub static OVERLAY_CONFIG: LazyLock<OverlayConfig> = LazyLock::new(|| OverlayCon
         .map(|v| v == "1")
         .unwrap_or(false),
 });
+
+/// Rotation limits for container log files.
+pub struct LogConfig {
+    pub max_size: u64,
+    pub max_files: usize,
+}
+
+/// Globally initialized log configuration, read from environment variables at first access.
+pub static LOG_CONFIG: LazyLock<LogConfig> = LazyLock::new(|| LogConfig {
+    max_size: std::env::var("RKL_CONTAINER_LOG_MAX_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_SIZE),
+    max_files: std::env::var("RKL_CONTAINER_LOG_MAX_FILES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_FILES),
+});
+
+/// Node-pressure eviction and garbage collection settings.
+pub struct EvictionConfig {
+    pub hard: Vec<Threshold>,
+    pub soft: Vec<Threshold>,
+    pub soft_grace_period: Duration,
+    pub pressure_transition_period: Duration,
+    pub monitoring_interval: Duration,
+    pub nodefs_path: PathBuf,
+    pub image_gc_high_threshold: u8,
+    pub image_gc_low_threshold: u8,
+    pub container_gc_min_age: Duration,
+}
+
+/// Globally initialized eviction configuration, read from environment variables at first access.
+/// Invalid thresholds are logged and replaced by the defaults.
+pub static EVICTION_CONFIG: LazyLock<EvictionConfig> = LazyLock::new(|| {
+    let thresholds = |var: &str, default: &str| {
+        let value = std::env::var(var).unwrap_or_else(|_| default.to_string());
+        parse_thresholds(&value).unwrap_or_else(|e| {
+            warn!("invalid {var}: {e}, using {default:?}");
+            parse_thresholds(default).expect("default thresholds are valid")
+        })
+    };
+    let secs = |var: &str, default: u64| {
+        Duration::from_secs(
+            std::env::var(var)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default),
+        )
+    };
+    let percent = |var: &str, default: u8| {
+        std::env::var(var)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|p| *p <= 100)
+            .unwrap_or(default)
+    };
+    EvictionConfig {
+        hard: thresholds("RKL_EVICTION_HARD", DEFAULT_HARD_THRESHOLDS),
+        soft: thresholds("RKL_EVICTION_SOFT", ""),
+        soft_grace_period: secs("RKL_EVICTION_SOFT_GRACE_PERIOD", 90),
+        pressure_transition_period: secs("RKL_EVICTION_PRESSURE_TRANSITION_PERIOD", 300),
+        monitoring_interval: secs("RKL_EVICTION_MONITORING_INTERVAL", 10),
+        nodefs_path: std::env::var("RKL_NODEFS_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("/")),
+        image_gc_high_threshold: percent("RKL_IMAGE_GC_HIGH_THRESHOLD", 85),
+        image_gc_low_threshold: percent("RKL_IMAGE_GC_LOW_THRESHOLD", 80),
+        container_gc_min_age: secs("RKL_CONTAINER_GC_MIN_AGE", 60),
+    }
+});
+
+/// Resources reserved on the node and how the reservations are enforced.
+pub struct NodeConfig {
+    pub kube_reserved: Reservation,
+    pub system_reserved: Reservation,
+    pub max_pods: u64,
+    pub enforce_node_allocatable: bool,
+}
+
+/// Globally initialized node configuration, read from environment variables at first access.
+/// Invalid reservations are logged and ignored.
+pub static NODE_CONFIG: LazyLock<NodeConfig> = LazyLock::new(|| {
+    let reservation = |var: &str| {
+        let value = std::env::var(var).unwrap_or_default();
+        value.parse().unwrap_or_else(|e| {
+            warn!("invalid {var}: {e}, reserving nothing");
+            Reservation::default()
+        })
+    };
+    NodeConfig {
+        kube_reserved: reservation("RKL_KUBE_RESERVED"),
+        system_reserved: reservation("RKL_SYSTEM_RESERVED"),
+        max_pods: std::env::var("RKL_MAX_PODS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(110),
+        enforce_node_allocatable: std::env::var("RKL_ENFORCE_NODE_ALLOCATABLE")
+            .map(|v| v != "0")
+            .unwrap_or(true),
+    }
+});
+
+/// Socket the CRI services are served on, `None` when they are disabled.
+pub static CRI_SOCKET: LazyLock<Option<PathBuf>> =
+    LazyLock::new(|| match std::env::var("RKL_CRI_SOCKET") {
+        Ok(path) if path.is_empty() => None,
+        Ok(path) => Some(PathBuf::from(path)),
+        Err(_) => Some(PathBuf::from("/run/rkl/rkl.sock")),
+    });
+
+/// Address of the streaming server `Exec` sessions are served by. An invalid
+/// address is logged and the default used.
+pub static CRI_STREAMING_ADDRESS: LazyLock<SocketAddr> = LazyLock::new(|| {
+    let default = SocketAddr::from(([127, 0, 0, 1], 10010));
+    match std::env::var("RKL_CRI_STREAMING_ADDRESS") {
+        Ok(addr) => addr
+            .parse()
+            .inspect_err(|e| {
+                warn!("invalid RKL_CRI_STREAMING_ADDRESS {addr}: {e}, using {default}")
+            })
+            .unwrap_or(default),
+        Err(_) => default,
+    }
+});
+
+/// Resource usage collection settings.
+pub struct StatsConfig {
+    pub interval: Duration,
+    /// Address Prometheus metrics are served on, `None` when disabled.
+    pub metrics_address: Option<SocketAddr>,
+}
+
+/// Globally initialized stats configuration, read from environment variables at first access.
+/// An invalid metrics address is logged and disables the metrics.
+pub static STATS_CONFIG: LazyLock<StatsConfig> = LazyLock::new(|| StatsConfig {
+    interval: Duration::from_secs(
+        std::env::var("RKL_STATS_INTERVAL")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(10),
+    ),
+    metrics_address: match std::env::var("RKL_METRICS_ADDRESS") {
+        Ok(addr) if addr.is_empty() => None,
+        Ok(addr) => addr
+            .parse()
+            .inspect_err(|e| warn!("invalid RKL_METRICS_ADDRESS {addr}: {e}, not serving metrics"))
+            .ok(),
+        Err(_) => Some(SocketAddr::from(([0, 0, 0, 0], 10255))),
+    },
+});
@@ -0,0 +1,371 @@
+//! Node capacity and allocatable resources.
+//!
+//! The capacity is discovered from procfs, sysfs and the cgroup rkl runs in:
+//! CPUs and memory capped by the limits of that cgroup, the filesystem at
+//! `RKL_NODEFS_PATH` for ephemeral storage, and the hugepages pools. The
+//! allocatable resources are the capacity minus kube-reserved,
+//! system-reserved and the hard eviction thresholds, as the kubelet computes
+//! them. Hugepages are not available as memory.
+//!
+//! Unless disabled in [`NODE_CONFIG`], the containers of pods run below the
+//! [`PODS_CGROUP`] cgroup, whose memory, CPU weight and pids are limited to
+//! the capacity minus the reservations, so that pods can't starve the
+//! system of the resources reserved for it.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result, anyhow, bail};
+use common::NodeResources;
+use procfs::{CpuInfo, Current, Meminfo};
+use tracing::{info, warn};
+
+use crate::config::{EVICTION_CONFIG, NODE_CONFIG};
+use crate::daemon::eviction::FsStats;
+use crate::daemon::eviction::threshold::{Quantity, Signal, Threshold};
+
+/// Cgroup the containers of pods are created in, relative to the cgroup root.
+pub const PODS_CGROUP: &str = "/rkl-pods";
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+const HUGEPAGES_DIR: &str = "/sys/kernel/mm/hugepages";
+const CONTROLLERS: [&str; 3] = ["cpu", "memory", "pids"];
+
+/// Resources set aside from the capacity, parsed from
+/// `cpu=500m,memory=1Gi,ephemeral-storage=1Gi,pid=1000`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Reservation {
+    /// Millicores.
+    pub cpu: u64,
+    /// Bytes.
+    pub memory: u64,
+    /// Bytes.
+    pub ephemeral_storage: u64,
+    pub pids: u64,
+}
+
+impl Reservation {
+    fn add(&self, other: &Reservation) -> Reservation {
+        Reservation {
+            cpu: self.cpu + other.cpu,
+            memory: self.memory + other.memory,
+            ephemeral_storage: self.ephemeral_storage + other.ephemeral_storage,
+            pids: self.pids + other.pids,
+        }
+    }
+}
+
+impl FromStr for Reservation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut reservation = Reservation::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, value) = entry.split_once('=').ok_or_else(|| {
+                anyhow!("invalid reservation {entry}, expected <resource>=<quantity>")
+            })?;
+            let bytes = || match value.parse()? {
+                Quantity::Absolute(bytes) => Ok::<_, anyhow::Error>(bytes),
+                Quantity::Percentage(_) => bail!("{name} can't be reserved as a percentage"),
+            };
+            match name.trim() {
+                "cpu" => reservation.cpu = parse_millicores(value)?,
+                "memory" => reservation.memory = bytes()?,
+                "ephemeral-storage" => reservation.ephemeral_storage = bytes()?,
+                "pid" => {
+                    reservation.pids = value
+                        .parse()
+                        .map_err(|_| anyhow!("invalid pid reservation {value}"))?
+                }
+                other => bail!("unknown resource {other}"),
+            }
+        }
+        Ok(reservation)
+    }
+}
+
+/// Millicores of a CPU quantity, `500m` or `1.5`.
+pub(crate) fn parse_millicores(s: &str) -> Result<u64> {
+    if let Some(millis) = s.strip_suffix('m') {
+        return millis
+            .parse()
+            .map_err(|_| anyhow!("invalid cpu quantity {s}"));
+    }
+    let cores: f64 = s.parse().map_err(|_| anyhow!("invalid cpu quantity {s}"))?;
+    if cores < 0.0 {
+        bail!("invalid cpu quantity {s}");
+    }
+    Ok((cores * 1000.0).round() as u64)
+}
+
+/// The resources of the node, and the part of them pods may use.
+pub fn node_resources() -> Result<(NodeResources, NodeResources)> {
+    let capacity = capacity()?;
+    let allocatable = allocatable(
+        &capacity,
+        &NODE_CONFIG.kube_reserved.add(&NODE_CONFIG.system_reserved),
+        &EVICTION_CONFIG.hard,
+    );
+    Ok((capacity, allocatable))
+}
+
+fn capacity() -> Result<NodeResources> {
+    let cgroup = own_cgroup();
+    let cores = CpuInfo::current()
+        .context("failed to read /proc/cpuinfo")?
+        .num_cores() as u64;
+    let cpu_limit = cgroup
+        .as_deref()
+        .and_then(|cgroup| cgroup_limit(cgroup, "cpu.max", parse_cpu_max));
+    let mem_total = Meminfo::current()
+        .context("failed to read /proc/meminfo")?
+        .mem_total;
+    let memory_limit = cgroup
+        .as_deref()
+        .and_then(|cgroup| cgroup_limit(cgroup, "memory.max", |s| s.trim().parse().ok()));
+    Ok(NodeResources {
+        cpu: (cores * 1000).min(cpu_limit.unwrap_or(u64::MAX)),
+        memory: mem_total.min(memory_limit.unwrap_or(u64::MAX)),
+        ephemeral_storage: FsStats::observe(&EVICTION_CONFIG.nodefs_path)?.capacity,
+        hugepages: hugepages(),
+        pods: NODE_CONFIG.max_pods,
+    })
+}
+
+/// The capacity minus the reservations and the hard eviction thresholds.
+fn allocatable(
+    capacity: &NodeResources,
+    reserved: &Reservation,
+    hard: &[Threshold],
+) -> NodeResources {
+    let eviction = |signal: Signal, capacity: u64| {
+        hard.iter()
+            .filter(|t| t.signal == signal)
+            .map(|t| t.quantity.resolve(capacity))
+            .max()
+            .unwrap_or(0)
+    };
+    let hugepages = capacity.hugepages.values().sum::<u64>();
+    NodeResources {
+        cpu: capacity.cpu.saturating_sub(reserved.cpu),
+        memory: capacity
+            .memory
+            .saturating_sub(reserved.memory)
+            .saturating_sub(eviction(Signal::MemoryAvailable, capacity.memory))
+            .saturating_sub(hugepages),
+        ephemeral_storage: capacity
+            .ephemeral_storage
+            .saturating_sub(reserved.ephemeral_storage)
+            .saturating_sub(eviction(
+                Signal::NodeFsAvailable,
+                capacity.ephemeral_storage,
+            )),
+        hugepages: capacity.hugepages.clone(),
+        pods: capacity.pods,
+    }
+}
+
+/// Bytes in each hugepages pool, keyed by page size.
+fn hugepages() -> BTreeMap<String, u64> {
+    let Ok(entries) = fs::read_dir(HUGEPAGES_DIR) else {
+        return Default::default();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let page_kb: u64 = name
+                .to_str()?
+                .strip_prefix("hugepages-")?
+                .strip_suffix("kB")?
+                .parse()
+                .ok()?;
+            let pages: u64 = fs::read_to_string(entry.path().join("nr_hugepages"))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()?;
+            Some((page_size_name(page_kb), pages * page_kb * 1024))
+        })
+        .collect()
+}
+
+/// `2Mi` for 2048 kB pages.
+fn page_size_name(kb: u64) -> String {
+    if kb % (1 << 20) == 0 {
+        format!("{}Gi", kb >> 20)
+    } else if kb % (1 << 10) == 0 {
+        format!("{}Mi", kb >> 10)
+    } else {
+        format!("{kb}Ki")
+    }
+}
+
+fn is_cgroup_v2() -> bool {
+    Path::new(CGROUP_ROOT).join("cgroup.controllers").exists()
+}
+
+/// The cgroup v2 this process runs in, relative to the cgroup root.
+fn own_cgroup() -> Option<PathBuf> {
+    if !is_cgroup_v2() {
+        return None;
+    }
+    fs::read_to_string("/proc/self/cgroup")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(|path| PathBuf::from(path.trim_start_matches('/')))
+}
+
+/// The lowest limit `file` sets on `cgroup` or its ancestors.
+fn cgroup_limit(cgroup: &Path, file: &str, parse: impl Fn(&str) -> Option<u64>) -> Option<u64> {
+    cgroup
+        .ancestors()
+        .filter_map(|dir| fs::read_to_string(Path::new(CGROUP_ROOT).join(dir).join(file)).ok())
+        .filter_map(|value| parse(&value))
+        .min()
+}
+
+/// Millicores of a `cpu.max` quota, `None` when unlimited.
+fn parse_cpu_max(value: &str) -> Option<u64> {
+    let mut fields = value.split_whitespace();
+    let quota: u64 = fields.next()?.parse().ok()?;
+    let period: u64 = fields.next().unwrap_or("100000").parse().ok()?;
+    (period > 0).then(|| quota * 1000 / period)
+}
+
+/// `cpu.weight` for a share of `millicores`, converted from the cgroup v1
+/// shares the kubelet would give it.
+fn cpu_weight(millicores: u64) -> u64 {
+    let shares = (millicores * 1024 / 1000).clamp(2, 262144);
+    1 + (shares - 2) * 9999 / 262142
+}
+
+/// The cgroup the container `container_id` of a pod is created in, when
+/// node allocatable is enforced.
+pub fn pod_cgroups_path(container_id: &str) -> Option<PathBuf> {
+    (NODE_CONFIG.enforce_node_allocatable && is_cgroup_v2())
+        .then(|| Path::new(PODS_CGROUP).join(container_id))
+}
+
+/// Create [`PODS_CGROUP`] and limit it to the capacity minus the reservations.
+pub fn enforce_node_allocatable() -> Result<()> {
+    if !NODE_CONFIG.enforce_node_allocatable {
+        return Ok(());
+    }
+    if !is_cgroup_v2() {
+        warn!("[allocatable] node allocatable is only enforced with cgroup v2");
+        return Ok(());
+    }
+    let capacity = capacity()?;
+    let reserved = NODE_CONFIG.kube_reserved.add(&NODE_CONFIG.system_reserved);
+
+    let root = Path::new(CGROUP_ROOT);
+    let pods = root.join(PODS_CGROUP.trim_start_matches('/'));
+    fs::create_dir_all(&pods).with_context(|| format!("failed to create {}", pods.display()))?;
+    for dir in [root, pods.as_path()] {
+        for controller in CONTROLLERS {
+            // Controllers the kernel lacks can't be enabled, their limits are skipped
+            if let Err(e) = fs::write(dir.join("cgroup.subtree_control"), format!("+{controller}"))
+            {
+                warn!(
+                    "[allocatable] failed to enable the {controller} controller in {}: {e}",
+                    dir.display()
+                );
+            }
+        }
+    }
+
+    let memory = capacity.memory.saturating_sub(reserved.memory);
+    let cpu = capacity.cpu.saturating_sub(reserved.cpu);
+    let mut limits = vec![
+        ("memory.max", memory.to_string()),
+        ("cpu.weight", cpu_weight(cpu).to_string()),
+    ];
+    if reserved.pids > 0 {
+        let pid_max: u64 = fs::read_to_string("/proc/sys/kernel/pid_max")?
+            .trim()
+            .parse()?;
+        limits.push((
+            "pids.max",
+            pid_max.saturating_sub(reserved.pids).to_string(),
+        ));
+    }
+    for (file, value) in limits {
+        if let Err(e) = fs::write(pods.join(file), &value) {
+            warn!("[allocatable] failed to set {file} of {PODS_CGROUP} to {value}: {e}");
+        }
+    }
+    info!("[allocatable] pods are limited to {memory} bytes of memory and {cpu}m of CPU");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::eviction::threshold::parse_thresholds;
+
+    #[test]
+    fn test_parse_reservation() {
+        let reservation: Reservation = "cpu=500m, memory=1Gi,ephemeral-storage=2Gi,pid=100"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            reservation,
+            Reservation {
+                cpu: 500,
+                memory: 1 << 30,
+                ephemeral_storage: 2 << 30,
+                pids: 100,
+            }
+        );
+        assert_eq!("".parse::<Reservation>().unwrap(), Reservation::default());
+        assert_eq!("cpu=1.5".parse::<Reservation>().unwrap().cpu, 1500);
+        assert!("memory=10%".parse::<Reservation>().is_err());
+        assert!("gpu=1".parse::<Reservation>().is_err());
+        assert!("cpu".parse::<Reservation>().is_err());
+    }
+
+    #[test]
+    fn test_allocatable() {
+        let capacity = NodeResources {
+            cpu: 4000,
+            memory: 8 << 30,
+            ephemeral_storage: 100 << 30,
+            hugepages: [("2Mi".to_string(), 1 << 30)].into(),
+            pods: 110,
+        };
+        let reserved = Reservation {
+            cpu: 500,
+            memory: 1 << 30,
+            ephemeral_storage: 0,
+            pids: 0,
+        };
+        let hard = parse_thresholds("memory.available<100Mi,nodefs.available<10%").unwrap();
+        let allocatable = allocatable(&capacity, &reserved, &hard);
+        assert_eq!(allocatable.cpu, 3500);
+        assert_eq!(allocatable.memory, (6 << 30) - (100 << 20));
+        assert_eq!(allocatable.ephemeral_storage, 90 << 30);
+        assert_eq!(allocatable.hugepages, capacity.hugepages);
+        assert_eq!(allocatable.pods, 110);
+
+        let reserved = Reservation {
+            cpu: 8000,
+            ..Default::default()
+        };
+        assert_eq!(allocatable(&capacity, &reserved, &[]).cpu, 0);
+    }
+
+    #[test]
+    fn test_cgroup_values() {
+        assert_eq!(parse_cpu_max("max 100000\n"), None);
+        assert_eq!(parse_cpu_max("200000 100000\n"), Some(2000));
+        assert_eq!(parse_cpu_max("50000 100000"), Some(500));
+        assert_eq!(cpu_weight(1000), 39);
+        assert_eq!(cpu_weight(0), 1);
+        assert_eq!(page_size_name(2048), "2Mi");
+        assert_eq!(page_size_name(1 << 20), "1Gi");
+        assert_eq!(page_size_name(64), "64Ki");
+    }
+}
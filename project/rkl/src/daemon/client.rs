@@ -1,11 +1,26 @@
 use anyhow::Result;
 use std::collections::HashMap;
-use std::{env, fs, net::SocketAddr, path::Path, sync::Arc, time::Duration};
+use std::{
+    env, fs,
+    net::SocketAddr,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 // use tokio::sync::OnceCell;
 
 use tokio::time;
 
 use crate::commands::pod;
+use crate::commands::pod::debug;
+use crate::commands::pod::downward;
+use crate::daemon::allocatable;
+use crate::daemon::eviction;
+use crate::daemon::image_pull;
+use crate::daemon::metrics::{self, POD_START_DURATION};
+use crate::daemon::pod_worker::{self, RESTART_BACKOFF};
+use crate::daemon::session;
+use crate::daemon::stats::STATS_MANAGER;
 use crate::daemon::status::probe::probe_manager::PROBE_MANAGER;
 use crate::network::receiver::{NetworkConfigMessage, NetworkReceiver};
 use crate::task::TaskRunner;
@@ -20,7 +35,7 @@ use libnetwork::{
 
 use crate::commands::pod::TLSConnectionArgs;
 use crate::quic::client::{Daemon as ClientDaemon, QUICClient};
-use sysinfo::{Disks, System};
+use sysinfo::System;
 use tracing::{error, info, warn};
 
 // pub static DAEMON_CLIENT: OnceCell<Arc<QUICClient<ClientDaemon>>> = OnceCell::const_new();
@@ -175,9 +190,20 @@ pub async fn run_once(
             } else {
                 info!("[worker] heartbeat sent");
             }
+
+            if let Some(mut stats) = STATS_MANAGER.get().and_then(|manager| manager.latest()) {
+                stats.node_name = node_name.clone();
+                let msg = RksMessage::UpdateNodeStats(Box::new(stats));
+                if let Err(e) = hb_conn.send_msg(&msg).await {
+                    error!("[worker heartbeat] stats send failed: {e}");
+                }
+            }
         }
     });
 
+    // Interactive sessions arrive on streams of their own.
+    let session_handle = tokio::spawn(session::serve((*client).clone()));
+
     //Main receive loop: handle CreatePod/DeletePod/Network...
     loop {
         match client.accept_uni().await {
@@ -264,54 +290,21 @@ pub async fn run_once(
                                 }
                             };
 
-                            match pod::run_pod_from_taskrunner(runner).await {
-                                Ok(result) => {
-                                    let pod_name = result.pod_task.metadata.name.clone();
-
-                                    if let Some(pm) = PROBE_MANAGER.get() {
-                                        if let Err(e) =
-                                            pm.add_pod(&result.pod_task, &result.pod_ip).await
-                                        {
-                                            error!(
-                                                error = e.to_string(),
-                                                "[worker] failed to add probes for pod"
-                                            );
-                                        } else {
-                                            info!("[worker] probes added for pod {}", pod_name);
-                                        }
-                                    } else {
-                                        error!("[worker] PROBE_MANAGER not initialized");
-                                    }
-
-                                    let pod_ip = result
-                                        .pod_ip
-                                        .split('/')
-                                        .next()
-                                        .unwrap_or(&result.pod_ip)
-                                        .to_string();
-
-                                    info!("[worker] SetPodip {} -> {}", pod_name, pod_ip);
-                                    if let Err(e) = client
-                                        .send_msg(&RksMessage::SetPodip((pod_name, pod_ip)))
-                                        .await
-                                    {
-                                        error!("[worker] SetPodip send failed: {e}");
-                                    }
-                                }
-
-                                Err(e) => {
-                                    error!("[worker] run_pod_from_taskrunner failed: {e:?}");
-                                    let _ = client
-                                        .send_msg(&RksMessage::Error(format!(
-                                            "create {} failed: {e}",
-                                            pod.metadata.name
-                                        )))
-                                        .await;
-                                }
-                            }
+                            // Image pulls and init containers may take a
+                            // while, keep handling messages meanwhile.
+                            tokio::spawn(create_pod(
+                                client.clone(),
+                                server_addr.to_string(),
+                                tls_cfg.clone(),
+                                pod,
+                                runner,
+                            ));
                         }
                         Ok(RksMessage::DeletePod(name)) => {
                             info!("[worker] DeletePod {name}");
+                            image_pull::cancel(&name);
+                            pod_worker::forget_pod_restarts(&name);
+                            metrics::forget_pod(&name);
                             match pod::standalone::delete_pod(&name) {
                                 Ok(_) => {
                                     // Ensure probe deregistration completes before sending the Ack.
@@ -343,6 +336,33 @@ pub async fn run_once(
                                 }
                             }
                         }
+                        Ok(RksMessage::TerminatePod(pod)) => {
+                            info!("[worker] TerminatePod {}", pod.metadata.name);
+                            image_pull::cancel(&pod.metadata.name);
+                            // Stopping may take the whole grace period, keep
+                            // handling messages meanwhile.
+                            tokio::spawn(terminate_pod(client.clone(), *pod));
+                        }
+                        Ok(RksMessage::UpdatePodMetadata(pod)) => {
+                            info!("[worker] UpdatePodMetadata {}", pod.metadata.name);
+                            if let Err(e) = downward::update_metadata(&pod) {
+                                warn!(
+                                    "[worker] failed to update metadata of pod {}: {e}",
+                                    pod.metadata.name
+                                );
+                            }
+                        }
+                        Ok(RksMessage::RunEphemeralContainers(pod)) => {
+                            info!("[worker] RunEphemeralContainers {}", pod.metadata.name);
+                            // Image pulls may take a while, keep handling
+                            // messages meanwhile.
+                            tokio::spawn(run_ephemeral_containers(
+                                client.clone(),
+                                server_addr.to_string(),
+                                tls_cfg.clone(),
+                                *pod,
+                            ));
+                        }
                         Ok(RksMessage::SetDns(ip, dns_port)) => {
                             info!("[worker] received dns config: {ip}:{dns_port}");
 
@@ -398,6 +418,24 @@ pub async fn run_once(
                                 }
                             }
                         }
+                        Ok(RksMessage::GetPodLogs(req)) => {
+                            info!("[worker] GetPodLogs {}", req.pod_name);
+                            let res = match pod::standalone::pod_logs(&req.pod_name, &req.options) {
+                                Ok(lines) => PodLogsResponse {
+                                    request_id: req.request_id,
+                                    logs: lines.iter().map(|l| format!("{l}\n")).collect(),
+                                    error: None,
+                                },
+                                Err(e) => PodLogsResponse {
+                                    request_id: req.request_id,
+                                    logs: String::new(),
+                                    error: Some(e.to_string()),
+                                },
+                            };
+                            let _ = client
+                                .send_msg(&RksMessage::PodLogsRes(Box::new(res)))
+                                .await;
+                        }
                         Ok(other) => {
                             warn!("[worker] unexpected message: {other:?}");
                         }
@@ -417,12 +455,186 @@ pub async fn run_once(
             Err(e) => {
                 error!("[worker] accept_uni error: {e}, breaking to reconnect");
                 hb_handle.abort();
+                session_handle.abort();
                 return Err(anyhow::anyhow!("accept_uni failed: {e}"));
             }
         }
     }
 }
 
+/// Run a pod rks assigned to this node. Its init containers may take a
+/// while to complete, so this runs apart from the message loop.
+async fn create_pod(
+    client: QUICClient<ClientDaemon>,
+    server_addr: String,
+    tls_cfg: TLSConnectionArgs,
+    pod: PodTask,
+    mut runner: TaskRunner,
+) {
+    let start = Instant::now();
+    let result =
+        match image_pull::pull_images(&client, &server_addr, &tls_cfg, &pod, &mut runner).await {
+            Ok(()) => pod::run_pod_from_taskrunner(runner).await,
+            Err(e) => Err(e),
+        };
+    match result {
+        Ok(result) => {
+            POD_START_DURATION.observe_since(&[], start);
+            let pod_name = result.pod_task.metadata.name.clone();
+
+            if let Some(pm) = PROBE_MANAGER.get() {
+                if let Err(e) = pm.add_pod(&result.pod_task, &result.pod_ip).await {
+                    error!(
+                        error = e.to_string(),
+                        "[worker] failed to add probes for pod"
+                    );
+                } else {
+                    info!("[worker] probes added for pod {}", pod_name);
+                }
+            } else {
+                error!("[worker] PROBE_MANAGER not initialized");
+            }
+
+            let pod_ip = result
+                .pod_ip
+                .split('/')
+                .next()
+                .unwrap_or(&result.pod_ip)
+                .to_string();
+
+            info!("[worker] SetPodip {} -> {}", pod_name, pod_ip);
+            if let Err(e) = client
+                .send_msg(&RksMessage::SetPodip((pod_name, pod_ip)))
+                .await
+            {
+                error!("[worker] SetPodip send failed: {e}");
+            }
+        }
+
+        Err(e) => {
+            error!("[worker] run_pod_from_taskrunner failed: {e:?}");
+            let _ = client
+                .send_msg(&RksMessage::Error(format!(
+                    "create {} failed: {e}",
+                    pod.metadata.name
+                )))
+                .await;
+        }
+    }
+}
+
+/// Stop a pod marked for deletion and report its progress to rks, which
+/// removes the pod once it sees the terminal status.
+async fn terminate_pod(client: QUICClient<ClientDaemon>, pod: PodTask) {
+    let pod_name = pod.metadata.name.clone();
+    let mut status = pod.status.clone();
+    status.reason = Some("Terminating".to_string());
+    status.message = Some(format!(
+        "Stopping containers within {}s",
+        pod.spec.termination_grace_period()
+    ));
+    send_pod_status(&client, &pod, status.clone()).await;
+
+    if let Some(pm) = PROBE_MANAGER.get() {
+        pm.remove_pod(&pod_name).await;
+    }
+    RESTART_BACKOFF.forget_pod(pod.metadata.uid);
+    metrics::forget_pod(&pod_name);
+    let name = pod_name.clone();
+    match tokio::task::spawn_blocking(move || pod::standalone::delete_pod(&name)).await {
+        Ok(Ok(())) => info!("[worker] pod {pod_name} terminated"),
+        Ok(Err(e)) => error!("[worker] delete_pod {pod_name} failed: {e:?}"),
+        Err(e) => error!("[worker] delete_pod {pod_name} panicked: {e}"),
+    }
+
+    if status.phase != PodPhase::Failed {
+        status.phase = PodPhase::Succeeded;
+    }
+    status.reason = None;
+    status.message = None;
+    send_pod_status(&client, &pod, status).await;
+}
+
+/// Start the ephemeral containers added to a pod, report their statuses to
+/// rks and report them again once they exit. They are never restarted.
+async fn run_ephemeral_containers(
+    client: QUICClient<ClientDaemon>,
+    server_addr: String,
+    tls_cfg: TLSConnectionArgs,
+    pod: PodTask,
+) {
+    let pod_name = pod.metadata.name.clone();
+    let credentials = image_pull::pod_registry_credentials(&server_addr, &tls_cfg, &pod)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("[worker] failed to get image pull secrets of pod {pod_name}: {e}");
+            HashMap::new()
+        });
+    let statuses = match debug::run_ephemeral_containers(&pod, credentials).await {
+        Ok(statuses) => statuses,
+        Err(e) => {
+            error!("[worker] failed to run ephemeral containers of pod {pod_name}: {e:?}");
+            return;
+        }
+    };
+
+    for status in statuses {
+        send_ephemeral_container_status(&client, &pod_name, status.clone()).await;
+        let Some(ContainerState::Running { started_at }) = status.state else {
+            continue;
+        };
+        let client = client.clone();
+        let pod_name = pod_name.clone();
+        tokio::spawn(async move {
+            let name = status.name.clone();
+            match tokio::task::spawn_blocking(move || debug::exited_status(&name, started_at)).await
+            {
+                Ok(Ok(status)) => send_ephemeral_container_status(&client, &pod_name, status).await,
+                Ok(Err(e)) => warn!(
+                    "[worker] failed to wait for ephemeral container {}: {e}",
+                    status.name
+                ),
+                Err(e) => error!(
+                    "[worker] waiting for ephemeral container {} panicked: {e}",
+                    status.name
+                ),
+            }
+        });
+    }
+}
+
+async fn send_ephemeral_container_status(
+    client: &QUICClient<ClientDaemon>,
+    pod_name: &str,
+    status: ContainerStatus,
+) {
+    let msg = RksMessage::UpdateEphemeralContainerStatus {
+        pod_name: pod_name.to_string(),
+        status: Box::new(status),
+    };
+    if let Err(e) = client.send_msg(&msg).await {
+        error!("[worker] failed to report ephemeral container status of pod {pod_name}: {e}");
+    }
+}
+
+pub(crate) async fn send_pod_status(
+    client: &QUICClient<ClientDaemon>,
+    pod: &PodTask,
+    status: PodStatus,
+) {
+    let msg = RksMessage::UpdatePodStatus {
+        pod_name: pod.metadata.name.clone(),
+        pod_namespace: pod.metadata.namespace.clone(),
+        status,
+    };
+    if let Err(e) = client.send_msg(&msg).await {
+        error!(
+            "[worker] failed to report status of pod {}: {e}",
+            pod.metadata.name
+        );
+    }
+}
+
 async fn handle_network_config(
     network_receiver: &NetworkReceiver,
     node_cfg: &NodeNetworkConfig,
@@ -483,9 +695,6 @@ async fn handle_network_config(
 
 /// Generate NodeStatus for heartbeat
 pub async fn generate_node_status(ext_iface: &ExternalInterface) -> Result<NodeStatus> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-
     // hostname
     let hostname = gethostname().to_string_lossy().into_owned();
 
@@ -503,39 +712,24 @@ pub async fn generate_node_status(ext_iface: &ExternalInterface) -> Result<NodeS
         address: hostname,
     });
 
-    // CPU / memory
-    let total_cpu = sys.cpus().len().to_string();
-    let total_mem = format!("{}Mi", sys.total_memory() / 1024);
-
-    let mut capacity = HashMap::new();
-    capacity.insert("cpu".to_string(), total_cpu.clone());
-    capacity.insert("memory".to_string(), total_mem.clone());
-    capacity.insert("pods".to_string(), "110".to_string());
-
-    let mut allocatable = capacity.clone();
-    allocatable.insert("cpu".to_string(), (sys.cpus().len() - 1).to_string());
+    let (capacity, allocatable) = allocatable::node_resources()?;
 
     // conditions - include all condition types
-    let conditions = vec![
-        ready_condition(),
-        memory_condition(0.9),
-        disk_condition(1.0),
-        pid_condition(0.9),
-        network_condition(),
-    ];
+    let mut conditions = vec![ready_condition(), pid_condition(0.9), network_condition()];
+    conditions.extend(eviction::node_conditions());
 
     Ok(NodeStatus {
-        capacity,
-        allocatable,
+        capacity: capacity.quantities(),
+        allocatable: allocatable.quantities(),
         addresses,
         conditions,
+        images: local_images(),
+        capacity_resources: Some(capacity),
+        allocatable_resources: Some(allocatable),
     })
 }
 
 pub async fn generate_node(ext_iface: &ExternalInterface) -> Result<Node> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-
     // hostname
     let hostname = gethostname().to_string_lossy().into_owned();
 
@@ -553,17 +747,7 @@ pub async fn generate_node(ext_iface: &ExternalInterface) -> Result<Node> {
         address: hostname.clone(),
     });
 
-    // CPU / memory
-    let total_cpu = sys.cpus().len().to_string();
-    let total_mem = format!("{}Mi", sys.total_memory() / 1024);
-
-    let mut capacity = HashMap::new();
-    capacity.insert("cpu".to_string(), total_cpu.clone());
-    capacity.insert("memory".to_string(), total_mem.clone());
-    capacity.insert("pods".to_string(), "110".to_string());
-
-    let mut allocatable = capacity.clone();
-    allocatable.insert("cpu".to_string(), (sys.cpus().len() - 1).to_string());
+    let (capacity, allocatable) = allocatable::node_resources()?;
 
     // conditions
     let now = Utc::now().to_rfc3339();
@@ -588,74 +772,39 @@ pub async fn generate_node(ext_iface: &ExternalInterface) -> Result<Node> {
             taints: vec![],
         },
         status: NodeStatus {
-            capacity,
-            allocatable,
+            capacity: capacity.quantities(),
+            allocatable: allocatable.quantities(),
             addresses,
             conditions,
+            images: local_images(),
+            capacity_resources: Some(capacity),
+            allocatable_resources: Some(allocatable),
         },
     })
 }
 
-fn ready_condition() -> NodeCondition {
-    NodeCondition {
-        condition_type: NodeConditionType::Ready,
-        status: ConditionStatus::True,
-        last_heartbeat_time: Some(Utc::now().to_rfc3339()),
-    }
-}
-fn memory_condition(threshold: f64) -> NodeCondition {
-    let mut sys = System::new_all();
-    sys.refresh_memory();
-
-    let total_mem = sys.total_memory();
-    let avail_mem = sys.available_memory();
-    let used_ratio = if total_mem > 0 {
-        (total_mem - avail_mem) as f64 / total_mem as f64
-    } else {
-        0.0
-    };
-
-    let status = if used_ratio > threshold {
-        ConditionStatus::True
-    } else {
-        ConditionStatus::False
-    };
-
-    NodeCondition {
-        condition_type: NodeConditionType::MemoryPressure,
-        status,
-        last_heartbeat_time: Some(Utc::now().to_rfc3339()),
-    }
-}
-
-fn disk_condition(threshold: f64) -> NodeCondition {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-
-    let mut pressure = false;
-    let disks = Disks::new_with_refreshed_list();
-    for disk in disks.list() {
-        let total = disk.total_space();
-        let avail = disk.available_space();
-
-        if total > 0 {
-            let used_ratio = 1.0 - (avail as f64 / total as f64);
-            if used_ratio > threshold {
-                pressure = true;
-                break;
-            }
+/// Images cached by rkforge on this node, reported so the scheduler can prefer
+/// nodes which already have a pod's images.
+fn local_images() -> Vec<ContainerImage> {
+    match rkforge::images::local_images() {
+        Ok(images) => images
+            .into_iter()
+            .map(|(name, size_bytes)| ContainerImage {
+                names: vec![name],
+                size_bytes,
+            })
+            .collect(),
+        Err(e) => {
+            warn!("failed to list local images: {e}");
+            vec![]
         }
     }
+}
 
-    let status = if pressure {
-        ConditionStatus::True
-    } else {
-        ConditionStatus::False
-    };
-
+fn ready_condition() -> NodeCondition {
     NodeCondition {
-        condition_type: NodeConditionType::DiskPressure,
-        status,
+        condition_type: NodeConditionType::Ready,
+        status: ConditionStatus::True,
         last_heartbeat_time: Some(Utc::now().to_rfc3339()),
     }
 }
@@ -0,0 +1,197 @@
+//! `ImageService` backed by the rkforge image store.
+
+use anyhow::Result;
+use chrono::Utc;
+use libruntime::cri::cri_api::image_service_server::ImageService;
+use libruntime::cri::cri_api::{
+    AuthConfig, FilesystemIdentifier, FilesystemUsage, Image, ImageFsInfoRequest,
+    ImageFsInfoResponse, ImageSpec, ImageStatusRequest, ImageStatusResponse, ListImagesRequest,
+    ListImagesResponse, PullImageRequest, PullImageResponse, RemoveImageRequest,
+    RemoveImageResponse, UInt64Value,
+};
+use rkforge::images::{self, CachedImage};
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+use super::{internal, nanos};
+use crate::daemon::eviction::FsStats;
+
+pub struct ImageServer;
+
+/// The image an image spec names, by reference or by digest.
+fn find_image(image: &str) -> Result<Option<CachedImage>> {
+    let digest = images::cached_image_digest(image);
+    Ok(images::cached_images()?.into_iter().find(|cached| {
+        cached.digest == image
+            || digest.as_deref() == Some(cached.digest.as_str())
+            || cached.image_refs.iter().any(|r| r == image)
+    }))
+}
+
+/// A reference rkforge can pull `spec` with. Clients may pass back the
+/// digest [`ImageServer::pull_image`] returned instead of a reference.
+pub fn resolve_image(spec: &ImageSpec) -> Result<String> {
+    if !spec.user_specified_image.is_empty() {
+        return Ok(spec.user_specified_image.clone());
+    }
+    Ok(find_image(&spec.image)?
+        .and_then(|cached| cached.image_refs.into_iter().next())
+        .unwrap_or_else(|| spec.image.clone()))
+}
+
+fn image_name(spec: Option<&ImageSpec>) -> Result<&str, Status> {
+    spec.map(|s| s.image.as_str())
+        .filter(|image| !image.is_empty())
+        .ok_or_else(|| Status::invalid_argument("image is required"))
+}
+
+/// `<repository>@<digest>` for an image reference.
+fn repo_digest(image_ref: &str, digest: &str) -> String {
+    let repository = image_ref.split('@').next().unwrap_or(image_ref);
+    let repository = match repository.rsplit_once(':') {
+        Some((repo, tag)) if !tag.contains('/') => repo,
+        _ => repository,
+    };
+    format!("{repository}@{digest}")
+}
+
+fn to_cri_image(cached: CachedImage) -> Image {
+    let repo_digests = cached
+        .image_refs
+        .iter()
+        .map(|r| repo_digest(r, &cached.digest))
+        .collect();
+    Image {
+        id: cached.digest.clone(),
+        repo_tags: cached.image_refs,
+        repo_digests,
+        size: cached.size_bytes,
+        uid: None,
+        username: String::new(),
+        spec: Some(ImageSpec {
+            image: cached.digest,
+            ..Default::default()
+        }),
+        pinned: false,
+    }
+}
+
+#[tonic::async_trait]
+impl ImageService for ImageServer {
+    async fn list_images(
+        &self,
+        request: Request<ListImagesRequest>,
+    ) -> Result<Response<ListImagesResponse>, Status> {
+        let filter = request
+            .into_inner()
+            .filter
+            .and_then(|f| f.image)
+            .map(|spec| spec.image)
+            .filter(|image| !image.is_empty());
+        let images = match filter {
+            Some(image) => find_image(&image).map_err(internal)?.into_iter().collect(),
+            None => images::cached_images().map_err(internal)?,
+        };
+        Ok(Response::new(ListImagesResponse {
+            images: images.into_iter().map(to_cri_image).collect(),
+        }))
+    }
+
+    async fn image_status(
+        &self,
+        request: Request<ImageStatusRequest>,
+    ) -> Result<Response<ImageStatusResponse>, Status> {
+        let request = request.into_inner();
+        let image = image_name(request.image.as_ref())?;
+        Ok(Response::new(ImageStatusResponse {
+            image: find_image(image).map_err(internal)?.map(to_cri_image),
+            info: Default::default(),
+        }))
+    }
+
+    async fn pull_image(
+        &self,
+        request: Request<PullImageRequest>,
+    ) -> Result<Response<PullImageResponse>, Status> {
+        let request = request.into_inner();
+        let image = image_name(request.image.as_ref())?.to_string();
+        if request
+            .auth
+            .is_some_and(|auth| auth != AuthConfig::default())
+        {
+            warn!(
+                "[cri] ignoring the credentials passed to pull {image}, rkl uses the registries logged in with rkforge"
+            );
+        }
+        let pull_ref = image.clone();
+        tokio::task::spawn_blocking(move || {
+            rkforge::pull::sync_pull_or_get_image(&pull_ref, None::<&str>)
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map_err(internal)?;
+        info!("[cri] pulled image {image}");
+        let image_ref = images::cached_image_digest(&image).unwrap_or(image);
+        Ok(Response::new(PullImageResponse { image_ref }))
+    }
+
+    async fn remove_image(
+        &self,
+        request: Request<RemoveImageRequest>,
+    ) -> Result<Response<RemoveImageResponse>, Status> {
+        let request = request.into_inner();
+        let image = image_name(request.image.as_ref())?;
+        if let Some(cached) = find_image(image).map_err(internal)? {
+            images::remove_cached_image(&cached.digest).map_err(internal)?;
+            info!("[cri] removed image {}", cached.image_refs.join(", "));
+        }
+        Ok(Response::new(RemoveImageResponse {}))
+    }
+
+    async fn image_fs_info(
+        &self,
+        _request: Request<ImageFsInfoRequest>,
+    ) -> Result<Response<ImageFsInfoResponse>, Status> {
+        let path = &rkforge::config::image::CONFIG.layers_store_root;
+        let stats = FsStats::observe(path).map_err(internal)?;
+        let used_bytes = images::cached_images()
+            .map_err(internal)?
+            .iter()
+            .map(|image| image.size_bytes)
+            .sum();
+        Ok(Response::new(ImageFsInfoResponse {
+            image_filesystems: vec![FilesystemUsage {
+                timestamp: nanos(Utc::now()),
+                fs_id: Some(FilesystemIdentifier {
+                    mountpoint: path.display().to_string(),
+                }),
+                used_bytes: Some(UInt64Value { value: used_bytes }),
+                inodes_used: Some(UInt64Value {
+                    value: stats.inodes.saturating_sub(stats.inodes_free),
+                }),
+            }],
+            container_filesystems: vec![],
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repo_digest() {
+        assert_eq!(
+            repo_digest("docker.io/library/nginx:latest", "sha256:abc"),
+            "docker.io/library/nginx@sha256:abc"
+        );
+        assert_eq!(
+            repo_digest("localhost:5000/busybox", "sha256:abc"),
+            "localhost:5000/busybox@sha256:abc"
+        );
+        assert_eq!(
+            repo_digest("nginx@sha256:def", "sha256:abc"),
+            "nginx@sha256:abc"
+        );
+    }
+}
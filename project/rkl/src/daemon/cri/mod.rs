@@ -0,0 +1,339 @@
+//! CRI v1 runtime and image services.
+//!
+//! The daemon serves `runtime.v1.RuntimeService` and `runtime.v1.ImageService`
+//! on the unix socket [`CRI_SOCKET`], so that `crictl` can inspect and drive
+//! the node and a kubelet can use rkl as its container runtime.
+//!
+//! A sandbox is a pod as rkl runs it anyway, a pause container with a
+//! [`PodInfo`] file, so pods created through rks or `rkl pod` are listed
+//! too. The configs CRI clients create sandboxes and containers with are
+//! kept in a [`CriStore`], to be returned with their statuses.
+//!
+//! `Exec` sessions are served over WebSocket by the [`StreamingServer`] on
+//! [`CRI_STREAMING_ADDRESS`]. Container and sandbox stats come from the
+//! cgroups of the containers, like the daemon's resource usage collection.
+//! `Attach`, `PortForward`, metrics and container events are not supported.
+//!
+//! [`CRI_SOCKET`]: crate::config::CRI_SOCKET
+//! [`CRI_STREAMING_ADDRESS`]: crate::config::CRI_STREAMING_ADDRESS
+//! [`PodInfo`]: crate::commands::pod::PodInfo
+
+mod image;
+mod runtime;
+mod store;
+mod streaming;
+
+use std::collections::HashMap;
+use std::fs::{self, Permissions};
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use common::{ContainerSpec, SeccompProfile, SeccompProfileType, SecurityContext};
+use futures::stream;
+use libcontainer::container::ContainerStatus;
+use libcontainer::syscall::syscall::create_syscall;
+use libruntime::cri::cri_api::image_service_server::ImageServiceServer;
+use libruntime::cri::cri_api::runtime_service_server::RuntimeServiceServer;
+use libruntime::cri::cri_api::security_profile::ProfileType;
+use libruntime::cri::cri_api::{
+    ContainerConfig, ContainerMetadata, ContainerState, Mount, PodSandboxMetadata,
+};
+use libruntime::rootpath;
+use tokio::net::{TcpListener, UnixListener};
+use tonic::Status;
+use tonic::transport::Server;
+use tracing::{info, warn};
+
+use image::ImageServer;
+use runtime::RuntimeServer;
+pub use store::CriStore;
+pub use streaming::StreamingServer;
+
+use crate::config::CRI_STREAMING_ADDRESS;
+
+/// Serve the CRI services on `socket` until the server fails.
+pub async fn serve(socket: &Path) -> Result<()> {
+    if let Some(dir) = socket.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // A socket left behind by a previous daemon fails the bind
+    match fs::remove_file(socket) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let listener = UnixListener::bind(socket)
+        .with_context(|| format!("failed to bind {}", socket.display()))?;
+    fs::set_permissions(socket, Permissions::from_mode(0o600))?;
+
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let streaming_listener = TcpListener::bind(*CRI_STREAMING_ADDRESS)
+        .await
+        .with_context(|| format!("failed to bind {}", *CRI_STREAMING_ADDRESS))?;
+    let streaming = Arc::new(StreamingServer::new(
+        streaming_listener.local_addr()?,
+        root_path.clone(),
+    ));
+    tokio::spawn({
+        let streaming = streaming.clone();
+        async move {
+            if let Err(e) = streaming.serve(streaming_listener).await {
+                warn!("[cri] streaming server failed: {e:#}");
+            }
+        }
+    });
+    let incoming = stream::unfold(listener, |listener| async move {
+        let conn = listener.accept().await.map(|(stream, _)| stream);
+        Some((conn, listener))
+    });
+
+    info!("[cri] serving on {}", socket.display());
+    Server::builder()
+        .add_service(RuntimeServiceServer::new(RuntimeServer::new(
+            root_path.clone(),
+            streaming,
+        )))
+        .add_service(ImageServiceServer::new(ImageServer))
+        .serve_with_incoming(incoming)
+        .await?;
+    Ok(())
+}
+
+/// Id of a sandbox, which also names its pod file and pause container.
+pub fn sandbox_id(metadata: &PodSandboxMetadata) -> String {
+    format!(
+        "{}_{}_{}",
+        metadata.namespace, metadata.name, metadata.attempt
+    )
+}
+
+/// Name of a container within its pod. As for every pod, the container id
+/// is `<sandbox id>-<name>`.
+pub fn container_name(metadata: &ContainerMetadata) -> String {
+    format!("{}_{}", metadata.name, metadata.attempt)
+}
+
+fn container_state(status: ContainerStatus) -> ContainerState {
+    match status {
+        ContainerStatus::Creating | ContainerStatus::Created => ContainerState::ContainerCreated,
+        ContainerStatus::Running | ContainerStatus::Paused => ContainerState::ContainerRunning,
+        ContainerStatus::Stopped => ContainerState::ContainerExited,
+    }
+}
+
+fn nanos(time: DateTime<Utc>) -> i64 {
+    time.timestamp_nanos_opt().unwrap_or_default()
+}
+
+fn system_nanos(time: SystemTime) -> i64 {
+    nanos(DateTime::<Utc>::from(time))
+}
+
+/// Whether `labels` has every label of `selector`.
+fn matches_labels(labels: &HashMap<String, String>, selector: &HashMap<String, String>) -> bool {
+    selector.iter().all(|(k, v)| labels.get(k) == Some(v))
+}
+
+/// The spec rkl creates a container from for a CRI container config. The
+/// command and its arguments replace the image's whole command line.
+fn container_spec(container_id: &str, image: &str, config: &ContainerConfig) -> ContainerSpec {
+    let mut args = config.command.clone();
+    args.extend(config.args.iter().cloned());
+    let security_context = config
+        .linux
+        .as_ref()
+        .and_then(|linux| linux.security_context.as_ref())
+        .map(|ctx| SecurityContext {
+            run_as_user: ctx.run_as_user.as_ref().map(|v| v.value),
+            run_as_group: ctx.run_as_group.as_ref().map(|v| v.value),
+            privileged: Some(ctx.privileged),
+            allow_privilege_escalation: Some(!ctx.no_new_privs),
+            capabilities: None,
+            read_only_root_filesystem: Some(ctx.readonly_rootfs),
+            seccomp_profile: ctx.seccomp.as_ref().map(|profile| SeccompProfile {
+                profile_type: match ProfileType::try_from(profile.profile_type) {
+                    Ok(ProfileType::RuntimeDefault) => SeccompProfileType::RuntimeDefault,
+                    Ok(ProfileType::Localhost) => SeccompProfileType::Localhost,
+                    _ => SeccompProfileType::Unconfined,
+                },
+                localhost_profile: Some(profile.localhost_ref.clone())
+                    .filter(|path| !path.is_empty()),
+            }),
+            proc_mount: None,
+        });
+    ContainerSpec {
+        name: container_id.to_string(),
+        image: image.to_string(),
+        ports: vec![],
+        args,
+        resources: None,
+        liveness_probe: None,
+        readiness_probe: None,
+        startup_probe: None,
+        security_context,
+        env: None,
+        volume_mounts: None,
+        command: None,
+        working_dir: None,
+        lifecycle: None,
+        restart_policy: None,
+        image_pull_policy: None,
+    }
+}
+
+/// The host paths a CRI container config bind mounts into the container.
+/// Image volumes aren't supported.
+fn host_mounts(config: &ContainerConfig) -> Result<Vec<Mount>, Status> {
+    for mount in &config.mounts {
+        if mount.image.is_some() {
+            return Err(Status::invalid_argument(format!(
+                "mount {}: image mounts are not supported by rkl",
+                mount.container_path
+            )));
+        }
+        if !Path::new(&mount.container_path).is_absolute() {
+            return Err(Status::invalid_argument(format!(
+                "mount container path {:?} is not absolute",
+                mount.container_path
+            )));
+        }
+        if mount.host_path.is_empty() || !Path::new(&mount.host_path).exists() {
+            return Err(Status::invalid_argument(format!(
+                "mount {}: host path {:?} does not exist",
+                mount.container_path, mount.host_path
+            )));
+        }
+    }
+    Ok(config.mounts.clone())
+}
+
+fn internal(e: anyhow::Error) -> Status {
+    Status::internal(format!("{e:#}"))
+}
+
+fn unsupported(call: &str) -> Status {
+    Status::unimplemented(format!("{call} is not supported by rkl"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libruntime::cri::cri_api::{
+        ImageSpec, Int64Value, LinuxContainerConfig, LinuxContainerSecurityContext, SecurityProfile,
+    };
+
+    #[test]
+    fn test_ids() {
+        let sandbox = PodSandboxMetadata {
+            name: "nginx".to_string(),
+            uid: "1234".to_string(),
+            namespace: "default".to_string(),
+            attempt: 1,
+        };
+        assert_eq!(sandbox_id(&sandbox), "default_nginx_1");
+        let container = ContainerMetadata {
+            name: "web".to_string(),
+            attempt: 0,
+        };
+        assert_eq!(container_name(&container), "web_0");
+    }
+
+    #[test]
+    fn test_matches_labels() {
+        let labels = HashMap::from([
+            ("app".to_string(), "web".to_string()),
+            ("tier".to_string(), "front".to_string()),
+        ]);
+        assert!(matches_labels(&labels, &HashMap::new()));
+        assert!(matches_labels(
+            &labels,
+            &HashMap::from([("app".to_string(), "web".to_string())])
+        ));
+        assert!(!matches_labels(
+            &labels,
+            &HashMap::from([("app".to_string(), "db".to_string())])
+        ));
+    }
+
+    #[test]
+    fn test_container_spec() {
+        let config = ContainerConfig {
+            command: vec!["sh".to_string(), "-c".to_string()],
+            args: vec!["sleep 1000".to_string()],
+            linux: Some(LinuxContainerConfig {
+                resources: None,
+                security_context: Some(LinuxContainerSecurityContext {
+                    run_as_user: Some(Int64Value { value: 1000 }),
+                    readonly_rootfs: true,
+                    no_new_privs: true,
+                    seccomp: Some(SecurityProfile {
+                        profile_type: ProfileType::RuntimeDefault as i32,
+                        localhost_ref: String::new(),
+                    }),
+                    ..Default::default()
+                }),
+            }),
+            ..Default::default()
+        };
+        let spec = container_spec("default_busybox_0-sh_0", "busybox:latest", &config);
+        assert_eq!(spec.name, "default_busybox_0-sh_0");
+        assert_eq!(spec.image, "busybox:latest");
+        assert_eq!(spec.args, vec!["sh", "-c", "sleep 1000"]);
+        let ctx = spec.security_context.unwrap();
+        assert_eq!(ctx.run_as_user, Some(1000));
+        assert_eq!(ctx.read_only_root_filesystem, Some(true));
+        assert!(ctx.no_new_privileges());
+        assert_eq!(
+            ctx.seccomp_profile.unwrap().profile_type,
+            SeccompProfileType::RuntimeDefault
+        );
+    }
+
+    #[test]
+    fn test_host_mounts() {
+        let dir = tempfile::tempdir().unwrap();
+        let host_path = dir.path().to_string_lossy().into_owned();
+        let mount = Mount {
+            container_path: "/data".to_string(),
+            host_path: host_path.clone(),
+            readonly: true,
+            ..Default::default()
+        };
+        let config = ContainerConfig {
+            mounts: vec![mount.clone()],
+            ..Default::default()
+        };
+        assert_eq!(host_mounts(&config).unwrap(), vec![mount.clone()]);
+
+        let invalid = [
+            Mount {
+                container_path: "data".to_string(),
+                ..mount.clone()
+            },
+            Mount {
+                host_path: dir.path().join("missing").to_string_lossy().into_owned(),
+                ..mount.clone()
+            },
+            Mount {
+                host_path: String::new(),
+                image: Some(ImageSpec {
+                    image: "busybox".to_string(),
+                    ..Default::default()
+                }),
+                ..mount
+            },
+        ];
+        for mount in invalid {
+            let config = ContainerConfig {
+                mounts: vec![mount],
+                ..Default::default()
+            };
+            let status = host_mounts(&config).unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+    }
+}
@@ -0,0 +1,1195 @@
+//! `RuntimeService` on top of the pods and containers rkl runs.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use common::{ObjectMeta, PodSpec, PodStatus, PodTask};
+use futures::Stream;
+use libcontainer::container::ContainerStatus as OciStatus;
+use liboci_cli::{Delete, Start};
+use libruntime::cri::cri_api::runtime_service_server::RuntimeService;
+use libruntime::cri::cri_api::*;
+use nix::sys::signal::{Signal, kill};
+use nix::sys::wait::{WaitStatus, waitpid};
+use rkforge::commands::container::rootfs_mount::RootfsMount;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tonic::{Request, Response, Status};
+use tracing::info;
+use uuid::Uuid;
+
+use super::image::resolve_image;
+use super::{
+    CriStore, StreamingServer, container_name, container_spec, container_state, host_mounts,
+    internal, matches_labels, nanos, sandbox_id, system_nanos, unsupported,
+};
+use crate::commands::container::checkpoint::{CheckpointArgs, checkpoint_container};
+use crate::commands::pod::lifecycle::ContainerLifecycle;
+use crate::commands::pod::{PodInfo, lifecycle, standalone};
+use crate::commands::{delete, load_container, start};
+use crate::daemon::metrics;
+use crate::daemon::pod_worker::{RESTART_BACKOFF, resolve_exit_status};
+use crate::daemon::session::spawn_process;
+use crate::daemon::stats::{self, STATS_MANAGER};
+use crate::logs;
+use crate::task::TaskRunner;
+
+/// Time a container gets to stop after SIGKILL.
+const KILL_TIMEOUT: Duration = Duration::from_secs(10);
+/// Output of `ExecSync` kept per stream, the rest is discarded.
+const MAX_EXEC_OUTPUT: u64 = 16 * 1024 * 1024;
+const CNI_CONF_DIR: &str = "/etc/cni/net.d";
+
+#[derive(Clone)]
+pub struct RuntimeServer {
+    root_path: PathBuf,
+    store: CriStore,
+    streaming: Arc<StreamingServer>,
+}
+
+/// Run blocking runtime work off the async workers.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, Status> + Send + 'static,
+) -> Result<T, Status> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+}
+
+fn required<T>(value: Option<T>, what: &str) -> Result<T, Status> {
+    value.ok_or_else(|| Status::invalid_argument(format!("{what} is required")))
+}
+
+/// The one id out of `ids` equal to or starting with `id`.
+fn resolve_id<'a>(id: &str, ids: impl Iterator<Item = &'a String>) -> Option<&'a String> {
+    let matches = ids.filter(|c| c.starts_with(id)).collect::<Vec<_>>();
+    matches
+        .iter()
+        .find(|c| c.as_str() == id)
+        .or(matches.first().filter(|_| matches.len() == 1))
+        .copied()
+}
+
+impl RuntimeServer {
+    pub fn new(root_path: PathBuf, streaming: Arc<StreamingServer>) -> Self {
+        RuntimeServer {
+            store: CriStore::new(&root_path),
+            root_path,
+            streaming,
+        }
+    }
+
+    /// Every pod on the node, keyed by sandbox id.
+    fn pods(&self) -> Vec<(String, PodInfo)> {
+        let Ok(entries) = fs::read_dir(self.root_path.join("pods")) else {
+            return vec![];
+        };
+        let mut pods = entries
+            .flatten()
+            .filter_map(|entry| {
+                let id = entry.file_name().to_string_lossy().into_owned();
+                let pod_info = PodInfo::load(&self.root_path, &id).ok()?;
+                Some((id, pod_info))
+            })
+            .collect::<Vec<_>>();
+        pods.sort_by(|a, b| a.0.cmp(&b.0));
+        pods
+    }
+
+    fn find_sandbox(&self, id: &str) -> Result<(String, PodInfo), Status> {
+        let pods = self.pods();
+        let sandbox_id = resolve_id(id, pods.iter().map(|(id, _)| id))
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("sandbox {id} not found")))?;
+        Ok(pods.into_iter().find(|(id, _)| *id == sandbox_id).unwrap())
+    }
+
+    /// The sandbox id, pod and full id of a container.
+    fn find_container(&self, id: &str) -> Result<(String, PodInfo, String), Status> {
+        let pods = self.pods();
+        let container_id = resolve_id(
+            id,
+            pods.iter().flat_map(|(_, pod)| pod.container_names.iter()),
+        )
+        .cloned()
+        .ok_or_else(|| Status::not_found(format!("container {id} not found")))?;
+        let (sandbox_id, pod_info) = pods
+            .into_iter()
+            .find(|(_, pod)| pod.container_names.contains(&container_id))
+            .unwrap();
+        Ok((sandbox_id, pod_info, container_id))
+    }
+
+    /// The config the sandbox was created with, or one describing a pod
+    /// which was not created through the CRI.
+    fn sandbox_config(&self, sandbox_id: &str, pod_info: &PodInfo) -> PodSandboxConfig {
+        if let Some(config) = self.store.sandbox(sandbox_id) {
+            return config;
+        }
+        let mut config = PodSandboxConfig {
+            metadata: Some(PodSandboxMetadata {
+                name: sandbox_id.to_string(),
+                ..Default::default()
+            }),
+            log_directory: pod_info.log_directory.clone().unwrap_or_default(),
+            ..Default::default()
+        };
+        if let Some(pod) = &pod_info.pod_spec {
+            config.metadata = Some(PodSandboxMetadata {
+                name: pod.metadata.name.clone(),
+                uid: pod.metadata.uid.to_string(),
+                namespace: pod.metadata.namespace.clone(),
+                attempt: 0,
+            });
+            config.hostname = pod.metadata.name.clone();
+            config.labels = pod.metadata.labels.clone();
+            config.annotations = pod.metadata.annotations.clone();
+        }
+        config
+    }
+
+    /// The config the container was created with, or one describing a
+    /// container which was not created through the CRI.
+    fn container_config(
+        &self,
+        sandbox_id: &str,
+        pod_info: &PodInfo,
+        container_id: &str,
+    ) -> ContainerConfig {
+        if let Some(config) = self.store.container(container_id) {
+            return config;
+        }
+        let name = container_id
+            .strip_prefix(&format!("{sandbox_id}-"))
+            .unwrap_or(container_id);
+        let image = pod_info
+            .pod_spec
+            .iter()
+            .flat_map(|pod| pod.spec.containers.iter())
+            .find(|c| c.name == name)
+            .map(|c| c.image.clone())
+            .unwrap_or_default();
+        ContainerConfig {
+            metadata: Some(ContainerMetadata {
+                name: name.to_string(),
+                attempt: 0,
+            }),
+            image: Some(ImageSpec {
+                image: image.clone(),
+                user_specified_image: image,
+                ..Default::default()
+            }),
+            log_path: format!("{container_id}/0.log"),
+            ..Default::default()
+        }
+    }
+
+    fn sandbox_status(&self, sandbox_id: &str, pod_info: &PodInfo) -> PodSandboxStatus {
+        let config = self.sandbox_config(sandbox_id, pod_info);
+        let pause = load_container(&self.root_path, sandbox_id).ok();
+        let state = if pause
+            .as_ref()
+            .is_some_and(|c| c.status() == OciStatus::Running)
+        {
+            PodSandboxState::SandboxReady
+        } else {
+            PodSandboxState::SandboxNotready
+        };
+        let created_at = pause
+            .and_then(|c| c.created())
+            .map(nanos)
+            .or_else(|| {
+                fs::metadata(self.root_path.join("pods").join(sandbox_id))
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .map(system_nanos)
+            })
+            .unwrap_or_default();
+        PodSandboxStatus {
+            id: sandbox_id.to_string(),
+            metadata: config.metadata,
+            state: state as i32,
+            created_at,
+            network: Some(PodSandboxNetworkStatus {
+                ip: pod_info.pod_ip.clone().unwrap_or_default(),
+                additional_ips: vec![],
+            }),
+            linux: None,
+            labels: config.labels,
+            annotations: config.annotations,
+            runtime_handler: String::new(),
+        }
+    }
+
+    /// Path of the log file the container currently writes to.
+    fn log_path(pod_info: &PodInfo, container_id: &str, config: &ContainerConfig) -> String {
+        let Some(log_directory) = &pod_info.log_directory else {
+            return String::new();
+        };
+        let container_dir = Path::new(&config.log_path)
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map_or_else(|| PathBuf::from(container_id), Path::to_path_buf);
+        let dir = Path::new(log_directory).join(container_dir);
+        logs::current_instance(&dir)
+            .unwrap_or_else(|| Path::new(log_directory).join(&config.log_path))
+            .display()
+            .to_string()
+    }
+
+    fn container_status(
+        &self,
+        sandbox_id: &str,
+        pod_info: &PodInfo,
+        container_id: &str,
+    ) -> Result<ContainerStatus, Status> {
+        let container = load_container(&self.root_path, container_id)
+            .map_err(|_| Status::not_found(format!("container {container_id} not found")))?;
+        let config = self.container_config(sandbox_id, pod_info, container_id);
+        let state = container_state(container.status());
+        let created_at = container.created().map(nanos).unwrap_or_default();
+        let log_path = Self::log_path(pod_info, container_id, &config);
+        let image = config.image.clone().unwrap_or_default();
+        let image_ref = rkforge::images::cached_image_digest(&image.image)
+            .unwrap_or_else(|| image.image.clone());
+
+        let mut status = ContainerStatus {
+            id: container_id.to_string(),
+            metadata: config.metadata,
+            state: state as i32,
+            created_at,
+            // The start of a container is not recorded, it starts right
+            // after it is created.
+            started_at: if state == ContainerState::ContainerCreated {
+                0
+            } else {
+                created_at
+            },
+            image: Some(image),
+            image_ref: image_ref.clone(),
+            labels: config.labels,
+            annotations: config.annotations,
+            log_path,
+            image_id: image_ref,
+            ..Default::default()
+        };
+        if state == ContainerState::ContainerExited {
+            let (exit_code, _, message) = resolve_exit_status(&container);
+            status.exit_code = exit_code;
+            status.reason = if exit_code == 0 { "Completed" } else { "Error" }.to_string();
+            status.message = message.unwrap_or_default();
+            // The log shim closes the log file when the container exits
+            status.finished_at = fs::metadata(&status.log_path)
+                .and_then(|m| m.modified())
+                .map(system_nanos)
+                .unwrap_or(created_at);
+        }
+        Ok(status)
+    }
+
+    fn run_sandbox(&self, request: RunPodSandboxRequest) -> Result<String, Status> {
+        let config = required(request.config, "sandbox config")?;
+        let metadata = required(config.metadata.clone(), "sandbox metadata")?;
+        let id = sandbox_id(&metadata);
+        if self.root_path.join("pods").join(&id).exists() {
+            return Err(Status::already_exists(format!(
+                "sandbox {id} already exists"
+            )));
+        }
+
+        let task = PodTask {
+            api_version: "v1".to_string(),
+            kind: "Pod".to_string(),
+            metadata: ObjectMeta {
+                name: id.clone(),
+                namespace: metadata.namespace.clone(),
+                uid: metadata.uid.parse().unwrap_or_else(|_| Uuid::new_v4()),
+                labels: config.labels.clone(),
+                annotations: config.annotations.clone(),
+                ..Default::default()
+            },
+            spec: PodSpec::default(),
+            status: PodStatus::default(),
+        };
+        let mut runner = TaskRunner::from_task(task).map_err(internal)?;
+        runner.sandbox_config = Some(config.clone());
+        // The pause container is named after the sandbox id
+        let mut run_config = config.clone();
+        if let Some(metadata) = run_config.metadata.as_mut() {
+            metadata.name = id.clone();
+        }
+        let (_, pod_ip) = runner
+            .sync_run_pod_sandbox(RunPodSandboxRequest {
+                config: Some(run_config),
+                runtime_handler: request.runtime_handler,
+            })
+            .map_err(internal)?;
+
+        PodInfo::new(&runner, id.clone(), vec![], &pod_ip)
+            .save(&self.root_path, &id)
+            .map_err(internal)?;
+        self.store.save_sandbox(&id, &config).map_err(internal)?;
+        info!("[cri] sandbox {id} is running with IP {pod_ip}");
+        Ok(id)
+    }
+
+    fn stop_sandbox(&self, id: &str) -> Result<(), Status> {
+        let Ok((sandbox_id, pod_info)) = self.find_sandbox(id) else {
+            return Ok(());
+        };
+        lifecycle::terminate_containers(&self.root_path, &pod_info);
+        if let Ok(pause) = load_container(&self.root_path, &sandbox_id)
+            && pause.status() == OciStatus::Running
+            && let Some(pid) = pause.state.pid
+        {
+            standalone::remove_pod_network(pid).map_err(internal)?;
+            lifecycle::signal_and_wait(&self.root_path, &sandbox_id, "SIGKILL", KILL_TIMEOUT)
+                .map_err(internal)?;
+        }
+        info!("[cri] sandbox {sandbox_id} stopped");
+        Ok(())
+    }
+
+    fn remove_sandbox(&self, id: &str) -> Result<(), Status> {
+        let Ok((sandbox_id, pod_info)) = self.find_sandbox(id) else {
+            return Ok(());
+        };
+        self.stop_sandbox(&sandbox_id)?;
+        if let Some(pod) = &pod_info.pod_spec {
+            RESTART_BACKOFF.forget_pod(pod.metadata.uid);
+        }
+        metrics::forget_pod(&sandbox_id);
+        standalone::remove_pod(&self.root_path, &sandbox_id, &pod_info).map_err(internal)?;
+        for container_id in &pod_info.container_names {
+            self.store
+                .remove_container(container_id)
+                .map_err(internal)?;
+        }
+        self.store.remove_sandbox(&sandbox_id).map_err(internal)?;
+        info!("[cri] sandbox {sandbox_id} removed");
+        Ok(())
+    }
+
+    fn create(&self, request: CreateContainerRequest) -> Result<String, Status> {
+        let config = required(request.config, "container config")?;
+        let metadata = required(config.metadata.clone(), "container metadata")?;
+        let (sandbox_id, mut pod_info) = self.find_sandbox(&request.pod_sandbox_id)?;
+        let mut pod = pod_info.pod_spec.clone().ok_or_else(|| {
+            Status::failed_precondition(format!("sandbox {sandbox_id} has no pod spec"))
+        })?;
+        let name = container_name(&metadata);
+        let container_id = format!("{sandbox_id}-{name}");
+        if pod_info.container_names.contains(&container_id) {
+            return Err(Status::already_exists(format!(
+                "container {container_id} already exists"
+            )));
+        }
+        let image = resolve_image(&config.image.clone().unwrap_or_default()).map_err(internal)?;
+        if image.is_empty() {
+            return Err(Status::invalid_argument("image is required"));
+        }
+        let mounts = host_mounts(&config)?;
+        let pause = load_container(&self.root_path, &sandbox_id).map_err(internal)?;
+
+        let spec = container_spec(&container_id, &image, &config);
+        let mut pod_spec = spec.clone();
+        pod_spec.name = name;
+        pod.spec.containers.push(pod_spec);
+
+        let mut runner = TaskRunner::from_task(pod.clone()).map_err(internal)?;
+        runner.pause_pid = pause.state.pid;
+        runner.sandbox_config = Some(
+            request
+                .sandbox_config
+                .unwrap_or_else(|| self.sandbox_config(&sandbox_id, &pod_info)),
+        );
+        let mut create_request = runner
+            .sync_build_create_container_request(&sandbox_id, &spec)
+            .map_err(internal)?;
+        if let Some(built) = create_request.config.as_mut() {
+            built.envs.extend(config.envs.iter().cloned());
+            built.mounts.extend(mounts);
+            if !config.working_dir.is_empty() {
+                built.working_dir = config.working_dir.clone();
+            }
+            if !config.log_path.is_empty() {
+                built.log_path = config.log_path.clone();
+            }
+            built.labels = config.labels.clone();
+            built.annotations = config.annotations.clone();
+            if let Some(mut resources) = config.linux.as_ref().and_then(|l| l.resources.clone()) {
+                // A period without a quota is no CPU limit
+                if resources.cpu_quota <= 0 {
+                    resources.cpu_period = 0;
+                }
+                built.linux.get_or_insert_default().resources = Some(resources);
+            }
+        }
+        if let Err(e) = runner.create_container(create_request) {
+            runner.stop_all_rootfs_mounts();
+            return Err(internal(e));
+        }
+
+        if let Some(stop_signal) = runner.stop_signal(&container_id) {
+            let lifecycle = ContainerLifecycle {
+                stop_signal: Some(stop_signal),
+                ..Default::default()
+            };
+            pod_info.lifecycles.insert(container_id.clone(), lifecycle);
+        }
+        pod_info.container_names.push(container_id.clone());
+        pod_info.pod_spec = Some(pod);
+        PodInfo::delete(&self.root_path, &sandbox_id)
+            .and_then(|_| pod_info.save(&self.root_path, &sandbox_id))
+            .map_err(internal)?;
+        self.store
+            .save_container(&container_id, &config)
+            .map_err(internal)?;
+        info!("[cri] container {container_id} created");
+        Ok(container_id)
+    }
+
+    fn stop(&self, id: &str, timeout: i64) -> Result<(), Status> {
+        let (_, pod_info, container_id) = self.find_container(id)?;
+        let signal = pod_info
+            .lifecycles
+            .get(&container_id)
+            .and_then(|l| l.stop_signal.clone())
+            .unwrap_or_else(|| "SIGTERM".to_string());
+        let timeout = Duration::from_secs(timeout.max(0) as u64);
+        let stopped = lifecycle::signal_and_wait(&self.root_path, &container_id, &signal, timeout)
+            .map_err(internal)?;
+        if !stopped {
+            lifecycle::signal_and_wait(&self.root_path, &container_id, "SIGKILL", KILL_TIMEOUT)
+                .map_err(internal)?;
+        }
+        info!("[cri] container {container_id} stopped");
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> Result<(), Status> {
+        let Ok((sandbox_id, mut pod_info, container_id)) = self.find_container(id) else {
+            return Ok(());
+        };
+        lifecycle::signal_and_wait(&self.root_path, &container_id, "SIGKILL", KILL_TIMEOUT)
+            .map_err(internal)?;
+        if let Ok(container) = load_container(&self.root_path, &container_id) {
+            let bundle_path = container.bundle().to_path_buf();
+            delete(
+                Delete {
+                    container_id: container_id.clone(),
+                    force: true,
+                },
+                self.root_path.clone(),
+            )
+            .map_err(internal)?;
+            if let Some(rootfs_mount) = RootfsMount::load(&bundle_path).map_err(internal)? {
+                rootfs_mount.stop().map_err(internal)?;
+            }
+        }
+        let _ = fs::remove_dir_all(self.root_path.join("logs").join(&container_id));
+
+        let name = container_id.strip_prefix(&format!("{sandbox_id}-"));
+        pod_info.container_names.retain(|c| *c != container_id);
+        pod_info.lifecycles.remove(&container_id);
+        if let Some(pod) = pod_info.pod_spec.as_mut() {
+            pod.spec
+                .containers
+                .retain(|c| Some(c.name.as_str()) != name);
+        }
+        PodInfo::delete(&self.root_path, &sandbox_id)
+            .and_then(|_| pod_info.save(&self.root_path, &sandbox_id))
+            .map_err(internal)?;
+        self.store
+            .remove_container(&container_id)
+            .map_err(internal)?;
+        info!("[cri] container {container_id} removed");
+        Ok(())
+    }
+}
+
+impl RuntimeServer {
+    /// Usage of a container, only its attributes when it isn't running.
+    /// `millicores` is the CPU usage of the last collection of the daemon.
+    fn container_stats(
+        &self,
+        sandbox_id: &str,
+        pod_info: &PodInfo,
+        container_id: &str,
+        millicores: &HashMap<String, u64>,
+    ) -> ContainerStats {
+        let usage = stats::container_stats(&self.root_path, container_id).ok();
+        self.usage_stats(
+            sandbox_id,
+            pod_info,
+            container_id,
+            usage.as_ref(),
+            millicores,
+        )
+    }
+
+    fn usage_stats(
+        &self,
+        sandbox_id: &str,
+        pod_info: &PodInfo,
+        container_id: &str,
+        usage: Option<&common::ContainerStats>,
+        millicores: &HashMap<String, u64>,
+    ) -> ContainerStats {
+        let config = self.container_config(sandbox_id, pod_info, container_id);
+        let mut cri_stats = ContainerStats {
+            attributes: Some(ContainerAttributes {
+                id: container_id.to_string(),
+                metadata: config.metadata,
+                labels: config.labels,
+                annotations: config.annotations,
+            }),
+            ..Default::default()
+        };
+        if let Some(usage) = usage {
+            let timestamp = nanos(Utc::now());
+            cri_stats.cpu = Some(cpu_usage(
+                timestamp,
+                usage.cpu_usage_usec,
+                millicores.get(container_id).copied(),
+            ));
+            cri_stats.memory = Some(memory_usage(timestamp, usage.memory_bytes));
+        }
+        cri_stats
+    }
+
+    /// Usage of a sandbox, the sum of its containers.
+    fn sandbox_stats(
+        &self,
+        sandbox_id: &str,
+        pod_info: &PodInfo,
+        millicores: &HashMap<String, u64>,
+    ) -> PodSandboxStats {
+        let config = self.sandbox_config(sandbox_id, pod_info);
+        let timestamp = nanos(Utc::now());
+        let (mut cpu_usec, mut cpu_millicores, mut memory_bytes, mut pids) = (0, None, 0, 0);
+        let mut containers = Vec::new();
+        for container_id in &pod_info.container_names {
+            let usage = stats::container_stats(&self.root_path, container_id).ok();
+            if let Some(usage) = &usage {
+                cpu_usec += usage.cpu_usage_usec;
+                memory_bytes += usage.memory_bytes;
+                pids += usage.pids;
+                if let Some(m) = millicores.get(container_id) {
+                    cpu_millicores = Some(cpu_millicores.unwrap_or(0) + m);
+                }
+            }
+            containers.push(self.usage_stats(
+                sandbox_id,
+                pod_info,
+                container_id,
+                usage.as_ref(),
+                millicores,
+            ));
+        }
+        let network = stats::pod_network(&self.root_path, pod_info);
+        PodSandboxStats {
+            attributes: Some(PodSandboxAttributes {
+                id: sandbox_id.to_string(),
+                metadata: config.metadata,
+                labels: config.labels,
+                annotations: config.annotations,
+            }),
+            linux: Some(LinuxPodSandboxStats {
+                cpu: Some(cpu_usage(timestamp, cpu_usec, cpu_millicores)),
+                memory: Some(memory_usage(timestamp, memory_bytes)),
+                network: Some(NetworkUsage {
+                    timestamp,
+                    default_interface: Some(NetworkInterfaceUsage {
+                        name: "eth0".to_string(),
+                        rx_bytes: uint64(network.rx_bytes),
+                        tx_bytes: uint64(network.tx_bytes),
+                        ..Default::default()
+                    }),
+                    interfaces: vec![],
+                }),
+                process: Some(ProcessUsage {
+                    timestamp,
+                    process_count: uint64(pids),
+                }),
+                containers,
+                io: None,
+            }),
+            windows: None,
+        }
+    }
+}
+
+/// CPU usage in millicores of every container at the last collection of
+/// the daemon, keyed by container id.
+fn collected_millicores() -> HashMap<String, u64> {
+    STATS_MANAGER
+        .get()
+        .and_then(|manager| manager.latest())
+        .into_iter()
+        .flat_map(|node| node.pods)
+        .flat_map(|pod| pod.containers)
+        .map(|c| (c.name, c.cpu_millicores))
+        .collect()
+}
+
+fn uint64(value: u64) -> Option<UInt64Value> {
+    Some(UInt64Value { value })
+}
+
+fn cpu_usage(timestamp: i64, usage_usec: u64, millicores: Option<u64>) -> CpuUsage {
+    CpuUsage {
+        timestamp,
+        usage_core_nano_seconds: uint64(usage_usec * 1000),
+        usage_nano_cores: millicores.and_then(|m| uint64(m * 1_000_000)),
+        psi: None,
+    }
+}
+
+fn memory_usage(timestamp: i64, memory_bytes: u64) -> MemoryUsage {
+    MemoryUsage {
+        timestamp,
+        working_set_bytes: uint64(memory_bytes),
+        usage_bytes: uint64(memory_bytes),
+        ..Default::default()
+    }
+}
+
+/// Read at most [`MAX_EXEC_OUTPUT`] bytes of `output`, and drain the rest.
+async fn read_output(mut output: impl AsyncRead + Unpin) -> std::io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    (&mut output)
+        .take(MAX_EXEC_OUTPUT)
+        .read_to_end(&mut buf)
+        .await?;
+    tokio::io::copy(&mut output, &mut tokio::io::sink()).await?;
+    Ok(buf)
+}
+
+fn network_ready() -> bool {
+    fs::read_dir(CNI_CONF_DIR).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.ends_with(".conf") || name.ends_with(".conflist") || name.ends_with(".json")
+        })
+    })
+}
+
+#[tonic::async_trait]
+impl RuntimeService for RuntimeServer {
+    async fn version(
+        &self,
+        _request: Request<VersionRequest>,
+    ) -> Result<Response<VersionResponse>, Status> {
+        Ok(Response::new(VersionResponse {
+            version: "0.1.0".to_string(),
+            runtime_name: "rkl".to_string(),
+            runtime_version: env!("CARGO_PKG_VERSION").to_string(),
+            runtime_api_version: "v1".to_string(),
+        }))
+    }
+
+    async fn run_pod_sandbox(
+        &self,
+        request: Request<RunPodSandboxRequest>,
+    ) -> Result<Response<RunPodSandboxResponse>, Status> {
+        let this = self.clone();
+        let pod_sandbox_id = blocking(move || this.run_sandbox(request.into_inner())).await?;
+        Ok(Response::new(RunPodSandboxResponse { pod_sandbox_id }))
+    }
+
+    async fn stop_pod_sandbox(
+        &self,
+        request: Request<StopPodSandboxRequest>,
+    ) -> Result<Response<StopPodSandboxResponse>, Status> {
+        let this = self.clone();
+        let id = request.into_inner().pod_sandbox_id;
+        blocking(move || this.stop_sandbox(&id)).await?;
+        Ok(Response::new(StopPodSandboxResponse {}))
+    }
+
+    async fn remove_pod_sandbox(
+        &self,
+        request: Request<RemovePodSandboxRequest>,
+    ) -> Result<Response<RemovePodSandboxResponse>, Status> {
+        let this = self.clone();
+        let id = request.into_inner().pod_sandbox_id;
+        blocking(move || this.remove_sandbox(&id)).await?;
+        Ok(Response::new(RemovePodSandboxResponse {}))
+    }
+
+    async fn pod_sandbox_status(
+        &self,
+        request: Request<PodSandboxStatusRequest>,
+    ) -> Result<Response<PodSandboxStatusResponse>, Status> {
+        let (sandbox_id, pod_info) = self.find_sandbox(&request.into_inner().pod_sandbox_id)?;
+        let containers_statuses = pod_info
+            .container_names
+            .iter()
+            .filter_map(|id| self.container_status(&sandbox_id, &pod_info, id).ok())
+            .collect();
+        Ok(Response::new(PodSandboxStatusResponse {
+            status: Some(self.sandbox_status(&sandbox_id, &pod_info)),
+            info: Default::default(),
+            containers_statuses,
+            timestamp: nanos(Utc::now()),
+        }))
+    }
+
+    async fn list_pod_sandbox(
+        &self,
+        request: Request<ListPodSandboxRequest>,
+    ) -> Result<Response<ListPodSandboxResponse>, Status> {
+        let filter = request.into_inner().filter.unwrap_or_default();
+        let items = self
+            .pods()
+            .iter()
+            .filter(|(id, _)| id.starts_with(&filter.id))
+            .map(|(id, pod_info)| self.sandbox_status(id, pod_info))
+            .filter(|s| filter.state.as_ref().is_none_or(|f| f.state == s.state))
+            .filter(|s| matches_labels(&s.labels, &filter.label_selector))
+            .map(|s| PodSandbox {
+                id: s.id,
+                metadata: s.metadata,
+                state: s.state,
+                created_at: s.created_at,
+                labels: s.labels,
+                annotations: s.annotations,
+                runtime_handler: s.runtime_handler,
+            })
+            .collect();
+        Ok(Response::new(ListPodSandboxResponse { items }))
+    }
+
+    async fn create_container(
+        &self,
+        request: Request<CreateContainerRequest>,
+    ) -> Result<Response<CreateContainerResponse>, Status> {
+        let this = self.clone();
+        let container_id = blocking(move || this.create(request.into_inner())).await?;
+        Ok(Response::new(CreateContainerResponse { container_id }))
+    }
+
+    async fn start_container(
+        &self,
+        request: Request<StartContainerRequest>,
+    ) -> Result<Response<StartContainerResponse>, Status> {
+        let (_, _, container_id) = self.find_container(&request.into_inner().container_id)?;
+        let root_path = self.root_path.clone();
+        blocking(move || {
+            start(
+                Start {
+                    container_id: container_id.clone(),
+                },
+                root_path,
+            )
+            .map_err(internal)?;
+            info!("[cri] container {container_id} started");
+            Ok(())
+        })
+        .await?;
+        Ok(Response::new(StartContainerResponse {}))
+    }
+
+    async fn stop_container(
+        &self,
+        request: Request<StopContainerRequest>,
+    ) -> Result<Response<StopContainerResponse>, Status> {
+        let this = self.clone();
+        let request = request.into_inner();
+        blocking(move || this.stop(&request.container_id, request.timeout)).await?;
+        Ok(Response::new(StopContainerResponse {}))
+    }
+
+    async fn remove_container(
+        &self,
+        request: Request<RemoveContainerRequest>,
+    ) -> Result<Response<RemoveContainerResponse>, Status> {
+        let this = self.clone();
+        let id = request.into_inner().container_id;
+        blocking(move || this.remove(&id)).await?;
+        Ok(Response::new(RemoveContainerResponse {}))
+    }
+
+    async fn list_containers(
+        &self,
+        request: Request<ListContainersRequest>,
+    ) -> Result<Response<ListContainersResponse>, Status> {
+        let filter = request.into_inner().filter.unwrap_or_default();
+        let mut containers = Vec::new();
+        for (sandbox_id, pod_info) in self.pods() {
+            if !sandbox_id.starts_with(&filter.pod_sandbox_id) {
+                continue;
+            }
+            for container_id in &pod_info.container_names {
+                if !container_id.starts_with(&filter.id) {
+                    continue;
+                }
+                let Ok(s) = self.container_status(&sandbox_id, &pod_info, container_id) else {
+                    continue;
+                };
+                if filter.state.as_ref().is_some_and(|f| f.state != s.state)
+                    || !matches_labels(&s.labels, &filter.label_selector)
+                {
+                    continue;
+                }
+                containers.push(libruntime::cri::cri_api::Container {
+                    id: s.id,
+                    pod_sandbox_id: sandbox_id.clone(),
+                    metadata: s.metadata,
+                    image: s.image,
+                    image_ref: s.image_ref,
+                    state: s.state,
+                    created_at: s.created_at,
+                    labels: s.labels,
+                    annotations: s.annotations,
+                    image_id: s.image_id,
+                });
+            }
+        }
+        Ok(Response::new(ListContainersResponse { containers }))
+    }
+
+    async fn container_status(
+        &self,
+        request: Request<ContainerStatusRequest>,
+    ) -> Result<Response<ContainerStatusResponse>, Status> {
+        let (sandbox_id, pod_info, container_id) =
+            self.find_container(&request.into_inner().container_id)?;
+        Ok(Response::new(ContainerStatusResponse {
+            status: Some(self.container_status(&sandbox_id, &pod_info, &container_id)?),
+            info: Default::default(),
+        }))
+    }
+
+    async fn update_container_resources(
+        &self,
+        _request: Request<UpdateContainerResourcesRequest>,
+    ) -> Result<Response<UpdateContainerResourcesResponse>, Status> {
+        Err(unsupported("UpdateContainerResources"))
+    }
+
+    async fn reopen_container_log(
+        &self,
+        _request: Request<ReopenContainerLogRequest>,
+    ) -> Result<Response<ReopenContainerLogResponse>, Status> {
+        Err(Status::unimplemented(
+            "rkl rotates container logs itself, see RKL_CONTAINER_LOG_MAX_SIZE",
+        ))
+    }
+
+    async fn exec_sync(
+        &self,
+        request: Request<ExecSyncRequest>,
+    ) -> Result<Response<ExecSyncResponse>, Status> {
+        let request = request.into_inner();
+        if request.cmd.is_empty() {
+            return Err(Status::invalid_argument("cmd is required"));
+        }
+        let (_, _, container_id) = self.find_container(&request.container_id)?;
+        let container = load_container(&self.root_path, &container_id).map_err(internal)?;
+        if container.status() != OciStatus::Running {
+            return Err(Status::failed_precondition(format!(
+                "container {container_id} is not running"
+            )));
+        }
+
+        let root_path = self.root_path.clone();
+        let process = blocking(move || {
+            spawn_process(&root_path, &container_id, request.cmd, false, false).map_err(internal)
+        })
+        .await?;
+        let stdout = tokio::spawn(read_output(tokio::fs::File::from_std(process.stdout)));
+        let stderr = process
+            .stderr
+            .map(|stderr| tokio::spawn(read_output(tokio::fs::File::from_std(stderr))));
+
+        let pid = process.pid;
+        let wait = tokio::task::spawn_blocking(move || waitpid(pid, None));
+        let status = if request.timeout > 0 {
+            let timeout = Duration::from_secs(request.timeout as u64);
+            match tokio::time::timeout(timeout, wait).await {
+                Ok(status) => status,
+                Err(_) => {
+                    let _ = kill(pid, Signal::SIGKILL);
+                    return Err(Status::deadline_exceeded(format!(
+                        "command timed out after {}s",
+                        request.timeout
+                    )));
+                }
+            }
+        } else {
+            wait.await
+        };
+        let exit_code = match status
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(|e| Status::internal(e.to_string()))?
+        {
+            WaitStatus::Exited(_, code) => code,
+            WaitStatus::Signaled(_, signal, _) => 128 + signal as i32,
+            _ => 0,
+        };
+
+        let join = |output: Result<std::io::Result<Vec<u8>>, tokio::task::JoinError>| {
+            output
+                .map_err(|e| Status::internal(e.to_string()))?
+                .map_err(|e| Status::internal(e.to_string()))
+        };
+        let stdout = join(stdout.await)?;
+        let stderr = match stderr {
+            Some(stderr) => join(stderr.await)?,
+            None => vec![],
+        };
+        Ok(Response::new(ExecSyncResponse {
+            stdout,
+            stderr,
+            exit_code,
+        }))
+    }
+
+    async fn exec(&self, request: Request<ExecRequest>) -> Result<Response<ExecResponse>, Status> {
+        let mut request = request.into_inner();
+        if request.cmd.is_empty() {
+            return Err(Status::invalid_argument("cmd is required"));
+        }
+        if request.tty && request.stderr {
+            return Err(Status::invalid_argument(
+                "stderr can't be streamed separately from a tty",
+            ));
+        }
+        if !(request.stdin || request.stdout || request.stderr) {
+            return Err(Status::invalid_argument(
+                "one of stdin, stdout and stderr is required",
+            ));
+        }
+        let (_, _, container_id) = self.find_container(&request.container_id)?;
+        let container = load_container(&self.root_path, &container_id).map_err(internal)?;
+        if container.status() != OciStatus::Running {
+            return Err(Status::failed_precondition(format!(
+                "container {container_id} is not running"
+            )));
+        }
+        request.container_id = container_id;
+        Ok(Response::new(ExecResponse {
+            url: self.streaming.exec_url(request),
+        }))
+    }
+
+    async fn attach(
+        &self,
+        _request: Request<AttachRequest>,
+    ) -> Result<Response<AttachResponse>, Status> {
+        Err(unsupported("Attach"))
+    }
+
+    async fn port_forward(
+        &self,
+        _request: Request<PortForwardRequest>,
+    ) -> Result<Response<PortForwardResponse>, Status> {
+        Err(unsupported("PortForward, use rkl pod port-forward"))
+    }
+
+    async fn container_stats(
+        &self,
+        request: Request<ContainerStatsRequest>,
+    ) -> Result<Response<ContainerStatsResponse>, Status> {
+        let (sandbox_id, pod_info, container_id) =
+            self.find_container(&request.into_inner().container_id)?;
+        let stats = self.container_stats(
+            &sandbox_id,
+            &pod_info,
+            &container_id,
+            &collected_millicores(),
+        );
+        Ok(Response::new(ContainerStatsResponse { stats: Some(stats) }))
+    }
+
+    async fn list_container_stats(
+        &self,
+        request: Request<ListContainerStatsRequest>,
+    ) -> Result<Response<ListContainerStatsResponse>, Status> {
+        let filter = request.into_inner().filter.unwrap_or_default();
+        let millicores = collected_millicores();
+        let mut stats = Vec::new();
+        for (sandbox_id, pod_info) in self.pods() {
+            if !sandbox_id.starts_with(&filter.pod_sandbox_id) {
+                continue;
+            }
+            for container_id in &pod_info.container_names {
+                if !container_id.starts_with(&filter.id) {
+                    continue;
+                }
+                let container =
+                    self.container_stats(&sandbox_id, &pod_info, container_id, &millicores);
+                let labels = container.attributes.as_ref().map(|a| &a.labels);
+                if labels.is_some_and(|labels| matches_labels(labels, &filter.label_selector)) {
+                    stats.push(container);
+                }
+            }
+        }
+        Ok(Response::new(ListContainerStatsResponse { stats }))
+    }
+
+    async fn pod_sandbox_stats(
+        &self,
+        request: Request<PodSandboxStatsRequest>,
+    ) -> Result<Response<PodSandboxStatsResponse>, Status> {
+        let (sandbox_id, pod_info) = self.find_sandbox(&request.into_inner().pod_sandbox_id)?;
+        let stats = self.sandbox_stats(&sandbox_id, &pod_info, &collected_millicores());
+        Ok(Response::new(PodSandboxStatsResponse {
+            stats: Some(stats),
+        }))
+    }
+
+    async fn list_pod_sandbox_stats(
+        &self,
+        request: Request<ListPodSandboxStatsRequest>,
+    ) -> Result<Response<ListPodSandboxStatsResponse>, Status> {
+        let filter = request.into_inner().filter.unwrap_or_default();
+        let millicores = collected_millicores();
+        let stats = self
+            .pods()
+            .iter()
+            .filter(|(id, _)| id.starts_with(&filter.id))
+            .filter(|(id, pod_info)| {
+                let labels = self.sandbox_config(id, pod_info).labels;
+                matches_labels(&labels, &filter.label_selector)
+            })
+            .map(|(id, pod_info)| self.sandbox_stats(id, pod_info, &millicores))
+            .collect();
+        Ok(Response::new(ListPodSandboxStatsResponse { stats }))
+    }
+
+    async fn update_runtime_config(
+        &self,
+        _request: Request<UpdateRuntimeConfigRequest>,
+    ) -> Result<Response<UpdateRuntimeConfigResponse>, Status> {
+        // The pod CIDR comes from rks through the subnet config, not from here
+        Ok(Response::new(UpdateRuntimeConfigResponse {}))
+    }
+
+    async fn status(
+        &self,
+        _request: Request<StatusRequest>,
+    ) -> Result<Response<StatusResponse>, Status> {
+        let network_ready = network_ready();
+        let conditions = vec![
+            RuntimeCondition {
+                r#type: "RuntimeReady".to_string(),
+                status: true,
+                reason: String::new(),
+                message: String::new(),
+            },
+            RuntimeCondition {
+                r#type: "NetworkReady".to_string(),
+                status: network_ready,
+                reason: if network_ready {
+                    String::new()
+                } else {
+                    "NetworkPluginNotReady".to_string()
+                },
+                message: if network_ready {
+                    String::new()
+                } else {
+                    format!("no CNI configuration in {CNI_CONF_DIR}")
+                },
+            },
+        ];
+        Ok(Response::new(StatusResponse {
+            status: Some(RuntimeStatus { conditions }),
+            ..Default::default()
+        }))
+    }
+
+    async fn checkpoint_container(
+        &self,
+        request: Request<CheckpointContainerRequest>,
+    ) -> Result<Response<CheckpointContainerResponse>, Status> {
+        let request = request.into_inner();
+        if request.location.is_empty() {
+            return Err(Status::invalid_argument("location is required"));
+        }
+        let (_, _, container_id) = self.find_container(&request.container_id)?;
+        blocking(move || {
+            let options = CheckpointArgs {
+                leave_running: true,
+                ..Default::default()
+            };
+            checkpoint_container(&container_id, options.into(), Path::new(&request.location))
+                .map_err(internal)
+        })
+        .await?;
+        Ok(Response::new(CheckpointContainerResponse {}))
+    }
+
+    type GetContainerEventsStream =
+        Pin<Box<dyn Stream<Item = Result<ContainerEventResponse, Status>> + Send>>;
+
+    async fn get_container_events(
+        &self,
+        _request: Request<GetEventsRequest>,
+    ) -> Result<Response<Self::GetContainerEventsStream>, Status> {
+        Err(unsupported("GetContainerEvents"))
+    }
+
+    async fn list_metric_descriptors(
+        &self,
+        _request: Request<ListMetricDescriptorsRequest>,
+    ) -> Result<Response<ListMetricDescriptorsResponse>, Status> {
+        Err(unsupported("ListMetricDescriptors"))
+    }
+
+    async fn list_pod_sandbox_metrics(
+        &self,
+        _request: Request<ListPodSandboxMetricsRequest>,
+    ) -> Result<Response<ListPodSandboxMetricsResponse>, Status> {
+        Err(unsupported("ListPodSandboxMetrics"))
+    }
+
+    async fn runtime_config(
+        &self,
+        _request: Request<RuntimeConfigRequest>,
+    ) -> Result<Response<RuntimeConfigResponse>, Status> {
+        Ok(Response::new(RuntimeConfigResponse {
+            linux: Some(LinuxRuntimeConfiguration {
+                cgroup_driver: CgroupDriver::Cgroupfs as i32,
+            }),
+        }))
+    }
+
+    async fn update_pod_sandbox_resources(
+        &self,
+        _request: Request<UpdatePodSandboxResourcesRequest>,
+    ) -> Result<Response<UpdatePodSandboxResourcesResponse>, Status> {
+        Err(unsupported("UpdatePodSandboxResources"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_id() {
+        let ids = [
+            "default_nginx_0".to_string(),
+            "default_nginx_1".to_string(),
+            "kube-system_dns_0".to_string(),
+        ];
+        assert_eq!(
+            resolve_id("default_nginx_0", ids.iter()).map(String::as_str),
+            Some("default_nginx_0")
+        );
+        assert_eq!(
+            resolve_id("kube", ids.iter()).map(String::as_str),
+            Some("kube-system_dns_0")
+        );
+        assert_eq!(resolve_id("default_nginx", ids.iter()), None);
+        assert_eq!(resolve_id("web", ids.iter()), None);
+    }
+}
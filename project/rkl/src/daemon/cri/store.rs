@@ -0,0 +1,98 @@
+//! The configs CRI clients created sandboxes and containers with.
+//!
+//! They are kept under `<root>/cri/`, one protobuf encoded file per sandbox
+//! or container, as the CRI requires labels, annotations and metadata to be
+//! returned as they were passed.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use libruntime::cri::cri_api::{ContainerConfig, PodSandboxConfig};
+use prost::Message;
+
+#[derive(Clone)]
+pub struct CriStore {
+    dir: PathBuf,
+}
+
+impl CriStore {
+    pub fn new(root_path: &Path) -> Self {
+        CriStore {
+            dir: root_path.join("cri"),
+        }
+    }
+
+    pub fn save_sandbox(&self, sandbox_id: &str, config: &PodSandboxConfig) -> Result<()> {
+        self.save("sandboxes", sandbox_id, config)
+    }
+
+    pub fn sandbox(&self, sandbox_id: &str) -> Option<PodSandboxConfig> {
+        self.load("sandboxes", sandbox_id)
+    }
+
+    pub fn remove_sandbox(&self, sandbox_id: &str) -> Result<()> {
+        self.remove("sandboxes", sandbox_id)
+    }
+
+    pub fn save_container(&self, container_id: &str, config: &ContainerConfig) -> Result<()> {
+        self.save("containers", container_id, config)
+    }
+
+    pub fn container(&self, container_id: &str) -> Option<ContainerConfig> {
+        self.load("containers", container_id)
+    }
+
+    pub fn remove_container(&self, container_id: &str) -> Result<()> {
+        self.remove("containers", container_id)
+    }
+
+    fn save(&self, kind: &str, id: &str, message: &impl Message) -> Result<()> {
+        let dir = self.dir.join(kind);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(id), message.encode_to_vec())?;
+        Ok(())
+    }
+
+    fn load<M: Message + Default>(&self, kind: &str, id: &str) -> Option<M> {
+        let data = fs::read(self.dir.join(kind).join(id)).ok()?;
+        M::decode(data.as_slice()).ok()
+    }
+
+    fn remove(&self, kind: &str, id: &str) -> Result<()> {
+        match fs::remove_file(self.dir.join(kind).join(id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libruntime::cri::cri_api::PodSandboxMetadata;
+
+    #[test]
+    fn test_store_roundtrip() {
+        let root = tempfile::tempdir().unwrap();
+        let store = CriStore::new(root.path());
+        let config = PodSandboxConfig {
+            metadata: Some(PodSandboxMetadata {
+                name: "nginx".to_string(),
+                uid: "1234".to_string(),
+                namespace: "default".to_string(),
+                attempt: 0,
+            }),
+            annotations: [("a".to_string(), "b".to_string())].into(),
+            ..Default::default()
+        };
+        store.save_sandbox("default_nginx_0", &config).unwrap();
+        assert_eq!(store.sandbox("default_nginx_0"), Some(config));
+        assert_eq!(store.container("default_nginx_0-web_0"), None);
+
+        store.remove_sandbox("default_nginx_0").unwrap();
+        store.remove_sandbox("default_nginx_0").unwrap();
+        assert_eq!(store.sandbox("default_nginx_0"), None);
+    }
+}
@@ -0,0 +1,315 @@
+//! Streaming server for CRI `Exec`.
+//!
+//! `Exec` hands out a single-use URL on this server, which the client
+//! upgrades to a WebSocket speaking the Kubernetes channel protocol
+//! (`v4.channel.k8s.io` or `v5.channel.k8s.io`). Every binary message starts
+//! with the stream it belongs to: 0 stdin, 1 stdout, 2 stderr, 3 the final
+//! status and 4 terminal resizes. v5 adds 255 to close a stream. SPDY isn't
+//! served.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::net::SocketAddr;
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use futures::{SinkExt, StreamExt};
+use libruntime::cri::cri_api::ExecRequest;
+use nix::sys::wait::{WaitStatus, waitpid};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::daemon::session::{resize, spawn_process};
+
+const V4_PROTOCOL: &str = "v4.channel.k8s.io";
+const V5_PROTOCOL: &str = "v5.channel.k8s.io";
+
+const STDIN: u8 = 0;
+const STDOUT: u8 = 1;
+const STDERR: u8 = 2;
+const STATUS: u8 = 3;
+const RESIZE: u8 = 4;
+const CLOSE: u8 = 255;
+
+/// Time a client has to connect to the URL `Exec` returned.
+const SESSION_TTL: Duration = Duration::from_secs(60);
+const READ_BUFFER_SIZE: usize = 32 * 1024;
+
+/// Exec sessions handed out by `Exec`, waiting for their client to connect.
+pub struct StreamingServer {
+    address: SocketAddr,
+    root_path: PathBuf,
+    sessions: Mutex<HashMap<String, (ExecRequest, Instant)>>,
+}
+
+/// A terminal size sent on the resize stream.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TerminalSize {
+    width: u16,
+    height: u16,
+}
+
+impl StreamingServer {
+    pub fn new(address: SocketAddr, root_path: PathBuf) -> Self {
+        StreamingServer {
+            address,
+            root_path,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Register `request` and return the URL its streams are served on.
+    /// `request.container_id` must be the full id of a running container.
+    pub fn exec_url(&self, request: ExecRequest) -> String {
+        let token = Uuid::new_v4().simple().to_string();
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, (_, created)| created.elapsed() < SESSION_TTL);
+        sessions.insert(token.clone(), (request, Instant::now()));
+        format!("http://{}/exec/{token}", self.address)
+    }
+
+    /// The session of `token`, which can only be taken once.
+    fn take(&self, token: &str) -> Option<ExecRequest> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .remove(token)
+            .filter(|(_, created)| created.elapsed() < SESSION_TTL)
+            .map(|(request, _)| request)
+    }
+
+    /// Serve the sessions on `listener` until the server fails.
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        info!("[cri] serving streams on {}", self.address);
+        let app = Router::new()
+            .route("/exec/{token}", get(exec))
+            .with_state(self);
+        axum::serve(listener, app).await?;
+        Ok(())
+    }
+}
+
+async fn exec(
+    State(server): State<Arc<StreamingServer>>,
+    Path(token): Path<String>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let Some(request) = server.take(&token) else {
+        return (StatusCode::NOT_FOUND, "unknown or expired exec session").into_response();
+    };
+    let root_path = server.root_path.clone();
+    ws.protocols([V5_PROTOCOL, V4_PROTOCOL])
+        .on_upgrade(move |socket| async move {
+            let container_id = request.container_id.clone();
+            if let Err(e) = run_exec(&root_path, request, socket).await {
+                warn!("[cri] exec in {container_id} failed: {e:#}");
+            }
+        })
+}
+
+async fn run_exec(root_path: &FsPath, request: ExecRequest, socket: WebSocket) -> Result<()> {
+    let v5 = socket.protocol().is_some_and(|p| p == V5_PROTOCOL);
+    let (mut sink, mut stream) = socket.split();
+    let (tx, mut rx) = mpsc::channel::<Message>(16);
+    let writer = tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            if sink.send(message).await.is_err() {
+                return;
+            }
+        }
+        let _ = sink.close().await;
+    });
+
+    let root = root_path.to_path_buf();
+    let ExecRequest {
+        container_id,
+        cmd,
+        tty,
+        stdin,
+        stdout,
+        stderr,
+    } = request;
+    let process = match tokio::task::spawn_blocking(move || {
+        spawn_process(&root, &container_id, cmd, tty, stdin)
+    })
+    .await?
+    {
+        Ok(process) => process,
+        Err(e) => {
+            let status = json!({
+                "metadata": {},
+                "status": "Failure",
+                "message": format!("{e:#}"),
+                "reason": "InternalError",
+            });
+            let _ = tx.send(frame(STATUS, status.to_string().as_bytes())).await;
+            drop(tx);
+            let _ = writer.await;
+            return Err(e);
+        }
+    };
+
+    let mut outputs = vec![tokio::spawn(forward_output(
+        tokio::fs::File::from_std(process.stdout),
+        Some(STDOUT).filter(|_| stdout),
+        tx.clone(),
+    ))];
+    if let Some(stderr_pipe) = process.stderr {
+        outputs.push(tokio::spawn(forward_output(
+            tokio::fs::File::from_std(stderr_pipe),
+            Some(STDERR).filter(|_| stderr),
+            tx.clone(),
+        )));
+    }
+
+    let mut process_stdin = process.stdin.map(tokio::fs::File::from_std);
+    let pty_master = process.pty_master;
+    let input = tokio::spawn(async move {
+        while let Some(Ok(message)) = stream.next().await {
+            let data = match message {
+                Message::Binary(data) => data,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            let Some((&channel, payload)) = data.split_first() else {
+                continue;
+            };
+            match channel {
+                STDIN => {
+                    if let Some(input) = process_stdin.as_mut() {
+                        input.write_all(payload).await?;
+                        input.flush().await?;
+                    }
+                }
+                RESIZE => {
+                    if let Some(master) = &pty_master {
+                        resize_terminal(master, payload);
+                    }
+                }
+                CLOSE if v5 && payload.first() == Some(&STDIN) => process_stdin = None,
+                _ => {}
+            }
+        }
+        anyhow::Ok(())
+    });
+
+    let pid = process.pid;
+    let status = tokio::task::spawn_blocking(move || waitpid(pid, None)).await?;
+    input.abort();
+    for output in outputs {
+        output.await?;
+    }
+    let exit_code = match status? {
+        WaitStatus::Exited(_, code) => code,
+        WaitStatus::Signaled(_, signal, _) => 128 + signal as i32,
+        _ => 0,
+    };
+    let _ = tx
+        .send(frame(STATUS, exit_status(exit_code).to_string().as_bytes()))
+        .await;
+    drop(tx);
+    let _ = writer.await;
+    Ok(())
+}
+
+fn resize_terminal(master: &File, payload: &[u8]) {
+    match serde_json::from_slice::<TerminalSize>(payload) {
+        Ok(size) => {
+            if let Err(e) = resize(master, size.width, size.height) {
+                warn!("[cri] failed to resize the exec terminal: {e}");
+            }
+        }
+        Err(e) => warn!("[cri] invalid terminal size: {e}"),
+    }
+}
+
+/// Send everything read from `reader` on `channel`, or discard it when the
+/// client didn't ask for that stream.
+async fn forward_output(
+    mut reader: impl AsyncRead + Unpin,
+    channel: Option<u8>,
+    tx: mpsc::Sender<Message>,
+) {
+    let mut buf = vec![0u8; READ_BUFFER_SIZE];
+    loop {
+        // A terminal reports EIO instead of EOF once the process is gone.
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        if let Some(channel) = channel
+            && tx.send(frame(channel, &buf[..n])).await.is_err()
+        {
+            return;
+        }
+    }
+}
+
+fn frame(channel: u8, payload: &[u8]) -> Message {
+    let mut data = Vec::with_capacity(payload.len() + 1);
+    data.push(channel);
+    data.extend_from_slice(payload);
+    Message::Binary(Bytes::from(data))
+}
+
+/// The `metav1.Status` clients read the exit code of the command from.
+fn exit_status(exit_code: i32) -> Value {
+    if exit_code == 0 {
+        return json!({ "metadata": {}, "status": "Success" });
+    }
+    json!({
+        "metadata": {},
+        "status": "Failure",
+        "message": format!("command terminated with non-zero exit code: {exit_code}"),
+        "reason": "NonZeroExitCode",
+        "details": {
+            "causes": [{ "reason": "ExitCode", "message": exit_code.to_string() }],
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exec_url_is_single_use() {
+        let server = StreamingServer::new(
+            SocketAddr::from(([127, 0, 0, 1], 10010)),
+            PathBuf::from("/run/youki"),
+        );
+        let url = server.exec_url(ExecRequest {
+            container_id: "default_nginx_0-web_0".to_string(),
+            cmd: vec!["sh".to_string()],
+            ..Default::default()
+        });
+        let token = url.strip_prefix("http://127.0.0.1:10010/exec/").unwrap();
+        let request = server.take(token).unwrap();
+        assert_eq!(request.container_id, "default_nginx_0-web_0");
+        assert!(server.take(token).is_none());
+        assert!(server.take("unknown").is_none());
+    }
+
+    #[test]
+    fn test_exit_status() {
+        assert_eq!(exit_status(0)["status"], "Success");
+        let status = exit_status(137);
+        assert_eq!(status["reason"], "NonZeroExitCode");
+        assert_eq!(status["details"]["causes"][0]["message"], "137");
+    }
+}
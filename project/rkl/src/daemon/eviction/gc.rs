@@ -0,0 +1,172 @@
+//! Garbage collection of exited containers and unused images.
+//!
+//! Once the image filesystem is fuller than the high watermark, exited
+//! containers which don't belong to a pod are removed, and then images no
+//! container uses, least recently used first, until the usage drops below
+//! the low watermark.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use chrono::Utc;
+use libcontainer::container::{Container, ContainerStatus, state};
+use liboci_cli::Delete;
+use libruntime::oci::IMAGE_ANNOTATION;
+use oci_spec::runtime::Spec;
+use rkforge::commands::container::rootfs_mount::RootfsMount;
+use tracing::{info, warn};
+
+use crate::commands::delete;
+use crate::commands::pod::PodInfo;
+use crate::daemon::eviction::FsStats;
+
+/// Tracks when images were last used by a container, to remove the least
+/// recently used ones first.
+#[derive(Default)]
+pub struct ImageGc {
+    last_used: HashMap<String, SystemTime>,
+}
+
+impl ImageGc {
+    /// Record the images used by the containers on the node right now.
+    pub fn observe(&mut self, root_path: &Path) -> HashSet<String> {
+        let in_use = images_in_use(root_path);
+        let now = SystemTime::now();
+        for digest in &in_use {
+            self.last_used.insert(digest.clone(), now);
+        }
+        in_use
+    }
+
+    /// Remove exited containers and unused images until the image
+    /// filesystem usage is at most `low_percent`, or, with `low_percent` of
+    /// 0, until there is nothing left to remove. Returns the number of
+    /// containers and images removed.
+    pub fn collect(
+        &mut self,
+        root_path: &Path,
+        imagefs: &Path,
+        low_percent: u8,
+        min_container_age: Duration,
+    ) -> Result<(usize, usize)> {
+        let below_low = || FsStats::observe(imagefs).is_ok_and(|s| s.usage_percent() < low_percent);
+
+        let mut containers = 0;
+        for container in exited_containers(root_path, min_container_age)? {
+            if below_low() {
+                return Ok((containers, 0));
+            }
+            match remove_exited_container(root_path, &container) {
+                Ok(()) => {
+                    info!("[gc] removed exited container {}", container.id());
+                    containers += 1;
+                }
+                Err(e) => warn!("[gc] failed to remove container {}: {e:#}", container.id()),
+            }
+        }
+
+        let in_use = self.observe(root_path);
+        let mut unused = rkforge::images::cached_images()?
+            .into_iter()
+            .filter(|image| !in_use.contains(&image.digest))
+            .collect::<Vec<_>>();
+        let last_used = |digest: &str, stored_at: SystemTime| {
+            self.last_used
+                .get(digest)
+                .copied()
+                .map_or(stored_at, |used| used.max(stored_at))
+        };
+        unused.sort_by_key(|image| last_used(&image.digest, image.stored_at));
+
+        let mut images = 0;
+        for image in unused {
+            if below_low() {
+                break;
+            }
+            match rkforge::images::remove_cached_image(&image.digest) {
+                Ok(()) => {
+                    info!(
+                        "[gc] removed unused image {} ({} bytes)",
+                        image.image_refs.join(", "),
+                        image.size_bytes
+                    );
+                    self.last_used.remove(&image.digest);
+                    images += 1;
+                }
+                Err(e) => warn!(
+                    "[gc] failed to remove image {}: {e:#}",
+                    image.image_refs.join(", ")
+                ),
+            }
+        }
+        Ok((containers, images))
+    }
+}
+
+/// Every container directory under the runtime root.
+fn containers(root_path: &Path) -> Vec<Container> {
+    let Ok(entries) = fs::read_dir(root_path) else {
+        return vec![];
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|dir| state::State::file_path(dir).exists())
+        .filter_map(|dir| Container::load(dir).ok())
+        .collect()
+}
+
+/// Manifest digests of the images the containers on the node were created from.
+fn images_in_use(root_path: &Path) -> HashSet<String> {
+    containers(root_path)
+        .iter()
+        .filter_map(|container| {
+            let spec = Spec::load(container.bundle().join("config.json")).ok()?;
+            let image = spec.annotations().as_ref()?.get(IMAGE_ANNOTATION)?.clone();
+            rkforge::images::cached_image_digest(&image)
+        })
+        .collect()
+}
+
+/// Stopped containers older than `min_age` which no pod owns, oldest first.
+fn exited_containers(root_path: &Path, min_age: Duration) -> Result<Vec<Container>> {
+    let mut owned = HashSet::new();
+    if let Ok(entries) = fs::read_dir(root_path.join("pods")) {
+        for entry in entries.flatten() {
+            let pod_name = entry.file_name().to_string_lossy().into_owned();
+            if let Ok(pod_info) = PodInfo::load(root_path, &pod_name) {
+                owned.insert(pod_info.pod_sandbox_id);
+                owned.extend(pod_info.container_names);
+                owned.extend(pod_info.ephemeral_containers);
+            }
+        }
+    }
+
+    let cutoff = Utc::now() - chrono::Duration::from_std(min_age)?;
+    let mut exited = containers(root_path)
+        .into_iter()
+        .filter(|c| matches!(c.status(), ContainerStatus::Stopped))
+        .filter(|c| !owned.contains(c.id()))
+        .filter(|c| c.created().is_some_and(|created| created < cutoff))
+        .collect::<Vec<_>>();
+    exited.sort_by_key(|c| c.created());
+    Ok(exited)
+}
+
+fn remove_exited_container(root_path: &Path, container: &Container) -> Result<()> {
+    let bundle_path = container.bundle().to_path_buf();
+    delete(
+        Delete {
+            container_id: container.id().to_string(),
+            force: true,
+        },
+        root_path.to_path_buf(),
+    )?;
+    if let Some(rootfs_mount) = RootfsMount::load(&bundle_path)? {
+        rootfs_mount.stop()?;
+    }
+    Ok(())
+}
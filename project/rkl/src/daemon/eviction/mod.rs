@@ -0,0 +1,545 @@
+//! Node-pressure eviction.
+//!
+//! [`EvictionManager`] checks the available memory and the free space and
+//! inodes of the node and image filesystems against the thresholds of
+//! [`EVICTION_CONFIG`]. While a threshold is met the matching
+//! `MemoryPressure` or `DiskPressure` node condition is reported with the
+//! heartbeats, and rks taints the node. Disk is first reclaimed by garbage
+//! collection (see [`gc`]); if that is not enough, or memory is short, one pod
+//! is evicted per check: BestEffort before Burstable before Guaranteed pods,
+//! lower priority first, and the largest user of the starved resource first
+//! among equals.
+
+pub mod gc;
+pub mod threshold;
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use common::{ConditionStatus, NodeCondition, NodeConditionType, PodPhase, PodSpec, PodTask};
+use libcontainer::syscall::syscall::create_syscall;
+use libruntime::rootpath;
+use nix::sys::statvfs::statvfs;
+use sysinfo::System;
+use tokio::sync::OnceCell;
+use tracing::{error, info, warn};
+
+use crate::commands::pod::{PodInfo, standalone};
+use crate::config::{EVICTION_CONFIG, EvictionConfig};
+use crate::daemon::metrics;
+use crate::daemon::pod_worker;
+use crate::daemon::status::probe::probe_manager::PROBE_MANAGER;
+use crate::daemon::status::status_manager::STATUS_MANAGER;
+use gc::ImageGc;
+use threshold::{Resource, Signal, Threshold};
+
+/// Global singleton [`EvictionManager`], initialized once by the daemon.
+pub static EVICTION_MANAGER: OnceCell<Arc<EvictionManager>> = OnceCell::const_new();
+
+/// Quality of service class of a pod, derived from its resource limits.
+/// Ordered by eviction preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QosClass {
+    /// No container has limits.
+    BestEffort,
+    Burstable,
+    /// Every container has cpu and memory limits.
+    Guaranteed,
+}
+
+pub fn qos_class(spec: &PodSpec) -> QosClass {
+    let limits = spec
+        .init_containers
+        .iter()
+        .chain(&spec.containers)
+        .map(|c| {
+            let limits = c.resources.as_ref().and_then(|r| r.limits.as_ref());
+            (
+                limits.is_some_and(|l| l.cpu.is_some()),
+                limits.is_some_and(|l| l.memory.is_some()),
+            )
+        })
+        .collect::<Vec<_>>();
+    if !limits.is_empty() && limits.iter().all(|(cpu, memory)| *cpu && *memory) {
+        QosClass::Guaranteed
+    } else if limits.iter().all(|(cpu, memory)| !cpu && !memory) {
+        QosClass::BestEffort
+    } else {
+        QosClass::Burstable
+    }
+}
+
+/// Space and inodes of a filesystem.
+#[derive(Debug, Clone, Copy)]
+pub struct FsStats {
+    pub available: u64,
+    pub capacity: u64,
+    pub inodes_free: u64,
+    pub inodes: u64,
+}
+
+impl FsStats {
+    /// Stats of the filesystem `path` is on.
+    pub fn observe(path: &Path) -> Result<Self> {
+        let stat = statvfs(path).with_context(|| format!("statvfs {}", path.display()))?;
+        let fragment_size = stat.fragment_size() as u64;
+        Ok(FsStats {
+            available: stat.blocks_available() as u64 * fragment_size,
+            capacity: stat.blocks() as u64 * fragment_size,
+            inodes_free: stat.files_available() as u64,
+            inodes: stat.files() as u64,
+        })
+    }
+
+    pub fn usage_percent(&self) -> u8 {
+        if self.capacity == 0 {
+            return 0;
+        }
+        (100 - self.available.min(self.capacity) * 100 / self.capacity) as u8
+    }
+}
+
+struct Observations {
+    memory_available: u64,
+    memory_capacity: u64,
+    nodefs: FsStats,
+    imagefs: FsStats,
+}
+
+impl Observations {
+    fn observe(nodefs: &Path, imagefs: &Path) -> Result<Self> {
+        let mut sys = System::new();
+        sys.refresh_memory();
+        Ok(Observations {
+            memory_available: sys.available_memory(),
+            memory_capacity: sys.total_memory(),
+            nodefs: FsStats::observe(nodefs)?,
+            imagefs: FsStats::observe(imagefs)?,
+        })
+    }
+
+    fn is_met(&self, threshold: &Threshold) -> bool {
+        let (observed, capacity) = match threshold.signal {
+            Signal::MemoryAvailable => (self.memory_available, self.memory_capacity),
+            Signal::NodeFsAvailable => (self.nodefs.available, self.nodefs.capacity),
+            Signal::NodeFsInodesFree => (self.nodefs.inodes_free, self.nodefs.inodes),
+            Signal::ImageFsAvailable => (self.imagefs.available, self.imagefs.capacity),
+            Signal::ImageFsInodesFree => (self.imagefs.inodes_free, self.imagefs.inodes),
+        };
+        threshold.is_met(observed, capacity)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// Since when each soft threshold, by index, has been met.
+    soft_met_since: HashMap<usize, Instant>,
+    /// When a threshold for the resource was last met.
+    last_pressure: HashMap<Resource, Instant>,
+}
+
+pub struct EvictionManager {
+    state: Mutex<State>,
+    image_gc: Mutex<ImageGc>,
+    imagefs: PathBuf,
+}
+
+impl EvictionManager {
+    pub fn new() -> Self {
+        EvictionManager {
+            state: Mutex::new(State::default()),
+            image_gc: Mutex::new(ImageGc::default()),
+            imagefs: rkforge::config::image::CONFIG.layers_store_root.clone(),
+        }
+    }
+
+    /// Start checking the node every monitoring interval.
+    pub fn run(self: &Arc<Self>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(EVICTION_CONFIG.monitoring_interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = manager.synchronize(&EVICTION_CONFIG).await {
+                    error!("[eviction] check failed: {e:#}");
+                }
+            }
+        });
+    }
+
+    /// The pressure conditions of the node.
+    pub fn conditions(&self) -> Vec<NodeCondition> {
+        let state = self.state.lock().unwrap();
+        let transition = EVICTION_CONFIG.pressure_transition_period;
+        [
+            (Resource::Memory, NodeConditionType::MemoryPressure),
+            (Resource::Disk, NodeConditionType::DiskPressure),
+        ]
+        .into_iter()
+        .map(|(resource, condition_type)| {
+            let under_pressure = state
+                .last_pressure
+                .get(&resource)
+                .is_some_and(|at| at.elapsed() < transition);
+            pressure_condition(condition_type, under_pressure)
+        })
+        .collect()
+    }
+
+    async fn synchronize(self: &Arc<Self>, config: &'static EvictionConfig) -> Result<()> {
+        let root_path = rootpath::determine(None, &*create_syscall())?;
+        let mut observations = self.observe(config).await?;
+
+        let usage = observations.imagefs.usage_percent();
+        if usage > config.image_gc_high_threshold {
+            info!(
+                "[eviction] image filesystem {usage}% full, collecting garbage down to {}%",
+                config.image_gc_low_threshold
+            );
+            self.collect_garbage(&root_path, config.image_gc_low_threshold, config)
+                .await?;
+            observations = self.observe(config).await?;
+        } else {
+            let manager = self.clone();
+            let root = root_path.clone();
+            tokio::task::spawn_blocking(move || manager.image_gc.lock().unwrap().observe(&root))
+                .await?;
+        }
+
+        let mut starved = self.starved(&observations, config);
+        if starved
+            .iter()
+            .any(|t| t.signal.resource() == Resource::Disk)
+        {
+            info!("[eviction] disk pressure, removing all unused images and exited containers");
+            self.collect_garbage(&root_path, 0, config).await?;
+            observations = self.observe(config).await?;
+            starved = self.starved(&observations, config);
+        }
+        if starved.is_empty() {
+            return Ok(());
+        }
+
+        let resource = if starved
+            .iter()
+            .any(|t| t.signal.resource() == Resource::Memory)
+        {
+            Resource::Memory
+        } else {
+            Resource::Disk
+        };
+        let thresholds = starved
+            .iter()
+            .filter(|t| t.signal.resource() == resource)
+            .map(Threshold::to_string)
+            .collect::<Vec<_>>();
+        self.evict_pod(root_path, resource, thresholds.join(", "))
+            .await
+    }
+
+    async fn observe(&self, config: &'static EvictionConfig) -> Result<Observations> {
+        let imagefs = self.imagefs.clone();
+        tokio::task::spawn_blocking(move || Observations::observe(&config.nodefs_path, &imagefs))
+            .await?
+    }
+
+    /// Record which thresholds are met and return those to act on: the hard
+    /// ones, and the soft ones met for longer than their grace period.
+    fn starved(&self, observations: &Observations, config: &EvictionConfig) -> Vec<Threshold> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let mut starved = Vec::new();
+        for threshold in &config.hard {
+            if observations.is_met(threshold) {
+                state.last_pressure.insert(threshold.signal.resource(), now);
+                starved.push(*threshold);
+            }
+        }
+        for (i, threshold) in config.soft.iter().enumerate() {
+            if !observations.is_met(threshold) {
+                state.soft_met_since.remove(&i);
+                continue;
+            }
+            state.last_pressure.insert(threshold.signal.resource(), now);
+            let since = *state.soft_met_since.entry(i).or_insert(now);
+            if now.duration_since(since) >= config.soft_grace_period {
+                starved.push(*threshold);
+            }
+        }
+        starved
+    }
+
+    async fn collect_garbage(
+        self: &Arc<Self>,
+        root_path: &Path,
+        low_percent: u8,
+        config: &'static EvictionConfig,
+    ) -> Result<()> {
+        let manager = self.clone();
+        let root_path = root_path.to_path_buf();
+        let (containers, images) = tokio::task::spawn_blocking(move || {
+            manager.image_gc.lock().unwrap().collect(
+                &root_path,
+                &manager.imagefs,
+                low_percent,
+                config.container_gc_min_age,
+            )
+        })
+        .await??;
+        info!("[eviction] removed {containers} exited containers and {images} unused images");
+        Ok(())
+    }
+
+    async fn evict_pod(
+        &self,
+        root_path: PathBuf,
+        resource: Resource,
+        thresholds: String,
+    ) -> Result<()> {
+        let candidates =
+            tokio::task::spawn_blocking(move || rank_pods(&root_path, resource)).await??;
+        let Some(candidate) = candidates.into_iter().next() else {
+            warn!("[eviction] node is under {resource:?} pressure but has no pod to evict");
+            return Ok(());
+        };
+        let pod_name = candidate.pod_name.clone();
+        info!(
+            "[eviction] evicting pod {pod_name} ({:?}, priority {}) for {resource:?} pressure: {thresholds}",
+            candidate.qos, candidate.priority
+        );
+
+        if let Some(pm) = PROBE_MANAGER.get() {
+            pm.remove_pod(&pod_name).await;
+        }
+        pod_worker::forget_pod_restarts(&pod_name);
+        metrics::forget_pod(&pod_name);
+        let name = pod_name.clone();
+        tokio::task::spawn_blocking(move || standalone::delete_pod(&name)).await??;
+
+        let Some(pod) = candidate.pod else {
+            warn!("[eviction] pod {pod_name} doesn't record its spec, its status is not updated");
+            return Ok(());
+        };
+        if let Some(sm) = STATUS_MANAGER.get() {
+            let mut status = sm
+                .get_pod_status(pod.metadata.uid)
+                .await
+                .unwrap_or_else(|| pod.status.clone());
+            status.phase = PodPhase::Failed;
+            status.reason = Some("Evicted".to_string());
+            status.message = Some(format!(
+                "The node was low on resource: {}. Threshold quantity: {thresholds}",
+                match resource {
+                    Resource::Memory => "memory",
+                    Resource::Disk => "ephemeral-storage",
+                }
+            ));
+            sm.set_pod_status(&pod, &status).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for EvictionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The pressure conditions to report, all false if the manager doesn't run.
+pub fn node_conditions() -> Vec<NodeCondition> {
+    match EVICTION_MANAGER.get() {
+        Some(manager) => manager.conditions(),
+        None => vec![
+            pressure_condition(NodeConditionType::MemoryPressure, false),
+            pressure_condition(NodeConditionType::DiskPressure, false),
+        ],
+    }
+}
+
+fn pressure_condition(condition_type: NodeConditionType, under_pressure: bool) -> NodeCondition {
+    NodeCondition {
+        condition_type,
+        status: if under_pressure {
+            ConditionStatus::True
+        } else {
+            ConditionStatus::False
+        },
+        last_heartbeat_time: Some(Utc::now().to_rfc3339()),
+    }
+}
+
+struct Candidate {
+    pod_name: String,
+    pod: Option<PodTask>,
+    qos: QosClass,
+    priority: i32,
+    /// Bytes of the starved resource the pod uses.
+    usage: u64,
+}
+
+/// The pods on the node, in the order they are to be evicted in.
+fn rank_pods(root_path: &Path, resource: Resource) -> Result<Vec<Candidate>> {
+    let mut sys = System::new();
+    if resource == Resource::Memory {
+        sys.refresh_processes();
+    }
+    let mut candidates = Vec::new();
+    let Ok(entries) = fs::read_dir(root_path.join("pods")) else {
+        return Ok(candidates);
+    };
+    for entry in entries.flatten() {
+        let pod_name = entry.file_name().to_string_lossy().into_owned();
+        let pod_info = match PodInfo::load(root_path, &pod_name) {
+            Ok(pod_info) => pod_info,
+            Err(e) => {
+                warn!("[eviction] skipping pod {pod_name}: {e}");
+                continue;
+            }
+        };
+        let containers = pod_info.get_pod_containers(root_path).unwrap_or_default();
+        let usage = match resource {
+            Resource::Memory => containers
+                .iter()
+                .filter_map(|c| c.pid())
+                .map(|pid| process_tree_memory(&sys, pid.as_raw() as u32))
+                .sum(),
+            // Logs and what the containers wrote to their root filesystem,
+            // the upper dir of its overlay.
+            Resource::Disk => {
+                let logs = pod_info
+                    .log_directory
+                    .as_deref()
+                    .map(|dir| dir_size(Path::new(dir)))
+                    .unwrap_or(0);
+                let writable_layers: u64 = containers
+                    .iter()
+                    .map(|c| dir_size(&c.bundle().join("upper")))
+                    .sum();
+                logs + writable_layers
+            }
+        };
+        let (qos, priority) = pod_info
+            .pod_spec
+            .as_ref()
+            .map(|pod| (qos_class(&pod.spec), pod.spec.priority.unwrap_or(0)))
+            .unwrap_or((QosClass::BestEffort, 0));
+        candidates.push(Candidate {
+            pod_name,
+            pod: pod_info.pod_spec,
+            qos,
+            priority,
+            usage,
+        });
+    }
+    candidates.sort_by_key(|c| (c.qos, c.priority, Reverse(c.usage)));
+    Ok(candidates)
+}
+
+/// Resident memory of `root` and all of its descendants.
+fn process_tree_memory(sys: &System, root: u32) -> u64 {
+    let root = sysinfo::Pid::from_u32(root);
+    sys.processes()
+        .values()
+        .filter(|process| {
+            let mut current = Some(process.pid());
+            while let Some(pid) = current {
+                if pid == root {
+                    return true;
+                }
+                current = sys.process(pid).and_then(|p| p.parent());
+            }
+            false
+        })
+        .map(|process| process.memory())
+        .sum()
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{ContainerRes, ContainerSpec, Resource as Limits};
+
+    fn container(cpu: Option<&str>, memory: Option<&str>) -> ContainerSpec {
+        ContainerSpec {
+            name: "c".to_string(),
+            image: "busybox".to_string(),
+            ports: vec![],
+            args: vec![],
+            resources: Some(ContainerRes {
+                limits: Some(Limits {
+                    cpu: cpu.map(str::to_string),
+                    memory: memory.map(str::to_string),
+                }),
+            }),
+            liveness_probe: None,
+            readiness_probe: None,
+            startup_probe: None,
+            security_context: None,
+            env: None,
+            volume_mounts: None,
+            command: None,
+            working_dir: None,
+            lifecycle: None,
+            restart_policy: None,
+            image_pull_policy: None,
+        }
+    }
+
+    #[test]
+    fn test_qos_class() {
+        let mut spec = PodSpec {
+            containers: vec![container(Some("1"), Some("1Gi"))],
+            ..Default::default()
+        };
+        assert_eq!(qos_class(&spec), QosClass::Guaranteed);
+
+        spec.init_containers = vec![container(None, Some("1Gi"))];
+        assert_eq!(qos_class(&spec), QosClass::Burstable);
+
+        spec.init_containers.clear();
+        spec.containers = vec![container(None, None)];
+        assert_eq!(qos_class(&spec), QosClass::BestEffort);
+
+        assert!(QosClass::BestEffort < QosClass::Burstable);
+        assert!(QosClass::Burstable < QosClass::Guaranteed);
+    }
+
+    #[test]
+    fn test_fs_usage_percent() {
+        let stats = FsStats {
+            available: 25,
+            capacity: 100,
+            inodes_free: 0,
+            inodes: 0,
+        };
+        assert_eq!(stats.usage_percent(), 75);
+        let empty = FsStats {
+            available: 0,
+            capacity: 0,
+            inodes_free: 0,
+            inodes: 0,
+        };
+        assert_eq!(empty.usage_percent(), 0);
+    }
+}
@@ -0,0 +1,231 @@
+//! Eviction signals and thresholds.
+//!
+//! Thresholds are written the way the kubelet takes them, a comma separated
+//! list of `<signal><<quantity>`, e.g.
+//! `memory.available<100Mi,nodefs.available<10%`. The quantity is either a
+//! number of bytes (inodes for the `inodesFree` signals), optionally with a
+//! `Ki`/`Mi`/`Gi`/`Ti` or `k`/`M`/`G`/`T` suffix, or a percentage of the
+//! capacity.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow, bail};
+
+/// Thresholds used when `RKL_EVICTION_HARD` is not set.
+pub const DEFAULT_HARD_THRESHOLDS: &str =
+    "memory.available<100Mi,nodefs.available<10%,nodefs.inodesFree<5%,imagefs.available<15%";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Signal {
+    MemoryAvailable,
+    NodeFsAvailable,
+    NodeFsInodesFree,
+    ImageFsAvailable,
+    ImageFsInodesFree,
+}
+
+/// The resource a signal reports on, each maps to one node condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resource {
+    Memory,
+    Disk,
+}
+
+impl Signal {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Signal::MemoryAvailable => "memory.available",
+            Signal::NodeFsAvailable => "nodefs.available",
+            Signal::NodeFsInodesFree => "nodefs.inodesFree",
+            Signal::ImageFsAvailable => "imagefs.available",
+            Signal::ImageFsInodesFree => "imagefs.inodesFree",
+        }
+    }
+
+    pub fn resource(&self) -> Resource {
+        match self {
+            Signal::MemoryAvailable => Resource::Memory,
+            _ => Resource::Disk,
+        }
+    }
+}
+
+impl Display for Signal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Signal {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "memory.available" => Ok(Signal::MemoryAvailable),
+            "nodefs.available" => Ok(Signal::NodeFsAvailable),
+            "nodefs.inodesFree" => Ok(Signal::NodeFsInodesFree),
+            "imagefs.available" => Ok(Signal::ImageFsAvailable),
+            "imagefs.inodesFree" => Ok(Signal::ImageFsInodesFree),
+            _ => Err(anyhow!("unknown eviction signal {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantity {
+    Absolute(u64),
+    /// Share of the capacity, between 0 and 1.
+    Percentage(f64),
+}
+
+impl Quantity {
+    /// The quantity as an absolute amount of a resource with `capacity`.
+    pub fn resolve(&self, capacity: u64) -> u64 {
+        match self {
+            Quantity::Absolute(value) => *value,
+            Quantity::Percentage(share) => (capacity as f64 * share) as u64,
+        }
+    }
+}
+
+impl FromStr for Quantity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(percent) = s.strip_suffix('%') {
+            let percent: f64 = percent
+                .parse()
+                .map_err(|_| anyhow!("invalid percentage {s}"))?;
+            if !(0.0..=100.0).contains(&percent) {
+                bail!("percentage {s} is out of range");
+            }
+            return Ok(Quantity::Percentage(percent / 100.0));
+        }
+        const SUFFIXES: [(&str, u64); 8] = [
+            ("Ki", 1 << 10),
+            ("Mi", 1 << 20),
+            ("Gi", 1 << 30),
+            ("Ti", 1 << 40),
+            ("k", 1_000),
+            ("M", 1_000_000),
+            ("G", 1_000_000_000),
+            ("T", 1_000_000_000_000),
+        ];
+        let (number, unit) = SUFFIXES
+            .iter()
+            .find_map(|(suffix, unit)| s.strip_suffix(suffix).map(|n| (n, *unit)))
+            .unwrap_or((s, 1));
+        let number: u64 = number
+            .parse()
+            .map_err(|_| anyhow!("invalid quantity {s}"))?;
+        number
+            .checked_mul(unit)
+            .map(Quantity::Absolute)
+            .ok_or_else(|| anyhow!("quantity {s} is too large"))
+    }
+}
+
+/// The node is under pressure once `signal` drops below `quantity`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Threshold {
+    pub signal: Signal,
+    pub quantity: Quantity,
+}
+
+impl Threshold {
+    /// Whether `observed` out of `capacity` is below the threshold.
+    pub fn is_met(&self, observed: u64, capacity: u64) -> bool {
+        observed < self.quantity.resolve(capacity)
+    }
+}
+
+impl Display for Threshold {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.quantity {
+            Quantity::Absolute(value) => write!(f, "{}<{value}", self.signal),
+            Quantity::Percentage(share) => write!(f, "{}<{}%", self.signal, share * 100.0),
+        }
+    }
+}
+
+/// Parse a comma separated list of thresholds. An empty list is allowed.
+pub fn parse_thresholds(s: &str) -> Result<Vec<Threshold>> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            let (signal, quantity) = t.split_once('<').ok_or_else(|| {
+                anyhow!("invalid eviction threshold {t}, expected <signal><<quantity>")
+            })?;
+            Ok(Threshold {
+                signal: signal.trim().parse()?,
+                quantity: quantity.trim().parse()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_thresholds() {
+        let thresholds = parse_thresholds(DEFAULT_HARD_THRESHOLDS).unwrap();
+        assert_eq!(
+            thresholds,
+            vec![
+                Threshold {
+                    signal: Signal::MemoryAvailable,
+                    quantity: Quantity::Absolute(100 << 20),
+                },
+                Threshold {
+                    signal: Signal::NodeFsAvailable,
+                    quantity: Quantity::Percentage(0.1),
+                },
+                Threshold {
+                    signal: Signal::NodeFsInodesFree,
+                    quantity: Quantity::Percentage(0.05),
+                },
+                Threshold {
+                    signal: Signal::ImageFsAvailable,
+                    quantity: Quantity::Percentage(0.15),
+                },
+            ]
+        );
+        assert!(parse_thresholds("").unwrap().is_empty());
+        assert_eq!(
+            parse_thresholds(" imagefs.inodesFree<1000 ").unwrap(),
+            vec![Threshold {
+                signal: Signal::ImageFsInodesFree,
+                quantity: Quantity::Absolute(1000),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_thresholds_invalid() {
+        assert!(parse_thresholds("memory.available>100Mi").is_err());
+        assert!(parse_thresholds("memory.free<100Mi").is_err());
+        assert!(parse_thresholds("nodefs.available<120%").is_err());
+        assert!(parse_thresholds("nodefs.available<lots").is_err());
+    }
+
+    #[test]
+    fn test_threshold_is_met() {
+        let absolute = Threshold {
+            signal: Signal::MemoryAvailable,
+            quantity: "1Gi".parse().unwrap(),
+        };
+        assert!(absolute.is_met(512 << 20, 8 << 30));
+        assert!(!absolute.is_met(2 << 30, 8 << 30));
+
+        let percentage = Threshold {
+            signal: Signal::NodeFsAvailable,
+            quantity: "10%".parse().unwrap(),
+        };
+        assert!(percentage.is_met(5, 100));
+        assert!(!percentage.is_met(10, 100));
+    }
+}
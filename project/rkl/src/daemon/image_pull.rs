@@ -0,0 +1,216 @@
+//! Pulling the images of the pods rks assigns to this node.
+//!
+//! The credentials of a pod's `imagePullSecrets` are fetched from rks and
+//! handed to rkforge, taking precedence over the registries logged in on the
+//! node. While a pull fails the container waits with reason `ErrImagePull`,
+//! then `ImagePullBackOff` as the pull is retried with an exponential
+//! back-off, like the kubelet does.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use common::{ContainerState, ContainerStatus, PodPhase, PodTask, RksMessage, Secret};
+use rkforge::pull::RegistryAuth;
+use tracing::{info, warn};
+
+use crate::commands::pod::TLSConnectionArgs;
+use crate::commands::secret::registry_credentials;
+use crate::daemon::client::send_pod_status;
+use crate::quic::client::{Cli, Daemon as ClientDaemon, QUICClient};
+use crate::task::{ImagePullError, TaskRunner};
+
+const ERR_IMAGE_PULL: &str = "ErrImagePull";
+const ERR_IMAGE_NEVER_PULL: &str = "ErrImageNeverPull";
+const IMAGE_PULL_BACK_OFF: &str = "ImagePullBackOff";
+
+const INITIAL_PULL_BACKOFF: Duration = Duration::from_secs(10);
+const MAX_PULL_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Pods whose images are being pulled. Deleting a pod removes it, which
+/// stops retrying its pulls.
+static PULLING_PODS: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(Default::default);
+
+/// Stop pulling the images of pod `pod_name`, as it is being deleted.
+pub fn cancel(pod_name: &str) {
+    PULLING_PODS.lock().unwrap().remove(pod_name);
+}
+
+fn is_pulling(pod_name: &str) -> bool {
+    PULLING_PODS.lock().unwrap().contains(pod_name)
+}
+
+/// Fetches a [`Secret`] from rks by name.
+async fn get_secret(client: &QUICClient<Cli>, name: &str) -> Result<Secret> {
+    client
+        .send_msg(&RksMessage::GetSecret(name.to_string()))
+        .await?;
+    match client.fetch_msg().await? {
+        RksMessage::GetSecretRes(secret) => Ok(*secret),
+        RksMessage::Error(err) => Err(anyhow!(err)),
+        msg => Err(anyhow!("unexpected response: {msg:?}")),
+    }
+}
+
+/// The registry credentials of the pod's `imagePullSecrets`. Secrets which
+/// can't be read are skipped, the pull may still succeed without them.
+pub(crate) async fn pod_registry_credentials(
+    server_addr: &str,
+    tls_cfg: &TLSConnectionArgs,
+    pod: &PodTask,
+) -> Result<HashMap<String, RegistryAuth>> {
+    let mut credentials = HashMap::new();
+    if pod.spec.image_pull_secrets.is_empty() {
+        return Ok(credentials);
+    }
+    let client = QUICClient::<Cli>::connect(server_addr, tls_cfg).await?;
+    for secret_ref in &pod.spec.image_pull_secrets {
+        match get_secret(&client, &secret_ref.name)
+            .await
+            .and_then(|secret| registry_credentials(&secret))
+        {
+            Ok(secret_credentials) => credentials.extend(secret_credentials),
+            Err(e) => warn!(
+                "[worker] pod {}: ignoring image pull secret {}: {e}",
+                pod.metadata.name, secret_ref.name
+            ),
+        }
+    }
+    Ok(credentials)
+}
+
+/// Pull the images of `pod` with the credentials of its `imagePullSecrets`,
+/// retrying failed pulls until they succeed or the pod is deleted.
+pub async fn pull_images(
+    client: &QUICClient<ClientDaemon>,
+    server_addr: &str,
+    tls_cfg: &TLSConnectionArgs,
+    pod: &PodTask,
+    runner: &mut TaskRunner,
+) -> Result<()> {
+    let pod_name = &pod.metadata.name;
+    runner.registry_credentials = pod_registry_credentials(server_addr, tls_cfg, pod).await?;
+
+    PULLING_PODS.lock().unwrap().insert(pod_name.clone());
+    let mut backoff = INITIAL_PULL_BACKOFF;
+    let mut failed = false;
+    let result = loop {
+        let err = match runner.pull_images().await {
+            Ok(()) => break Ok(()),
+            Err(err) => err,
+        };
+        warn!("[worker] pod {pod_name}: {err}");
+        let reason = if err.not_present() {
+            ERR_IMAGE_NEVER_PULL
+        } else if failed {
+            IMAGE_PULL_BACK_OFF
+        } else {
+            ERR_IMAGE_PULL
+        };
+        failed = true;
+        send_pod_status(client, pod, waiting_status(pod, &err, reason)).await;
+
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_PULL_BACKOFF);
+        if !is_pulling(pod_name) {
+            info!("[worker] pod {pod_name} deleted, no longer pulling its images");
+            break Err(anyhow!(
+                "pod {pod_name} was deleted while pulling its images"
+            ));
+        }
+    };
+    cancel(pod_name);
+    result
+}
+
+/// The status of `pod` with the container whose image could not be pulled
+/// waiting for `reason`.
+fn waiting_status(pod: &PodTask, err: &ImagePullError, reason: &str) -> common::PodStatus {
+    let mut status = pod.status.clone();
+    status.phase = PodPhase::Pending;
+    let init = pod
+        .spec
+        .init_containers
+        .iter()
+        .any(|c| format!("{}-{}", pod.metadata.name, c.name) == err.container);
+    let statuses = if init {
+        &mut status.init_container_statuses
+    } else {
+        &mut status.container_statuses
+    };
+    let state = ContainerState::Waiting {
+        reason: Some(reason.to_string()),
+        message: Some(err.error.to_string()),
+    };
+    match statuses.iter_mut().find(|s| s.name == err.container) {
+        Some(container_status) => container_status.state = Some(state),
+        None => statuses.push(ContainerStatus {
+            name: err.container.clone(),
+            state: Some(state),
+            ..Default::default()
+        }),
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{ContainerSpec, ObjectMeta, PodSpec};
+
+    fn container(name: &str) -> ContainerSpec {
+        ContainerSpec {
+            name: name.to_string(),
+            image: "busybox:1.36".to_string(),
+            ports: vec![],
+            args: vec![],
+            resources: None,
+            liveness_probe: None,
+            readiness_probe: None,
+            startup_probe: None,
+            security_context: None,
+            env: None,
+            volume_mounts: None,
+            command: None,
+            working_dir: None,
+            lifecycle: None,
+            restart_policy: None,
+            image_pull_policy: None,
+        }
+    }
+
+    #[test]
+    fn test_waiting_status_reports_failed_container() {
+        let pod = PodTask {
+            api_version: "v1".to_string(),
+            kind: "Pod".to_string(),
+            metadata: ObjectMeta {
+                name: "web".to_string(),
+                ..Default::default()
+            },
+            spec: PodSpec {
+                init_containers: vec![container("setup")],
+                containers: vec![container("app")],
+                ..Default::default()
+            },
+            status: Default::default(),
+        };
+        let err = ImagePullError {
+            container: "web-setup".to_string(),
+            image: "busybox:1.36".to_string(),
+            error: anyhow!("unauthorized"),
+        };
+
+        let status = waiting_status(&pod, &err, ERR_IMAGE_PULL);
+
+        assert_eq!(status.phase, PodPhase::Pending);
+        assert!(status.container_statuses.is_empty());
+        assert_eq!(status.init_container_statuses[0].name, "web-setup");
+        assert!(matches!(
+            &status.init_container_statuses[0].state,
+            Some(ContainerState::Waiting { reason: Some(reason), message: Some(message) })
+                if reason == ERR_IMAGE_PULL && message == "unauthorized"
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+//! HTTP server of the daemon's Prometheus metrics.
+//!
+//! `/metrics/resource` serves the resource usage of the node and its pods
+//! as last collected by the [`STATS_MANAGER`]. `/metrics` serves the
+//! metrics of the daemon itself, declared below. Their names and labels are
+//! what dashboards are built on, keep them stable.
+
+use std::net::SocketAddr;
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use axum::{Router, http::StatusCode, http::header, response::IntoResponse, routing::get};
+use common::metrics::{
+    Counter, DEFAULT_BUCKETS, Histogram, Registry, SLOW_BUCKETS, registry, render,
+};
+use tracing::info;
+
+use crate::daemon::stats::{STATS_MANAGER, render_metrics};
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+pub static PLEG_RELIST_DURATION: Histogram = Histogram::new(
+    "rkl_pleg_relist_duration_seconds",
+    "Latency of a PLEG relist in seconds.",
+    &[],
+    DEFAULT_BUCKETS,
+);
+
+/// Time from a pod being assigned to the node to its containers running,
+/// image pulls included. Only pods which started are observed.
+pub static POD_START_DURATION: Histogram = Histogram::new(
+    "rkl_pod_start_duration_seconds",
+    "Latency of starting a pod in seconds, image pulls included.",
+    &[],
+    SLOW_BUCKETS,
+);
+
+/// Probe executions, `result` is `success`, `failure` or `timeout`.
+pub static PROBE_RESULTS: Counter = Counter::new(
+    "rkl_probe_results_total",
+    "Number of probe executions, by probe type and result.",
+    &["probe_type", "result"],
+);
+
+pub static IMAGE_PULL_DURATION: Histogram = Histogram::new(
+    "rkl_image_pull_duration_seconds",
+    "Latency of pulling an image in seconds, by result.",
+    &["result"],
+    SLOW_BUCKETS,
+);
+
+pub static CONTAINER_RESTARTS: Counter = Counter::new(
+    "rkl_container_restarts_total",
+    "Number of container restarts, by pod and container.",
+    &["pod", "container"],
+);
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(|| {
+    registry(&[
+        &PLEG_RELIST_DURATION,
+        &POD_START_DURATION,
+        &PROBE_RESULTS,
+        &IMAGE_PULL_DURATION,
+        &CONTAINER_RESTARTS,
+    ])
+});
+
+/// Drop the series of pod `pod_name` once it's removed from the node.
+pub fn forget_pod(pod_name: &str) {
+    CONTAINER_RESTARTS.remove_matching("pod", pod_name);
+}
+
+pub async fn serve(addr: SocketAddr) -> Result<()> {
+    let app = Router::new()
+        .route("/metrics", get(daemon_metrics))
+        .route("/metrics/resource", get(resource_metrics));
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed to bind {addr}"))?;
+    info!("[metrics] serving on {addr}");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn daemon_metrics() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], render(&REGISTRY))
+}
+
+async fn resource_metrics() -> impl IntoResponse {
+    match STATS_MANAGER.get().and_then(|manager| manager.latest()) {
+        Some(stats) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, CONTENT_TYPE)],
+            render_metrics(&stats),
+        ),
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::CONTENT_TYPE, CONTENT_TYPE)],
+            "resource usage not collected yet\n".to_string(),
+        ),
+    }
+}
@@ -1,7 +1,14 @@
+pub mod allocatable;
 pub mod client;
+pub mod cri;
+pub mod eviction;
+pub mod image_pull;
+pub mod metrics;
 pub mod pod_worker;
+pub mod session;
 // pub mod probe;
 pub mod static_pods;
+pub mod stats;
 pub mod status;
 pub mod sync_loop;
 
@@ -10,8 +17,11 @@ use std::{env, sync::Arc, time::Duration};
 //mod status_access;
 use crate::{
     commands::pod::TLSConnectionArgs,
+    config::{CRI_SOCKET, STATS_CONFIG},
     daemon::{
+        eviction::{EVICTION_MANAGER, EvictionManager},
         pod_worker::PodWorker,
+        stats::{STATS_MANAGER, StatsManager},
         status::{
             pleg::PLEG,
             probe::probe_manager::{PROBE_MANAGER, ProbeManager, restore_existing_probes},
@@ -40,6 +50,18 @@ pub fn main(tls_cfg: TLSConnectionArgs) -> Result<(), anyhow::Error> {
                 }
             });
 
+            if let Err(e) = allocatable::enforce_node_allocatable() {
+                warn!("[daemon] failed to enforce node allocatable: {e:?}");
+            }
+
+            if let Some(socket) = CRI_SOCKET.clone() {
+                tokio::spawn(async move {
+                    if let Err(e) = cri::serve(&socket).await {
+                        error!("[daemon] CRI server exited with error: {e:?}");
+                    }
+                });
+            }
+
             let mut status_manager = StatusManager::try_new(server_addr.clone(), tls_cfg.clone())
                 .await
                 .expect("Failed to construct StatusManager");
@@ -54,6 +76,26 @@ pub fn main(tls_cfg: TLSConnectionArgs) -> Result<(), anyhow::Error> {
                 .set(probe_manager.clone())
                 .expect("[daemon] failed to set global PROBE_MANAGER");
 
+            let eviction_manager = Arc::new(EvictionManager::new());
+            eviction_manager.run();
+            EVICTION_MANAGER
+                .set(eviction_manager)
+                .expect("[daemon] failed to set global EVICTION_MANAGER");
+
+            let stats_manager = Arc::new(StatsManager::new());
+            stats_manager.run();
+            STATS_MANAGER
+                .set(stats_manager)
+                .expect("[daemon] failed to set global STATS_MANAGER");
+
+            if let Some(addr) = STATS_CONFIG.metrics_address {
+                tokio::spawn(async move {
+                    if let Err(e) = metrics::serve(addr).await {
+                        error!("[daemon] metrics server exited with error: {e:?}");
+                    }
+                });
+            }
+
             tokio::spawn(async move {
                 let mut pleg = PLEG::new(
                     server_addr.clone(),
@@ -7,9 +7,11 @@
 //! container readiness (readiness probes) or triggers container restarts (liveness probe failures,
 //! respecting [`common::RestartPolicy`]). It runs as a single background task consuming from multiple async channels.
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
 
-use chrono::DateTime;
+use chrono::Utc;
 use common::{
     ConditionStatus, ContainerState, ContainerStatus, PodCondition, PodConditionType, PodPhase,
     PodStatus, PodTask,
@@ -21,7 +23,11 @@ use libruntime::{cri::cri_api::StartContainerRequest, rootpath};
 use nix::sys::wait::WaitStatus;
 use nix::unistd::Pid;
 use procfs::process::Process;
-use tokio::{select, sync::mpsc::UnboundedReceiver, task::JoinHandle};
+use tokio::{
+    select,
+    sync::mpsc::UnboundedReceiver,
+    task::{AbortHandle, JoinHandle},
+};
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
@@ -30,14 +36,18 @@ use crate::{
         delete, load_container,
         pod::{PodInfo, TLSConnectionArgs},
     },
-    daemon::status::{
-        get_pod_by_uid,
-        pleg::{PodLifecycleEvent, PodLifecycleEventType},
-        probe::{
-            probe_manager::{ProbeManager, ProbeResult, ProbeResultType},
-            prober::match_container_name,
+    daemon::{
+        image_pull,
+        metrics::CONTAINER_RESTARTS,
+        status::{
+            get_pod_by_uid,
+            pleg::{PodLifecycleEvent, PodLifecycleEventType},
+            probe::{
+                probe_manager::{ProbeManager, ProbeResult, ProbeResultType},
+                prober::match_container_name,
+            },
+            status_manager::StatusManager,
         },
-        status_manager::StatusManager,
     },
     quic::client::{Cli, QUICClient},
     task::TaskRunner,
@@ -62,6 +72,88 @@ pub struct PodWorker {
 
 const UNKNOWN_EXIT_CODE: i32 = -1;
 
+/// Waiting reason of a container whose restart is being delayed.
+const CRASH_LOOP_BACK_OFF: &str = "CrashLoopBackOff";
+const INITIAL_RESTART_BACKOFF: Duration = Duration::from_secs(10);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(300);
+/// A container that ran this long before exiting starts over from
+/// [`INITIAL_RESTART_BACKOFF`].
+const RESTART_BACKOFF_RESET: Duration = Duration::from_secs(600);
+
+/// Restart delays of the pods of the node, forgotten by every path removing
+/// a pod through [`forget_pod_restarts`].
+pub(crate) static RESTART_BACKOFF: LazyLock<RestartBackoff> = LazyLock::new(Default::default);
+
+/// Per-container restart delays, doubled on every crash up to
+/// [`MAX_RESTART_BACKOFF`], and the restarts waiting for them.
+#[derive(Debug, Default)]
+pub(crate) struct RestartBackoff {
+    delays: Mutex<HashMap<(Uuid, String), Duration>>,
+    pending: Mutex<HashMap<Uuid, Vec<AbortHandle>>>,
+}
+
+impl RestartBackoff {
+    /// The delay before restarting `container` of pod `pod_uid`, which ran
+    /// for `ran_for` before it exited.
+    pub(crate) fn next(
+        &self,
+        pod_uid: Uuid,
+        container: &str,
+        ran_for: Option<Duration>,
+    ) -> Duration {
+        let mut delays = self.delays.lock().unwrap();
+        let key = (pod_uid, container.to_string());
+        let delay = match delays.get(&key) {
+            Some(prev) if ran_for.is_none_or(|d| d < RESTART_BACKOFF_RESET) => {
+                (*prev * 2).min(MAX_RESTART_BACKOFF)
+            }
+            _ => INITIAL_RESTART_BACKOFF,
+        };
+        delays.insert(key, delay);
+        delay
+    }
+
+    /// Keep `restart` of pod `pod_uid` to cancel it should the pod be
+    /// forgotten before its delay is over.
+    pub(crate) fn add_pending(&self, pod_uid: Uuid, restart: AbortHandle) {
+        let mut pending = self.pending.lock().unwrap();
+        let restarts = pending.entry(pod_uid).or_default();
+        restarts.retain(|r| !r.is_finished());
+        restarts.push(restart);
+    }
+
+    /// Drop the delays of all containers of pod `pod_uid` and cancel its
+    /// pending restarts.
+    pub(crate) fn forget_pod(&self, pod_uid: Uuid) {
+        self.delays
+            .lock()
+            .unwrap()
+            .retain(|(uid, _), _| *uid != pod_uid);
+        let restarts = self.pending.lock().unwrap().remove(&pod_uid);
+        for restart in restarts.into_iter().flatten() {
+            restart.abort();
+        }
+    }
+}
+
+/// Forget the restart delays of pod `pod_name` and cancel its pending
+/// restarts. Called before the pod is removed, while its pod file still
+/// records its uid.
+pub(crate) fn forget_pod_restarts(pod_name: &str) {
+    if let Some(uid) = recorded_pod_uid(pod_name) {
+        RESTART_BACKOFF.forget_pod(uid);
+    }
+}
+
+/// Uid of the pod named `pod_name` on this node, if it records its spec.
+fn recorded_pod_uid(pod_name: &str) -> Option<Uuid> {
+    let root_path = rootpath::determine(None, &*create_syscall()).ok()?;
+    PodInfo::load(&root_path, pod_name)
+        .ok()?
+        .pod_spec
+        .map(|pod| pod.metadata.uid)
+}
+
 struct State {
     server_addr: String,
     tls_cfg: Arc<TLSConnectionArgs>,
@@ -70,6 +162,7 @@ struct State {
     liveness_probe_result_rx: tokio::sync::broadcast::Receiver<ProbeResult>,
     readiness_probe_result_rx: tokio::sync::broadcast::Receiver<ProbeResult>,
     startup_probe_result_rx: tokio::sync::broadcast::Receiver<ProbeResult>,
+    restart_backoff: &'static RestartBackoff,
 }
 
 impl PodWorker {
@@ -136,6 +229,7 @@ impl PodWorker {
             liveness_probe_result_rx: self.liveness_probe_result_rx.resubscribe(),
             readiness_probe_result_rx: self.readiness_probe_result_rx.resubscribe(),
             startup_probe_result_rx: self.startup_probe_result_rx.resubscribe(),
+            restart_backoff: &RESTART_BACKOFF,
         };
 
         self.sync_loop_handle = Some(tokio::spawn(async move {
@@ -344,7 +438,7 @@ async fn handle_liveness_probe_result(
         container_id = %probe_result.container_id,
         "[PodWorker] Restarting container due to liveness probe failure"
     );
-    restart_container_locally(&pod, &event).await?;
+    restart_container_locally(&state.server_addr, &state.tls_cfg, &pod, &event).await?;
     tracing::debug!(
         pod_name = %pod.metadata.name,
         pod_uid = %pod_uid,
@@ -392,7 +486,14 @@ async fn sync_pod_for_pod_lifecycle_event(
         "[PodWorker] Current cached pod status before lifecycle handling"
     );
 
-    apply_pod_lifecycle_event(&pod, &mut pod_status, event).await?;
+    let restart_delay =
+        apply_pod_lifecycle_event(&pod, &mut pod_status, event, state.restart_backoff).await?;
+    // Lifecycle events only come once the init containers have completed
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let init_statuses = PodInfo::load(&root_path, &event.pod_name)
+        .map(|info| info.init_container_statuses)
+        .unwrap_or_default();
+    apply_init_container_statuses(&mut pod_status, &init_statuses);
 
     debug!(
         pod_uid = %event.pod_uid,
@@ -408,6 +509,42 @@ async fn sync_pod_for_pod_lifecycle_event(
         "[PodWorker] Persisted lifecycle-derived pod status to status manager"
     );
 
+    if let Some(delay) = restart_delay {
+        let server_addr = state.server_addr.clone();
+        let tls_cfg = state.tls_cfg.clone();
+        let pod_task = pod.clone();
+        let event = event.clone();
+        let pod_uid = event.pod_uid;
+        let restart = tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            // The pod may have been deleted, or replaced by one of the same
+            // name, meanwhile
+            if recorded_pod_uid(&event.pod_name) != Some(event.pod_uid) {
+                debug!(
+                    pod_uid = %event.pod_uid,
+                    pod_name = %event.pod_name,
+                    container_id = %event.container.state.id,
+                    "[PodWorker] Pod is gone; not restarting container"
+                );
+                return;
+            }
+            if let Err(e) =
+                restart_container_locally(&server_addr, &tls_cfg, &pod_task, &event).await
+            {
+                warn!(
+                    pod_uid = %event.pod_uid,
+                    pod_name = %event.pod_name,
+                    container_id = %event.container.state.id,
+                    error = %e,
+                    "[PodWorker] Failed to restart container after back-off"
+                );
+            }
+        });
+        state
+            .restart_backoff
+            .add_pending(pod_uid, restart.abort_handle());
+    }
+
     if pod_status.phase == PodPhase::Running {
         for container_spec in pod.spec.containers {
             if container_spec.readiness_probe.is_none() {
@@ -422,16 +559,23 @@ async fn sync_pod_for_pod_lifecycle_event(
     Ok(())
 }
 
+/// Returns the delay before restarting the container which died, if any.
 async fn apply_pod_lifecycle_event(
     pod_task: &PodTask,
     pod_status: &mut PodStatus,
     event: &PodLifecycleEvent,
-) -> anyhow::Result<()> {
+    restart_backoff: &RestartBackoff,
+) -> anyhow::Result<Option<Duration>> {
     if pod_status.conditions.is_none() {
         pod_status.conditions = Some(Vec::new());
     }
 
+    let mut restart_delay = None;
+
     let container = &event.container;
+    // Sidecars are reported with the init containers and don't count
+    // towards the pod's phase.
+    let sidecar = is_sidecar(pod_task, &container.state.id);
     match event.event_type {
         PodLifecycleEventType::ContainerCreating => {
             debug!(
@@ -441,7 +585,9 @@ async fn apply_pod_lifecycle_event(
                 "[PodWorker] Handling ContainerCreating event"
             );
 
-            pod_status.phase = PodPhase::Pending;
+            if !sidecar {
+                pod_status.phase = PodPhase::Pending;
+            }
             let pod_conditions = pod_status.conditions.as_mut().unwrap();
             match pod_conditions
                 .iter_mut()
@@ -459,11 +605,8 @@ async fn apply_pod_lifecycle_event(
                     pod_conditions.push(condition);
                 }
             }
-            match pod_status
-                .container_statuses
-                .iter_mut()
-                .find(|cs| cs.name == container.state.id)
-            {
+            let statuses = container_statuses(pod_status, sidecar);
+            match statuses.iter_mut().find(|cs| cs.name == container.state.id) {
                 Some(container_status) => {
                     container_status.state = Some(ContainerState::Waiting {
                         reason: Some("Pulling".to_string()),
@@ -479,7 +622,7 @@ async fn apply_pod_lifecycle_event(
                         }),
                         ..Default::default()
                     };
-                    pod_status.container_statuses.push(container_status);
+                    statuses.push(container_status);
                 }
             };
         }
@@ -491,12 +634,11 @@ async fn apply_pod_lifecycle_event(
                 "[PodWorker] Handling ContainerStarted event"
             );
 
-            pod_status.phase = PodPhase::Running;
-            match pod_status
-                .container_statuses
-                .iter_mut()
-                .find(|cs| cs.name == container.state.id)
-            {
+            if !sidecar {
+                pod_status.phase = PodPhase::Running;
+            }
+            let statuses = container_statuses(pod_status, sidecar);
+            match statuses.iter_mut().find(|cs| cs.name == container.state.id) {
                 Some(container_status) => {
                     container_status.name = container.state.id.clone();
                     container_status.state = Some(ContainerState::Running {
@@ -511,7 +653,7 @@ async fn apply_pod_lifecycle_event(
                         }),
                         ..Default::default()
                     };
-                    pod_status.container_statuses.push(container_status);
+                    statuses.push(container_status);
                 }
             };
         }
@@ -524,57 +666,84 @@ async fn apply_pod_lifecycle_event(
             );
 
             let (exit_code, signal, message) = resolve_exit_status(&event.container);
-
-            match pod_status
-                .container_statuses
-                .iter_mut()
-                .find(|cs| cs.name == container.state.id)
-            {
-                Some(container_status) => {
-                    container_status.name = container.state.id.clone();
-                    container_status.state = Some(ContainerState::Terminated {
-                        exit_code,
-                        started_at: container.state.created,
-                        finished_at: Some(DateTime::<chrono::Utc>::from(
-                            std::time::SystemTime::now(),
-                        )),
-                        signal,
-                        reason: Some("ContainerDied".to_string()),
-                        message,
-                    });
-                }
-                None => {
-                    let container_status = ContainerStatus {
-                        name: container.state.id.clone(),
-                        state: Some(ContainerState::Terminated {
-                            exit_code,
-                            started_at: container.state.created,
-                            finished_at: Some(DateTime::<chrono::Utc>::from(
-                                std::time::SystemTime::now(),
-                            )),
-                            signal,
-                            reason: Some("ContainerDied".to_string()),
-                            message,
-                        }),
-                        ..Default::default()
-                    };
-                    pod_status.container_statuses.push(container_status);
-                }
+            let finished_at = Utc::now();
+            let terminated = ContainerState::Terminated {
+                exit_code,
+                started_at: container.state.created,
+                finished_at: Some(finished_at),
+                signal,
+                reason: Some("ContainerDied".to_string()),
+                message,
             };
 
-            match pod_task.spec.restart_policy {
-                common::RestartPolicy::Always => {
-                    info!(
+            // Sidecars are always restarted, whatever the pod's policy
+            let restart_policy = if sidecar {
+                &common::RestartPolicy::Always
+            } else {
+                &pod_task.spec.restart_policy
+            };
+            let restart = match restart_policy {
+                common::RestartPolicy::Always if pod_task.metadata.deletion_timestamp.is_some() => {
+                    debug!(
                         pod_uid = %event.pod_uid,
                         pod_name = %event.pod_name,
                         container_id = %container.state.id,
-                        "[PodWorker] Restarting container due to RestartPolicy::Always"
+                        "[PodWorker] Pod is terminating; not restarting container"
                     );
-
-                    restart_container_locally(pod_task, event).await?;
+                    restart_backoff.forget_pod(event.pod_uid);
+                    false
                 }
-                common::RestartPolicy::OnFailure => {}
-                common::RestartPolicy::Never => {}
+                common::RestartPolicy::Always => true,
+                common::RestartPolicy::OnFailure => false,
+                common::RestartPolicy::Never => false,
+            };
+
+            let statuses = container_statuses(pod_status, sidecar);
+            let container_status =
+                match statuses.iter().position(|cs| cs.name == container.state.id) {
+                    Some(idx) => &mut statuses[idx],
+                    None => {
+                        statuses.push(ContainerStatus {
+                            name: container.state.id.clone(),
+                            ..Default::default()
+                        });
+                        statuses.last_mut().unwrap()
+                    }
+                };
+
+            if !restart {
+                container_status.state = Some(terminated);
+            } else {
+                let ran_for = container
+                    .state
+                    .created
+                    .and_then(|started| (finished_at - started).to_std().ok());
+                let delay = restart_backoff.next(event.pod_uid, &container.state.id, ran_for);
+                container_status.last_termination_state = Some(terminated);
+                container_status.restart_count += 1;
+                let container_name = container
+                    .state
+                    .id
+                    .strip_prefix(&format!("{}-", event.pod_name))
+                    .unwrap_or(&container.state.id);
+                CONTAINER_RESTARTS.inc(&[event.pod_name.as_str(), container_name]);
+                container_status.state = Some(ContainerState::Waiting {
+                    reason: Some(CRASH_LOOP_BACK_OFF.to_string()),
+                    message: Some(format!(
+                        "back-off {}s restarting failed container {}",
+                        delay.as_secs(),
+                        container.state.id
+                    )),
+                });
+
+                info!(
+                    pod_uid = %event.pod_uid,
+                    pod_name = %event.pod_name,
+                    container_id = %container.state.id,
+                    delay_secs = delay.as_secs(),
+                    "[PodWorker] Restarting container after back-off due to RestartPolicy::Always"
+                );
+                restart_delay = Some(delay);
             }
         }
         _ => {
@@ -613,10 +782,53 @@ async fn apply_pod_lifecycle_event(
         container_status_count = pod_status.container_statuses.len(),
         "[PodWorker] Finished applying lifecycle event to pod status"
     );
-    Ok(())
+    Ok(restart_delay)
 }
 
-fn resolve_exit_status(container: &Container) -> (i32, Option<i32>, Option<String>) {
+/// Whether the runtime container `container_id` is a sidecar of the pod.
+fn is_sidecar(pod_task: &PodTask, container_id: &str) -> bool {
+    pod_task.spec.init_containers.iter().any(|c| {
+        c.is_sidecar()
+            && (c.name == container_id
+                || format!("{}-{}", pod_task.metadata.name, c.name) == container_id)
+    })
+}
+
+fn container_statuses(pod_status: &mut PodStatus, sidecar: bool) -> &mut Vec<ContainerStatus> {
+    if sidecar {
+        &mut pod_status.init_container_statuses
+    } else {
+        &mut pod_status.container_statuses
+    }
+}
+
+/// Report the init containers rkl ran before starting the workload
+/// containers, and mark the pod initialized.
+fn apply_init_container_statuses(pod_status: &mut PodStatus, init_statuses: &[ContainerStatus]) {
+    for status in init_statuses {
+        if !pod_status
+            .init_container_statuses
+            .iter()
+            .any(|s| s.name == status.name)
+        {
+            pod_status.init_container_statuses.push(status.clone());
+        }
+    }
+    let conditions = pod_status.conditions.get_or_insert_default();
+    match conditions
+        .iter_mut()
+        .find(|cond| cond.condition_type == PodConditionType::PodInitialized)
+    {
+        Some(condition) => condition.status = ConditionStatus::True,
+        None => conditions.push(PodCondition {
+            condition_type: PodConditionType::PodInitialized,
+            status: ConditionStatus::True,
+            ..Default::default()
+        }),
+    }
+}
+
+pub(crate) fn resolve_exit_status(container: &Container) -> (i32, Option<i32>, Option<String>) {
     match exit_status_from_container(container) {
         Some((exit_code, signal)) => {
             tracing::debug!(
@@ -685,6 +897,8 @@ fn decode_wait_status(raw_status: i32) -> Option<(i32, Option<i32>)> {
 }
 
 async fn restart_container_locally(
+    server_addr: &str,
+    tls_cfg: &TLSConnectionArgs,
     pod_task: &PodTask,
     event: &PodLifecycleEvent,
 ) -> anyhow::Result<()> {
@@ -706,8 +920,17 @@ async fn restart_container_locally(
 
     let mut task_runner = TaskRunner::from_task(pod_task.clone())?;
     task_runner.pause_pid = Some(pause_pid);
-    task_runner.sandbox_config =
-        Some(task_runner.create_pod_sandbox_config(&event.pod_uid.to_string(), 0)?);
+    task_runner.registry_credentials =
+        image_pull::pod_registry_credentials(server_addr, tls_cfg, pod_task).await?;
+    task_runner.allocate_id_range()?;
+    let mut sandbox_config =
+        task_runner.create_pod_sandbox_config(&event.pod_uid.to_string(), 0)?;
+    // Keep logging into the directory the pod was started with, so the
+    // previous instance's log stays next to the new one.
+    if let Some(dir) = &pod_info.log_directory {
+        sandbox_config.log_directory = dir.clone();
+    }
+    task_runner.sandbox_config = Some(sandbox_config);
     tracing::debug!(
         pod_uid = %event.pod_uid,
         pod_name = %event.pod_name,
@@ -720,8 +943,10 @@ async fn restart_container_locally(
     let container_spec = task_runner
         .task
         .spec
-        .containers
+        .init_containers
         .iter()
+        .filter(|c| c.is_sidecar())
+        .chain(&task_runner.task.spec.containers)
         .find(|c| {
             c.name == *container_id
                 || match_container_name(&c.name, &pod_info.container_names).as_deref()
@@ -770,7 +995,7 @@ async fn restart_container_locally(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
+    use chrono::{DateTime, Utc};
     use common::{ContainerSpec, ObjectMeta, PodSpec};
     use libcontainer::container::{Container, ContainerStatus as LibContainerStatus};
     use uuid::Uuid;
@@ -816,11 +1041,21 @@ mod tests {
                     volume_mounts: None,
                     command: None,
                     working_dir: None,
+                    lifecycle: None,
+                    restart_policy: None,
+                    image_pull_policy: None,
                 }],
                 init_containers: vec![],
                 tolerations: vec![],
                 affinity: None,
                 restart_policy,
+                scheduler_name: None,
+                termination_grace_period_seconds: None,
+                host_users: None,
+                priority: None,
+                volumes: vec![],
+                image_pull_secrets: vec![],
+                ephemeral_containers: vec![],
             },
             status: PodStatus::default(),
         }
@@ -833,9 +1068,14 @@ mod tests {
         let container = make_container("c1", None);
         let event = make_event(PodLifecycleEventType::ContainerCreating, container);
 
-        apply_pod_lifecycle_event(&pod_task, &mut pod_status, &event)
-            .await
-            .unwrap();
+        apply_pod_lifecycle_event(
+            &pod_task,
+            &mut pod_status,
+            &event,
+            &RestartBackoff::default(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(pod_status.phase, PodPhase::Pending);
         let conditions = pod_status.conditions.as_ref().unwrap();
@@ -876,9 +1116,14 @@ mod tests {
             ..Default::default()
         });
 
-        apply_pod_lifecycle_event(&pod_task, &mut pod_status, &event)
-            .await
-            .unwrap();
+        apply_pod_lifecycle_event(
+            &pod_task,
+            &mut pod_status,
+            &event,
+            &RestartBackoff::default(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(pod_status.phase, PodPhase::Running);
         let container_status = pod_status
@@ -894,6 +1139,67 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn apply_event_sidecar_started_reports_init_container_status() {
+        let mut pod_task = make_pod_task(common::RestartPolicy::Never);
+        let mut sidecar = pod_task.spec.containers[0].clone();
+        sidecar.name = "proxy".to_string();
+        sidecar.restart_policy = Some(common::RestartPolicy::Always);
+        pod_task.spec.init_containers.push(sidecar);
+        let event = make_event(
+            PodLifecycleEventType::ContainerStarted,
+            make_container("pod-proxy", Some(Utc::now())),
+        );
+
+        let mut pod_status = PodStatus::default();
+        apply_pod_lifecycle_event(
+            &pod_task,
+            &mut pod_status,
+            &event,
+            &RestartBackoff::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(pod_status.phase, PodPhase::Pending);
+        assert!(pod_status.container_statuses.is_empty());
+        assert_eq!(pod_status.init_container_statuses.len(), 1);
+        assert_eq!(pod_status.init_container_statuses[0].name, "pod-proxy");
+        assert!(matches!(
+            pod_status.init_container_statuses[0].state,
+            Some(ContainerState::Running { .. })
+        ));
+    }
+
+    #[test]
+    fn apply_init_container_statuses_marks_pod_initialized() {
+        let init_status = ContainerStatus {
+            name: "pod-migrate".to_string(),
+            state: Some(ContainerState::Terminated {
+                exit_code: 0,
+                signal: None,
+                reason: Some("Completed".to_string()),
+                message: None,
+                started_at: None,
+                finished_at: None,
+            }),
+            ready: true,
+            ..Default::default()
+        };
+        let mut pod_status = PodStatus::default();
+        apply_init_container_statuses(&mut pod_status, std::slice::from_ref(&init_status));
+        apply_init_container_statuses(&mut pod_status, std::slice::from_ref(&init_status));
+
+        assert_eq!(pod_status.init_container_statuses, vec![init_status]);
+        let conditions = pod_status.conditions.unwrap();
+        assert_eq!(conditions.len(), 1);
+        assert_eq!(
+            conditions[0].condition_type,
+            PodConditionType::PodInitialized
+        );
+        assert_eq!(conditions[0].status, ConditionStatus::True);
+    }
+
     #[tokio::test]
     async fn apply_event_container_died_fails_when_any_exit_code_non_zero() {
         let pod_task = make_pod_task(common::RestartPolicy::Never);
@@ -904,9 +1210,14 @@ mod tests {
         let mut pod_status = PodStatus::default();
         pod_status.phase = PodPhase::Running;
 
-        apply_pod_lifecycle_event(&pod_task, &mut pod_status, &event)
-            .await
-            .unwrap();
+        apply_pod_lifecycle_event(
+            &pod_task,
+            &mut pod_status,
+            &event,
+            &RestartBackoff::default(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(pod_status.phase, PodPhase::Failed);
         let container_status = pod_status
@@ -954,9 +1265,14 @@ mod tests {
             ..Default::default()
         });
 
-        apply_pod_lifecycle_event(&pod_task, &mut pod_status, &event)
-            .await
-            .unwrap();
+        apply_pod_lifecycle_event(
+            &pod_task,
+            &mut pod_status,
+            &event,
+            &RestartBackoff::default(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(pod_status.phase, PodPhase::Succeeded);
     }
@@ -976,9 +1292,14 @@ mod tests {
             ..Default::default()
         });
 
-        apply_pod_lifecycle_event(&pod_task, &mut pod_status, &event)
-            .await
-            .unwrap();
+        apply_pod_lifecycle_event(
+            &pod_task,
+            &mut pod_status,
+            &event,
+            &RestartBackoff::default(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(pod_status.phase, PodPhase::Running);
     }
@@ -996,4 +1317,76 @@ mod tests {
         assert_eq!(status.0, 137);
         assert_eq!(status.1, Some(9));
     }
+
+    #[tokio::test]
+    async fn apply_event_container_died_backs_off_restart() {
+        let pod_task = make_pod_task(common::RestartPolicy::Always);
+        let container = make_container("c1", Some(Utc::now()));
+        let event = make_event(PodLifecycleEventType::ContainerDied, container);
+
+        let mut pod_status = PodStatus::default();
+        pod_status.phase = PodPhase::Running;
+
+        let restart_delay = apply_pod_lifecycle_event(
+            &pod_task,
+            &mut pod_status,
+            &event,
+            &RestartBackoff::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(restart_delay, Some(INITIAL_RESTART_BACKOFF));
+        assert_eq!(pod_status.phase, PodPhase::Running);
+        let container_status = &pod_status.container_statuses[0];
+        assert_eq!(container_status.restart_count, 1);
+        assert!(matches!(
+            container_status.last_termination_state,
+            Some(ContainerState::Terminated { .. })
+        ));
+        match container_status.state.as_ref().unwrap() {
+            ContainerState::Waiting { reason, message } => {
+                assert_eq!(reason.as_deref(), Some(CRASH_LOOP_BACK_OFF));
+                assert!(message.as_deref().unwrap().starts_with("back-off 10s"));
+            }
+            state => panic!("unexpected container state: {state:?}"),
+        }
+    }
+
+    #[test]
+    fn restart_backoff_doubles_up_to_cap_and_resets() {
+        let backoff = RestartBackoff::default();
+        let pod_uid = Uuid::new_v4();
+        let short = Some(Duration::from_secs(1));
+
+        let delays: Vec<_> = (0..7).map(|_| backoff.next(pod_uid, "c1", short)).collect();
+        assert_eq!(
+            delays.iter().map(Duration::as_secs).collect::<Vec<_>>(),
+            vec![10, 20, 40, 80, 160, 300, 300]
+        );
+        assert_eq!(backoff.next(pod_uid, "c2", short), INITIAL_RESTART_BACKOFF);
+
+        assert_eq!(
+            backoff.next(pod_uid, "c1", Some(RESTART_BACKOFF_RESET)),
+            INITIAL_RESTART_BACKOFF
+        );
+        backoff.next(pod_uid, "c1", short);
+        backoff.forget_pod(pod_uid);
+        assert_eq!(backoff.next(pod_uid, "c1", short), INITIAL_RESTART_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn restart_backoff_forget_pod_cancels_pending_restarts() {
+        let backoff = RestartBackoff::default();
+        let pod_uid = Uuid::new_v4();
+        let other_uid = Uuid::new_v4();
+        let restart = tokio::spawn(tokio::time::sleep(Duration::from_secs(60)));
+        let other = tokio::spawn(tokio::time::sleep(Duration::from_millis(10)));
+        backoff.add_pending(pod_uid, restart.abort_handle());
+        backoff.add_pending(other_uid, other.abort_handle());
+
+        backoff.forget_pod(pod_uid);
+        assert!(restart.await.unwrap_err().is_cancelled());
+        assert!(other.await.is_ok());
+    }
 }
@@ -19,6 +19,8 @@ use tokio::{
     time::{Interval, MissedTickBehavior, sleep, timeout},
 };
 
+use crate::daemon::status::probe::prober::grpc_health_check;
+
 lazy_static! {
     static ref PROBE_REGISTRY: Mutex<HashMap<String, PodProbes>> = Mutex::new(HashMap::new());
 }
@@ -60,6 +62,11 @@ pub enum ProbeKind {
         host: String,
         port: u16,
     },
+    Grpc {
+        host: String,
+        port: u16,
+        service: String,
+    },
 }
 
 /// Complete specification of a probe.
@@ -339,6 +346,11 @@ async fn exec_probe(kind: &ProbeKind) -> Result<()> {
         ProbeKind::Exec { command } => run_exec_probe(command).await,
         ProbeKind::HttpGet { host, port, path } => run_http_probe(host, *port, path).await,
         ProbeKind::TcpSocket { host, port } => run_tcp_probe(host, *port).await,
+        ProbeKind::Grpc {
+            host,
+            port,
+            service,
+        } => grpc_health_check(host, *port, service).await,
     }
 }
 
@@ -432,7 +444,7 @@ fn build_probe_spec(
         warn!(
             container = container,
             pod = %pod.metadata.name,
-            "ignored probe with no action (exec/http/tcp)"
+            "ignored probe with no action (exec/http/tcp/grpc)"
         );
         return Ok(None);
     };
@@ -512,6 +524,29 @@ fn build_probe_spec(
                 port: tcp.port,
             }
         }
+        ProbeAction::Grpc(grpc) => {
+            if grpc.port == 0 {
+                warn!(
+                    container = container,
+                    pod = %pod.metadata.name,
+                    "ignored grpc probe with port 0"
+                );
+                return Ok(None);
+            }
+            if pod_ip.is_empty() {
+                return Err(anyhow!(
+                    "grpc probe requires pod IP (container={}, pod={})",
+                    container,
+                    pod.metadata.name
+                ));
+            }
+
+            ProbeKind::Grpc {
+                host: pod_ip.to_string(),
+                port: grpc.port,
+                service: grpc.service.clone().unwrap_or_default(),
+            }
+        }
     };
 
     let spec = ProbeSpec::new(
@@ -876,11 +911,19 @@ mod tests {
                     volume_mounts: None,
                     command: None,
                     working_dir: None,
+                    lifecycle: None,
+                    restart_policy: None,
+                    image_pull_policy: None,
                 }],
                 init_containers: vec![],
                 tolerations: vec![],
                 affinity: None,
                 restart_policy: RestartPolicy::Always,
+                scheduler_name: None,
+                termination_grace_period_seconds: None,
+                host_users: None,
+                priority: None,
+                volumes: vec![],
             },
             status: PodStatus::default(),
         }
@@ -0,0 +1,496 @@
+//! Interactive sessions relayed by rks, see [`common::session`].
+//!
+//! rks opens one bidirectional stream per session on the worker connection.
+//! Every stream starts with a [`SessionRequest`] and then carries frames:
+//! the daemon sends the process output and finally its exit status, the
+//! client sends stdin and terminal resizes.
+
+use std::fs::{self, File};
+use std::io::{self, IoSliceMut};
+use std::net::{Ipv4Addr, TcpStream as StdTcpStream};
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::Utc;
+use common::PodLogOptions;
+use common::quic::{RksConnection, RksStream};
+use common::session::{Frame, SessionRecvExt, SessionRequest, SessionSendExt};
+use libcontainer::container::ContainerStatus;
+use libcontainer::container::builder::ContainerBuilder;
+use libcontainer::syscall::syscall::{SyscallType, create_syscall};
+use libcontainer::workload::default::DefaultExecutor;
+use libruntime::checkpoint::CheckpointOptions;
+use libruntime::rootpath;
+use nix::fcntl::OFlag;
+use nix::sched::{CloneFlags, setns};
+use nix::sys::socket::{ControlMessageOwned, MsgFlags, recvmsg};
+use nix::sys::wait::{WaitStatus, waitpid};
+use nix::unistd::{Pid, pipe2};
+use quinn::RecvStream;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::commands::load_container;
+use crate::commands::pod::PodInfo;
+use crate::commands::pod::checkpoint::{checkpoint_pod, restore_pod};
+use crate::commands::pod::standalone::{self, resolve_container};
+use crate::daemon::metrics;
+use crate::daemon::pod_worker;
+use crate::daemon::status::probe::probe_manager::PROBE_MANAGER;
+use crate::logs::{self, Stream};
+
+const READ_BUFFER_SIZE: usize = 32 * 1024;
+
+/// Serve the session streams rks opens on `conn` until it is closed.
+pub async fn serve(conn: RksConnection) {
+    while let Ok(stream) = conn.accept_bi().await {
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream).await {
+                error!("[worker] session failed: {e:#}");
+            }
+        });
+    }
+}
+
+async fn handle(stream: RksStream) -> Result<()> {
+    let (mut send, mut recv) = stream.into_inner();
+    let req = recv.recv_request().await?;
+    info!("[worker] session {req:?}");
+
+    // All output goes through one writer, the frames of a session can come
+    // from several tasks.
+    let (tx, mut rx) = mpsc::channel::<Frame>(32);
+    let writer = tokio::spawn(async move {
+        while let Some(frame) = rx.recv().await {
+            send.send_frame(&frame).await?;
+        }
+        send.finish()?;
+        anyhow::Ok(())
+    });
+
+    let result = match req {
+        SessionRequest::Exec {
+            pod_name,
+            container,
+            command,
+            tty,
+            stdin,
+        } => {
+            exec(
+                &pod_name,
+                container.as_deref(),
+                command,
+                tty,
+                stdin,
+                recv,
+                tx.clone(),
+            )
+            .await
+        }
+        SessionRequest::Attach {
+            pod_name,
+            container,
+        } => attach(&pod_name, container.as_deref(), tx.clone()).await,
+        SessionRequest::PortForward { pod_name, port } => {
+            port_forward(&pod_name, port, recv, tx.clone()).await
+        }
+        SessionRequest::Checkpoint {
+            pod_name,
+            tcp_established,
+        } => checkpoint(&pod_name, tcp_established, tx.clone()).await,
+        SessionRequest::Restore { pod_name } => restore(&pod_name, recv, tx.clone()).await,
+    };
+    if let Err(e) = result {
+        warn!("[worker] session ended with error: {e:#}");
+        let _ = tx.send(Frame::Error(format!("{e:#}"))).await;
+    }
+    drop(tx);
+    writer.await?
+}
+
+/// A process started in a container by [`spawn_process`].
+pub(crate) struct ExecProcess {
+    pub(crate) pid: Pid,
+    pub(crate) stdin: Option<File>,
+    pub(crate) stdout: File,
+    pub(crate) stderr: Option<File>,
+    /// Master side of the terminal, for resizes.
+    pub(crate) pty_master: Option<File>,
+}
+
+async fn exec(
+    pod_name: &str,
+    container: Option<&str>,
+    command: Vec<String>,
+    tty: bool,
+    stdin: bool,
+    mut recv: RecvStream,
+    tx: mpsc::Sender<Frame>,
+) -> Result<()> {
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let pod_info = PodInfo::load(&root_path, pod_name)?;
+    let container_id = resolve_container(pod_name, &pod_info, container)?.clone();
+
+    let process = tokio::task::spawn_blocking(move || {
+        spawn_process(&root_path, &container_id, command, tty, stdin)
+    })
+    .await??;
+
+    let mut outputs = vec![tokio::spawn(forward_output(
+        tokio::fs::File::from_std(process.stdout),
+        Frame::Stdout,
+        tx.clone(),
+    ))];
+    if let Some(stderr) = process.stderr {
+        outputs.push(tokio::spawn(forward_output(
+            tokio::fs::File::from_std(stderr),
+            Frame::Stderr,
+            tx.clone(),
+        )));
+    }
+
+    let mut stdin = process.stdin.map(tokio::fs::File::from_std);
+    let pty_master = process.pty_master;
+    let input = tokio::spawn(async move {
+        while let Some(frame) = recv.recv_frame().await? {
+            match frame {
+                Frame::Stdin(data) => {
+                    if let Some(stdin) = stdin.as_mut() {
+                        stdin.write_all(&data).await?;
+                        stdin.flush().await?;
+                    }
+                }
+                Frame::CloseStdin => stdin = None,
+                Frame::Resize { cols, rows } => {
+                    if let Some(master) = &pty_master {
+                        resize(master, cols, rows)?;
+                    }
+                }
+                other => warn!("[worker] unexpected session frame {other:?}"),
+            }
+        }
+        anyhow::Ok(())
+    });
+
+    let pid = process.pid;
+    let status = tokio::task::spawn_blocking(move || waitpid(pid, None)).await?;
+    input.abort();
+    for output in outputs {
+        output.await?;
+    }
+    let exit_code = match status? {
+        WaitStatus::Exited(_, status) => status,
+        WaitStatus::Signaled(_, sig, _) => 128 + sig as i32,
+        _ => 0,
+    };
+    let _ = tx.send(Frame::Exit(exit_code)).await;
+    Ok(())
+}
+
+/// Start `command` in the container, either on a new terminal or with its
+/// stdio connected to pipes.
+pub(crate) fn spawn_process(
+    root_path: &Path,
+    container_id: &str,
+    command: Vec<String>,
+    tty: bool,
+    stdin: bool,
+) -> Result<ExecProcess> {
+    let builder = ContainerBuilder::new(container_id.to_string(), SyscallType::default())
+        .with_executor(DefaultExecutor {})
+        .with_root_path(root_path)?;
+
+    if tty {
+        // The runtime hands the master side of the new terminal over a unix
+        // socket, before the process is started.
+        let socket_dir = root_path.join("sessions");
+        fs::create_dir_all(&socket_dir)?;
+        let socket_path = socket_dir.join(format!("{}.sock", Uuid::new_v4()));
+        let listener = UnixListener::bind(&socket_path)?;
+        let pid = builder
+            .with_console_socket(Some(&socket_path))
+            .validate_id()?
+            .as_tenant()
+            .with_detach(false)
+            .with_container_args(command)
+            .build();
+        let _ = fs::remove_file(&socket_path);
+        let pid = pid?;
+
+        let master = receive_pty_master(&listener)?;
+        return Ok(ExecProcess {
+            pid,
+            stdin: if stdin {
+                Some(master.try_clone()?)
+            } else {
+                None
+            },
+            stdout: master.try_clone()?,
+            stderr: None,
+            pty_master: Some(master),
+        });
+    }
+
+    let (stdin_read, stdin_write) = pipe2(OFlag::O_CLOEXEC)?;
+    let (stdout_read, stdout_write) = pipe2(OFlag::O_CLOEXEC)?;
+    let (stderr_read, stderr_write) = pipe2(OFlag::O_CLOEXEC)?;
+    let builder = if stdin {
+        builder.with_stdin(stdin_read)
+    } else {
+        builder.with_stdin(File::open("/dev/null")?)
+    };
+    let pid = builder
+        .with_stdout(stdout_write)
+        .with_stderr(stderr_write)
+        .validate_id()?
+        .as_tenant()
+        .with_detach(false)
+        .with_container_args(command)
+        .build()?;
+
+    Ok(ExecProcess {
+        pid,
+        stdin: if stdin {
+            Some(File::from(stdin_write))
+        } else {
+            None
+        },
+        stdout: File::from(stdout_read),
+        stderr: Some(File::from(stderr_read)),
+        pty_master: None,
+    })
+}
+
+fn receive_pty_master(listener: &UnixListener) -> Result<File> {
+    // The runtime has connected and sent the descriptor by the time the
+    // process is built, don't wait for a runtime that never does.
+    listener.set_nonblocking(true)?;
+    let (stream, _) = listener
+        .accept()
+        .context("the runtime did not connect to the console socket")?;
+
+    let mut buf = [0u8; 4096];
+    let mut iov = [IoSliceMut::new(&mut buf)];
+    let mut cmsg = nix::cmsg_space!([RawFd; 1]);
+    let msg = recvmsg::<()>(
+        stream.as_raw_fd(),
+        &mut iov,
+        Some(&mut cmsg),
+        MsgFlags::MSG_CMSG_CLOEXEC,
+    )?;
+    for cmsg in msg.cmsgs()? {
+        if let ControlMessageOwned::ScmRights(fds) = cmsg
+            && let Some(&fd) = fds.first()
+        {
+            // SAFETY: the descriptor was just received and nothing else owns it.
+            return Ok(unsafe { File::from_raw_fd(fd) });
+        }
+    }
+    bail!("no terminal received on the console socket")
+}
+
+pub(crate) fn resize(master: &File, cols: u16, rows: u16) -> io::Result<()> {
+    let size = libc::winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCSWINSZ only reads the winsize passed to it.
+    if unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Send everything read from `reader` as frames built by `frame`.
+async fn forward_output(
+    mut reader: impl AsyncRead + Unpin,
+    frame: fn(Vec<u8>) -> Frame,
+    tx: mpsc::Sender<Frame>,
+) {
+    let mut buf = vec![0u8; READ_BUFFER_SIZE];
+    loop {
+        // A terminal reports EIO instead of EOF once the process is gone.
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        if tx.send(frame(buf[..n].to_vec())).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Stream the output of a container from now on, until it stops running.
+///
+/// Containers on this node aren't started with stdin kept open, so attaching
+/// only follows what the container writes to its log.
+async fn attach(pod_name: &str, container: Option<&str>, tx: mpsc::Sender<Frame>) -> Result<()> {
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let pod_info = PodInfo::load(&root_path, pod_name)?;
+    let container_id = resolve_container(pod_name, &pod_info, container)?.clone();
+
+    let mut options = PodLogOptions {
+        container: Some(container_id.clone()),
+        since_time: Some(Utc::now()),
+        ..Default::default()
+    };
+    loop {
+        // Check first, so that the last lines are still sent.
+        let running = load_container(&root_path, &container_id)
+            .is_ok_and(|c| matches!(c.status(), ContainerStatus::Running));
+        let lines = standalone::pod_logs(pod_name, &options)?;
+        for line in &lines {
+            let mut data = line.content.clone().into_bytes();
+            if !line.partial {
+                data.push(b'\n');
+            }
+            let frame = match line.stream {
+                Stream::Stdout => Frame::Stdout(data),
+                Stream::Stderr => Frame::Stderr(data),
+            };
+            if tx.send(frame).await.is_err() {
+                return Ok(());
+            }
+        }
+        if !running {
+            break;
+        }
+        options = logs::follow_options(&options, &lines, Utc::now());
+        tokio::time::sleep(logs::FOLLOW_INTERVAL).await;
+    }
+    let _ = tx.send(Frame::Exit(0)).await;
+    Ok(())
+}
+
+/// Forward the session to `port` on the loopback interface of the pod.
+async fn port_forward(
+    pod_name: &str,
+    port: u16,
+    mut recv: RecvStream,
+    tx: mpsc::Sender<Frame>,
+) -> Result<()> {
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let pod_info = PodInfo::load(&root_path, pod_name)?;
+    let sandbox = load_container(&root_path, &pod_info.pod_sandbox_id)?;
+    let pid = sandbox
+        .pid()
+        .with_context(|| format!("sandbox of pod {pod_name} is not running"))?;
+
+    let stream = tokio::task::spawn_blocking(move || connect_in_netns(pid, port)).await??;
+    stream.set_nonblocking(true)?;
+    let (reader, mut writer) = tokio::net::TcpStream::from_std(stream)?.into_split();
+
+    let input = async move {
+        while let Some(frame) = recv.recv_frame().await? {
+            match frame {
+                Frame::Stdin(data) => writer.write_all(&data).await?,
+                Frame::CloseStdin => break,
+                other => warn!("[worker] unexpected port-forward frame {other:?}"),
+            }
+        }
+        writer.shutdown().await?;
+        anyhow::Ok(())
+    };
+    let output = forward_output(reader, Frame::Stdout, tx);
+    tokio::pin!(input, output);
+    // The client may keep its side open after the pod has closed the
+    // connection, so stop as soon as the output is done.
+    tokio::select! {
+        _ = &mut output => return Ok(()),
+        res = &mut input => res?,
+    }
+    output.await;
+    Ok(())
+}
+
+/// Checkpoint a pod for a migration and send the archive. The pod is removed
+/// from this node once the archive is written; should sending it fail, the
+/// archive is kept so the pod can still be restored by hand.
+async fn checkpoint(pod_name: &str, tcp_established: bool, tx: mpsc::Sender<Frame>) -> Result<()> {
+    let archive = checkpoint_archive(pod_name)?;
+    let options = CheckpointOptions {
+        tcp_established,
+        ..Default::default()
+    };
+    let (name, path) = (pod_name.to_string(), archive.clone());
+    pod_worker::forget_pod_restarts(pod_name);
+    metrics::forget_pod(pod_name);
+    tokio::task::spawn_blocking(move || checkpoint_pod(&name, &options, &path)).await??;
+    if let Some(pm) = PROBE_MANAGER.get() {
+        pm.remove_pod(pod_name).await;
+    }
+
+    let mut file = tokio::fs::File::open(&archive).await?;
+    let mut buf = vec![0u8; READ_BUFFER_SIZE];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        tx.send(Frame::Stdout(buf[..n].to_vec()))
+            .await
+            .with_context(|| {
+                format!("checkpoint of pod {pod_name} kept at {}", archive.display())
+            })?;
+    }
+    tx.send(Frame::Exit(0)).await?;
+    fs::remove_file(&archive)?;
+    Ok(())
+}
+
+/// Restore a pod from the archive sent by the client and answer with its IP.
+async fn restore(pod_name: &str, mut recv: RecvStream, tx: mpsc::Sender<Frame>) -> Result<()> {
+    let archive = checkpoint_archive(pod_name)?;
+    let mut file = tokio::fs::File::create(&archive).await?;
+    while let Some(frame) = recv.recv_frame().await? {
+        match frame {
+            Frame::Stdin(data) => file.write_all(&data).await?,
+            Frame::CloseStdin => break,
+            other => warn!("[worker] unexpected restore frame {other:?}"),
+        }
+    }
+    file.flush().await?;
+    drop(file);
+
+    let path = archive.clone();
+    let result = tokio::task::spawn_blocking(move || restore_pod(&path)).await?;
+    let _ = fs::remove_file(&archive);
+    let result = result?;
+    if let Some(pm) = PROBE_MANAGER.get()
+        && let Err(e) = pm.add_pod(&result.pod_task, &result.pod_ip).await
+    {
+        error!("[worker] failed to add probes for pod {pod_name}: {e}");
+    }
+
+    let pod_ip = result.pod_ip.split('/').next().unwrap_or(&result.pod_ip);
+    tx.send(Frame::Stdout(pod_ip.as_bytes().to_vec())).await?;
+    tx.send(Frame::Exit(0)).await?;
+    Ok(())
+}
+
+fn checkpoint_archive(pod_name: &str) -> Result<PathBuf> {
+    let root_path = rootpath::determine(None, &*create_syscall())?;
+    let dir = root_path.join("checkpoints");
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(format!("{pod_name}.tar.gz")))
+}
+
+fn connect_in_netns(pid: Pid, port: u16) -> Result<StdTcpStream> {
+    // setns only switches the calling thread, do it on a thread of its own.
+    // The socket stays in the pod's namespace after the thread is gone.
+    thread::spawn(move || {
+        let netns = File::open(format!("/proc/{pid}/ns/net"))?;
+        setns(netns, CloneFlags::CLONE_NEWNET)?;
+        StdTcpStream::connect((Ipv4Addr::LOCALHOST, port))
+            .with_context(|| format!("failed to connect to port {port} in the pod"))
+    })
+    .join()
+    .map_err(|_| anyhow!("port-forward thread panicked"))?
+}
@@ -10,6 +10,8 @@ use tokio::task::JoinHandle;
 use tokio::time::sleep;
 
 use crate::commands::pod;
+use crate::daemon::metrics;
+use crate::daemon::pod_worker::RESTART_BACKOFF;
 use crate::daemon::status::probe::probe_manager::PROBE_MANAGER;
 use crate::task::TaskRunner;
 use common::PodTask;
@@ -125,6 +127,8 @@ async fn stop_removed_pods(state: Arc<State>, pods: &Vec<PodTask>) -> Result<(),
             continue;
         }
 
+        RESTART_BACKOFF.forget_pod(pod.metadata.uid);
+        metrics::forget_pod(&pod.metadata.name);
         match pod::standalone::delete_pod(&pod.metadata.name) {
             Ok(_) => {
                 // spawn deregistration but keep the JoinHandle so we can observe failures
@@ -0,0 +1,572 @@
+//! Resource usage of the node and of the containers of its pods.
+//!
+//! Containers are accounted from their cgroup v2: `cpu.stat`,
+//! `memory.current`, `io.stat` and `pids.current`, found through the cgroup
+//! of the container's init process. Pods also report the traffic of their
+//! network namespace, read from `/proc/<pause pid>/net/dev`. CPU usage is a
+//! counter, so [`StatsCollector`] keeps the previous sample to turn it into
+//! millicores.
+//!
+//! The daemon collects every [`STATS_CONFIG`] interval, reports the result
+//! to rks with the heartbeats for `rkl top`, and serves it as Prometheus
+//! metrics.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::{Context, Result, anyhow};
+use chrono::Utc;
+use common::{ContainerStats, NetworkStats, NodeStats, PodStats};
+use libcontainer::syscall::syscall::create_syscall;
+use libruntime::rootpath;
+use procfs::{Current, Meminfo};
+use tokio::sync::OnceCell;
+use tracing::{debug, error};
+
+use crate::commands::load_container;
+use crate::commands::pod::PodInfo;
+use crate::config::STATS_CONFIG;
+use crate::daemon::allocatable;
+
+/// Global singleton [`StatsManager`], initialized once by the daemon.
+pub static STATS_MANAGER: OnceCell<Arc<StatsManager>> = OnceCell::const_new();
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+/// Key of the node's own CPU sample.
+const NODE_SAMPLE: &str = "";
+
+/// Collects resource usage, turning CPU time into millicores against the
+/// previous collection.
+#[derive(Default)]
+pub struct StatsCollector {
+    /// CPU time in microseconds and when it was read, keyed by container
+    /// name.
+    cpu_samples: HashMap<String, (u64, Instant)>,
+}
+
+impl StatsCollector {
+    /// Usage of the node and of the pods under `root_path`.
+    pub fn collect(&mut self, root_path: &Path) -> Result<NodeStats> {
+        let now = Instant::now();
+        let mut samples = HashMap::new();
+
+        let cpu_usage_usec = node_cpu_usage_usec()?;
+        let cpu_millicores = self.millicores(NODE_SAMPLE, cpu_usage_usec, now);
+        samples.insert(NODE_SAMPLE.to_string(), (cpu_usage_usec, now));
+
+        let meminfo = Meminfo::current().context("failed to read /proc/meminfo")?;
+        let memory_available = meminfo.mem_available.unwrap_or(meminfo.mem_free);
+        let capacity = allocatable::node_resources()
+            .map(|(capacity, _)| capacity)
+            .unwrap_or_default();
+
+        let mut pods = Vec::new();
+        for pod_name in pod_names(root_path) {
+            let pod_info = match PodInfo::load(root_path, &pod_name) {
+                Ok(pod_info) => pod_info,
+                Err(e) => {
+                    debug!("[stats] skipping pod {pod_name}: {e}");
+                    continue;
+                }
+            };
+            let mut pod = pod_stats(root_path, &pod_name, &pod_info);
+            for container in &mut pod.containers {
+                container.cpu_millicores =
+                    self.millicores(&container.name, container.cpu_usage_usec, now);
+                samples.insert(container.name.clone(), (container.cpu_usage_usec, now));
+            }
+            pods.push(pod);
+        }
+        // Containers which are gone no longer need a sample.
+        self.cpu_samples = samples;
+
+        Ok(NodeStats {
+            node_name: String::new(),
+            timestamp: Some(Utc::now()),
+            cpu_usage_usec,
+            cpu_millicores,
+            cpu_capacity: capacity.cpu,
+            memory_bytes: meminfo.mem_total.saturating_sub(memory_available),
+            memory_capacity: capacity.memory,
+            pods,
+        })
+    }
+
+    fn millicores(&self, key: &str, usage_usec: u64, now: Instant) -> u64 {
+        let Some((previous_usec, at)) = self.cpu_samples.get(key) else {
+            return 0;
+        };
+        let elapsed_usec = now.duration_since(*at).as_micros() as u64;
+        cpu_millicores(*previous_usec, usage_usec, elapsed_usec)
+    }
+}
+
+/// Collects usage periodically in the daemon and keeps the latest result.
+pub struct StatsManager {
+    collector: Mutex<StatsCollector>,
+    latest: Mutex<Option<NodeStats>>,
+}
+
+impl StatsManager {
+    pub fn new() -> Self {
+        StatsManager {
+            collector: Mutex::new(StatsCollector::default()),
+            latest: Mutex::new(None),
+        }
+    }
+
+    /// Start collecting every [`STATS_CONFIG`] interval.
+    pub fn run(self: &Arc<Self>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(STATS_CONFIG.interval);
+            loop {
+                ticker.tick().await;
+                let collecting = manager.clone();
+                match tokio::task::spawn_blocking(move || collecting.collect()).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => error!("[stats] collection failed: {e:#}"),
+                    Err(e) => error!("[stats] collection panicked: {e}"),
+                }
+            }
+        });
+    }
+
+    fn collect(&self) -> Result<()> {
+        let root_path = rootpath::determine(None, &*create_syscall())?;
+        let stats = self.collector.lock().unwrap().collect(&root_path)?;
+        *self.latest.lock().unwrap() = Some(stats);
+        Ok(())
+    }
+
+    /// The latest usage collected, if any.
+    pub fn latest(&self) -> Option<NodeStats> {
+        self.latest.lock().unwrap().clone()
+    }
+}
+
+impl Default for StatsManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Millicores used between two readings of a CPU time counter taken
+/// `elapsed_usec` apart.
+fn cpu_millicores(previous_usec: u64, usage_usec: u64, elapsed_usec: u64) -> u64 {
+    if elapsed_usec == 0 {
+        return 0;
+    }
+    usage_usec.saturating_sub(previous_usec) * 1000 / elapsed_usec
+}
+
+fn pod_names(root_path: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(root_path.join("pods")) else {
+        return Vec::new();
+    };
+    let mut names = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+fn pod_stats(root_path: &Path, pod_name: &str, pod_info: &PodInfo) -> PodStats {
+    let containers = pod_info
+        .container_names
+        .iter()
+        .chain(&pod_info.ephemeral_containers)
+        .filter_map(|name| match container_stats(root_path, name) {
+            Ok(stats) => Some(stats),
+            Err(e) => {
+                debug!("[stats] no stats for container {name}: {e:#}");
+                None
+            }
+        })
+        .collect();
+    PodStats {
+        name: pod_name.to_string(),
+        namespace: pod_info
+            .pod_spec
+            .as_ref()
+            .map(|pod| pod.metadata.namespace.clone())
+            .unwrap_or_else(|| "default".to_string()),
+        containers,
+        network: pod_network(root_path, pod_info),
+    }
+}
+
+/// Traffic of the pod's network namespace, none if its sandbox is gone.
+pub(crate) fn pod_network(root_path: &Path, pod_info: &PodInfo) -> NetworkStats {
+    pod_info
+        .get_pod_sandbox(root_path)
+        .ok()
+        .and_then(|sandbox| sandbox.pid())
+        .and_then(|pid| fs::read_to_string(format!("/proc/{pid}/net/dev")).ok())
+        .map(|net_dev| parse_net_dev(&net_dev))
+        .unwrap_or_default()
+}
+
+/// Usage of a running container, read from its cgroup. CPU usage in
+/// millicores is left to [`StatsCollector`].
+pub(crate) fn container_stats(root_path: &Path, container_id: &str) -> Result<ContainerStats> {
+    let pid = load_container(root_path, container_id)?
+        .pid()
+        .ok_or_else(|| anyhow!("container is not running"))?;
+    let cgroup = process_cgroup(pid.as_raw())?;
+    let read = |file: &str| {
+        fs::read_to_string(cgroup.join(file))
+            .with_context(|| format!("failed to read {}", cgroup.join(file).display()))
+    };
+    let (io_read_bytes, io_write_bytes) = read("io.stat")
+        .map(|io_stat| parse_io_stat(&io_stat))
+        .unwrap_or_default();
+    Ok(ContainerStats {
+        name: container_id.to_string(),
+        cpu_usage_usec: parse_cpu_stat(&read("cpu.stat")?)
+            .ok_or_else(|| anyhow!("no usage_usec in cpu.stat"))?,
+        cpu_millicores: 0,
+        memory_bytes: read("memory.current")?.trim().parse()?,
+        io_read_bytes,
+        io_write_bytes,
+        pids: read("pids.current")
+            .ok()
+            .and_then(|pids| pids.trim().parse().ok())
+            .unwrap_or(0),
+    })
+}
+
+/// The cgroup v2 directory the process `pid` runs in.
+fn process_cgroup(pid: i32) -> Result<PathBuf> {
+    let cgroup = fs::read_to_string(format!("/proc/{pid}/cgroup"))
+        .with_context(|| format!("failed to read the cgroup of process {pid}"))?;
+    parse_proc_cgroup(&cgroup)
+        .map(|path| Path::new(CGROUP_ROOT).join(path))
+        .ok_or_else(|| anyhow!("process {pid} is not in a cgroup v2"))
+}
+
+/// The cgroup v2 path of a `/proc/<pid>/cgroup` file, relative to the
+/// cgroup root.
+fn parse_proc_cgroup(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(|path| path.trim_start_matches('/'))
+}
+
+/// `usage_usec` of a `cpu.stat` file.
+fn parse_cpu_stat(content: &str) -> Option<u64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("usage_usec "))
+        .and_then(|usage| usage.trim().parse().ok())
+}
+
+/// Bytes read and written on all devices, from an `io.stat` file.
+fn parse_io_stat(content: &str) -> (u64, u64) {
+    let mut read = 0;
+    let mut written = 0;
+    for field in content.split_whitespace() {
+        if let Some(bytes) = field.strip_prefix("rbytes=") {
+            read += bytes.parse::<u64>().unwrap_or(0);
+        } else if let Some(bytes) = field.strip_prefix("wbytes=") {
+            written += bytes.parse::<u64>().unwrap_or(0);
+        }
+    }
+    (read, written)
+}
+
+/// Traffic of all interfaces but loopback, from a `/proc/net/dev` file.
+fn parse_net_dev(content: &str) -> NetworkStats {
+    let mut stats = NetworkStats::default();
+    for line in content.lines().skip(2) {
+        let Some((iface, counters)) = line.split_once(':') else {
+            continue;
+        };
+        if iface.trim() == "lo" {
+            continue;
+        }
+        let counters = counters
+            .split_whitespace()
+            .map(|c| c.parse::<u64>().unwrap_or(0))
+            .collect::<Vec<_>>();
+        if counters.len() < 10 {
+            continue;
+        }
+        stats.rx_bytes += counters[0];
+        stats.rx_packets += counters[1];
+        stats.tx_bytes += counters[8];
+        stats.tx_packets += counters[9];
+    }
+    stats
+}
+
+/// CPU time the node spent on anything but idling since boot, from
+/// `/proc/stat`.
+fn node_cpu_usage_usec() -> Result<u64> {
+    let stat = fs::read_to_string("/proc/stat").context("failed to read /proc/stat")?;
+    let ticks = parse_proc_stat(&stat).ok_or_else(|| anyhow!("no cpu line in /proc/stat"))?;
+    Ok(ticks * 1_000_000 / procfs::ticks_per_second())
+}
+
+/// Ticks spent in user, nice, system, irq, softirq and steal time, from the
+/// `cpu` line of `/proc/stat`.
+fn parse_proc_stat(content: &str) -> Option<u64> {
+    let fields = content
+        .lines()
+        .find_map(|line| line.strip_prefix("cpu "))?
+        .split_whitespace()
+        .map(|field| field.parse::<u64>().unwrap_or(0))
+        .collect::<Vec<_>>();
+    // user nice system idle iowait irq softirq steal
+    (!fields.is_empty()).then(|| {
+        [0, 1, 2, 5, 6, 7]
+            .iter()
+            .map(|&i| fields.get(i).copied().unwrap_or(0))
+            .sum()
+    })
+}
+
+/// `stats` in the Prometheus text format.
+pub fn render_metrics(stats: &NodeStats) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, f64)>| {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        for (labels, value) in samples {
+            if labels.is_empty() {
+                let _ = writeln!(out, "{name} {value}");
+            } else {
+                let _ = writeln!(out, "{name}{{{labels}}} {value}");
+            }
+        }
+    };
+
+    metric(
+        "node_cpu_usage_seconds_total",
+        "counter",
+        "Cumulative CPU time used by the node in seconds.",
+        vec![(String::new(), seconds(stats.cpu_usage_usec))],
+    );
+    metric(
+        "node_memory_usage_bytes",
+        "gauge",
+        "Memory used by the node in bytes.",
+        vec![(String::new(), stats.memory_bytes as f64)],
+    );
+
+    let containers = stats
+        .pods
+        .iter()
+        .flat_map(|pod| {
+            pod.containers.iter().map(move |container| {
+                let name = container
+                    .name
+                    .strip_prefix(&format!("{}-", pod.name))
+                    .unwrap_or(&container.name);
+                (
+                    format!(
+                        "container=\"{}\",pod=\"{}\",namespace=\"{}\"",
+                        escape_label(name),
+                        escape_label(&pod.name),
+                        escape_label(&pod.namespace)
+                    ),
+                    container,
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    let container_metrics: [(&str, &str, &str, fn(&ContainerStats) -> f64); 5] = [
+        (
+            "container_cpu_usage_seconds_total",
+            "counter",
+            "Cumulative CPU time used by the container in seconds.",
+            |c| seconds(c.cpu_usage_usec),
+        ),
+        (
+            "container_memory_usage_bytes",
+            "gauge",
+            "Memory used by the container in bytes.",
+            |c| c.memory_bytes as f64,
+        ),
+        (
+            "container_fs_reads_bytes_total",
+            "counter",
+            "Cumulative bytes read by the container.",
+            |c| c.io_read_bytes as f64,
+        ),
+        (
+            "container_fs_writes_bytes_total",
+            "counter",
+            "Cumulative bytes written by the container.",
+            |c| c.io_write_bytes as f64,
+        ),
+        (
+            "container_processes",
+            "gauge",
+            "Number of processes in the container.",
+            |c| c.pids as f64,
+        ),
+    ];
+    for (name, kind, help, value) in container_metrics {
+        let samples = containers
+            .iter()
+            .map(|(labels, container)| (labels.clone(), value(container)))
+            .collect();
+        metric(name, kind, help, samples);
+    }
+
+    let pods = stats
+        .pods
+        .iter()
+        .map(|pod| {
+            (
+                format!(
+                    "pod=\"{}\",namespace=\"{}\"",
+                    escape_label(&pod.name),
+                    escape_label(&pod.namespace)
+                ),
+                &pod.network,
+            )
+        })
+        .collect::<Vec<_>>();
+    let network_metrics: [(&str, &str, fn(&NetworkStats) -> f64); 4] = [
+        (
+            "pod_network_receive_bytes_total",
+            "Cumulative bytes received by the pod.",
+            |n| n.rx_bytes as f64,
+        ),
+        (
+            "pod_network_transmit_bytes_total",
+            "Cumulative bytes sent by the pod.",
+            |n| n.tx_bytes as f64,
+        ),
+        (
+            "pod_network_receive_packets_total",
+            "Cumulative packets received by the pod.",
+            |n| n.rx_packets as f64,
+        ),
+        (
+            "pod_network_transmit_packets_total",
+            "Cumulative packets sent by the pod.",
+            |n| n.tx_packets as f64,
+        ),
+    ];
+    for (name, help, value) in network_metrics {
+        let samples = pods
+            .iter()
+            .map(|(labels, network)| (labels.clone(), value(network)))
+            .collect();
+        metric(name, "counter", help, samples);
+    }
+    out
+}
+
+fn seconds(usec: u64) -> f64 {
+    usec as f64 / 1_000_000.0
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cgroup_files() {
+        assert_eq!(
+            parse_proc_cgroup("0::/rkl-pods/web-app\n"),
+            Some("rkl-pods/web-app")
+        );
+        assert_eq!(parse_proc_cgroup("12:pids:/docker/abc\n"), None);
+
+        let cpu_stat = "usage_usec 250000\nuser_usec 200000\nsystem_usec 50000\n";
+        assert_eq!(parse_cpu_stat(cpu_stat), Some(250000));
+
+        let io_stat = "8:0 rbytes=4096 wbytes=1024 rios=1 wios=1 dbytes=0 dios=0\n\
+                       8:16 rbytes=100 wbytes=0 rios=1 wios=0 dbytes=0 dios=0\n";
+        assert_eq!(parse_io_stat(io_stat), (4196, 1024));
+        assert_eq!(parse_io_stat(""), (0, 0));
+    }
+
+    #[test]
+    fn test_parse_net_dev_skips_loopback() {
+        let net_dev = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:    1000      10    0    0    0     0          0         0     1000      10    0    0    0     0       0          0
+  eth0:    5000      50    0    0    0     0          0         0     3000      30    0    0    0     0       0          0
+";
+        assert_eq!(
+            parse_net_dev(net_dev),
+            NetworkStats {
+                rx_bytes: 5000,
+                tx_bytes: 3000,
+                rx_packets: 50,
+                tx_packets: 30,
+            }
+        );
+    }
+
+    #[test]
+    fn test_cpu_usage() {
+        // user nice system idle iowait irq softirq steal
+        let stat = "cpu  100 5 50 1000 20 3 2 1 0 0\ncpu0 100 5 50 1000 20 3 2 1 0 0\n";
+        assert_eq!(parse_proc_stat(stat), Some(161));
+
+        // Half a core over one second
+        assert_eq!(cpu_millicores(1_000_000, 1_500_000, 1_000_000), 500);
+        // A restarted container's counter goes back
+        assert_eq!(cpu_millicores(1_500_000, 1_000, 1_000_000), 0);
+        assert_eq!(cpu_millicores(0, 1_000, 0), 0);
+    }
+
+    #[test]
+    fn test_render_metrics() {
+        let stats = NodeStats {
+            node_name: "node-1".to_string(),
+            cpu_usage_usec: 3_500_000,
+            memory_bytes: 1024,
+            pods: vec![PodStats {
+                name: "web".to_string(),
+                namespace: "default".to_string(),
+                containers: vec![ContainerStats {
+                    name: "web-app".to_string(),
+                    cpu_usage_usec: 2_000_000,
+                    memory_bytes: 512,
+                    pids: 3,
+                    ..Default::default()
+                }],
+                network: NetworkStats {
+                    rx_bytes: 10,
+                    ..Default::default()
+                },
+            }],
+            ..Default::default()
+        };
+        let metrics = render_metrics(&stats);
+        assert!(metrics.contains("# TYPE node_cpu_usage_seconds_total counter\n"));
+        assert!(metrics.contains("node_cpu_usage_seconds_total 3.5\n"));
+        assert!(metrics.contains(
+            "container_memory_usage_bytes{container=\"app\",pod=\"web\",namespace=\"default\"} 512\n"
+        ));
+        assert!(metrics.contains(
+            "container_processes{container=\"app\",pod=\"web\",namespace=\"default\"} 3\n"
+        ));
+        assert!(
+            metrics.contains(
+                "pod_network_receive_bytes_total{pod=\"web\",namespace=\"default\"} 10\n"
+            )
+        );
+    }
+}
@@ -19,7 +19,11 @@
 //! shorter interval increases responsiveness at the cost of higher CPU and
 //! network usage.
 
-use std::{collections::HashSet, sync::Arc, time::Duration};
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{anyhow, bail};
 use dashmap::DashMap;
@@ -30,7 +34,10 @@ use uuid::Uuid;
 
 use crate::{
     commands::pod::TLSConnectionArgs,
-    daemon::status::pod::{Pod, get_pods},
+    daemon::{
+        metrics::PLEG_RELIST_DURATION,
+        status::pod::{Pod, get_pods},
+    },
 };
 
 #[derive(Debug, Clone)]
@@ -100,7 +107,7 @@ enum ContainerState {
 ///
 /// Consumed by [`super::super::pod_worker::PodWorker`] to update the cached
 /// [`common::PodStatus`] and trigger restart / probe logic.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PodLifecycleEvent {
     /// UID of the pod this event belongs to.
     pub pod_uid: Uuid,
@@ -176,7 +183,10 @@ impl PLEG {
                     }
                     _ = tokio::time::sleep(state.relist_duration) => {
                         // Perform relist operation
-                        match relist(&mut state).await {
+                        let start = Instant::now();
+                        let relisted = relist(&mut state).await;
+                        PLEG_RELIST_DURATION.observe_since(&[], start);
+                        match relisted {
                             Ok(events) => {
                                 let Some(event_tx) = state.event_tx.as_ref() else {
                                     tracing::warn!("[pleg] event channel is not available, stopping relist loop");
@@ -8,7 +8,9 @@
 use std::{sync::Arc, time::Duration};
 
 use anyhow::anyhow;
-use common::{ExecAction, HttpGetAction, PodTask, ProbeAction, RksMessage, TcpSocketAction};
+use common::{
+    ExecAction, GrpcAction, HttpGetAction, PodTask, ProbeAction, RksMessage, TcpSocketAction,
+};
 use dashmap::DashMap;
 use libcontainer::syscall::syscall::create_syscall;
 use libruntime::rootpath;
@@ -18,8 +20,9 @@ use uuid::Uuid;
 
 use crate::{
     commands::pod::{PodInfo, TLSConnectionArgs},
+    daemon::metrics::PROBE_RESULTS,
     daemon::status::probe::prober::{
-        ExecProber, HttpGetProber, ProbeConfig, Prober, TcpSocketProber,
+        ExecProber, GrpcProber, HttpGetProber, ProbeConfig, Prober, TcpSocketProber,
     },
     quic::client::{Cli, QUICClient},
 };
@@ -38,6 +41,17 @@ pub enum ProbeClass {
     Startup,
 }
 
+impl ProbeClass {
+    /// The `probe_type` label of the probe metrics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProbeClass::Liveness => "liveness",
+            ProbeClass::Readiness => "readiness",
+            ProbeClass::Startup => "startup",
+        }
+    }
+}
+
 /// Outcome of a single probe execution.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum ProbeResultType {
@@ -262,6 +276,7 @@ impl ProbeManager {
                 let mut worker = ProbeWorker::new(
                     results_manager,
                     prober,
+                    probe_class.clone(),
                     pod.metadata.uid,
                     container_name.clone(),
                 );
@@ -360,6 +375,12 @@ fn create_prober_from_spec(
                     config.clone(),
                 )))
             }
+            ProbeAction::Grpc(GrpcAction { port, service }) => Some(Arc::new(GrpcProber::new(
+                pod_ip.to_string(),
+                *port,
+                service.clone().unwrap_or_default(),
+                config.clone(),
+            ))),
         }
     } else {
         debug!(
@@ -376,6 +397,7 @@ fn create_prober_from_spec(
 pub struct ProbeWorker {
     results_manager: Arc<ProbeResultManager>,
     prober: Arc<dyn Prober + Send + Sync>,
+    probe_class: ProbeClass,
     pod_id: String,
     container_id: String,
     handle: Option<tokio::task::JoinHandle<()>>,
@@ -396,12 +418,14 @@ impl ProbeWorker {
     pub fn new(
         results_manager: Arc<ProbeResultManager>,
         prober: Arc<dyn Prober + Send + Sync>,
+        probe_class: ProbeClass,
         pod_id: Uuid,
         container_id: String,
     ) -> Self {
         Self {
             results_manager,
             prober,
+            probe_class,
             pod_id: pod_id.to_string(),
             container_id,
             handle: None,
@@ -427,6 +451,7 @@ impl ProbeWorker {
         let results_manager = self.results_manager.clone();
         let prober = self.prober.clone();
         let config = prober.config().clone();
+        let probe_type = self.probe_class.as_str();
         let pod_id = self.pod_id.clone();
         let container_id = self.container_id.clone();
         let (stop_tx, mut stop_rx) = tokio::sync::oneshot::channel();
@@ -464,6 +489,7 @@ impl ProbeWorker {
                     _ = interval.tick() => {
                         match tokio::time::timeout(config.timeout, prober.probe()).await {
                             Ok(Ok(())) => {
+                                PROBE_RESULTS.inc(&[probe_type, "success"]);
                                 success_count = success_count.saturating_add(1);
                                 failure_count = 0;
                                 if success_count >= config.success_threshold {
@@ -480,6 +506,7 @@ impl ProbeWorker {
                                 }
                             }
                             Ok(Err(e)) => {
+                                PROBE_RESULTS.inc(&[probe_type, "failure"]);
                                 failure_count = failure_count.saturating_add(1);
                                 success_count = 0;
                                 if failure_count >= config.failure_threshold {
@@ -497,6 +524,7 @@ impl ProbeWorker {
                                 tracing::warn!(error = %e, "[Probe] probe failed");
                             }
                             Err(_) => {
+                                PROBE_RESULTS.inc(&[probe_type, "timeout"]);
                                 failure_count = failure_count.saturating_add(1);
                                 success_count = 0;
                                 if failure_count >= config.failure_threshold {
@@ -700,6 +728,9 @@ mod tests {
                         volume_mounts: None,
                         command: None,
                         working_dir: None,
+                        lifecycle: None,
+                        restart_policy: None,
+                        image_pull_policy: None,
                     },
                     ContainerSpec {
                         name: "sidecar".to_string(),
@@ -715,12 +746,22 @@ mod tests {
                         volume_mounts: None,
                         command: None,
                         working_dir: None,
+                        lifecycle: None,
+                        restart_policy: None,
+                        image_pull_policy: None,
                     },
                 ],
                 init_containers: vec![],
                 tolerations: vec![],
                 affinity: None,
                 restart_policy: RestartPolicy::Always,
+                scheduler_name: None,
+                termination_grace_period_seconds: None,
+                host_users: None,
+                priority: None,
+                volumes: vec![],
+                image_pull_secrets: vec![],
+                ephemeral_containers: vec![],
             },
             status: PodStatus::default(),
         }
@@ -833,7 +874,13 @@ mod tests {
             success: true,
             config: test_probe_config(),
         });
-        let mut worker = ProbeWorker::new(manager, prober, Uuid::nil(), "c1".to_string());
+        let mut worker = ProbeWorker::new(
+            manager,
+            prober,
+            ProbeClass::Liveness,
+            Uuid::nil(),
+            "c1".to_string(),
+        );
 
         worker.run().await;
         let result = timeout(Duration::from_millis(200), rx.recv())
@@ -854,7 +901,13 @@ mod tests {
             success: false,
             config: test_probe_config(),
         });
-        let mut worker = ProbeWorker::new(manager, prober, Uuid::nil(), "c2".to_string());
+        let mut worker = ProbeWorker::new(
+            manager,
+            prober,
+            ProbeClass::Readiness,
+            Uuid::nil(),
+            "c2".to_string(),
+        );
 
         worker.run().await;
         let result = timeout(Duration::from_millis(200), rx.recv())
@@ -953,6 +1006,33 @@ mod tests {
         assert_eq!(prober.port(), 9090);
     }
 
+    #[test]
+    fn create_prober_from_spec_builds_grpc_prober() {
+        let probe = Probe {
+            action: Some(ProbeAction::Grpc(GrpcAction {
+                port: 50051,
+                service: Some("echo".to_string()),
+            })),
+            ..Default::default()
+        };
+        let prober = create_prober_from_spec(
+            &probe,
+            Uuid::new_v4(),
+            "pod".to_string(),
+            "app".to_string(),
+            "127.0.0.1",
+        )
+        .expect("prober");
+        assert!(matches!(prober.probe_kind(), ProbeKind::Grpc));
+        let prober = prober
+            .as_any()
+            .downcast_ref::<GrpcProber>()
+            .expect("grpc prober");
+        assert_eq!(prober.host(), "127.0.0.1");
+        assert_eq!(prober.port(), 50051);
+        assert_eq!(prober.service(), "echo");
+    }
+
     #[test]
     fn create_prober_from_spec_clamps_zero_period_to_one_second() {
         let probe = Probe {
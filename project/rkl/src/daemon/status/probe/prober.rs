@@ -1,10 +1,11 @@
 //! Container health probe implementations.
 //!
-//! This module defines the [`Prober`] trait and three concrete implementations for container
+//! This module defines the [`Prober`] trait and four concrete implementations for container
 //! health checking. Supported probe types are:
 //! - Exec: runs a command inside the container
 //! - HTTP GET: sends an HTTP request to the container
 //! - TCP socket: attempts a TCP connection to the container
+//! - gRPC: calls the standard `grpc.health.v1.Health/Check` of the container
 //!
 //! Each prober carries a [`ProbeConfig`] that controls timing (initial delay, period, timeout)
 //! and thresholds (success and failure counts).
@@ -17,13 +18,14 @@ use tokio::{
     io::{AsyncBufReadExt as _, AsyncReadExt as _, AsyncWriteExt as _},
     net::TcpStream,
 };
+use tonic::{codec::ProstCodec, codegen::http::uri::PathAndQuery, transport::Endpoint};
 use uuid::Uuid;
 
 use crate::commands::{Exec, exec, pod::PodInfo};
 
 /// Interface for container health probes.
 ///
-/// Implementers define the probe mechanism (exec, HTTP, or TCP) and execute the probe
+/// Implementers define the probe mechanism (exec, HTTP, TCP, or gRPC) and execute the probe
 /// when `probe()` is called.
 #[async_trait::async_trait]
 #[allow(unused)]
@@ -34,7 +36,7 @@ pub trait Prober: Any {
     /// Timeout behavior is applied by the caller.
     async fn probe(&self) -> anyhow::Result<()>;
 
-    /// Returns the kind of probe (Exec, HttpGet, or TcpSocket).
+    /// Returns the kind of probe (Exec, HttpGet, TcpSocket, or Grpc).
     fn probe_kind(&self) -> ProbeKind;
 
     /// Returns the probe's configuration.
@@ -90,6 +92,8 @@ pub enum ProbeKind {
     HttpGet,
     /// Attempts a TCP connection to the container.
     TcpSocket,
+    /// Calls the gRPC health service of the container.
+    Grpc,
 }
 
 /// Runs a command inside the container via the runtime exec API.
@@ -113,6 +117,14 @@ pub struct TcpSocketProber {
     config: ProbeConfig,
 }
 
+/// Calls `grpc.health.v1.Health/Check` of the container.
+pub struct GrpcProber {
+    host: String,
+    port: u16,
+    service: String,
+    config: ProbeConfig,
+}
+
 impl ExecProber {
     /// Creates a new exec prober with the given command and config.
     pub fn new(command: Vec<String>, config: ProbeConfig) -> Self {
@@ -356,6 +368,119 @@ impl Prober for TcpSocketProber {
     }
 }
 
+#[allow(unused)]
+impl GrpcProber {
+    /// Creates a new gRPC health prober. An empty `service` checks the
+    /// server as a whole.
+    pub fn new(host: String, port: u16, service: String, config: ProbeConfig) -> Self {
+        Self {
+            host,
+            port,
+            service,
+            config,
+        }
+    }
+
+    /// Returns the target host.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Returns the target port.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns the checked service.
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+}
+
+#[async_trait::async_trait]
+impl Prober for GrpcProber {
+    async fn probe(&self) -> anyhow::Result<()> {
+        grpc_health_check(&self.host, self.port, &self.service).await
+    }
+
+    fn probe_kind(&self) -> ProbeKind {
+        ProbeKind::Grpc
+    }
+
+    fn config(&self) -> &ProbeConfig {
+        &self.config
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// `grpc.health.v1.HealthCheckRequest`
+#[derive(Clone, PartialEq, prost::Message)]
+struct HealthCheckRequest {
+    #[prost(string, tag = "1")]
+    service: String,
+}
+
+/// `grpc.health.v1.HealthCheckResponse`
+#[derive(Clone, PartialEq, prost::Message)]
+struct HealthCheckResponse {
+    #[prost(enumeration = "ServingStatus", tag = "1")]
+    status: i32,
+}
+
+/// `grpc.health.v1.HealthCheckResponse.ServingStatus`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+enum ServingStatus {
+    Unknown = 0,
+    Serving = 1,
+    NotServing = 2,
+    ServiceUnknown = 3,
+}
+
+const HEALTH_CHECK_PATH: &str = "/grpc.health.v1.Health/Check";
+
+/// Calls `grpc.health.v1.Health/Check` on `host:port`. Only a `SERVING`
+/// response passes, the timeout is applied by the caller like for the other
+/// probes.
+pub(crate) async fn grpc_health_check(host: &str, port: u16, service: &str) -> anyhow::Result<()> {
+    let channel = Endpoint::from_shared(format!("http://{host}:{port}"))?
+        .connect()
+        .await
+        .map_err(|e| anyhow::anyhow!("grpc probe could not connect to {host}:{port}: {e}"))?;
+    let mut client = tonic::client::Grpc::new(channel);
+    client
+        .ready()
+        .await
+        .map_err(|e| anyhow::anyhow!("grpc probe connection to {host}:{port} not ready: {e}"))?;
+
+    let request = tonic::Request::new(HealthCheckRequest {
+        service: service.to_string(),
+    });
+    let response: tonic::Response<HealthCheckResponse> = client
+        .unary(
+            request,
+            PathAndQuery::from_static(HEALTH_CHECK_PATH),
+            ProstCodec::default(),
+        )
+        .await
+        .map_err(|status| anyhow::anyhow!("grpc probe health check failed: {status}"))?;
+
+    serving_status_result(response.into_inner().status)
+}
+
+fn serving_status_result(status: i32) -> anyhow::Result<()> {
+    let status = match ServingStatus::try_from(status) {
+        Ok(ServingStatus::Serving) => return Ok(()),
+        Ok(ServingStatus::NotServing) => "NOT_SERVING",
+        Ok(ServingStatus::ServiceUnknown) => "SERVICE_UNKNOWN",
+        Ok(ServingStatus::Unknown) | Err(_) => "UNKNOWN",
+    };
+    Err(anyhow::anyhow!("grpc probe service is {status}"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -486,6 +611,44 @@ mod tests {
         assert!(matches!(prober.probe_kind(), ProbeKind::HttpGet));
     }
 
+    #[test]
+    fn grpc_prober_stores_fields() {
+        let prober = GrpcProber::new(
+            "127.0.0.1".to_string(),
+            50051,
+            "echo".to_string(),
+            ProbeConfig::default(),
+        );
+        assert_eq!(prober.host, "127.0.0.1");
+        assert_eq!(prober.port, 50051);
+        assert_eq!(prober.service, "echo");
+        assert!(matches!(prober.probe_kind(), ProbeKind::Grpc));
+    }
+
+    #[test]
+    fn grpc_serving_status_maps_to_result() {
+        assert!(serving_status_result(ServingStatus::Serving as i32).is_ok());
+        assert!(serving_status_result(ServingStatus::NotServing as i32).is_err());
+        assert!(serving_status_result(ServingStatus::Unknown as i32).is_err());
+        assert!(serving_status_result(ServingStatus::ServiceUnknown as i32).is_err());
+        assert!(serving_status_result(42).is_err());
+    }
+
+    #[tokio::test]
+    async fn grpc_prober_fails_without_server() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let port = listener.local_addr().expect("local addr").port();
+        drop(listener);
+
+        let prober = GrpcProber::new(
+            "127.0.0.1".to_string(),
+            port,
+            String::new(),
+            ProbeConfig::default(),
+        );
+        assert!(prober.probe().await.is_err());
+    }
+
     #[test]
     fn tcp_socket_prober_stores_fields() {
         let prober = TcpSocketProber::new("127.0.0.1".to_string(), 9090, ProbeConfig::default());
@@ -1381,6 +1381,9 @@ mod tests {
             volume_mounts: None,
             command: None,
             working_dir: None,
+            lifecycle: None,
+            restart_policy: None,
+            image_pull_policy: None,
         }
     }
 
@@ -1403,6 +1406,13 @@ mod tests {
                 tolerations: Vec::new(),
                 affinity: None,
                 restart_policy,
+                scheduler_name: None,
+                termination_grace_period_seconds: None,
+                host_users: None,
+                priority: None,
+                volumes: vec![],
+                image_pull_secrets: vec![],
+                ephemeral_containers: vec![],
             },
             status: PodStatus::default(),
         }
@@ -1,6 +1,7 @@
 pub mod commands;
 pub mod config;
 pub mod daemon;
+pub mod logs;
 pub mod network;
 mod quic;
 pub mod task;
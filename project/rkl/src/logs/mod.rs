@@ -0,0 +1,427 @@
+//! Container logs in the CRI format.
+//!
+//! Every line is `<timestamp> <stream> <tag> <content>`: an RFC 3339
+//! timestamp with nanoseconds, `stdout` or `stderr`, and `F` for a full line
+//! or `P` for a piece of a line which was too long to be written at once.
+//!
+//! A container's logs live in `<pod log directory>/<container>/`, one
+//! `<instance>.log` per container instance. Only the current and the previous
+//! instance are kept. A file which grows past the size limit is rotated to
+//! `<instance>.log.1`, `<instance>.log.2`, and so on, oldest last.
+
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, SecondsFormat, Utc};
+use common::PodLogOptions;
+
+pub mod shim;
+
+pub const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_MAX_FILES: usize = 5;
+/// Longer lines are split into partial lines of at most this size.
+pub const MAX_LINE_SIZE: usize = 16 * 1024;
+/// How often `--follow` polls for new lines.
+pub const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+impl FromStr for Stream {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "stdout" => Ok(Stream::Stdout),
+            "stderr" => Ok(Stream::Stderr),
+            _ => Err(anyhow!("unknown stream {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    pub timestamp: DateTime<Utc>,
+    pub stream: Stream,
+    /// The line continues in the next entry of the same stream.
+    pub partial: bool,
+    pub content: String,
+}
+
+impl LogLine {
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(4, ' ');
+        let timestamp = DateTime::parse_from_rfc3339(parts.next()?)
+            .ok()?
+            .with_timezone(&Utc);
+        let stream = parts.next()?.parse().ok()?;
+        let partial = match parts.next()? {
+            "P" => true,
+            "F" => false,
+            _ => return None,
+        };
+        Some(LogLine {
+            timestamp,
+            stream,
+            partial,
+            content: parts.next().unwrap_or_default().to_string(),
+        })
+    }
+}
+
+impl Display for LogLine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            self.stream.as_str(),
+            if self.partial { "P" } else { "F" },
+            self.content
+        )
+    }
+}
+
+/// Appends CRI log lines to a file, rotating it by size.
+pub struct LogWriter {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl LogWriter {
+    pub fn open(path: impl Into<PathBuf>, max_size: u64, max_files: usize) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_size,
+            max_files,
+        })
+    }
+
+    pub fn write_line(&mut self, line: &LogLine) -> io::Result<()> {
+        let entry = format!("{line}\n");
+        if self.size > 0 && self.size + entry.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(entry.as_bytes())?;
+        self.size += entry.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        // `max_files` counts the live file as well.
+        let keep = self.max_files.saturating_sub(1);
+        if keep == 0 {
+            self.file.set_len(0)?;
+            self.size = 0;
+            return Ok(());
+        }
+        let _ = fs::remove_file(rotated_path(&self.path, keep));
+        for n in (1..keep).rev() {
+            let from = rotated_path(&self.path, n);
+            if from.exists() {
+                fs::rename(from, rotated_path(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+fn instance_path(dir: &Path, instance: u32) -> PathBuf {
+    dir.join(format!("{instance}.log"))
+}
+
+/// Files of one container instance, oldest first.
+fn instance_files(path: &Path) -> Vec<PathBuf> {
+    let mut rotated = 0;
+    while rotated_path(path, rotated + 1).exists() {
+        rotated += 1;
+    }
+    let mut files: Vec<_> = (1..=rotated).rev().map(|n| rotated_path(path, n)).collect();
+    if path.exists() {
+        files.push(path.to_path_buf());
+    }
+    files
+}
+
+/// Instances which have logs in `dir`, in ascending order.
+fn instances(dir: &Path) -> Vec<u32> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut instances: Vec<u32> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            e.file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(".log"))
+                .and_then(|n| n.parse().ok())
+        })
+        .collect();
+    instances.sort_unstable();
+    instances
+}
+
+/// Pick the log file for a new instance of the container logging to `dir`.
+///
+/// The logs of the instance before it are kept for `--previous`, older ones
+/// are removed.
+pub fn new_instance(dir: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let existing = instances(dir);
+    let next = existing.last().map_or(0, |n| n + 1);
+    for n in existing.iter().rev().skip(1) {
+        for file in instance_files(&instance_path(dir, *n)) {
+            fs::remove_file(file)?;
+        }
+    }
+    Ok(instance_path(dir, next))
+}
+
+/// The log file of the latest instance of the container logging to `dir`.
+pub fn current_instance(dir: &Path) -> Option<PathBuf> {
+    instances(dir).last().map(|n| instance_path(dir, *n))
+}
+
+/// Read the logs of the container logging to `dir`, filtered by `options`.
+pub fn read_logs(dir: &Path, options: &PodLogOptions, now: DateTime<Utc>) -> Result<Vec<LogLine>> {
+    let instances = instances(dir);
+    let instance = if options.previous {
+        instances
+            .iter()
+            .rev()
+            .nth(1)
+            .ok_or_else(|| anyhow!("previous terminated container not found"))?
+    } else {
+        instances
+            .last()
+            .ok_or_else(|| anyhow!("no logs found in {}", dir.display()))?
+    };
+
+    let mut lines = Vec::new();
+    for file in instance_files(&instance_path(dir, *instance)) {
+        let content = fs::read(&file)?;
+        lines.extend(
+            String::from_utf8_lossy(&content)
+                .lines()
+                .filter_map(LogLine::parse),
+        );
+    }
+    Ok(filter_lines(lines, options, now))
+}
+
+/// Apply the `since` and `tail` options to log lines.
+///
+/// `tail_lines` counts full lines, the partial pieces of a line stay with it.
+pub fn filter_lines(
+    lines: Vec<LogLine>,
+    options: &PodLogOptions,
+    now: DateTime<Utc>,
+) -> Vec<LogLine> {
+    let since = options
+        .since_seconds
+        .map(|s| now - chrono::Duration::seconds(s as i64));
+    let mut lines: Vec<_> = lines
+        .into_iter()
+        .filter(|l| since.is_none_or(|t| l.timestamp >= t))
+        .filter(|l| options.since_time.is_none_or(|t| l.timestamp > t))
+        .collect();
+
+    if let Some(tail) = options.tail_lines {
+        let mut full = 0;
+        let mut start = lines.len();
+        for (i, line) in lines.iter().enumerate().rev() {
+            if !line.partial {
+                if full == tail {
+                    break;
+                }
+                full += 1;
+            }
+            start = i;
+        }
+        lines.drain(..start);
+    }
+    lines
+}
+
+/// Write the contents of log lines as the container printed them.
+pub fn print_lines(lines: &[LogLine], out: &mut impl Write) -> io::Result<()> {
+    for line in lines {
+        out.write_all(line.content.as_bytes())?;
+        if !line.partial {
+            out.write_all(b"\n")?;
+        }
+    }
+    out.flush()
+}
+
+/// Options to fetch the lines following `lines` when following logs.
+pub fn follow_options(
+    options: &PodLogOptions,
+    lines: &[LogLine],
+    now: DateTime<Utc>,
+) -> PodLogOptions {
+    PodLogOptions {
+        since_seconds: None,
+        since_time: lines
+            .last()
+            .map(|l| l.timestamp)
+            .or(options.since_time)
+            .or(Some(now)),
+        tail_lines: None,
+        ..options.clone()
+    }
+}
+
+/// Print the lines returned by `fetch`. With `follow`, keep polling it for
+/// newer lines until interrupted.
+pub fn print_logs(
+    options: &PodLogOptions,
+    follow: bool,
+    mut fetch: impl FnMut(&PodLogOptions) -> Result<Vec<LogLine>>,
+) -> Result<()> {
+    let mut options = options.clone();
+    loop {
+        let lines = fetch(&options)?;
+        print_lines(&lines, &mut io::stdout())?;
+        // The previous instance has exited, there is nothing to follow.
+        if !follow || options.previous {
+            return Ok(());
+        }
+        options = follow_options(&options, &lines, Utc::now());
+        std::thread::sleep(FOLLOW_INTERVAL);
+    }
+}
+
+/// Parse log lines sent in the CRI format, e.g. by a remote node.
+pub fn parse_lines(content: &str) -> Vec<LogLine> {
+    content.lines().filter_map(LogLine::parse).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn line(secs: i64, partial: bool, content: &str) -> LogLine {
+        LogLine {
+            timestamp: DateTime::from_timestamp(secs, 123).unwrap(),
+            stream: Stream::Stdout,
+            partial,
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_log_line_round_trip() {
+        let l = line(1_700_000_000, false, "hello world");
+        let text = l.to_string();
+        assert_eq!(text, "2023-11-14T22:13:20.000000123Z stdout F hello world");
+        assert_eq!(LogLine::parse(&text), Some(l));
+        assert_eq!(LogLine::parse("garbage"), None);
+    }
+
+    #[test]
+    fn test_filter_tail_keeps_partials_with_line() {
+        let lines = vec![
+            line(1, false, "a"),
+            line(2, true, "b1"),
+            line(2, false, "b2"),
+            line(3, false, "c"),
+        ];
+        let options = PodLogOptions {
+            tail_lines: Some(2),
+            ..Default::default()
+        };
+        let now = DateTime::from_timestamp(10, 0).unwrap();
+        let tail = filter_lines(lines.clone(), &options, now);
+        assert_eq!(tail, lines[1..].to_vec());
+
+        let options = PodLogOptions {
+            since_seconds: Some(8),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter_lines(lines.clone(), &options, now),
+            lines[3..].to_vec()
+        );
+    }
+
+    #[test]
+    fn test_rotation_and_previous_instance() {
+        let dir = tempdir().unwrap();
+        let path = new_instance(dir.path()).unwrap();
+        assert!(path.ends_with("0.log"));
+
+        let entry_len = line(1, false, "x").to_string().len() as u64 + 1;
+        let mut writer = LogWriter::open(&path, entry_len * 2, 2).unwrap();
+        for i in 0..5 {
+            writer.write_line(&line(i, false, "x")).unwrap();
+        }
+        // One rotated file plus the live one.
+        assert_eq!(instance_files(&path).len(), 2);
+        assert!(!rotated_path(&path, 2).exists());
+
+        let next = new_instance(dir.path()).unwrap();
+        assert!(next.ends_with("1.log"));
+        let mut writer = LogWriter::open(&next, DEFAULT_MAX_SIZE, DEFAULT_MAX_FILES).unwrap();
+        writer.write_line(&line(10, false, "y")).unwrap();
+
+        let now = DateTime::from_timestamp(20, 0).unwrap();
+        let options = PodLogOptions::default();
+        let current = read_logs(dir.path(), &options, now).unwrap();
+        assert_eq!(current, vec![line(10, false, "y")]);
+
+        let previous = PodLogOptions {
+            previous: true,
+            ..Default::default()
+        };
+        let lines = read_logs(dir.path(), &previous, now).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].timestamp.timestamp(), 2);
+
+        // A third instance drops the first one.
+        new_instance(dir.path()).unwrap();
+        assert_eq!(instances(dir.path()), vec![1]);
+    }
+}
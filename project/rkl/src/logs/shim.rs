@@ -0,0 +1,258 @@
+//! Per-container log shim.
+//!
+//! rkl starts one shim process for every container instance it creates. The
+//! container writes its stdout and stderr into two FIFOs, and the shim copies
+//! them into the container's CRI log file until both streams are closed,
+//! i.e. until the container exits. Being a separate process, the shim keeps
+//! collecting output after a standalone `rkl pod run` has returned.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::Utc;
+use clap::Args;
+use libruntime::cri::ContainerStdio;
+use nix::sys::stat::Mode;
+use nix::unistd::mkfifo;
+use tracing::debug;
+
+use super::{DEFAULT_MAX_FILES, DEFAULT_MAX_SIZE, LogLine, LogWriter, MAX_LINE_SIZE, Stream};
+
+/// Printed by the shim once it has opened both FIFOs.
+const READY: &str = "ready";
+
+#[derive(Args, Debug)]
+pub struct LogShimArgs {
+    /// FIFO the container writes its stdout to
+    #[arg(long)]
+    pub stdout: PathBuf,
+
+    /// FIFO the container writes its stderr to
+    #[arg(long)]
+    pub stderr: PathBuf,
+
+    /// CRI log file to write
+    #[arg(long)]
+    pub log_path: PathBuf,
+
+    /// Rotate the log file when it grows past this many bytes
+    #[arg(long, default_value_t = DEFAULT_MAX_SIZE)]
+    pub max_size: u64,
+
+    /// Number of log files to keep, including the one being written
+    #[arg(long, default_value_t = DEFAULT_MAX_FILES)]
+    pub max_files: usize,
+}
+
+/// Entry point of the hidden `rkl log-shim` command.
+pub fn run(args: LogShimArgs) -> Result<()> {
+    let stdout = File::open(&args.stdout)
+        .with_context(|| format!("failed to open {}", args.stdout.display()))?;
+    let stderr = File::open(&args.stderr)
+        .with_context(|| format!("failed to open {}", args.stderr.display()))?;
+    // Both ends are open, the FIFOs don't need a name any more.
+    let _ = fs::remove_file(&args.stdout);
+    let _ = fs::remove_file(&args.stderr);
+
+    let writer = Arc::new(Mutex::new(LogWriter::open(
+        &args.log_path,
+        args.max_size,
+        args.max_files,
+    )?));
+
+    println!("{READY}");
+    io::stdout().flush()?;
+
+    let handles = [(stdout, Stream::Stdout), (stderr, Stream::Stderr)].map(|(file, stream)| {
+        let writer = writer.clone();
+        thread::spawn(move || copy_stream(file, stream, &writer))
+    });
+    for handle in handles {
+        handle
+            .join()
+            .map_err(|_| anyhow!("log shim thread panicked"))??;
+    }
+    Ok(())
+}
+
+/// Copy one stream of the container into the log file, line by line.
+fn copy_stream(reader: impl Read, stream: Stream, writer: &Mutex<LogWriter>) -> io::Result<()> {
+    let mut reader = BufReader::with_capacity(MAX_LINE_SIZE, reader);
+    let mut buf = Vec::new();
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            if !buf.is_empty() {
+                write_entry(writer, stream, false, &buf)?;
+            }
+            return Ok(());
+        }
+
+        let (consumed, full_line) = match available.iter().position(|&b| b == b'\n') {
+            Some(i) => {
+                buf.extend_from_slice(&available[..i]);
+                (i + 1, true)
+            }
+            None => {
+                buf.extend_from_slice(available);
+                (available.len(), false)
+            }
+        };
+        reader.consume(consumed);
+
+        while buf.len() > MAX_LINE_SIZE || (!full_line && buf.len() == MAX_LINE_SIZE) {
+            let rest = buf.split_off(MAX_LINE_SIZE);
+            write_entry(writer, stream, true, &buf)?;
+            buf = rest;
+        }
+        if full_line {
+            write_entry(writer, stream, false, &buf)?;
+            buf.clear();
+        }
+    }
+}
+
+fn write_entry(
+    writer: &Mutex<LogWriter>,
+    stream: Stream,
+    partial: bool,
+    content: &[u8],
+) -> io::Result<()> {
+    let line = LogLine {
+        timestamp: Utc::now(),
+        stream,
+        partial,
+        content: String::from_utf8_lossy(content).into_owned(),
+    };
+    writer
+        .lock()
+        .map_err(|_| io::Error::other("log writer lock poisoned"))?
+        .write_line(&line)
+}
+
+/// Start a log shim for `container_id`, writing to `log_path`.
+///
+/// The FIFOs are created under `<runtime_dir>/logs/<container_id>/`. The
+/// returned stdio holds their write ends and should be handed to the
+/// container.
+pub fn spawn(
+    container_id: &str,
+    runtime_dir: &Path,
+    log_path: &Path,
+    max_size: u64,
+    max_files: usize,
+) -> Result<ContainerStdio> {
+    let fifo_dir = runtime_dir.join("logs").join(container_id);
+    fs::create_dir_all(&fifo_dir)?;
+    let stdout_fifo = fifo_dir.join("stdout");
+    let stderr_fifo = fifo_dir.join("stderr");
+    for fifo in [&stdout_fifo, &stderr_fifo] {
+        let _ = fs::remove_file(fifo);
+        mkfifo(fifo.as_path(), Mode::S_IRUSR | Mode::S_IWUSR)
+            .with_context(|| format!("failed to create fifo {}", fifo.display()))?;
+    }
+
+    // Opening read-write doesn't block on a missing reader, and keeps a
+    // writer around until the container has taken over the FIFOs, so the
+    // shim doesn't see an early EOF.
+    let open_fifo = |path: &Path| OpenOptions::new().read(true).write(true).open(path);
+    let stdout = open_fifo(&stdout_fifo)?;
+    let stderr = open_fifo(&stderr_fifo)?;
+
+    let mut child =
+        Command::new(std::env::current_exe().context("Failed to get current exe path")?)
+            .arg("log-shim")
+            .arg("--stdout")
+            .arg(&stdout_fifo)
+            .arg("--stderr")
+            .arg(&stderr_fifo)
+            .arg("--log-path")
+            .arg(log_path)
+            .arg("--max-size")
+            .arg(max_size.to_string())
+            .arg("--max-files")
+            .arg(max_files.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            // Keep the shim out of the caller's process group, so that Ctrl-C on
+            // `rkl pod run` doesn't stop log collection.
+            .process_group(0)
+            .spawn()
+            .context("Failed to spawn log shim")?;
+    debug!(container_id, pid = child.id(), "spawned log shim");
+
+    let ready = child.stdout.take().is_some_and(|out| {
+        BufReader::new(out)
+            .lines()
+            .map_while(Result::ok)
+            .any(|line| line == READY)
+    });
+    if !ready {
+        let _ = child.kill();
+        let _ = child.wait();
+        bail!("log shim for container {container_id} exited before it was ready");
+    }
+    // Reap the shim once the container is gone.
+    thread::spawn(move || child.wait());
+
+    Ok(ContainerStdio {
+        stdin: None,
+        stdout: Some(stdout.into()),
+        stderr: Some(stderr.into()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::parse_lines;
+    use tempfile::tempdir;
+
+    fn copy(input: &[u8]) -> Vec<LogLine> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("0.log");
+        let writer =
+            Mutex::new(LogWriter::open(&path, DEFAULT_MAX_SIZE, DEFAULT_MAX_FILES).unwrap());
+        copy_stream(input, Stream::Stderr, &writer).unwrap();
+        parse_lines(&fs::read_to_string(&path).unwrap())
+    }
+
+    #[test]
+    fn test_copy_stream_splits_lines() {
+        let lines = copy(b"first\nsecond\nno newline");
+        let contents: Vec<_> = lines
+            .iter()
+            .map(|l| (l.content.as_str(), l.partial, l.stream))
+            .collect();
+        assert_eq!(
+            contents,
+            vec![
+                ("first", false, Stream::Stderr),
+                ("second", false, Stream::Stderr),
+                ("no newline", false, Stream::Stderr),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_copy_stream_long_line_is_partial() {
+        let mut input = vec![b'a'; MAX_LINE_SIZE * 2 + 10];
+        input.push(b'\n');
+        let lines = copy(&input);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].partial && lines[1].partial && !lines[2].partial);
+        assert_eq!(lines[0].content.len(), MAX_LINE_SIZE);
+        assert_eq!(lines[2].content.len(), 10);
+    }
+}
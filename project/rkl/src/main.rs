@@ -10,17 +10,20 @@ use tracing_subscriber::{EnvFilter, Registry, layer::SubscriberExt};
 mod commands;
 mod config;
 mod daemon;
+mod logs;
 mod network;
 mod quic;
 mod task;
 
 use commands::{
     compose::ComposeCommand, container::ContainerCommand, deployment::DeploymentCommand,
-    pod::PodCommand, replicaset::ReplicaSetCommand, service::ServiceCommand,
+    pdb::PdbCommand, pod::PodCommand, replicaset::ReplicaSetCommand, secret::SecretCommand,
+    service::ServiceCommand, top::TopCommand,
 };
 use commands::{
     compose::compose_execute, container::container_execute, deployment::deployment_execute,
-    pod::pod_execute, replicaset::replicaset_execute, service::service_execute,
+    pdb::pdb_execute, pod::pod_execute, replicaset::replicaset_execute, secret::secret_execute,
+    service::service_execute, top::top_execute,
 };
 use tracing::error;
 
@@ -28,6 +31,8 @@ use crate::commands::volume::{VolumeCommand, volume_execute};
 
 use rkforge::overlayfs::MountArgs;
 
+use crate::logs::shim::LogShimArgs;
+
 const DEFAULT_RKL_LOG_DIR: &str = "/var/log/rk8s/rkl";
 const LOG_PREFIX: &str = "rkl.log";
 const DAEMON_LOG_PREFIX: &str = "rkl-daemon.log";
@@ -54,7 +59,11 @@ impl Cli {
             Workload::Replicaset(cmd) => replicaset_execute(cmd),
             Workload::Deployment(cmd) => deployment_execute(cmd),
             Workload::Service(cmd) => service_execute(cmd),
+            Workload::Secret(cmd) => secret_execute(cmd),
+            Workload::PodDisruptionBudget(cmd) => pdb_execute(cmd),
+            Workload::Top(cmd) => top_execute(cmd),
             Workload::Mount(args) => rkforge::overlayfs::do_mount(args),
+            Workload::LogShim(args) => logs::shim::run(args),
         }
     }
 }
@@ -85,9 +94,22 @@ enum Workload {
     #[command(subcommand, about = "Manage Services", alias = "svc")]
     Service(ServiceCommand),
 
+    #[command(subcommand, about = "Manage Secrets")]
+    Secret(SecretCommand),
+
+    #[command(subcommand, about = "Manage PodDisruptionBudgets", alias = "pdb")]
+    PodDisruptionBudget(PdbCommand),
+
+    #[command(subcommand, about = "Show the resource usage of pods and nodes")]
+    Top(TopCommand),
+
     /// Internal: overlay mount daemon (hidden from help)
     #[command(hide = true)]
     Mount(MountArgs),
+
+    /// Internal: per-container log collector (hidden from help)
+    #[command(hide = true)]
+    LogShim(LogShimArgs),
 }
 
 fn main() -> Result<(), anyhow::Error> {
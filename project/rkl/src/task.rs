@@ -1,19 +1,28 @@
-use anyhow::{Result, anyhow};
-use common::{ContainerSpec, PodTask};
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, Utc};
+use common::{
+    ContainerSpec, ContainerState, ContainerStatus, EphemeralContainer, ImagePullPolicy, PodTask,
+    RestartPolicy,
+};
 use json::JsonValue;
 use libcni::rust_cni::cni::Libcni;
+use libcontainer::container::ContainerStatus as OciStatus;
 use libcontainer::syscall::syscall::create_syscall;
 use liboci_cli::{Create, Delete, Kill, Start};
 use libruntime::cri::config::ContainerConfigBuilder;
 use thiserror::Error;
 // use libruntime::cri::config::get_linux_container_config;
+use libruntime::checkpoint::{self, RestoreTarget};
 use libruntime::cri::cri_api::{
     ContainerConfig, CreateContainerRequest, CreateContainerResponse, PodSandboxConfig,
     PodSandboxMetadata, PortMapping, Protocol, RemovePodSandboxRequest, RemovePodSandboxResponse,
-    RunPodSandboxRequest, RunPodSandboxResponse, StartContainerRequest, StartContainerResponse,
-    StopPodSandboxRequest, StopPodSandboxResponse,
+    RunPodSandboxRequest, RunPodSandboxResponse, Signal, StartContainerRequest,
+    StartContainerResponse, StopPodSandboxRequest, StopPodSandboxResponse,
+};
+use libruntime::cri::{
+    ContainerStdio, create, create_with_stdio, delete, kill, load_container, start,
 };
-use libruntime::cri::{create, delete, kill, load_container, start};
+use libruntime::oci::userns::{self, IdRange};
 use libruntime::oci::{self, OCISpecGenerator};
 use libruntime::rootpath;
 use libruntime::utils::{
@@ -21,14 +30,24 @@ use libruntime::utils::{
     sync_handle_oci_image_no_copy,
 };
 
-use crate::config::OVERLAY_CONFIG;
+use crate::commands::pod::PodInfo;
+use crate::commands::pod::downward;
+use crate::commands::pod::userns as pod_userns;
+use crate::commands::secret::image_registry;
+use crate::config::{LOG_CONFIG, OVERLAY_CONFIG};
+use crate::daemon::allocatable;
+use crate::daemon::metrics::IMAGE_PULL_DURATION;
+use crate::daemon::pod_worker::resolve_exit_status;
+use crate::logs;
 use oci_spec::runtime::RootBuilder;
 use rkforge::commands::container::rootfs_mount::RootfsMount;
-use std::collections::HashMap;
+use rkforge::pull::{ImageNotPresent, PullOptions, PullPolicy, RegistryAuth};
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
-use tracing::{debug, error, info};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
 
 /// Error indicating pause container is dead, Pod needs to be rebuilt
 #[derive(Debug, Error)]
@@ -68,16 +87,67 @@ fn check_pause_alive(pid: i32) -> Result<(), PauseDeadError> {
     Ok(())
 }
 
-struct RkforgeImagePuller {}
+/// Delay before the first restart of a failed init container, doubled on
+/// each further failure.
+const INIT_BACKOFF_INITIAL: Duration = Duration::from_secs(10);
+const INIT_BACKOFF_MAX: Duration = Duration::from_secs(300);
+
+/// Interval at which a running container is checked for exit.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// What became of an init container once exited.
+enum InitOutcome {
+    /// Exited successfully.
+    Completed,
+    /// Failed, to be restarted after the delay.
+    Restart(Duration),
+}
+
+/// Wait for a container to exit, returning its exit code, the signal which
+/// killed it and a message if the exit code is unknown.
+pub(crate) fn wait_for_exit(
+    root_path: &Path,
+    container_id: &str,
+) -> Result<(i32, Option<i32>, Option<String>)> {
+    loop {
+        let container = load_container(root_path, container_id)?;
+        if container.status() == OciStatus::Stopped {
+            return Ok(resolve_exit_status(&container));
+        }
+        std::thread::sleep(EXIT_POLL_INTERVAL);
+    }
+}
+
+/// Failure to pull the image of a container.
+#[derive(Debug, Error)]
+#[error("failed to pull image {image} of container {container}: {error}")]
+pub struct ImagePullError {
+    pub container: String,
+    pub image: String,
+    pub error: anyhow::Error,
+}
+
+impl ImagePullError {
+    /// Whether the image is missing and its pull policy is `Never`.
+    pub fn not_present(&self) -> bool {
+        self.error.is::<ImageNotPresent>()
+    }
+}
+
+struct RkforgeImagePuller {
+    options: PullOptions,
+}
 
 #[async_trait::async_trait]
 impl ImagePuller for RkforgeImagePuller {
     async fn pull_or_get_image(&self, image_ref: &str) -> Result<(PathBuf, Vec<PathBuf>)> {
-        rkforge::pull::pull_or_get_image(image_ref, None::<&str>).await
+        let (registry, image_ref) = image_registry(image_ref);
+        rkforge::pull::pull_or_get_image_with_options(image_ref, registry, &self.options).await
     }
 
     fn sync_pull_or_get_image(&self, image_ref: &str) -> Result<(PathBuf, Vec<PathBuf>)> {
-        rkforge::pull::sync_pull_or_get_image(image_ref, None::<&str>)
+        let (registry, image_ref) = image_registry(image_ref);
+        rkforge::pull::sync_pull_or_get_image_with_options(image_ref, registry, &self.options)
     }
 }
 
@@ -87,13 +157,37 @@ pub struct TaskRunner {
     pub sandbox_config: Option<PodSandboxConfig>,
     /// Per-container persistent overlay rootfs mounts (keyed by container name)
     rootfs_mounts: HashMap<String, RootfsMount>,
+    /// Stop signals of the container images (keyed by container name)
+    stop_signals: HashMap<String, String>,
+    /// Host ids of the pod's user namespace, for pods with `hostUsers: false`
+    id_range: Option<IdRange>,
+    /// Checkpoints the containers are restored from (keyed by container name)
+    checkpoints: HashMap<String, PathBuf>,
+    /// Address of the pod, once the sandbox network is set up
+    pod_ip: Option<String>,
+    /// Statuses of the init containers run so far
+    init_container_statuses: Vec<ContainerStatus>,
+    /// Credentials of the pod's `imagePullSecrets`, by registry host
+    pub registry_credentials: HashMap<String, RegistryAuth>,
+    /// Images already pulled by [`Self::pull_images`]
+    pulled_images: HashSet<String>,
+    /// Ephemeral containers started by [`Self::run_ephemeral_container`]
+    ephemeral_containers: Vec<ContainerSpec>,
+    /// Processes whose PID namespace an ephemeral container joins (keyed by
+    /// container name)
+    target_pids: HashMap<String, i32>,
 }
 
 impl TaskRunner {
     pub fn from_task(mut task: PodTask) -> Result<Self> {
         let pod_name = task.metadata.name.clone();
 
-        for container in &mut task.spec.containers {
+        for container in task
+            .spec
+            .init_containers
+            .iter_mut()
+            .chain(&mut task.spec.containers)
+        {
             let original_name = container.name.clone();
             container.name = format!("{pod_name}-{original_name}");
         }
@@ -103,9 +197,69 @@ impl TaskRunner {
             pause_pid: None,
             sandbox_config: None,
             rootfs_mounts: HashMap::new(),
+            stop_signals: HashMap::new(),
+            id_range: None,
+            checkpoints: HashMap::new(),
+            pod_ip: None,
+            init_container_statuses: Vec::new(),
+            registry_credentials: HashMap::new(),
+            pulled_images: HashSet::new(),
+            ephemeral_containers: Vec::new(),
+            target_pids: HashMap::new(),
         })
     }
 
+    /// A puller for the image of `container` following its pull policy.
+    /// Images pulled by [`Self::pull_images`] are not pulled again.
+    fn image_puller(&self, container: &ContainerSpec) -> RkforgeImagePuller {
+        let policy = match container.image_pull_policy() {
+            _ if self.pulled_images.contains(&container.image) => PullPolicy::IfNotPresent,
+            ImagePullPolicy::Always => PullPolicy::Always,
+            ImagePullPolicy::IfNotPresent => PullPolicy::IfNotPresent,
+            ImagePullPolicy::Never => PullPolicy::Never,
+        };
+        RkforgeImagePuller {
+            options: PullOptions {
+                policy,
+                credentials: self.registry_credentials.clone(),
+            },
+        }
+    }
+
+    /// Pull the images of all containers of the pod, so a failing pull can
+    /// be reported before anything is created.
+    pub async fn pull_images(&mut self) -> Result<(), ImagePullError> {
+        let containers = self
+            .task
+            .spec
+            .init_containers
+            .iter()
+            .chain(&self.task.spec.containers)
+            .cloned()
+            .collect::<Vec<_>>();
+        for container in &containers {
+            if self.pulled_images.contains(&container.image)
+                || !matches!(determine_image(&container.image), Ok(ImageType::OCIImage))
+            {
+                continue;
+            }
+            let start = Instant::now();
+            let pulled = self
+                .image_puller(container)
+                .pull_or_get_image(&container.image)
+                .await;
+            let result = if pulled.is_ok() { "success" } else { "error" };
+            IMAGE_PULL_DURATION.observe_since(&[result], start);
+            pulled.map_err(|error| ImagePullError {
+                container: container.name.clone(),
+                image: container.image.clone(),
+                error,
+            })?;
+            self.pulled_images.insert(container.image.clone());
+        }
+        Ok(())
+    }
+
     //get information from a file  record in Podtask
     pub fn from_file(path: &str) -> Result<Self> {
         let mut file = File::open(path)?;
@@ -167,6 +321,14 @@ impl TaskRunner {
         })
     }
 
+    /// Log directory of the pod, once the sandbox config is set.
+    pub fn log_directory(&self) -> Option<String> {
+        self.sandbox_config
+            .as_ref()
+            .map(|c| c.log_directory.clone())
+            .filter(|dir| !dir.is_empty())
+    }
+
     //get RunPodSandboxRequest
     pub fn build_run_pod_sandbox_request(&self) -> RunPodSandboxRequest {
         let uid = uuid::Uuid::new_v4().to_string();
@@ -204,9 +366,12 @@ impl TaskRunner {
             volume_mounts: None,
             command: None,
             working_dir: None,
+            lifecycle: None,
+            restart_policy: None,
+            image_pull_policy: None,
         };
 
-        let puller = RkforgeImagePuller {};
+        let puller = self.image_puller(&sandbox_spec);
         let (config_builder, bundle_path) = handle_image_typ(&puller, &sandbox_spec)
             .await
             .map_err(|e| anyhow!("failed to get pause container's bundle_path: {e}"))?;
@@ -220,23 +385,19 @@ impl TaskRunner {
                 .build();
         }
         let oci_spec = oci::OCISpecGenerator::new(&config, &sandbox_spec, None)
+            .user_namespace(self.id_range)
+            .cgroups_path(allocatable::pod_cgroups_path(&sandbox_id))
             .generate()
             .map_err(|e| anyhow!("failed to generate sandbox pause oci spec: {e}"))?;
 
-        let config_path = format!("{bundle_path}/config.json");
-        if !Path::new(&config_path).exists() {
-            let file = File::create(&config_path)?;
-            let mut writer = BufWriter::new(file);
-            serde_json::to_writer_pretty(&mut writer, &oci_spec)?;
-            writer.flush()?;
-        }
-
-        let bundle_dir = PathBuf::from(&bundle_path);
-        if !bundle_dir.exists() {
-            return Err(anyhow!("Bundle directory does not exist"));
-        }
+        let root_path = rootpath::determine(None, &*create_syscall())
+            .map_err(|e| anyhow!("Failed to determine root path: {}", e))?;
+        let bundle_dir = write_sandbox_bundle(&root_path, &sandbox_id, oci_spec, &bundle_path)?;
 
-        info!("Get sandbox {sandbox_id}'s bundle path: {bundle_path}");
+        info!(
+            "Get sandbox {sandbox_id}'s bundle path: {}",
+            bundle_dir.display()
+        );
 
         // 3. Create container use cri
         let create_args = Create {
@@ -249,9 +410,6 @@ impl TaskRunner {
             container_id: sandbox_id.clone(),
         };
 
-        let root_path = rootpath::determine(None, &*create_syscall())
-            .map_err(|e| anyhow!("Failed to determine root path: {}", e))?;
-
         create(create_args, root_path.clone(), false)
             .map_err(|e| anyhow!("Failed to create container: {}", e))?;
 
@@ -290,6 +448,9 @@ impl TaskRunner {
             .to_string();
         self.pause_pid = Some(pid_i32);
         // let podip = runner.ip().unwrap().to_string();
+        self.pod_ip = podip.split('/').next().map(str::to_string);
+        downward::write_volumes(&root_path, &self.task, &podip)
+            .map_err(|e| anyhow!("failed to write the downwardAPI volumes: {e}"))?;
 
         info!("podip:{podip}");
         let response = RunPodSandboxResponse {
@@ -322,9 +483,12 @@ impl TaskRunner {
             volume_mounts: None,
             command: None,
             working_dir: None,
+            lifecycle: None,
+            restart_policy: None,
+            image_pull_policy: None,
         };
 
-        let puller = RkforgeImagePuller {};
+        let puller = self.image_puller(&sandbox_spec);
         let (config_builder, bundle_path) = sync_handle_image_typ(&puller, &sandbox_spec)
             .map_err(|e| anyhow!("failed to get pause container's bundle_path: {e}"))?;
 
@@ -337,23 +501,19 @@ impl TaskRunner {
                 .build();
         }
         let oci_spec = oci::OCISpecGenerator::new(&config, &sandbox_spec, None)
+            .user_namespace(self.id_range)
+            .cgroups_path(allocatable::pod_cgroups_path(&sandbox_id))
             .generate()
             .map_err(|e| anyhow!("failed to generate sandbox pause oci spec: {e}"))?;
 
-        let config_path = format!("{bundle_path}/config.json");
-        if !Path::new(&config_path).exists() {
-            let file = File::create(&config_path)?;
-            let mut writer = BufWriter::new(file);
-            serde_json::to_writer_pretty(&mut writer, &oci_spec)?;
-            writer.flush()?;
-        }
-
-        let bundle_dir = PathBuf::from(&bundle_path);
-        if !bundle_dir.exists() {
-            return Err(anyhow!("Bundle directory does not exist"));
-        }
+        let root_path = rootpath::determine(None, &*create_syscall())
+            .map_err(|e| anyhow!("Failed to determine root path: {}", e))?;
+        let bundle_dir = write_sandbox_bundle(&root_path, &sandbox_id, oci_spec, &bundle_path)?;
 
-        info!("Get sandbox {sandbox_id}'s bundle path: {bundle_path}");
+        info!(
+            "Get sandbox {sandbox_id}'s bundle path: {}",
+            bundle_dir.display()
+        );
 
         // 3. Create container use cri
         let create_args = Create {
@@ -366,9 +526,6 @@ impl TaskRunner {
             container_id: sandbox_id.clone(),
         };
 
-        let root_path = rootpath::determine(None, &*create_syscall())
-            .map_err(|e| anyhow!("Failed to determine root path: {}", e))?;
-
         create(create_args, root_path.clone(), false)
             .map_err(|e| anyhow!("Failed to create container: {}", e))?;
 
@@ -407,6 +564,9 @@ impl TaskRunner {
             .to_string();
         self.pause_pid = Some(pid_i32);
         // let podip = runner.ip().unwrap().to_string();
+        self.pod_ip = podip.split('/').next().map(str::to_string);
+        downward::write_volumes(&root_path, &self.task, &podip)
+            .map_err(|e| anyhow!("failed to write the downwardAPI volumes: {e}"))?;
 
         info!("podip:{podip}");
         let response = RunPodSandboxResponse {
@@ -435,7 +595,7 @@ impl TaskRunner {
         pod_sandbox_id: &str,
         container: &ContainerSpec,
     ) -> Result<CreateContainerRequest, anyhow::Error> {
-        let puller = RkforgeImagePuller {};
+        let puller = self.image_puller(container);
 
         let (mut config_builder, bundle_path) = if OVERLAY_CONFIG.use_overlay_rootfs {
             if let ImageType::OCIImage = determine_image(&container.image)? {
@@ -450,14 +610,18 @@ impl TaskRunner {
                         &layers,
                     ))?;
 
+                self.apply_checkpoint_rootfs(&container.name, &upper_dir, true)?;
+
                 // Start background overlay daemon
-                let rootfs_mount = RootfsMount::start(
+                let id_mapping = self.id_range.map(|range| range.fuse_mapping());
+                let rootfs_mount = RootfsMount::start_with_id_mapping(
                     &lower_dirs,
                     &upper_dir,
                     &work_dir,
                     &merged_dir,
                     &PathBuf::from(&bundle_path),
                     OVERLAY_CONFIG.use_libfuse_overlay,
+                    id_mapping.as_deref(),
                 )?;
 
                 self.rootfs_mounts
@@ -468,16 +632,24 @@ impl TaskRunner {
                     builder.args_from_image_config(config.entrypoint(), config.cmd());
                     builder.envs_from_image_config(config.env());
                     builder.work_dir(config.working_dir());
+                    builder.stop_signal_from_image_config(config.stop_signal());
                 }
                 (Some(builder), bundle_path)
             } else {
                 (None, "".to_string())
             }
         } else {
-            sync_handle_image_typ(&puller, container)?
+            let (builder, bundle_path) = sync_handle_image_typ(&puller, container)?;
+            self.shift_rootfs_ownership(&bundle_path)?;
+            self.apply_checkpoint_rootfs(
+                &container.name,
+                &Path::new(&bundle_path).join("rootfs"),
+                false,
+            )?;
+            (builder, bundle_path)
         };
 
-        let config = if let Some(ref mut builder) = config_builder {
+        let mut config = if let Some(ref mut builder) = config_builder {
             builder.container_spec(container.clone())?;
             builder.images(bundle_path);
             builder.clone().build()
@@ -487,6 +659,8 @@ impl TaskRunner {
                 .clone()
                 .build()
         };
+        self.apply_downward_api(&mut config, container)?;
+        self.record_stop_signal(&container.name, &config);
 
         Ok(CreateContainerRequest {
             pod_sandbox_id: pod_sandbox_id.to_string(),
@@ -500,7 +674,7 @@ impl TaskRunner {
         pod_sandbox_id: &str,
         container: &ContainerSpec,
     ) -> Result<CreateContainerRequest, anyhow::Error> {
-        let puller = RkforgeImagePuller {};
+        let puller = self.image_puller(container);
 
         let (mut config_builder, bundle_path) = if OVERLAY_CONFIG.use_overlay_rootfs {
             if let ImageType::OCIImage = determine_image(&container.image)? {
@@ -511,14 +685,18 @@ impl TaskRunner {
                 let (lower_dirs, upper_dir, work_dir, merged_dir) =
                     libruntime::bundle::prepare_overlay_dirs(&bundle_path, &layers).await?;
 
+                self.apply_checkpoint_rootfs(&container.name, &upper_dir, true)?;
+
                 // Start background overlay daemon
-                let rootfs_mount = RootfsMount::start(
+                let id_mapping = self.id_range.map(|range| range.fuse_mapping());
+                let rootfs_mount = RootfsMount::start_with_id_mapping(
                     &lower_dirs,
                     &upper_dir,
                     &work_dir,
                     &merged_dir,
                     &PathBuf::from(&bundle_path),
                     OVERLAY_CONFIG.use_libfuse_overlay,
+                    id_mapping.as_deref(),
                 )?;
 
                 self.rootfs_mounts
@@ -529,16 +707,24 @@ impl TaskRunner {
                     builder.args_from_image_config(config.entrypoint(), config.cmd());
                     builder.envs_from_image_config(config.env());
                     builder.work_dir(config.working_dir());
+                    builder.stop_signal_from_image_config(config.stop_signal());
                 }
                 (Some(builder), bundle_path)
             } else {
                 (None, "".to_string())
             }
         } else {
-            handle_image_typ(&puller, container).await?
+            let (builder, bundle_path) = handle_image_typ(&puller, container).await?;
+            self.shift_rootfs_ownership(&bundle_path)?;
+            self.apply_checkpoint_rootfs(
+                &container.name,
+                &Path::new(&bundle_path).join("rootfs"),
+                false,
+            )?;
+            (builder, bundle_path)
         };
 
-        let config = if let Some(ref mut builder) = config_builder {
+        let mut config = if let Some(ref mut builder) = config_builder {
             builder.container_spec(container.clone())?;
             builder.images(bundle_path);
             builder.clone().build()
@@ -548,6 +734,8 @@ impl TaskRunner {
                 .clone()
                 .build()
         };
+        self.apply_downward_api(&mut config, container)?;
+        self.record_stop_signal(&container.name, &config);
 
         Ok(CreateContainerRequest {
             pod_sandbox_id: pod_sandbox_id.to_string(),
@@ -556,16 +744,154 @@ impl TaskRunner {
         })
     }
 
+    /// Add the env vars of the container, with the downward API references
+    /// resolved, and the downwardAPI volumes it mounts.
+    fn apply_downward_api(
+        &self,
+        config: &mut ContainerConfig,
+        container: &ContainerSpec,
+    ) -> Result<()> {
+        let pod_ip = self.pod_ip.as_deref().unwrap_or_default();
+        config
+            .envs
+            .extend(downward::container_env(&self.task, pod_ip, container)?);
+        let root_path = rootpath::determine(None, &*create_syscall())?;
+        config.mounts.extend(downward::container_mounts(
+            &root_path, &self.task, container,
+        ));
+        Ok(())
+    }
+
+    /// Hand a rootfs copied for a single container over to the pod's user
+    /// namespace. Bundles given as a path are shared and left as they are.
+    fn shift_rootfs_ownership(&self, bundle_path: &str) -> Result<()> {
+        if let Some(id_range) = &self.id_range
+            && !bundle_path.is_empty()
+        {
+            let rootfs = Path::new(bundle_path).join("rootfs");
+            userns::shift_ownership(&rootfs, id_range)
+                .map_err(|e| anyhow!("failed to shift ownership of {}: {e}", rootfs.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Restore `container_name` from the checkpoint in `dir` instead of
+    /// starting it afresh, see [`TaskRunner::restore_container`].
+    pub fn set_checkpoint(&mut self, container_name: &str, dir: PathBuf) {
+        self.checkpoints.insert(container_name.to_string(), dir);
+    }
+
+    fn apply_checkpoint_rootfs(
+        &self,
+        container_name: &str,
+        target: &Path,
+        overlay: bool,
+    ) -> Result<()> {
+        match self.checkpoints.get(container_name) {
+            Some(dir) if !target.as_os_str().is_empty() => {
+                checkpoint::apply_rootfs_diff(dir, target, overlay).map_err(|e| {
+                    anyhow!("failed to restore the rootfs of container {container_name}: {e}")
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Allocate the pod's user namespace range when it doesn't use the host's.
+    /// A pod that already has a range keeps it.
+    pub fn allocate_id_range(&mut self) -> Result<()> {
+        if !self.task.spec.host_users() {
+            self.id_range = Some(pod_userns::allocate(&self.task.metadata.name)?);
+        }
+        Ok(())
+    }
+
+    fn record_stop_signal(&mut self, container_name: &str, config: &ContainerConfig) {
+        match Signal::try_from(config.stop_signal) {
+            Ok(Signal::RuntimeDefault) | Err(_) => {}
+            Ok(signal) => {
+                self.stop_signals
+                    .insert(container_name.to_string(), signal.as_str_name().to_string());
+            }
+        }
+    }
+
+    /// Signal from the image's STOPSIGNAL for a container, if it has one.
+    pub fn stop_signal(&self, container_name: &str) -> Option<String> {
+        self.stop_signals.get(container_name).cloned()
+    }
+
     //create work container
     pub fn create_container(
         &self,
         request: CreateContainerRequest,
     ) -> Result<CreateContainerResponse, anyhow::Error> {
-        let _pod_sandbox_id = request.pod_sandbox_id.clone();
         let config = request
             .config
             .as_ref()
             .ok_or_else(|| anyhow!("Container config is required"))?;
+        let (container_id, bundle_path) = self.prepare_bundle(config)?;
+
+        let create_args = Create {
+            bundle: bundle_path.into(),
+            console_socket: None,
+            pid_file: None,
+            no_pivot: false,
+            no_new_keyring: false,
+            preserve_fds: 0,
+            container_id: container_id.clone(),
+        };
+
+        let root_path = rootpath::determine(None, &*create_syscall())
+            .map_err(|e| anyhow!("Failed to determine root path: {}", e))?;
+
+        let stdio = self
+            .start_log_shim(&container_id, config, &root_path)
+            .map_err(|e| anyhow!("Failed to start log shim for {container_id}: {e}"))?;
+        create_with_stdio(create_args, root_path.clone(), false, stdio)
+            .map_err(|e| anyhow!("Failed to create container: {}", e))?;
+
+        Ok(CreateContainerResponse { container_id })
+    }
+
+    /// Restore a work container from the checkpoint set with
+    /// [`TaskRunner::set_checkpoint`]. The container comes back running, in
+    /// the namespaces of this pod's pause container.
+    pub fn restore_container(
+        &self,
+        request: CreateContainerRequest,
+    ) -> Result<CreateContainerResponse, anyhow::Error> {
+        let config = request
+            .config
+            .as_ref()
+            .ok_or_else(|| anyhow!("Container config is required"))?;
+        let (container_id, bundle_path) = self.prepare_bundle(config)?;
+        let checkpoint_dir = self
+            .checkpoints
+            .get(&container_id)
+            .ok_or_else(|| anyhow!("No checkpoint found for container {container_id}"))?;
+
+        let root_path = rootpath::determine(None, &*create_syscall())
+            .map_err(|e| anyhow!("Failed to determine root path: {}", e))?;
+        let stdio = self
+            .start_log_shim(&container_id, config, &root_path)
+            .map_err(|e| anyhow!("Failed to start log shim for {container_id}: {e}"))?;
+        let target = RestoreTarget {
+            container_id: &container_id,
+            root_path: &root_path,
+            bundle: Path::new(&bundle_path),
+            pause_pid: self.pause_pid,
+            stdio,
+        };
+        checkpoint::restore(checkpoint_dir, target)
+            .map_err(|e| anyhow!("Failed to restore container: {}", e))?;
+
+        Ok(CreateContainerResponse { container_id })
+    }
+
+    /// Write the OCI spec of a work container into its bundle. Returns the
+    /// container id and the bundle path.
+    fn prepare_bundle(&self, config: &ContainerConfig) -> Result<(String, String)> {
         let container_id = config
             .metadata
             .as_ref()
@@ -575,8 +901,10 @@ impl TaskRunner {
         let container_spec = self
             .task
             .spec
-            .containers
+            .init_containers
             .iter()
+            .chain(&self.task.spec.containers)
+            .chain(&self.ephemeral_containers)
             .find(|c| c.name == container_id)
             .ok_or_else(|| anyhow!("Container spec not found for ID: {}", container_id))?;
 
@@ -598,16 +926,24 @@ impl TaskRunner {
             anyhow::Error::from(e)
         })?;
 
-        let generator = OCISpecGenerator::new(config, container_spec, Some(pause_pid));
+        let generator = OCISpecGenerator::new(config, container_spec, Some(pause_pid))
+            .user_namespace(self.id_range)
+            .pid_namespace_of(self.target_pids.get(&container_id).copied())
+            .cgroups_path(allocatable::pod_cgroups_path(&container_id));
         let mut spec = generator.generate().map_err(|e| {
             anyhow!("failed to build OCI Specification for container {container_id}: {e}")
         })?;
 
         // If this container uses overlay rootfs, override Root path to "merged"
         if self.rootfs_mounts.contains_key(&container_id) {
+            let readonly = spec
+                .root()
+                .as_ref()
+                .and_then(|root| root.readonly())
+                .unwrap_or(false);
             let root = RootBuilder::default()
                 .path("merged")
-                .readonly(false)
+                .readonly(readonly)
                 .build()
                 .map_err(|e| anyhow!("failed to build root spec: {e}"))?;
             spec.set_root(Some(root));
@@ -640,23 +976,41 @@ impl TaskRunner {
             writer.flush()?;
         }
 
-        let create_args = Create {
-            bundle: bundle_path.clone().into(),
-            console_socket: None,
-            pid_file: None,
-            no_pivot: false,
-            no_new_keyring: false,
-            preserve_fds: 0,
-            container_id: container_id.clone(),
-        };
-
-        let root_path = rootpath::determine(None, &*create_syscall())
-            .map_err(|e| anyhow!("Failed to determine root path: {}", e))?;
-
-        create(create_args, root_path.clone(), false)
-            .map_err(|e| anyhow!("Failed to create container: {}", e))?;
+        Ok((container_id, bundle_path))
+    }
 
-        Ok(CreateContainerResponse { container_id })
+    /// Start the log shim which writes the container's output under the
+    /// pod's log directory. Without a log directory the container inherits
+    /// rkl's stdio.
+    fn start_log_shim(
+        &self,
+        container_id: &str,
+        config: &ContainerConfig,
+        root_path: &Path,
+    ) -> Result<ContainerStdio> {
+        let log_directory = self
+            .sandbox_config
+            .as_ref()
+            .map(|c| c.log_directory.as_str())
+            .unwrap_or_default();
+        if log_directory.is_empty() {
+            return Ok(ContainerStdio::default());
+        }
+        // `log_path` names the container's directory and its first instance,
+        // the instance number itself is picked from the files on disk so
+        // restarts don't overwrite the previous log.
+        let container_dir = Path::new(&config.log_path)
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map_or_else(|| PathBuf::from(container_id), Path::to_path_buf);
+        let log_path = logs::new_instance(&Path::new(log_directory).join(container_dir))?;
+        logs::shim::spawn(
+            container_id,
+            root_path,
+            &log_path,
+            LOG_CONFIG.max_size,
+            LOG_CONFIG.max_files,
+        )
     }
 
     pub fn start_container(
@@ -704,11 +1058,15 @@ impl TaskRunner {
         };
         delete(delete_args, root_path.clone())
             .map_err(|e| anyhow!("Failed to delete PodSandbox {}: {}", pod_sandbox_id, e))?;
+        remove_sandbox_bundle(&root_path, &pod_sandbox_id);
 
         Ok(RemovePodSandboxResponse {})
     }
 
     pub async fn run(&mut self) -> Result<(String, String), anyhow::Error> {
+        self.allocate_id_range()
+            .map_err(|e| anyhow!("Failed to allocate user namespace: {e}"))?;
+
         // run PodSandbox（Pause container）
         let pod_request = self.build_run_pod_sandbox_request();
         let config = pod_request
@@ -736,6 +1094,17 @@ impl TaskRunner {
         // if fail clear all containers created
         let mut created_containers = Vec::new();
 
+        // init containers run to completion before the workload containers
+        // are created, sidecars among them keep running
+        if let Err(e) = self
+            .run_init_containers(&pod_sandbox_id, &mut created_containers)
+            .await
+        {
+            self.rollback(&pod_sandbox_id, &created_containers);
+            return Err(e);
+        }
+        let sidecars = created_containers.len();
+
         // Clone containers list to avoid borrow conflict with &mut self
         let containers = self.task.spec.containers.clone();
 
@@ -754,53 +1123,7 @@ impl TaskRunner {
                 }
                 Err(e) => {
                     error!("Failed to create container {}: {}", container.name, e);
-
-                    // Stop all overlay rootfs mounts during rollback
-                    self.stop_all_rootfs_mounts();
-
-                    // delete container created
-                    for container_id in &created_containers {
-                        let delete_args = Delete {
-                            container_id: container_id.clone(),
-                            force: true,
-                        };
-                        let root_path = rootpath::determine(None, &*create_syscall())?;
-                        if let Err(delete_err) = delete(delete_args, root_path.clone()) {
-                            error!(
-                                "Failed to delete container {} during rollback: {}",
-                                container_id, delete_err
-                            );
-                        } else {
-                            info!("Container deleted during rollback: {}", container_id);
-                        }
-                    }
-
-                    // stop pause
-                    let stop_request = StopPodSandboxRequest {
-                        pod_sandbox_id: pod_sandbox_id.clone(),
-                    };
-                    if let Err(stop_err) = self.stop_pod_sandbox(stop_request) {
-                        error!(
-                            "Failed to stop PodSandbox {} during rollback: {}",
-                            pod_sandbox_id, stop_err
-                        );
-                    } else {
-                        info!("PodSandbox stopped during rollback: {}", pod_sandbox_id);
-                    }
-
-                    // delete pause
-                    let remove_request = RemovePodSandboxRequest {
-                        pod_sandbox_id: pod_sandbox_id.clone(),
-                    };
-                    if let Err(remove_err) = self.remove_pod_sandbox(remove_request) {
-                        error!(
-                            "Failed to remove PodSandbox {} during rollback: {}",
-                            pod_sandbox_id, remove_err
-                        );
-                    } else {
-                        info!("PodSandbox deleted during rollback: {}", pod_sandbox_id);
-                    }
-
+                    self.rollback(&pod_sandbox_id, &created_containers);
                     return Err(anyhow!(
                         "Failed to create container {}: {}",
                         container.name,
@@ -811,7 +1134,7 @@ impl TaskRunner {
         }
 
         // start all container
-        for container_id in &created_containers {
+        for container_id in &created_containers[sidecars..] {
             let start_request = StartContainerRequest {
                 container_id: container_id.clone(),
             };
@@ -821,50 +1144,7 @@ impl TaskRunner {
                 }
                 Err(e) => {
                     error!("Failed to start container {}: {}", container_id, e);
-
-                    // Stop all overlay rootfs mounts during rollback
-                    self.stop_all_rootfs_mounts();
-
-                    for container_id in &created_containers {
-                        let delete_args = Delete {
-                            container_id: container_id.clone(),
-                            force: true,
-                        };
-                        let root_path = rootpath::determine(None, &*create_syscall())?;
-                        if let Err(delete_err) = delete(delete_args, root_path.clone()) {
-                            error!(
-                                "Failed to delete container {} during rollback: {}",
-                                container_id, delete_err
-                            );
-                        } else {
-                            info!("Container deleted during rollback: {}", container_id);
-                        }
-                    }
-
-                    let stop_request = StopPodSandboxRequest {
-                        pod_sandbox_id: pod_sandbox_id.clone(),
-                    };
-                    if let Err(stop_err) = self.stop_pod_sandbox(stop_request) {
-                        error!(
-                            "Failed to stop PodSandbox {} during rollback: {}",
-                            pod_sandbox_id, stop_err
-                        );
-                    } else {
-                        info!("PodSandbox stopped during rollback: {}", pod_sandbox_id);
-                    }
-
-                    let remove_request = RemovePodSandboxRequest {
-                        pod_sandbox_id: pod_sandbox_id.clone(),
-                    };
-                    if let Err(remove_err) = self.remove_pod_sandbox(remove_request) {
-                        error!(
-                            "Failed to remove PodSandbox {} during rollback: {}",
-                            pod_sandbox_id, remove_err
-                        );
-                    } else {
-                        info!("PodSandbox deleted during rollback: {}", pod_sandbox_id);
-                    }
-
+                    self.rollback(&pod_sandbox_id, &created_containers);
                     return Err(anyhow!("Failed to start container {}: {}", container_id, e));
                 }
             }
@@ -874,6 +1154,9 @@ impl TaskRunner {
     }
 
     pub fn sync_run(&mut self) -> Result<(String, String), anyhow::Error> {
+        self.allocate_id_range()
+            .map_err(|e| anyhow!("Failed to allocate user namespace: {e}"))?;
+
         // run PodSandbox（Pause container）
         let pod_request = self.build_run_pod_sandbox_request();
         let config = pod_request
@@ -900,6 +1183,14 @@ impl TaskRunner {
         // if fail clear all containers created
         let mut created_containers = Vec::new();
 
+        // init containers run to completion before the workload containers
+        // are created, sidecars among them keep running
+        if let Err(e) = self.sync_run_init_containers(&pod_sandbox_id, &mut created_containers) {
+            self.rollback(&pod_sandbox_id, &created_containers);
+            return Err(e);
+        }
+        let sidecars = created_containers.len();
+
         // Clone containers list to avoid borrow conflict with &mut self
         let containers = self.task.spec.containers.clone();
 
@@ -917,53 +1208,7 @@ impl TaskRunner {
                 }
                 Err(e) => {
                     error!("Failed to create container {}: {}", container.name, e);
-
-                    // Stop all overlay rootfs mounts during rollback
-                    self.stop_all_rootfs_mounts();
-
-                    // delete container created
-                    for container_id in &created_containers {
-                        let delete_args = Delete {
-                            container_id: container_id.clone(),
-                            force: true,
-                        };
-                        let root_path = rootpath::determine(None, &*create_syscall())?;
-                        if let Err(delete_err) = delete(delete_args, root_path.clone()) {
-                            error!(
-                                "Failed to delete container {} during rollback: {}",
-                                container_id, delete_err
-                            );
-                        } else {
-                            info!("Container deleted during rollback: {}", container_id);
-                        }
-                    }
-
-                    // stop pause
-                    let stop_request = StopPodSandboxRequest {
-                        pod_sandbox_id: pod_sandbox_id.clone(),
-                    };
-                    if let Err(stop_err) = self.stop_pod_sandbox(stop_request) {
-                        error!(
-                            "Failed to stop PodSandbox {} during rollback: {}",
-                            pod_sandbox_id, stop_err
-                        );
-                    } else {
-                        info!("PodSandbox stopped during rollback: {}", pod_sandbox_id);
-                    }
-
-                    // delete pause
-                    let remove_request = RemovePodSandboxRequest {
-                        pod_sandbox_id: pod_sandbox_id.clone(),
-                    };
-                    if let Err(remove_err) = self.remove_pod_sandbox(remove_request) {
-                        error!(
-                            "Failed to remove PodSandbox {} during rollback: {}",
-                            pod_sandbox_id, remove_err
-                        );
-                    } else {
-                        info!("PodSandbox deleted during rollback: {}", pod_sandbox_id);
-                    }
-
+                    self.rollback(&pod_sandbox_id, &created_containers);
                     return Err(anyhow!(
                         "Failed to create container {}: {}",
                         container.name,
@@ -974,7 +1219,7 @@ impl TaskRunner {
         }
 
         // start all container
-        for container_id in &created_containers {
+        for container_id in &created_containers[sidecars..] {
             let start_request = StartContainerRequest {
                 container_id: container_id.clone(),
             };
@@ -984,60 +1229,365 @@ impl TaskRunner {
                 }
                 Err(e) => {
                     error!("Failed to start container {}: {}", container_id, e);
+                    self.rollback(&pod_sandbox_id, &created_containers);
+                    return Err(anyhow!("Failed to start container {}: {}", container_id, e));
+                }
+            }
+        }
+
+        Ok((pod_sandbox_id, podip))
+    }
 
-                    // Stop all overlay rootfs mounts during rollback
-                    self.stop_all_rootfs_mounts();
-
-                    for container_id in &created_containers {
-                        let delete_args = Delete {
-                            container_id: container_id.clone(),
-                            force: true,
-                        };
-                        let root_path = rootpath::determine(None, &*create_syscall())?;
-                        if let Err(delete_err) = delete(delete_args, root_path.clone()) {
-                            error!(
-                                "Failed to delete container {} during rollback: {}",
-                                container_id, delete_err
-                            );
-                        } else {
-                            info!("Container deleted during rollback: {}", container_id);
-                        }
+    /// Run the init containers in order, each to successful exit before the
+    /// next one starts. Sidecars are started and left running, their ids are
+    /// added to `created_containers`. The pod is saved beforehand, so that
+    /// deleting it meanwhile stops the init containers from being restarted.
+    pub async fn run_init_containers(
+        &mut self,
+        pod_sandbox_id: &str,
+        created_containers: &mut Vec<String>,
+    ) -> Result<()> {
+        let root_path = rootpath::determine(None, &*create_syscall())?;
+        self.save_pod_info(&root_path, pod_sandbox_id, created_containers)?;
+        let init_containers = self.task.spec.init_containers.clone();
+        for container in &init_containers {
+            for restarts in 0.. {
+                let create_request = self
+                    .build_create_container_request(pod_sandbox_id, container)
+                    .await?;
+                let container_id =
+                    self.start_init_container(create_request, container, restarts)?;
+                if container.is_sidecar() {
+                    created_containers.push(container_id);
+                    self.update_pod_info(&root_path, pod_sandbox_id, created_containers)?;
+                    break;
+                }
+                let started_at = Some(Utc::now());
+                let exit = loop {
+                    let container = load_container(&root_path, &container_id)?;
+                    if container.status() == OciStatus::Stopped {
+                        break resolve_exit_status(&container);
                     }
+                    if let Err(e) = self.ensure_not_deleted(&root_path) {
+                        self.remove_init_container(&root_path, &container_id);
+                        return Err(e);
+                    }
+                    tokio::time::sleep(EXIT_POLL_INTERVAL).await;
+                };
+                let outcome = self.init_container_exited(
+                    &root_path,
+                    &container_id,
+                    started_at,
+                    exit,
+                    restarts,
+                )?;
+                self.update_pod_info(&root_path, pod_sandbox_id, created_containers)?;
+                match outcome {
+                    InitOutcome::Completed => break,
+                    InitOutcome::Restart(delay) => tokio::time::sleep(delay).await,
+                }
+                self.ensure_not_deleted(&root_path)?;
+            }
+        }
+        Ok(())
+    }
 
-                    let stop_request = StopPodSandboxRequest {
-                        pod_sandbox_id: pod_sandbox_id.clone(),
-                    };
-                    if let Err(stop_err) = self.stop_pod_sandbox(stop_request) {
-                        error!(
-                            "Failed to stop PodSandbox {} during rollback: {}",
-                            pod_sandbox_id, stop_err
-                        );
-                    } else {
-                        info!("PodSandbox stopped during rollback: {}", pod_sandbox_id);
+    /// Blocking version of [`TaskRunner::run_init_containers`].
+    pub fn sync_run_init_containers(
+        &mut self,
+        pod_sandbox_id: &str,
+        created_containers: &mut Vec<String>,
+    ) -> Result<()> {
+        let root_path = rootpath::determine(None, &*create_syscall())?;
+        self.save_pod_info(&root_path, pod_sandbox_id, created_containers)?;
+        let init_containers = self.task.spec.init_containers.clone();
+        for container in &init_containers {
+            for restarts in 0.. {
+                let create_request =
+                    self.sync_build_create_container_request(pod_sandbox_id, container)?;
+                let container_id =
+                    self.start_init_container(create_request, container, restarts)?;
+                if container.is_sidecar() {
+                    created_containers.push(container_id);
+                    self.update_pod_info(&root_path, pod_sandbox_id, created_containers)?;
+                    break;
+                }
+                let started_at = Some(Utc::now());
+                let exit = loop {
+                    let container = load_container(&root_path, &container_id)?;
+                    if container.status() == OciStatus::Stopped {
+                        break resolve_exit_status(&container);
+                    }
+                    if let Err(e) = self.ensure_not_deleted(&root_path) {
+                        self.remove_init_container(&root_path, &container_id);
+                        return Err(e);
                     }
+                    std::thread::sleep(EXIT_POLL_INTERVAL);
+                };
+                let outcome = self.init_container_exited(
+                    &root_path,
+                    &container_id,
+                    started_at,
+                    exit,
+                    restarts,
+                )?;
+                self.update_pod_info(&root_path, pod_sandbox_id, created_containers)?;
+                match outcome {
+                    InitOutcome::Completed => break,
+                    InitOutcome::Restart(delay) => std::thread::sleep(delay),
+                }
+                self.ensure_not_deleted(&root_path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Create and start an init container, recording a sidecar as running.
+    fn start_init_container(
+        &mut self,
+        request: CreateContainerRequest,
+        container: &ContainerSpec,
+        restarts: u32,
+    ) -> Result<String> {
+        let container_id = self.create_container(request)?.container_id;
+        self.start_container(StartContainerRequest {
+            container_id: container_id.clone(),
+        })?;
+
+        if container.is_sidecar() {
+            info!("Sidecar container started: {container_id}");
+            self.record_init_container_status(ContainerStatus {
+                name: container_id.clone(),
+                state: Some(ContainerState::Running {
+                    started_at: Some(Utc::now()),
+                }),
+                restart_count: restarts,
+                ..Default::default()
+            });
+        }
+        Ok(container_id)
+    }
+
+    /// Record the exit of an init container and remove it. A failed init
+    /// container is restarted after a backoff, or fails the pod if its
+    /// restart policy is `Never`.
+    fn init_container_exited(
+        &mut self,
+        root_path: &Path,
+        container_id: &str,
+        started_at: Option<DateTime<Utc>>,
+        (exit_code, signal, message): (i32, Option<i32>, Option<String>),
+        restarts: u32,
+    ) -> Result<InitOutcome> {
+        self.remove_init_container(root_path, container_id);
+        self.record_init_container_status(ContainerStatus {
+            name: container_id.to_string(),
+            state: Some(ContainerState::Terminated {
+                exit_code,
+                signal,
+                reason: Some(if exit_code == 0 { "Completed" } else { "Error" }.to_string()),
+                message,
+                started_at,
+                finished_at: Some(Utc::now()),
+            }),
+            ready: exit_code == 0,
+            restart_count: restarts,
+            ..Default::default()
+        });
+
+        if exit_code == 0 {
+            info!("Init container completed: {container_id}");
+            return Ok(InitOutcome::Completed);
+        }
+        if self.task.spec.restart_policy == RestartPolicy::Never {
+            bail!("init container {container_id} failed with exit code {exit_code}");
+        }
+        let delay = INIT_BACKOFF_MAX.min(INIT_BACKOFF_INITIAL * 2u32.saturating_pow(restarts));
+        warn!(
+            "Init container {container_id} failed with exit code {exit_code}, restarting in {}s",
+            delay.as_secs()
+        );
+        Ok(InitOutcome::Restart(delay))
+    }
+
+    /// Save the pod while its init containers run, `created_containers`
+    /// being the sidecars started so far.
+    fn save_pod_info(
+        &self,
+        root_path: &Path,
+        pod_sandbox_id: &str,
+        created_containers: &[String],
+    ) -> Result<()> {
+        let pod_ip = self.pod_ip.clone().unwrap_or_default();
+        PodInfo::new(
+            self,
+            pod_sandbox_id.to_string(),
+            created_containers.to_vec(),
+            &pod_ip,
+        )
+        .save(root_path, &self.task.metadata.name)
+    }
 
-                    let remove_request = RemovePodSandboxRequest {
-                        pod_sandbox_id: pod_sandbox_id.clone(),
+    /// Save the pod again, unless it was deleted meanwhile.
+    fn update_pod_info(
+        &self,
+        root_path: &Path,
+        pod_sandbox_id: &str,
+        created_containers: &[String],
+    ) -> Result<()> {
+        self.ensure_not_deleted(root_path)?;
+        PodInfo::delete(root_path, &self.task.metadata.name)?;
+        self.save_pod_info(root_path, pod_sandbox_id, created_containers)
+    }
+
+    /// Fail if the pod was deleted, or replaced by another one of the same
+    /// name, since it was saved by [`Self::save_pod_info`].
+    fn ensure_not_deleted(&self, root_path: &Path) -> Result<()> {
+        let pod_name = &self.task.metadata.name;
+        let uid = PodInfo::load(root_path, pod_name)
+            .ok()
+            .and_then(|info| info.pod_spec)
+            .map(|pod| pod.metadata.uid);
+        if uid != Some(self.task.metadata.uid) {
+            bail!("pod {pod_name} was deleted while running its init containers");
+        }
+        Ok(())
+    }
+
+    /// Create and start an ephemeral container in the running pod, joining
+    /// the PID namespace of `target_pid` if given. Returns the container id.
+    pub async fn run_ephemeral_container(
+        &mut self,
+        pod_sandbox_id: &str,
+        container: &EphemeralContainer,
+        target_pid: Option<i32>,
+    ) -> Result<String> {
+        let mut spec = container.to_container_spec();
+        spec.name = format!("{}-{}", self.task.metadata.name, container.name);
+        if let Some(pid) = target_pid {
+            self.target_pids.insert(spec.name.clone(), pid);
+        }
+        self.ephemeral_containers.push(spec.clone());
+
+        let create_request = self
+            .build_create_container_request(pod_sandbox_id, &spec)
+            .await?;
+        let container_id = self.create_container(create_request)?.container_id;
+        self.start_container(StartContainerRequest {
+            container_id: container_id.clone(),
+        })?;
+        info!("Ephemeral container started: {container_id}");
+        Ok(container_id)
+    }
+
+    fn record_init_container_status(&mut self, status: ContainerStatus) {
+        match self
+            .init_container_statuses
+            .iter_mut()
+            .find(|s| s.name == status.name)
+        {
+            Some(existing) => *existing = status,
+            None => self.init_container_statuses.push(status),
+        }
+    }
+
+    /// Statuses of the init containers run so far, keyed by the full
+    /// container name.
+    pub fn init_container_statuses(&self) -> &[ContainerStatus] {
+        &self.init_container_statuses
+    }
+
+    /// Remove an init container which exited, along with its rootfs mount.
+    fn remove_init_container(&mut self, root_path: &Path, container_id: &str) {
+        if let Some(mount) = self.rootfs_mounts.remove(container_id)
+            && let Err(e) = mount.stop()
+        {
+            error!("Failed to stop rootfs overlay mount for {container_id}: {e}");
+        }
+        let delete_args = Delete {
+            container_id: container_id.to_string(),
+            force: true,
+        };
+        if let Err(e) = delete(delete_args, root_path.to_path_buf()) {
+            warn!("Failed to delete init container {container_id}: {e}");
+        }
+    }
+
+    /// Names of the containers kept running in the pod: the sidecars, then
+    /// the workload containers.
+    pub fn container_names(&self) -> Vec<String> {
+        self.task
+            .spec
+            .init_containers
+            .iter()
+            .filter(|c| c.is_sidecar())
+            .chain(&self.task.spec.containers)
+            .map(|c| c.name.clone())
+            .collect()
+    }
+
+    /// Remove the containers created so far and the pod sandbox, after the
+    /// pod failed to start.
+    fn rollback(&mut self, pod_sandbox_id: &str, created_containers: &[String]) {
+        // Stop all overlay rootfs mounts during rollback
+        self.stop_all_rootfs_mounts();
+
+        // delete container created
+        match rootpath::determine(None, &*create_syscall()) {
+            Ok(root_path) => {
+                // the pod is saved while its init containers run
+                if self.ensure_not_deleted(&root_path).is_ok()
+                    && let Err(e) = PodInfo::delete(&root_path, &self.task.metadata.name)
+                {
+                    error!("Failed to delete pod info during rollback: {e}");
+                }
+                for container_id in created_containers {
+                    let delete_args = Delete {
+                        container_id: container_id.clone(),
+                        force: true,
                     };
-                    if let Err(remove_err) = self.remove_pod_sandbox(remove_request) {
+                    if let Err(delete_err) = delete(delete_args, root_path.clone()) {
                         error!(
-                            "Failed to remove PodSandbox {} during rollback: {}",
-                            pod_sandbox_id, remove_err
+                            "Failed to delete container {} during rollback: {}",
+                            container_id, delete_err
                         );
                     } else {
-                        info!("PodSandbox deleted during rollback: {}", pod_sandbox_id);
+                        info!("Container deleted during rollback: {}", container_id);
                     }
-
-                    return Err(anyhow!("Failed to start container {}: {}", container_id, e));
                 }
             }
+            Err(e) => error!("Failed to determine root path during rollback: {e}"),
         }
 
-        Ok((pod_sandbox_id, podip))
+        // stop pause
+        let stop_request = StopPodSandboxRequest {
+            pod_sandbox_id: pod_sandbox_id.to_string(),
+        };
+        if let Err(stop_err) = self.stop_pod_sandbox(stop_request) {
+            error!(
+                "Failed to stop PodSandbox {} during rollback: {}",
+                pod_sandbox_id, stop_err
+            );
+        } else {
+            info!("PodSandbox stopped during rollback: {}", pod_sandbox_id);
+        }
+
+        // delete pause
+        let remove_request = RemovePodSandboxRequest {
+            pod_sandbox_id: pod_sandbox_id.to_string(),
+        };
+        if let Err(remove_err) = self.remove_pod_sandbox(remove_request) {
+            error!(
+                "Failed to remove PodSandbox {} during rollback: {}",
+                pod_sandbox_id, remove_err
+            );
+        } else {
+            info!("PodSandbox deleted during rollback: {}", pod_sandbox_id);
+        }
     }
 
     /// Stop all started overlay rootfs mounts (used for rollback cleanup).
-    fn stop_all_rootfs_mounts(&mut self) {
+    pub fn stop_all_rootfs_mounts(&mut self) {
         for (name, mount) in self.rootfs_mounts.drain() {
             if let Err(e) = mount.stop() {
                 error!("Failed to stop rootfs overlay mount for {name}: {e}");
@@ -1046,6 +1596,53 @@ impl TaskRunner {
     }
 }
 
+/// Bundle of the pause container of pod sandbox `sandbox_id`.
+fn sandbox_bundle_dir(root_path: &Path, sandbox_id: &str) -> PathBuf {
+    root_path.join("sandboxes").join(sandbox_id)
+}
+
+/// Write the pause container's `spec` to a bundle of its own, as the spec
+/// holds the pod's user namespace and cgroup. The rootfs is the one of the
+/// pause image bundle at `image_bundle`, used read-only.
+fn write_sandbox_bundle(
+    root_path: &Path,
+    sandbox_id: &str,
+    mut spec: oci_spec::runtime::Spec,
+    image_bundle: &str,
+) -> Result<PathBuf> {
+    if image_bundle.is_empty() || !Path::new(image_bundle).exists() {
+        return Err(anyhow!("Bundle directory does not exist"));
+    }
+    let bundle_dir = sandbox_bundle_dir(root_path, sandbox_id);
+    std::fs::create_dir_all(&bundle_dir)?;
+
+    let root = RootBuilder::default()
+        .path(Path::new(image_bundle).join("rootfs"))
+        .readonly(true)
+        .build()
+        .map_err(|e| anyhow!("failed to build root spec: {e}"))?;
+    spec.set_root(Some(root));
+
+    let file = File::create(bundle_dir.join("config.json"))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, &spec)?;
+    writer.flush()?;
+    Ok(bundle_dir)
+}
+
+/// Remove the bundle written by [`write_sandbox_bundle`].
+pub fn remove_sandbox_bundle(root_path: &Path, sandbox_id: &str) {
+    let bundle_dir = sandbox_bundle_dir(root_path, sandbox_id);
+    if let Err(e) = std::fs::remove_dir_all(&bundle_dir)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        warn!(
+            "Failed to remove sandbox bundle {}: {e}",
+            bundle_dir.display()
+        );
+    }
+}
+
 pub fn get_cni() -> Result<Libcni, anyhow::Error> {
     let plugin_dirs = vec!["/opt/cni/bin".to_string()];
     let plugin_conf_dir = Path::new("/etc/cni/net.d");
@@ -1,3 +1,4 @@
+use crate::metrics::timed;
 use crate::protocol::config::NetworkConfig;
 use anyhow::Result;
 use common::*;
@@ -38,12 +39,14 @@ impl XlineStore {
     pub async fn list_pod_names(&self) -> Result<Vec<String>> {
         let key = "/registry/pods/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(
+        let resp = timed(
+            "get",
+            client.get(
                 key.clone(),
                 Some(GetOptions::new().with_prefix().with_keys_only()),
-            )
-            .await?;
+            ),
+        )
+        .await?;
         Ok(resp
             .kvs()
             .iter()
@@ -55,12 +58,14 @@ impl XlineStore {
     pub async fn list_node_names(&self) -> Result<Vec<String>> {
         let key = "/registry/nodes/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(
+        let resp = timed(
+            "get",
+            client.get(
                 key.clone(),
                 Some(GetOptions::new().with_prefix().with_keys_only()),
-            )
-            .await?;
+            ),
+        )
+        .await?;
         Ok(resp
             .kvs()
             .iter()
@@ -71,9 +76,11 @@ impl XlineStore {
     pub async fn list_nodes(&self) -> Result<Vec<Node>> {
         let key = "/registry/nodes/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(key.clone(), Some(GetOptions::new().with_prefix()))
-            .await?;
+        let resp = timed(
+            "get",
+            client.get(key.clone(), Some(GetOptions::new().with_prefix())),
+        )
+        .await?;
 
         let nodes: Vec<Node> = resp
             .kvs()
@@ -90,9 +97,11 @@ impl XlineStore {
     pub async fn list_pods(&self) -> Result<Vec<PodTask>> {
         let key = "/registry/pods/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(key.clone(), Some(GetOptions::new().with_prefix()))
-            .await?;
+        let resp = timed(
+            "get",
+            client.get(key.clone(), Some(GetOptions::new().with_prefix())),
+        )
+        .await?;
 
         let pods: Vec<PodTask> = resp
             .kvs()
@@ -110,7 +119,7 @@ impl XlineStore {
     pub async fn insert_node_yaml(&self, node_name: &str, node_yaml: &str) -> Result<()> {
         let key = format!("/registry/nodes/{node_name}");
         let mut client = self.client.write().await;
-        client.put(key, node_yaml, Some(PutOptions::new())).await?;
+        timed("put", client.put(key, node_yaml, Some(PutOptions::new()))).await?;
         Ok(())
     }
 
@@ -128,7 +137,7 @@ impl XlineStore {
     pub async fn get_node_yaml(&self, node_name: &str) -> Result<Option<String>> {
         let key = format!("/registry/nodes/{node_name}");
         let mut client = self.client.write().await;
-        let resp = client.get(key, None).await?;
+        let resp = timed("get", client.get(key, None)).await?;
         Ok(resp
             .kvs()
             .first()
@@ -148,7 +157,7 @@ impl XlineStore {
     pub async fn insert_pod_yaml(&self, pod_name: &str, pod_yaml: &str) -> Result<()> {
         let key = format!("/registry/pods/{pod_name}");
         let mut client = self.client.write().await;
-        client.put(key, pod_yaml, Some(PutOptions::new())).await?;
+        timed("put", client.put(key, pod_yaml, Some(PutOptions::new()))).await?;
         Ok(())
     }
 
@@ -156,7 +165,7 @@ impl XlineStore {
     pub async fn get_pod_yaml(&self, pod_name: &str) -> Result<Option<String>> {
         let key = format!("/registry/pods/{pod_name}");
         let mut client = self.client.write().await;
-        let resp = client.get(key, None).await?;
+        let resp = timed("get", client.get(key, None)).await?;
         if let Some(kv) = resp.kvs().first() {
             Ok(Some(String::from_utf8_lossy(kv.value()).to_string()))
         } else {
@@ -172,8 +181,33 @@ impl XlineStore {
         }
     }
 
-    /// Delete a pod from xline.
+    /// Delete a pod gracefully. A pod running on a node only gets its
+    /// deletionTimestamp set, the node then stops its containers within the
+    /// termination grace period and reports back before the pod is removed.
+    /// Other pods are removed right away.
     pub async fn delete_pod(&self, pod_name: &str) -> Result<()> {
+        let Some(yaml) = self.get_pod_yaml(pod_name).await? else {
+            return Ok(());
+        };
+        // Objects that don't parse as pods can't be running anywhere.
+        let Ok(pod) = serde_yaml::from_str::<PodTask>(&yaml) else {
+            return self.force_delete_pod(pod_name).await;
+        };
+        if pod.metadata.deletion_timestamp.is_some() {
+            return Ok(());
+        }
+        if pod.spec.node_name.is_none() || pod.spec.termination_grace_period() == 0 {
+            return self.force_delete_pod(pod_name).await;
+        }
+
+        let mut meta = pod.metadata;
+        meta.deletion_timestamp = Some(chrono::Utc::now());
+        self.update_meta(&format!("/registry/pods/{pod_name}"), &yaml, &meta)
+            .await
+    }
+
+    /// Delete a pod from xline without waiting for its containers to stop.
+    pub async fn force_delete_pod(&self, pod_name: &str) -> Result<()> {
         self.delete_object(
             ResourceKind::Pod,
             pod_name,
@@ -185,7 +219,7 @@ impl XlineStore {
     pub async fn delete_node(&self, node_name: &str) -> Result<()> {
         let key = format!("/registry/nodes/{node_name}");
         let mut client = self.client.write().await;
-        client.delete(key, None).await?;
+        timed("delete", client.delete(key, None)).await?;
         Ok(())
     }
 
@@ -195,14 +229,14 @@ impl XlineStore {
         let value = serde_json::to_string(config)?;
 
         let mut client = self.client.write().await;
-        client.put(key, value, Some(PutOptions::new())).await?;
+        timed("put", client.put(key, value, Some(PutOptions::new()))).await?;
         Ok(())
     }
 
     pub async fn get_network_config(&self, prefix: &str) -> Result<Option<NetworkConfig>> {
         let key = format!("{}/config", prefix.trim_end_matches('/'));
         let mut client = self.client.write().await;
-        let resp = client.get(key, None).await?;
+        let resp = timed("get", client.get(key, None)).await?;
         if let Some(kv) = resp.kvs().first() {
             let cfg: NetworkConfig = serde_json::from_slice(kv.value())?;
             Ok(Some(cfg))
@@ -215,9 +249,11 @@ impl XlineStore {
     pub async fn pods_snapshot_with_rev(&self) -> Result<(Vec<(String, String)>, i64)> {
         let key_prefix = "/registry/pods/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(key_prefix.clone(), Some(GetOptions::new().with_prefix()))
-            .await?;
+        let resp = timed(
+            "get",
+            client.get(key_prefix.clone(), Some(GetOptions::new().with_prefix())),
+        )
+        .await?;
         let rev = resp.header().map(|h| h.revision()).unwrap_or(0);
         let items: Vec<(String, String)> = resp
             .kvs()
@@ -236,9 +272,11 @@ impl XlineStore {
     pub async fn services_snapshot_with_rev(&self) -> Result<(Vec<(String, String)>, i64)> {
         let key_prefix = "/registry/services/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(key_prefix.clone(), Some(GetOptions::new().with_prefix()))
-            .await?;
+        let resp = timed(
+            "get",
+            client.get(key_prefix.clone(), Some(GetOptions::new().with_prefix())),
+        )
+        .await?;
         let rev = resp.header().map(|h| h.revision()).unwrap_or(0);
         let items: Vec<(String, String)> = resp
             .kvs()
@@ -257,9 +295,11 @@ impl XlineStore {
     pub async fn endpoints_snapshot_with_rev(&self) -> Result<(Vec<(String, String)>, i64)> {
         let key_prefix = "/registry/endpoints/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(key_prefix.clone(), Some(GetOptions::new().with_prefix()))
-            .await?;
+        let resp = timed(
+            "get",
+            client.get(key_prefix.clone(), Some(GetOptions::new().with_prefix())),
+        )
+        .await?;
         let rev = resp.header().map(|h| h.revision()).unwrap_or(0);
         let items: Vec<(String, String)> = resp
             .kvs()
@@ -282,7 +322,7 @@ impl XlineStore {
             .with_prev_key()
             .with_start_revision(start_rev);
         let mut client = self.client.write().await;
-        let (watcher, stream) = client.watch(key_prefix, Some(opts)).await?;
+        let (watcher, stream) = timed("watch", client.watch(key_prefix, Some(opts))).await?;
         Ok((watcher, stream))
     }
 
@@ -299,9 +339,7 @@ impl XlineStore {
 
         let key = "/coreos.com/network/config";
         let mut client = self.client.write().await;
-        client
-            .put(key, config_json, Some(PutOptions::new()))
-            .await?;
+        timed("put", client.put(key, config_json, Some(PutOptions::new()))).await?;
         Ok(())
     }
 
@@ -309,12 +347,14 @@ impl XlineStore {
     pub async fn list_service_names(&self) -> Result<Vec<String>> {
         let key = "/registry/services/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(
+        let resp = timed(
+            "get",
+            client.get(
                 key.clone(),
                 Some(GetOptions::new().with_prefix().with_keys_only()),
-            )
-            .await?;
+            ),
+        )
+        .await?;
         Ok(resp
             .kvs()
             .iter()
@@ -326,9 +366,11 @@ impl XlineStore {
     pub async fn list_services(&self) -> Result<Vec<ServiceTask>> {
         let key = "/registry/services/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(key.clone(), Some(GetOptions::new().with_prefix()))
-            .await?;
+        let resp = timed(
+            "get",
+            client.get(key.clone(), Some(GetOptions::new().with_prefix())),
+        )
+        .await?;
 
         let services: Vec<ServiceTask> = resp
             .kvs()
@@ -346,9 +388,11 @@ impl XlineStore {
     pub async fn list_endpoints(&self) -> Result<Vec<Endpoint>> {
         let key = "/registry/endpoints/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(key.clone(), Some(GetOptions::new().with_prefix()))
-            .await?;
+        let resp = timed(
+            "get",
+            client.get(key.clone(), Some(GetOptions::new().with_prefix())),
+        )
+        .await?;
         let endpoints: Vec<Endpoint> = resp
             .kvs()
             .iter()
@@ -375,9 +419,11 @@ impl XlineStore {
     pub async fn insert_service_yaml(&self, service_name: &str, service_yaml: &str) -> Result<()> {
         let key = format!("/registry/services/{service_name}");
         let mut client = self.client.write().await;
-        client
-            .put(key, service_yaml, Some(PutOptions::new()))
-            .await?;
+        timed(
+            "put",
+            client.put(key, service_yaml, Some(PutOptions::new())),
+        )
+        .await?;
         Ok(())
     }
 
@@ -389,16 +435,18 @@ impl XlineStore {
     ) -> Result<()> {
         let key = format!("/registry/endpoints/{endpoint_name}");
         let mut client = self.client.write().await;
-        client
-            .put(key, endpoint_yaml, Some(PutOptions::new()))
-            .await?;
+        timed(
+            "put",
+            client.put(key, endpoint_yaml, Some(PutOptions::new())),
+        )
+        .await?;
         Ok(())
     }
 
     pub async fn get_endpoint_yaml(&self, endpoint_name: &str) -> Result<Option<String>> {
         let key = format!("/registry/endpoints/{endpoint_name}");
         let mut client = self.client.write().await;
-        let resp = client.get(key, None).await?;
+        let resp = timed("get", client.get(key, None)).await?;
         Ok(resp
             .kvs()
             .first()
@@ -409,7 +457,7 @@ impl XlineStore {
     pub async fn delete_endpoint(&self, endpoint_name: &str) -> Result<()> {
         let key = format!("/registry/endpoints/{endpoint_name}");
         let mut client = self.client.write().await;
-        client.delete(key, None).await?;
+        timed("delete", client.delete(key, None)).await?;
         Ok(())
     }
 
@@ -417,7 +465,7 @@ impl XlineStore {
     pub async fn get_service_yaml(&self, service_name: &str) -> Result<Option<String>> {
         let key = format!("/registry/services/{service_name}");
         let mut client = self.client.write().await;
-        let resp = client.get(key, None).await?;
+        let resp = timed("get", client.get(key, None)).await?;
         Ok(resp
             .kvs()
             .first()
@@ -438,7 +486,102 @@ impl XlineStore {
     pub async fn delete_service(&self, service_name: &str) -> Result<()> {
         let key = format!("/registry/services/{service_name}");
         let mut client = self.client.write().await;
-        client.delete(key, None).await?;
+        timed("delete", client.delete(key, None)).await?;
+        Ok(())
+    }
+
+    /// Insert a secret YAML definition into xline.
+    pub async fn insert_secret_yaml(&self, secret_name: &str, secret_yaml: &str) -> Result<()> {
+        let key = format!("/registry/secrets/{secret_name}");
+        let mut client = self.client.write().await;
+        timed("put", client.put(key, secret_yaml, Some(PutOptions::new()))).await?;
+        Ok(())
+    }
+
+    /// Get a secret object from xline.
+    pub async fn get_secret(&self, secret_name: &str) -> Result<Option<Secret>> {
+        let key = format!("/registry/secrets/{secret_name}");
+        let mut client = self.client.write().await;
+        let resp = timed("get", client.get(key, None)).await?;
+        match resp.kvs().first() {
+            Some(kv) => Ok(Some(serde_yaml::from_slice(kv.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// List all secrets (deserialize values).
+    pub async fn list_secrets(&self) -> Result<Vec<Secret>> {
+        let key = "/registry/secrets/".to_string();
+        let mut client = self.client.write().await;
+        let resp = timed(
+            "get",
+            client.get(key.clone(), Some(GetOptions::new().with_prefix())),
+        )
+        .await?;
+        Ok(resp
+            .kvs()
+            .iter()
+            .filter_map(|kv| serde_yaml::from_slice::<Secret>(kv.value()).ok())
+            .collect())
+    }
+
+    /// Delete a secret from xline.
+    pub async fn delete_secret(&self, secret_name: &str) -> Result<()> {
+        let key = format!("/registry/secrets/{secret_name}");
+        let mut client = self.client.write().await;
+        timed("delete", client.delete(key, None)).await?;
+        Ok(())
+    }
+
+    /// Insert a PodDisruptionBudget YAML definition into xline.
+    pub async fn insert_pdb_yaml(
+        &self,
+        namespace: &str,
+        pdb_name: &str,
+        pdb_yaml: &str,
+    ) -> Result<()> {
+        let key = format!("/registry/poddisruptionbudgets/{namespace}/{pdb_name}");
+        let mut client = self.client.write().await;
+        timed("put", client.put(key, pdb_yaml, Some(PutOptions::new()))).await?;
+        Ok(())
+    }
+
+    /// Get a PodDisruptionBudget object from xline.
+    pub async fn get_pdb(
+        &self,
+        namespace: &str,
+        pdb_name: &str,
+    ) -> Result<Option<PodDisruptionBudget>> {
+        let key = format!("/registry/poddisruptionbudgets/{namespace}/{pdb_name}");
+        let mut client = self.client.write().await;
+        let resp = timed("get", client.get(key, None)).await?;
+        match resp.kvs().first() {
+            Some(kv) => Ok(Some(serde_yaml::from_slice(kv.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// List the PodDisruptionBudgets of every namespace (deserialize values).
+    pub async fn list_pdbs(&self) -> Result<Vec<PodDisruptionBudget>> {
+        let key = "/registry/poddisruptionbudgets/".to_string();
+        let mut client = self.client.write().await;
+        let resp = timed(
+            "get",
+            client.get(key.clone(), Some(GetOptions::new().with_prefix())),
+        )
+        .await?;
+        Ok(resp
+            .kvs()
+            .iter()
+            .filter_map(|kv| serde_yaml::from_slice::<PodDisruptionBudget>(kv.value()).ok())
+            .collect())
+    }
+
+    /// Delete a PodDisruptionBudget from xline.
+    pub async fn delete_pdb(&self, namespace: &str, pdb_name: &str) -> Result<()> {
+        let key = format!("/registry/poddisruptionbudgets/{namespace}/{pdb_name}");
+        let mut client = self.client.write().await;
+        timed("delete", client.delete(key, None)).await?;
         Ok(())
     }
 
@@ -450,7 +593,7 @@ impl XlineStore {
             .with_prev_key()
             .with_start_revision(start_rev);
         let mut client = self.client.write().await;
-        let (watcher, stream) = client.watch(key_prefix, Some(opts)).await?;
+        let (watcher, stream) = timed("watch", client.watch(key_prefix, Some(opts))).await?;
         Ok((watcher, stream))
     }
 
@@ -462,7 +605,7 @@ impl XlineStore {
             .with_prev_key()
             .with_start_revision(start_rev);
         let mut client = self.client.write().await;
-        let (watcher, stream) = client.watch(key_prefix, Some(opts)).await?;
+        let (watcher, stream) = timed("watch", client.watch(key_prefix, Some(opts))).await?;
         Ok((watcher, stream))
     }
 
@@ -470,7 +613,7 @@ impl XlineStore {
     pub async fn insert_replicaset_yaml(&self, rs_name: &str, rs_yaml: &str) -> Result<()> {
         let key = format!("/registry/replicasets/{rs_name}");
         let mut client = self.client.write().await;
-        client.put(key, rs_yaml, Some(PutOptions::new())).await?;
+        timed("put", client.put(key, rs_yaml, Some(PutOptions::new()))).await?;
         Ok(())
     }
 
@@ -478,7 +621,7 @@ impl XlineStore {
     pub async fn get_replicaset_yaml(&self, rs_name: &str) -> Result<Option<String>> {
         let key = format!("/registry/replicasets/{rs_name}");
         let mut client = self.client.write().await;
-        let resp = client.get(key, None).await?;
+        let resp = timed("get", client.get(key, None)).await?;
         Ok(resp
             .kvs()
             .first()
@@ -491,7 +634,7 @@ impl XlineStore {
     ) -> Result<Option<(String, i64)>> {
         let key = format!("/registry/replicasets/{rs_name}");
         let mut client = self.client.write().await;
-        let resp = client.get(key, None).await?;
+        let resp = timed("get", client.get(key, None)).await?;
         Ok(resp.kvs().first().map(|kv| {
             (
                 String::from_utf8_lossy(kv.value()).to_string(),
@@ -525,7 +668,7 @@ impl XlineStore {
             .when(vec![cmp])
             .and_then(then_ops)
             .or_else(else_ops);
-        let resp = client.txn(txn).await?;
+        let resp = timed("txn", client.txn(txn)).await?;
         Ok(resp.succeeded())
     }
 
@@ -533,9 +676,11 @@ impl XlineStore {
     pub async fn list_replicasets(&self) -> Result<Vec<ReplicaSet>> {
         let key = "/registry/replicasets/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(key.clone(), Some(GetOptions::new().with_prefix()))
-            .await?;
+        let resp = timed(
+            "get",
+            client.get(key.clone(), Some(GetOptions::new().with_prefix())),
+        )
+        .await?;
 
         let rss: Vec<ReplicaSet> = resp
             .kvs()
@@ -553,9 +698,11 @@ impl XlineStore {
     pub async fn replicasets_snapshot_with_rev(&self) -> Result<(Vec<(String, String)>, i64)> {
         let key_prefix = "/registry/replicasets/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(key_prefix.clone(), Some(GetOptions::new().with_prefix()))
-            .await?;
+        let resp = timed(
+            "get",
+            client.get(key_prefix.clone(), Some(GetOptions::new().with_prefix())),
+        )
+        .await?;
         let rev = resp.header().map(|h| h.revision()).unwrap_or(0);
         let items: Vec<(String, String)> = resp
             .kvs()
@@ -578,7 +725,7 @@ impl XlineStore {
             .with_prev_key()
             .with_start_revision(start_rev);
         let mut client = self.client.write().await;
-        let (watcher, stream) = client.watch(key_prefix, Some(opts)).await?;
+        let (watcher, stream) = timed("watch", client.watch(key_prefix, Some(opts))).await?;
         Ok((watcher, stream))
     }
     /// Get all deployments as a snapshot with the current revision
@@ -586,7 +733,7 @@ impl XlineStore {
         let prefix = "/registry/deployments/";
         let opts = Some(GetOptions::new().with_prefix());
         let mut client = self.client.write().await;
-        let resp = client.get(prefix, opts).await?;
+        let resp = timed("get", client.get(prefix, opts)).await?;
 
         let mut items = Vec::new();
         let rev = resp.header().unwrap().revision();
@@ -608,7 +755,7 @@ impl XlineStore {
             .with_prev_key()
             .with_start_revision(start_rev);
         let mut client = self.client.write().await;
-        let (watcher, stream) = client.watch(key_prefix, Some(opts)).await?;
+        let (watcher, stream) = timed("watch", client.watch(key_prefix, Some(opts))).await?;
         Ok((watcher, stream))
     }
 
@@ -616,9 +763,7 @@ impl XlineStore {
     pub async fn insert_deployment_yaml(&self, deploy_name: &str, deploy_yaml: &str) -> Result<()> {
         let key = format!("/registry/deployments/{deploy_name}");
         let mut client = self.client.write().await;
-        client
-            .put(key, deploy_yaml, Some(PutOptions::new()))
-            .await?;
+        timed("put", client.put(key, deploy_yaml, Some(PutOptions::new()))).await?;
         Ok(())
     }
 
@@ -626,7 +771,7 @@ impl XlineStore {
     pub async fn get_deployment_yaml(&self, deploy_name: &str) -> Result<Option<String>> {
         let key = format!("/registry/deployments/{deploy_name}");
         let mut client = self.client.write().await;
-        let resp = client.get(key, None).await?;
+        let resp = timed("get", client.get(key, None)).await?;
         Ok(resp
             .kvs()
             .first()
@@ -647,9 +792,11 @@ impl XlineStore {
     pub async fn list_deployments(&self) -> Result<Vec<Deployment>> {
         let key = "/registry/deployments/".to_string();
         let mut client = self.client.write().await;
-        let resp = client
-            .get(key.clone(), Some(GetOptions::new().with_prefix()))
-            .await?;
+        let resp = timed(
+            "get",
+            client.get(key.clone(), Some(GetOptions::new().with_prefix())),
+        )
+        .await?;
 
         let deployments: Vec<Deployment> = resp
             .kvs()
@@ -781,7 +928,7 @@ impl XlineStore {
         let updated_yaml = serde_yaml::to_string(&yaml_value)?;
 
         let mut client = self.client.write().await;
-        client.put(key, updated_yaml, None).await?;
+        timed("put", client.put(key, updated_yaml, None)).await?;
         Ok(())
     }
 }
@@ -1,4 +1,5 @@
 use crate::protocol::config::load_config;
+use crate::simulate::OutputFormat;
 use crate::vault::Vault;
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
@@ -17,6 +18,30 @@ pub enum Commands {
         #[arg(short, long)]
         config: PathBuf,
     },
+    /// Simulate where pending pods would be scheduled, without binding them
+    Simulate {
+        /// RKS config providing the xline endpoints and scheduler profiles
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// YAML dump of nodes and pods to use instead of the state in xline
+        #[arg(long)]
+        snapshot: Option<PathBuf>,
+        /// Also place again the pods which are already scheduled
+        #[arg(long)]
+        reschedule_existing: bool,
+        #[arg(short, long, value_enum, default_value_t)]
+        output: OutputFormat,
+    },
+    /// Run the descheduler strategies from the config file once
+    Deschedule {
+        #[arg(short, long)]
+        config: PathBuf,
+        /// Only report which pods would be evicted
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(short, long, value_enum, default_value_t)]
+        output: OutputFormat,
+    },
     /// Generate something
     Gen {
         #[clap(subcommand)]
@@ -13,8 +13,10 @@ pub async fn watch_delete(
     conn: &Connection,
     node_id: &str,
 ) -> Result<()> {
+    // Pods deleted gracefully have been terminated by the worker already.
     if let Ok(pod_task) = serde_yaml::from_str::<PodTask>(&pod_yaml)
         && pod_task.spec.node_name.as_deref() == Some(node_id)
+        && pod_task.metadata.deletion_timestamp.is_none()
     {
         info!(
             target: "rks::node::watch_pods",
@@ -0,0 +1,855 @@
+//! Descheduler: evicts running pods which no longer fit their node, or which
+//! would be better placed on another one, so the scheduler can place them
+//! again.
+//!
+//! Unlike the other controllers it doesn't react to watch events but runs
+//! periodically over a snapshot of nodes, pods and PodDisruptionBudgets. Pods
+//! are only evicted when they have an owner to recreate them and every
+//! PodDisruptionBudget selecting them keeps enough ready pods without them.
+
+use crate::api::xlinestore::XlineStore;
+use crate::simulate::OutputFormat;
+use anyhow::Result;
+use common::{ConditionStatus, Node, PodConditionType, PodDisruptionBudget, PodPhase, PodTask};
+use libscheduler::models::{NodeInfo, PodInfo};
+use libscheduler::predicates::Predicates;
+use libscheduler::with_xline::utils::{
+    convert_k8s_node_to_node_info, convert_pod_task_to_pod_info,
+};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Filters a pod has to pass on a node to be moved there.
+const PLACEMENT_PLUGINS: &[&str] = &[
+    "NodeUnschedulable",
+    "NodeAffinity",
+    "TaintToleration",
+    "NodeResourcesFit",
+    "NodePorts",
+    "PodAffinity",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeschedulerConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_interval_seconds")]
+    pub interval_seconds: u64,
+    /// Only report the evictions instead of doing them.
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub max_pods_to_evict_per_node: Option<usize>,
+    #[serde(default)]
+    pub strategies: Strategies,
+}
+
+fn default_interval_seconds() -> u64 {
+    300
+}
+
+impl Default for DeschedulerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_seconds: default_interval_seconds(),
+            dry_run: false,
+            max_pods_to_evict_per_node: None,
+            strategies: Strategies::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Strategies {
+    /// Spread pods of the same owner which ended up on one node.
+    #[serde(default)]
+    pub remove_duplicates: bool,
+    #[serde(default)]
+    pub remove_pods_violating_node_affinity: bool,
+    #[serde(default)]
+    pub remove_pods_violating_node_taints: bool,
+    #[serde(default)]
+    pub remove_pods_violating_inter_pod_anti_affinity: bool,
+    #[serde(default)]
+    pub low_node_utilization: Option<LowNodeUtilization>,
+    #[serde(default)]
+    pub high_node_utilization: Option<HighNodeUtilization>,
+}
+
+/// Utilization in percent of the allocatable resources of a node.
+/// Resources left unset aren't taken into account.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct Thresholds {
+    #[serde(default)]
+    pub cpu: Option<f64>,
+    #[serde(default)]
+    pub memory: Option<f64>,
+    #[serde(default)]
+    pub pods: Option<f64>,
+}
+
+/// Move pods from nodes above `target_thresholds` to nodes below `thresholds`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LowNodeUtilization {
+    pub thresholds: Thresholds,
+    pub target_thresholds: Thresholds,
+}
+
+/// Empty nodes below `thresholds` by moving their pods to busier nodes,
+/// for use with the `MostAllocated` scoring strategy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighNodeUtilization {
+    pub thresholds: Thresholds,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Eviction {
+    pub pod_name: String,
+    pub node_name: String,
+    pub strategy: String,
+    pub reason: String,
+}
+
+pub struct Descheduler {
+    store: Arc<XlineStore>,
+    config: DeschedulerConfig,
+}
+
+impl Descheduler {
+    pub fn new(store: Arc<XlineStore>, config: DeschedulerConfig) -> Self {
+        Self { store, config }
+    }
+
+    pub fn spawn(self) {
+        tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval(Duration::from_secs(self.config.interval_seconds.max(1)));
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.run_once().await {
+                    error!("descheduler run failed: {e:?}");
+                }
+            }
+        });
+    }
+
+    /// Plan the evictions over the current cluster state and carry them out,
+    /// unless running in dry-run mode.
+    pub async fn run_once(&self) -> Result<Vec<Eviction>> {
+        let nodes = self.store.list_nodes().await?;
+        let pods = self.store.list_pods().await?;
+        let pdbs = self.store.list_pdbs().await?;
+        let evictions = plan(&self.config, nodes, pods, &pdbs);
+        for e in evictions.iter() {
+            if self.config.dry_run {
+                info!(
+                    "[dry-run] would evict pod {} from node {} ({}): {}",
+                    e.pod_name, e.node_name, e.strategy, e.reason
+                );
+                continue;
+            }
+            info!(
+                "Evicting pod {} from node {} ({}): {}",
+                e.pod_name, e.node_name, e.strategy, e.reason
+            );
+            if let Err(err) = self.store.delete_pod(&e.pod_name).await {
+                error!("Failed to evict pod {}: {:?}", e.pod_name, err);
+            }
+        }
+        Ok(evictions)
+    }
+}
+
+/// Print a descheduler report.
+pub fn print_evictions(evictions: &[Eviction], output: OutputFormat) -> Result<()> {
+    match output {
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(evictions)?),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(evictions)?),
+        OutputFormat::Text => {
+            for e in evictions {
+                println!(
+                    "evict pod {} from node {} ({}): {}",
+                    e.pod_name, e.node_name, e.strategy, e.reason
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+struct RunningPod {
+    task: PodTask,
+    info: PodInfo,
+    node_name: String,
+}
+
+impl RunningPod {
+    /// The controller owning the pod, as `Kind/name`.
+    fn owner(&self) -> Option<String> {
+        self.task
+            .metadata
+            .owner_references
+            .as_ref()?
+            .iter()
+            .find(|o| o.controller)
+            .map(|o| format!("{}/{}", o.kind, o.name))
+    }
+}
+
+/// The cluster as the strategies see it, updated as evictions are planned.
+struct Cluster {
+    nodes: Vec<NodeInfo>,
+    pods: Vec<RunningPod>,
+}
+
+impl Cluster {
+    fn new(nodes: Vec<Node>, pods: Vec<PodTask>) -> Self {
+        let mut nodes: Vec<NodeInfo> = nodes
+            .into_iter()
+            .map(convert_k8s_node_to_node_info)
+            .collect();
+        nodes.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut running: Vec<RunningPod> = pods
+            .into_iter()
+            .filter(|p| p.metadata.deletion_timestamp.is_none())
+            .filter_map(|task| {
+                let node_name = task.spec.node_name.clone()?;
+                Some(RunningPod {
+                    info: convert_pod_task_to_pod_info(task.clone()),
+                    task,
+                    node_name,
+                })
+            })
+            .collect();
+        running.sort_by(|a, b| a.info.name.cmp(&b.info.name));
+        let mut cluster = Self {
+            nodes,
+            pods: vec![],
+        };
+        for p in running {
+            cluster.add_usage(&p.node_name, &p.info, 1);
+            cluster.pods.push(p);
+        }
+        cluster
+    }
+
+    fn add_usage(&mut self, node_name: &str, pod: &PodInfo, sign: i64) {
+        if let Some(n) = self.nodes.iter_mut().find(|n| n.name == node_name) {
+            let apply = |v: u64, d: u64| (v as i64 + sign * d as i64).max(0) as u64;
+            n.requested.cpu = apply(n.requested.cpu, pod.spec.resources.cpu);
+            n.requested.memory = apply(n.requested.memory, pod.spec.resources.memory);
+            n.pod_count = apply(n.pod_count, 1);
+        }
+    }
+
+    fn infos(&self) -> Vec<PodInfo> {
+        self.pods.iter().map(|p| p.info.clone()).collect()
+    }
+
+    fn node(&self, name: &str) -> Option<&NodeInfo> {
+        self.nodes.iter().find(|n| n.name == name)
+    }
+
+    fn pods_on(&self, node_name: &str) -> Vec<usize> {
+        let mut res: Vec<usize> = (0..self.pods.len())
+            .filter(|i| self.pods[*i].node_name == node_name)
+            .collect();
+        // Least important pods go first.
+        res.sort_by_key(|i| self.pods[*i].info.spec.priority);
+        res
+    }
+
+    /// Names of the nodes other than the pod's own which it could be moved to.
+    fn feasible_nodes(&self, predicates: &Predicates, idx: usize) -> Vec<String> {
+        let pod = &self.pods[idx];
+        let infos = self.infos();
+        self.nodes
+            .iter()
+            .filter(|n| n.name != pod.node_name)
+            .filter(|n| {
+                predicates
+                    .check(&pod.info, &n.name, &self.nodes, &infos)
+                    .is_ok()
+            })
+            .map(|n| n.name.clone())
+            .collect()
+    }
+
+    /// Drop an evicted pod. With `to`, its resources are accounted on that
+    /// node, where it's expected to be placed again.
+    fn evict(&mut self, idx: usize, to: Option<&str>) -> RunningPod {
+        let pod = self.pods.remove(idx);
+        self.add_usage(&pod.node_name.clone(), &pod.info, -1);
+        if let Some(to) = to {
+            self.add_usage(to, &pod.info, 1);
+        }
+        pod
+    }
+}
+
+fn usage(node: &NodeInfo) -> Thresholds {
+    let percent = |used: u64, total: u64| {
+        if total == 0 {
+            0.0
+        } else {
+            used as f64 * 100.0 / total as f64
+        }
+    };
+    Thresholds {
+        cpu: Some(percent(node.requested.cpu, node.allocatable.cpu)),
+        memory: Some(percent(node.requested.memory, node.allocatable.memory)),
+        pods: Some(percent(node.pod_count, node.max_pods)),
+    }
+}
+
+impl Thresholds {
+    fn pairs(&self, limit: &Thresholds) -> Vec<(f64, f64)> {
+        [
+            (self.cpu, limit.cpu),
+            (self.memory, limit.memory),
+            (self.pods, limit.pods),
+        ]
+        .into_iter()
+        .filter_map(|(v, l)| Some((v?, l?)))
+        .collect()
+    }
+
+    fn all_below(&self, limit: &Thresholds) -> bool {
+        self.pairs(limit).iter().all(|(v, l)| v < l)
+    }
+
+    fn any_above(&self, limit: &Thresholds) -> bool {
+        self.pairs(limit).iter().any(|(v, l)| v > l)
+    }
+}
+
+/// Whether `pdb` selects `pod`.
+fn covers(pdb: &PodDisruptionBudget, pod: &PodTask) -> bool {
+    pdb.metadata.namespace == pod.metadata.namespace
+        && pdb.spec.selector.matches(&pod.metadata.labels)
+}
+
+fn is_healthy(pod: &PodTask) -> bool {
+    pod.status.phase == PodPhase::Running
+        && pod.status.conditions.as_ref().is_some_and(|conds| {
+            conds.iter().any(|c| {
+                c.condition_type == PodConditionType::PodReady && c.status == ConditionStatus::True
+            })
+        })
+}
+
+/// Keeps the evictions of a run within the PodDisruptionBudgets and the per
+/// node limit.
+struct Evictor {
+    /// Each PodDisruptionBudget with the pods it may still lose.
+    budgets: Vec<(PodDisruptionBudget, i32)>,
+    per_node: HashMap<String, usize>,
+    max_per_node: Option<usize>,
+    evictions: Vec<Eviction>,
+}
+
+impl Evictor {
+    fn new(config: &DeschedulerConfig, pods: &[PodTask], pdbs: &[PodDisruptionBudget]) -> Self {
+        let budgets = pdbs
+            .iter()
+            .map(|pdb| {
+                let matching: Vec<&PodTask> = pods
+                    .iter()
+                    .filter(|p| p.metadata.deletion_timestamp.is_none() && covers(pdb, p))
+                    .collect();
+                let healthy = matching.iter().filter(|p| is_healthy(p)).count() as i32;
+                let allowed = healthy - pdb.desired_healthy(matching.len() as i32);
+                (pdb.clone(), allowed)
+            })
+            .collect();
+        Self {
+            budgets,
+            per_node: HashMap::new(),
+            max_per_node: config.max_pods_to_evict_per_node,
+            evictions: vec![],
+        }
+    }
+
+    /// Whether the pod may go. Pods without an owner aren't recreated, so
+    /// they're never evicted.
+    fn can_evict(&self, pod: &RunningPod) -> bool {
+        if pod.owner().is_none() {
+            return false;
+        }
+        let within_budgets = self
+            .budgets
+            .iter()
+            .filter(|(pdb, _)| covers(pdb, &pod.task))
+            .all(|(_, allowed)| *allowed > 0);
+        if !within_budgets {
+            return false;
+        }
+        match self.max_per_node {
+            Some(max) => self.per_node.get(&pod.node_name).copied().unwrap_or(0) < max,
+            None => true,
+        }
+    }
+
+    fn evict(
+        &mut self,
+        cluster: &mut Cluster,
+        idx: usize,
+        to: Option<&str>,
+        strategy: &str,
+        reason: String,
+    ) -> bool {
+        if !self.can_evict(&cluster.pods[idx]) {
+            return false;
+        }
+        let pod = cluster.evict(idx, to);
+        for (pdb, allowed) in self.budgets.iter_mut() {
+            if covers(pdb, &pod.task) {
+                *allowed -= 1;
+            }
+        }
+        *self.per_node.entry(pod.node_name.clone()).or_default() += 1;
+        self.evictions.push(Eviction {
+            pod_name: pod.info.name,
+            node_name: pod.node_name,
+            strategy: strategy.to_string(),
+            reason,
+        });
+        true
+    }
+}
+
+/// Work out which pods to evict. Nothing is changed in the cluster.
+pub fn plan(
+    config: &DeschedulerConfig,
+    nodes: Vec<Node>,
+    pods: Vec<PodTask>,
+    pdbs: &[PodDisruptionBudget],
+) -> Vec<Eviction> {
+    let mut evictor = Evictor::new(config, &pods, pdbs);
+    let mut cluster = Cluster::new(nodes, pods);
+    let strategies = &config.strategies;
+    let placement = Predicates::new(PLACEMENT_PLUGINS);
+
+    if strategies.remove_pods_violating_node_taints {
+        remove_violating(
+            &mut cluster,
+            &mut evictor,
+            &placement,
+            &Predicates::new(&["TaintToleration"]),
+            "RemovePodsViolatingNodeTaints",
+        );
+    }
+    if strategies.remove_pods_violating_node_affinity {
+        remove_violating(
+            &mut cluster,
+            &mut evictor,
+            &placement,
+            &Predicates::new(&["NodeAffinity"]),
+            "RemovePodsViolatingNodeAffinity",
+        );
+    }
+    if strategies.remove_pods_violating_inter_pod_anti_affinity {
+        remove_violating(
+            &mut cluster,
+            &mut evictor,
+            &placement,
+            &Predicates::new(&["PodAffinity"]),
+            "RemovePodsViolatingInterPodAntiAffinity",
+        );
+    }
+    if strategies.remove_duplicates {
+        remove_duplicates(&mut cluster, &mut evictor, &placement);
+    }
+    if let Some(s) = &strategies.low_node_utilization {
+        low_node_utilization(&mut cluster, &mut evictor, &placement, s);
+    }
+    if let Some(s) = &strategies.high_node_utilization {
+        high_node_utilization(&mut cluster, &mut evictor, &placement, s);
+    }
+    evictor.evictions
+}
+
+/// Evict pods failing `violation` on their own node when another node
+/// would take them.
+fn remove_violating(
+    cluster: &mut Cluster,
+    evictor: &mut Evictor,
+    placement: &Predicates,
+    violation: &Predicates,
+    strategy: &str,
+) {
+    let mut idx = 0;
+    while idx < cluster.pods.len() {
+        let pod = &cluster.pods[idx];
+        let infos = cluster.infos();
+        let checked = violation.check(&pod.info, &pod.node_name, &cluster.nodes, &infos);
+        if let Err(rejection) = checked
+            && !cluster.feasible_nodes(placement, idx).is_empty()
+        {
+            let reason = format!("{}: {}", rejection.plugin, rejection.reasons.join(", "));
+            if evictor.evict(cluster, idx, None, strategy, reason) {
+                continue;
+            }
+        }
+        idx += 1;
+    }
+}
+
+/// Evict all but one pod of each owner from every node, if the others can
+/// go elsewhere.
+fn remove_duplicates(cluster: &mut Cluster, evictor: &mut Evictor, placement: &Predicates) {
+    let node_names: Vec<String> = cluster.nodes.iter().map(|n| n.name.clone()).collect();
+    for node_name in node_names {
+        let mut seen = HashSet::new();
+        let mut duplicates = Vec::new();
+        for idx in cluster.pods_on(&node_name).into_iter().rev() {
+            if let Some(owner) = cluster.pods[idx].owner()
+                && !seen.insert(owner.clone())
+            {
+                duplicates.push((cluster.pods[idx].info.name.clone(), owner));
+            }
+        }
+        for (pod_name, owner) in duplicates {
+            let Some(idx) = cluster.pods.iter().position(|p| p.info.name == pod_name) else {
+                continue;
+            };
+            if cluster.feasible_nodes(placement, idx).is_empty() {
+                continue;
+            }
+            evictor.evict(
+                cluster,
+                idx,
+                None,
+                "RemoveDuplicates",
+                format!("another pod of {owner} runs on the node"),
+            );
+        }
+    }
+}
+
+fn low_node_utilization(
+    cluster: &mut Cluster,
+    evictor: &mut Evictor,
+    placement: &Predicates,
+    s: &LowNodeUtilization,
+) {
+    let underutilized: Vec<String> = cluster
+        .nodes
+        .iter()
+        .filter(|n| !n.spec.unschedulable && usage(n).all_below(&s.thresholds))
+        .map(|n| n.name.clone())
+        .collect();
+    let overutilized: Vec<String> = cluster
+        .nodes
+        .iter()
+        .filter(|n| usage(n).any_above(&s.target_thresholds))
+        .map(|n| n.name.clone())
+        .collect();
+    if underutilized.is_empty() {
+        return;
+    }
+
+    for node_name in overutilized {
+        for pod_name in pod_names_on(cluster, &node_name) {
+            let still_over = cluster
+                .node(&node_name)
+                .is_some_and(|n| usage(n).any_above(&s.target_thresholds));
+            if !still_over {
+                break;
+            }
+            let Some(idx) = cluster.pods.iter().position(|p| p.info.name == pod_name) else {
+                continue;
+            };
+            // The pod must fit an underutilized node without pushing it over the target.
+            let target = cluster
+                .feasible_nodes(placement, idx)
+                .into_iter()
+                .filter(|n| underutilized.contains(n))
+                .find(|n| {
+                    cluster.node(n).is_some_and(|node| {
+                        let mut node = node.clone();
+                        node.requested.cpu += cluster.pods[idx].info.spec.resources.cpu;
+                        node.requested.memory += cluster.pods[idx].info.spec.resources.memory;
+                        node.pod_count += 1;
+                        !usage(&node).any_above(&s.target_thresholds)
+                    })
+                });
+            if let Some(target) = target {
+                evictor.evict(
+                    cluster,
+                    idx,
+                    Some(&target),
+                    "LowNodeUtilization",
+                    format!("node is overutilized, {target} is underutilized"),
+                );
+            }
+        }
+    }
+}
+
+fn high_node_utilization(
+    cluster: &mut Cluster,
+    evictor: &mut Evictor,
+    placement: &Predicates,
+    s: &HighNodeUtilization,
+) {
+    let underutilized: Vec<String> = cluster
+        .nodes
+        .iter()
+        .filter(|n| usage(n).all_below(&s.thresholds))
+        .map(|n| n.name.clone())
+        .collect();
+    if underutilized.len() == cluster.nodes.len() {
+        return;
+    }
+
+    for node_name in underutilized.iter() {
+        for pod_name in pod_names_on(cluster, node_name) {
+            let Some(idx) = cluster.pods.iter().position(|p| p.info.name == pod_name) else {
+                continue;
+            };
+            let target = cluster
+                .feasible_nodes(placement, idx)
+                .into_iter()
+                .find(|n| !underutilized.contains(n));
+            if let Some(target) = target {
+                evictor.evict(
+                    cluster,
+                    idx,
+                    Some(&target),
+                    "HighNodeUtilization",
+                    format!("node is underutilized, pod can be packed onto {target}"),
+                );
+            }
+        }
+    }
+}
+
+fn pod_names_on(cluster: &Cluster, node_name: &str) -> Vec<String> {
+    cluster
+        .pods_on(node_name)
+        .into_iter()
+        .map(|i| cluster.pods[i].info.name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_node(name: &str, taints: &str) -> Node {
+        serde_yaml::from_str(&format!(
+            r#"
+apiVersion: v1
+kind: Node
+metadata:
+  name: {name}
+spec:
+  podCIDR: 10.0.0.0/24
+  taints: {taints}
+status:
+  capacity:
+    cpu: "4"
+    memory: 8Gi
+    pods: "10"
+  allocatable:
+    cpu: "4"
+    memory: 8Gi
+    pods: "10"
+"#
+        ))
+        .unwrap()
+    }
+
+    fn make_pod(name: &str, node: &str, owner: &str) -> PodTask {
+        serde_yaml::from_str(&format!(
+            r#"
+apiVersion: v1
+kind: Pod
+metadata:
+  name: {name}
+  labels:
+    app: {owner}
+  ownerReferences:
+    - apiVersion: v1
+      kind: ReplicaSet
+      name: {owner}
+      uid: 00000000-0000-0000-0000-000000000000
+      controller: true
+spec:
+  node_name: {node}
+  containers:
+    - name: app
+      image: nginx
+      resources:
+        limits:
+          cpu: 500m
+          memory: 256Mi
+status:
+  phase: Running
+  conditions:
+    - type: PodReady
+      status: "True"
+"#
+        ))
+        .unwrap()
+    }
+
+    fn make_pdb(app: &str, budget: &str) -> PodDisruptionBudget {
+        serde_yaml::from_str(&format!(
+            r#"
+apiVersion: policy/v1
+kind: PodDisruptionBudget
+metadata:
+  name: {app}-pdb
+spec:
+  selector:
+    matchLabels:
+      app: {app}
+  {budget}
+"#
+        ))
+        .unwrap()
+    }
+
+    const NO_SCHEDULE_TAINT: &str = "[{key: NodeNotReady, effect: NoSchedule}]";
+
+    #[test]
+    fn test_remove_pods_violating_node_taints() {
+        let mut config = DeschedulerConfig::default();
+        config.strategies.remove_pods_violating_node_taints = true;
+        let evictions = plan(
+            &config,
+            vec![
+                make_node("node1", NO_SCHEDULE_TAINT),
+                make_node("node2", "[]"),
+            ],
+            vec![
+                make_pod("pod1", "node1", "rs"),
+                make_pod("pod2", "node2", "rs"),
+            ],
+            &[],
+        );
+        assert_eq!(evictions.len(), 1);
+        assert_eq!(evictions[0].pod_name, "pod1");
+        assert_eq!(evictions[0].strategy, "RemovePodsViolatingNodeTaints");
+    }
+
+    #[test]
+    fn test_remove_duplicates_respects_pod_disruption_budget() {
+        let mut config = DeschedulerConfig::default();
+        config.strategies.remove_duplicates = true;
+        let pods = vec![
+            make_pod("pod1", "node1", "rs"),
+            make_pod("pod2", "node1", "rs"),
+            make_pod("pod3", "node1", "rs"),
+        ];
+        let evictions = plan(
+            &config,
+            vec![make_node("node1", "[]"), make_node("node2", "[]")],
+            pods,
+            &[make_pdb("rs", "maxUnavailable: 1")],
+        );
+        // Two duplicates, but the budget only allows one pod to be unavailable.
+        assert_eq!(evictions.len(), 1);
+        assert_eq!(evictions[0].strategy, "RemoveDuplicates");
+    }
+
+    #[test]
+    fn test_pods_without_owner_are_kept() {
+        let mut config = DeschedulerConfig::default();
+        let mut pod = make_pod("pod1", "node1", "rs");
+        pod.metadata.owner_references = None;
+        config.strategies.remove_pods_violating_node_taints = true;
+        let evictions = plan(
+            &config,
+            vec![
+                make_node("node1", NO_SCHEDULE_TAINT),
+                make_node("node2", "[]"),
+            ],
+            vec![pod],
+            &[],
+        );
+        assert!(evictions.is_empty());
+    }
+
+    #[test]
+    fn test_pod_disruption_budget_counts_ready_pods() {
+        let mut config = DeschedulerConfig::default();
+        config.strategies.remove_pods_violating_node_taints = true;
+        let mut not_ready = make_pod("pod2", "node2", "rs");
+        not_ready.status = Default::default();
+        let nodes = || {
+            vec![
+                make_node("node1", NO_SCHEDULE_TAINT),
+                make_node("node2", "[]"),
+            ]
+        };
+        let pods = || vec![make_pod("pod1", "node1", "rs"), not_ready.clone()];
+        // One of two pods is ready, so evicting it leaves none.
+        let evictions = plan(
+            &config,
+            nodes(),
+            pods(),
+            &[make_pdb("rs", "minAvailable: 1")],
+        );
+        assert!(evictions.is_empty());
+        let evictions = plan(
+            &config,
+            nodes(),
+            pods(),
+            &[make_pdb("rs", "minAvailable: 0")],
+        );
+        assert_eq!(evictions.len(), 1);
+        // Budgets of other pods don't apply.
+        let evictions = plan(
+            &config,
+            nodes(),
+            pods(),
+            &[make_pdb("web", "minAvailable: 1")],
+        );
+        assert_eq!(evictions.len(), 1);
+        let evictions = plan(
+            &config,
+            nodes(),
+            pods(),
+            &[make_pdb("rs", "maxUnavailable: \"50%\"")],
+        );
+        assert!(evictions.is_empty());
+    }
+
+    #[test]
+    fn test_low_node_utilization() {
+        let mut config = DeschedulerConfig::default();
+        config.strategies.low_node_utilization = Some(LowNodeUtilization {
+            thresholds: Thresholds {
+                cpu: Some(20.0),
+                ..Default::default()
+            },
+            target_thresholds: Thresholds {
+                cpu: Some(50.0),
+                ..Default::default()
+            },
+        });
+        let pods = (0..6)
+            .map(|i| make_pod(&format!("pod{i}"), "node1", "rs"))
+            .collect();
+        let evictions = plan(
+            &config,
+            vec![make_node("node1", "[]"), make_node("node2", "[]")],
+            pods,
+            &[make_pdb("rs", "maxUnavailable: 2")],
+        );
+        // 6 * 500m on 4 cpus is 75%, moving 2 pods brings it to 50%.
+        assert_eq!(evictions.len(), 2);
+        assert!(
+            evictions
+                .iter()
+                .all(|e| e.node_name == "node1" && e.strategy == "LowNodeUtilization")
+        );
+    }
+}
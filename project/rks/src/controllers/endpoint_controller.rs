@@ -380,7 +380,8 @@ fn build_endpoints_from_service_and_pods(svc: &ServiceTask, pods: &[PodTask]) ->
             selector_match(selector, &svc.metadata.namespace, pod)
         };
 
-        if !matched {
+        // Stop sending traffic to terminating pods while they drain.
+        if !matched || pod.metadata.deletion_timestamp.is_some() {
             continue;
         }
 
@@ -1,11 +1,12 @@
 use crate::api::xlinestore::XlineStore;
+use crate::metrics::CONTROLLER_RECONCILE_DURATION;
 use anyhow::Result;
 use async_trait::async_trait;
 use common::ResourceKind;
 use once_cell::sync::Lazy;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::{RwLock, mpsc, watch};
 use tokio::time::sleep;
 
@@ -369,8 +370,11 @@ impl ControllerManager {
                                 // spawn a new task to handle the watch response
                                 tokio::spawn(async move {
                                     if let Err(e) = retry_with_backoff(|| async {
-                                        controller.write().await.handle_watch_response(&resp).await?;
-                                        Ok(())
+                                        let start = Instant::now();
+                                        let res = controller.write().await.handle_watch_response(&resp).await;
+                                        let result = if res.is_ok() { "success" } else { "error" };
+                                        CONTROLLER_RECONCILE_DURATION.observe_since(&[name.as_str(), result], start);
+                                        res
                                     }).await {
                                         log::error!(
                                             "controller {} handle watch response {} failed: {:?}",
@@ -1094,6 +1098,16 @@ impl ControllerManager {
         let _ = self.stop_tx.send(true);
     }
 
+    /// Returns the number of events waiting in the work queue of each controller.
+    pub async fn queue_depths(&self) -> Vec<(String, usize)> {
+        self.queues
+            .read()
+            .await
+            .iter()
+            .map(|(name, tx)| (name.clone(), tx.max_capacity() - tx.capacity()))
+            .collect()
+    }
+
     /// Gets all queue senders for controllers that need to watch the specified resource kind.
     ///
     /// This method iterates through all registered controllers, finds those whose `watch_resources`
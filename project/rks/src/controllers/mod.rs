@@ -1,4 +1,5 @@
 pub mod deployment;
+pub mod descheduler;
 pub mod replicaset;
 pub use deployment::DeploymentController;
 pub use replicaset::ReplicaSetController;
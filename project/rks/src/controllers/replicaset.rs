@@ -107,7 +107,8 @@ impl ReplicaSetController {
         let mut orphan_pods = Vec::new();
 
         for pod in pods {
-            if !Self::selector_match(rs, &pod) {
+            // Terminating pods no longer count towards the replicas.
+            if !Self::selector_match(rs, &pod) || pod.metadata.deletion_timestamp.is_some() {
                 continue;
             }
 
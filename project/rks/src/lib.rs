@@ -3,10 +3,12 @@ pub mod cli;
 pub mod commands;
 pub mod controllers;
 pub mod dns;
+pub mod metrics;
 pub mod network;
 pub mod node;
 pub mod protocol;
 pub mod scheduler;
+pub mod simulate;
 pub mod vault;
 
 #[allow(dead_code)]
@@ -4,12 +4,15 @@ mod commands;
 mod controllers;
 mod dns;
 mod internal;
+mod metrics;
 mod network;
 mod node;
 mod protocol;
 mod scheduler;
+mod simulate;
 mod vault;
 
+use crate::controllers::descheduler::{Descheduler, print_evictions};
 use crate::controllers::endpoint_controller::EndpointController;
 use crate::controllers::garbage_collector::GarbageCollector;
 use crate::controllers::{
@@ -21,14 +24,17 @@ use crate::network::init;
 use crate::network::manager::LocalManager;
 use crate::node::{NodeRegistry, RksNode, Shared};
 use crate::protocol::config::{Config, config_ref, load_config};
+use crate::simulate::{OutputFormat, print_placements};
 use crate::{api::xlinestore::XlineStore, scheduler::Scheduler, vault::Vault};
 use anyhow::Context;
 use clap::Parser;
 use cli::{Cli, Commands};
-use libscheduler::plugins::{Plugins, node_resources_fit::ScoringStrategy};
+use libscheduler::simulation::Snapshot;
+use libscheduler::with_xline::simulate_with_xline;
 use libvault::storage::xline::XlineOptions;
 use log::{error, info};
 use rustls::crypto::CryptoProvider;
+use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -46,6 +52,27 @@ async fn main() -> anyhow::Result<()> {
             load_config(config.to_str().unwrap())?;
             handle_start_command().await?;
         }
+        Commands::Simulate {
+            config,
+            snapshot,
+            reschedule_existing,
+            output,
+        } => {
+            let cfg = config
+                .as_ref()
+                .map(|c| load_config(c.to_str().unwrap()))
+                .transpose()?;
+            handle_simulate_command(cfg, snapshot.as_deref(), *reschedule_existing, *output)
+                .await?;
+        }
+        Commands::Deschedule {
+            config,
+            dry_run,
+            output,
+        } => {
+            let cfg = load_config(config.to_str().unwrap())?;
+            handle_deschedule_command(cfg, *dry_run, *output).await?;
+        }
         Commands::Gen { sub } => sub.handle().await?,
     }
 
@@ -63,7 +90,7 @@ async fn handle_start_command() -> anyhow::Result<()> {
     info!(target: "rks::main", "listening on {}", cfg.addr);
 
     let local_manager = init_local_manager(cfg, &xline_options).await?;
-    launch_scheduler(xline_options, xline_store.clone()).await?;
+    launch_scheduler(cfg, xline_options, xline_store.clone()).await?;
 
     let node_registry = Arc::new(NodeRegistry::default());
 
@@ -78,6 +105,9 @@ async fn handle_start_command() -> anyhow::Result<()> {
         .clone()
         .start_watch(xline_store.clone())
         .await?;
+    if cfg.descheduler_config.enabled {
+        Descheduler::new(xline_store.clone(), cfg.descheduler_config.clone()).spawn();
+    }
 
     let shared = Arc::new(Shared::new(
         xline_store.clone(),
@@ -87,9 +117,58 @@ async fn handle_start_command() -> anyhow::Result<()> {
     ));
 
     internal::start_internal_server(vault.clone()).await?;
+    metrics::start_metrics_server(&cfg.metrics_addr).await?;
     RksNode::new(cfg.addr.clone(), shared).run().await
 }
 
+async fn handle_simulate_command(
+    cfg: Option<&Config>,
+    snapshot: Option<&Path>,
+    reschedule_existing: bool,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let profiles = cfg
+        .map(|c| c.scheduler_config.clone())
+        .unwrap_or_default()
+        .build_profiles()
+        .context("Invalid scheduler_config")?;
+    let placements = match (snapshot, cfg) {
+        (Some(path), _) => {
+            let content = tokio::fs::read_to_string(path)
+                .await
+                .with_context(|| format!("Failed to read snapshot {}", path.display()))?;
+            Snapshot::from_yaml(&content)?
+                .simulate(profiles, reschedule_existing)
+                .await
+        }
+        (None, Some(cfg)) => {
+            let (xline_options, _) = prepare_xline_options(cfg).await?;
+            simulate_with_xline(xline_options, profiles, reschedule_existing).await?
+        }
+        (None, None) => anyhow::bail!("either --config or --snapshot is required"),
+    };
+    print_placements(&placements, output)
+}
+
+async fn handle_deschedule_command(
+    cfg: &Config,
+    dry_run: bool,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let (xline_options, _) = prepare_xline_options(cfg).await?;
+    let xline_store = Arc::new(
+        XlineStore::new(xline_options)
+            .await
+            .with_context(|| "Failed to connect xline")?,
+    );
+    let mut descheduler_config = cfg.descheduler_config.clone();
+    descheduler_config.dry_run |= dry_run;
+    let evictions = Descheduler::new(xline_store, descheduler_config)
+        .run_once()
+        .await?;
+    print_evictions(&evictions, output)
+}
+
 async fn prepare_xline_options(cfg: &Config) -> anyhow::Result<(XlineOptions, Option<Arc<Vault>>)> {
     let mut option = XlineOptions::new(cfg.xline_config.endpoints.clone());
 
@@ -158,17 +237,17 @@ async fn init_local_manager(
 }
 
 async fn launch_scheduler(
+    cfg: &Config,
     option: XlineOptions,
     xline_store: Arc<XlineStore>,
 ) -> anyhow::Result<()> {
-    let scheduler = Scheduler::try_new(
-        option,
-        xline_store,
-        ScoringStrategy::LeastAllocated,
-        Plugins::default(),
-    )
-    .await
-    .context("Failed to create Scheduler")?;
+    let profiles = cfg
+        .scheduler_config
+        .build_profiles()
+        .context("Invalid scheduler_config")?;
+    let scheduler = Scheduler::try_new_with_profiles(option, xline_store, profiles)
+        .await
+        .context("Failed to create Scheduler")?;
 
     scheduler.run().await;
     Ok(())
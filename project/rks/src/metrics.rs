@@ -0,0 +1,98 @@
+//! Prometheus metrics of rks, served on `/metrics` at `metrics_addr`.
+//!
+//! Covers the controller queues and reconciles, the scheduler (whose metrics
+//! live in `libscheduler::metrics`), requests to xline and the connected
+//! workers. Metric and label names are part of the interface dashboards are
+//! built on, keep them stable.
+
+use std::future::Future;
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use axum::{Router, http::header, response::IntoResponse, routing::get};
+use common::log_error;
+use common::metrics::{DEFAULT_BUCKETS, Gauge, Histogram, Metric, Registry, registry, render};
+use log::info;
+
+use crate::controllers::CONTROLLER_MANAGER;
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Events waiting in the work queue of each controller, sampled on scrape.
+pub static CONTROLLER_QUEUE_DEPTH: Gauge = Gauge::new(
+    "rks_controller_queue_depth",
+    "Number of events waiting in the work queue of a controller.",
+    &["controller"],
+);
+
+/// Latency of one `handle_watch_response` attempt, `result` is `success`
+/// or `error`. Retries are observed separately.
+pub static CONTROLLER_RECONCILE_DURATION: Histogram = Histogram::new(
+    "rks_controller_reconcile_duration_seconds",
+    "Latency of a controller reconcile in seconds, by controller and result.",
+    &["controller", "result"],
+    DEFAULT_BUCKETS,
+);
+
+pub static XLINE_REQUEST_DURATION: Histogram = Histogram::new(
+    "rks_xline_request_duration_seconds",
+    "Latency of a request to xline in seconds, by operation and result.",
+    &["operation", "result"],
+    DEFAULT_BUCKETS,
+);
+
+pub static CONNECTED_NODES: Gauge = Gauge::new(
+    "rks_connected_nodes",
+    "Number of workers connected to rks.",
+    &[],
+);
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(|| {
+    let metrics: [&dyn Metric; 4] = [
+        &CONTROLLER_QUEUE_DEPTH,
+        &CONTROLLER_RECONCILE_DURATION,
+        &XLINE_REQUEST_DURATION,
+        &CONNECTED_NODES,
+    ];
+    let metrics = metrics
+        .into_iter()
+        .chain(libscheduler::metrics::metrics())
+        .collect::<Vec<_>>();
+    registry(&metrics)
+});
+
+/// Await the xline request `request` and observe its latency.
+pub async fn timed<T, E, F>(operation: &'static str, request: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let start = Instant::now();
+    let res = request.await;
+    let result = if res.is_ok() { "success" } else { "error" };
+    XLINE_REQUEST_DURATION.observe_since(&[operation, result], start);
+    res
+}
+
+/// Start serving `/metrics` on `addr` in the background. An empty address
+/// disables the endpoint.
+pub async fn start_metrics_server(addr: &str) -> anyhow::Result<()> {
+    if addr.is_empty() {
+        info!(target: "rks::metrics", "metrics endpoint disabled");
+        return Ok(());
+    }
+    let router = Router::new().route("/metrics", get(metrics));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tokio::spawn(async move {
+        log_error!(axum::serve(listener, router).await);
+    });
+
+    info!(target: "rks::metrics", "metrics are served on {addr}/metrics");
+    Ok(())
+}
+
+async fn metrics() -> impl IntoResponse {
+    for (controller, depth) in CONTROLLER_MANAGER.queue_depths().await {
+        CONTROLLER_QUEUE_DEPTH.set(&[controller.as_str()], depth as f64);
+    }
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], render(&REGISTRY))
+}
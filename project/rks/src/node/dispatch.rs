@@ -1,5 +1,6 @@
 use crate::api::xlinestore::XlineStore;
 use crate::commands::{create, delete};
+use crate::node::{NodeRegistry, migrate};
 use chrono::Utc;
 use common::quic::RksConnection;
 use common::*;
@@ -12,6 +13,7 @@ pub async fn dispatch_worker(
     msg: RksMessage,
     conn: &RksConnection,
     xline_store: &Arc<XlineStore>,
+    node_registry: &Arc<NodeRegistry>,
 ) -> anyhow::Result<()> {
     match msg {
         RksMessage::Heartbeat { node_name, status } => {
@@ -44,6 +46,26 @@ pub async fn dispatch_worker(
                 );
             }
         }
+        RksMessage::PodLogsRes(res) => node_registry.complete_pod_logs(*res).await,
+        RksMessage::UpdateNodeStats(stats) => node_registry.update_stats(*stats).await,
+        RksMessage::UpdatePodStatus {
+            pod_name, status, ..
+        } => {
+            if !update_pod_status(xline_store, &pod_name, status).await? {
+                warn!(
+                    target: "rks::node::worker_dispatch",
+                    "Pod {pod_name} not found when updating status"
+                );
+            }
+        }
+        RksMessage::UpdateEphemeralContainerStatus { pod_name, status } => {
+            if !update_ephemeral_container_status(xline_store, &pod_name, *status).await? {
+                warn!(
+                    target: "rks::node::worker_dispatch",
+                    "Pod {pod_name} not found when updating ephemeral container status"
+                );
+            }
+        }
         _ => warn!(
             target: "rks::node::worker_dispatch",
             "unknown or unexpected message from worker"
@@ -57,6 +79,7 @@ pub async fn dispatch_user(
     msg: RksMessage,
     conn: &RksConnection,
     xline_store: &Arc<XlineStore>,
+    node_registry: &Arc<NodeRegistry>,
 ) -> anyhow::Result<()> {
     match msg {
         RksMessage::CreatePod(pod_task) => {
@@ -94,6 +117,64 @@ pub async fn dispatch_user(
                     .await?;
             }
         }
+        RksMessage::GetPodLogs(req) => {
+            let node_name = match xline_store.get_pod(&req.pod_name).await? {
+                Some(pod) => pod.spec.node_name,
+                None => {
+                    conn.send_msg(&RksMessage::Error(format!(
+                        "Pod {} not found",
+                        req.pod_name
+                    )))
+                    .await?;
+                    return Ok(());
+                }
+            };
+            let Some(node_name) = node_name else {
+                conn.send_msg(&RksMessage::Error(format!(
+                    "Pod {} is not scheduled to a node yet",
+                    req.pod_name
+                )))
+                .await?;
+                return Ok(());
+            };
+            match node_registry.request_pod_logs(&node_name, *req).await {
+                Ok(res) => {
+                    conn.send_msg(&RksMessage::PodLogsRes(Box::new(res)))
+                        .await?
+                }
+                Err(e) => conn.send_msg(&RksMessage::Error(e.to_string())).await?,
+            }
+        }
+        RksMessage::MigratePod {
+            pod_name,
+            node_name,
+        } => match migrate::migrate_pod(&pod_name, node_name, xline_store, node_registry).await {
+            Ok(pod) => conn.send_msg(&RksMessage::GetPodRes(Box::new(pod))).await?,
+            Err(e) => {
+                error!(
+                    target: "rks::node::user_dispatch",
+                    "migration of Pod {pod_name} failed: {e:#}"
+                );
+                conn.send_msg(&RksMessage::Error(format!("{e:#}"))).await?
+            }
+        },
+        RksMessage::DebugPod {
+            pod_name,
+            container,
+        } => match add_ephemeral_container(xline_store, &pod_name, *container).await {
+            Ok(pod) => {
+                info!(
+                    target: "rks::node::user_dispatch",
+                    "added ephemeral container to Pod {pod_name}"
+                );
+                conn.send_msg(&RksMessage::GetPodRes(Box::new(pod))).await?
+            }
+            Err(e) => conn.send_msg(&RksMessage::Error(format!("{e:#}"))).await?,
+        },
+        RksMessage::GetNodeStats => {
+            let stats = node_registry.list_stats().await;
+            conn.send_msg(&RksMessage::NodeStatsRes(stats)).await?;
+        }
         RksMessage::ListPod => {
             let pods = xline_store.list_pods().await?;
             info!(
@@ -421,6 +502,107 @@ pub async fn dispatch_user(
             conn.send_msg(&RksMessage::ListServiceRes(services)).await?;
         }
 
+        // Secret operations
+        RksMessage::CreateSecret(mut secret) => {
+            let name = secret.metadata.name.clone();
+            if xline_store.get_secret(&name).await?.is_some() {
+                let err_msg = format!("secret \"{name}\" already exists");
+                conn.send_msg(&RksMessage::Error(err_msg)).await?;
+                return Ok(());
+            }
+            if secret.metadata.creation_timestamp.is_none() {
+                secret.metadata.creation_timestamp = Some(Utc::now());
+            }
+            let yaml = serde_yaml::to_string(&*secret)?;
+            xline_store.insert_secret_yaml(&name, &yaml).await?;
+            info!(
+                target: "rks::node::user_dispatch",
+                "created Secret {name}"
+            );
+            conn.send_msg(&RksMessage::Ack).await?;
+        }
+
+        RksMessage::DeleteSecret(name) => {
+            xline_store.delete_secret(&name).await?;
+            info!(
+                target: "rks::node::user_dispatch",
+                "deleted Secret {name}"
+            );
+            conn.send_msg(&RksMessage::Ack).await?;
+        }
+
+        RksMessage::GetSecret(name) => {
+            if let Some(secret) = xline_store.get_secret(&name).await? {
+                conn.send_msg(&RksMessage::GetSecretRes(Box::new(secret)))
+                    .await?;
+            } else {
+                conn.send_msg(&RksMessage::Error(format!("Secret {} not found", name)))
+                    .await?;
+            }
+        }
+
+        RksMessage::ListSecret => {
+            let secrets = xline_store.list_secrets().await?;
+            info!(
+                target: "rks::node::user_dispatch",
+                "list current secrets: {} items",
+                secrets.len()
+            );
+            conn.send_msg(&RksMessage::ListSecretRes(secrets)).await?;
+        }
+
+        // PodDisruptionBudget operations
+        RksMessage::CreatePodDisruptionBudget(mut pdb) => {
+            let name = pdb.metadata.name.clone();
+            let namespace = pdb.metadata.namespace.clone();
+            if xline_store.get_pdb(&namespace, &name).await?.is_some() {
+                let err_msg = format!(
+                    "poddisruptionbudget \"{name}\" already exists in namespace {namespace}"
+                );
+                conn.send_msg(&RksMessage::Error(err_msg)).await?;
+                return Ok(());
+            }
+            if pdb.spec.min_available.is_some() && pdb.spec.max_unavailable.is_some() {
+                let err_msg = format!(
+                    "poddisruptionbudget \"{name}\": minAvailable and maxUnavailable cannot both be set"
+                );
+                conn.send_msg(&RksMessage::Error(err_msg)).await?;
+                return Ok(());
+            }
+            if pdb.metadata.creation_timestamp.is_none() {
+                pdb.metadata.creation_timestamp = Some(Utc::now());
+            }
+            let yaml = serde_yaml::to_string(&*pdb)?;
+            xline_store
+                .insert_pdb_yaml(&namespace, &name, &yaml)
+                .await?;
+            info!(
+                target: "rks::node::user_dispatch",
+                "created PodDisruptionBudget {namespace}/{name}"
+            );
+            conn.send_msg(&RksMessage::Ack).await?;
+        }
+
+        RksMessage::DeletePodDisruptionBudget { namespace, name } => {
+            xline_store.delete_pdb(&namespace, &name).await?;
+            info!(
+                target: "rks::node::user_dispatch",
+                "deleted PodDisruptionBudget {namespace}/{name}"
+            );
+            conn.send_msg(&RksMessage::Ack).await?;
+        }
+
+        RksMessage::ListPodDisruptionBudget => {
+            let pdbs = xline_store.list_pdbs().await?;
+            info!(
+                target: "rks::node::user_dispatch",
+                "list current poddisruptionbudgets: {} items",
+                pdbs.len()
+            );
+            conn.send_msg(&RksMessage::ListPodDisruptionBudgetRes(pdbs))
+                .await?;
+        }
+
         RksMessage::GetNodeCount => {
             info!(
                 target: "rks::node::user_dispatch",
@@ -430,23 +612,13 @@ pub async fn dispatch_user(
         RksMessage::UpdatePodStatus {
             pod_name,
             pod_namespace,
-            mut status,
+            status,
         } => {
             info!(
                 target: "rks::node::user_dispatch",
                 "UpdatePodStatus received for Pod {}/{}", pod_namespace, pod_name
             );
-            // Update the pod status in xline store
-            if let Some(pod_yaml) = xline_store.get_pod_yaml(&pod_name).await? {
-                let mut pod_task: PodTask = serde_yaml::from_str(&pod_yaml)?;
-                // Preserve existing pod_ip if the incoming status does not carry it.
-                // This avoids wiping pod_ip set by SetPodip.
-                if status.pod_ip.is_none() {
-                    status.pod_ip = pod_task.status.pod_ip.clone();
-                }
-                pod_task.status = status;
-                let new_yaml = serde_yaml::to_string(&pod_task)?;
-                xline_store.insert_pod_yaml(&pod_name, &new_yaml).await?;
+            if update_pod_status(xline_store, &pod_name, status).await? {
                 info!(
                     target: "rks::node::user_dispatch",
                     "updated PodTask {}/{} status", pod_namespace, pod_name
@@ -472,6 +644,86 @@ pub async fn dispatch_user(
     Ok(())
 }
 
+/// Store a pod status reported by a worker, returns false if the pod does
+/// not exist. A pod marked for deletion is removed once it reaches a terminal
+/// phase, its containers are gone by then.
+async fn update_pod_status(
+    xline_store: &Arc<XlineStore>,
+    pod_name: &str,
+    mut status: PodStatus,
+) -> anyhow::Result<bool> {
+    let Some(pod_yaml) = xline_store.get_pod_yaml(pod_name).await? else {
+        return Ok(false);
+    };
+    let mut pod_task: PodTask = serde_yaml::from_str(&pod_yaml)?;
+    if pod_task.metadata.deletion_timestamp.is_some()
+        && matches!(status.phase, PodPhase::Succeeded | PodPhase::Failed)
+    {
+        info!(
+            target: "rks::node::dispatch",
+            "Pod {pod_name} terminated, removing it"
+        );
+        xline_store.force_delete_pod(pod_name).await?;
+        return Ok(true);
+    }
+
+    // Preserve existing pod_ip if the incoming status does not carry it.
+    // This avoids wiping pod_ip set by SetPodip.
+    if status.pod_ip.is_none() {
+        status.pod_ip = pod_task.status.pod_ip.clone();
+    }
+    // Ephemeral container statuses are reported separately.
+    if status.ephemeral_container_statuses.is_empty() {
+        status.ephemeral_container_statuses =
+            std::mem::take(&mut pod_task.status.ephemeral_container_statuses);
+    }
+    pod_task.status = status;
+    let new_yaml = serde_yaml::to_string(&pod_task)?;
+    xline_store.insert_pod_yaml(pod_name, &new_yaml).await?;
+    Ok(true)
+}
+
+/// Add an ephemeral container to a running pod. The node running the pod
+/// starts it when it sees the updated spec.
+async fn add_ephemeral_container(
+    xline_store: &Arc<XlineStore>,
+    pod_name: &str,
+    container: EphemeralContainer,
+) -> anyhow::Result<PodTask> {
+    let Some(mut pod) = xline_store.get_pod(pod_name).await? else {
+        anyhow::bail!("Pod {pod_name} not found");
+    };
+    if pod.spec.node_name.is_none() {
+        anyhow::bail!("Pod {pod_name} is not scheduled to a node yet");
+    }
+    if pod.metadata.deletion_timestamp.is_some() {
+        anyhow::bail!("Pod {pod_name} is terminating");
+    }
+    pod.add_ephemeral_container(container)
+        .map_err(|e| anyhow::anyhow!("invalid ephemeral container: {e}"))?;
+    let new_yaml = serde_yaml::to_string(&pod)?;
+    xline_store.insert_pod_yaml(pod_name, &new_yaml).await?;
+    Ok(pod)
+}
+
+async fn update_ephemeral_container_status(
+    xline_store: &Arc<XlineStore>,
+    pod_name: &str,
+    status: ContainerStatus,
+) -> anyhow::Result<bool> {
+    let Some(mut pod) = xline_store.get_pod(pod_name).await? else {
+        return Ok(false);
+    };
+    let statuses = &mut pod.status.ephemeral_container_statuses;
+    match statuses.iter_mut().find(|s| s.name == status.name) {
+        Some(existing) => *existing = status,
+        None => statuses.push(status),
+    }
+    let new_yaml = serde_yaml::to_string(&pod)?;
+    xline_store.insert_pod_yaml(pod_name, &new_yaml).await?;
+    Ok(true)
+}
+
 async fn handle_heartbeat(
     xline_store: &Arc<XlineStore>,
     node_name: &str,
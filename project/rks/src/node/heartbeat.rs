@@ -1,9 +1,14 @@
 use crate::api::xlinestore::XlineStore;
-use common::{Node, Taint, TaintEffect, TaintKey};
+use chrono::{DateTime, Utc};
+use common::{ConditionStatus, Node, PodTask, Taint, TaintEffect, TaintKey};
 use log::{error, info, warn};
 use std::sync::Arc;
 use std::time::Duration;
 
+/// Time the node of a terminating pod has on top of the pod's grace period
+/// to report that its containers stopped.
+const TERMINATION_MARGIN: Duration = Duration::from_secs(30);
+
 pub fn watch(xline_store: Arc<XlineStore>, grace: Duration, interval: Duration) {
     tokio::spawn(async move {
         let mut ticker = tokio::time::interval(interval);
@@ -28,9 +33,56 @@ async fn handle_node_heartbeats(
             persist_node(&xline_store, &node_id, &node).await?;
         }
     }
+    force_delete_expired_pods(&xline_store, Utc::now()).await
+}
+
+/// Remove the terminating pods whose node isn't ready and didn't confirm
+/// they stopped within their grace period, which it never will while it's
+/// down or unreachable.
+async fn force_delete_expired_pods(
+    xline_store: &Arc<XlineStore>,
+    now: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    for pod in xline_store.list_pods().await? {
+        if !termination_expired(&pod, now) {
+            continue;
+        }
+        let node = match &pod.spec.node_name {
+            Some(node_name) => xline_store.get_node(node_name).await?,
+            None => None,
+        };
+        if node.as_ref().is_some_and(is_ready) {
+            continue;
+        }
+        warn!(
+            "Force deleting pod {}, its node {} didn't confirm termination",
+            pod.metadata.name,
+            pod.spec.node_name.as_deref().unwrap_or("<none>")
+        );
+        if let Err(e) = xline_store.force_delete_pod(&pod.metadata.name).await {
+            error!("Failed to force delete pod {}: {:?}", pod.metadata.name, e);
+        }
+    }
     Ok(())
 }
 
+/// Whether the terminating `pod` waited longer than its grace period plus
+/// [`TERMINATION_MARGIN`] at `now`.
+fn termination_expired(pod: &PodTask, now: DateTime<Utc>) -> bool {
+    let Some(deleted_at) = pod.metadata.deletion_timestamp else {
+        return false;
+    };
+    let grace = Duration::from_secs(pod.spec.termination_grace_period()) + TERMINATION_MARGIN;
+    now.signed_duration_since(deleted_at)
+        .to_std()
+        .is_ok_and(|waited| waited > grace)
+}
+
+fn is_ready(node: &Node) -> bool {
+    node.ready_condition()
+        .is_some_and(|cond| matches!(cond.status, ConditionStatus::True))
+}
+
 fn process_heartbeat_timeout(node: &mut Node, grace: Duration) -> bool {
     if node.update_ready_status_on_timeout(grace) {
         // If expired is true, it means out of date; change status only when timeout is reached
@@ -82,9 +134,72 @@ pub(crate) async fn evict_pods_for_node(node_id: &str, xline_store: Arc<XlineSto
         if !has_toleration {
             // Evict if no toleration found
             info!("Evicting pod {} from node {}", pod.metadata.name, node_id);
-            if let Err(e) = xline_store.delete_pod(&pod.metadata.name).await {
+            // The node is gone, there is nobody left to stop the containers.
+            if let Err(e) = xline_store.force_delete_pod(&pod.metadata.name).await {
                 error!("Failed to evict pod {}: {:?}", pod.metadata.name, e);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_pod(grace: u64, deleted_secs_ago: Option<i64>) -> PodTask {
+        let mut pod: PodTask = serde_yaml::from_str(&format!(
+            r#"
+apiVersion: v1
+kind: Pod
+metadata:
+  name: pod
+spec:
+  node_name: node1
+  terminationGracePeriodSeconds: {grace}
+  containers:
+    - name: app
+      image: nginx
+"#
+        ))
+        .unwrap();
+        pod.metadata.deletion_timestamp =
+            deleted_secs_ago.map(|secs| Utc::now() - chrono::Duration::seconds(secs));
+        pod
+    }
+
+    fn make_node(ready: &str) -> Node {
+        serde_yaml::from_str(&format!(
+            r#"
+apiVersion: v1
+kind: Node
+metadata:
+  name: node1
+spec:
+  podCIDR: 10.0.0.0/24
+status:
+  capacity: {{}}
+  allocatable: {{}}
+  conditions:
+    - type: Ready
+      status: "{ready}"
+"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_termination_expired() {
+        let now = Utc::now();
+        assert!(!termination_expired(&make_pod(30, None), now));
+        assert!(!termination_expired(&make_pod(30, Some(45)), now));
+        assert!(termination_expired(&make_pod(30, Some(61)), now));
+        assert!(termination_expired(&make_pod(0, Some(31)), now));
+    }
+
+    #[test]
+    fn test_is_ready() {
+        assert!(is_ready(&make_node("True")));
+        assert!(!is_ready(&make_node("False")));
+        assert!(!is_ready(&make_node("Unknown")));
+    }
+}
@@ -0,0 +1,225 @@
+//! Live migration of pods between workers.
+//!
+//! The pod is checkpointed on its node, which removes it there, and restored
+//! on a node picked by the scheduler. The checkpoint archive passes through
+//! rks, buffered in a temporary file. If the target can't restore the pod it
+//! is restored on its original node again.
+
+use crate::api::xlinestore::XlineStore;
+use crate::node::NodeRegistry;
+use crate::protocol::config::config_ref;
+use anyhow::{Context, bail};
+use common::PodTask;
+use common::session::{Frame, SessionRecvExt, SessionRequest, SessionSendExt};
+use libscheduler::simulation::Snapshot;
+use log::{info, warn};
+use std::io::SeekFrom;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// Size of the `Stdin` frames the archive is sent to the target in.
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Move `pod_name` to `node_name`, or to the node the scheduler prefers.
+/// Returns the pod as stored after the move.
+pub async fn migrate_pod(
+    pod_name: &str,
+    node_name: Option<String>,
+    xline_store: &XlineStore,
+    node_registry: &NodeRegistry,
+) -> anyhow::Result<PodTask> {
+    let pod = xline_store
+        .get_pod(pod_name)
+        .await?
+        .with_context(|| format!("Pod {pod_name} not found"))?;
+    if pod.metadata.deletion_timestamp.is_some() {
+        bail!("Pod {pod_name} is being deleted");
+    }
+    let source = pod
+        .spec
+        .node_name
+        .clone()
+        .with_context(|| format!("Pod {pod_name} is not scheduled to a node yet"))?;
+    let target = match node_name {
+        Some(target) if target == source => bail!("Pod {pod_name} already runs on {target}"),
+        Some(target) => {
+            if node_registry.get(&target).await.is_none() {
+                bail!("No active worker for {target}");
+            }
+            target
+        }
+        None => choose_target(&pod, &source, xline_store, node_registry).await?,
+    };
+    info!("migrating Pod {pod_name} from {source} to {target}");
+
+    let mut archive = File::from_std(tempfile::tempfile()?);
+    checkpoint(pod_name, &source, &mut archive, node_registry)
+        .await
+        .with_context(|| format!("Failed to checkpoint Pod {pod_name} on {source}"))?;
+
+    let (node_name, pod_ip) = match restore(pod_name, &target, &mut archive, node_registry).await {
+        Ok(pod_ip) => (target, pod_ip),
+        Err(e) => {
+            warn!(
+                "Failed to restore Pod {pod_name} on {target}: {e:#}, moving it back to {source}"
+            );
+            let pod_ip = restore(pod_name, &source, &mut archive, node_registry)
+                .await
+                .with_context(|| {
+                    format!("Failed to restore Pod {pod_name} on {target} ({e:#}) and on {source}")
+                })?;
+            update_pod(pod_name, &source, pod_ip, xline_store).await?;
+            return Err(e.context(format!(
+                "Failed to restore Pod {pod_name} on {target}, it is back on {source}"
+            )));
+        }
+    };
+    let pod = update_pod(pod_name, &node_name, pod_ip, xline_store).await?;
+    info!("migrated Pod {pod_name} to {node_name}");
+    Ok(pod)
+}
+
+/// Let the scheduler place the pod as if it were pending, on any worker
+/// but its current one.
+async fn choose_target(
+    pod: &PodTask,
+    source: &str,
+    xline_store: &XlineStore,
+    node_registry: &NodeRegistry,
+) -> anyhow::Result<String> {
+    let mut nodes = Vec::new();
+    for node in xline_store.list_nodes().await? {
+        if node.metadata.name != source && node_registry.get(&node.metadata.name).await.is_some() {
+            nodes.push(node);
+        }
+    }
+    let mut pods = xline_store.list_pods().await?;
+    for p in &mut pods {
+        if p.metadata.name == pod.metadata.name {
+            p.spec.node_name = None;
+        }
+    }
+    let profiles = config_ref()
+        .scheduler_config
+        .build_profiles()
+        .context("Invalid scheduler_config")?;
+    let placement = Snapshot { nodes, pods }
+        .simulate(profiles, false)
+        .await
+        .into_iter()
+        .find(|p| p.pod_name == pod.metadata.name)
+        .with_context(|| {
+            format!(
+                "Pod {} was not considered by the scheduler",
+                pod.metadata.name
+            )
+        })?;
+    placement.node_name.with_context(|| {
+        format!(
+            "No node to migrate Pod {} to: {}",
+            pod.metadata.name,
+            placement.reasons.join("; ")
+        )
+    })
+}
+
+/// Checkpoint the pod on `node_name` into `archive`.
+async fn checkpoint(
+    pod_name: &str,
+    node_name: &str,
+    archive: &mut File,
+    node_registry: &NodeRegistry,
+) -> anyhow::Result<()> {
+    let (mut send, mut recv) = open_stream(node_name, node_registry).await?;
+    send.send_request(&SessionRequest::Checkpoint {
+        pod_name: pod_name.to_string(),
+        tcp_established: true,
+    })
+    .await?;
+    send.finish()?;
+    loop {
+        match recv.recv_frame().await? {
+            Some(Frame::Stdout(data)) => archive.write_all(&data).await?,
+            Some(Frame::Exit(0)) => break,
+            Some(Frame::Exit(code)) => bail!("checkpoint exited with {code}"),
+            Some(Frame::Error(e)) => bail!(e),
+            Some(other) => warn!("unexpected checkpoint frame {other:?}"),
+            None => bail!("{node_name} closed the stream before the checkpoint was done"),
+        }
+    }
+    archive.flush().await?;
+    Ok(())
+}
+
+/// Restore the pod on `node_name` from `archive`, returns its new IP.
+async fn restore(
+    pod_name: &str,
+    node_name: &str,
+    archive: &mut File,
+    node_registry: &NodeRegistry,
+) -> anyhow::Result<String> {
+    let (mut send, mut recv) = open_stream(node_name, node_registry).await?;
+    send.send_request(&SessionRequest::Restore {
+        pod_name: pod_name.to_string(),
+    })
+    .await?;
+    archive.seek(SeekFrom::Start(0)).await?;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = archive.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        send.send_frame(&Frame::Stdin(buf[..n].to_vec())).await?;
+    }
+    send.send_frame(&Frame::CloseStdin).await?;
+    send.finish()?;
+
+    let mut pod_ip = Vec::new();
+    loop {
+        match recv.recv_frame().await? {
+            Some(Frame::Stdout(data)) => pod_ip.extend_from_slice(&data),
+            Some(Frame::Exit(0)) => break,
+            Some(Frame::Exit(code)) => bail!("restore exited with {code}"),
+            Some(Frame::Error(e)) => bail!(e),
+            Some(other) => warn!("unexpected restore frame {other:?}"),
+            None => bail!("{node_name} closed the stream before the restore was done"),
+        }
+    }
+    Ok(String::from_utf8(pod_ip)?)
+}
+
+async fn open_stream(
+    node_name: &str,
+    node_registry: &NodeRegistry,
+) -> anyhow::Result<(quinn::SendStream, quinn::RecvStream)> {
+    let worker = node_registry
+        .get(node_name)
+        .await
+        .with_context(|| format!("No active worker for {node_name}"))?;
+    let stream = worker
+        .conn
+        .open_bi()
+        .await
+        .with_context(|| format!("Failed to open session stream to {node_name}"))?;
+    Ok(stream.into_inner())
+}
+
+async fn update_pod(
+    pod_name: &str,
+    node_name: &str,
+    pod_ip: String,
+    xline_store: &XlineStore,
+) -> anyhow::Result<PodTask> {
+    let pod_yaml = xline_store
+        .get_pod_yaml(pod_name)
+        .await?
+        .with_context(|| format!("Pod {pod_name} was deleted during the migration"))?;
+    let mut pod: PodTask = serde_yaml::from_str(&pod_yaml)?;
+    pod.spec.node_name = Some(node_name.to_string());
+    pod.status.pod_ip = Some(pod_ip).filter(|ip| !ip.is_empty());
+    xline_store
+        .insert_pod_yaml(pod_name, &serde_yaml::to_string(&pod)?)
+        .await?;
+    Ok(pod)
+}
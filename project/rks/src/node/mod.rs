@@ -1,10 +1,13 @@
 use crate::api::xlinestore::XlineStore;
+use crate::metrics::CONNECTED_NODES;
 use crate::network::manager::LocalManager;
 use crate::node::lease_sync::LeaseSynchronizer;
 use crate::node::server::QUICServer;
 use crate::vault::Vault;
-use common::RksMessage;
+use anyhow::Context;
 use common::lease::Lease;
+use common::quic::RksConnection;
+use common::{NodeStats, PodLogsRequest, PodLogsResponse, RksMessage};
 use log::info;
 use log::warn;
 use nftables::{batch::Batch, schema, types};
@@ -12,27 +15,33 @@ use serde_json::json;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
-use tokio::sync::{Mutex, Notify, mpsc};
+use tokio::sync::{Mutex, Notify, mpsc, oneshot};
 
 pub mod cert;
 mod dispatch;
 mod heartbeat;
 mod lease_sync;
+mod migrate;
 mod register;
 mod server;
+mod session;
 mod watcher;
 
 #[derive(Clone)]
 pub struct WorkerSession {
+    /// Connection of the worker, used to open session streams to it.
+    pub conn: RksConnection,
     pub tx: mpsc::Sender<RksMessage>,
     pub cancel_notify: Arc<Notify>,
     pub lease: Arc<Mutex<Lease>>,
 }
 
 impl WorkerSession {
-    pub fn new(tx: mpsc::Sender<RksMessage>, lease: Lease) -> Self {
+    pub fn new(conn: RksConnection, tx: mpsc::Sender<RksMessage>, lease: Lease) -> Self {
         Self {
+            conn,
             tx,
             lease: Arc::new(Mutex::new(lease)),
             cancel_notify: Arc::new(Notify::new()),
@@ -40,9 +49,17 @@ impl WorkerSession {
     }
 }
 
+/// How long a user waits for a worker to answer a logs request.
+const POD_LOGS_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(Default)]
 pub struct NodeRegistry {
     inner: Mutex<HashMap<String, Arc<WorkerSession>>>,
+    /// Logs requests forwarded to workers, keyed by request id.
+    pending_logs: Mutex<HashMap<u64, oneshot::Sender<PodLogsResponse>>>,
+    next_request_id: AtomicU64,
+    /// Latest resource usage reported by each worker.
+    stats: Mutex<HashMap<String, NodeStats>>,
 }
 
 #[allow(unused)]
@@ -50,13 +67,17 @@ impl NodeRegistry {
     pub async fn register(&self, node_id: String, session: Arc<WorkerSession>) {
         let mut inner = self.inner.lock().await;
         inner.insert(node_id, session);
+        CONNECTED_NODES.set(&[], inner.len() as f64);
     }
 
     pub async fn unregister(&self, node_id: &str) {
         let session = {
             let mut inner = self.inner.lock().await;
-            inner.remove(node_id)
+            let session = inner.remove(node_id);
+            CONNECTED_NODES.set(&[], inner.len() as f64);
+            session
         };
+        self.stats.lock().await.remove(node_id);
 
         if let Some(session) = session {
             let cleanup_rules = build_delete_table_ruleset();
@@ -76,6 +97,73 @@ impl NodeRegistry {
         inner.get(node_id).cloned()
     }
 
+    /// Forward a logs request to the worker `node_id` and wait for its answer.
+    pub async fn request_pod_logs(
+        &self,
+        node_id: &str,
+        mut req: PodLogsRequest,
+    ) -> anyhow::Result<PodLogsResponse> {
+        let session = self
+            .get(node_id)
+            .await
+            .with_context(|| format!("No active worker for {node_id}"))?;
+        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed) + 1;
+        req.request_id = request_id;
+
+        let (tx, rx) = oneshot::channel();
+        self.pending_logs.lock().await.insert(request_id, tx);
+        let result = async {
+            session
+                .tx
+                .send(RksMessage::GetPodLogs(Box::new(req)))
+                .await
+                .with_context(|| format!("Failed to enqueue message for {node_id}"))?;
+            tokio::time::timeout(POD_LOGS_TIMEOUT, rx)
+                .await
+                .with_context(|| format!("timed out waiting for logs from {node_id}"))?
+                .with_context(|| format!("worker {node_id} dropped the logs request"))
+        }
+        .await;
+        self.pending_logs.lock().await.remove(&request_id);
+        result
+    }
+
+    /// Hand a worker's logs response to the request waiting for it.
+    pub async fn complete_pod_logs(&self, res: PodLogsResponse) {
+        match self.pending_logs.lock().await.remove(&res.request_id) {
+            Some(tx) => {
+                let _ = tx.send(res);
+            }
+            None => warn!(
+                "no pending logs request with id {}, dropping response",
+                res.request_id
+            ),
+        }
+    }
+
+    /// Record the resource usage reported by a worker.
+    pub async fn update_stats(&self, stats: NodeStats) {
+        self.stats
+            .lock()
+            .await
+            .insert(stats.node_name.clone(), stats);
+    }
+
+    /// The latest resource usage of the connected workers, by node name.
+    pub async fn list_stats(&self) -> Vec<NodeStats> {
+        let nodes = self.inner.lock().await;
+        let mut stats = self
+            .stats
+            .lock()
+            .await
+            .values()
+            .filter(|stats| nodes.contains_key(&stats.node_name))
+            .cloned()
+            .collect::<Vec<_>>();
+        stats.sort_by(|a, b| a.node_name.cmp(&b.node_name));
+        stats
+    }
+
     /// Return a snapshot of all registered worker sessions.
     pub async fn list_sessions(&self) -> Vec<(String, Arc<WorkerSession>)> {
         let inner = self.inner.lock().await;
@@ -89,7 +89,7 @@ impl<'a> NodeRegister<'a> {
             self.conn.remote_address().ip()
         );
 
-        let session = Arc::new(WorkerSession::new(msg_tx.clone(), lease));
+        let session = Arc::new(WorkerSession::new(self.conn.clone(), msg_tx.clone(), lease));
         self.shared
             .node_registry
             .register(node_id.clone(), session.clone())
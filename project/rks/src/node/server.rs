@@ -3,6 +3,7 @@ use crate::node::cert::build_quic_config;
 use crate::node::dispatch::{dispatch_user, dispatch_worker};
 use crate::node::register::NodeRegister;
 use crate::node::server::private::Sealed;
+use crate::node::session::serve_sessions;
 use crate::node::watcher::PodsWatcher;
 use crate::protocol::config::config_ref;
 use crate::vault::{CertRole, Vault};
@@ -206,11 +207,27 @@ impl AuthConnection<Verified> {
             info!("fetched message: {msg}");
 
             if is_worker {
-                log_error!(dispatch_worker(msg, &self.conn, &self.shared.xline_store).await);
+                log_error!(
+                    dispatch_worker(
+                        msg,
+                        &self.conn,
+                        &self.shared.xline_store,
+                        &self.shared.node_registry
+                    )
+                    .await
+                );
                 continue;
             }
 
-            log_error!(dispatch_user(msg, &self.conn, &self.shared.xline_store).await)
+            log_error!(
+                dispatch_user(
+                    msg,
+                    &self.conn,
+                    &self.shared.xline_store,
+                    &self.shared.node_registry
+                )
+                .await
+            )
         }
     }
 }
@@ -263,6 +280,10 @@ impl ConnectionState for Verified {
             // Start watching pods if this is a registered worker node
             let watcher = PodsWatcher::new(node_id, conn.conn.clone(), conn.shared.clone());
             watcher.spawn()?;
+        } else {
+            // Users may open interactive sessions next to their requests.
+            // They are only accepted here, on a verified user connection.
+            tokio::spawn(serve_sessions(conn.conn.clone(), conn.shared.clone()));
         }
 
         conn.dispatch_loop(is_worker).await
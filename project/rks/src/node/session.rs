@@ -0,0 +1,89 @@
+//! Relay of interactive sessions (exec, attach, port-forward) from users to
+//! the worker running the pod.
+
+use crate::node::Shared;
+use anyhow::Context;
+use common::log_error;
+use common::quic::{RksConnection, RksStream};
+use common::session::{Frame, SessionRecvExt, SessionRequest, SessionSendExt};
+use log::{debug, info};
+use quinn::{RecvStream, SendStream};
+use std::sync::Arc;
+
+/// Accept session streams opened by an authenticated user connection and
+/// relay each of them to the owning worker, until the connection is closed.
+pub async fn serve_sessions(conn: RksConnection, shared: Arc<Shared>) {
+    while let Ok(stream) = conn.accept_bi().await {
+        let shared = shared.clone();
+        tokio::spawn(async move { log_error!(relay(stream, &shared).await) });
+    }
+    debug!("session listener for {} stopped", conn.remote_address());
+}
+
+async fn relay(stream: RksStream, shared: &Shared) -> anyhow::Result<()> {
+    let (mut send, mut recv) = stream.into_inner();
+    let req = recv.recv_request().await?;
+    info!("relaying session {req:?}");
+    if matches!(
+        req,
+        SessionRequest::Checkpoint { .. } | SessionRequest::Restore { .. }
+    ) {
+        send.send_frame(&Frame::Error(
+            "checkpoint and restore sessions are reserved for pod migration".to_string(),
+        ))
+        .await?;
+        send.finish()?;
+        return Ok(());
+    }
+
+    let (mut worker_send, mut worker_recv) = match open_worker_stream(&req, shared).await {
+        Ok(stream) => stream.into_inner(),
+        Err(e) => {
+            send.send_frame(&Frame::Error(e.to_string())).await?;
+            send.finish()?;
+            return Ok(());
+        }
+    };
+    worker_send.send_request(&req).await?;
+
+    let upstream = copy(&mut recv, &mut worker_send);
+    let downstream = copy(&mut worker_recv, &mut send);
+    tokio::pin!(upstream, downstream);
+    // The session is over once the worker finishes its side, the user may
+    // still be holding stdin open at that point.
+    tokio::select! {
+        res = &mut downstream => return res,
+        res = &mut upstream => res?,
+    }
+    downstream.await
+}
+
+async fn open_worker_stream(req: &SessionRequest, shared: &Shared) -> anyhow::Result<RksStream> {
+    let pod_name = req.pod_name();
+    let pod = shared
+        .xline_store
+        .get_pod(pod_name)
+        .await?
+        .with_context(|| format!("Pod {pod_name} not found"))?;
+    let node_name = pod
+        .spec
+        .node_name
+        .with_context(|| format!("Pod {pod_name} is not scheduled to a node yet"))?;
+    let worker = shared
+        .node_registry
+        .get(&node_name)
+        .await
+        .with_context(|| format!("No active worker for {node_name}"))?;
+    worker
+        .conn
+        .open_bi()
+        .await
+        .with_context(|| format!("Failed to open session stream to {node_name}"))
+}
+
+/// Copy `from` into `to` and finish `to` once `from` has been finished.
+async fn copy(from: &mut RecvStream, to: &mut SendStream) -> anyhow::Result<()> {
+    tokio::io::copy(from, to).await?;
+    to.finish()?;
+    Ok(())
+}
@@ -80,9 +80,12 @@ impl PodsWatcher {
             let pod_task = serde_yaml::from_str::<PodTask>(&pod_yaml)?;
             // Send snapshot to the worker
             if pod_task.spec.node_name.as_deref() == Some(node_id.as_str()) {
-                self.conn
-                    .send_msg(&RksMessage::CreatePod(Box::new(pod_task)))
-                    .await?;
+                let msg = if pod_task.metadata.deletion_timestamp.is_some() {
+                    RksMessage::TerminatePod(Box::new(pod_task))
+                } else {
+                    RksMessage::CreatePod(Box::new(pod_task))
+                };
+                self.conn.send_msg(&msg).await?;
                 info!(
                     target: "rks::node::watch_pods",
                     "sent existing pod to worker: {pod_name}"
@@ -148,9 +151,20 @@ impl PodsWatcher {
         if let Some(prev_kv) = prev_kv {
             let prev_pod: PodTask = serde_yaml::from_slice(prev_kv.value())?;
 
-            // Only updating node_name can be watched and send to node
+            // Only updating node_name, deletionTimestamp, ephemeral containers,
+            // labels or annotations can be watched and send to node
             if prev_pod.spec.node_name.is_none() && new_pod.spec.node_name.is_some() {
                 self.enqueue_create(node_id, kv.value(), &new_pod).await?;
+            } else if prev_pod.metadata.deletion_timestamp.is_none()
+                && new_pod.metadata.deletion_timestamp.is_some()
+            {
+                self.enqueue_terminate(node_id, new_pod).await?;
+            } else if prev_pod.spec.ephemeral_containers != new_pod.spec.ephemeral_containers {
+                self.enqueue_ephemeral_containers(node_id, new_pod).await?;
+            } else if prev_pod.metadata.labels != new_pod.metadata.labels
+                || prev_pod.metadata.annotations != new_pod.metadata.annotations
+            {
+                self.enqueue_metadata_update(node_id, new_pod).await?;
             }
 
             return Ok(());
@@ -160,6 +174,52 @@ impl PodsWatcher {
         self.enqueue_create(node_id, kv.value(), &new_pod).await
     }
 
+    async fn enqueue_terminate(&self, node_id: &str, pod: PodTask) -> anyhow::Result<()> {
+        if pod.spec.node_name.as_deref() != Some(node_id) {
+            return Ok(());
+        }
+        info!(
+            target: "rks::node::watch_pods",
+            "Pod {} marked for deletion, terminating on {node_id}",
+            pod.metadata.name
+        );
+        self.conn
+            .send_msg(&RksMessage::TerminatePod(Box::new(pod)))
+            .await
+    }
+
+    async fn enqueue_metadata_update(&self, node_id: &str, pod: PodTask) -> anyhow::Result<()> {
+        if pod.spec.node_name.as_deref() != Some(node_id) {
+            return Ok(());
+        }
+        info!(
+            target: "rks::node::watch_pods",
+            "Metadata of pod {} changed, updating on {node_id}",
+            pod.metadata.name
+        );
+        self.conn
+            .send_msg(&RksMessage::UpdatePodMetadata(Box::new(pod)))
+            .await
+    }
+
+    async fn enqueue_ephemeral_containers(
+        &self,
+        node_id: &str,
+        pod: PodTask,
+    ) -> anyhow::Result<()> {
+        if pod.spec.node_name.as_deref() != Some(node_id) {
+            return Ok(());
+        }
+        info!(
+            target: "rks::node::watch_pods",
+            "Ephemeral containers of pod {} changed, starting them on {node_id}",
+            pod.metadata.name
+        );
+        self.conn
+            .send_msg(&RksMessage::RunEphemeralContainers(Box::new(pod)))
+            .await
+    }
+
     async fn enqueue_create(
         &self,
         node_id: &str,
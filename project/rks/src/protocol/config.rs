@@ -1,5 +1,7 @@
+use crate::controllers::descheduler::DeschedulerConfig;
 use anyhow::Context;
 use either::Either;
+use libscheduler::config::SchedulerConfiguration;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::net::SocketAddr;
@@ -24,6 +26,15 @@ pub struct Config {
     pub tls_config: TLSConfig,
     // DNS config
     pub dns_config: DnsConfig,
+    // scheduler profiles, defaults to a single `default-scheduler` profile
+    #[serde(default)]
+    pub scheduler_config: SchedulerConfiguration,
+    // descheduler strategies, disabled by default
+    #[serde(default)]
+    pub descheduler_config: DeschedulerConfig,
+    // ip:port serving Prometheus metrics on `/metrics`, empty to disable
+    #[serde(default = "default_metrics_addr")]
+    pub metrics_addr: String,
 }
 
 #[allow(dead_code)]
@@ -66,6 +77,10 @@ fn default_vault_folder() -> PathBuf {
     PathBuf::from("./backend")
 }
 
+fn default_metrics_addr() -> String {
+    "0.0.0.0:10257".to_string()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DnsConfig {
     #[serde(rename = "Port")]
@@ -4,9 +4,10 @@ use crate::api::xlinestore::XlineStore;
 use anyhow::Result;
 use common::PodTask;
 use libscheduler::{
+    config::{DEFAULT_SCHEDULER_NAME, Profile},
     models::Assignment,
     plugins::{Plugins, node_resources_fit::ScoringStrategy},
-    with_xline::run_scheduler_with_xline,
+    with_xline::run_scheduler_with_xline_profiles,
 };
 use libvault::storage::xline::XlineOptions;
 use log::{debug, error};
@@ -23,10 +24,28 @@ impl Scheduler {
         xline_store: Arc<XlineStore>,
         scoring_strategy: ScoringStrategy,
         plugins: Plugins,
+    ) -> Result<Self> {
+        Self::try_new_with_profiles(
+            xline_options,
+            xline_store,
+            vec![Profile::new(
+                DEFAULT_SCHEDULER_NAME,
+                scoring_strategy,
+                plugins,
+            )],
+        )
+        .await
+    }
+
+    /// Creates a scheduler serving every profile from the scheduler configuration.
+    pub async fn try_new_with_profiles(
+        xline_options: XlineOptions,
+        xline_store: Arc<XlineStore>,
+        profiles: Vec<Profile>,
     ) -> Result<Self> {
         let (_unassume_tx, unassume_rx) = mpsc::unbounded_channel();
         let assignment_rx =
-            run_scheduler_with_xline(xline_options, scoring_strategy, plugins, unassume_rx).await?;
+            run_scheduler_with_xline_profiles(xline_options, profiles, unassume_rx).await?;
         Ok(Self {
             assignment_rx,
             xline_store,
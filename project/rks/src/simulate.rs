@@ -0,0 +1,90 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use libscheduler::simulation::PodPlacement;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Yaml,
+    Json,
+}
+
+/// Print the result of a scheduling simulation.
+pub fn print_placements(placements: &[PodPlacement], output: OutputFormat) -> Result<()> {
+    match output {
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(placements)?),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(placements)?),
+        OutputFormat::Text => print!("{}", render_text(placements)),
+    }
+    Ok(())
+}
+
+fn render_text(placements: &[PodPlacement]) -> String {
+    let mut out = String::new();
+    for p in placements {
+        let node = p.node_name.as_deref().unwrap_or("<unschedulable>");
+        match &p.previous_node {
+            Some(prev) => out.push_str(&format!("pod {}: {prev} -> {node}\n", p.pod_name)),
+            None => out.push_str(&format!("pod {}: {node}\n", p.pod_name)),
+        }
+        for r in p.reasons.iter() {
+            out.push_str(&format!("  reason: {r}\n"));
+        }
+        for s in p.scores.iter() {
+            let plugins: Vec<_> = s
+                .plugins
+                .iter()
+                .map(|ps| format!("{}={}x{}", ps.plugin, ps.score, ps.weight))
+                .collect();
+            out.push_str(&format!(
+                "  score {} {}: {}\n",
+                s.node_name,
+                s.total,
+                plugins.join(" ")
+            ));
+        }
+        for r in p.rejected.iter() {
+            out.push_str(&format!(
+                "  rejected {} by {}: {}\n",
+                r.node_name,
+                r.plugin,
+                r.reasons.join(", ")
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libscheduler::simulation::{NodeRejection, NodeScore, PluginScore};
+
+    #[test]
+    fn test_render_text() {
+        let placements = vec![PodPlacement {
+            pod_name: "pod".to_string(),
+            node_name: Some("node1".to_string()),
+            scores: vec![NodeScore {
+                node_name: "node1".to_string(),
+                total: 200,
+                plugins: vec![PluginScore {
+                    plugin: "NodeResourcesFit".to_string(),
+                    score: 100,
+                    weight: 2,
+                }],
+            }],
+            rejected: vec![NodeRejection {
+                node_name: "node2".to_string(),
+                plugin: "TaintToleration".to_string(),
+                reasons: vec!["node(s) had untolerated taint".to_string()],
+            }],
+            ..Default::default()
+        }];
+        assert_eq!(
+            render_text(&placements),
+            "pod pod: node1\n  score node1 200: NodeResourcesFit=100x2\n  rejected node2 by TaintToleration: node(s) had untolerated taint\n"
+        );
+    }
+}
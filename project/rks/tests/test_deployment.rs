@@ -402,6 +402,9 @@ async fn test_deployment_hash_collision() -> Result<()> {
                         volume_mounts: None,
                         command: None,
                         working_dir: None,
+                        lifecycle: None,
+                        restart_policy: None,
+                        image_pull_policy: None,
                         name: "blocker".to_string(),
                         image: "./blocker-image".to_string(),
                         ports: Vec::new(),
@@ -716,6 +719,9 @@ fn create_test_deployment(name: &str, replicas: i32) -> Deployment {
                         volume_mounts: None,
                         command: None,
                         working_dir: None,
+                        lifecycle: None,
+                        restart_policy: None,
+                        image_pull_policy: None,
                     }],
                     init_containers: Vec::new(),
                     tolerations: Vec::new(),
@@ -67,7 +67,7 @@ async fn clean_store(store: &XlineStore) -> Result<()> {
     // delete pods
     let pods = store.list_pod_names().await?;
     for name in pods {
-        let _ = store.delete_pod(&name).await;
+        let _ = store.force_delete_pod(&name).await;
     }
     Ok(())
 }
@@ -68,6 +68,9 @@ fn pod_with_meta(name: &str, uid: Uuid, owners: Option<Vec<OwnerReference>>) ->
         volume_mounts: None,
         command: None,
         working_dir: None,
+        lifecycle: None,
+        restart_policy: None,
+        image_pull_policy: None,
         startup_probe: None,
     };
 
@@ -143,6 +146,9 @@ fn replicaset_with_meta(name: &str, uid: Uuid, replicas: i32) -> ReplicaSet {
                         volume_mounts: None,
                         command: None,
                         working_dir: None,
+                        lifecycle: None,
+                        restart_policy: None,
+                        image_pull_policy: None,
                     }],
                     init_containers: vec![],
                     tolerations: vec![],
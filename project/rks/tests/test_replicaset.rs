@@ -100,6 +100,9 @@ fn make_test_replicaset(name: &str, replicas: i32) -> ReplicaSet {
                         volume_mounts: None,
                         command: None,
                         working_dir: None,
+                        lifecycle: None,
+                        restart_policy: None,
+                        image_pull_policy: None,
                     }],
                     init_containers: Vec::new(),
                     tolerations: Vec::new(),
@@ -468,6 +471,9 @@ async fn test_replicaset_adopts_orphan_pods() -> Result<()> {
                     volume_mounts: None,
                     command: None,
                     working_dir: None,
+                    lifecycle: None,
+                    restart_policy: None,
+                    image_pull_policy: None,
                 }],
                 init_containers: Vec::new(),
                 tolerations: Vec::new(),
@@ -85,6 +85,9 @@ fn create_test_node(name: &str, cpu: &str, memory: &str) -> Node {
                 status: common::ConditionStatus::True,
                 last_heartbeat_time: None,
             }],
+            images: vec![],
+            capacity_resources: None,
+            allocatable_resources: None,
         },
     }
 }
@@ -127,6 +130,9 @@ fn create_test_pod(name: &str, cpu_limit: Option<&str>, memory_limit: Option<&st
                 volume_mounts: None,
                 command: None,
                 working_dir: None,
+                lifecycle: None,
+                restart_policy: None,
+                image_pull_policy: None,
             }],
             init_containers: vec![],
             tolerations: vec![],
@@ -166,7 +172,7 @@ async fn cleanup() -> Result<()> {
     let pod_names = store.list_pod_names().await?;
     for pod_name in pod_names {
         if pod_name.contains("scheduler-test") {
-            store.delete_pod(&pod_name).await?;
+            store.force_delete_pod(&pod_name).await?;
         }
     }
 